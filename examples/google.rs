@@ -1,13 +1,18 @@
 //! Render google.com!
 
-use dioxus_blitz::Config;
+use dioxus_blitz::{Config, Shortcuts};
 
 fn main() {
     dioxus_blitz::launch_static_html_cfg(
         &get_html(),
         Config {
             stylesheets: Vec::new(),
+            user_stylesheets: Vec::new(),
             base_url: Some(String::from("https://www.google.com/")),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
         },
     );
 }
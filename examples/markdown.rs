@@ -1,7 +1,7 @@
 //! Render the readme.md using the gpu renderer
 
 use comrak::{markdown_to_html, ExtensionOptionsBuilder, Options};
-use dioxus_blitz::Config;
+use dioxus_blitz::{Config, Shortcuts};
 
 fn main() {
     let stylesheet = include_str!("./assets/github-markdown-light.css");
@@ -44,7 +44,12 @@ fn main() {
         &html,
         Config {
             stylesheets: vec![String::from(stylesheet)],
+            user_stylesheets: Vec::new(),
             base_url: Some("https://raw.githubusercontent.com/DioxusLabs/blitz/main/".to_string()),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
         },
     );
 }
@@ -0,0 +1,24 @@
+//! Render HTML into a PNG without opening a window
+
+use dioxus_blitz::{Config, Shortcuts};
+
+fn main() {
+    let image = dioxus_blitz::render_static_html_to_image(
+        include_str!("./assets/google.html"),
+        Config {
+            stylesheets: Vec::new(),
+            user_stylesheets: Vec::new(),
+            base_url: Some(String::from("https://www.google.com/")),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
+        },
+        1200,
+        800,
+    );
+
+    let path = std::env::temp_dir().join("blitz-screenshot.png");
+    image.save(&path).expect("failed to save screenshot");
+    println!("Saved screenshot to {}", path.display());
+}
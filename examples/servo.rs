@@ -1,11 +1,16 @@
-use dioxus_blitz::Config;
+use dioxus_blitz::{Config, Shortcuts};
 
 fn main() {
     dioxus_blitz::launch_static_html_cfg(
         include_str!("./assets/servo.html"),
         Config {
             stylesheets: Vec::new(),
+            user_stylesheets: Vec::new(),
             base_url: Some(String::from("https://servo.org/")),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
         },
     );
 }
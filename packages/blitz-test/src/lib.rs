@@ -0,0 +1,244 @@
+//! Headless integration testing of Blitz documents
+//!
+//! A [`TestHarness`] loads an HTML document without a window: styles and layout are resolved synchronously for a
+//! fixed viewport, and every resource the document fetches is served from canned responses (see
+//! [`MockNetwork`]), so tests never touch the network or the file system. The loaded [`TestDocument`] can then be
+//! queried and asserted on by element id: the geometry of boxes, their text, JSON snapshots of the whole document
+//! (see [`blitz_dom::tree_dump`]), and screenshots.
+//!
+//! ```ignore
+//! let doc = TestHarness::new("<link rel=stylesheet href=style.css><div id=banner>Hello</div>")
+//!     .base_url("https://example.com/")
+//!     .mock("https://example.com/style.css", "#banner { height: 40px }", "text/css")
+//!     .viewport(400, 300)
+//!     .load();
+//! assert_eq!(doc.rect("banner").height, 40.0);
+//! doc.assert_text("banner", "Hello");
+//! ```
+//!
+//! Note: text is shaped with the fonts installed on the system, so the sizes of boxes that contain text can differ
+//! between machines. Screenshots are rendered on the GPU, so they need a wgpu adapter (a software one will do).
+use std::path::Path;
+use std::sync::Arc;
+
+use blitz::{HeadlessRenderer, RgbaImage, Viewport};
+use blitz_dom::geometry::DomRect;
+use blitz_dom::network::{MockNetwork, NetworkProvider};
+use blitz_dom::{Document, DocumentHtmlParser};
+
+/// The user agent stylesheet that documents are styled with by default, which is the one `dioxus-blitz` uses
+const DEFAULT_UA_STYLESHEET: &str = include_str!("../../dioxus-blitz/src/documents/default.css");
+
+/// Configures how a document is loaded for a test
+pub struct TestHarness {
+    html: String,
+    width: u32,
+    height: u32,
+    scale: f32,
+    base_url: Option<String>,
+    ua_stylesheet: Option<String>,
+    stylesheets: Vec<String>,
+    network: MockNetwork,
+}
+
+impl TestHarness {
+    /// Load `html` into an 800x600 viewport at a scale of 1, with no resources available
+    pub fn new(html: impl Into<String>) -> Self {
+        Self {
+            html: html.into(),
+            width: 800,
+            height: 600,
+            scale: 1.0,
+            base_url: None,
+            ua_stylesheet: Some(DEFAULT_UA_STYLESHEET.to_string()),
+            stylesheets: Vec::new(),
+            network: MockNetwork::new(),
+        }
+    }
+
+    /// The size of the viewport, in physical pixels
+    pub fn viewport(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// The ratio of physical pixels to CSS pixels
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// The URL that the document's relative URLs are resolved against
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Style the document with a different user agent stylesheet, or with none at all
+    pub fn ua_stylesheet(mut self, css: Option<String>) -> Self {
+        self.ua_stylesheet = css;
+        self
+    }
+
+    /// Add an author stylesheet to the document
+    pub fn stylesheet(mut self, css: impl Into<String>) -> Self {
+        self.stylesheets.push(css.into());
+        self
+    }
+
+    /// Serve `body` to fetches of `url`, with the given `Content-Type`
+    pub fn mock(mut self, url: &str, body: impl Into<Vec<u8>>, content_type: &str) -> Self {
+        self.network = self.network.with_response(url, body, Some(content_type));
+        self
+    }
+
+    /// Load the document and lay it out
+    pub fn load(self) -> TestDocument {
+        let mut doc = Document::new(Viewport::new((self.width, self.height)).make_device());
+        doc.set_network(Arc::new(NetworkProvider::mocked(self.network)));
+        if let Some(url) = &self.base_url {
+            doc.set_base_url(url);
+        }
+        if let Some(css) = &self.ua_stylesheet {
+            doc.add_ua_stylesheet(css);
+        }
+        for css in &self.stylesheets {
+            doc.add_stylesheet(css);
+        }
+        DocumentHtmlParser::parse_into_doc(&mut doc, &self.html);
+
+        TestDocument {
+            renderer: HeadlessRenderer::new_headless(doc, self.width, self.height, self.scale),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A loaded and laid out document
+pub struct TestDocument {
+    renderer: HeadlessRenderer<Document>,
+    width: u32,
+    height: u32,
+}
+
+impl TestDocument {
+    pub fn document(&self) -> &Document {
+        &self.renderer.dom
+    }
+
+    /// The document, for changing it. Call [`TestDocument::resolve`] afterwards to update its layout.
+    pub fn document_mut(&mut self) -> &mut Document {
+        &mut self.renderer.dom
+    }
+
+    /// Restyle and relayout the document after changing it
+    pub fn resolve(&mut self) {
+        self.renderer.dom.resolve();
+    }
+
+    /// Resize the viewport (in physical pixels) and relayout the document
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.renderer.set_size((width, height));
+        self.renderer.dom.resolve();
+    }
+
+    /// The id of the node of the element with the given `id` attribute
+    #[track_caller]
+    pub fn element(&self, id: &str) -> usize {
+        self.document()
+            .get_element_by_id(id)
+            .unwrap_or_else(|| panic!("no element with id {id:?}"))
+    }
+
+    /// The border box of an element relative to the document, in CSS pixels
+    #[track_caller]
+    pub fn rect(&self, id: &str) -> DomRect {
+        let node_id = self.element(id);
+        self.document()
+            .node_document_rect(node_id)
+            .unwrap_or_else(|| panic!("element {id:?} isn't rendered"))
+    }
+
+    /// The text content of an element
+    #[track_caller]
+    pub fn text(&self, id: &str) -> String {
+        let node_id = self.element(id);
+        self.document().tree()[node_id].text_content()
+    }
+
+    /// Assert that an element's border box is within half a pixel of `expected`
+    #[track_caller]
+    pub fn assert_rect(&self, id: &str, expected: DomRect) {
+        let actual = self.rect(id);
+        let close = [
+            (actual.x, expected.x),
+            (actual.y, expected.y),
+            (actual.width, expected.width),
+            (actual.height, expected.height),
+        ]
+        .iter()
+        .all(|(actual, expected)| (actual - expected).abs() <= 0.5);
+        assert!(
+            close,
+            "element {id:?} has the box {actual:?}, expected {expected:?}"
+        );
+    }
+
+    /// Assert that an element's text content (with white space collapsed) is `expected`
+    #[track_caller]
+    pub fn assert_text(&self, id: &str, expected: &str) {
+        let text = self.text(id);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(text, expected, "text of element {id:?}");
+    }
+
+    /// Assert that the document matches a golden JSON snapshot (see [`Document::assert_golden_dump`])
+    #[track_caller]
+    pub fn assert_golden(&self, path: impl AsRef<Path>) {
+        self.document().assert_golden_dump(path);
+    }
+
+    /// Render the viewport into an image
+    pub fn screenshot(&mut self) -> RgbaImage {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(self.renderer.render_to_image(self.width, self.height))
+    }
+}
+
+#[test]
+fn loads_documents_with_mocked_resources() {
+    let mut doc = TestHarness::new(
+        "<html><head><link rel=stylesheet href=style.css></head>\
+        <body style='margin: 0'><div id=banner style='width: 50%'><span>Hello</span>   world</div></body></html>",
+    )
+    .base_url("https://example.com/")
+    .mock(
+        "https://example.com/style.css",
+        "#banner { height: 40px }",
+        "text/css",
+    )
+    .viewport(400, 300)
+    .load();
+
+    doc.assert_rect(
+        "banner",
+        DomRect {
+            x: 0.0,
+            y: 0.0,
+            width: 200.0,
+            height: 40.0,
+        },
+    );
+    doc.assert_text("banner", "Hello world");
+
+    // Layout follows the viewport
+    doc.resize(200, 300);
+    assert_eq!(doc.rect("banner").width, 100.0);
+}
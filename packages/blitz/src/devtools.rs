@@ -4,4 +4,8 @@ pub struct Devtools {
     pub highlight_hover: bool,
     pub show_style: bool,
     pub print_hover: bool,
+    /// Draw a graph of the time spent in each phase of recent frames
+    pub show_frame_timings: bool,
+    /// The node whose box model is drawn over the document (selected with Ctrl+Shift+click, or from the context menu)
+    pub inspected_node: Option<usize>,
 }
@@ -0,0 +1,78 @@
+//! Per-element display item caching
+//!
+//! Encoding an element's backgrounds, borders and text into vello paths is the bulk of the work of building a scene.
+//! Each element's display items are encoded into a scene fragment of their own, relative to the element's border box,
+//! and cached along with a key derived from everything that affects them (see
+//! [`blitz_dom::Document::paint_signature`]). While the key is unchanged the cached fragment is spliced into the scene
+//! at the element's current position, so elements that have only moved (e.g. by scrolling) aren't re-encoded either.
+//! Entries for elements that weren't visited while painting a frame are evicted at the end of it.
+use std::collections::HashMap;
+
+use vello::{kurbo::Affine, Scene};
+
+/// Identifies an element across frames: the address of its document (iframes have their own) and its node id
+pub(crate) type DisplayItemsId = (usize, usize);
+
+#[derive(Default)]
+pub(crate) struct DisplayItemCache {
+    entries: HashMap<DisplayItemsId, CachedDisplayItems>,
+    frame: u64,
+}
+
+struct CachedDisplayItems {
+    key: u64,
+    fragment: Scene,
+    last_used: u64,
+}
+
+impl DisplayItemCache {
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Evict the entries of elements that weren't visited this frame
+    pub(crate) fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.entries.retain(|_, entry| entry.last_used == frame);
+    }
+
+    /// Keep an element's entry (if any) for another frame without painting it
+    pub(crate) fn retain(&mut self, id: DisplayItemsId) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.last_used = self.frame;
+        }
+    }
+
+    /// Append an element's display items to the scene, encoding them with `encode` unless they are cached under the
+    /// same key. Returns whether the cached display items were reused.
+    pub(crate) fn append(
+        &mut self,
+        scene: &mut Scene,
+        id: DisplayItemsId,
+        key: u64,
+        transform: Affine,
+        encode: impl FnOnce(&mut Scene),
+    ) -> bool {
+        let frame = self.frame;
+        let mut reused = true;
+        let entry = self.entries.entry(id).or_insert_with(|| {
+            reused = false;
+            CachedDisplayItems {
+                key,
+                fragment: Scene::new(),
+                last_used: frame,
+            }
+        });
+
+        if !reused || entry.key != key {
+            reused = false;
+            entry.key = key;
+            entry.fragment.reset();
+            encode(&mut entry.fragment);
+        }
+        entry.last_used = frame;
+
+        scene.append(&entry.fragment, Some(transform));
+        reused
+    }
+}
@@ -0,0 +1,126 @@
+//! Rendering into textures owned by the embedder
+//!
+//! An embedded renderer doesn't own a window surface or a GPU device. Instead, the embedder (e.g. a wgpu, bevy or egui
+//! application showing the document as a widget) passes its own device and a texture to draw into each frame. The
+//! viewport follows the size of the texture, and input is forwarded to the renderer in the texture's physical pixels,
+//! just like a windowed renderer.
+use blitz_dom::DocumentLike;
+use vello::{
+    peniko::Color, AaSupport, RenderParams, Renderer as VelloRenderer, RendererOptions, Scene,
+};
+
+use crate::repaint::{BlitPipeline, FrameTarget};
+use crate::{NoWindow, RenderState, Renderer, Viewport};
+
+/// The state of a renderer that draws into textures provided by the embedder (see [`Renderer::new_embedded`])
+pub struct EmbeddedRenderState {
+    pub(crate) viewport: Viewport,
+    /// Created on the embedder's device when the first frame is rendered
+    renderer: Option<VelloRenderer>,
+    /// The texture vello renders into, which is then drawn onto the embedder's texture
+    frame: Option<FrameTarget>,
+    /// Draws the frame onto textures of the given format
+    blitter: Option<(wgpu::TextureFormat, BlitPipeline)>,
+}
+
+/// A renderer that draws into textures provided by the embedder. Create one with [`Renderer::new_embedded`].
+pub type EmbeddedRenderer<Doc> = Renderer<'static, NoWindow, Doc>;
+
+impl<Doc: DocumentLike> Renderer<'static, NoWindow, Doc> {
+    /// Create a renderer that draws into textures provided by the embedder (with [`Renderer::render_to_texture`]). The
+    /// document is laid out for a viewport of the given size in physical pixels, at the given display scale.
+    pub fn new_embedded(dom: Doc, width: u32, height: u32, scale: f32) -> Self {
+        let mut renderer = Self::new(dom);
+
+        let mut viewport = Viewport::new((width, height));
+        viewport.set_hidpi_scale(scale);
+        renderer.render_state = RenderState::Embedded(EmbeddedRenderState {
+            viewport,
+            renderer: None,
+            frame: None,
+            blitter: None,
+        });
+        renderer.kick_viewport();
+        renderer.dom.as_mut().resolve();
+
+        renderer
+    }
+
+    /// Draw the document into `texture`, which must belong to `device` and have the `RENDER_ATTACHMENT` usage. The
+    /// document is relaid out first if the texture's size differs from the viewport's.
+    ///
+    /// The embedder must always pass the same device, and submit its own work that samples the texture after this
+    /// returns.
+    pub fn render_to_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) {
+        let (width, height) = (texture.width(), texture.height());
+        let RenderState::Embedded(state) = &self.render_state else {
+            return;
+        };
+        if state.viewport.window_size != (width, height) {
+            self.set_size((width, height));
+            self.dom.as_mut().resolve();
+        }
+
+        // The whole texture is repainted, so the damage tracked since the last frame isn't needed
+        self.dom.as_mut().collect_damage();
+        let mut scene = Scene::new();
+        self.paint_scene(&mut scene);
+
+        let RenderState::Embedded(state) = &mut self.render_state else {
+            return;
+        };
+        let renderer = state.renderer.get_or_insert_with(|| {
+            VelloRenderer::new(
+                device,
+                RendererOptions {
+                    surface_format: None,
+                    antialiasing_support: AaSupport::all(),
+                    use_cpu: false,
+                    num_init_threads: None,
+                },
+            )
+            .unwrap()
+        });
+        if !state
+            .frame
+            .as_ref()
+            .is_some_and(|frame| frame.width == width && frame.height == height)
+        {
+            state.frame = Some(FrameTarget::new(device, width, height));
+        }
+        let frame = state.frame.as_ref().unwrap();
+        if !state
+            .blitter
+            .as_ref()
+            .is_some_and(|(format, _)| *format == texture.format())
+        {
+            state.blitter = Some((
+                texture.format(),
+                BlitPipeline::new(device, texture.format()),
+            ));
+        }
+        let (_, blitter) = state.blitter.as_ref().unwrap();
+
+        let render_params = RenderParams {
+            base_color: palette_color(self.dom.as_ref().control_palette().canvas),
+            width,
+            height,
+            antialiasing_method: state.viewport.text_rendering.antialiasing.aa_config(),
+        };
+        renderer
+            .render_to_texture(device, queue, &scene, &frame.view, &render_params)
+            .expect("failed to render to texture");
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Embedded frame"),
+        });
+        let target_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        blitter.blit(device, &mut encoder, &frame.view, &target_view);
+        queue.submit([encoder.finish()]);
+    }
+}
@@ -0,0 +1,28 @@
+//! Rendering without a window
+//!
+//! A headless renderer lays a document out for a fixed viewport size and renders it into an image rather than a
+//! window surface. This allows documents to be rendered on a server, or compared against reference images in visual
+//! regression tests. Rendering still happens on the GPU (through wgpu), into an offscreen texture.
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+
+use crate::Renderer;
+
+/// A stand-in window type for renderers that never render into a window
+pub struct NoWindow;
+
+impl HasWindowHandle for NoWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+impl HasDisplayHandle for NoWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+/// A renderer without a window. Create one with [`Renderer::new_headless`].
+pub type HeadlessRenderer<Doc> = Renderer<'static, NoWindow, Doc>;
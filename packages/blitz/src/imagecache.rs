@@ -1,2 +1,185 @@
-#[derive(Default)]
-pub struct ImageCache {}
+//! Images resized for display
+//!
+//! Elements draw their decoded images resized to the size they're displayed at. The resized images are kept here,
+//! keyed by the decoded image and the size, so an image is only resized (and its pixels handed to vello) once, however
+//! many elements and frames draw it. When the cache grows beyond its memory budget, the images that were drawn least
+//! recently are evicted (images drawn in the current frame are kept, so a frame never evicts its own images). Entries
+//! for decoded images that have been dropped, such as past frames of a video, are evicted at the start of each frame.
+//!
+//! Note: vello 0.1 can't keep textures on the GPU between frames: it writes the images of each scene into its image
+//! atlas as it renders. What the cache keeps stable is each image's pixel data (and so the id of its blob), so vello
+//! writes an image into the atlas once per frame however many elements draw it, and the display items that draw it
+//! stay valid (see [`crate::display_items`]).
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use image::{imageops::FilterType, DynamicImage};
+use vello::peniko;
+
+/// The memory budget of the cache by default, in bytes
+pub const DEFAULT_IMAGE_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Statistics about the images resized for display
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImageCacheStats {
+    /// The number of resized images in the cache
+    pub entries: usize,
+    /// The memory used by those images, in bytes
+    pub bytes: usize,
+    /// The memory budget of the cache, in bytes
+    pub budget: usize,
+    /// Draws that reused a resized image
+    pub hits: u64,
+    /// Draws that had to resize an image
+    pub misses: u64,
+    /// Resized images evicted to stay within the budget, or because their decoded image was dropped
+    pub evictions: u64,
+}
+
+/// A decoded image at a display size. The decoded image is identified by the address of its allocation, which is
+/// checked against the entry's weak reference to it, as the address can be reused once the image is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ImageKey {
+    source: usize,
+    width: u32,
+    height: u32,
+}
+
+struct CachedImage {
+    source: Weak<DynamicImage>,
+    image: Arc<peniko::Image>,
+    /// The frame the image was last drawn in
+    last_used: u64,
+}
+
+impl CachedImage {
+    fn bytes(&self) -> usize {
+        self.image.data.data().len()
+    }
+}
+
+/// Resized images, evicted least recently used first under a memory budget
+pub(crate) struct ImageCache {
+    entries: HashMap<ImageKey, CachedImage>,
+    frame: u64,
+    stats: ImageCacheStats,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            frame: 0,
+            stats: ImageCacheStats {
+                budget: DEFAULT_IMAGE_CACHE_BUDGET,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl ImageCache {
+    /// Start painting a new frame, evicting the images of decoded images that have been dropped
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.retain(|entry| entry.source.strong_count() > 0);
+    }
+
+    /// The image resized to the given size, from the cache if it has been resized to that size before
+    pub(crate) fn get(
+        &mut self,
+        source: &Arc<DynamicImage>,
+        width: u32,
+        height: u32,
+    ) -> Arc<peniko::Image> {
+        let key = ImageKey {
+            source: Arc::as_ptr(source) as usize,
+            width,
+            height,
+        };
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.source.as_ptr() == Arc::as_ptr(source) && entry.source.strong_count() > 0 {
+                entry.last_used = self.frame;
+                self.stats.hits += 1;
+                return entry.image.clone();
+            }
+        }
+
+        self.stats.misses += 1;
+        let entry = CachedImage {
+            source: Arc::downgrade(source),
+            image: Arc::new(resize(source, width, height)),
+            last_used: self.frame,
+        };
+        let image = entry.image.clone();
+        self.stats.bytes += entry.bytes();
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.stats.bytes -= old.bytes();
+        }
+        self.evict_to_budget();
+        image
+    }
+
+    pub(crate) fn stats(&self) -> ImageCacheStats {
+        ImageCacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Change the memory budget, evicting images if the cache is now over it
+    pub(crate) fn set_budget(&mut self, bytes: usize) {
+        self.stats.budget = bytes;
+        self.evict_to_budget();
+    }
+
+    /// Evict the least recently drawn images until the cache is within its budget, keeping the current frame's
+    fn evict_to_budget(&mut self) {
+        if self.stats.bytes <= self.stats.budget {
+            return;
+        }
+        let mut candidates: Vec<(u64, ImageKey)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_used < self.frame)
+            .map(|(key, entry)| (entry.last_used, *key))
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+        for (_, key) in candidates {
+            if self.stats.bytes <= self.stats.budget {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                self.stats.bytes -= entry.bytes();
+                self.stats.evictions += 1;
+            }
+        }
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&CachedImage) -> bool) {
+        let stats = &mut self.stats;
+        self.entries.retain(|_, entry| {
+            let kept = keep(entry);
+            if !kept {
+                stats.bytes -= entry.bytes();
+                stats.evictions += 1;
+            }
+            kept
+        });
+    }
+}
+
+/// Resize a decoded image to fill the given size
+fn resize(image: &DynamicImage, width: u32, height: u32) -> peniko::Image {
+    let data = image
+        .resize_to_fill(width, height, FilterType::Lanczos3)
+        .into_rgba8()
+        .into_raw();
+    peniko::Image {
+        data: peniko::Blob::new(Arc::new(data)),
+        format: peniko::Format::Rgba8,
+        width,
+        height,
+        extend: peniko::Extend::Pad,
+    }
+}
@@ -0,0 +1,109 @@
+//! Layer compositing
+//!
+//! The document is painted into layers that are composited into the frame's scene:
+//!
+//! - The scrolling content, painted in document coordinates and translated by the scroll position.
+//! - One layer per element promoted by a `will-change: transform`, `opacity` or `scroll-position` hint (and its
+//!   descendants), painted in document coordinates. They are composited above the scrolling content.
+//! - One layer per `position: fixed` element (and its descendants), painted in viewport coordinates so they stay in
+//!   place when scrolling. They are composited above the scrolling content.
+//!
+//! When only the scroll position has changed since the last frame the layers are reused as they are, so scrolling is
+//! a translation of the cached scrolling content rather than a repaint of the document. When everything that changed
+//! is within promoted layers, only those layers are repainted, so animating a promoted element doesn't repaint the
+//! rest of the document.
+use std::fmt::Write;
+
+use vello::{
+    kurbo::{Point, Rect},
+    Scene,
+};
+
+/// What a layer contains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LayerKind {
+    /// The root element and everything that scrolls with it
+    ScrollingContent,
+    /// An element promoted by its `will-change` hint
+    Promoted(usize),
+    /// A `position: fixed` element
+    Fixed(usize),
+}
+
+pub(crate) struct Layer {
+    pub(crate) kind: LayerKind,
+    pub(crate) scene: Scene,
+    /// The area covered by the layer's root element, in CSS pixels of the layer's coordinate space
+    pub(crate) bounds: Rect,
+    /// Where the layer's root element was painted from (the position of its parent), so it can be repainted alone
+    pub(crate) location: Point,
+    /// How many times the layer has been painted
+    pub(crate) paints: u64,
+}
+
+impl Layer {
+    pub(crate) fn new(kind: LayerKind) -> Self {
+        Self {
+            kind,
+            scene: Scene::new(),
+            bounds: Rect::ZERO,
+            location: Point::ZERO,
+            paints: 0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct LayerTree {
+    /// The scrolling content followed by the promoted and fixed layers, in paint order
+    pub(crate) layers: Vec<Layer>,
+    /// Whether the layers hold everything (rather than only what intersected a damaged region when they were
+    /// painted) and so can be reused
+    pub(crate) complete: bool,
+    /// The parameters the layers were painted with
+    pub(crate) scale: f64,
+    pub(crate) show_layout: bool,
+    /// How many frames the layers have been composited without being repainted
+    pub(crate) reuses: u64,
+}
+
+impl LayerTree {
+    /// Take the layer of the given kind from the previous frame (to keep its statistics), or create a new one
+    pub(crate) fn take_layer(&mut self, kind: LayerKind) -> Layer {
+        match self.layers.iter().position(|layer| layer.kind == kind) {
+            Some(idx) => self.layers.swap_remove(idx),
+            None => Layer::new(kind),
+        }
+    }
+
+    /// A textual dump of the layer tree, for debugging
+    pub(crate) fn dump(&self, scroll: f64) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Layer tree (scroll: {scroll}, reused for {} frames)",
+            self.reuses
+        );
+        for layer in &self.layers {
+            let (name, offset) = match layer.kind {
+                LayerKind::ScrollingContent => ("scrolling content".to_string(), -scroll),
+                LayerKind::Promoted(node_id) => (format!("promoted #{node_id}"), -scroll),
+                LayerKind::Fixed(node_id) => (format!("fixed #{node_id}"), 0.0),
+            };
+            let indent = match layer.kind {
+                LayerKind::ScrollingContent => "  ",
+                LayerKind::Promoted(_) | LayerKind::Fixed(_) => "    ",
+            };
+            let _ = writeln!(
+                out,
+                "{indent}{name}: {}x{} at ({}, {}), painted {} times",
+                layer.bounds.width(),
+                layer.bounds.height(),
+                layer.bounds.x0,
+                layer.bounds.y0 + offset,
+                layer.paints
+            );
+        }
+        out
+    }
+}
@@ -5,11 +5,28 @@ lib.rs -> DioxusNative -> Window -> Document
 */
 
 mod devtools;
+mod display_items;
+mod embedded;
 mod fontcache;
+mod headless;
 mod imagecache;
+mod layers;
 pub mod render;
+mod repaint;
+pub mod shell;
+mod snap;
+pub mod text_rendering;
+pub mod touch;
 mod util;
 mod viewport;
 
+pub use embedded::{EmbeddedRenderState, EmbeddedRenderer};
+pub use headless::{HeadlessRenderer, NoWindow};
+pub use image::RgbaImage;
+pub use imagecache::{ImageCacheStats, DEFAULT_IMAGE_CACHE_BUDGET};
 pub use render::*;
+pub use repaint::RepaintStats;
+pub use shell::{PointerButton, Shell, ShellEvent};
+pub use text_rendering::{Antialiasing, TextHinting, TextRendering};
+pub use touch::TouchPhase;
 pub use viewport::Viewport;
@@ -1,22 +1,50 @@
 mod multicolor_rounded_rect;
 
+use std::cell::{Cell, RefCell};
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
+use std::time::Instant;
 // So many imports
 use self::multicolor_rounded_rect::{Edge, ElementFrame};
 use crate::{
     devtools::Devtools,
-    util::{GradientSlice, StyloGradient, ToVelloColor},
+    display_items::DisplayItemCache,
+    embedded::EmbeddedRenderState,
+    imagecache::{ImageCache, ImageCacheStats},
+    layers::{Layer, LayerKind, LayerTree},
+    repaint::{create_render_target, BlitPipeline, FrameTarget, PixelRect, RepaintStats},
+    snap::{snap_baseline, snap_glyph_x, snap_point},
+    text_rendering::{TextHinting, TextRendering},
+    touch::TouchGesture,
+    util::{palette_color, GradientSlice, StyloGradient, ToVelloColor},
     viewport::Viewport,
 };
 use blitz_dom::node::TextBrush;
 use blitz_dom::{
-    events::{EventData, RendererEvent},
+    clip_path::ClipShape,
+    context_menu::CONTEXT_MENU_ITEM_PADDING,
+    controls::{ControlKind, MeterRegion, RANGE_THUMB_RADIUS},
+    damage::Damage,
+    datalist::SUGGESTION_PADDING,
+    editing::EditCommand,
+    events::{EventData, InputType, RendererEvent, WheelDelta, WheelEventData},
+    fieldset::LegendNotch,
+    forms::ButtonType,
+    geometry::DomRect,
+    layout::{ALT_TEXT_GAP, TEXT_INDENT_BOX_ID},
     node::{NodeData, TextLayout, TextNodeData},
-    DocumentLike, Node,
+    number_input::{SpinDirection, SPIN_BUTTON_WIDTH},
+    pickers::{parse_simple_color, PickerAction, PickerClick, PickerKind},
+    profiling::{FramePhase, FRAME_TIMING_HISTORY},
+    resource_errors::BROKEN_IMAGE_SIZE,
+    scrolling::ScrollBehavior,
+    spatial_navigation::NavigationDirection,
+    top_layer::TopLayerKind,
+    Document, DocumentLike, Node,
 };
 use html5ever::local_name;
-use image::{imageops::FilterType, DynamicImage};
+use image::{DynamicImage, RgbaImage};
 use parley::layout::LayoutItem2;
 use style::{
     dom::TElement,
@@ -44,14 +72,19 @@ use style::{
 };
 use taffy::prelude::Layout;
 use vello::{
-    kurbo::{Affine, Point, Rect, Shape, Stroke, Vec2},
-    peniko::{self, Color, Fill},
+    kurbo::{
+        Affine, BezPath, Circle, Ellipse, Line, Point, Rect, RoundedRect, Shape, Size, Stroke, Vec2,
+    },
+    peniko::{self, Color, Fill, Mix},
     util::RenderContext,
     util::RenderSurface,
     AaSupport, RenderParams, Renderer as VelloRenderer, RendererOptions, Scene,
 };
 use wgpu::{PresentMode, SurfaceError, WasmNotSend};
 
+/// The number of CSS pixels that each line of a mouse wheel's delta scrolls by default
+pub const DEFAULT_WHEEL_LINE_HEIGHT: f64 = 20.0;
+
 // Simple struct to hold the state of the renderer
 pub struct ActiveRenderState<'s, W> {
     // The fields MUST be in this order, so that the surface is dropped before the window
@@ -63,12 +96,44 @@ pub struct ActiveRenderState<'s, W> {
     /// We need this since the part of the page that's being viewed might not be the page in its entirety.
     /// This will let us opt of rendering some stuff
     viewport: Viewport,
+
+    /// The last frame, which damaged regions are rendered into
+    frame: Option<FrameTarget>,
+
+    /// Draws the frame onto the surface
+    blitter: BlitPipeline,
 }
 
 pub enum RenderState<'s, W> {
     Active(ActiveRenderState<'s, W>),
     // Cache a window so that it can be reused when the app is resumed after being suspended
     Suspended(Option<(Arc<W>, Viewport)>),
+    /// Rendering into images rather than a window (see [`Renderer::new_headless`])
+    Headless(Viewport),
+    /// Rendering into textures provided by the embedder (see [`Renderer::new_embedded`])
+    Embedded(EmbeddedRenderState),
+}
+
+impl<W> RenderState<'_, W> {
+    /// The viewport that is being rendered (if any)
+    pub fn viewport(&self) -> Option<&Viewport> {
+        match self {
+            RenderState::Active(state) => Some(&state.viewport),
+            RenderState::Headless(viewport) => Some(viewport),
+            RenderState::Embedded(state) => Some(&state.viewport),
+            RenderState::Suspended(_) => None,
+        }
+    }
+
+    /// The viewport that is being rendered (if any)
+    pub fn viewport_mut(&mut self) -> Option<&mut Viewport> {
+        match self {
+            RenderState::Active(state) => Some(&mut state.viewport),
+            RenderState::Headless(viewport) => Some(viewport),
+            RenderState::Embedded(state) => Some(&mut state.viewport),
+            RenderState::Suspended(_) => None,
+        }
+    }
 }
 
 pub struct Renderer<'s, W, Doc: DocumentLike> {
@@ -78,16 +143,55 @@ pub struct Renderer<'s, W, Doc: DocumentLike> {
 
     pub(crate) render_context: RenderContext,
 
-    /// Our image cache
-    // pub(crate) images: ImageCache,
+    /// Images resized for display
+    images: RefCell<ImageCache>,
 
     /// A storage of fonts to load in and out.
     /// Whenever we encounter new fonts during parsing + mutations, this will become populated
     // pub(crate) fonts: FontCache,
     pub devtools: Devtools,
 
-    scroll_offset: f64,
     mouse_pos: (f32, f32),
+
+    /// The number of CSS pixels that each line of a mouse wheel's delta scrolls
+    wheel_line_height: f64,
+
+    /// The finger that is panning or tapping the document (see [`crate::touch`])
+    pub(crate) touch: Option<TouchGesture>,
+
+    /// The region (in physical pixels of the layer being painted) being repainted. Elements outside of it are not
+    /// painted.
+    paint_region: Cell<Option<Rect>>,
+
+    /// The layers the document was last painted into
+    layers: RefCell<LayerTree>,
+
+    /// Whether the layers can be composited as they are because only the scroll position has changed (or everything
+    /// that changed is within the promoted layers in `dirty_layers`)
+    reuse_layers: Cell<bool>,
+
+    /// The promoted layers (by their root element) to repaint on their own when reusing the others
+    dirty_layers: RefCell<Vec<usize>>,
+
+    /// Fixed position elements (and where they are) found while painting the scrolling content
+    fixed_elements: RefCell<Option<Vec<(usize, Point)>>>,
+
+    /// Elements promoted by `will-change` (and where they are) found while painting the scrolling content
+    promoted_elements: RefCell<Option<Vec<(usize, Point)>>>,
+
+    /// The number of elements painted into the current scene
+    painted_elements: Cell<usize>,
+
+    /// The number of elements painted into the current scene using cached display items
+    reused_elements: Cell<usize>,
+
+    /// Display items encoded for each element in previous frames
+    display_items: RefCell<DisplayItemCache>,
+
+    /// A hash of the UI drawn over the document (the caret, pickers, etc) when it was last painted
+    painted_overlay: Option<u64>,
+
+    repaint_stats: RepaintStats,
 }
 
 impl<'a, W, Doc: DocumentLike> Renderer<'a, W, Doc>
@@ -111,13 +215,52 @@ where
             render_state: RenderState::Suspended(None),
             dom,
             devtools: Default::default(),
-            scroll_offset: 0.0,
             mouse_pos: (0.0, 0.0),
+            wheel_line_height: DEFAULT_WHEEL_LINE_HEIGHT,
+            touch: None,
+            paint_region: Cell::new(None),
+            layers: RefCell::new(LayerTree::default()),
+            reuse_layers: Cell::new(false),
+            dirty_layers: RefCell::new(Vec::new()),
+            fixed_elements: RefCell::new(None),
+            promoted_elements: RefCell::new(None),
+            painted_elements: Cell::new(0),
+            reused_elements: Cell::new(0),
+            display_items: RefCell::new(DisplayItemCache::default()),
+            images: RefCell::new(ImageCache::default()),
+            painted_overlay: None,
+            repaint_stats: RepaintStats::default(),
         }
     }
 
-    pub fn poll(&mut self, cx: std::task::Context) -> bool {
-        self.dom.poll(cx)
+    /// Create a renderer that renders into images (with [`Renderer::render_to_image`]) instead of a window. The document
+    /// is laid out for a viewport of the given size in physical pixels, at the given display scale.
+    pub fn new_headless(dom: Doc, width: u32, height: u32, scale: f32) -> Self {
+        let mut renderer = Self::new(dom);
+
+        let mut viewport = Viewport::new((width, height));
+        viewport.set_hidpi_scale(scale);
+        viewport.set_meta(renderer.dom.as_ref().viewport_meta());
+        renderer
+            .dom
+            .as_mut()
+            .set_stylist_device(viewport.make_device());
+        renderer.dom.as_mut().set_scale(viewport.scale());
+        renderer.render_state = RenderState::Headless(viewport);
+        renderer.dom.as_mut().resolve();
+
+        renderer
+    }
+
+    pub fn poll(&mut self, mut cx: std::task::Context) -> bool {
+        let videos_changed = self.dom.as_mut().poll_video_frames(&mut cx);
+        let images_changed = self.dom.as_mut().poll_rasterized_images(&mut cx);
+        let files_changed = self.dom.as_mut().poll_file_dialog(&mut cx);
+        if let Some(node_id) = files_changed {
+            self.dispatch_input_event(node_id, "input");
+            self.dispatch_input_event(node_id, "change");
+        }
+        self.dom.poll(cx) | videos_changed | images_changed | files_changed.is_some()
     }
 
     pub async fn resume(&mut self, window_builder: impl FnOnce() -> (Arc<W>, Viewport)) {
@@ -125,7 +268,8 @@ where
             return;
         };
 
-        let (window, viewport) = cached_window.take().unwrap_or_else(window_builder);
+        let (window, mut viewport) = cached_window.take().unwrap_or_else(window_builder);
+        viewport.set_meta(self.dom.as_ref().viewport_meta());
 
         let device = viewport.make_device();
         self.dom.as_mut().set_stylist_device(device);
@@ -160,15 +304,17 @@ where
             num_init_threads: DEFAULT_THREADS,
         };
 
-        let renderer =
-            VelloRenderer::new(&self.render_context.devices[surface.dev_id].device, options)
-                .unwrap();
+        let device = &self.render_context.devices[surface.dev_id].device;
+        let renderer = VelloRenderer::new(device, options).unwrap();
+        let blitter = BlitPipeline::new(device, surface.config.format);
 
         self.render_state = RenderState::Active(ActiveRenderState {
             renderer,
             surface,
             window,
             viewport,
+            frame: None,
+            blitter,
         });
 
         self.dom.as_mut().resolve();
@@ -180,38 +326,67 @@ where
             RenderState::Active(state) => {
                 RenderState::Suspended(Some((state.window, state.viewport)))
             }
-            RenderState::Suspended(_) => old_state,
+            RenderState::Suspended(_) | RenderState::Headless(_) | RenderState::Embedded(_) => {
+                old_state
+            }
         };
     }
 
     pub fn zoom(&mut self, zoom: f32) {
-        let RenderState::Active(state) = &mut self.render_state else {
+        let Some(viewport) = self.render_state.viewport_mut() else {
             return;
         };
-        *state.viewport.zoom_mut() += zoom;
+        *viewport.zoom_mut() += zoom;
         self.kick_viewport()
     }
 
     pub fn reset_zoom(&mut self) {
-        let RenderState::Active(state) = &mut self.render_state else {
+        let Some(viewport) = self.render_state.viewport_mut() else {
             return;
         };
-        *state.viewport.zoom_mut() = 1.0;
+        *viewport.zoom_mut() = 1.0;
         self.kick_viewport()
     }
 
+    /// The settings that text is rendered into the current surface with
+    pub fn text_rendering(&self) -> TextRendering {
+        self.render_state
+            .viewport()
+            .map(|viewport| viewport.text_rendering)
+            .unwrap_or_default()
+    }
+
+    /// Change the settings that text is rendered into the current surface with. The whole surface is repainted.
+    pub fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        let Some(viewport) = self.render_state.viewport_mut() else {
+            return;
+        };
+        if viewport.text_rendering != text_rendering {
+            viewport.text_rendering = text_rendering;
+            self.dom.as_mut().mark_fully_damaged();
+        }
+    }
+
     pub fn mouse_move(&mut self, x: f32, y: f32) -> bool {
-        let RenderState::Active(state) = &self.render_state else {
+        let Some(viewport) = self.render_state.viewport() else {
             return false;
         };
 
-        let x = x / state.viewport.zoom();
-        let y = (y - self.scroll_offset as f32) / state.viewport.zoom();
+        let x = x / viewport.page_scale();
+        let y = y / viewport.page_scale() + self.dom.as_ref().viewport_scroll() as f32;
+        self.mouse_pos = (x, y);
 
         // println!("Mouse move: ({}, {})", x, y);
         // println!("Unscaled: ({}, {})",);
 
-        self.dom.as_mut().set_hover_to(x, y)
+        // Dragging the thumb of a range input
+        let mut changed = false;
+        if let Some(node_id) = self.dom.as_mut().update_range_drag(x) {
+            self.dispatch_input_event(node_id, "input");
+            changed = true;
+        }
+
+        self.dom.as_mut().set_hover_to(x, y) || changed
     }
 
     pub fn get_cursor(&self) -> Option<CursorKind> {
@@ -237,18 +412,52 @@ where
         Some(cursor)
     }
 
-    pub fn scroll_by(&mut self, px: f64) {
-        // Invert scrolling on macos
-        #[cfg(target_os = "macos")]
-        {
-            self.scroll_offset += px;
-        }
-        #[cfg(not(target_os = "macos"))]
-        {
-            self.scroll_offset -= px;
+    /// Scroll the viewport down by a number of CSS pixels (or up, if it's negative)
+    pub fn scroll_by(&mut self, px: f64, behavior: ScrollBehavior) {
+        // Scrolls during a smooth scroll continue from where it's heading
+        let scroll = self.dom.as_ref().viewport_scroll_target();
+        self.dom.as_mut().scroll_viewport_to(scroll + px, behavior);
+    }
+
+    /// Set the number of CSS pixels that each line of a mouse wheel's delta scrolls
+    pub fn set_wheel_line_height(&mut self, line_height: f64) {
+        self.wheel_line_height = line_height;
+    }
+
+    /// Dispatch a `wheel` event to the hovered element, then scroll the viewport by the delta. The platform's delta
+    /// is normalized to CSS pixels, so that wheels and touchpads scroll at the same speed on every platform.
+    pub fn wheel(&mut self, delta: WheelDelta) {
+        let scale = self
+            .render_state
+            .viewport()
+            .map_or(1.0, Viewport::scale_f64);
+        let (delta_x, delta_y) = delta.to_css_pixels(self.wheel_line_height, scale);
+
+        if let Some(node_id) = self.dom.as_ref().get_hover_node_id() {
+            let pointer = self.dom.as_ref().pointer_event_data(
+                node_id,
+                node_id,
+                self.mouse_pos.0,
+                self.mouse_pos.1,
+            );
+            self.dom.handle_event(RendererEvent {
+                name: "wheel".to_string(),
+                target: node_id,
+                data: EventData::Wheel(WheelEventData {
+                    pointer,
+                    raw: delta,
+                    delta_x,
+                    delta_y,
+                }),
+            });
         }
 
-        self.clamp_scroll();
+        // Mouse wheels scroll in steps, which are smoothed. Touchpads already scroll smoothly.
+        let behavior = match delta {
+            WheelDelta::Lines { .. } => ScrollBehavior::Smooth,
+            WheelDelta::Pixels { .. } => ScrollBehavior::Instant,
+        };
+        self.scroll_by(delta_y, behavior);
     }
 
     /// Clamp scroll offset
@@ -261,20 +470,67 @@ where
             .au_viewport_size()
             .height
             .to_f64_px();
-        self.scroll_offset = self
-            .scroll_offset
-            .max(-(content_height - viewport_height))
-            .min(0.0);
+        let scroll = self
+            .dom
+            .as_ref()
+            .viewport_scroll()
+            .min(content_height - viewport_height)
+            .max(0.0);
+        self.dom.as_mut().set_viewport_scroll(scroll);
+    }
+
+    /// Select the element under the mouse for the box model overlay, or deselect it if it's already selected
+    pub fn inspect_node_at_mouse(&mut self) {
+        let dom = self.dom.as_ref();
+        let mut node_id = dom.hit(self.mouse_pos.0, self.mouse_pos.1);
+
+        // Text is inspected through the element that contains it
+        while let Some(id) = node_id.filter(|id| !dom.tree()[*id].is_element()) {
+            node_id = dom.tree()[id].parent;
+        }
+
+        self.devtools.inspected_node = match node_id == self.devtools.inspected_node {
+            true => None,
+            false => node_id,
+        };
     }
 
     pub fn click(&mut self, button: &str) {
+        // An open context menu is above everything else, so it receives clicks first
+        if button == "left" && self.dom.as_ref().open_context_menu().is_some() {
+            let x = self.mouse_pos.0;
+            let y = self.mouse_pos.1 - self.dom.as_ref().viewport_scroll() as f32;
+            if self.dom.as_mut().handle_context_menu_click(x, y) {
+                // Elements chosen with "Inspect" have their box model drawn
+                if let Some(node_id) = self.dom.as_mut().take_inspect_request() {
+                    self.devtools.inspected_node = Some(node_id);
+                }
+                return;
+            }
+        }
+
+        // As is an open picker popup
+        if button == "left" && self.dom.as_ref().open_picker().is_some() {
+            let x = self.mouse_pos.0;
+            let y = self.mouse_pos.1 - self.dom.as_ref().viewport_scroll() as f32;
+            match self.dom.as_mut().handle_picker_click(x, y) {
+                PickerClick::ValueChanged(node_id) => {
+                    self.dispatch_input_event(node_id, "input");
+                    self.dispatch_input_event(node_id, "change");
+                    return;
+                }
+                PickerClick::Handled => return,
+                PickerClick::Outside => {}
+            }
+        }
+
         let Some(node_id) = self.dom.as_ref().get_hover_node_id() else {
             return;
         };
 
-        let RenderState::Active(_) = &self.render_state else {
+        if self.render_state.viewport().is_none() {
             return;
-        };
+        }
 
         if self.devtools.highlight_hover {
             let mut node = self.dom.as_ref().get_node(node_id).unwrap();
@@ -354,18 +610,388 @@ where
             // taffy::print_tree(&self.dom, node_id.into());
         }
 
+        // Right clicks open a context menu, unless the page handles the `contextmenu` event
+        if !self.devtools.highlight_hover && button == "right" {
+            let data = self.dom.as_ref().pointer_event_data(
+                node_id,
+                node_id,
+                self.mouse_pos.0,
+                self.mouse_pos.1,
+            );
+            let handled = self.dom.handle_event(RendererEvent {
+                name: "contextmenu".to_string(),
+                target: node_id,
+                data: EventData::Click(data),
+            });
+            let dom = self.dom.as_mut();
+            if handled {
+                dom.close_context_menu();
+            } else {
+                let y = self.mouse_pos.1 - dom.viewport_scroll() as f32;
+                dom.show_context_menu(node_id, self.mouse_pos.0, y);
+            }
+        }
+
         // If we hit a node, then we collect the node to its parents, check for listeners, and then
         // call those listeners
         if !self.devtools.highlight_hover && button == "left" {
+            // Default actions
+            self.dom.as_mut().handle_popover_click(node_id);
+
+            // Clicking moves focus to the clicked element (or the focusable element it's within), and presses it until
+            // the button is released
+            let dom = self.dom.as_mut();
+            match dom.focusable_ancestor(node_id) {
+                Some(focus_id) => {
+                    dom.focus(focus_id);
+                }
+                None => dom.blur(),
+            }
+            dom.set_active_element(Some(node_id));
+
+            // Clicking within a contenteditable element places the caret
+            self.dom
+                .as_mut()
+                .place_caret(self.mouse_pos.0, self.mouse_pos.1, false);
+
+            // Date and color inputs open a picker, file inputs show the file dialog
+            self.dom.as_mut().activate_picker(node_id);
+
+            // Pressing a number input's spin buttons steps its value
+            let spin_button =
+                self.dom
+                    .as_ref()
+                    .spin_button_at(node_id, self.mouse_pos.0, self.mouse_pos.1);
+            if let Some(direction) = spin_button {
+                self.step_number_input(node_id, direction);
+            }
+
+            // Pressing on a range input moves its thumb to the pointer and starts dragging it
+            let value_before = self.dom.as_ref().tree()[node_id]
+                .attr(local_name!("value"))
+                .map(str::to_owned);
+            if self
+                .dom
+                .as_mut()
+                .begin_range_drag(node_id, self.mouse_pos.0)
+            {
+                if self.dom.as_ref().tree()[node_id].attr(local_name!("value"))
+                    != value_before.as_deref()
+                {
+                    self.dispatch_input_event(node_id, "input");
+                }
+                return;
+            }
+
+            // Clicks within an iframe are dispatched to the nested document's hovered node, with coordinates relative
+            // to the nested document
+            let sub_document_hit = self
+                .dom
+                .as_ref()
+                .hit_sub_document(self.mouse_pos.0, self.mouse_pos.1);
+            if let Some(sub_document) = self.dom.as_mut().sub_document_mut(node_id) {
+                if let (Some(target), Some((_, x, y))) =
+                    (sub_document.get_hover_node_id(), sub_document_hit)
+                {
+                    let data = sub_document.pointer_event_data(target, target, x, y);
+                    sub_document.handle_event(RendererEvent {
+                        name: "click".to_string(),
+                        target,
+                        data: EventData::Click(data),
+                    });
+                }
+                return;
+            }
+
+            let data = self.dom.as_ref().pointer_event_data(
+                node_id,
+                node_id,
+                self.mouse_pos.0,
+                self.mouse_pos.1,
+            );
             self.dom.handle_event(RendererEvent {
                 name: "click".to_string(),
                 target: node_id,
-                data: EventData::Click {
-                    x: self.mouse_pos.0 as f64,
-                    y: self.mouse_pos.1 as f64,
-                },
+                data: EventData::Click(data),
             });
+            self.activate_label_target(node_id);
+            self.run_button_action(node_id);
+        }
+    }
+
+    /// Activate an element as if it had been clicked (e.g. with its access key), scrolling it into view
+    pub fn activate(&mut self, node_id: usize) {
+        let dom = self.dom.as_mut();
+        let position = dom.absolute_position(node_id);
+        let height = dom.tree()[node_id].final_layout.size.height;
+        dom.scroll_into_view(position.y as f64, (position.y + height) as f64);
+
+        // Default actions
+        dom.handle_popover_click(node_id);
+        dom.activate_picker(node_id);
+
+        let data = self
+            .dom
+            .as_ref()
+            .pointer_event_data(node_id, node_id, position.x, position.y);
+        self.dom.handle_event(RendererEvent {
+            name: "click".to_string(),
+            target: node_id,
+            data: EventData::Click(data),
+        });
+        self.activate_label_target(node_id);
+        self.run_button_action(node_id);
+    }
+
+    /// Submit buttons submit their form and reset buttons reset it (see [`blitz_dom::forms`])
+    fn run_button_action(&mut self, node_id: usize) {
+        let dom = self.dom.as_ref();
+        let Some(button_id) = dom
+            .button_ancestor(node_id)
+            .filter(|&button_id| !dom.is_disabled(button_id))
+        else {
+            return;
+        };
+        let Some(form_id) = dom.form_owner(button_id) else {
+            return;
+        };
+        match dom.button_type(button_id) {
+            Some(ButtonType::Submit) => {
+                self.submit_form(form_id, Some(button_id));
+            }
+            Some(ButtonType::Reset) => self.reset_form(form_id),
+            Some(ButtonType::Button) | None => {}
+        }
+    }
+
+    /// Submit a form, unless it's invalid or the page handles its "submit" event. Returns true if it was submitted.
+    pub fn submit_form(&mut self, form_id: usize, submitter: Option<usize>) -> bool {
+        let Some(submission) = self
+            .dom
+            .as_mut()
+            .prepare_form_submission(form_id, submitter)
+        else {
+            return false;
+        };
+        if self.dispatch_form_event(form_id, "submit") {
+            return false;
+        }
+        self.dom.as_mut().submit_form(submission);
+        true
+    }
+
+    /// Reset a form, unless the page handles its "reset" event
+    pub fn reset_form(&mut self, form_id: usize) {
+        if !self.dispatch_form_event(form_id, "reset") {
+            self.dom.as_mut().reset_form(form_id);
+        }
+    }
+
+    /// Submit the form of a text field in which enter was pressed, by activating the form's default button (or
+    /// submitting it directly if it has none). Returns false if the field isn't in a form.
+    pub fn submit_implicitly(&mut self, node_id: usize) -> bool {
+        let dom = self.dom.as_ref();
+        let Some(form_id) = dom.form_owner(node_id) else {
+            return false;
+        };
+        match dom.default_button(form_id) {
+            Some(button_id) => {
+                if !dom.is_disabled(button_id) {
+                    self.activate(button_id);
+                }
+            }
+            None => {
+                self.submit_form(form_id, None);
+            }
+        }
+        true
+    }
+
+    /// Dispatch a "submit" or "reset" event on a form. Returns true if the page handled it.
+    fn dispatch_form_event(&mut self, form_id: usize, name: &str) -> bool {
+        self.dom.handle_event(RendererEvent {
+            name: name.to_string(),
+            target: form_id,
+            data: EventData::Input {
+                value: String::new(),
+                input_type: None,
+                data: None,
+            },
+        })
+    }
+
+    /// Clicking within a `<label>` focuses and activates its control (see [`blitz_dom::labels`])
+    fn activate_label_target(&mut self, node_id: usize) {
+        if let Some(control_id) = self.dom.as_ref().label_target(node_id) {
+            self.dom.as_mut().focus(control_id);
+            self.activate(control_id);
+        }
+    }
+
+    /// Activate the element with the given access key (see [`blitz_dom::access_keys`]). Returns false if no element
+    /// has the access key.
+    pub fn activate_access_key(&mut self, key: char) -> bool {
+        match self.dom.as_ref().access_key_element(key) {
+            Some(node_id) => {
+                self.activate(node_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move focus to the nearest focusable element in a direction (see [`blitz_dom::spatial_navigation`]). Returns
+    /// false if there's no element to move to.
+    pub fn navigate(&mut self, direction: NavigationDirection) -> bool {
+        self.dom.as_mut().navigate_spatially(direction).is_some()
+    }
+
+    /// Activate the focused element, as if it had been clicked. Returns false if nothing has focus.
+    pub fn activate_focused(&mut self) -> bool {
+        match self.dom.as_ref().focused_element() {
+            Some(node_id) => {
+                self.activate(node_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handle the release of a mouse button
+    pub fn mouse_up(&mut self, button: &str) {
+        if button != "left" {
+            return;
+        }
+        self.dom.as_mut().set_active_element(None);
+
+        // Releasing a range input's thumb commits its value
+        if let Some(node_id) = self.dom.as_mut().end_range_drag() {
+            self.dispatch_input_event(node_id, "change");
+        }
+    }
+
+    /// Apply an editing command to the focused text field, or at the caret of a `contenteditable` element. A
+    /// "beforeinput" event is dispatched on the field or editing host first, and an "input" event after if it changed.
+    ///
+    /// Note: event handlers can't prevent default actions yet, so "beforeinput" events can't cancel the edit.
+    pub fn edit(&mut self, command: EditCommand) -> bool {
+        let dom = self.dom.as_ref();
+        let Some(target) = dom
+            .focused_element()
+            .filter(|&node_id| dom.is_text_field(node_id))
+            .or_else(|| {
+                dom.caret()
+                    .and_then(|caret| dom.editing_host(caret.node_id))
+            })
+        else {
+            return false;
+        };
+        let input_type = command.input_type();
+        let data = command.data().map(str::to_string);
+
+        self.dispatch_edit_event(target, "beforeinput", input_type, data.clone());
+        let changed = if self.dom.as_ref().is_text_field(target) {
+            self.dom.as_mut().edit_text_field(target, command)
+        } else {
+            self.dom.as_mut().exec_command(command)
+        };
+        if !changed {
+            return false;
+        }
+        self.dispatch_edit_event(target, "input", input_type, data);
+        true
+    }
+
+    /// Choose the highlighted suggestion of an open `<datalist>` dropdown, dispatching "input" and "change" events on
+    /// its input if its value changed
+    pub fn choose_suggestion(&mut self) -> bool {
+        if !self.dom.as_ref().suggestions_open() {
+            return false;
+        }
+        if let Some(node_id) = self.dom.as_mut().choose_highlighted_suggestion() {
+            self.dispatch_input_event(node_id, "input");
+            self.dispatch_input_event(node_id, "change");
+        }
+        true
+    }
+
+    /// Step the value of an `<input type=number>` up or down, dispatching "input" and "change" events on it if its
+    /// value changed
+    pub fn step_number_input(&mut self, node_id: usize, direction: SpinDirection) -> bool {
+        if !self.dom.as_mut().step_number_input(node_id, direction) {
+            return false;
+        }
+        self.dispatch_input_event(node_id, "input");
+        self.dispatch_input_event(node_id, "change");
+        true
+    }
+
+    /// Undo the last edit (or embedder transaction), dispatching an "input" event on the element it changed
+    pub fn undo(&mut self) -> bool {
+        let Some(scope) = self.dom.as_mut().undo() else {
+            return false;
+        };
+        self.dispatch_edit_event(scope, "input", InputType::HistoryUndo, None);
+        true
+    }
+
+    /// Redo the last edit that was undone, dispatching an "input" event on the element it changed
+    pub fn redo(&mut self) -> bool {
+        let Some(scope) = self.dom.as_mut().redo() else {
+            return false;
+        };
+        self.dispatch_edit_event(scope, "input", InputType::HistoryRedo, None);
+        true
+    }
+
+    /// Dispatch a "beforeinput" or "input" event for an edit, carrying the value of a text field or the text of an
+    /// editing host
+    fn dispatch_edit_event(
+        &mut self,
+        node_id: usize,
+        name: &str,
+        input_type: InputType,
+        data: Option<String>,
+    ) {
+        let dom = self.dom.as_ref();
+        let value = if dom.is_text_field(node_id) {
+            dom.tree()[node_id]
+                .attr(local_name!("value"))
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            dom.tree()[node_id].text_content()
+        };
+        self.dom.handle_event(RendererEvent {
+            name: name.to_string(),
+            target: node_id,
+            data: EventData::Input {
+                value,
+                input_type: Some(input_type),
+                data,
+            },
+        });
+    }
+
+    /// Dispatch an "input" or "change" event carrying the current value of a form control
+    fn dispatch_input_event(&mut self, node_id: usize, name: &str) {
+        if name == "change" {
+            self.dom.as_mut().mark_user_interacted(node_id);
         }
+
+        let value = self.dom.as_ref().tree()[node_id]
+            .attr(local_name!("value"))
+            .unwrap_or_default()
+            .to_string();
+        self.dom.handle_event(RendererEvent {
+            name: name.to_string(),
+            target: node_id,
+            data: EventData::Input {
+                value,
+                input_type: None,
+                data: None,
+            },
+        });
     }
 
     pub fn print_taffy_tree(&self) {
@@ -374,27 +1000,31 @@ where
 
     // Adjust the viewport
     pub fn set_size(&mut self, physical_size: (u32, u32)) {
-        let RenderState::Active(state) = &mut self.render_state else {
+        let Some(viewport) = self.render_state.viewport_mut() else {
             return;
         };
-        state.viewport.window_size = physical_size;
+        viewport.window_size = physical_size;
         self.kick_viewport()
     }
 
     pub fn kick_viewport(&mut self) {
-        let RenderState::Active(state) = &mut self.render_state else {
+        let meta = self.dom.as_ref().viewport_meta();
+        let Some(viewport) = self.render_state.viewport_mut() else {
             return;
         };
 
-        let (width, height) = state.viewport.window_size;
+        let (width, height) = viewport.window_size;
+        viewport.set_meta(meta);
 
         if width > 0 && height > 0 {
-            self.dom
-                .as_mut()
-                .set_stylist_device(state.viewport.make_device());
-            self.dom.as_mut().set_scale(state.viewport.scale());
-            self.render_context
-                .resize_surface(&mut state.surface, width, height);
+            let device = viewport.make_device();
+            let scale = viewport.scale();
+            self.dom.as_mut().set_stylist_device(device);
+            self.dom.as_mut().set_scale(scale);
+            if let RenderState::Active(state) = &mut self.render_state {
+                self.render_context
+                    .resize_surface(&mut state.surface, width, height);
+            }
             self.clamp_scroll();
         }
     }
@@ -405,66 +1035,775 @@ where
     /// This assumes styles are resolved and layout is complete.
     /// Make sure you do those before trying to render
     pub fn render(&mut self, scene: &mut Scene) {
-        // Simply render the document (the root element (note that this is not the same as the root node)))
-        scene.reset();
-        self.render_element(
-            scene,
-            self.dom.as_ref().root_element().id,
-            Point {
-                x: 0.0,
-                y: self.scroll_offset,
-            },
-        );
+        let RenderState::Active(state) = &self.render_state else {
+            return;
+        };
+        let width = state.surface.config.width;
+        let height = state.surface.config.height;
+        let scale = state.viewport.scale_f64();
+        let frame_valid = state
+            .frame
+            .as_ref()
+            .is_some_and(|frame| frame.width == width && frame.height == height);
+
+        // Work out which region of the viewport needs to be repainted
+        let mut damage = self.dom.as_mut().collect_damage();
+
+        // The cached layers can be composited at the new scroll position as long as nothing else has changed, or
+        // everything that changed is within promoted layers (which are repainted on their own)
+        let dirty_layers = self.dirty_promoted_layers(&damage);
+        self.reuse_layers
+            .set(!damage.content_changed() || dirty_layers.is_some());
+        *self.dirty_layers.borrow_mut() = dirty_layers.unwrap_or_default();
+        if damage.scrolled {
+            damage.full = true;
+        }
 
-        // Render debug overlay
-        if self.devtools.highlight_hover {
-            if let Some(node_id) = self.dom.as_ref().get_hover_node_id() {
-                self.render_debug_overlay(scene, node_id);
-            }
+        let overlay = self.overlay_signature();
+        if self.painted_overlay != Some(overlay) || !frame_valid {
+            damage.full = true;
+            self.painted_overlay = Some(overlay);
+        }
+        let region = if damage.full {
+            Some(PixelRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            })
+        } else {
+            damage.bounds().and_then(|bounds| {
+                let x0 = ((f64::from(bounds.x) * scale).floor().max(0.0) as u32).min(width);
+                let y0 = ((f64::from(bounds.y) * scale).floor().max(0.0) as u32).min(height);
+                let x1 = ((f64::from(bounds.x + bounds.width) * scale).ceil().max(0.0) as u32)
+                    .min(width);
+                let y1 = ((f64::from(bounds.y + bounds.height) * scale)
+                    .ceil()
+                    .max(0.0) as u32)
+                    .min(height);
+                (x1 > x0 && y1 > y0).then_some(PixelRect {
+                    x: x0,
+                    y: y0,
+                    width: x1 - x0,
+                    height: y1 - y0,
+                })
+            })
+        };
+        let Some(region) = region else {
+            self.repaint_stats.skipped_frames += 1;
+            self.dom.as_mut().end_frame();
+            return;
+        };
+
+        // Only paint the elements that intersect the damaged region
+        self.paint_region.set((!damage.full).then(|| {
+            Rect::new(
+                f64::from(region.x),
+                f64::from(region.y),
+                f64::from(region.x + region.width),
+                f64::from(region.y + region.height),
+            )
+        }));
+        let paint_start = Instant::now();
+        tracing::info_span!("frame_phase", phase = FramePhase::Paint.name())
+            .in_scope(|| self.paint_scene(scene));
+        self.dom
+            .as_mut()
+            .record_timing(FramePhase::Paint, paint_start.elapsed());
+        self.paint_region.set(None);
+
+        if damage.full {
+            self.repaint_stats.full_repaints += 1;
+        } else {
+            self.repaint_stats.partial_repaints += 1;
         }
+        self.repaint_stats.last_repaint_area = region.area();
+        self.repaint_stats.last_painted_elements = self.painted_elements.get();
+        self.repaint_stats.last_reused_elements = self.reused_elements.get();
 
         let RenderState::Active(state) = &mut self.render_state else {
             return;
         };
+        let _gpu_span =
+            tracing::info_span!("frame_phase", phase = FramePhase::GpuSubmit.name()).entered();
+        let gpu_start = Instant::now();
+        let device = &self.render_context.devices[state.surface.dev_id];
+        if !frame_valid {
+            state.frame = Some(FrameTarget::new(&device.device, width, height));
+        }
+        let frame = state.frame.as_ref().unwrap();
+
+        let render_params = RenderParams {
+            base_color: palette_color(self.dom.as_ref().control_palette().canvas),
+            width: region.width,
+            height: region.height,
+            antialiasing_method: state.viewport.text_rendering.antialiasing.aa_config(),
+        };
+        let mut encoder = device
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Repaint"),
+            });
+
+        if damage.full {
+            state
+                .renderer
+                .render_to_texture(
+                    &device.device,
+                    &device.queue,
+                    scene,
+                    &frame.view,
+                    &render_params,
+                )
+                .expect("failed to render to texture");
+        } else {
+            // Render the damaged region on its own and copy it into the frame
+            let target = create_render_target(
+                &device.device,
+                "Damaged region",
+                region.width,
+                region.height,
+                wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            );
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut region_scene = Scene::new();
+            region_scene.append(
+                scene,
+                Some(Affine::translate((
+                    -f64::from(region.x),
+                    -f64::from(region.y),
+                ))),
+            );
+            state
+                .renderer
+                .render_to_texture(
+                    &device.device,
+                    &device.queue,
+                    &region_scene,
+                    &view,
+                    &render_params,
+                )
+                .expect("failed to render to texture");
+
+            encoder.copy_texture_to_texture(
+                target.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    texture: &frame.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: region.x,
+                        y: region.y,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: region.width,
+                    height: region.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
         let surface_texture = match state.surface.surface.get_current_texture() {
             Ok(surface) => surface,
-            // When resizing too aggresively, the surface can get outdated (another resize) before being rendered into
-            Err(SurfaceError::Outdated) => return,
+            // When resizing too aggresively, the surface can get outdated (another resize) before being rendered into.
+            // The frame is up to date, so it will be presented in full next time.
+            Err(SurfaceError::Outdated) => {
+                device.queue.submit([encoder.finish()]);
+                let dom = self.dom.as_mut();
+                dom.record_timing(FramePhase::GpuSubmit, gpu_start.elapsed());
+                dom.end_frame();
+                return;
+            }
             Err(_) => panic!("failed to get surface texture"),
         };
+        let surface_view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        state
+            .blitter
+            .blit(&device.device, &mut encoder, &frame.view, &surface_view);
+        device.queue.submit([encoder.finish()]);
 
-        let device = &self.render_context.devices[state.surface.dev_id];
+        surface_texture.present();
+        device.device.poll(wgpu::Maintain::Wait);
+
+        let dom = self.dom.as_mut();
+        dom.record_timing(FramePhase::GpuSubmit, gpu_start.elapsed());
+        dom.end_frame();
+    }
+
+    /// Statistics about how much of the viewport has been repainted
+    pub fn repaint_stats(&self) -> RepaintStats {
+        self.repaint_stats
+    }
+
+    /// Statistics about the images resized for display
+    pub fn image_cache_stats(&self) -> ImageCacheStats {
+        self.images.borrow().stats()
+    }
+
+    /// Set how much memory (in bytes) images resized for display can use before the least recently drawn are evicted
+    pub fn set_image_cache_budget(&mut self, bytes: usize) {
+        self.images.borrow_mut().set_budget(bytes);
+    }
+
+    /// A hash of the UI drawn over the document. Any change to it repaints the whole viewport.
+    fn overlay_signature(&self) -> u64 {
+        let dom = self.dom.as_ref();
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+
+        dom.caret_rect()
+            .map(|(position, height)| {
+                [position.x.to_bits(), position.y.to_bits(), height.to_bits()]
+            })
+            .hash(&mut hasher);
+        for find_match in dom.find_matches() {
+            (
+                find_match.node_id,
+                find_match.range.start,
+                find_match.range.end,
+            )
+                .hash(&mut hasher);
+        }
+        dom.active_find_match().hash(&mut hasher);
+        for (node_id, _) in dom.top_layer() {
+            node_id.hash(&mut hasher);
+        }
+        if let Some(picker) = dom.open_picker() {
+            picker.node_id.hash(&mut hasher);
+            for cell in &picker.cells {
+                cell.selected.hash(&mut hasher);
+                cell.label
+                    .as_ref()
+                    .map(|label| &label.text)
+                    .hash(&mut hasher);
+            }
+        }
+        if let Some(menu) = dom.open_context_menu() {
+            menu.target.hash(&mut hasher);
+            [menu.location.x.to_bits(), menu.location.y.to_bits()].hash(&mut hasher);
+            for row in &menu.rows {
+                row.item.hash(&mut hasher);
+            }
+        }
+        self.devtools.show_layout.hash(&mut hasher);
+        self.devtools.highlight_hover.hash(&mut hasher);
+        if self.devtools.highlight_hover {
+            dom.get_hover_node_id().hash(&mut hasher);
+        }
+        if let Some(box_model) = self
+            .devtools
+            .inspected_node
+            .and_then(|node_id| dom.box_model(node_id))
+        {
+            let rect = box_model.margin;
+            [rect.x, rect.y, rect.width, rect.height]
+                .map(f32::to_bits)
+                .hash(&mut hasher);
+        }
+        self.devtools.show_frame_timings.hash(&mut hasher);
+        if self.devtools.show_frame_timings {
+            dom.frame_timings().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    /// Lay the document out for a viewport of the given size (in physical pixels) and render it into an image. The
+    /// display scale of the current viewport (if any) is kept. Works whether or not the renderer has a window: the
+    /// window's layout is restored afterwards.
+    pub async fn render_to_image(&mut self, width: u32, height: u32) -> RgbaImage {
+        let mut viewport = Viewport::new((width, height));
+        viewport.set_hidpi_scale(self.render_state.viewport().map_or(1.0, Viewport::scale));
+        viewport.text_rendering = self.text_rendering();
+
+        let previous_state =
+            std::mem::replace(&mut self.render_state, RenderState::Headless(viewport));
+        self.kick_layout();
+        // There's no event loop to show SVG images once they've been rasterized
+        self.dom.as_mut().wait_for_rasterized_images();
+
+        let mut scene = Scene::new();
+        self.paint_scene(&mut scene);
+        let image = self.rasterize(&scene, width, height).await;
+
+        self.render_state = previous_state;
+        self.kick_layout();
+
+        image
+    }
+
+    /// Render the document into a PNG file (see [`Renderer::render_to_image`])
+    pub async fn render_to_png(
+        &mut self,
+        width: u32,
+        height: u32,
+        path: impl AsRef<std::path::Path>,
+    ) -> image::ImageResult<()> {
+        self.render_to_image(width, height).await.save(path)
+    }
+
+    /// Restyle and relayout the document for the current viewport
+    fn kick_layout(&mut self) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let device = viewport.make_device();
+        let scale = viewport.scale();
+        self.dom.as_mut().set_stylist_device(device);
+        self.dom.as_mut().set_scale(scale);
+        self.dom.as_mut().resolve();
+    }
+
+    /// Render a scene into an offscreen texture and read it back
+    async fn rasterize(&mut self, scene: &Scene, width: u32, height: u32) -> RgbaImage {
+        if width == 0 || height == 0 {
+            return RgbaImage::new(width, height);
+        }
+
+        let dev_id = self
+            .render_context
+            .device(None)
+            .await
+            .expect("No compatible GPU device found");
+        let device_handle = &self.render_context.devices[dev_id];
+        let device = &device_handle.device;
+        let queue = &device_handle.queue;
+
+        let mut renderer = VelloRenderer::new(
+            device,
+            RendererOptions {
+                surface_format: None,
+                antialiasing_support: AaSupport::all(),
+                use_cpu: false,
+                num_init_threads: None,
+            },
+        )
+        .unwrap();
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let target = create_render_target(
+            device,
+            "Headless render target",
+            width,
+            height,
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        );
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
 
         let render_params = RenderParams {
-            base_color: Color::WHITE,
-            width: state.surface.config.width,
-            height: state.surface.config.height,
-            antialiasing_method: vello::AaConfig::Msaa16,
+            base_color: palette_color(self.dom.as_ref().control_palette().canvas),
+            width,
+            height,
+            antialiasing_method: self.text_rendering().antialiasing.aa_config(),
         };
+        renderer
+            .render_to_texture(device, queue, scene, &view, &render_params)
+            .expect("failed to render to texture");
+
+        // Copy the texture into a buffer that can be read by the CPU. Rows of the copy must be padded to a multiple of
+        // 256 bytes.
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless readback buffer"),
+            size: u64::from(padded_bytes_per_row * height),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
 
-        state
-            .renderer
-            .render_to_surface(
-                &device.device,
-                &device.queue,
-                scene,
-                &surface_texture,
-                &render_params,
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("readback buffer was dropped")
+            .expect("failed to map readback buffer");
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+        drop(data);
+        buffer.unmap();
+
+        RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+
+    /// Paint the document (and any UI drawn over it, such as the caret and pickers) into a scene, without presenting it
+    pub fn paint_scene(&self, scene: &mut Scene) {
+        // Simply render the document (the root element (note that this is not the same as the root node)))
+        scene.reset();
+        self.painted_elements.set(0);
+        self.reused_elements.set(0);
+        self.display_items.borrow_mut().begin_frame();
+        self.images.borrow_mut().begin_frame();
+        let repainted_layers = self.paint_layers(scene, self.dom.as_ref());
+
+        // Highlight find-in-page matches
+        self.render_find_matches(scene, self.dom.as_ref());
+
+        // Highlight the selection within a contenteditable element (if any)
+        self.render_selection(scene, self.dom.as_ref());
+
+        // Render the top layer above everything else
+        self.render_top_layer(scene, self.dom.as_ref());
+
+        // Render the caret of the focused contenteditable element (if any)
+        self.render_caret(scene, self.dom.as_ref());
+
+        // Render the open picker popup (if any) above the top layer
+        self.render_picker(scene, self.dom.as_ref());
+
+        // Render the open context menu (if any) above that
+        self.render_context_menu(scene, self.dom.as_ref());
+
+        // Render debug overlay
+        if self.devtools.highlight_hover {
+            if let Some(node_id) = self.dom.as_ref().get_hover_node_id() {
+                self.render_debug_overlay(scene, node_id);
+            }
+        }
+
+        // Render the box model of the inspected element
+        if let Some(node_id) = self.devtools.inspected_node {
+            self.render_box_model(scene, self.dom.as_ref(), node_id);
+        }
+
+        // Render the frame timings graph
+        if self.devtools.show_frame_timings {
+            self.render_frame_timings(scene, self.dom.as_ref());
+        }
+
+        // Reused layers still refer to the display items of the elements in them
+        if repainted_layers {
+            self.display_items.borrow_mut().end_frame();
+        }
+    }
+
+    /// Composites the document's layers into the scene, repainting them unless only the scroll position has changed.
+    /// Returns whether the layers were repainted.
+    fn paint_layers(&self, scene: &mut Scene, dom: &Document) -> bool {
+        let Some(viewport) = self.render_state.viewport() else {
+            return false;
+        };
+        let scale = viewport.scale_f64();
+        let scroll = dom.viewport_scroll();
+        let scroll_transform = Affine::translate((0.0, -scroll * scale));
+
+        let mut tree = self.layers.borrow_mut();
+        let reuse = self.reuse_layers.replace(false)
+            && tree.complete
+            && tree.scale == scale
+            && tree.show_layout == self.devtools.show_layout;
+
+        if reuse {
+            tree.reuses += 1;
+
+            // Promoted layers whose content changed are repainted on their own. Their fixed position descendants
+            // haven't changed, so keep their own layers.
+            let dirty = self.dirty_layers.take();
+            if !dirty.is_empty() {
+                let region = self.paint_region.replace(None);
+                *self.fixed_elements.borrow_mut() = Some(Vec::new());
+                for layer in tree.layers.iter_mut() {
+                    if let LayerKind::Promoted(node_id) = layer.kind {
+                        if dirty.contains(&node_id) {
+                            let location = layer.location;
+                            self.paint_layer(layer, dom, node_id, location);
+                        }
+                    }
+                }
+                self.fixed_elements.take();
+                self.paint_region.set(region);
+            }
+        } else {
+            let root = dom.root_element();
+            let region = self.paint_region.get();
+
+            // The scrolling content is painted in document coordinates
+            let mut content = tree.take_layer(LayerKind::ScrollingContent);
+            content.scene.reset();
+            content.paints += 1;
+            content.bounds = Rect::new(
+                0.0,
+                0.0,
+                f64::from(root.final_layout.size.width),
+                f64::from(root.final_layout.size.height),
+            );
+            self.paint_region
+                .set(region.map(|region| region + Vec2::new(0.0, scroll * scale)));
+            *self.fixed_elements.borrow_mut() = Some(Vec::new());
+            *self.promoted_elements.borrow_mut() = Some(Vec::new());
+            self.render_element(&mut content.scene, dom, root.id, Point::ZERO);
+            let promoted_elements = self.promoted_elements.take().unwrap_or_default();
+
+            // Promoted elements are painted in document coordinates
+            let mut layers = vec![content];
+            for (node_id, location) in promoted_elements {
+                let mut layer = tree.take_layer(LayerKind::Promoted(node_id));
+                self.paint_layer(&mut layer, dom, node_id, location);
+                layers.push(layer);
+            }
+            let fixed_elements = self.fixed_elements.take().unwrap_or_default();
+            self.paint_region.set(region);
+
+            // Fixed position elements are painted in viewport coordinates
+            for (node_id, location) in fixed_elements {
+                let mut layer = tree.take_layer(LayerKind::Fixed(node_id));
+                self.paint_layer(&mut layer, dom, node_id, location);
+                layers.push(layer);
+            }
+
+            tree.layers = layers;
+            tree.complete = region.is_none();
+            tree.scale = scale;
+            tree.show_layout = self.devtools.show_layout;
+            tree.reuses = 0;
+        }
+
+        for layer in &tree.layers {
+            let transform = match layer.kind {
+                LayerKind::ScrollingContent | LayerKind::Promoted(_) => Some(scroll_transform),
+                LayerKind::Fixed(_) => None,
+            };
+            scene.append(&layer.scene, transform);
+        }
+
+        !reuse
+    }
+
+    /// Paint an element (and its descendants) into a layer of its own
+    fn paint_layer(&self, layer: &mut Layer, dom: &Document, node_id: usize, location: Point) {
+        layer.scene.reset();
+        layer.paints += 1;
+        let (layout, pos) = self.node_position(dom, node_id, location);
+        layer.bounds = Rect::new(
+            pos.x,
+            pos.y,
+            pos.x + f64::from(layout.size.width),
+            pos.y + f64::from(layout.size.height),
+        );
+        layer.location = location;
+        self.render_element(&mut layer.scene, dom, node_id, location);
+    }
+
+    /// The promoted layers that hold everything that changed, or `None` if anything else changed
+    fn dirty_promoted_layers(&self, damage: &Damage) -> Option<Vec<usize>> {
+        if damage.full || damage.unattributed {
+            return None;
+        }
+        let dom = self.dom.as_ref();
+        let tree = self.layers.borrow();
+        let mut dirty = Vec::new();
+        for &node_id in &damage.nodes {
+            // Find the promoted layer the box was painted into. Fixed position descendants have layers of their own.
+            let mut maybe_id = Some(node_id);
+            let layer_id = loop {
+                let id = maybe_id?;
+                if tree
+                    .layers
+                    .iter()
+                    .any(|layer| layer.kind == LayerKind::Promoted(id))
+                {
+                    break id;
+                }
+                let node = dom.tree().get(id)?;
+                if node.is_fixed_position() {
+                    return None;
+                }
+                maybe_id = node.parent;
+            };
+
+            // Elements that are no longer promoted are painted into the scrolling content
+            if !dom.tree()[layer_id].will_change_compositing() {
+                return None;
+            }
+            if !dirty.contains(&layer_id) {
+                dirty.push(layer_id);
+            }
+        }
+        Some(dirty)
+    }
+
+    /// A textual dump of the layers the document was last painted into
+    pub fn layer_tree(&self) -> String {
+        self.layers
+            .borrow()
+            .dump(self.dom.as_ref().viewport_scroll())
+    }
+
+    pub fn print_layer_tree(&self) {
+        print!("{}", self.layer_tree());
+    }
+
+    /// Renders the box model of an element like the element highlighting of browser devtools: its margin, border,
+    /// padding and content boxes, the baselines of its lines of text, and the tracks of its flex or grid layout.
+    fn render_box_model(&self, scene: &mut Scene, dom: &Document, node_id: usize) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let Some(box_model) = dom.box_model(node_id) else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+        let to_rect = |rect: DomRect| {
+            Rect::new(
+                f64::from(rect.x) * scale,
+                f64::from(rect.y) * scale,
+                f64::from(rect.right()) * scale,
+                f64::from(rect.bottom()) * scale,
             )
-            .expect("failed to render to surface");
+        };
 
-        surface_texture.present();
-        device.device.poll(wgpu::Maintain::Wait);
+        // Each box is filled where it extends beyond the box within it
+        let boxes = [
+            (box_model.margin, Color::rgba8(246, 178, 107, 0xa8)),
+            (box_model.border, Color::rgba8(255, 229, 153, 0xa8)),
+            (box_model.padding, Color::rgba8(147, 196, 125, 0xa8)),
+        ];
+        for (index, (rect, color)) in boxes.iter().enumerate() {
+            let inner = boxes
+                .get(index + 1)
+                .map_or(box_model.content, |(inner, _)| *inner);
+            let mut path = to_rect(*rect).to_path(0.1);
+            path.extend(to_rect(inner).path_elements(0.1));
+            scene.fill(Fill::EvenOdd, Affine::IDENTITY, *color, None, &path);
+        }
+        let content = to_rect(box_model.content);
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::rgba8(111, 168, 220, 0xa8),
+            None,
+            &content,
+        );
+
+        for baseline in &box_model.baselines {
+            let y = f64::from(*baseline) * scale;
+            scene.stroke(
+                &Stroke::new(scale),
+                Affine::IDENTITY,
+                Color::rgb8(0xe0, 0x40, 0xe0),
+                None,
+                &Line::new((content.x0, y), (content.x1, y)),
+            );
+        }
+
+        let track_stroke = Stroke::new(scale).with_dashes(0.0, [4.0 * scale, 2.0 * scale]);
+        for track in &box_model.tracks {
+            scene.stroke(
+                &track_stroke,
+                Affine::IDENTITY,
+                Color::rgb8(0x80, 0x40, 0xd0),
+                None,
+                &to_rect(track.rect),
+            );
+        }
+    }
+
+    /// Renders a graph of the timings of recent frames in the top right corner of the viewport: a bar per frame,
+    /// stacked from the bottom in the order of the phases. The line marks the budget of a frame at 60 frames per
+    /// second, and the graph is tall enough for frames that take twice as long.
+    fn render_frame_timings(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+        let (window_width, _) = viewport.window_size;
+
+        const BAR_WIDTH: f64 = 2.0;
+        const GRAPH_HEIGHT: f64 = 80.0;
+        const FRAME_BUDGET_MS: f64 = 1000.0 / 60.0;
+        const MARGIN: f64 = 8.0;
+
+        let graph_width = BAR_WIDTH * FRAME_TIMING_HISTORY as f64 * scale;
+        let graph_height = GRAPH_HEIGHT * scale;
+        let transform = Affine::translate((
+            f64::from(window_width) - graph_width - MARGIN * scale,
+            MARGIN * scale,
+        ));
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            Color::rgba8(0, 0, 0, 0xb0),
+            None,
+            &Rect::new(0.0, 0.0, graph_width, graph_height),
+        );
+
+        let px_per_ms = graph_height / (FRAME_BUDGET_MS * 2.0);
+        let history = dom.frame_timing_history();
+        let x_offset = (FRAME_TIMING_HISTORY - history.len()) as f64;
+        for (index, timings) in history.enumerate() {
+            let x = (x_offset + index as f64) * BAR_WIDTH * scale;
+            let mut y = graph_height;
+            for phase in FramePhase::ALL {
+                let height = (timings.get(phase).as_secs_f64() * 1000.0 * px_per_ms).min(y);
+                if height <= 0.0 {
+                    continue;
+                }
+                let color = match phase {
+                    FramePhase::Parse => Color::rgb8(0xb0, 0x7c, 0xf0),
+                    FramePhase::Style => Color::rgb8(0x5a, 0xa0, 0xf0),
+                    FramePhase::Layout => Color::rgb8(0x4c, 0xc8, 0x8c),
+                    FramePhase::Paint => Color::rgb8(0xf0, 0xc0, 0x40),
+                    FramePhase::GpuSubmit => Color::rgb8(0xf0, 0x60, 0x50),
+                };
+                scene.fill(
+                    Fill::NonZero,
+                    transform,
+                    color,
+                    None,
+                    &Rect::new(x, y - height, x + BAR_WIDTH * scale, y),
+                );
+                y -= height;
+            }
+        }
+
+        let budget_y = graph_height - FRAME_BUDGET_MS * px_per_ms;
+        scene.stroke(
+            &Stroke::new(scale),
+            transform,
+            Color::WHITE,
+            None,
+            &Line::new((0.0, budget_y), (graph_width, budget_y)),
+        );
     }
 
     /// Renders a layout debugging overlay which visualises the content size, padding and border
     /// of the node with a transparent overlay.
     fn render_debug_overlay(&self, scene: &mut Scene, node_id: usize) {
-        let RenderState::Active(state) = &self.render_state else {
+        let Some(viewport) = self.render_state.viewport() else {
             return;
         };
-        let scale = state.viewport.scale_f64();
+        let scale = viewport.scale_f64();
 
         let mut node = &self.dom.as_ref().tree()[node_id];
 
@@ -488,14 +1827,22 @@ where
 
         let mut abs_x = x;
         let mut abs_y = y;
+        let mut in_top_layer = node.in_top_layer;
         while let Some(parent_id) = node.parent {
+            if in_top_layer {
+                break;
+            }
             node = &self.dom.as_ref().tree()[parent_id];
+            in_top_layer = node.in_top_layer;
             let taffy::Point { x, y } = node.final_layout.location;
             abs_x += x;
             abs_y += y;
         }
 
-        abs_y += self.scroll_offset as f32;
+        // Top layer elements are positioned relative to the viewport rather than the document
+        if !in_top_layer {
+            abs_y -= self.dom.as_ref().viewport_scroll() as f32;
+        }
 
         // Hack: scale factor
         let abs_x = f64::from(abs_x) * scale;
@@ -588,9 +1935,9 @@ where
     ///
     /// Approaching rendering this way guarantees we have all the styles we need when rendering text with not having
     /// to traverse back to the parent for its styles, or needing to pass down styles
-    fn render_element(&self, scene: &mut Scene, node_id: usize, location: Point) {
-        // Need to do research on how we can cache most of the bezpaths - there's gonna be a lot of encoding between frames.
-        // Might be able to cache resources deeper in vello.
+    fn render_element(&self, scene: &mut Scene, dom: &Document, node_id: usize, location: Point) {
+        // The element's own display items are cached between frames (see `display_items`), but its children are
+        // always visited since they may have changed independently.
         //
         // Implemented (completely):
         //  - nothing is completely done:
@@ -604,10 +1951,10 @@ where
         //  - list, position, table, text, ui,
         //  - custom_properties, writing_mode, rules, visited_style, flags,  box_, column, counters, effects,
         //  - inherited_box, inherited_table, inherited_text, inherited_ui,
-        let element = &self.dom.as_ref().tree()[node_id];
+        let element = &dom.tree()[node_id];
 
         // Early return if the element is hidden
-        if matches!(element.style.display, taffy::prelude::Display::None) {
+        if matches!(element.style.display, taffy::prelude::Display::None) || element.hidden {
             return;
         }
 
@@ -627,37 +1974,120 @@ where
             return;
         }
 
-        // Hide elements with a visibility style other than visible
-        if element
+        // Elements with a visibility style other than visible aren't painted, but their descendants may be visible
+        let visible = element
             .primary_styles()
             .unwrap()
             .get_inherited_box()
             .visibility
-            != StyloVisibility::Visible
-        {
-            return;
+            == StyloVisibility::Visible;
+
+        let cx = self.element_cx(dom, element, location);
+        let control_kind = dom.control_kind(node_id);
+        let sub_document = dom.sub_document(node_id);
+        let text_layout = element.is_inline_root.then(|| {
+            element
+                .raw_dom_data
+                .downcast_element()
+                .unwrap()
+                .inline_layout
+                .as_ref()
+                .unwrap_or_else(|| {
+                    dbg!(&element);
+                    panic!("Tried to render node marked as inline root that does not have an inline layout");
+                })
+        });
+
+        // The element and its descendants are clipped to its clip path
+        let clip = element.clip_shape();
+        if let Some(clip) = &clip {
+            scene.push_layer(
+                Mix::Clip,
+                1.0,
+                cx.transform,
+                &clip_shape_path(clip, cx.scale),
+            );
+        }
+
+        // When only part of the viewport is being repainted, elements outside of it are skipped (but their
+        // descendants may still be within it)
+        let items_id = (dom as *const Document as usize, node_id);
+        if !visible {
+            // Invisible elements have no display items to keep
+        } else if self.in_paint_region(&cx) {
+            self.painted_elements.set(self.painted_elements.get() + 1);
+
+            // The element's own display items are encoded relative to its border box, so they can be reused
+            // wherever it ends up
+            let key = self.display_items_key(dom, &cx);
+            let transform = cx.transform;
+            let reused = self.display_items.borrow_mut().append(
+                scene,
+                items_id,
+                key,
+                transform,
+                |fragment| {
+                    let cx = cx.at_origin();
+                    cx.stroke_effects(fragment);
+                    cx.stroke_outline(fragment);
+                    cx.stroke_frame(fragment);
+                    cx.stroke_border(fragment);
+                    cx.stroke_devtools(fragment);
+                    cx.draw_image(fragment);
+
+                    // Color inputs display their color
+                    if dom.picker_kind(node_id) == Some(PickerKind::Color) {
+                        cx.draw_color_swatch(fragment);
+                    }
+
+                    // Number inputs have spin buttons
+                    if dom.is_number_input(node_id) {
+                        cx.draw_spin_buttons(fragment, dom);
+                    }
+
+                    // MathML square roots draw their radical sign in their left padding
+                    if dom.is_radical(node_id) {
+                        cx.draw_radical(fragment);
+                    }
+
+                    // Progress bars, meters and range inputs are drawn rather than rendering their children
+                    if let Some(kind) = control_kind {
+                        cx.draw_control(fragment, dom, kind);
+                    } else if let (Some(text_layout), None) = (text_layout, sub_document) {
+                        // Text is offset by the inline root's padding and border
+                        let taffy::Layout {
+                            border, padding, ..
+                        } = element.final_layout;
+                        let scaled_pb = (padding + border).map(f64::from);
+                        let pos = Point {
+                            x: scaled_pb.left,
+                            y: scaled_pb.top,
+                        };
+                        cx.stroke_text(fragment, text_layout, pos);
+                    }
+                },
+            );
+            if reused {
+                self.reused_elements.set(self.reused_elements.get() + 1);
+            }
+
+            // The content of an iframe is its nested document rather than its children
+            if let Some(sub_document) = sub_document {
+                self.render_sub_document(scene, sub_document, &cx);
+            }
+        } else {
+            self.display_items.borrow_mut().retain(items_id);
         }
 
-        let cx = self.element_cx(element, location);
-        cx.stroke_effects(scene);
-        cx.stroke_outline(scene);
-        cx.stroke_frame(scene);
-        cx.stroke_border(scene);
-        cx.stroke_devtools(scene);
-        cx.draw_image(scene);
+        if control_kind.is_some() || sub_document.is_some() {
+            if clip.is_some() {
+                scene.pop_layer();
+            }
+            return;
+        }
 
-        if element.is_inline_root {
-            let (_layout, pos) = self.node_position(node_id, location);
-            let text_layout = &element
-                .raw_dom_data
-                .downcast_element()
-                .unwrap()
-                .inline_layout
-                .as_ref()
-                .unwrap_or_else(|| {
-                    dbg!(&element);
-                    panic!("Tried to render node marked as inline root that does not have an inline layout");
-                });
+        if let Some(text_layout) = text_layout {
+            let (_layout, pos) = self.node_position(dom, node_id, location);
 
             // Apply padding/border offset to inline root
             let taffy::Layout {
@@ -669,14 +2099,14 @@ where
                 y: pos.y + scaled_pb.top,
             };
 
-            // Render text
-            cx.stroke_text(scene, text_layout, pos);
-
             // Render inline boxes
             for line in text_layout.layout.lines() {
                 for item in line.items() {
-                    if let LayoutItem2::InlineBox(ibox) = item {
-                        self.render_node(scene, ibox.id as usize, pos);
+                    match item {
+                        LayoutItem2::InlineBox(ibox) if ibox.id != TEXT_INDENT_BOX_ID => {
+                            self.render_node(scene, dom, ibox.id as usize, pos);
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -690,31 +2120,420 @@ where
                 .iter()
                 .copied()
             {
-                self.render_node(scene, child_id, cx.pos);
+                self.render_node(scene, dom, child_id, cx.pos);
+            }
+        }
+
+        if clip.is_some() {
+            scene.pop_layer();
+        }
+    }
+
+    /// A hash of everything that affects an element's own display items
+    fn display_items_key(&self, dom: &Document, cx: &ElementCx) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        dom.paint_signature(cx.element.id).hash(&mut hasher);
+
+        let layout = self.layout(dom, cx.element.id);
+        let edges = [layout.border, layout.padding];
+        for edge in edges {
+            [edge.left, edge.right, edge.top, edge.bottom]
+                .map(f32::to_bits)
+                .hash(&mut hasher);
+        }
+        [layout.size.width, layout.size.height]
+            .map(f32::to_bits)
+            .hash(&mut hasher);
+        // The snapped size depends on where the element lands relative to the device pixel grid
+        [cx.frame.outer_rect.width(), cx.frame.outer_rect.height()]
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+        cx.scale.to_bits().hash(&mut hasher);
+        cx.hinting.hash(&mut hasher);
+        cx.legend_notch
+            .map(|notch| [notch.border_top, notch.left, notch.right].map(f32::to_bits))
+            .hash(&mut hasher);
+        self.devtools.show_layout.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Whether an element (including its outline) intersects the region of the viewport being repainted
+    fn in_paint_region(&self, cx: &ElementCx) -> bool {
+        let Some(region) = self.paint_region.get() else {
+            return true;
+        };
+        let size = cx.element.final_layout.size;
+        let margin = cx.frame.outline_width + cx.scale;
+        let bounds = Rect::new(
+            cx.pos.x * cx.scale - margin,
+            cx.pos.y * cx.scale - margin,
+            (cx.pos.x + f64::from(size.width)) * cx.scale + margin,
+            (cx.pos.y + f64::from(size.height)) * cx.scale + margin,
+        );
+        bounds.intersect(region).area() > 0.0
+    }
+
+    /// Renders an iframe's nested document clipped to the iframe's content box
+    fn render_sub_document(&self, scene: &mut Scene, sub_document: &Document, cx: &ElementCx) {
+        let Some(root_element) = sub_document.try_root_element() else {
+            return;
+        };
+
+        let taffy::Layout {
+            size,
+            border,
+            padding,
+            ..
+        } = cx.element.final_layout;
+        let origin = Point {
+            x: cx.pos.x + f64::from(border.left + padding.left),
+            y: cx.pos.y + f64::from(border.top + padding.top),
+        };
+        let width =
+            f64::from(size.width - border.left - border.right - padding.left - padding.right);
+        let height =
+            f64::from(size.height - border.top - border.bottom - padding.top - padding.bottom);
+
+        let transform = Affine::translate((origin.x * cx.scale, origin.y * cx.scale));
+        let clip = Rect::new(0.0, 0.0, width * cx.scale, height * cx.scale);
+
+        scene.push_layer(Mix::Clip, 1.0, transform, &clip);
+        // Fixed position elements within the nested document are fixed to the iframe, so aren't given layers
+        let fixed_elements = self.fixed_elements.take();
+        self.render_element(scene, sub_document, root_element.id, origin);
+        *self.fixed_elements.borrow_mut() = fixed_elements;
+        scene.pop_layer();
+    }
+
+    /// Renders the elements in the top layer, from bottom-most to top-most, relative to the viewport.
+    /// Modal dialogs are drawn above a backdrop that covers the viewport.
+    fn render_top_layer(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let (width, height) = viewport.window_size;
+
+        for (node_id, kind) in dom.top_layer() {
+            if *kind == TopLayerKind::ModalDialog {
+                let backdrop = Rect::new(0.0, 0.0, f64::from(width), f64::from(height));
+                scene.fill(
+                    Fill::NonZero,
+                    Affine::IDENTITY,
+                    Color::rgba(0.0, 0.0, 0.0, 0.1),
+                    None,
+                    &backdrop,
+                );
+            }
+
+            self.render_element(scene, dom, *node_id, Point::ZERO);
+        }
+    }
+
+    /// Highlights the matches of a find-in-page search, with the active match in a different color
+    fn render_find_matches(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+        let scroll = dom.viewport_scroll();
+        let active = dom.active_find_match();
+
+        for (idx, find_match) in dom.find_matches().iter().enumerate() {
+            let color = if Some(idx) == active {
+                Color::rgba8(0xff, 0x96, 0x32, 0xb0)
+            } else {
+                Color::rgba8(0xff, 0xeb, 0x3b, 0x80)
+            };
+            for rect in dom.find_match_rects(find_match) {
+                let x = f64::from(rect.location.x) * scale;
+                let y = (f64::from(rect.location.y) - scroll) * scale;
+                let rect = Rect::new(
+                    x,
+                    y,
+                    x + f64::from(rect.size.width) * scale,
+                    y + f64::from(rect.size.height) * scale,
+                );
+                scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+            }
+        }
+    }
+
+    /// Highlights the selection within a `contenteditable` element. With a `::selection` style, the selected text is
+    /// drawn again over the highlight in the selection's color.
+    fn render_selection(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+        let scroll = dom.viewport_scroll();
+
+        for highlight in dom.selection_highlights() {
+            let mut area = BezPath::new();
+            for rect in &highlight.rects {
+                let x = f64::from(rect.location.x) * scale;
+                let y = (f64::from(rect.location.y) - scroll) * scale;
+                let rect = Rect::new(
+                    x,
+                    y,
+                    x + f64::from(rect.size.width) * scale,
+                    y + f64::from(rect.size.height) * scale,
+                );
+                area.extend(rect.path_elements(0.1));
+            }
+            scene.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                highlight.background,
+                None,
+                &area,
+            );
+
+            let Some(color) = highlight.color else {
+                continue;
+            };
+            let (root_id, origin) = highlight.inline_root;
+            let Some(text_layout) = dom
+                .get_node(root_id)
+                .and_then(|node| node.element_data())
+                .and_then(|element_data| element_data.inline_layout.as_ref())
+            else {
+                continue;
+            };
+            let transform = Affine::translate((
+                f64::from(origin.x) * scale,
+                (f64::from(origin.y) - scroll) * scale,
+            ));
+            scene.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &area);
+            draw_text_layout_in_color(
+                scene,
+                text_layout,
+                transform,
+                viewport.text_rendering.hinting,
+                Some(color),
+            );
+            scene.pop_layer();
+        }
+    }
+
+    /// Renders the caret of a `contenteditable` element as a thin vertical line
+    fn render_caret(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let Some((position, height)) = dom.caret_rect() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+
+        let x = f64::from(position.x) * scale;
+        let y = (f64::from(position.y) - dom.viewport_scroll()) * scale;
+        let rect = Rect::new(x, y, x + scale.max(1.0), y + f64::from(height) * scale);
+        let color = dom
+            .caret_color()
+            .unwrap_or_else(|| palette_color(dom.control_palette().text));
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+    }
+
+    /// Renders a date or color picker popup, relative to the viewport
+    fn render_picker(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let Some(picker) = dom.open_picker() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+
+        let transform = Affine::translate((
+            f64::from(picker.location.x) * scale,
+            f64::from(picker.location.y) * scale,
+        ));
+        let background = RoundedRect::new(
+            0.0,
+            0.0,
+            f64::from(picker.size.width) * scale,
+            f64::from(picker.size.height) * scale,
+            4.0 * scale,
+        );
+        let palette = dom.control_palette();
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            palette_color(palette.popup),
+            None,
+            &background,
+        );
+        scene.stroke(
+            &Stroke::new(scale),
+            transform,
+            palette_color(palette.border),
+            None,
+            &background,
+        );
+
+        for cell in &picker.cells {
+            let rect = Rect::new(
+                f64::from(cell.location.x) * scale,
+                f64::from(cell.location.y) * scale,
+                f64::from(cell.location.x + cell.size.width) * scale,
+                f64::from(cell.location.y + cell.size.height) * scale,
+            );
+
+            if let Some([r, g, b]) = cell.color {
+                scene.fill(Fill::NonZero, transform, Color::rgb8(r, g, b), None, &rect);
+                if cell.selected {
+                    scene.stroke(
+                        &Stroke::new(2.0 * scale),
+                        transform,
+                        palette_color(palette.text),
+                        None,
+                        &rect,
+                    );
+                }
+            } else if cell.selected {
+                let highlight = RoundedRect::from_rect(rect, 4.0 * scale);
+                scene.fill(
+                    Fill::NonZero,
+                    transform,
+                    Color::rgba8(0x00, 0x75, 0xff, 0x40),
+                    None,
+                    &highlight,
+                );
+            }
+
+            // Labels are centered within their cell, except for suggestions which are aligned to the start
+            if let Some(label) = &cell.label {
+                let x = match cell.action {
+                    PickerAction::SelectSuggestion(_) => {
+                        rect.x0 + f64::from(SUGGESTION_PADDING) * scale
+                    }
+                    _ => rect.x0 + (rect.width() - f64::from(label.layout.width())) / 2.0,
+                };
+                let y = rect.y0 + (rect.height() - f64::from(label.layout.height())) / 2.0;
+                draw_text_layout(
+                    scene,
+                    label,
+                    transform * Affine::translate((x, y)),
+                    self.text_rendering().hinting,
+                );
+            }
+        }
+    }
+
+    fn render_context_menu(&self, scene: &mut Scene, dom: &Document) {
+        let Some(viewport) = self.render_state.viewport() else {
+            return;
+        };
+        let Some(menu) = dom.open_context_menu() else {
+            return;
+        };
+        let scale = viewport.scale_f64();
+
+        let transform = Affine::translate((
+            f64::from(menu.location.x) * scale,
+            f64::from(menu.location.y) * scale,
+        ));
+        let background = RoundedRect::new(
+            0.0,
+            0.0,
+            f64::from(menu.size.width) * scale,
+            f64::from(menu.size.height) * scale,
+            4.0 * scale,
+        );
+        let palette = dom.control_palette();
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            palette_color(palette.popup),
+            None,
+            &background,
+        );
+        scene.stroke(
+            &Stroke::new(scale),
+            transform,
+            palette_color(palette.border),
+            None,
+            &background,
+        );
+
+        // Labels are vertically centered within their row, after its padding
+        for row in &menu.rows {
+            let rect = Rect::new(
+                f64::from(row.location.x) * scale,
+                f64::from(row.location.y) * scale,
+                f64::from(row.location.x + row.size.width) * scale,
+                f64::from(row.location.y + row.size.height) * scale,
+            );
+            let x = rect.x0 + f64::from(CONTEXT_MENU_ITEM_PADDING) * scale;
+            let y = rect.y0 + (rect.height() - f64::from(row.label.layout.height())) / 2.0;
+            draw_text_layout(
+                scene,
+                &row.label,
+                transform * Affine::translate((x, y)),
+                self.text_rendering().hinting,
+            );
+
+            // Disabled items are faded out
+            if !row.item.enabled {
+                let [r, g, b, _] = palette.popup;
+                scene.fill(
+                    Fill::NonZero,
+                    transform,
+                    Color::rgba8(r, g, b, 0x99),
+                    None,
+                    &rect,
+                );
             }
         }
     }
 
-    fn render_node(&self, scene: &mut Scene, node_id: usize, location: Point) {
-        let node = &self.dom.as_ref().tree()[node_id];
+    fn render_node(&self, scene: &mut Scene, dom: &Document, node_id: usize, location: Point) {
+        let node = &dom.tree()[node_id];
+
+        // Top layer elements are rendered in a separate pass
+        if node.in_top_layer {
+            return;
+        }
+
+        // Fixed position elements are rendered into layers of their own
+        if let Some(fixed_elements) = self.fixed_elements.borrow_mut().as_mut() {
+            if node.is_fixed_position() {
+                fixed_elements.push((node_id, location));
+                return;
+            }
+        }
+
+        // As are elements promoted by their `will-change` hint
+        if let Some(promoted_elements) = self.promoted_elements.borrow_mut().as_mut() {
+            if node.will_change_compositing() {
+                promoted_elements.push((node_id, location));
+                return;
+            }
+        }
 
         match &node.raw_dom_data {
             NodeData::Element(_) | NodeData::AnonymousBlock(_) => {
-                self.render_element(scene, node_id, location)
+                self.render_element(scene, dom, node_id, location)
             }
             NodeData::Text(TextNodeData { .. }) => {
                 // Text nodes should never be rendered directly
                 // (they should always be rendered as part of an inline layout)
                 unreachable!()
             }
-            NodeData::Document => {}
+            NodeData::Document | NodeData::DocumentFragment => {}
             // NodeData::Doctype => {}
             NodeData::Comment => {} // NodeData::ProcessingInstruction { .. } => {}
         }
     }
 
-    fn element_cx<'w>(&'w self, element: &'w Node, location: Point) -> ElementCx {
-        let RenderState::Active(state) = &self.render_state else {
+    fn element_cx<'w>(
+        &'w self,
+        dom: &'w Document,
+        element: &'w Node,
+        location: Point,
+    ) -> ElementCx {
+        let Some(viewport) = self.render_state.viewport() else {
             panic!("Renderer is not active");
         };
 
@@ -725,17 +2544,19 @@ where
             .map(|element_data| element_data.styles.primary().clone())
             .unwrap_or(ComputedValues::initial_values().to_arc());
 
-        let (layout, pos) = self.node_position(element.id, location);
-        let scale = state.viewport.scale_f64();
+        let (layout, pos) = self.node_position(dom, element.id, location);
+        let scale = viewport.scale_f64();
 
-        // the bezpaths for every element are (potentially) cached (not yet, tbd)
+        // Display items are encoded with `ElementCx::at_origin` and cached (see `display_items`)
         // By performing the transform, we prevent the cache from becoming invalid when the page shifts around
-        let transform = Affine::translate((pos.x * scale, pos.y * scale));
+        // The element is snapped to the device pixel grid so that its borders are painted crisply
+        let origin = Point::new(pos.x * scale, pos.y * scale);
+        let transform = Affine::translate(snap_point(origin).to_vec2());
 
         // todo: maybe cache this so we don't need to constantly be figuring it out
         // It is quite a bit of math to calculate during render/traverse
         // Also! we can cache the bezpaths themselves, saving us a bunch of work
-        let frame = ElementFrame::new(&style, &layout, scale);
+        let frame = ElementFrame::new(&style, &layout, origin, scale);
 
         ElementCx {
             frame,
@@ -745,19 +2566,22 @@ where
             element,
             transform,
             image: element.element_data().unwrap().image.clone(),
+            images: &self.images,
             devtools: &self.devtools,
+            hinting: viewport.text_rendering.hinting,
+            legend_notch: dom.legend_notch(element.id),
         }
     }
 
-    fn node_position(&self, node: usize, location: Point) -> (Layout, Point) {
-        let layout = self.layout(node);
+    fn node_position(&self, dom: &Document, node: usize, location: Point) -> (Layout, Point) {
+        let layout = self.layout(dom, node);
         let pos = location + Vec2::new(layout.location.x as f64, layout.location.y as f64);
         (layout, pos)
     }
 
-    fn layout(&self, child: usize) -> Layout {
-        self.dom.as_ref().tree()[child].unrounded_layout
-        // self.dom.tree()[child].final_layout
+    fn layout(&self, dom: &Document, child: usize) -> Layout {
+        dom.tree()[child].unrounded_layout
+        // dom.tree()[child].final_layout
     }
 }
 
@@ -770,123 +2594,273 @@ struct ElementCx<'a> {
     element: &'a Node,
     transform: Affine,
     image: Option<Arc<DynamicImage>>,
+    images: &'a RefCell<ImageCache>,
     devtools: &'a Devtools,
+    hinting: TextHinting,
+    /// Where a fieldset's top border goes through its legend
+    legend_notch: Option<LegendNotch>,
 }
 
-impl ElementCx<'_> {
+impl<'a> ElementCx<'a> {
+    /// The same context positioned at the origin, for encoding display items that are translated into place
+    fn at_origin(&self) -> ElementCx<'a> {
+        ElementCx {
+            frame: self.frame.clone(),
+            style: self.style.clone(),
+            pos: Point::ZERO,
+            scale: self.scale,
+            element: self.element,
+            transform: Affine::IDENTITY,
+            image: self.image.clone(),
+            images: self.images,
+            devtools: self.devtools,
+            hinting: self.hinting,
+            legend_notch: self.legend_notch,
+        }
+    }
+
     fn stroke_text(&self, scene: &mut Scene, text_layout: &TextLayout, pos: Point) {
         let transform = Affine::translate((pos.x * self.scale, pos.y * self.scale));
 
-        for line in text_layout.layout.lines() {
-            for item in line.items() {
-                if let LayoutItem2::GlyphRun(glyph_run) = item {
-                    let mut x = glyph_run.offset();
-                    let y = glyph_run.baseline();
-                    let run = glyph_run.run();
-                    let font = run.font();
-                    let font_size = run.font_size();
-                    let metrics = run.metrics();
-                    let style = glyph_run.style();
-                    let synthesis = run.synthesis();
-                    let glyph_xform = synthesis
-                        .skew()
-                        .map(|angle| Affine::skew(angle.to_radians().tan() as f64, 0.0));
-                    let coords = run
-                        .normalized_coords()
-                        .iter()
-                        .map(|coord| vello::skrifa::instance::NormalizedCoord::from_bits(*coord))
-                        .collect::<Vec<_>>();
-
-                    scene
-                        .draw_glyphs(font)
-                        .brush(style.brush.color)
-                        .transform(transform)
-                        .glyph_transform(glyph_xform)
-                        .font_size(font_size)
-                        .normalized_coords(&coords)
-                        .draw(
-                            Fill::NonZero,
-                            glyph_run.glyphs().map(|glyph| {
-                                let gx = x + glyph.x;
-                                let gy = y - glyph.y;
-                                x += glyph.advance;
-                                vello::glyph::Glyph {
-                                    id: glyph.id as _,
-                                    x: gx,
-                                    y: gy,
-                                }
-                            }),
-                        );
-
-                    let mut draw_decoration_line = |offset: f32, size: f32, brush: &TextBrush| {
-                        let x = glyph_run.offset() as f64;
-                        let w = glyph_run.advance() as f64;
-                        let y = (glyph_run.baseline() - offset + size / 2.0) as f64;
-                        let line = vello::kurbo::Line::new((x, y), (x + w, y));
-                        scene.stroke(
-                            &Stroke::new(size as f64),
-                            transform,
-                            brush.color,
-                            None,
-                            &line,
-                        )
-                    };
+        draw_text_layout(scene, text_layout, transform, self.hinting);
+    }
 
-                    if let Some(underline) = &style.underline {
-                        let offset = underline.offset.unwrap_or(metrics.underline_offset);
-                        let size = underline.size.unwrap_or(metrics.underline_size);
+    fn draw_image(&self, scene: &mut Scene) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
 
-                        // TODO: intercept line when crossing an descending character like "gqy"
-                        draw_decoration_line(offset, size, &underline.brush);
-                    }
-                    if let Some(strikethrough) = &style.strikethrough {
-                        let offset = strikethrough.offset.unwrap_or(metrics.strikethrough_offset);
-                        let size = strikethrough.size.unwrap_or(metrics.strikethrough_size);
+        let width = self.frame.inner_rect.width() as u32;
+        let height = self.frame.inner_rect.height() as u32;
 
-                        draw_decoration_line(offset, size, &strikethrough.brush);
-                    }
-                }
+        if let Some(image) = &self.image {
+            if width == 0 || height == 0 {
+                return;
             }
+            let resized_image = self.images.borrow_mut().get(image, width, height);
+            scene.draw_image(&resized_image, transform);
+        } else if self.element.element_data().unwrap().broken_image {
+            self.draw_broken_image(scene);
         }
     }
 
-    fn draw_image(&self, scene: &mut Scene) {
+    /// Draws the placeholder of an `<img>` whose image couldn't be loaded: an outlined box crossed out from corner
+    /// to corner. If the image has alt text, the box is drawn at the icon's size, followed by the text.
+    fn draw_broken_image(&self, scene: &mut Scene) {
         let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let Some(text_layout) = &self.element.element_data().unwrap().alt_text_layout else {
+            self.draw_broken_image_icon(scene, self.frame.inner_rect);
+            return;
+        };
 
-        let width = self.frame.inner_rect.width() as u32;
-        let height = self.frame.inner_rect.height() as u32;
+        // The icon and the text are clipped to the content box
+        let content_rect = self.frame.inner_rect;
+        if content_rect.width() <= 0.0 || content_rect.height() <= 0.0 {
+            return;
+        }
+        scene.push_layer(Mix::Clip, 1.0, transform, &content_rect);
+        let icon_size = Size::new(
+            f64::from(BROKEN_IMAGE_SIZE.width) * self.scale,
+            f64::from(BROKEN_IMAGE_SIZE.height) * self.scale,
+        );
+        self.draw_broken_image_icon(
+            scene,
+            Rect::from_origin_size(content_rect.origin(), icon_size),
+        );
+        let text_offset = f64::from(BROKEN_IMAGE_SIZE.width + ALT_TEXT_GAP) * self.scale;
+        let text_transform =
+            transform * Affine::translate((content_rect.x0 + text_offset, content_rect.y0));
+        draw_text_layout(scene, text_layout, text_transform, self.hinting);
+        scene.pop_layer();
+    }
 
-        if let Some(image) = &self.image {
-            let mut resized_image = self
-                .element
-                .element_data()
-                .unwrap()
-                .resized_image
-                .borrow_mut();
+    fn draw_broken_image_icon(&self, scene: &mut Scene, rect: Rect) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let rect = rect.inset(-0.5 * self.scale);
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
 
-            if resized_image.is_none()
-                || resized_image
-                    .as_ref()
-                    .is_some_and(|img| img.width != width || img.height != height)
-            {
-                let image_data = image
-                    .clone()
-                    .resize_to_fill(width, height, FilterType::Lanczos3)
-                    .into_rgba8()
-                    .into_raw();
-
-                let peniko_image = peniko::Image {
-                    data: peniko::Blob::new(Arc::new(image_data)),
-                    format: peniko::Format::Rgba8,
-                    width,
-                    height,
-                    extend: peniko::Extend::Pad,
+        let color = Color::rgb8(0xa0, 0xa0, 0xa0);
+        let stroke = Stroke::new(self.scale);
+        scene.stroke(&stroke, transform, color, None, &rect);
+        let mut cross = BezPath::new();
+        cross.move_to((rect.x0, rect.y0));
+        cross.line_to((rect.x1, rect.y1));
+        cross.move_to((rect.x1, rect.y0));
+        cross.line_to((rect.x0, rect.y1));
+        scene.stroke(&stroke, transform, color, None, &cross);
+    }
+
+    /// Draws the color of an `<input type=color>` within its padding box
+    fn draw_color_swatch(&self, scene: &mut Scene) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let [r, g, b] = self
+            .element
+            .attr(local_name!("value"))
+            .and_then(parse_simple_color)
+            .unwrap_or([0, 0, 0]);
+
+        let inset = 4.0 * self.scale;
+        let rect = self.frame.inner_rect.inset(-inset);
+        if rect.width() > 0.0 && rect.height() > 0.0 {
+            scene.fill(Fill::NonZero, transform, Color::rgb8(r, g, b), None, &rect);
+        }
+    }
+
+    /// Draws the up and down spin buttons of an `<input type=number>` at the right of its padding box
+    fn draw_spin_buttons(&self, scene: &mut Scene, dom: &Document) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let rect = self.frame.inner_rect;
+        let width = (f64::from(SPIN_BUTTON_WIDTH) * self.scale).min(rect.width());
+        if width <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+
+        let palette = dom.control_palette();
+        let buttons = Rect::new(rect.x1 - width, rect.y0, rect.x1, rect.y1);
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            palette_color(palette.track),
+            None,
+            &buttons,
+        );
+        let divider = Line::new(
+            (buttons.x0, buttons.center().y),
+            (buttons.x1, buttons.center().y),
+        );
+        scene.stroke(
+            &Stroke::new(self.scale),
+            transform,
+            palette_color(palette.border),
+            None,
+            &divider,
+        );
+
+        // An arrow pointing up in the top button and down in the bottom one
+        let half_width = (buttons.width() / 4.0).min(buttons.height() / 6.0);
+        let center_x = buttons.center().x;
+        for (center_y, direction) in [
+            (buttons.y0 + buttons.height() / 4.0, -1.0),
+            (buttons.y1 - buttons.height() / 4.0, 1.0),
+        ] {
+            let mut arrow = BezPath::new();
+            arrow.move_to((
+                center_x - half_width,
+                center_y - direction * half_width / 2.0,
+            ));
+            arrow.line_to((
+                center_x + half_width,
+                center_y - direction * half_width / 2.0,
+            ));
+            arrow.line_to((center_x, center_y + direction * half_width / 2.0));
+            arrow.close_path();
+            scene.fill(
+                Fill::NonZero,
+                transform,
+                palette_color(palette.text),
+                None,
+                &arrow,
+            );
+        }
+    }
+
+    /// Draws the radical sign of an `msqrt` or `mroot` in its left padding, meeting the overbar drawn by its top
+    /// border
+    fn draw_radical(&self, scene: &mut Scene) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let taffy::Layout {
+            border, padding, ..
+        } = self.element.final_layout;
+        let rect = self.frame.inner_rect;
+        let width = f64::from(padding.left) * self.scale;
+        if width <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+
+        let line_width = (f64::from(border.top) * self.scale).max(self.scale);
+        let top = rect.y0 - f64::from(border.top) * self.scale / 2.0;
+        let bottom = rect.y1;
+        let mut radical = BezPath::new();
+        radical.move_to((rect.x0 + width * 0.1, top + (bottom - top) * 0.6));
+        radical.line_to((rect.x0 + width * 0.3, top + (bottom - top) * 0.55));
+        radical.line_to((rect.x0 + width * 0.5, bottom));
+        radical.line_to((rect.x0 + width, top));
+
+        let color = self.style.get_inherited_text().color.as_vello();
+        scene.stroke(&Stroke::new(line_width), transform, color, None, &radical);
+    }
+
+    /// Draws the track and fill of a `<progress>` or `<meter>`, or the track, fill and thumb of an
+    /// `<input type=range>`, within the element's padding box
+    fn draw_control(&self, scene: &mut Scene, dom: &Document, kind: ControlKind) {
+        let transform = Affine::translate((self.pos.x * self.scale, self.pos.y * self.scale));
+        let rect = self.frame.inner_rect;
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+
+        let palette = dom.control_palette();
+        let track_color = palette_color(palette.track);
+        let accent_color = palette_color(palette.accent);
+
+        let (fraction, fill_color) = match kind {
+            ControlKind::Progress => (
+                dom.progress_position(self.element.id).unwrap_or(0.0),
+                accent_color,
+            ),
+            ControlKind::Meter => {
+                let (fraction, region) = dom.meter_state(self.element.id);
+                let color = match region {
+                    MeterRegion::Optimum => Color::rgb8(0x2e, 0xb8, 0x2e),
+                    MeterRegion::SubOptimum => Color::rgb8(0xf2, 0xc0, 0x1f),
+                    MeterRegion::EvenLessGood => Color::rgb8(0xdc, 0x35, 0x35),
                 };
+                (fraction, color)
+            }
+            ControlKind::Range => (dom.range_value(self.element.id).fraction(), accent_color),
+        };
 
-                *resized_image = Some(Arc::new(peniko_image));
+        // Range inputs have a thin track with a thumb, progress bars and meters fill their box
+        let (track, thumb_radius) = match kind {
+            ControlKind::Range => {
+                let thumb_radius =
+                    (rect.height() / 2.0).min(f64::from(RANGE_THUMB_RADIUS) * self.scale);
+                let track_height = (4.0 * self.scale).min(rect.height());
+                let track = Rect::new(
+                    rect.x0 + thumb_radius,
+                    rect.center().y - track_height / 2.0,
+                    rect.x1 - thumb_radius,
+                    rect.center().y + track_height / 2.0,
+                );
+                (track, Some(thumb_radius))
             }
+            ControlKind::Progress | ControlKind::Meter => (rect, None),
+        };
+
+        let radius = track.height() / 2.0;
+        scene.fill(
+            Fill::NonZero,
+            transform,
+            track_color,
+            None,
+            &RoundedRect::from_rect(track, radius),
+        );
 
-            scene.draw_image(resized_image.as_ref().unwrap(), transform);
+        let fill_x = track.x0 + track.width() * fraction.clamp(0.0, 1.0);
+        if fill_x > track.x0 {
+            let fill = Rect::new(track.x0, track.y0, fill_x, track.y1);
+            scene.fill(
+                Fill::NonZero,
+                transform,
+                fill_color,
+                None,
+                &RoundedRect::from_rect(fill, radius),
+            );
+        }
+
+        if let Some(thumb_radius) = thumb_radius {
+            let thumb = Circle::new((fill_x, track.center().y), thumb_radius);
+            scene.fill(Fill::NonZero, transform, accent_color, None, &thumb);
         }
     }
 
@@ -1185,9 +3159,45 @@ impl ElementCx<'_> {
     ///
     /// The border-style property can have from one to four values (for the top border, right border, bottom border, and the left border).
     fn stroke_border(&self, sb: &mut Scene) {
+        if let Some(notch) = self.legend_notch {
+            self.stroke_fieldset_border(sb, notch);
+            return;
+        }
+        for edge in [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left] {
+            self.stroke_border_edge(sb, &self.frame, self.transform, edge);
+        }
+    }
+
+    /// Stroke the border of a fieldset with a legend: the top border goes through the middle of the legend, leaving a
+    /// gap where the legend is
+    fn stroke_fieldset_border(&self, sb: &mut Scene, notch: LegendNotch) {
+        let offset = f64::from(notch.border_top) * self.scale;
+        let mut layout = self.element.unrounded_layout;
+        layout.size.height = (layout.size.height - notch.border_top).max(0.0);
+        let origin = Point::new(self.pos.x * self.scale, self.pos.y * self.scale + offset);
+        let frame = ElementFrame::new(&self.style, &layout, origin, self.scale);
+
+        // Everything but the gap, which is wound the other way so that it's cut out
+        let outer = self.frame.outer_rect;
+        let gap_bottom = offset + frame.inner_rect.y0;
+        let (left, right) = (
+            f64::from(notch.left) * self.scale,
+            f64::from(notch.right) * self.scale,
+        );
+        let mut clip = BezPath::new();
+        clip.extend(outer.path_elements(0.1));
+        clip.move_to((left, outer.y0));
+        clip.line_to((left, gap_bottom));
+        clip.line_to((right, gap_bottom));
+        clip.line_to((right, outer.y0));
+        clip.close_path();
+
+        sb.push_layer(Mix::Clip, 1.0, self.transform, &clip);
+        let transform = self.transform * Affine::translate((0.0, offset));
         for edge in [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left] {
-            self.stroke_border_edge(sb, edge);
+            self.stroke_border_edge(sb, &frame, transform, edge);
         }
+        sb.pop_layer();
     }
 
     /// The border-style property specifies what kind of border to display.
@@ -1207,9 +3217,15 @@ impl ElementCx<'_> {
     /// - ✅ hidden: Defines a hidden border
     ///
     /// [*] The effect depends on the border-color value
-    fn stroke_border_edge(&self, sb: &mut Scene, edge: Edge) {
+    fn stroke_border_edge(
+        &self,
+        sb: &mut Scene,
+        frame: &ElementFrame,
+        transform: Affine,
+        edge: Edge,
+    ) {
         let border = self.style.get_border();
-        let path = self.frame.border(edge);
+        let path = frame.border(edge);
 
         let color = match edge {
             Edge::Top => border.border_top_color.as_vello(),
@@ -1218,7 +3234,7 @@ impl ElementCx<'_> {
             Edge::Left => border.border_left_color.as_vello(),
         };
 
-        sb.fill(Fill::NonZero, self.transform, color, None, &path);
+        sb.fill(Fill::NonZero, transform, color, None, &path);
     }
 
     /// ❌ dotted - Defines a dotted border
@@ -1316,3 +3332,141 @@ impl ElementCx<'_> {
         unimplemented!()
     }
 }
+
+/// The path of a clip shape in device pixels, relative to the element's border box
+fn clip_shape_path(shape: &ClipShape, scale: f64) -> BezPath {
+    let scaled = |value: f32| f64::from(value) * scale;
+    match shape {
+        ClipShape::Inset {
+            x0,
+            y0,
+            x1,
+            y1,
+            radius,
+        } => RoundedRect::new(
+            scaled(*x0),
+            scaled(*y0),
+            scaled(*x1),
+            scaled(*y1),
+            scaled(*radius),
+        )
+        .to_path(0.1),
+        ClipShape::Circle { cx, cy, radius } => {
+            Circle::new((scaled(*cx), scaled(*cy)), scaled(*radius)).to_path(0.1)
+        }
+        ClipShape::Ellipse { cx, cy, rx, ry } => {
+            Ellipse::new((scaled(*cx), scaled(*cy)), (scaled(*rx), scaled(*ry)), 0.0).to_path(0.1)
+        }
+        ClipShape::Polygon { points, .. } => {
+            let mut path = BezPath::new();
+            for (index, &(x, y)) in points.iter().enumerate() {
+                match index {
+                    0 => path.move_to((scaled(x), scaled(y))),
+                    _ => path.line_to((scaled(x), scaled(y))),
+                }
+            }
+            path.close_path();
+            path
+        }
+    }
+}
+
+/// Draws the glyphs (and decorations) of a text layout
+fn draw_text_layout(
+    scene: &mut Scene,
+    text_layout: &TextLayout,
+    transform: Affine,
+    hinting: TextHinting,
+) {
+    draw_text_layout_in_color(scene, text_layout, transform, hinting, None);
+}
+
+/// Draws the glyphs (and decorations) of a text layout, all in one color if one is given rather than their own
+fn draw_text_layout_in_color(
+    scene: &mut Scene,
+    text_layout: &TextLayout,
+    transform: Affine,
+    hinting: TextHinting,
+    color: Option<Color>,
+) {
+    for line in text_layout.layout.lines() {
+        for item in line.items() {
+            if let LayoutItem2::GlyphRun(glyph_run) = item {
+                let mut x = glyph_run.offset();
+                // Baselines are snapped so that glyphs aren't blurred vertically at fractional scales
+                let y = match hinting {
+                    TextHinting::None => glyph_run.baseline(),
+                    TextHinting::Vertical | TextHinting::Full => {
+                        snap_baseline(transform, glyph_run.baseline())
+                    }
+                };
+                let run = glyph_run.run();
+                let font = run.font();
+                let font_size = run.font_size();
+                let metrics = run.metrics();
+                let style = glyph_run.style();
+                let synthesis = run.synthesis();
+                let glyph_xform = synthesis
+                    .skew()
+                    .map(|angle| Affine::skew(angle.to_radians().tan() as f64, 0.0));
+                let coords = run
+                    .normalized_coords()
+                    .iter()
+                    .map(|coord| vello::skrifa::instance::NormalizedCoord::from_bits(*coord))
+                    .collect::<Vec<_>>();
+
+                scene
+                    .draw_glyphs(font)
+                    .brush(color.unwrap_or(style.brush.color))
+                    .transform(transform)
+                    .glyph_transform(glyph_xform)
+                    .font_size(font_size)
+                    .normalized_coords(&coords)
+                    .draw(
+                        Fill::NonZero,
+                        glyph_run.glyphs().map(|glyph| {
+                            let gx = match hinting {
+                                TextHinting::Full => snap_glyph_x(transform, x + glyph.x),
+                                TextHinting::None | TextHinting::Vertical => x + glyph.x,
+                            };
+                            let gy = y - glyph.y;
+                            x += glyph.advance;
+                            vello::glyph::Glyph {
+                                id: glyph.id as _,
+                                x: gx,
+                                y: gy,
+                            }
+                        }),
+                    );
+
+                let mut draw_decoration_line = |offset: f32, size: f32, brush: &TextBrush| {
+                    let x = glyph_run.offset() as f64;
+                    let w = glyph_run.advance() as f64;
+                    let y = (y - offset + size / 2.0) as f64;
+                    let line = vello::kurbo::Line::new((x, y), (x + w, y));
+                    scene.stroke(
+                        &Stroke::new(size as f64),
+                        transform,
+                        color.unwrap_or(brush.color),
+                        None,
+                        &line,
+                    )
+                };
+
+                if let Some(underline) = &style.underline {
+                    let offset = underline.offset.unwrap_or(metrics.underline_offset);
+                    let size = underline.size.unwrap_or(metrics.underline_size);
+
+                    // TODO: intercept line when crossing an descending character like "gqy"
+                    draw_decoration_line(offset, size, &underline.brush);
+                }
+                if let Some(strikethrough) = &style.strikethrough {
+                    let offset = strikethrough.offset.unwrap_or(metrics.strikethrough_offset);
+                    let size = strikethrough.size.unwrap_or(metrics.strikethrough_size);
+
+                    draw_decoration_line(offset, size, &strikethrough.brush);
+                }
+            }
+        }
+    }
+}
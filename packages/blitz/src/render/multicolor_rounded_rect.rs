@@ -6,6 +6,7 @@
 //! Can I just say, this is a lot of work for a border
 //! HTML/css is annoyingly wild
 
+use crate::snap::{snap_border_width, snap_length};
 use std::{f64::consts::FRAC_PI_2, f64::consts::PI};
 use style::{properties::ComputedValues, values::computed::CSSPixelLength};
 use taffy::prelude::Layout;
@@ -41,23 +42,25 @@ pub struct ElementFrame {
 
 impl ElementFrame {
     #[rustfmt::skip]
-    pub fn new(style: &ComputedValues, layout: &Layout, scale: f64) -> Self {
+    pub fn new(style: &ComputedValues, layout: &Layout, origin: Point, scale: f64) -> Self {
         let (border, outline) = (style.get_border(), style.get_outline());
 
         // let scale = 1.0;
 
         // Resolve and rescale
         // We have to scale since document pixels are not same same as rendered pixels
-        let border_top_width = scale * border.border_top_width.to_f64_px();
-        let border_left_width = scale * border.border_left_width.to_f64_px();
-        let border_right_width = scale * border.border_right_width.to_f64_px();
-        let border_bottom_width = scale * border.border_bottom_width.to_f64_px();
-        let outline_width =  scale * outline.outline_width.to_f64_px();
-
+        // Borders and outlines are snapped to whole device pixels so that they stay crisp at fractional scales
+        let border_top_width = snap_border_width(scale * border.border_top_width.to_f64_px());
+        let border_left_width = snap_border_width(scale * border.border_left_width.to_f64_px());
+        let border_right_width = snap_border_width(scale * border.border_right_width.to_f64_px());
+        let border_bottom_width = snap_border_width(scale * border.border_bottom_width.to_f64_px());
+        let outline_width =  snap_border_width(scale * outline.outline_width.to_f64_px());
+
+        // The edges of the box are snapped relative to its (unsnapped) position in device pixels
         let width: f64 = layout.size.width.into();
         let height: f64 = layout.size.height.into();
-        let width =  scale * width;
-        let height = scale * height;
+        let width = snap_length(origin.x, scale * width);
+        let height = snap_length(origin.y, scale * height);
 
         let outer_rect = Rect::new(0.0, 0.0, width, height);
         let inner_rect = Rect::new(
@@ -0,0 +1,214 @@
+//! Partial repainting
+//!
+//! Rather than rendering straight to the window surface, the renderer keeps the last frame in a persistent texture.
+//! Each frame only the damaged region of the viewport (see [`blitz_dom::damage`]) is rendered, into a texture of its
+//! own, which is then copied into the frame texture. The frame texture is then drawn onto the surface with a simple
+//! blit pipeline. Frames without any damage are skipped entirely.
+
+/// Statistics about how much of the viewport has been repainted, for embedders to verify the effect of partial
+/// repainting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RepaintStats {
+    /// Frames in which the whole viewport was repainted
+    pub full_repaints: u64,
+    /// Frames in which only part of the viewport was repainted
+    pub partial_repaints: u64,
+    /// Frames that were skipped because nothing changed
+    pub skipped_frames: u64,
+    /// The area (in physical pixels) repainted in the last frame that wasn't skipped
+    pub last_repaint_area: u64,
+    /// The number of elements painted in the last frame that wasn't skipped
+    pub last_painted_elements: usize,
+    /// How many of those elements had their cached display items reused rather than being re-encoded
+    pub last_reused_elements: usize,
+}
+
+/// A region of the viewport in physical pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PixelRect {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl PixelRect {
+    pub(crate) fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+/// The texture that holds the last frame
+pub(crate) struct FrameTarget {
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl FrameTarget {
+    pub(crate) fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = create_render_target(
+            device,
+            "Frame texture",
+            width,
+            height,
+            wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            width,
+            height,
+        }
+    }
+}
+
+/// A texture that vello can render into
+pub(crate) fn create_render_target(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage,
+        view_formats: &[],
+    })
+}
+
+const BLIT_SHADER: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) ix: u32) -> @builtin(position) vec4<f32> {
+    // A triangle that covers the whole viewport
+    var vertex = vec2(-1.0, 1.0);
+    switch ix {
+        case 1u: {
+            vertex = vec2(-1.0, -3.0);
+        }
+        case 2u: {
+            vertex = vec2(3.0, 1.0);
+        }
+        default: {}
+    }
+    return vec4(vertex, 0.0, 1.0);
+}
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let rgba = textureLoad(frame, vec2<i32>(pos.xy), 0);
+    return vec4(rgba.rgb * rgba.a, rgba.a);
+}
+"#;
+
+/// Draws the frame texture onto the window surface
+pub(crate) struct BlitPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl BlitPipeline {
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit shader"),
+            source: wgpu::ShaderSource::Wgsl(BLIT_SHADER.into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+
+    pub(crate) fn blit(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            }],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Running a renderer inside any windowing system
+//!
+//! A [`Shell`] is the window that a [`Renderer`] draws into: it provides the surface (through its raw window and
+//! display handles), reports its size and scale, and takes the renderer's requests to redraw, change the cursor or
+//! change the title. The embedder owns the event loop, and forwards the shell's input to the renderer as
+//! [`ShellEvent`]s with [`Renderer::handle_shell_event`]. So blitz can run under tao, SDL, or a custom loop in the
+//! same way as under winit, whose `Window` is a shell when the `winit` feature is enabled (which it is by default).
+//!
+//! ```ignore
+//! renderer.resume_shell(|| Arc::new(MyWindow::new())).await;
+//! loop {
+//!     for event in my_event_loop.pump_events() {
+//!         renderer.handle_shell_event(event, &mut scene);
+//!     }
+//! }
+//! ```
+use std::sync::Arc;
+use std::time::Instant;
+
+use blitz_dom::color_scheme::ColorScheme;
+use blitz_dom::events::WheelDelta;
+use blitz_dom::visual_viewport::ViewportInsets;
+use blitz_dom::DocumentLike;
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use style::values::computed::ui::CursorKind;
+use vello::Scene;
+use wgpu::WasmNotSend;
+
+use crate::{RenderState, Renderer, TouchPhase, Viewport};
+
+/// A window that a [`Renderer`] can draw into
+pub trait Shell: HasWindowHandle + HasDisplayHandle + Sync + WasmNotSend + 'static {
+    /// The size of the window's surface, in physical pixels
+    fn surface_size(&self) -> (u32, u32);
+
+    /// The ratio of physical pixels to logical pixels
+    fn scale_factor(&self) -> f32;
+
+    /// Ask the event loop for a [`ShellEvent::RedrawRequested`]
+    fn request_redraw(&self);
+
+    /// Show the cursor for the hovered element
+    fn set_cursor(&self, cursor: CursorKind);
+
+    /// Show the document's title in the window's chrome
+    fn set_title(&self, title: &str);
+}
+
+/// A mouse button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl PointerButton {
+    /// The name the renderer knows the button by (as in [`Renderer::click`])
+    pub fn name(self) -> &'static str {
+        match self {
+            PointerButton::Left => "left",
+            PointerButton::Right => "right",
+            PointerButton::Middle => "middle",
+        }
+    }
+}
+
+/// Input from a [`Shell`], in a form that doesn't depend on the windowing system
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShellEvent {
+    /// The window's surface was resized (in physical pixels)
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    /// The ratio of physical pixels to logical pixels changed, e.g. when the window moved to another monitor
+    ScaleFactorChanged(f32),
+    /// The pointer moved to a position in the window, in logical pixels
+    PointerMoved {
+        x: f32,
+        y: f32,
+    },
+    PointerDown(PointerButton),
+    PointerUp(PointerButton),
+    Wheel(WheelDelta),
+    /// A finger touched, moved on or lifted from the window, at a position in logical pixels
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        x: f32,
+        y: f32,
+    },
+    /// The soft keyboard or system bars now cover the edges of the window by these amounts (in logical pixels)
+    InsetsChanged(ViewportInsets),
+    /// The OS switched between light and dark themes
+    ColorSchemeChanged(ColorScheme),
+    /// The window should be drawn
+    RedrawRequested,
+}
+
+impl<'a, S: Shell, Doc: DocumentLike> Renderer<'a, S, Doc> {
+    /// Create the surface of a shell and start rendering into it. The shell is only built if the renderer isn't
+    /// being resumed with the shell it was suspended with.
+    ///
+    /// Mobile platforms destroy the surface when the app is suspended, so the renderer should be suspended then and
+    /// resumed here. The shell may have been rotated or moved to another display while suspended, so its size and scale
+    /// are read again.
+    pub async fn resume_shell(&mut self, shell_builder: impl FnOnce() -> Arc<S>) {
+        if let RenderState::Suspended(Some((shell, viewport))) = &mut self.render_state {
+            viewport.window_size = shell.surface_size();
+            viewport.set_hidpi_scale(shell.scale_factor());
+        }
+        self.resume(|| {
+            let shell = shell_builder();
+            let mut viewport = Viewport::new(shell.surface_size());
+            viewport.set_hidpi_scale(shell.scale_factor());
+            (shell, viewport)
+        })
+        .await;
+    }
+
+    /// The shell being rendered into (if the renderer isn't suspended)
+    pub fn shell(&self) -> Option<&Arc<S>> {
+        match &self.render_state {
+            RenderState::Active(state) => Some(&state.window),
+            _ => None,
+        }
+    }
+
+    /// Ask the shell for a redraw
+    pub fn request_redraw(&self) {
+        if let Some(shell) = self.shell() {
+            shell.request_redraw();
+        }
+    }
+
+    /// Start a new frame, then resolve and render the document
+    pub fn redraw(&mut self, scene: &mut Scene) {
+        let dom = self.dom.as_mut();
+        dom.begin_frame(Instant::now());
+        dom.resolve();
+        self.render(scene);
+    }
+
+    /// Handle input from the shell, asking it for a redraw if the document changed
+    pub fn handle_shell_event(&mut self, event: ShellEvent, scene: &mut Scene) {
+        match event {
+            ShellEvent::Resized { width, height } => {
+                self.set_size((width, height));
+                self.request_redraw();
+            }
+            ShellEvent::ScaleFactorChanged(scale) => {
+                let Some(viewport) = self.render_state.viewport_mut() else {
+                    return;
+                };
+                viewport.set_hidpi_scale(scale);
+                self.kick_viewport();
+                self.request_redraw();
+            }
+            ShellEvent::PointerMoved { x, y } => {
+                if !self.mouse_move(x, y) {
+                    return;
+                }
+                if let (Some(shell), Some(cursor)) = (self.shell(), self.get_cursor()) {
+                    shell.set_cursor(cursor);
+                }
+                self.request_redraw();
+            }
+            ShellEvent::PointerDown(button) => {
+                self.click(button.name());
+                self.request_redraw();
+            }
+            ShellEvent::PointerUp(button) => {
+                self.mouse_up(button.name());
+                self.request_redraw();
+            }
+            ShellEvent::Wheel(delta) => {
+                self.wheel(delta);
+                self.request_redraw();
+            }
+            ShellEvent::Touch { id, phase, x, y } => {
+                self.touch(id, phase, x, y);
+                self.request_redraw();
+            }
+            ShellEvent::InsetsChanged(insets) => {
+                let old_insets = self.dom.as_ref().viewport_insets();
+                let scale = self
+                    .render_state
+                    .viewport()
+                    .map_or(1.0, Viewport::page_scale);
+                self.dom.as_mut().set_viewport_insets(ViewportInsets {
+                    top: insets.top / scale,
+                    right: insets.right / scale,
+                    bottom: insets.bottom / scale,
+                    left: insets.left / scale,
+                });
+                if self.dom.as_ref().viewport_insets() != old_insets {
+                    self.request_redraw();
+                }
+            }
+            ShellEvent::ColorSchemeChanged(scheme) => {
+                self.dom.as_mut().set_preferred_color_scheme(scheme);
+                self.request_redraw();
+            }
+            ShellEvent::RedrawRequested => self.redraw(scene),
+        }
+    }
+}
+
+#[cfg(feature = "winit")]
+impl Shell for winit::window::Window {
+    fn surface_size(&self) -> (u32, u32) {
+        // The inner size of iOS windows is their safe area, but the surface covers the whole screen
+        #[cfg(target_os = "ios")]
+        let size = self.outer_size();
+        #[cfg(not(target_os = "ios"))]
+        let size = self.inner_size();
+        (size.width, size.height)
+    }
+
+    fn scale_factor(&self) -> f32 {
+        winit::window::Window::scale_factor(self) as f32
+    }
+
+    fn request_redraw(&self) {
+        winit::window::Window::request_redraw(self);
+    }
+
+    fn set_cursor(&self, cursor: CursorKind) {
+        use winit::window::CursorIcon;
+        let icon = match cursor {
+            CursorKind::None => {
+                self.set_cursor_visible(false);
+                return;
+            }
+            CursorKind::Default => CursorIcon::Default,
+            CursorKind::Pointer => CursorIcon::Pointer,
+            CursorKind::ContextMenu => CursorIcon::ContextMenu,
+            CursorKind::Help => CursorIcon::Help,
+            CursorKind::Progress => CursorIcon::Progress,
+            CursorKind::Wait => CursorIcon::Wait,
+            CursorKind::Cell => CursorIcon::Cell,
+            CursorKind::Crosshair => CursorIcon::Crosshair,
+            CursorKind::Text => CursorIcon::Text,
+            CursorKind::VerticalText => CursorIcon::VerticalText,
+            CursorKind::Alias => CursorIcon::Alias,
+            CursorKind::Copy => CursorIcon::Copy,
+            CursorKind::Move => CursorIcon::Move,
+            CursorKind::NoDrop => CursorIcon::NoDrop,
+            CursorKind::NotAllowed => CursorIcon::NotAllowed,
+            CursorKind::Grab => CursorIcon::Grab,
+            CursorKind::Grabbing => CursorIcon::Grabbing,
+            CursorKind::EResize => CursorIcon::EResize,
+            CursorKind::NResize => CursorIcon::NResize,
+            CursorKind::NeResize => CursorIcon::NeResize,
+            CursorKind::NwResize => CursorIcon::NwResize,
+            CursorKind::SResize => CursorIcon::SResize,
+            CursorKind::SeResize => CursorIcon::SeResize,
+            CursorKind::SwResize => CursorIcon::SwResize,
+            CursorKind::WResize => CursorIcon::WResize,
+            CursorKind::EwResize => CursorIcon::EwResize,
+            CursorKind::NsResize => CursorIcon::NsResize,
+            CursorKind::NeswResize => CursorIcon::NeswResize,
+            CursorKind::NwseResize => CursorIcon::NwseResize,
+            CursorKind::ColResize => CursorIcon::ColResize,
+            CursorKind::RowResize => CursorIcon::RowResize,
+            CursorKind::AllScroll => CursorIcon::AllScroll,
+            CursorKind::ZoomIn => CursorIcon::ZoomIn,
+            CursorKind::ZoomOut => CursorIcon::ZoomOut,
+            // todo: we should be the ones determining this based on the UA?
+            // https://developer.mozilla.org/en-US/docs/Web/CSS/cursor
+            CursorKind::Auto => CursorIcon::Default,
+        };
+        self.set_cursor_visible(true);
+        winit::window::Window::set_cursor(self, icon);
+    }
+
+    fn set_title(&self, title: &str) {
+        winit::window::Window::set_title(self, title);
+    }
+}
@@ -0,0 +1,45 @@
+//! Pixel snapping
+//!
+//! Layout positions are fractional, so at fractional display scales a 1px border can straddle two device pixels and
+//! be antialiased into a blurry 2px line. Rather than rounding the layout itself, the geometry is aligned to whole
+//! device pixels as it's painted.
+
+use vello::kurbo::{Affine, Point};
+
+/// Rounds a position in device pixels to the nearest whole pixel
+pub(crate) fn snap(value: f64) -> f64 {
+    value.round()
+}
+
+/// Rounds a point in device pixels to the nearest whole pixel
+pub(crate) fn snap_point(point: Point) -> Point {
+    Point::new(snap(point.x), snap(point.y))
+}
+
+/// The length of a span in device pixels once both of its ends have been snapped
+pub(crate) fn snap_length(start: f64, length: f64) -> f64 {
+    snap(start + length) - snap(start)
+}
+
+/// Rounds a border width in device pixels to a whole number of pixels
+///
+/// Borders that are present are always at least one device pixel wide, so hairlines don't disappear.
+pub(crate) fn snap_border_width(width: f64) -> f64 {
+    if width <= 0.0 {
+        0.0
+    } else {
+        snap(width).max(1.0)
+    }
+}
+
+/// Offsets a baseline (relative to `transform`) so that it lands on a whole device pixel
+pub(crate) fn snap_baseline(transform: Affine, baseline: f32) -> f32 {
+    let ty = transform.as_coeffs()[5];
+    (snap(ty + f64::from(baseline)) - ty) as f32
+}
+
+/// Offsets the horizontal position of a glyph (relative to `transform`) so that it lands on a whole device pixel
+pub(crate) fn snap_glyph_x(transform: Affine, x: f32) -> f32 {
+    let tx = transform.as_coeffs()[4];
+    (snap(tx + f64::from(x)) - tx) as f32
+}
@@ -0,0 +1,48 @@
+//! Text rendering settings
+//!
+//! Each surface (a window, a headless renderer or an embedded texture) is rendered with its own [`TextRendering`],
+//! which is kept in its [`crate::Viewport`]. Small text is crisper with analytic antialiasing and full hinting, at
+//! the cost of glyph positions that are rounded to the device pixel grid.
+
+/// How the shapes of a surface (including glyphs) are antialiased
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Antialiasing {
+    /// Analytic coverage. The fastest method, and the sharpest for small text, but it can leave faint seams where
+    /// shapes meet.
+    Area,
+    /// 8x multisampling
+    Msaa8,
+    /// 16x multisampling
+    #[default]
+    Msaa16,
+}
+
+impl Antialiasing {
+    pub(crate) fn aa_config(self) -> vello::AaConfig {
+        match self {
+            Antialiasing::Area => vello::AaConfig::Area,
+            Antialiasing::Msaa8 => vello::AaConfig::Msaa8,
+            Antialiasing::Msaa16 => vello::AaConfig::Msaa16,
+        }
+    }
+}
+
+/// How glyphs are aligned to the device pixel grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextHinting {
+    /// Glyphs are drawn exactly where layout places them
+    None,
+    /// Baselines are snapped to whole device pixels, so that horizontal stems aren't blurred across two rows
+    #[default]
+    Vertical,
+    /// Each glyph is also snapped to a whole device pixel horizontally, which makes vertical stems crisper but
+    /// spacing slightly uneven
+    Full,
+}
+
+/// The settings that a surface's text is rendered with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextRendering {
+    pub antialiasing: Antialiasing,
+    pub hinting: TextHinting,
+}
@@ -0,0 +1,106 @@
+//! Scrolling and tapping with touches
+//!
+//! Phones have no mouse, so the shell forwards touches instead (see [`crate::ShellEvent::Touch`]). A touch that moves
+//! further than [`TOUCH_SLOP`] pans the viewport, following the finger, and flings it onwards with a smooth scroll if
+//! the finger was still moving when it lifted. A touch that doesn't move that far is a tap, which clicks where it
+//! landed. Only the first finger is followed: other fingers are ignored until it lifts.
+//!
+//! Note: there are no `touchstart`/`touchmove` events yet, so pages can't prevent panning, and pinch zooming isn't
+//! supported.
+use std::time::Instant;
+
+use blitz_dom::scrolling::ScrollBehavior;
+use blitz_dom::DocumentLike;
+use wgpu::WasmNotSend;
+
+use crate::{Renderer, Viewport};
+
+/// How far (in logical pixels) a touch has to move before it pans rather than taps
+pub const TOUCH_SLOP: f32 = 8.0;
+
+/// How far a fling continues, in seconds of the finger's speed when it lifted
+const FLING_TIME: f64 = 0.3;
+
+/// The phase of a touch, as reported by the shell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+/// The finger that is being followed
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TouchGesture {
+    id: u64,
+    start: (f32, f32),
+    last: (f32, f32),
+    last_time: Instant,
+    /// Whether the touch has moved far enough to pan
+    panning: bool,
+    /// How fast the finger is moving up the document, in CSS pixels per second
+    velocity: f64,
+}
+
+impl<'a, W, Doc: DocumentLike> Renderer<'a, W, Doc>
+where
+    W: raw_window_handle::HasWindowHandle
+        + raw_window_handle::HasDisplayHandle
+        + Sync
+        + WasmNotSend
+        + 'a,
+{
+    /// Handle a touch at a position in the window, in logical pixels
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        let now = Instant::now();
+        match (phase, self.touch) {
+            (TouchPhase::Started, None) => {
+                self.touch = Some(TouchGesture {
+                    id,
+                    start: (x, y),
+                    last: (x, y),
+                    last_time: now,
+                    panning: false,
+                    velocity: 0.0,
+                });
+            }
+            (TouchPhase::Moved, Some(mut gesture)) if gesture.id == id => {
+                let (dx, dy) = (x - gesture.start.0, y - gesture.start.1);
+                gesture.panning |= dx.hypot(dy) > TOUCH_SLOP;
+                if gesture.panning {
+                    let page_scale = self
+                        .render_state
+                        .viewport()
+                        .map_or(1.0, Viewport::page_scale);
+                    let delta = f64::from((gesture.last.1 - y) / page_scale);
+                    let elapsed = now.duration_since(gesture.last_time).as_secs_f64();
+                    if elapsed > 0.0 {
+                        // Smooth out the jitter of individual touch events
+                        gesture.velocity = gesture.velocity * 0.2 + delta / elapsed * 0.8;
+                    }
+                    self.scroll_by(delta, ScrollBehavior::Instant);
+                    gesture.last = (x, y);
+                    gesture.last_time = now;
+                }
+                self.touch = Some(gesture);
+            }
+            (TouchPhase::Ended, Some(gesture)) if gesture.id == id => {
+                self.touch = None;
+                if gesture.panning {
+                    // A finger that rested before lifting doesn't fling
+                    let resting = now.duration_since(gesture.last_time).as_secs_f64() > 0.1;
+                    if !resting && gesture.velocity != 0.0 {
+                        self.scroll_by(gesture.velocity * FLING_TIME, ScrollBehavior::Smooth);
+                    }
+                } else {
+                    self.mouse_move(x, y);
+                    self.click("left");
+                    self.mouse_up("left");
+                }
+            }
+            (TouchPhase::Cancelled, Some(gesture)) if gesture.id == id => self.touch = None,
+            _ => {}
+        }
+    }
+}
@@ -1,5 +1,12 @@
 pub use stylo_types::*;
 
+use vello::peniko::Color;
+
+/// Convert an RGBA color from the document's [`blitz_dom::color_scheme::ControlPalette`] to a vello color
+pub fn palette_color([r, g, b, a]: [u8; 4]) -> Color {
+    Color::rgba8(r, g, b, a)
+}
+
 mod stylo_types {
     use style::{
         color::AbsoluteColor,
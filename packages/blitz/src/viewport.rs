@@ -1,6 +1,9 @@
 use blitz_dom::document::DummyFontMetricsProvider;
+use blitz_dom::meta_viewport::ViewportMeta;
 use style::media_queries::{Device, MediaType};
 
+use crate::text_rendering::TextRendering;
+
 #[derive(Default, Debug)]
 pub struct Viewport {
     pub window_size: (u32, u32),
@@ -9,7 +12,13 @@ pub struct Viewport {
 
     zoom: f32,
 
+    /// The configuration requested by the document's `<meta name=viewport>` element
+    meta: Option<ViewportMeta>,
+
     pub font_size: f32,
+
+    /// The settings that text is rendered into the surface with
+    pub text_rendering: TextRendering,
 }
 
 impl Viewport {
@@ -18,13 +27,28 @@ impl Viewport {
             window_size,
             hidpi_scale: 1.0,
             zoom: 1.0,
+            meta: None,
             font_size: 16.0,
+            text_rendering: TextRendering::default(),
         }
     }
 
-    // Total scaling, the product of the zoom and hdpi scale
+    // Total scaling, the product of the page scale and hdpi scale
     pub fn scale(&self) -> f32 {
-        self.hidpi_scale * self.zoom
+        self.hidpi_scale * self.page_scale()
+    }
+
+    /// The scale the page is shown at: the zoom, adjusted by the document's `<meta name=viewport>` configuration
+    pub fn page_scale(&self) -> f32 {
+        match &self.meta {
+            Some(meta) => meta.scale(self.window_width(), self.zoom),
+            None => self.zoom,
+        }
+    }
+
+    /// The width of the window in CSS pixels, before the page is scaled
+    fn window_width(&self) -> f32 {
+        self.window_size.0 as f32 / self.hidpi_scale
     }
     // Total scaling, the product of the zoom and hdpi scale
     pub fn scale_f64(&self) -> f64 {
@@ -47,9 +71,24 @@ impl Viewport {
         &mut self.zoom
     }
 
+    pub fn meta(&self) -> Option<&ViewportMeta> {
+        self.meta.as_ref()
+    }
+
+    pub fn set_meta(&mut self, meta: Option<ViewportMeta>) {
+        self.meta = meta;
+    }
+
     pub fn make_device(&self) -> Device {
-        let width = self.window_size.0 as f32 / self.scale();
-        let height = self.window_size.1 as f32 / self.scale();
+        let visible_width = self.window_size.0 as f32 / self.scale();
+        let visible_height = self.window_size.1 as f32 / self.scale();
+
+        // Pages with a fixed width wider than the window are laid out at that width, and the height grows with it
+        let width = match &self.meta {
+            Some(meta) => meta.layout_width(self.window_width(), self.page_scale()),
+            None => visible_width,
+        };
+        let height = visible_height * width / visible_width;
         let viewport_size = euclid::Size2D::new(width, height);
         let device_pixel_ratio = euclid::Scale::new(self.scale());
 
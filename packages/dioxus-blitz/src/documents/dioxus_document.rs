@@ -4,8 +4,8 @@ use std::rc::Rc;
 
 use blitz::Viewport;
 use blitz_dom::{
-    namespace_url, node::Attribute, ns, Atom, Document, DocumentLike, ElementNodeData, NodeData,
-    QualName, TextNodeData,
+    events::EventData, namespace_url, node::Attribute, ns, profiling::FramePhase, Atom, Document,
+    DocumentLike, ElementNodeData, NodeData, QualName, TextNodeData,
 };
 
 use dioxus::{
@@ -18,7 +18,7 @@ use dioxus::{
 use futures_util::{pin_mut, FutureExt};
 use rustc_hash::FxHashMap;
 
-use super::event_handler::{NativeClickData, NativeConverter};
+use super::event_handler::{NativeClickData, NativeConverter, NativeFormData, NativeWheelData};
 
 type NodeId = usize;
 
@@ -65,9 +65,10 @@ impl DocumentLike for DioxusDocument {
             }
         }
 
-        self.vdom.render_immediate(&mut MutationWriter {
-            doc: &mut self.inner,
-            state: &mut self.vdom_state,
+        let vdom = &mut self.vdom;
+        let state = &mut self.vdom_state;
+        self.inner.time_phase(FramePhase::Parse, |doc| {
+            vdom.render_immediate(&mut MutationWriter { doc, state });
         });
 
         true
@@ -111,8 +112,31 @@ impl DocumentLike for DioxusDocument {
                         let id = ElementId(value);
                         // let data = dioxus::html::EventData::Mouse()
 
-                        let data = Rc::new(PlatformEventData::new(Box::new(NativeClickData {})));
-                        self.vdom.handle_event("click", data, id, true);
+                        let data = match &event.data {
+                            EventData::Input { value, .. } => {
+                                Rc::new(PlatformEventData::new(Box::new(NativeFormData {
+                                    value: value.clone(),
+                                })))
+                            }
+                            EventData::Click(pointer) => {
+                                Rc::new(PlatformEventData::new(Box::new(NativeClickData {
+                                    pointer: *pointer,
+                                })))
+                            }
+                            EventData::Wheel(wheel) => {
+                                Rc::new(PlatformEventData::new(Box::new(NativeWheelData {
+                                    click: NativeClickData {
+                                        pointer: wheel.pointer,
+                                    },
+                                    delta_x: wheel.delta_x,
+                                    delta_y: wheel.delta_y,
+                                })))
+                            }
+                            _ => Rc::new(PlatformEventData::new(Box::new(
+                                NativeClickData::default(),
+                            ))),
+                        };
+                        self.vdom.handle_event(&event.name, data, id, true);
                         return true;
                     }
                 }
@@ -130,8 +154,8 @@ impl DioxusDocument {
 
         // doc.add_element()
 
-        // Include default and user-specified stylesheets
-        doc.add_stylesheet(include_str!("./default.css"));
+        // Include the default stylesheet
+        doc.add_ua_stylesheet(include_str!("./default.css"));
 
         let state = DioxusState::create(&mut doc);
         let mut doc = Self {
@@ -148,11 +172,11 @@ impl DioxusDocument {
     }
 
     pub fn initial_build(&mut self) {
-        let mut writer = MutationWriter {
-            doc: &mut self.inner,
-            state: &mut self.vdom_state,
-        };
-        self.vdom.rebuild(&mut writer);
+        let vdom = &mut self.vdom;
+        let state = &mut self.vdom_state;
+        self.inner.time_phase(FramePhase::Parse, |doc| {
+            vdom.rebuild(&mut MutationWriter { doc, state });
+        });
         // dbg!(self.vdom.rebuild_to_vec());
         // std::process::exit(0);
         // dbg!(writer.state);
@@ -269,9 +293,7 @@ impl WriteMutations for MutationWriter<'_> {
         println!("append_children id:{} m:{}", id.0, m);
         let children = self.state.stack.split_off(self.state.stack.len() - m);
         let parent = self.state.element_to_node_id(id);
-        for child in children {
-            self.doc.get_node_mut(parent).unwrap().children.push(child);
-        }
+        self.doc.append_children(parent, &children);
     }
 
     fn assign_node_id(&mut self, path: &'static [u8], id: ElementId) {
@@ -384,67 +406,33 @@ impl WriteMutations for MutationWriter<'_> {
         id: ElementId,
     ) {
         let node_id = self.state.element_to_node_id(id);
-        let node = self.doc.get_node_mut(node_id).unwrap();
-        if let NodeData::Element(ref mut element) = node.raw_dom_data {
+        let node = self.doc.get_node(node_id).unwrap();
+        if let NodeData::Element(ref element) = node.raw_dom_data {
+            // FIXME check namespace
+            let exists = element.attr(Atom::from(name)).is_some();
+
             // FIXME: support non-text attributes
             if let AttributeValue::Text(val) = value {
-                // FIXME check namespace
-                let existing_attr = element
-                    .attrs
-                    .iter_mut()
-                    .find(|attr| attr.name.local == *name);
-
-                if let Some(existing_attr) = existing_attr {
-                    existing_attr.value = val.to_string();
+                // we have overloaded the style namespace to accumulate style attributes without a `style` block
+                if ns == Some("style") && !exists {
+                    // todo: need to accumulate style attributes into a single style
+                    //
+                    // element.
                 } else {
-                    // we have overloaded the style namespace to accumulate style attributes without a `style` block
-                    if ns == Some("style") {
-                        // todo: need to accumulate style attributes into a single style
-                        //
-                        // element.
-                    } else {
-                        element.attrs.push(Attribute {
-                            name: qual_name(name, ns),
-                            value: val.to_string(),
-                        });
-                    }
+                    self.doc.set_attribute(node_id, qual_name(name, ns), val);
                 }
             }
 
             if let AttributeValue::None = value {
                 // FIXME: check namespace
-                element.attrs.retain(|attr| attr.name.local != *name);
+                self.doc.remove_attribute(node_id, &Atom::from(name));
             }
         }
     }
 
     fn set_node_text(&mut self, value: &str, id: ElementId) {
         let node_id = self.state.element_to_node_id(id);
-        let node = self.doc.get_node_mut(node_id).unwrap();
-
-        let text = match node.raw_dom_data {
-            NodeData::Text(ref mut text) => text,
-
-            // todo: otherwise this is basically element.textContent which is a bit different - need to parse as html
-            _ => return,
-        };
-
-        // todo: this is very inefficient for inline styles - lots of string cloning going on
-        let changed = text.content != value;
-        text.content = value.to_string();
-        let contents = text.content.clone();
-
-        if let Some(parent) = node.parent {
-            // if the text is the child of a style element, we want to put the style into the stylesheet cache
-            let parent = self.doc.get_node(parent).unwrap();
-            if let NodeData::Element(ref element) = parent.raw_dom_data {
-                // Only set stylsheets if the text content has *changed* - we need to unload
-                if changed && element.name.local.as_ref() == "style" {
-                    self.doc.add_stylesheet(value);
-                    self.doc.remove_stylehsheet(&contents);
-                }
-            }
-        }
+        self.doc.set_text(node_id, value.to_string());
     }
 
     fn create_event_listener(&mut self, _name: &'static str, _id: ElementId) {
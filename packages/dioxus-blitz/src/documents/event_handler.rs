@@ -1,24 +1,30 @@
+use blitz_dom::events::PointerEventData;
+use dioxus::prelude::dioxus_elements::geometry::{
+    ClientPoint, ElementPoint, PagePoint, WheelDelta,
+};
 use dioxus::prelude::{HtmlEventConverter, PlatformEventData};
 
-#[derive(Clone)]
-pub struct NativeClickData {}
+#[derive(Clone, Default)]
+pub struct NativeClickData {
+    pub pointer: PointerEventData,
+}
 
 impl dioxus::html::point_interaction::InteractionLocation for NativeClickData {
-    fn client_coordinates(&self) -> dioxus::prelude::dioxus_elements::geometry::ClientPoint {
-        todo!()
+    fn client_coordinates(&self) -> ClientPoint {
+        ClientPoint::new(self.pointer.client_x, self.pointer.client_y)
     }
 
     fn screen_coordinates(&self) -> dioxus::prelude::dioxus_elements::geometry::ScreenPoint {
         todo!()
     }
 
-    fn page_coordinates(&self) -> dioxus::prelude::dioxus_elements::geometry::PagePoint {
-        todo!()
+    fn page_coordinates(&self) -> PagePoint {
+        PagePoint::new(self.pointer.page_x, self.pointer.page_y)
     }
 }
 impl dioxus::html::point_interaction::InteractionElementOffset for NativeClickData {
-    fn element_coordinates(&self) -> dioxus::prelude::dioxus_elements::geometry::ElementPoint {
-        todo!()
+    fn element_coordinates(&self) -> ElementPoint {
+        ElementPoint::new(self.pointer.offset_x, self.pointer.offset_y)
     }
 }
 impl dioxus::html::point_interaction::ModifiersInteraction for NativeClickData {
@@ -42,6 +48,78 @@ impl dioxus::html::HasMouseData for NativeClickData {
     }
 }
 
+/// A wheel event, which carries the pointer's position like other mouse events
+#[derive(Clone)]
+pub struct NativeWheelData {
+    pub click: NativeClickData,
+    /// The delta in CSS pixels, positive to scroll right and down
+    pub delta_x: f64,
+    pub delta_y: f64,
+}
+
+impl dioxus::html::point_interaction::InteractionLocation for NativeWheelData {
+    fn client_coordinates(&self) -> ClientPoint {
+        self.click.client_coordinates()
+    }
+
+    fn screen_coordinates(&self) -> dioxus::prelude::dioxus_elements::geometry::ScreenPoint {
+        self.click.screen_coordinates()
+    }
+
+    fn page_coordinates(&self) -> PagePoint {
+        self.click.page_coordinates()
+    }
+}
+impl dioxus::html::point_interaction::InteractionElementOffset for NativeWheelData {
+    fn element_coordinates(&self) -> ElementPoint {
+        self.click.element_coordinates()
+    }
+}
+impl dioxus::html::point_interaction::ModifiersInteraction for NativeWheelData {
+    fn modifiers(&self) -> dioxus::prelude::Modifiers {
+        self.click.modifiers()
+    }
+}
+
+impl dioxus::html::point_interaction::PointerInteraction for NativeWheelData {
+    fn trigger_button(&self) -> Option<dioxus::prelude::dioxus_elements::input_data::MouseButton> {
+        self.click.trigger_button()
+    }
+
+    fn held_buttons(&self) -> dioxus::prelude::dioxus_elements::input_data::MouseButtonSet {
+        self.click.held_buttons()
+    }
+}
+impl dioxus::html::HasMouseData for NativeWheelData {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+impl dioxus::html::HasWheelData for NativeWheelData {
+    fn delta(&self) -> WheelDelta {
+        WheelDelta::pixels(self.delta_x, self.delta_y, 0.0)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+#[derive(Clone)]
+pub struct NativeFormData {
+    pub value: String,
+}
+
+impl dioxus::html::HasFormData for NativeFormData {
+    fn value(&self) -> String {
+        self.value.clone()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
 pub struct NativeConverter {}
 
 impl HtmlEventConverter for NativeConverter {
@@ -68,8 +146,9 @@ impl HtmlEventConverter for NativeConverter {
         todo!()
     }
 
-    fn convert_form_data(&self, _event: &PlatformEventData) -> dioxus::prelude::FormData {
-        todo!()
+    fn convert_form_data(&self, event: &PlatformEventData) -> dioxus::prelude::FormData {
+        let o = event.downcast::<NativeFormData>().unwrap().clone();
+        dioxus::prelude::FormData::from(o)
     }
 
     fn convert_image_data(&self, _event: &PlatformEventData) -> dioxus::prelude::ImageData {
@@ -120,7 +199,8 @@ impl HtmlEventConverter for NativeConverter {
         todo!()
     }
 
-    fn convert_wheel_data(&self, _event: &PlatformEventData) -> dioxus::prelude::WheelData {
-        todo!()
+    fn convert_wheel_data(&self, event: &PlatformEventData) -> dioxus::prelude::WheelData {
+        let o = event.downcast::<NativeWheelData>().unwrap().clone();
+        dioxus::prelude::WheelData::new(o)
     }
 }
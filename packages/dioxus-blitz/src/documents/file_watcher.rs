@@ -0,0 +1,76 @@
+//! Watching the files that a document's stylesheets and images were loaded from, so that they're reloaded when they
+//! change (with the `hot-reload` feature)
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+pub(crate) struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Arc<Mutex<Changes>>,
+}
+
+/// The watched files that have changed, and the waker of the document that is waiting for them
+#[derive(Default)]
+struct Changes {
+    paths: HashSet<PathBuf>,
+    waker: Option<Waker>,
+}
+
+impl FileWatcher {
+    /// Watch a set of files. Returns `None` if there are none, or they can't be watched.
+    pub(crate) fn new(paths: Vec<PathBuf>) -> Option<Self> {
+        if paths.is_empty() {
+            return None;
+        }
+
+        let changes = Arc::new(Mutex::new(Changes::default()));
+        let watched: HashSet<PathBuf> = paths.iter().cloned().collect();
+        let handler_changes = changes.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if !event.kind.is_modify() && !event.kind.is_create() {
+                    return;
+                }
+                let mut changes = handler_changes.lock().unwrap();
+                changes.paths.extend(
+                    event
+                        .paths
+                        .into_iter()
+                        .filter(|path| watched.contains(path)),
+                );
+                if !changes.paths.is_empty() {
+                    if let Some(waker) = changes.waker.take() {
+                        waker.wake();
+                    }
+                }
+            })
+            .map_err(|err| eprintln!("Error watching files: {}", err))
+            .ok()?;
+
+        // Editors often save by replacing files, so their directories are watched rather than the files themselves
+        let dirs: HashSet<&Path> = paths.iter().filter_map(|path| path.parent()).collect();
+        for dir in dirs {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Error watching {}: {}", dir.display(), err);
+            }
+        }
+
+        Some(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Take the files that have changed since this was last called. The waker is woken when more files change.
+    pub(crate) fn take_changes(&self, waker: &Waker) -> Vec<PathBuf> {
+        let mut changes = self.changes.lock().unwrap();
+        changes.waker = Some(waker.clone());
+        changes.paths.drain().collect()
+    }
+}
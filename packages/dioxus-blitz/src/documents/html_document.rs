@@ -1,10 +1,33 @@
+use std::sync::{Arc, Mutex};
+
 use blitz::Viewport;
-use blitz_dom::{Document, DocumentHtmlParser, DocumentLike};
+use blitz_dom::{
+    charset, iframe::IframeSource, navigation::Navigator, network::NetworkProvider, util::FetchErr,
+    Document, DocumentHtmlParser, DocumentLike,
+};
+use url::Url;
 
-use crate::Config;
+use crate::{Config, Shortcuts};
 
 pub struct HtmlDocument {
     inner: Document,
+    /// The user-specified stylesheets, which are also added to documents that are navigated to
+    stylesheets: Vec<String>,
+    user_stylesheets: Vec<String>,
+    navigation: PendingNavigation,
+    /// Watches the files that stylesheets and images were loaded from
+    #[cfg(feature = "hot-reload")]
+    file_watcher: Option<super::file_watcher::FileWatcher>,
+}
+
+/// The URL the document has navigated to, which is loaded the next time the document is polled
+#[derive(Clone, Default)]
+struct PendingNavigation(Arc<Mutex<Option<Url>>>);
+
+impl Navigator for PendingNavigation {
+    fn navigate(&mut self, url: Url) {
+        *self.0.lock().unwrap() = Some(url);
+    }
 }
 
 // Implement DocumentLike and required traits for HtmlDocument
@@ -24,12 +47,99 @@ impl From<HtmlDocument> for Document {
         doc.inner
     }
 }
-impl DocumentLike for HtmlDocument {}
+impl DocumentLike for HtmlDocument {
+    fn poll(&mut self, _cx: std::task::Context) -> bool {
+        // Reload the stylesheets and images whose files have changed
+        #[cfg(feature = "hot-reload")]
+        let reloaded = match &self.file_watcher {
+            Some(watcher) => watcher
+                .take_changes(_cx.waker())
+                .iter()
+                .fold(false, |reloaded, path| {
+                    self.inner.reload_local_resource(path) || reloaded
+                }),
+            None => false,
+        };
+        #[cfg(not(feature = "hot-reload"))]
+        let reloaded = false;
+
+        let Some(url) = self.navigation.0.lock().unwrap().take() else {
+            return reloaded;
+        };
+        let network = self.inner.network().clone();
+        let html = match fetch_html(&network, url.as_str(), self.inner.base_url()) {
+            Ok(html) => html,
+            Err(err) => {
+                eprintln!("Error fetching {}: {}", url, err);
+                return false;
+            }
+        };
+
+        // Reloads keep the scroll position, form values and caret of the document
+        let session = (self.inner.base_url() == Some(&url)).then(|| self.inner.session_state());
+
+        // Replace the document, laying the new one out for the same viewport
+        let cfg = Config {
+            stylesheets: self.stylesheets.clone(),
+            user_stylesheets: self.user_stylesheets.clone(),
+            base_url: Some(url.to_string()),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
+        };
+        let mut document = Self::from_html_with_network(&html, &cfg, network);
+        let device = self.inner.clone_stylist_device();
+        document.inner.set_scale(device.device_pixel_ratio().get());
+        document.inner.set_stylist_device(device);
+        *self = document;
+        if let Some(session) = session {
+            self.inner.restore_session_state(&session);
+        }
+        true
+    }
+}
+
+/// Limit on how deeply iframes may be nested, to guard against pages that embed themselves
+const MAX_IFRAME_DEPTH: usize = 4;
 
 impl HtmlDocument {
     pub(crate) fn from_html(html: &str, cfg: &Config) -> Self {
+        Self::from_html_with_network(html, cfg, Arc::new(NetworkProvider::default()))
+    }
+
+    /// Load a document whose resources (and those of its iframes) are fetched with `network`
+    pub(crate) fn from_html_with_network(
+        html: &str,
+        cfg: &Config,
+        network: Arc<NetworkProvider>,
+    ) -> Self {
+        let mut document = Self::from_html_nested(html, cfg, network, 0);
+
+        // Navigations (e.g. by `<meta http-equiv=refresh>`) replace the document when it's next polled
+        document
+            .inner
+            .set_navigator(Box::new(document.navigation.clone()));
+        document.inner.schedule_meta_refresh();
+
+        #[cfg(feature = "hot-reload")]
+        {
+            document.file_watcher =
+                super::file_watcher::FileWatcher::new(document.inner.local_resources());
+        }
+
+        document
+    }
+
+    fn from_html_nested(
+        html: &str,
+        cfg: &Config,
+        network: Arc<NetworkProvider>,
+        depth: usize,
+    ) -> Self {
         // Spin up the virtualdom and include the default stylesheet
         let mut dom = Document::new(Viewport::new((0, 0)).make_device());
+        dom.set_network(network.clone());
 
         // Set base url if configured
         if let Some(url) = &cfg.base_url {
@@ -37,7 +147,10 @@ impl HtmlDocument {
         }
 
         // Include default and user-specified stylesheets
-        dom.add_stylesheet(include_str!("./default.css"));
+        dom.add_ua_stylesheet(include_str!("./default.css"));
+        for ss in &cfg.user_stylesheets {
+            dom.add_user_stylesheet(ss);
+        }
         for ss in &cfg.stylesheets {
             dom.add_stylesheet(ss);
         }
@@ -45,6 +158,51 @@ impl HtmlDocument {
         // Parse HTML string into document
         DocumentHtmlParser::parse_into_doc(&mut dom, html);
 
-        HtmlDocument { inner: dom }
+        // Load the nested documents of iframes
+        if depth < MAX_IFRAME_DEPTH {
+            for (node_id, source) in dom.iframe_sources() {
+                let (html, base_url) = match source {
+                    IframeSource::SrcDoc(html) => (html, cfg.base_url.clone()),
+                    IframeSource::Url(url) => match fetch_html(&network, url.as_str(), None) {
+                        Ok(html) => (html, Some(url.to_string())),
+                        Err(err) => {
+                            eprintln!("Error fetching iframe {}: {}", url, err);
+                            continue;
+                        }
+                    },
+                };
+                let cfg = Config {
+                    stylesheets: Vec::new(),
+                    user_stylesheets: cfg.user_stylesheets.clone(),
+                    base_url,
+                    shortcuts: Shortcuts::default(),
+                    wheel_line_height: None,
+                    spatial_navigation: false,
+                    text_rendering: Default::default(),
+                };
+                let sub_document = Self::from_html_nested(&html, &cfg, network.clone(), depth + 1);
+                dom.set_sub_document(node_id, sub_document.inner);
+            }
+        }
+
+        HtmlDocument {
+            inner: dom,
+            stylesheets: cfg.stylesheets.clone(),
+            user_stylesheets: cfg.user_stylesheets.clone(),
+            navigation: PendingNavigation::default(),
+            #[cfg(feature = "hot-reload")]
+            file_watcher: None,
+        }
     }
 }
+
+/// Fetch an HTML document navigated to from the document at `from` (or by the embedder, if it's `None`), decoding it
+/// from the encoding declared by its headers or content
+pub(crate) fn fetch_html(
+    network: &NetworkProvider,
+    url: &str,
+    from: Option<&Url>,
+) -> Result<String, FetchErr> {
+    let (bytes, content_type) = network.fetch_navigation(url, from)?;
+    Ok(charset::decode_html(&bytes, content_type.as_deref()))
+}
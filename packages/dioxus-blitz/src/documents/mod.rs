@@ -1,6 +1,8 @@
 mod dioxus_document;
 mod event_handler;
+#[cfg(feature = "hot-reload")]
+mod file_watcher;
 mod html_document;
 
 pub(crate) use dioxus_document::DioxusDocument;
-pub(crate) use html_document::HtmlDocument;
+pub(crate) use html_document::{fetch_html, HtmlDocument};
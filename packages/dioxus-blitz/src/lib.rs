@@ -1,17 +1,24 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod documents;
+mod shortcuts;
 mod waker;
 mod window;
 
+pub use shortcuts::{Shortcut, ShortcutHandler, Shortcuts};
+
 use crate::waker::{EventData, UserWindowEvent};
-use crate::{documents::HtmlDocument, window::View};
+use crate::{
+    documents::{fetch_html, HtmlDocument},
+    window::View,
+};
 
-use blitz::RenderState;
-use blitz_dom::DocumentLike;
+use blitz::{HeadlessRenderer, RenderState, TextRendering};
+use blitz_dom::{network::NetworkProvider, DocumentLike};
 use dioxus::prelude::*;
 use documents::DioxusDocument;
 use std::collections::HashMap;
+use std::time::Instant;
 use url::Url;
 use winit::event_loop::EventLoop;
 use winit::window::WindowId;
@@ -23,7 +30,18 @@ use winit::{
 #[derive(Default)]
 pub struct Config {
     pub stylesheets: Vec<String>,
+    /// Stylesheets applied at the user origin, which take precedence over the default styles but not the page's own
+    pub user_stylesheets: Vec<String>,
     pub base_url: Option<String>,
+    /// App-level keyboard shortcuts, which are handled before the document sees the key press
+    pub shortcuts: Shortcuts,
+    /// The number of CSS pixels that each line of a mouse wheel's delta scrolls (20 by default)
+    pub wheel_line_height: Option<f64>,
+    /// Move focus between elements with the arrow keys, and activate the focused element with enter or space. For
+    /// apps that are used without a pointer, such as on TVs and kiosks.
+    pub spatial_navigation: bool,
+    /// How the window's text is antialiased and hinted
+    pub text_rendering: TextRendering,
 }
 
 /// Launch an interactive HTML/CSS renderer driven by the Dioxus virtualdom
@@ -39,37 +57,40 @@ pub fn launch_cfg(root: fn() -> Element, cfg: Config) {
 pub fn launch_cfg_with_props<P: Clone + 'static, M: 'static>(
     root: impl ComponentFunction<P, M>,
     props: P,
-    _cfg: Config,
+    cfg: Config,
 ) {
     // Spin up the virtualdom
     // We're going to need to hit it with a special waker
     let vdom = VirtualDom::new_with_props(root, props);
     let document = DioxusDocument::new(vdom);
-    let window = View::new(document);
+    let window = View::new(document)
+        .with_shortcuts(cfg.shortcuts)
+        .with_wheel_line_height(cfg.wheel_line_height)
+        .with_spatial_navigation(cfg.spatial_navigation)
+        .with_text_rendering(cfg.text_rendering);
 
     launch_with_window(window)
 }
 
 pub fn launch_url(url: &str) {
-    const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:60.0) Gecko/20100101 Firefox/81.0";
     println!("{}", url);
 
     // Assert that url is valid
     let url = url.to_owned();
     Url::parse(&url).expect("Invalid url");
 
-    let html = ureq::get(&url)
-        .set("User-Agent", USER_AGENT)
-        .call()
-        .unwrap()
-        .into_string()
-        .unwrap();
+    let html = fetch_html(&NetworkProvider::default(), &url, None).expect("Error fetching url");
 
     launch_static_html_cfg(
         &html,
         Config {
             stylesheets: Vec::new(),
+            user_stylesheets: Vec::new(),
             base_url: Some(url),
+            shortcuts: Shortcuts::default(),
+            wheel_line_height: None,
+            spatial_navigation: false,
+            text_rendering: Default::default(),
         },
     )
 }
@@ -80,10 +101,33 @@ pub fn launch_static_html(html: &str) {
 
 pub fn launch_static_html_cfg(html: &str, cfg: Config) {
     let document = HtmlDocument::from_html(html, &cfg);
-    let window = View::new(document);
+    let window = View::new(document)
+        .with_shortcuts(cfg.shortcuts)
+        .with_wheel_line_height(cfg.wheel_line_height)
+        .with_spatial_navigation(cfg.spatial_navigation)
+        .with_text_rendering(cfg.text_rendering);
     launch_with_window(window)
 }
 
+/// Render static HTML into an image without opening a window. The document is laid out for a viewport of the given
+/// size (in pixels).
+pub fn render_static_html_to_image(
+    html: &str,
+    cfg: Config,
+    width: u32,
+    height: u32,
+) -> blitz::RgbaImage {
+    let document = HtmlDocument::from_html(html, &cfg);
+    let mut renderer = HeadlessRenderer::new_headless(document, width, height, 1.0);
+    renderer.set_text_rendering(cfg.text_rendering);
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    rt.block_on(renderer.render_to_image(width, height))
+}
+
 fn launch_with_window<Doc: DocumentLike + 'static>(window: View<'static, Doc>) {
     // Turn on the runtime and enter it
     let rt = tokio::runtime::Builder::new_multi_thread()
@@ -160,15 +204,15 @@ fn launch_with_window<Doc: DocumentLike + 'static>(window: View<'static, Doc>) {
                     event: winit::event::WindowEvent::RedrawRequested,
                 } => {
                     if let Some(window) = windows.get_mut(&window_id) {
-                        window.renderer.dom.as_mut().resolve();
-                        window.renderer.render(&mut window.scene);
+                        window.redraw();
                     };
                 }
 
                 Event::UserEvent(UserWindowEvent(EventData::Poll, id)) => {
                     if let Some(view) = windows.get_mut(&id) {
+                        // Changes are coalesced into the next scheduled frame
                         if view.poll() {
-                            view.request_redraw();
+                            view.renderer.dom.as_mut().invalidate();
                         }
                     };
                 }
@@ -183,6 +227,26 @@ fn launch_with_window<Doc: DocumentLike + 'static>(window: View<'static, Doc>) {
                     }
                 }
 
+                // Schedule the next frame of windows that have been invalidated or have animation frame callbacks
+                // waiting to run
+                Event::AboutToWait => {
+                    let now = Instant::now();
+                    let mut next_frame: Option<Instant> = None;
+                    for view in windows.values() {
+                        match view.renderer.dom.as_ref().next_frame_deadline() {
+                            Some(deadline) if deadline <= now => view.request_redraw(),
+                            Some(deadline) => {
+                                next_frame =
+                                    Some(next_frame.map_or(deadline, |next| next.min(deadline)))
+                            }
+                            None => {}
+                        }
+                    }
+                    if let Some(next_frame) = next_frame {
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame));
+                    }
+                }
+
                 Event::Suspended => {
                     for (_, view) in windows.iter_mut() {
                         view.suspend();
@@ -229,6 +293,6 @@ pub fn set_android_app(app: android_activity::AndroidApp) {
 #[cfg_attr(docsrs, doc(cfg(target_os = "android")))]
 /// Get the current [`AndroidApp`](android_activity::AndroidApp).
 /// This will panic if the android activity has not been setup with [`set_android_app`].
-pub fn current_android_app(app: android_activity::AndroidApp) -> AndroidApp {
+pub fn current_android_app() -> android_activity::AndroidApp {
     ANDROID_APP.get().unwrap().clone()
 }
@@ -0,0 +1,60 @@
+//! App-level keyboard shortcuts
+//!
+//! Shortcuts are checked before key presses are handled by the document, so a handled shortcut never reaches the
+//! page (or the built-in bindings, such as zooming and find-in-page).
+use blitz_dom::Document;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+/// A key pressed together with an exact set of modifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    pub key: KeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Shortcut {
+    pub fn new(key: KeyCode, modifiers: ModifiersState) -> Self {
+        Self { key, modifiers }
+    }
+}
+
+/// Handles a shortcut, returning whether the key press was consumed
+pub type ShortcutHandler = Box<dyn FnMut(&mut Document) -> bool>;
+
+/// The shortcuts bound by the app
+#[derive(Default)]
+pub struct Shortcuts {
+    bindings: Vec<(Shortcut, ShortcutHandler)>,
+}
+
+impl Shortcuts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a shortcut. If the handler doesn't consume the key press, it's handled as if the shortcut wasn't bound.
+    /// Shortcuts bound later take precedence.
+    pub fn bind(
+        mut self,
+        shortcut: Shortcut,
+        handler: impl FnMut(&mut Document) -> bool + 'static,
+    ) -> Self {
+        self.bindings.push((shortcut, Box::new(handler)));
+        self
+    }
+
+    /// Run the handlers bound to a key press until one consumes it. Returns whether it was consumed.
+    pub(crate) fn handle(
+        &mut self,
+        key: KeyCode,
+        modifiers: ModifiersState,
+        doc: &mut Document,
+    ) -> bool {
+        let shortcut = Shortcut::new(key, modifiers);
+        self.bindings
+            .iter_mut()
+            .rev()
+            .filter(|(bound, _)| *bound == shortcut)
+            .any(|(_, handler)| handler(doc))
+    }
+}
@@ -1,5 +1,14 @@
+use crate::shortcuts::Shortcuts;
 use crate::waker::UserWindowEvent;
-use blitz::{RenderState, Renderer, Viewport};
+use blitz::{PointerButton, RenderState, Renderer, Shell, ShellEvent, TextRendering, TouchPhase};
+use blitz_dom::color_scheme::ColorScheme;
+use blitz_dom::editing::EditCommand;
+use blitz_dom::events::WheelDelta;
+use blitz_dom::input_mode::InputMode;
+use blitz_dom::number_input::SpinDirection;
+use blitz_dom::spatial_navigation::NavigationDirection;
+#[cfg(any(target_os = "android", target_os = "ios"))]
+use blitz_dom::visual_viewport::ViewportInsets;
 use blitz_dom::DocumentLike;
 use winit::keyboard::PhysicalKey;
 
@@ -8,12 +17,17 @@ use wgpu::rwh::HasWindowHandle;
 
 use std::sync::Arc;
 use std::task::Waker;
+use std::time::Duration;
 use vello::Scene;
 use winit::dpi::LogicalSize;
 use winit::event::{ElementState, MouseButton};
 use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::window::{Icon, ImePurpose, Theme};
 use winit::{event::WindowEvent, keyboard::KeyCode, keyboard::ModifiersState, window::Window};
 
+/// The title of windows whose document has no `<title>`
+const DEFAULT_TITLE: &str = "Blitz";
+
 pub(crate) struct View<'s, Doc: DocumentLike> {
     pub(crate) renderer: Renderer<'s, Window, Doc>,
     pub(crate) scene: Scene,
@@ -22,6 +36,18 @@ pub(crate) struct View<'s, Doc: DocumentLike> {
     /// need to store them in order to have access to them when processing keypress events
     keyboard_modifiers: ModifiersState,
 
+    /// App-level keyboard shortcuts, handled before key presses reach the document
+    shortcuts: Shortcuts,
+
+    /// Whether the arrow keys move focus between elements (see [`blitz_dom::spatial_navigation`])
+    spatial_navigation: bool,
+
+    /// The settings that the window's text is rendered with
+    text_rendering: TextRendering,
+
+    /// The input mode of the focused text field when IME (and the soft keyboard) was last updated
+    input_mode: Option<InputMode>,
+
     /// Main menu bar of this view's window.
     #[cfg(all(feature = "menu", not(any(target_os = "android", target_os = "ios"))))]
     menu: Option<muda::Menu>,
@@ -34,6 +60,10 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
             scene: Scene::new(),
             waker: None,
             keyboard_modifiers: Default::default(),
+            shortcuts: Shortcuts::default(),
+            spatial_navigation: false,
+            text_rendering: TextRendering::default(),
+            input_mode: None,
             #[cfg(all(feature = "menu", not(any(target_os = "android", target_os = "ios"))))]
             menu: None,
         }
@@ -41,6 +71,28 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
 }
 
 impl<'a, Doc: DocumentLike> View<'a, Doc> {
+    pub(crate) fn with_shortcuts(mut self, shortcuts: Shortcuts) -> Self {
+        self.shortcuts = shortcuts;
+        self
+    }
+
+    pub(crate) fn with_spatial_navigation(mut self, spatial_navigation: bool) -> Self {
+        self.spatial_navigation = spatial_navigation;
+        self
+    }
+
+    pub(crate) fn with_text_rendering(mut self, text_rendering: TextRendering) -> Self {
+        self.text_rendering = text_rendering;
+        self
+    }
+
+    pub(crate) fn with_wheel_line_height(mut self, line_height: Option<f64>) -> Self {
+        if let Some(line_height) = line_height {
+            self.renderer.set_wheel_line_height(line_height);
+        }
+        self
+    }
+
     pub(crate) fn poll(&mut self) -> bool {
         match &self.waker {
             None => false,
@@ -51,12 +103,91 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
         }
     }
 
+    /// Start a new frame, then resolve and render the document
+    pub(crate) fn redraw(&mut self) {
+        // The soft keyboard can open or close between any two frames
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        self.update_insets();
+
+        self.renderer
+            .handle_shell_event(ShellEvent::RedrawRequested, &mut self.scene);
+        self.update_ime();
+    }
+
+    /// Allow IME (which shows the soft keyboard on mobile) while a text field has focus, unless its input mode is
+    /// `none`. Winit has no way to ask for a particular kind of soft keyboard, so the input mode only picks the
+    /// IME purpose.
+    fn update_ime(&mut self) {
+        let dom = self.renderer.dom.as_ref();
+        let input_mode = dom.focused_input_mode();
+        if input_mode == self.input_mode {
+            return;
+        }
+        self.input_mode = input_mode;
+        let Some(window) = self.renderer.shell() else {
+            return;
+        };
+
+        let is_password = dom
+            .focused_element()
+            .is_some_and(|node_id| dom.is_password_input(node_id));
+        window.set_ime_allowed(input_mode.is_some_and(|mode| mode != InputMode::None));
+        window.set_ime_purpose(if is_password {
+            ImePurpose::Password
+        } else {
+            ImePurpose::Normal
+        });
+    }
+
     pub fn request_redraw(&self) {
-        let RenderState::Active(state) = &self.renderer.render_state else {
+        self.renderer.request_redraw();
+    }
+
+    /// Tell the document how far the window is covered by the system bars, the display cutout and (on Android) the
+    /// soft keyboard, so that it can keep content out from under them
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    fn update_insets(&mut self) {
+        let Some(window) = self.renderer.shell() else {
             return;
         };
+        let scale = window.scale_factor() as f32;
+
+        // Android reports the part of the window that content should be laid out in
+        #[cfg(target_os = "android")]
+        let (top, right, bottom, left) = {
+            let size = window.inner_size();
+            let rect = crate::current_android_app().content_rect();
+            (
+                rect.top,
+                size.width as i32 - rect.right,
+                size.height as i32 - rect.bottom,
+                rect.left,
+            )
+        };
+        // iOS reports the safe area within the screen
+        #[cfg(target_os = "ios")]
+        let (top, right, bottom, left) = {
+            let (outer, inner) = (window.outer_size(), window.inner_size());
+            let Ok(position) = window.inner_position() else {
+                return;
+            };
+            (
+                position.y,
+                outer.width as i32 - inner.width as i32 - position.x,
+                outer.height as i32 - inner.height as i32 - position.y,
+                position.x,
+            )
+        };
 
-        state.window.request_redraw();
+        let logical = |px: i32| px.max(0) as f32 / scale;
+        let insets = ViewportInsets {
+            top: logical(top),
+            right: logical(right),
+            bottom: logical(bottom),
+            left: logical(left),
+        };
+        self.renderer
+            .handle_shell_event(ShellEvent::InsetsChanged(insets), &mut self.scene);
     }
 
     pub fn handle_window_event(&mut self, event: WindowEvent) {
@@ -68,22 +199,26 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                 // modifiers,
                 ..
             } => {
-                if state == ElementState::Pressed && matches!(button, MouseButton::Left | MouseButton::Right) {
-                    self.renderer.click(match button {
-                        MouseButton::Left => "left",
-                        MouseButton::Right => "right",
-                        _ => unreachable!(),
-
-                    });
-
+                // Ctrl+Shift+click selects the element whose box model is drawn over the document
+                let inspecting = self.keyboard_modifiers.control_key() && self.keyboard_modifiers.shift_key();
+                if state == ElementState::Pressed && button == MouseButton::Left && inspecting {
+                    self.renderer.inspect_node_at_mouse();
                     self.request_redraw();
+                } else if let Some(button) = pointer_button(button) {
+                    let event = match state {
+                        ElementState::Pressed => ShellEvent::PointerDown(button),
+                        ElementState::Released => ShellEvent::PointerUp(button),
+                    };
+                    self.renderer.handle_shell_event(event, &mut self.scene);
                 }
             }
 
             WindowEvent::Resized(physical_size) => {
-                self.renderer
-                    .set_size((physical_size.width, physical_size.height));
-                self.request_redraw();
+                let event = ShellEvent::Resized {
+                    width: physical_size.width,
+                    height: physical_size.height,
+                };
+                self.renderer.handle_shell_event(event, &mut self.scene);
             }
 
             // Store new keyboard modifier (ctrl, shift, etc) state for later use
@@ -95,6 +230,157 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
             WindowEvent::KeyboardInput { event, .. } => {
                 dbg!(&event);
 
+                if event.state == ElementState::Pressed {
+                    if let PhysicalKey::Code(key_code) = event.physical_key {
+                        // App-level shortcuts take precedence over everything else
+                        if self.shortcuts.handle(key_code, self.keyboard_modifiers, self.renderer.dom.as_mut()) {
+                            self.request_redraw();
+                            return;
+                        }
+
+                        // Alt and an element's access key activates it
+                        if self.keyboard_modifiers == ModifiersState::ALT {
+                            if let Some(key) = access_key(key_code) {
+                                if self.renderer.activate_access_key(key) {
+                                    self.request_redraw();
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                // The arrow keys open and move through the suggestions of an input with a datalist, enter chooses one
+                if event.state == ElementState::Pressed {
+                    let dom = self.renderer.dom.as_mut();
+                    let suggesting = dom
+                        .focused_element()
+                        .filter(|&node_id| dom.input_datalist(node_id).is_some());
+                    let handled = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::ArrowDown) if dom.suggestions_open() => dom.move_suggestion_highlight(true),
+                        PhysicalKey::Code(KeyCode::ArrowDown) => match suggesting {
+                            Some(node_id) => {
+                                dom.activate_picker(node_id);
+                                dom.suggestions_open()
+                            }
+                            None => false,
+                        },
+                        PhysicalKey::Code(KeyCode::ArrowUp) => dom.move_suggestion_highlight(false),
+                        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => self.renderer.choose_suggestion(),
+                        PhysicalKey::Code(KeyCode::Escape) if dom.suggestions_open() => {
+                            dom.close_picker();
+                            true
+                        }
+                        _ => false,
+                    };
+                    if handled {
+                        self.request_redraw();
+                        return;
+                    }
+                }
+
+                // The up and down arrows step a focused number input
+                if event.state == ElementState::Pressed {
+                    let focused = self.renderer.dom.as_ref().focused_element();
+                    let number_input = focused.filter(|&node_id| self.renderer.dom.as_ref().is_number_input(node_id));
+                    let direction = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::ArrowUp) => Some(SpinDirection::Up),
+                        PhysicalKey::Code(KeyCode::ArrowDown) => Some(SpinDirection::Down),
+                        _ => None,
+                    };
+                    if let (Some(node_id), Some(direction)) = (number_input, direction) {
+                        self.renderer.step_number_input(node_id, direction);
+                        self.request_redraw();
+                        return;
+                    }
+                }
+
+                // Keys typed while a text field has focus (or a contenteditable element has the caret) edit it
+                let dom = self.renderer.dom.as_ref();
+                let text_field = dom.focused_element().filter(|&node_id| dom.is_text_field(node_id));
+                if event.state == ElementState::Pressed && (text_field.is_some() || dom.caret().is_some()) {
+                    let control = self.keyboard_modifiers.control_key() || self.keyboard_modifiers.super_key();
+                    let shift = self.keyboard_modifiers.shift_key();
+                    let handled = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::Backspace) => self.renderer.edit(EditCommand::Delete),
+                        PhysicalKey::Code(KeyCode::Delete) => self.renderer.edit(EditCommand::ForwardDelete),
+                        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => {
+                            self.renderer.edit(EditCommand::InsertParagraph)
+                        }
+                        PhysicalKey::Code(KeyCode::ArrowLeft | KeyCode::ArrowRight) => {
+                            let forward = event.physical_key == PhysicalKey::Code(KeyCode::ArrowRight);
+                            let dom = self.renderer.dom.as_mut();
+                            match text_field {
+                                Some(node_id) => dom.move_text_field_caret(node_id, forward, shift),
+                                None => dom.move_caret(forward, shift),
+                            }
+                        }
+                        PhysicalKey::Code(KeyCode::KeyZ) if control && shift => self.renderer.redo(),
+                        PhysicalKey::Code(KeyCode::KeyZ) if control => self.renderer.undo(),
+                        PhysicalKey::Code(KeyCode::KeyY) if control => self.renderer.redo(),
+                        PhysicalKey::Code(KeyCode::KeyB) if control => self.renderer.edit(EditCommand::Bold),
+                        PhysicalKey::Code(KeyCode::KeyI) if control => self.renderer.edit(EditCommand::Italic),
+                        _ if !control => match event.text.as_ref().filter(|text| !text.chars().any(char::is_control)) {
+                            Some(text) => self.renderer.edit(EditCommand::InsertText(text.to_string())),
+                            None => false,
+                        },
+                        _ => false,
+                    };
+                    if handled {
+                        self.request_redraw();
+                        return;
+                    }
+                }
+
+                // Enter activates a focused button (or submits the form of a focused text field), and space presses a
+                // focused button until it's released
+                let dom = self.renderer.dom.as_ref();
+                if let Some(node_id) = dom.focused_element() {
+                    let is_button = dom.button_type(node_id).is_some();
+                    let pressed = event.state == ElementState::Pressed;
+                    let handled = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) if pressed && is_button => {
+                            self.renderer.activate(node_id);
+                            true
+                        }
+                        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) if pressed && dom.is_text_field(node_id) => {
+                            self.renderer.submit_implicitly(node_id)
+                        }
+                        PhysicalKey::Code(KeyCode::Space) if is_button && pressed => {
+                            self.renderer.dom.as_mut().set_active_element(Some(node_id));
+                            true
+                        }
+                        PhysicalKey::Code(KeyCode::Space) if is_button && dom.active_element() == Some(node_id) => {
+                            self.renderer.dom.as_mut().set_active_element(None);
+                            self.renderer.activate(node_id);
+                            true
+                        }
+                        _ => false,
+                    };
+                    if handled {
+                        self.request_redraw();
+                        return;
+                    }
+                }
+
+                // Arrow keys move focus to the nearest element in their direction, and enter or space activates it
+                if event.state == ElementState::Pressed && self.spatial_navigation {
+                    let handled = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::ArrowUp) => self.renderer.navigate(NavigationDirection::Up),
+                        PhysicalKey::Code(KeyCode::ArrowDown) => self.renderer.navigate(NavigationDirection::Down),
+                        PhysicalKey::Code(KeyCode::ArrowLeft) => self.renderer.navigate(NavigationDirection::Left),
+                        PhysicalKey::Code(KeyCode::ArrowRight) => self.renderer.navigate(NavigationDirection::Right),
+                        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space) => {
+                            self.renderer.activate_focused()
+                        }
+                        _ => false,
+                    };
+                    if handled {
+                        self.request_redraw();
+                        return;
+                    }
+                }
+
                 match event.physical_key {
                     PhysicalKey::Code(key_code) => {
                         match key_code {
@@ -122,6 +408,32 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                                     self.request_redraw();
                                 }
                             }
+                            KeyCode::Escape => {
+                                if event.state == ElementState::Pressed
+                                    && (self.renderer.dom.as_mut().close_context_menu()
+                                        || self.renderer.dom.as_mut().dismiss_top_layer())
+                                {
+                                    self.request_redraw();
+                                }
+                            }
+                            // Step through find-in-page matches
+                            KeyCode::F3 | KeyCode::KeyG => {
+                                if event.state == ElementState::Pressed
+                                    && (key_code == KeyCode::F3
+                                        || self.keyboard_modifiers.control_key()
+                                        || self.keyboard_modifiers.super_key())
+                                {
+                                    let dom = self.renderer.dom.as_mut();
+                                    let stepped = if self.keyboard_modifiers.shift_key() {
+                                        dom.find_previous().is_some()
+                                    } else {
+                                        dom.find_next().is_some()
+                                    };
+                                    if stepped {
+                                        self.request_redraw();
+                                    }
+                                }
+                            }
                             KeyCode::KeyD => {
                                 if event.state == ElementState::Pressed && self.keyboard_modifiers.alt_key()
                                 {
@@ -138,12 +450,26 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                                     self.request_redraw();
                                 }
                             }
+                            KeyCode::KeyP => {
+                                if event.state == ElementState::Pressed && self.keyboard_modifiers.alt_key()
+                                {
+                                    self.renderer.devtools.show_frame_timings =
+                                        !self.renderer.devtools.show_frame_timings;
+                                    self.request_redraw();
+                                }
+                            }
                             KeyCode::KeyT => {
                                 if event.state == ElementState::Pressed && self.keyboard_modifiers.alt_key()
                                 {
                                     self.renderer.print_taffy_tree();
                                 }
                             }
+                            KeyCode::KeyL => {
+                                if event.state == ElementState::Pressed && self.keyboard_modifiers.alt_key()
+                                {
+                                    self.renderer.print_layer_tree();
+                                }
+                            }
                             _ => {}
                         }
                     },
@@ -163,73 +489,11 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                 // modifiers,
                 ..
             } => {
-                let changed = if let RenderState::Active(state) = &self.renderer.render_state {
-                    let winit::dpi::LogicalPosition::<f32> { x, y } = position.to_logical(state.window.scale_factor());
-
-                    self.renderer.mouse_move(x, y)
-                } else {
-                    false
+                let Some(window) = self.renderer.shell() else {
+                    return;
                 };
-
-                if changed {
-                    let cursor = self.renderer.get_cursor();
-
-                    if let Some(cursor) = cursor {
-                        use style::values::computed::ui::CursorKind;
-                        use winit::window::CursorIcon as TaoCursor;
-                        let tao_cursor = match cursor {
-                            CursorKind::None => todo!("set the cursor to none"),
-                            CursorKind::Default => TaoCursor::Default,
-                            CursorKind::Pointer => TaoCursor::Pointer,
-                            CursorKind::ContextMenu => TaoCursor::ContextMenu,
-                            CursorKind::Help => TaoCursor::Help,
-                            CursorKind::Progress => TaoCursor::Progress,
-                            CursorKind::Wait => TaoCursor::Wait,
-                            CursorKind::Cell => TaoCursor::Cell,
-                            CursorKind::Crosshair => TaoCursor::Crosshair,
-                            CursorKind::Text => TaoCursor::Text,
-                            CursorKind::VerticalText => TaoCursor::VerticalText,
-                            CursorKind::Alias => TaoCursor::Alias,
-                            CursorKind::Copy => TaoCursor::Copy,
-                            CursorKind::Move => TaoCursor::Move,
-                            CursorKind::NoDrop => TaoCursor::NoDrop,
-                            CursorKind::NotAllowed => TaoCursor::NotAllowed,
-                            CursorKind::Grab => TaoCursor::Grab,
-                            CursorKind::Grabbing => TaoCursor::Grabbing,
-                            CursorKind::EResize => TaoCursor::EResize,
-                            CursorKind::NResize => TaoCursor::NResize,
-                            CursorKind::NeResize => TaoCursor::NeResize,
-                            CursorKind::NwResize => TaoCursor::NwResize,
-                            CursorKind::SResize => TaoCursor::SResize,
-                            CursorKind::SeResize => TaoCursor::SeResize,
-                            CursorKind::SwResize => TaoCursor::SwResize,
-                            CursorKind::WResize => TaoCursor::WResize,
-                            CursorKind::EwResize => TaoCursor::EwResize,
-                            CursorKind::NsResize => TaoCursor::NsResize,
-                            CursorKind::NeswResize => TaoCursor::NeswResize,
-                            CursorKind::NwseResize => TaoCursor::NwseResize,
-                            CursorKind::ColResize => TaoCursor::ColResize,
-                            CursorKind::RowResize => TaoCursor::RowResize,
-                            CursorKind::AllScroll => TaoCursor::AllScroll,
-                            CursorKind::ZoomIn => TaoCursor::ZoomIn,
-                            CursorKind::ZoomOut => TaoCursor::ZoomOut,
-                            CursorKind::Auto => {
-                                // todo: we should be the ones determining this based on the UA?
-                                // https://developer.mozilla.org/en-US/docs/Web/CSS/cursor
-
-
-                                TaoCursor::Default
-                            },
-                        };
-
-                        if let RenderState::Active(state) = &self.renderer.render_state {
-                            state.window.set_cursor(tao_cursor);
-                            self.request_redraw();
-                        }
-                    }
-                }
-
-
+                let winit::dpi::LogicalPosition::<f32> { x, y } = position.to_logical(window.scale_factor());
+                self.renderer.handle_shell_event(ShellEvent::PointerMoved { x, y }, &mut self.scene);
             }
             WindowEvent::CursorEntered { /*device_id*/.. } => {}
             WindowEvent::CursorLeft { /*device_id*/.. } => {}
@@ -240,15 +504,17 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                 // modifiers,
                 ..
             } => {
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        self.renderer.scroll_by(y as f64 * 20.0)
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(offsets) => {
-                        self.renderer.scroll_by(offsets.y)
-                    }
+                let delta = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(x, y) => WheelDelta::Lines {
+                        x: x as f64,
+                        y: y as f64,
+                    },
+                    winit::event::MouseScrollDelta::PixelDelta(offsets) => WheelDelta::Pixels {
+                        x: offsets.x,
+                        y: offsets.y,
+                    },
                 };
-                self.request_redraw();
+                self.renderer.handle_shell_event(ShellEvent::Wheel(delta), &mut self.scene);
             }
 
             WindowEvent::TouchpadPressure {
@@ -263,13 +529,32 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                 // value,
                 ..
             } => {}
-            WindowEvent::Touch(_) => {}
+            WindowEvent::Touch(touch) => {
+                let Some(window) = self.renderer.shell() else {
+                    return;
+                };
+                let winit::dpi::LogicalPosition::<f32> { x, y } = touch.location.to_logical(window.scale_factor());
+                let phase = match touch.phase {
+                    winit::event::TouchPhase::Started => TouchPhase::Started,
+                    winit::event::TouchPhase::Moved => TouchPhase::Moved,
+                    winit::event::TouchPhase::Ended => TouchPhase::Ended,
+                    winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+                };
+                let event = ShellEvent::Touch { id: touch.id, phase, x, y };
+                self.renderer.handle_shell_event(event, &mut self.scene);
+            }
             WindowEvent::ScaleFactorChanged {
-                // scale_factor,
+                scale_factor,
                 // new_inner_size,
                 ..
-            } => {}
-            WindowEvent::ThemeChanged(_) => {}
+            } => {
+                let event = ShellEvent::ScaleFactorChanged(scale_factor as f32);
+                self.renderer.handle_shell_event(event, &mut self.scene);
+            }
+            WindowEvent::ThemeChanged(theme) => {
+                let event = ShellEvent::ColorSchemeChanged(color_scheme(theme));
+                self.renderer.handle_shell_event(event, &mut self.scene);
+            }
             _ => {}
         }
     }
@@ -282,10 +567,14 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
     ) {
         let window_builder = || {
             let window = event_loop
-                .create_window(Window::default_attributes().with_inner_size(LogicalSize {
-                    width: 800,
-                    height: 600,
-                }))
+                .create_window(
+                    Window::default_attributes()
+                        .with_title(DEFAULT_TITLE)
+                        .with_inner_size(LogicalSize {
+                            width: 800,
+                            height: 600,
+                        }),
+                )
                 .unwrap();
 
             #[cfg(all(feature = "menu", not(any(target_os = "android", target_os = "ios"))))]
@@ -295,20 +584,75 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
                     &window,
                 ));
             }
-            let size: winit::dpi::PhysicalSize<u32> = window.inner_size();
-            let mut viewport = Viewport::new((size.width, size.height));
-            viewport.set_hidpi_scale(window.scale_factor() as _);
-
-            (Arc::from(window), viewport)
+            Arc::from(window)
         };
 
-        rt.block_on(self.renderer.resume(window_builder));
+        rt.block_on(self.renderer.resume_shell(window_builder));
+        self.renderer.set_text_rendering(self.text_rendering);
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        self.update_insets();
 
         let RenderState::Active(state) = &self.renderer.render_state else {
             panic!("Renderer failed to resume");
         };
 
         self.waker = Some(crate::waker::tao_waker(proxy, state.window.id()));
+
+        // Show the document's title, icon and theme color in the window's chrome
+        let window = state.window.clone();
+        let mut icon_url = None;
+        self.renderer
+            .dom
+            .as_mut()
+            .set_metadata_handler(move |doc, metadata| {
+                Shell::set_title(&*window, metadata.title.as_deref().unwrap_or(DEFAULT_TITLE));
+
+                // Icons are only fetched again when their URL changes
+                if metadata.icon != icon_url {
+                    icon_url = metadata.icon.clone();
+                    let icon = icon_url
+                        .as_ref()
+                        .and_then(|url| match doc.load_favicon(url) {
+                            Ok(icon) => Icon::from_rgba(icon.rgba, icon.width, icon.height).ok(),
+                            Err(err) => {
+                                eprintln!("Error fetching icon {}: {}", url, err);
+                                None
+                            }
+                        });
+                    window.set_window_icon(icon);
+                }
+
+                // Only Windows lets the color of the title bar be set
+                #[cfg(target_os = "windows")]
+                {
+                    use winit::platform::windows::{Color, WindowExtWindows};
+                    window.set_title_background_color(
+                        metadata
+                            .theme_color
+                            .map(|color| Color::from_rgb(color.r, color.g, color.b)),
+                    );
+                }
+            });
+
+        // Pace frames to the refresh rate of the display
+        if let Some(millihertz) = state
+            .window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|millihertz| *millihertz > 0)
+        {
+            let interval = Duration::from_secs_f64(1000.0 / f64::from(millihertz));
+            self.renderer.dom.as_mut().set_frame_interval(interval);
+        }
+
+        // Follow the theme of the OS
+        if let Some(theme) = state.window.theme() {
+            self.renderer
+                .dom
+                .as_mut()
+                .set_preferred_color_scheme(color_scheme(theme));
+            self.renderer.dom.as_mut().resolve();
+        }
         self.renderer.render(&mut self.scene);
     }
 
@@ -318,6 +662,23 @@ impl<'a, Doc: DocumentLike> View<'a, Doc> {
     }
 }
 
+/// The button of the document's pointer events that a mouse button presses
+fn pointer_button(button: MouseButton) -> Option<PointerButton> {
+    match button {
+        MouseButton::Left => Some(PointerButton::Left),
+        MouseButton::Right => Some(PointerButton::Right),
+        _ => None,
+    }
+}
+
+/// The color scheme matching a window theme
+fn color_scheme(theme: Theme) -> ColorScheme {
+    match theme {
+        Theme::Light => ColorScheme::Light,
+        Theme::Dark => ColorScheme::Dark,
+    }
+}
+
 /// Initialize the default menu bar.
 #[cfg(all(feature = "menu", not(any(target_os = "android", target_os = "ios"))))]
 pub fn init_menu(#[cfg(target_os = "windows")] window: &Window) -> muda::Menu {
@@ -358,3 +719,17 @@ pub fn init_menu(#[cfg(target_os = "windows")] window: &Window) -> muda::Menu {
 
     menu
 }
+
+/// The character typed by a letter or digit key without any modifiers, for matching access keys. This uses the
+/// physical key, as modifiers change the text of key presses on some platforms.
+fn access_key(key_code: KeyCode) -> Option<char> {
+    let name = format!("{key_code:?}");
+    let key = name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))?;
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) => Some(key.to_ascii_lowercase()),
+        _ => None,
+    }
+}
@@ -0,0 +1,47 @@
+//! The `accesskey` attribute
+//!
+//! <https://html.spec.whatwg.org/multipage/interaction.html#the-accesskey-attribute>
+//!
+//! An element's `accesskey` attribute lists the keys that activate it (together with a platform modifier, which the
+//! shell chooses). The first single-character key in the list is the element's access key. When several elements
+//! share an access key the first in tree order is activated.
+use html5ever::local_name;
+
+use crate::Document;
+
+impl Document {
+    /// The element whose access key is `key` (compared case-insensitively), if any. Disabled elements and elements
+    /// that aren't rendered can't be activated by their access key.
+    pub fn access_key_element(&self, key: char) -> Option<usize> {
+        let root_id = self.try_root_element()?.id;
+        self.find_access_key_element(root_id, key)
+    }
+
+    /// The access key of an element
+    pub fn access_key(&self, node_id: usize) -> Option<char> {
+        let value = self.nodes.get(node_id)?.attr(local_name!("accesskey"))?;
+        value.split_ascii_whitespace().find_map(|token| {
+            let mut chars = token.chars();
+            match (chars.next(), chars.next()) {
+                (Some(key), None) => Some(key),
+                _ => None,
+            }
+        })
+    }
+
+    fn find_access_key_element(&self, node_id: usize, key: char) -> Option<usize> {
+        let node = &self.nodes[node_id];
+        if node.hidden || node.style.display == taffy::Display::None {
+            return None;
+        }
+        let matches = self
+            .access_key(node_id)
+            .is_some_and(|access_key| access_key.to_lowercase().eq(key.to_lowercase()));
+        if matches && !node.is_disabled() {
+            return Some(node_id);
+        }
+        node.children
+            .iter()
+            .find_map(|child_id| self.find_access_key_element(*child_id, key))
+    }
+}
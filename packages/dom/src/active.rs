@@ -0,0 +1,87 @@
+//! The `:active` state of elements being pressed
+//!
+//! <https://html.spec.whatwg.org/multipage/semantics-other.html#selector-active>
+//!
+//! An element is being activated while the primary pointer button is held down on it, or while the space key is held
+//! down with it focused. The element and its ancestors match `:active` until the button or key is released.
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style_traits::dom::ElementState;
+
+use crate::Document;
+
+impl Document {
+    /// The element being pressed (if any)
+    pub fn active_element(&self) -> Option<usize> {
+        self.active_element
+            .filter(|&node_id| self.nodes.contains(node_id))
+    }
+
+    /// Start pressing an element (or the element a text node is in), or stop pressing with `None`
+    pub fn set_active_element(&mut self, node_id: Option<usize>) {
+        if let Some(old_id) = self.active_element.take() {
+            self.set_active_state(old_id, false);
+        }
+        let element_id = node_id.and_then(|node_id| {
+            let node = self.nodes.get(node_id)?;
+            if node.is_element() {
+                Some(node_id)
+            } else {
+                node.parent
+            }
+        });
+        if let Some(element_id) = element_id {
+            self.set_active_state(element_id, true);
+            self.active_element = Some(element_id);
+        }
+    }
+
+    /// Set or clear the active state of an element and its ancestors
+    fn set_active_state(&mut self, node_id: usize, active: bool) {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let Some(node) = self.nodes.get(id) else {
+                break;
+            };
+            if !node.is_element() {
+                break;
+            }
+            let parent = node.parent;
+            self.snapshot_node(id);
+            let node = &mut self.nodes[id];
+            node.element_state.set(ElementState::ACTIVE, active);
+            if let Some(element_data) = node.stylo_element_data.borrow_mut().as_mut() {
+                element_data.hint.insert(RestyleHint::RESTYLE_SELF);
+            }
+            maybe_id = parent;
+        }
+    }
+}
+
+#[test]
+fn presses_elements_and_their_ancestors() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div id=outer><button id=button>Go</button></div>\
+        <p id=other></p></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let is_active = |doc: &Document, id: &str| {
+        let node_id = doc.get_element_by_id(id).unwrap();
+        doc.nodes[node_id]
+            .element_state
+            .contains(ElementState::ACTIVE)
+    };
+    let button = doc.get_element_by_id("button").unwrap();
+
+    // Pressing the button's text presses the button and its ancestors
+    doc.set_active_element(Some(doc.nodes[button].children[0]));
+    assert_eq!(doc.active_element(), Some(button));
+    assert!(is_active(&doc, "button"));
+    assert!(is_active(&doc, "outer"));
+    assert!(!is_active(&doc, "other"));
+
+    doc.set_active_element(None);
+    assert_eq!(doc.active_element(), None);
+    assert!(!is_active(&doc, "outer"));
+}
@@ -0,0 +1,85 @@
+//! Constructed stylesheets that can be adopted by several documents (the equivalent of `new CSSStyleSheet()` and
+//! `document.adoptedStyleSheets`)
+//!
+//! Stylo's stylesheets are locked with the lock of the document they were parsed for, so a constructed stylesheet is
+//! only shared without reparsing between documents that share a lock (see [`Document::new_sharing_lock`]). Adopting
+//! it into any other document parses its source again for that document.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use style::media_queries::Device;
+use style::stylesheets::{DocumentStyleSheet, Origin};
+
+use crate::Document;
+
+static NEXT_LOCK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A unique id for the lock of a new document
+pub(crate) fn next_lock_id() -> u64 {
+    NEXT_LOCK_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A stylesheet parsed once from source text, that can be adopted by documents
+#[derive(Clone)]
+pub struct ConstructedStyleSheet {
+    css: Arc<str>,
+    sheet: DocumentStyleSheet,
+    lock_id: u64,
+}
+
+impl ConstructedStyleSheet {
+    /// Parse a stylesheet for a document (and any documents that share its lock)
+    pub fn new(doc: &Document, css: &str) -> Self {
+        Self {
+            css: Arc::from(css),
+            sheet: doc.parse_stylesheet(css, Origin::Author),
+            lock_id: doc.lock_id,
+        }
+    }
+
+    /// The source text the stylesheet was constructed from
+    pub fn css_text(&self) -> &str {
+        &self.css
+    }
+
+    /// Whether two handles refer to the same constructed stylesheet
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.sheet == other.sheet
+    }
+}
+
+impl Document {
+    /// Create a document that shares another document's lock, so that stylesheets constructed for either can be
+    /// adopted by both without being reparsed
+    pub fn new_sharing_lock(device: Device, other: &Document) -> Self {
+        Self::new_with_lock(device, other.guard.clone(), other.lock_id)
+    }
+
+    /// The constructed stylesheets adopted by the document
+    pub fn adopted_stylesheets(&self) -> impl Iterator<Item = &ConstructedStyleSheet> {
+        self.adopted_stylesheets.iter().map(|(sheet, _)| sheet)
+    }
+
+    /// Replace the constructed stylesheets adopted by the document. Adopted stylesheets apply after the document's
+    /// own stylesheets, in order.
+    pub fn set_adopted_stylesheets(&mut self, sheets: Vec<ConstructedStyleSheet>) {
+        let previous = std::mem::take(&mut self.adopted_stylesheets);
+        for (_, applied) in previous {
+            self.stylist.remove_stylesheet(applied, &self.guard.read());
+        }
+
+        for sheet in sheets {
+            let applied = match sheet.lock_id == self.lock_id {
+                true => sheet.sheet.clone(),
+                false => self.parse_stylesheet(&sheet.css, Origin::Author),
+            };
+            self.stylist
+                .append_stylesheet(applied.clone(), &self.guard.read());
+            self.adopted_stylesheets.push((sheet, applied));
+        }
+
+        self.stylist
+            .force_stylesheet_origins_dirty(Origin::Author.into());
+        self.invalidate();
+    }
+}
@@ -0,0 +1,53 @@
+//! Anchor positioning
+//!
+//! <https://drafts.csswg.org/css-anchor-position-1/>
+//!
+//! Top layer elements (popovers and dialogs) that have an anchor are positioned next to it rather than centered in
+//! the viewport: below the anchor and aligned with its left edge, flipping above it if there isn't room below, and
+//! shifted to stay within the viewport. An element's anchor is the element referenced by its `anchor` attribute or,
+//! for popovers, the `popovertarget` button that showed it (its implicit anchor).
+//!
+//! Note: the servo configuration of stylo doesn't parse `anchor-name`, `position-anchor` or the `anchor()` and
+//! `anchor-size()` functions, so anchors can't yet be named or referenced from CSS, and elements that aren't in the
+//! top layer aren't anchored.
+use html5ever::LocalName;
+
+use crate::Document;
+
+impl Document {
+    /// The element that an element is anchored to, if any
+    pub fn anchor_element(&self, node_id: usize) -> Option<usize> {
+        let node = self.nodes.get(node_id)?;
+        match node.attr(LocalName::from("anchor")) {
+            Some(id) => self.get_element_by_id(id),
+            None => self.popover_invokers.get(&node_id).copied(),
+        }
+        .filter(|anchor_id| *anchor_id != node_id && self.nodes.contains(*anchor_id))
+    }
+
+    /// The position (relative to the viewport) of an anchored top layer element of the given size, or `None` if the
+    /// element isn't anchored
+    pub(crate) fn anchored_position(
+        &self,
+        node_id: usize,
+        size: taffy::Size<f32>,
+        viewport: taffy::Size<f32>,
+    ) -> Option<taffy::Point<f32>> {
+        let anchor_id = self.anchor_element(node_id)?;
+        let anchor_position = self.absolute_position(anchor_id);
+        let anchor_size = self.nodes[anchor_id].final_layout.size;
+        let top = anchor_position.y - self.viewport_scroll as f32;
+        let bottom = top + anchor_size.height;
+
+        // Flip above the anchor if it doesn't fit below, and there's more room above
+        let space_below = viewport.height - bottom;
+        let y = if size.height > space_below && top > space_below {
+            top - size.height
+        } else {
+            bottom
+        };
+        let x = anchor_position.x.min(viewport.width - size.width).max(0.0);
+
+        Some(taffy::Point { x, y: y.max(0.0) })
+    }
+}
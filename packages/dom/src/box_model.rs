@@ -0,0 +1,214 @@
+//! The box model of laid out nodes, for devtools overlays
+//!
+//! <https://drafts.csswg.org/css-box-3/#box-model>
+//!
+//! [`Document::box_model`] gives the margin, border, padding and content boxes of a node (like the box model view of
+//! browser devtools), along with the baselines of its lines of text and the tracks of its flex or grid layout. Boxes
+//! are relative to the viewport, like [`Document::node_bounding_rect`].
+//!
+//! Note: taffy doesn't report the margins it resolves or the tracks it places items in. Margins are resolved from the
+//! node's style against the width of its parent, with `auto` margins treated as zero. Tracks are inferred from the
+//! margin boxes of the container's in-flow items: the lines of a flex container are the spans of its items on the
+//! cross axis, and the columns and rows of a grid container are the spans of its items on each axis. So empty tracks
+//! aren't shown, and the tracks that an item spans are merged into one.
+use taffy::ResolveOrZero;
+
+use crate::geometry::DomRect;
+use crate::Document;
+
+/// The direction that a track runs in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackAxis {
+    /// A track that runs horizontally: a row of a grid, or a line of a row flex container
+    Row,
+    /// A track that runs vertically: a column of a grid, or a line of a column flex container
+    Column,
+}
+
+/// A line of a flex container, or a row or column of a grid container
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutTrack {
+    pub axis: TrackAxis,
+    /// The area of the track, which spans the container's content box
+    pub rect: DomRect,
+}
+
+/// The boxes of a laid out node, relative to the viewport
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoxModel {
+    pub margin: DomRect,
+    pub border: DomRect,
+    pub padding: DomRect,
+    pub content: DomRect,
+    /// The vertical positions of the baselines of the node's lines of text (if it lays out text)
+    pub baselines: Vec<f32>,
+    /// The tracks of the node's flex or grid layout (if it's a flex or grid container)
+    pub tracks: Vec<LayoutTrack>,
+}
+
+impl Document {
+    /// The boxes of a node, relative to the viewport. Returns `None` if the node doesn't generate a box.
+    pub fn box_model(&self, node_id: usize) -> Option<BoxModel> {
+        let node = self.nodes.get(node_id)?;
+        node.element_data()?;
+        let border = self.node_bounding_rect(node_id)?;
+        let layout = node.final_layout;
+
+        // Margins are resolved against the width of the parent's content box
+        let parent_width = node.parent.map(|parent_id| {
+            let parent = self.nodes[parent_id].final_layout;
+            parent.size.width
+                - parent.padding.left
+                - parent.padding.right
+                - parent.border.left
+                - parent.border.right
+        });
+        let margin = node.style.margin.resolve_or_zero(taffy::Size {
+            width: parent_width,
+            height: parent_width,
+        });
+
+        let margin_box = DomRect {
+            x: border.x - margin.left,
+            y: border.y - margin.top,
+            width: border.width + margin.left + margin.right,
+            height: border.height + margin.top + margin.bottom,
+        };
+        let padding_box = inset(border, layout.border);
+        let content = inset(padding_box, layout.padding);
+
+        // Lines of text are laid out at the document's scale, from the top of the content box
+        let baselines = node
+            .element_data()
+            .and_then(|element_data| element_data.inline_layout.as_ref())
+            .filter(|_| node.is_inline_root)
+            .map(|inline_layout| {
+                inline_layout
+                    .layout
+                    .lines()
+                    .map(|line| content.y + line.metrics().baseline / self.scale)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(BoxModel {
+            margin: margin_box,
+            border,
+            padding: padding_box,
+            content,
+            baselines,
+            tracks: self.layout_tracks(node_id, content),
+        })
+    }
+
+    /// The tracks of a flex or grid container, inferred from the margin boxes of its in-flow items
+    fn layout_tracks(&self, node_id: usize, content: DomRect) -> Vec<LayoutTrack> {
+        let node = &self.nodes[node_id];
+        let axes: &[TrackAxis] = match node.style.display {
+            taffy::Display::Flex if node.style.flex_direction.is_row() => &[TrackAxis::Row],
+            taffy::Display::Flex => &[TrackAxis::Column],
+            taffy::Display::Grid => &[TrackAxis::Column, TrackAxis::Row],
+            _ => return Vec::new(),
+        };
+
+        let items: Vec<DomRect> = node
+            .layout_children
+            .borrow()
+            .iter()
+            .flatten()
+            .filter(|child_id| self.nodes[**child_id].style.position != taffy::Position::Absolute)
+            .filter_map(|child_id| Some(self.box_model(*child_id)?.margin))
+            .collect();
+
+        let mut tracks = Vec::new();
+        for axis in axes {
+            let spans = items.iter().map(|item| match axis {
+                TrackAxis::Row => (item.y, item.bottom()),
+                TrackAxis::Column => (item.x, item.right()),
+            });
+            for (start, end) in merge_spans(spans.collect()) {
+                let rect = match axis {
+                    TrackAxis::Row => DomRect {
+                        y: start,
+                        height: end - start,
+                        ..content
+                    },
+                    TrackAxis::Column => DomRect {
+                        x: start,
+                        width: end - start,
+                        ..content
+                    },
+                };
+                tracks.push(LayoutTrack { axis: *axis, rect });
+            }
+        }
+        tracks
+    }
+}
+
+/// A rectangle shrunk by the given edges
+fn inset(rect: DomRect, edges: taffy::Rect<f32>) -> DomRect {
+    DomRect {
+        x: rect.x + edges.left,
+        y: rect.y + edges.top,
+        width: (rect.width - edges.left - edges.right).max(0.0),
+        height: (rect.height - edges.top - edges.bottom).max(0.0),
+    }
+}
+
+/// Merge overlapping spans. Spans that only touch are kept apart, as they belong to adjacent tracks.
+fn merge_spans(mut spans: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start < last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[test]
+fn measures_box_models() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div id=box style='margin: 10px 20px; border: 2px solid; padding: 5px; width: 100px; height: 50px'></div>\
+        <div id=grid style='display: grid; grid-template-columns: 50px 50px; width: 100px'>\
+        <div style='height: 10px'></div><div style='height: 10px'></div><div style='height: 10px'></div>\
+        </div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let rect = |x, y, width, height| DomRect {
+        x,
+        y,
+        width,
+        height,
+    };
+    let box_model = doc
+        .box_model(doc.get_element_by_id("box").unwrap())
+        .unwrap();
+    assert_eq!(box_model.margin, rect(0.0, 0.0, 154.0, 84.0));
+    assert_eq!(box_model.border, rect(20.0, 10.0, 114.0, 64.0));
+    assert_eq!(box_model.padding, rect(22.0, 12.0, 110.0, 60.0));
+    assert_eq!(box_model.content, rect(27.0, 17.0, 100.0, 50.0));
+    assert!(box_model.tracks.is_empty());
+
+    let grid = doc
+        .box_model(doc.get_element_by_id("grid").unwrap())
+        .unwrap();
+    let spans = |axis| -> Vec<(f32, f32)> {
+        grid.tracks
+            .iter()
+            .filter(|track| track.axis == axis)
+            .map(|track| match axis {
+                TrackAxis::Row => (track.rect.y - grid.content.y, track.rect.height),
+                TrackAxis::Column => (track.rect.x - grid.content.x, track.rect.width),
+            })
+            .collect()
+    };
+    assert_eq!(spans(TrackAxis::Column), [(0.0, 50.0), (50.0, 50.0)]);
+    assert_eq!(spans(TrackAxis::Row), [(0.0, 10.0), (10.0, 10.0)]);
+}
@@ -0,0 +1,119 @@
+//! Inspecting the cascade layers (`@layer`) declared by the document's stylesheets
+//!
+//! Stylo orders layers across all of a document's stylesheets by where each layer is first declared. This walks the
+//! stylesheets in the same order to report the resulting layer order, for debugging styles that don't apply as
+//! expected. Layers declared inside `@media` and `@supports` rules are included whether or not their conditions match.
+use style::shared_lock::SharedRwLockReadGuard;
+use style::stylesheets::{CssRule, CssRules, Origin};
+use style_traits::ToCss;
+
+use crate::Document;
+
+/// A layer and the layers nested inside it, in the order they were first declared
+#[derive(Default)]
+struct LayerTree {
+    name: String,
+    children: Vec<LayerTree>,
+}
+
+impl LayerTree {
+    fn child(&mut self, name: &str) -> &mut LayerTree {
+        let index = match self.children.iter().position(|child| child.name == name) {
+            Some(index) => index,
+            None => {
+                self.children.push(LayerTree {
+                    name: name.to_string(),
+                    children: Vec::new(),
+                });
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+
+    /// Nested layers take precedence over each other in declaration order, and the styles of the layer they are
+    /// nested in take precedence over all of them
+    fn flatten(&self, prefix: &str, order: &mut Vec<String>) {
+        for child in &self.children {
+            let name = match prefix.is_empty() {
+                true => child.name.clone(),
+                false => format!("{prefix}.{}", child.name),
+            };
+            child.flatten(&name, order);
+            order.push(name);
+        }
+    }
+}
+
+impl Document {
+    /// The fully qualified names of the cascade layers declared by the document's stylesheets, from lowest to highest
+    /// precedence (for normal declarations). Styles that aren't in a layer take precedence over every layer.
+    /// Anonymous layers are named `<anonymous>`.
+    pub fn cascade_layer_order(&self) -> Vec<String> {
+        let guard = self.guard.read();
+        let mut root = LayerTree::default();
+        for index in 0..self.stylist.sheet_count(Origin::Author) {
+            let Some(sheet) = self.stylist.sheet_at(Origin::Author, index) else {
+                continue;
+            };
+            let rules = sheet.0.contents.rules.read_with(&guard);
+            collect_layers(rules, &guard, &mut root);
+        }
+
+        let mut order = Vec::new();
+        root.flatten("", &mut order);
+        order
+    }
+}
+
+fn collect_layers(rules: &CssRules, guard: &SharedRwLockReadGuard, parent: &mut LayerTree) {
+    for rule in rules.0.iter() {
+        match rule {
+            CssRule::LayerStatement(rule) => {
+                for name in rule.names.iter() {
+                    layer_for_name(parent, &name.to_css_string());
+                }
+            }
+            CssRule::LayerBlock(rule) => {
+                let layer = match &rule.name {
+                    Some(name) => layer_for_name(parent, &name.to_css_string()),
+                    None => {
+                        // Every anonymous layer is distinct
+                        parent.children.push(LayerTree {
+                            name: String::from("<anonymous>"),
+                            children: Vec::new(),
+                        });
+                        parent.children.last_mut().unwrap()
+                    }
+                };
+                collect_layers(rule.rules.read_with(guard), guard, layer);
+            }
+            CssRule::Media(rule) => collect_layers(rule.rules.read_with(guard), guard, parent),
+            CssRule::Supports(rule) => collect_layers(rule.rules.read_with(guard), guard, parent),
+            _ => {}
+        }
+    }
+}
+
+/// The layer for a (possibly dotted) layer name, declaring it and its parents if they haven't been already
+fn layer_for_name<'a>(parent: &'a mut LayerTree, name: &str) -> &'a mut LayerTree {
+    name.split('.')
+        .fold(parent, |layer, segment| layer.child(segment))
+}
+
+#[test]
+fn layers_are_ordered_across_stylesheets() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div id=a>text</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+
+    // The theme layer is declared after the base layer, so it wins even though the base layer's rules come later
+    doc.add_stylesheet("@layer base, theme; @layer theme.colors { #a { display: flex } }");
+    doc.add_stylesheet("@layer base { #a { display: grid } }");
+    doc.resolve();
+
+    let div_id = doc.get_element_by_id("a").unwrap();
+    assert_eq!(doc.nodes[div_id].style.display, taffy::Display::Flex);
+    assert_eq!(doc.cascade_layer_order(), ["base", "theme.colors", "theme"]);
+}
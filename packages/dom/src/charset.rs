@@ -0,0 +1,157 @@
+//! Detecting the character encoding of fetched documents and decoding them
+//!
+//! The encoding of an HTML document is determined, in order of precedence, by its byte order mark, the charset of its
+//! HTTP `Content-Type` header, and a `<meta charset>` (or `<meta http-equiv=content-type>`) element near its start.
+//! Documents without any of these are decoded as UTF-8 if they're valid UTF-8, and as windows-1252 otherwise.
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// How many bytes at the start of a document are scanned for a `<meta>` charset declaration
+const PRESCAN_LIMIT: usize = 1024;
+
+/// The `charset` parameter of a `Content-Type` header value, e.g. `text/html; charset=shift_jis`
+pub fn content_type_charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        let value = value.trim().trim_matches(['"', '\'']);
+        (!value.is_empty()).then_some(value)
+    })
+}
+
+/// Decode a fetched HTML document, given the value of its `Content-Type` header (if it has one)
+pub fn decode_html(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type_encoding(content_type))
+        .or_else(|| prescan_meta_charset(bytes));
+    decode(bytes, encoding)
+}
+
+/// Decode fetched text that isn't HTML (e.g. a stylesheet), given the value of its `Content-Type` header (if it has
+/// one)
+pub fn decode_text(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type_encoding(content_type));
+    decode(bytes, encoding)
+}
+
+fn content_type_encoding(content_type: Option<&str>) -> Option<&'static Encoding> {
+    Encoding::for_label(content_type_charset(content_type?)?.as_bytes())
+}
+
+fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> String {
+    let encoding = encoding.unwrap_or_else(|| match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => WINDOWS_1252,
+    });
+    // A byte order mark overrides the encoding, and is removed
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+/// Find the encoding declared by a `<meta>` element at the start of a document
+fn prescan_meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(PRESCAN_LIMIT)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start + 5..];
+        let end = tag.find('>').unwrap_or(tag.len());
+        let attrs = &tag[..end];
+        rest = &tag[end..];
+
+        // `<meta charset=...>`, or `<meta http-equiv=content-type content="text/html; charset=...">`
+        let attrs = parse_attributes(attrs);
+        let attr = |name: &str| {
+            attrs
+                .iter()
+                .find(|(attr_name, _)| *attr_name == name)
+                .map(|(_, value)| *value)
+        };
+        let label = match attr("charset") {
+            Some(label) => Some(label),
+            None if attr("http-equiv") == Some("content-type") => {
+                attr("content").and_then(content_type_charset)
+            }
+            None => None,
+        };
+        if let Some(encoding) = label.and_then(|label| Encoding::for_label(label.as_bytes())) {
+            // The document has already been decoded as ASCII to find the declaration, so it can't be UTF-16
+            return Some(if encoding == UTF_16BE || encoding == UTF_16LE {
+                UTF_8
+            } else {
+                encoding
+            });
+        }
+    }
+    None
+}
+
+/// The (name, value) pairs of the attributes in the source of a start tag
+fn parse_attributes(mut src: &str) -> Vec<(&str, &str)> {
+    let mut attrs = Vec::new();
+    loop {
+        src = src.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if src.is_empty() {
+            return attrs;
+        }
+
+        let name_end = src
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '=')
+            .unwrap_or(src.len());
+        let name = &src[..name_end];
+        src = src[name_end..].trim_start();
+
+        let Some(value_src) = src.strip_prefix('=') else {
+            attrs.push((name, ""));
+            continue;
+        };
+        let value_src = value_src.trim_start();
+        let (value, rest) = match value_src.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value_src = &value_src[1..];
+                let end = value_src.find(quote).unwrap_or(value_src.len());
+                (&value_src[..end], value_src.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = value_src
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(value_src.len());
+                value_src.split_at(end)
+            }
+        };
+        attrs.push((name, value));
+        src = rest;
+    }
+}
+
+#[test]
+fn detects_document_encodings() {
+    // "café" in windows-1252
+    let latin1 = b"<p>caf\xe9</p>";
+    assert_eq!(decode_html(latin1, None), "<p>caf\u{e9}</p>");
+
+    // "日本" in Shift-JIS, declared by the HTTP header and by a meta element
+    let shift_jis = b"<p>\x93\xfa\x96\x7b</p>";
+    assert_eq!(
+        decode_html(shift_jis, Some("text/html; charset=\"Shift_JIS\"")),
+        "<p>\u{65e5}\u{672c}</p>"
+    );
+    let declared = [
+        b"<meta http-equiv=Content-Type content='text/html; charset=shift_jis'>".as_slice(),
+        shift_jis.as_slice(),
+    ]
+    .concat();
+    assert!(decode_html(&declared, None).ends_with("<p>\u{65e5}\u{672c}</p>"));
+
+    // A byte order mark takes precedence over the header, and is removed
+    let bom = b"\xef\xbb\xbfcaf\xc3\xa9";
+    assert_eq!(
+        decode_html(bom, Some("text/html; charset=windows-1252")),
+        "caf\u{e9}"
+    );
+}
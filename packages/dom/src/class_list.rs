@@ -0,0 +1,160 @@
+//! Adding and removing classes from elements (the equivalent of `Element.classList`)
+//!
+//! Changes are written back to the `class` attribute through [`Document::set_attribute`], so they are snapshotted and
+//! restyle only the elements whose selectors they affect.
+use html5ever::{local_name, namespace_url, ns, QualName};
+
+use crate::node::ElementNodeData;
+use crate::Document;
+
+impl ElementNodeData {
+    /// The classes in the element's `class` attribute
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.attr(local_name!("class"))
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes().any(|other| other == class)
+    }
+}
+
+/// The classes of an element, returned by [`Document::class_list`]
+pub struct ClassList<'doc> {
+    doc: &'doc mut Document,
+    node_id: usize,
+}
+
+impl Document {
+    /// The classes of an element, for adding and removing them
+    pub fn class_list(&mut self, node_id: usize) -> ClassList<'_> {
+        ClassList { doc: self, node_id }
+    }
+}
+
+impl ClassList<'_> {
+    pub fn contains(&self, class: &str) -> bool {
+        self.doc.nodes[self.node_id]
+            .element_data()
+            .is_some_and(|element_data| element_data.has_class(class))
+    }
+
+    /// The classes, in the order they appear in the `class` attribute
+    pub fn to_vec(&self) -> Vec<String> {
+        self.doc.nodes[self.node_id]
+            .element_data()
+            .map(|element_data| element_data.classes().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn add(&mut self, class: &str) {
+        if self.contains(class) {
+            return;
+        }
+        let mut classes = self.to_vec();
+        classes.push(class.to_string());
+        self.set_classes(classes);
+    }
+
+    pub fn remove(&mut self, class: &str) {
+        if !self.contains(class) {
+            return;
+        }
+        let mut classes = self.to_vec();
+        classes.retain(|other| other != class);
+        self.set_classes(classes);
+    }
+
+    /// Add the class if the element doesn't have it, otherwise remove it. Returns whether the element has the class
+    /// afterwards.
+    pub fn toggle(&mut self, class: &str) -> bool {
+        let add = !self.contains(class);
+        self.set(class, add);
+        add
+    }
+
+    /// Add or remove a class
+    pub fn set(&mut self, class: &str, present: bool) {
+        match present {
+            true => self.add(class),
+            false => self.remove(class),
+        }
+    }
+
+    /// Replace one class with another (if the element has it). Returns whether the class was replaced.
+    pub fn replace(&mut self, class: &str, new_class: &str) -> bool {
+        if !self.contains(class) {
+            return false;
+        }
+        let mut classes = self.to_vec();
+        let mut replaced = false;
+        classes.retain_mut(|other| {
+            if other != class && other != new_class {
+                return true;
+            }
+            // The new class takes the place of whichever came first
+            let keep = !replaced;
+            *other = new_class.to_string();
+            replaced = true;
+            keep
+        });
+        self.set_classes(classes);
+        true
+    }
+
+    fn set_classes(&mut self, classes: Vec<String>) {
+        let name = QualName::new(None, ns!(), local_name!("class"));
+        self.doc
+            .set_attribute(self.node_id, name, &classes.join(" "));
+    }
+}
+
+#[test]
+fn edits_classes() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><head><style>.wide { width: 200px }</style></head><body>\
+        <div id=target class='a  b'></div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+    let target = doc.get_element_by_id("target").unwrap();
+    let class_attr = |doc: &Document| {
+        doc.nodes[target]
+            .attr(local_name!("class"))
+            .map(str::to_string)
+    };
+
+    // Adding an existing class leaves the attribute as it was written
+    doc.class_list(target).add("a");
+    assert_eq!(class_attr(&doc).as_deref(), Some("a  b"));
+    doc.class_list(target).add("c");
+    assert_eq!(class_attr(&doc).as_deref(), Some("a b c"));
+    assert_eq!(doc.class_list(target).to_vec(), ["a", "b", "c"]);
+
+    doc.class_list(target).remove("b");
+    doc.class_list(target).remove("missing");
+    assert_eq!(class_attr(&doc).as_deref(), Some("a c"));
+
+    assert!(doc.class_list(target).toggle("b"));
+    assert!(!doc.class_list(target).toggle("a"));
+    assert_eq!(class_attr(&doc).as_deref(), Some("c b"));
+
+    // Replacing with a class the element already has keeps the first of the two
+    assert!(doc.class_list(target).replace("b", "c"));
+    assert_eq!(class_attr(&doc).as_deref(), Some("c"));
+    assert!(!doc.class_list(target).replace("missing", "d"));
+    assert!(doc.class_list(target).replace("c", "d"));
+    assert_eq!(class_attr(&doc).as_deref(), Some("d"));
+    assert!(doc.nodes[target].element_data().unwrap().has_class("d"));
+
+    // Changes restyle the element
+    assert_ne!(doc.nodes[target].final_layout.size.width, 200.0);
+    doc.class_list(target).add("wide");
+    doc.resolve();
+    assert_eq!(doc.nodes[target].final_layout.size.width, 200.0);
+    doc.class_list(target).set("wide", false);
+    doc.resolve();
+    assert_eq!(doc.nodes[target].final_layout.size.width, 800.0);
+}
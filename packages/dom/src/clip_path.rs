@@ -0,0 +1,195 @@
+//! `clip-path` basic shapes
+//!
+//! `inset()`, `circle()`, `ellipse()` and `polygon()` clip paths are resolved against the node's border box into a
+//! [`ClipShape`], which the renderer clips the node and its descendants to and which hit testing uses to ignore points
+//! outside of the clipped area. Clip paths that reference SVG `<clipPath>` elements and `path()` shapes aren't
+//! supported, and leave the node unclipped.
+use style::values::computed::basic_shape::{BasicShape, ClipPath, ShapeRadius};
+use style::values::computed::{Length, LengthPercentage, Position};
+use style::values::generics::basic_shape::FillRule;
+use style::values::generics::position::GenericPositionOrAuto;
+
+use crate::Node;
+
+/// A clip path resolved in CSS pixels, relative to the top-left corner of the node's border box
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClipShape {
+    /// An inset rectangle with (uniformly) rounded corners
+    Inset {
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        radius: f32,
+    },
+    Circle {
+        cx: f32,
+        cy: f32,
+        radius: f32,
+    },
+    Ellipse {
+        cx: f32,
+        cy: f32,
+        rx: f32,
+        ry: f32,
+    },
+    Polygon {
+        points: Vec<(f32, f32)>,
+        /// Whether the polygon is filled with the `evenodd` rule rather than `nonzero`
+        even_odd: bool,
+    },
+}
+
+impl ClipShape {
+    /// Whether a point (relative to the node's border box) is within the clipped area
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            ClipShape::Inset {
+                x0,
+                y0,
+                x1,
+                y1,
+                radius,
+            } => {
+                if x < *x0 || x > *x1 || y < *y0 || y > *y1 {
+                    return false;
+                }
+                // Outside of the corners' arcs
+                let radius = radius.min((x1 - x0) / 2.0).min((y1 - y0) / 2.0);
+                let cx = x.clamp(x0 + radius, x1 - radius);
+                let cy = y.clamp(y0 + radius, y1 - radius);
+                (x - cx).powi(2) + (y - cy).powi(2) <= radius.powi(2)
+            }
+            ClipShape::Circle { cx, cy, radius } => {
+                (x - cx).powi(2) + (y - cy).powi(2) <= radius.powi(2)
+            }
+            ClipShape::Ellipse { cx, cy, rx, ry } => {
+                *rx > 0.0 && *ry > 0.0 && ((x - cx) / rx).powi(2) + ((y - cy) / ry).powi(2) <= 1.0
+            }
+            ClipShape::Polygon { points, even_odd } => {
+                let winding = winding_number(points, x, y);
+                match even_odd {
+                    true => winding % 2 != 0,
+                    false => winding != 0,
+                }
+            }
+        }
+    }
+}
+
+/// The number of times a polygon winds around a point
+fn winding_number(points: &[(f32, f32)], x: f32, y: f32) -> i32 {
+    let mut winding = 0;
+    for (index, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(index + 1) % points.len()];
+        let side = (x1 - x0) * (y - y0) - (x - x0) * (y1 - y0);
+        if y0 <= y && y1 > y && side > 0.0 {
+            winding += 1;
+        } else if y0 > y && y1 <= y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+impl Node {
+    /// The `clip-path` of the node resolved against its border box (if it has a supported one)
+    pub fn clip_shape(&self) -> Option<ClipShape> {
+        let style = self.primary_styles()?;
+        let ClipPath::Shape(shape, _) = &style.get_svg().clip_path else {
+            return None;
+        };
+        let size = self.final_layout.size;
+        let (width, height) = (size.width, size.height);
+        let resolve = |value: &LengthPercentage, basis: f32| value.resolve(Length::new(basis)).px();
+        let center = |position: &GenericPositionOrAuto<Position>| match position {
+            GenericPositionOrAuto::Position(position) => (
+                resolve(&position.horizontal, width),
+                resolve(&position.vertical, height),
+            ),
+            GenericPositionOrAuto::Auto => (width / 2.0, height / 2.0),
+        };
+        // Closest and farthest sides are measured separately for each axis
+        let radius = |radius: &ShapeRadius, center: f32, extent: f32, basis: f32| match radius {
+            ShapeRadius::Length(length) => resolve(&length.0, basis),
+            ShapeRadius::ClosestSide => center.min(extent - center).abs(),
+            ShapeRadius::FarthestSide => center.max(extent - center).abs(),
+        };
+
+        let shape = match &**shape {
+            BasicShape::Rect(inset) => {
+                let rect = &inset.rect;
+                ClipShape::Inset {
+                    x0: resolve(&rect.3, width),
+                    y0: resolve(&rect.0, height),
+                    x1: width - resolve(&rect.1, width),
+                    y1: height - resolve(&rect.2, height),
+                    radius: resolve(&inset.round.top_left.0.width.0, width),
+                }
+            }
+            BasicShape::Circle(circle) => {
+                let (cx, cy) = center(&circle.position);
+                // Percentages are relative to the box's normalized diagonal
+                let diagonal = (width.powi(2) + height.powi(2)).sqrt() / std::f32::consts::SQRT_2;
+                let radius = match &circle.radius {
+                    ShapeRadius::ClosestSide => radius(&circle.radius, cx, width, diagonal)
+                        .min(radius(&circle.radius, cy, height, diagonal)),
+                    ShapeRadius::FarthestSide => radius(&circle.radius, cx, width, diagonal)
+                        .max(radius(&circle.radius, cy, height, diagonal)),
+                    ShapeRadius::Length(_) => radius(&circle.radius, cx, width, diagonal),
+                };
+                ClipShape::Circle { cx, cy, radius }
+            }
+            BasicShape::Ellipse(ellipse) => {
+                let (cx, cy) = center(&ellipse.position);
+                ClipShape::Ellipse {
+                    cx,
+                    cy,
+                    rx: radius(&ellipse.semiaxis_x, cx, width, width),
+                    ry: radius(&ellipse.semiaxis_y, cy, height, height),
+                }
+            }
+            BasicShape::Polygon(polygon) => ClipShape::Polygon {
+                points: polygon
+                    .coordinates
+                    .iter()
+                    .map(|coord| (resolve(&coord.0, width), resolve(&coord.1, height)))
+                    .collect(),
+                even_odd: polygon.fill == FillRule::Evenodd,
+            },
+            _ => return None,
+        };
+        Some(shape)
+    }
+}
+
+#[test]
+fn clip_shapes_contain_points() {
+    let inset = ClipShape::Inset {
+        x0: 10.0,
+        y0: 10.0,
+        x1: 90.0,
+        y1: 90.0,
+        radius: 20.0,
+    };
+    assert!(inset.contains(50.0, 50.0));
+    assert!(!inset.contains(5.0, 50.0));
+    // The rounded corner cuts off the corner of the rectangle
+    assert!(!inset.contains(11.0, 11.0));
+
+    let triangle = ClipShape::Polygon {
+        points: vec![(50.0, 0.0), (100.0, 100.0), (0.0, 100.0)],
+        even_odd: false,
+    };
+    assert!(triangle.contains(50.0, 60.0));
+    assert!(!triangle.contains(5.0, 5.0));
+
+    let ellipse = ClipShape::Ellipse {
+        cx: 50.0,
+        cy: 25.0,
+        rx: 50.0,
+        ry: 25.0,
+    };
+    assert!(ellipse.contains(90.0, 25.0));
+    assert!(!ellipse.contains(90.0, 5.0));
+}
@@ -0,0 +1,237 @@
+//! Light and dark color schemes
+//!
+//! The embedder tells the document which color scheme the user prefers (typically following the OS theme), and the
+//! page declares which schemes it supports with `<meta name="color-scheme" content="light dark">`. When both agree
+//! on dark, a dark user agent stylesheet is applied on top of the default one, and form controls and the canvas are
+//! drawn with a dark palette, so that default buttons and inputs look correct in dark applications.
+//!
+//! Pages that don't declare a color scheme are always shown in the light scheme, as in browsers.
+use html5ever::local_name;
+use style::stylesheets::{DocumentStyleSheet, Origin};
+
+use crate::Document;
+
+/// A color scheme that a document can be shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// The color schemes supported by a page, from its `<meta name=color-scheme>` element
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SupportedColorSchemes {
+    pub light: bool,
+    pub dark: bool,
+    /// The page forbids the user agent from overriding its scheme (`only`)
+    pub only: bool,
+}
+
+impl SupportedColorSchemes {
+    /// Parse the `content` attribute of a `<meta name=color-scheme>` element
+    ///
+    /// Unknown keywords are ignored, and `normal` (or no keywords) supports only the light scheme.
+    pub fn parse(content: &str) -> Self {
+        let mut schemes = Self::default();
+        for keyword in content.split_ascii_whitespace() {
+            match keyword.to_ascii_lowercase().as_str() {
+                "light" => schemes.light = true,
+                "dark" => schemes.dark = true,
+                "only" => schemes.only = true,
+                _ => {}
+            }
+        }
+        schemes
+    }
+
+    /// The scheme used when the user prefers `preferred`
+    pub fn used(&self, preferred: ColorScheme) -> ColorScheme {
+        match (preferred, self.light, self.dark) {
+            (ColorScheme::Dark, _, true) => ColorScheme::Dark,
+            (ColorScheme::Light, false, true) => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        }
+    }
+}
+
+/// The colors that the renderer draws the canvas and form controls with, as RGBA
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlPalette {
+    /// The background behind the document
+    pub canvas: [u8; 4],
+    /// The caret and other text-colored details
+    pub text: [u8; 4],
+    /// The unfilled track of progress bars, meters and range inputs
+    pub track: [u8; 4],
+    /// The filled part of progress bars and range inputs
+    pub accent: [u8; 4],
+    /// The background of picker popups
+    pub popup: [u8; 4],
+    /// The outlines of popups and broken images
+    pub border: [u8; 4],
+    /// The translucent highlight of selected text without a `::selection` style
+    pub selection: [u8; 4],
+}
+
+impl ControlPalette {
+    pub const LIGHT: Self = Self {
+        canvas: [0xff, 0xff, 0xff, 0xff],
+        text: [0x00, 0x00, 0x00, 0xff],
+        track: [0xe6, 0xe6, 0xe6, 0xff],
+        accent: [0x00, 0x75, 0xff, 0xff],
+        popup: [0xff, 0xff, 0xff, 0xff],
+        border: [0xa0, 0xa0, 0xa0, 0xff],
+        selection: [0x00, 0x75, 0xff, 0x4d],
+    };
+
+    pub const DARK: Self = Self {
+        canvas: [0x12, 0x12, 0x12, 0xff],
+        text: [0xe8, 0xe8, 0xe8, 0xff],
+        track: [0x3b, 0x3b, 0x3b, 0xff],
+        accent: [0x3d, 0x9b, 0xff, 0xff],
+        popup: [0x2b, 0x2b, 0x2b, 0xff],
+        border: [0x6b, 0x6b, 0x6b, 0xff],
+        selection: [0x3d, 0x9b, 0xff, 0x66],
+    };
+}
+
+/// Applied at the user agent origin, after the other user agent stylesheets, while the document is shown in the dark scheme
+const DARK_UA_STYLESHEET: &str = r#"
+:root {
+    color: #e8e8e8;
+    background-color: #121212;
+}
+
+a:link {
+    color: #9e9eff;
+}
+
+a:visited {
+    color: #d0adf0;
+}
+
+input,
+textarea,
+select,
+button {
+    color: #e8e8e8;
+    background-color: #3b3b3b;
+    border-color: #858585;
+}
+
+input:disabled,
+textarea:disabled,
+select:disabled,
+button:disabled {
+    color: #8a8a8a;
+    background-color: #2b2b2b;
+}
+
+dialog,
+[popover] {
+    color: #e8e8e8;
+    background-color: #121212;
+}
+
+mark {
+    color: #121212;
+}
+"#;
+
+/// The color scheme state of a document
+#[derive(Default)]
+pub(crate) struct ColorSchemeState {
+    /// The scheme the embedder says the user prefers
+    pub(crate) preferred: ColorScheme,
+    /// The scheme the document was last shown in
+    pub(crate) used: ColorScheme,
+    /// The dark stylesheet, while it's applied
+    pub(crate) dark_sheet: Option<DocumentStyleSheet>,
+}
+
+impl Document {
+    /// Set the color scheme the user prefers. The document switches scheme on its next update if the page supports it.
+    pub fn set_preferred_color_scheme(&mut self, scheme: ColorScheme) {
+        if self.color_scheme.preferred != scheme {
+            self.color_scheme.preferred = scheme;
+            self.invalidate();
+        }
+    }
+
+    /// The color scheme the user prefers
+    pub fn preferred_color_scheme(&self) -> ColorScheme {
+        self.color_scheme.preferred
+    }
+
+    /// The color schemes supported by the document's last `<meta name=color-scheme>` element (if it has one)
+    pub fn supported_color_schemes(&self) -> Option<SupportedColorSchemes> {
+        self.get_elements_by_tag_name(local_name!("meta"))
+            .into_iter()
+            .filter_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                let name = element.attr(local_name!("name"))?;
+                if !name.trim().eq_ignore_ascii_case("color-scheme") {
+                    return None;
+                }
+                Some(SupportedColorSchemes::parse(
+                    element.attr(local_name!("content"))?,
+                ))
+            })
+            .last()
+    }
+
+    /// The color scheme the document is shown in
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.color_scheme.used
+    }
+
+    /// The colors the canvas and form controls are drawn with in the document's color scheme
+    pub fn control_palette(&self) -> ControlPalette {
+        match self.color_scheme.used {
+            ColorScheme::Light => ControlPalette::LIGHT,
+            ColorScheme::Dark => ControlPalette::DARK,
+        }
+    }
+
+    /// Apply or remove the dark user agent stylesheet if the scheme the document is shown in has changed
+    pub(crate) fn flush_color_scheme(&mut self) {
+        let used = self
+            .supported_color_schemes()
+            .unwrap_or_default()
+            .used(self.color_scheme.preferred);
+        if used == self.color_scheme.used {
+            return;
+        }
+        self.color_scheme.used = used;
+
+        if let Some(sheet) = self.color_scheme.dark_sheet.take() {
+            self.stylist.remove_stylesheet(sheet, &self.guard.read());
+        }
+        if used == ColorScheme::Dark {
+            let sheet = self.parse_stylesheet(DARK_UA_STYLESHEET, Origin::UserAgent);
+            self.stylist
+                .append_stylesheet(sheet.clone(), &self.guard.read());
+            self.color_scheme.dark_sheet = Some(sheet);
+        }
+        self.stylist
+            .force_stylesheet_origins_dirty(Origin::UserAgent.into());
+        self.invalidate();
+    }
+}
+
+#[test]
+fn uses_dark_scheme_only_when_supported() {
+    let schemes = SupportedColorSchemes::parse("light dark");
+    assert_eq!(schemes.used(ColorScheme::Dark), ColorScheme::Dark);
+    assert_eq!(schemes.used(ColorScheme::Light), ColorScheme::Light);
+
+    // Pages without a color scheme are always light
+    let schemes = SupportedColorSchemes::default();
+    assert_eq!(schemes.used(ColorScheme::Dark), ColorScheme::Light);
+
+    // Dark-only pages are dark even when the user prefers light
+    let schemes = SupportedColorSchemes::parse("only dark");
+    assert!(schemes.only);
+    assert_eq!(schemes.used(ColorScheme::Light), ColorScheme::Dark);
+}
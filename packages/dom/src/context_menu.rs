@@ -0,0 +1,326 @@
+//! Context menus
+//!
+//! Right clicking an element dispatches a `contextmenu` event to it. If no listener handles the event, a context menu
+//! is opened at the pointer: its items come from the document's [`ContextMenuProvider`] (by default
+//! [`DefaultContextMenu`], which offers copy, select all and inspect), and it is laid out here in viewport coordinates
+//! and drawn by the renderer above the top layer, like the pickers.
+//!
+//! Blitz has no clipboard of its own: embedders provide one by implementing [`Clipboard`]. Without a clipboard,
+//! copying does nothing. Likewise, inspecting an element only records a request, which embedders take with
+//! [`Document::take_inspect_request`] to show the element in their devtools.
+use parley::layout::Alignment;
+
+use crate::node::TextLayout;
+use crate::{stylo_to_parley, Document};
+
+/// The horizontal padding either side of the label of a context menu item
+pub const CONTEXT_MENU_ITEM_PADDING: f32 = 12.0;
+const ITEM_PADDING_Y: f32 = 4.0;
+const MENU_PADDING: f32 = 4.0;
+const MIN_MENU_WIDTH: f32 = 120.0;
+
+/// A platform clipboard, provided by the embedder
+pub trait Clipboard: Send {
+    fn write_text(&mut self, text: &str);
+}
+
+/// An item of a context menu
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContextMenuItem {
+    pub label: String,
+    /// Identifies the item to [`ContextMenuProvider::perform`]
+    pub action: String,
+    /// Disabled items are shown but can't be chosen
+    pub enabled: bool,
+}
+
+impl ContextMenuItem {
+    pub fn new(label: impl Into<String>, action: impl Into<String>, enabled: bool) -> Self {
+        Self {
+            label: label.into(),
+            action: action.into(),
+            enabled,
+        }
+    }
+}
+
+/// Decides what a context menu contains and what its items do. Embedders can replace the default menu with
+/// [`Document::set_context_menu_provider`].
+pub trait ContextMenuProvider: Send {
+    /// The items of the menu opened on `target`. No menu is opened if there are none.
+    fn items(&self, doc: &Document, target: usize) -> Vec<ContextMenuItem>;
+
+    /// Perform the action of the chosen item
+    fn perform(&mut self, doc: &mut Document, target: usize, action: &str);
+}
+
+/// The default context menu: copy the selection, select all of the editable text, and inspect the element
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultContextMenu;
+
+impl ContextMenuProvider for DefaultContextMenu {
+    fn items(&self, doc: &Document, target: usize) -> Vec<ContextMenuItem> {
+        let has_selection = doc.selection().is_some_and(|(start, end)| start != end);
+        vec![
+            ContextMenuItem::new("Copy", "copy", has_selection),
+            ContextMenuItem::new(
+                "Select All",
+                "select-all",
+                doc.editing_host(target).is_some(),
+            ),
+            ContextMenuItem::new("Inspect", "inspect", true),
+        ]
+    }
+
+    fn perform(&mut self, doc: &mut Document, target: usize, action: &str) {
+        match action {
+            "copy" => {
+                let text = doc.selected_text();
+                if let Some(clipboard) = doc.clipboard.as_mut() {
+                    clipboard.write_text(&text);
+                }
+            }
+            "select-all" => {
+                if let Some(host) = doc.editing_host(target) {
+                    doc.select_all(host);
+                }
+            }
+            "inspect" => doc.request_inspect(target),
+            _ => {}
+        }
+    }
+}
+
+/// An item of an open context menu
+pub struct ContextMenuRow {
+    /// Position relative to the menu
+    pub location: taffy::Point<f32>,
+    pub size: taffy::Size<f32>,
+    pub item: ContextMenuItem,
+    pub label: TextLayout,
+}
+
+/// A context menu that is currently showing
+pub struct OpenContextMenu {
+    /// The element the menu was opened on
+    pub target: usize,
+    /// Position relative to the viewport
+    pub location: taffy::Point<f32>,
+    pub size: taffy::Size<f32>,
+    pub rows: Vec<ContextMenuRow>,
+}
+
+impl Document {
+    /// Replace the provider of context menus
+    pub fn set_context_menu_provider(&mut self, provider: Box<dyn ContextMenuProvider>) {
+        self.context_menu_provider = Some(provider);
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = Some(clipboard);
+    }
+
+    /// Ask the embedder to show an element in its devtools
+    pub fn request_inspect(&mut self, node_id: usize) {
+        self.inspect_request = Some(node_id);
+    }
+
+    /// The element that was last asked to be inspected, if it hasn't been taken yet
+    pub fn take_inspect_request(&mut self) -> Option<usize> {
+        self.inspect_request
+            .take()
+            .filter(|node_id| self.nodes.contains(*node_id))
+    }
+
+    /// The open context menu (if any)
+    pub fn open_context_menu(&self) -> Option<&OpenContextMenu> {
+        self.context_menu.as_ref()
+    }
+
+    /// Close the open context menu. Returns false if there wasn't one.
+    pub fn close_context_menu(&mut self) -> bool {
+        self.context_menu.take().is_some()
+    }
+
+    /// Open a context menu on an element at a position relative to the viewport. Returns false if the menu has no
+    /// items.
+    pub fn show_context_menu(&mut self, target: usize, x: f32, y: f32) -> bool {
+        self.context_menu = None;
+        let items = match &self.context_menu_provider {
+            Some(provider) => provider.items(self, target),
+            None => return false,
+        };
+        if items.is_empty() {
+            return false;
+        }
+
+        // Items are stacked vertically, and are as wide as the widest label
+        let labels: Vec<TextLayout> = items
+            .iter()
+            .map(|item| self.build_context_menu_label(target, &item.label))
+            .collect();
+        let width = labels
+            .iter()
+            .map(|label| label.layout.width() / self.scale + 2.0 * CONTEXT_MENU_ITEM_PADDING)
+            .fold(MIN_MENU_WIDTH, f32::max);
+        let mut rows = Vec::new();
+        let mut row_y = MENU_PADDING;
+        for (item, label) in items.into_iter().zip(labels) {
+            let height = label.layout.height() / self.scale + 2.0 * ITEM_PADDING_Y;
+            rows.push(ContextMenuRow {
+                location: taffy::Point { x: 0.0, y: row_y },
+                size: taffy::Size { width, height },
+                item,
+                label,
+            });
+            row_y += height;
+        }
+        let size = taffy::Size {
+            width,
+            height: row_y + MENU_PADDING,
+        };
+
+        // Keep the menu within the viewport
+        let viewport = self.stylist.device().au_viewport_size();
+        let x = x.min(viewport.width.to_f32_px() - size.width).max(0.0);
+        let y = y.min(viewport.height.to_f32_px() - size.height).max(0.0);
+
+        self.context_menu = Some(OpenContextMenu {
+            target,
+            location: taffy::Point { x, y },
+            size,
+            rows,
+        });
+        true
+    }
+
+    /// Handle a click while a context menu is open. Takes a position relative to the viewport. The menu is closed
+    /// unless a disabled item was clicked, and the action of an enabled item is performed.
+    ///
+    /// Returns false if the click was outside of the menu.
+    pub fn handle_context_menu_click(&mut self, x: f32, y: f32) -> bool {
+        let Some(menu) = &self.context_menu else {
+            return false;
+        };
+        let x = x - menu.location.x;
+        let y = y - menu.location.y;
+        if x < 0.0 || y < 0.0 || x > menu.size.width || y > menu.size.height {
+            self.close_context_menu();
+            return false;
+        }
+
+        let target = menu.target;
+        let Some(row) = menu
+            .rows
+            .iter()
+            .find(|row| y >= row.location.y && y <= row.location.y + row.size.height)
+        else {
+            return true;
+        };
+        if !row.item.enabled {
+            return true;
+        }
+        let action = row.item.action.clone();
+        self.close_context_menu();
+
+        // The provider is taken out of the document so that it can change it
+        if let Some(mut provider) = self.context_menu_provider.take() {
+            if self.nodes.contains(target) {
+                provider.perform(self, target, &action);
+            }
+            self.context_menu_provider.get_or_insert(provider);
+        }
+        true
+    }
+
+    /// Shape the label of a context menu item using the text styles of the target
+    fn build_context_menu_label(&mut self, node_id: usize, text: &str) -> TextLayout {
+        let style = self.nodes[node_id]
+            .primary_styles()
+            .map(|style| stylo_to_parley::style(&style))
+            .unwrap_or_default();
+
+        let mut builder = self
+            .layout_ctx
+            .tree_builder(&mut self.font_ctx, self.scale, &style);
+        builder.push_text(text);
+        let (mut layout, text) = builder.build();
+        layout.break_all_lines(None);
+        layout.align(None, Alignment::Start);
+
+        TextLayout {
+            text,
+            layout,
+            line_break_bucket: Some(None),
+        }
+    }
+}
+
+#[test]
+fn performs_menu_actions() {
+    use crate::test_util::parse_test_document;
+    use std::sync::{Arc, Mutex};
+
+    struct TestClipboard(Arc<Mutex<String>>);
+    impl Clipboard for TestClipboard {
+        fn write_text(&mut self, text: &str) {
+            *self.0.lock().unwrap() = text.to_string();
+        }
+    }
+
+    let html = "<!DOCTYPE html><html><body>\
+        <div id=plain>Plain</div><div id=editable contenteditable>Some text</div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+    let copied = Arc::new(Mutex::new(String::new()));
+    doc.set_clipboard(Box::new(TestClipboard(copied.clone())));
+    let (plain, editable) = (
+        doc.get_element_by_id("plain").unwrap(),
+        doc.get_element_by_id("editable").unwrap(),
+    );
+
+    // Clicks the middle of the item with an action
+    let choose = |doc: &mut Document, action: &str| {
+        let menu = doc.open_context_menu().unwrap();
+        let row = menu
+            .rows
+            .iter()
+            .find(|row| row.item.action == action)
+            .unwrap();
+        let x = menu.location.x + row.location.x + row.size.width / 2.0;
+        let y = menu.location.y + row.location.y + row.size.height / 2.0;
+        doc.handle_context_menu_click(x, y)
+    };
+    let enabled = |doc: &Document| -> Vec<bool> {
+        let menu = doc.open_context_menu().unwrap();
+        menu.rows.iter().map(|row| row.item.enabled).collect()
+    };
+
+    // Outside of editable text only inspecting is possible. Disabled items leave the menu open.
+    assert!(doc.show_context_menu(plain, 10.0, 10.0));
+    assert_eq!(enabled(&doc), [false, false, true]);
+    assert!(choose(&mut doc, "copy"));
+    assert!(doc.open_context_menu().is_some());
+
+    // Inspecting is handed to the embedder
+    assert!(choose(&mut doc, "inspect"));
+    assert!(doc.open_context_menu().is_none());
+    assert_eq!(doc.take_inspect_request(), Some(plain));
+    assert_eq!(doc.take_inspect_request(), None);
+
+    // Editable text can be selected, and then copied
+    assert!(doc.show_context_menu(editable, 10.0, 10.0));
+    assert_eq!(enabled(&doc), [false, true, true]);
+    assert!(choose(&mut doc, "select-all"));
+    assert_eq!(doc.selected_text(), "Some text");
+    assert!(doc.show_context_menu(editable, 10.0, 10.0));
+    assert_eq!(enabled(&doc), [true, true, true]);
+    assert!(choose(&mut doc, "copy"));
+    assert_eq!(*copied.lock().unwrap(), "Some text");
+
+    // Clicking outside of the menu closes it without doing anything
+    assert!(doc.show_context_menu(plain, 10.0, 10.0));
+    assert!(!doc.handle_context_menu_click(700.0, 500.0));
+    assert!(doc.open_context_menu().is_none());
+}
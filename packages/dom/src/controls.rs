@@ -0,0 +1,266 @@
+//! Value-driven form controls: `<progress>`, `<meter>` and `<input type=range>`
+//!
+//! <https://html.spec.whatwg.org/multipage/form-elements.html#the-progress-element>
+//! <https://html.spec.whatwg.org/multipage/form-elements.html#the-meter-element>
+//! <https://html.spec.whatwg.org/multipage/input.html#range-state-(type=range)>
+//!
+//! These elements are replaced elements: their children (fallback content) are never rendered. Instead the
+//! renderer draws a track, a fill and (for range inputs) a thumb based on the values computed here. A range
+//! input's current value is stored in its `value` attribute, and is updated by dragging the thumb.
+use html5ever::{local_name, namespace_url, ns, QualName};
+
+use crate::Document;
+
+/// The intrinsic size of a `<progress>` element
+pub const DEFAULT_PROGRESS_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 160.0,
+    height: 16.0,
+};
+
+/// The intrinsic size of a `<meter>` element
+pub const DEFAULT_METER_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 80.0,
+    height: 16.0,
+};
+
+/// The intrinsic size of an `<input type=range>` element
+pub const DEFAULT_RANGE_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 129.0,
+    height: 16.0,
+};
+
+/// The maximum radius of the thumb of an `<input type=range>`
+pub const RANGE_THUMB_RADIUS: f32 = 8.0;
+
+/// Which kind of value-driven control an element is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlKind {
+    Progress,
+    Meter,
+    Range,
+}
+
+impl ControlKind {
+    pub fn default_size(self) -> taffy::Size<f32> {
+        match self {
+            ControlKind::Progress => DEFAULT_PROGRESS_SIZE,
+            ControlKind::Meter => DEFAULT_METER_SIZE,
+            ControlKind::Range => DEFAULT_RANGE_SIZE,
+        }
+    }
+}
+
+/// Which region of a meter's range its value falls in. Determines the color of the fill.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeterRegion {
+    Optimum,
+    SubOptimum,
+    EvenLessGood,
+}
+
+/// The resolved value of an `<input type=range>`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RangeValue {
+    pub min: f64,
+    pub max: f64,
+    /// `None` if the step is `any`
+    pub step: Option<f64>,
+    pub value: f64,
+}
+
+impl RangeValue {
+    /// The position of the value along the track (from 0.0 to 1.0)
+    pub fn fraction(&self) -> f64 {
+        if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+
+    /// Clamp a value to the range and snap it to the nearest step
+    pub fn sanitize(&self, value: f64) -> f64 {
+        let mut value = value.clamp(self.min, self.max.max(self.min));
+        if let Some(step) = self.step {
+            value = self.min + ((value - self.min) / step).round() * step;
+            // Rounding up may have taken the value past the maximum
+            if value > self.max {
+                value -= step;
+            }
+        }
+        value
+    }
+}
+
+fn parse_f64(value: Option<&str>) -> Option<f64> {
+    value
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+}
+
+impl Document {
+    /// The kind of value-driven control a node is (if any)
+    pub fn control_kind(&self, node_id: usize) -> Option<ControlKind> {
+        let element_data = self.nodes[node_id].element_data()?;
+        match element_data.name.local.as_ref() {
+            "progress" => Some(ControlKind::Progress),
+            "meter" => Some(ControlKind::Meter),
+            "input"
+                if element_data
+                    .attr(local_name!("type"))
+                    .is_some_and(|ty| ty.eq_ignore_ascii_case("range")) =>
+            {
+                Some(ControlKind::Range)
+            }
+            _ => None,
+        }
+    }
+
+    /// The position of a `<progress>` element (from 0.0 to 1.0), or `None` if it is indeterminate
+    pub fn progress_position(&self, node_id: usize) -> Option<f64> {
+        let node = &self.nodes[node_id];
+        let value = parse_f64(node.attr(local_name!("value")))?;
+        let max = parse_f64(node.attr(local_name!("max")))
+            .filter(|max| *max > 0.0)
+            .unwrap_or(1.0);
+        Some(value.clamp(0.0, max) / max)
+    }
+
+    /// The position of a `<meter>` element's value (from 0.0 to 1.0) and the region that it falls in
+    pub fn meter_state(&self, node_id: usize) -> (f64, MeterRegion) {
+        let node = &self.nodes[node_id];
+        let attr = |name| parse_f64(node.attr(name));
+
+        let min = attr(local_name!("min")).unwrap_or(0.0);
+        let max = attr(local_name!("max")).unwrap_or(1.0).max(min);
+        let value = attr(local_name!("value")).unwrap_or(0.0).clamp(min, max);
+        let low = attr(local_name!("low")).unwrap_or(min).clamp(min, max);
+        let high = attr(local_name!("high")).unwrap_or(max).clamp(low, max);
+        let optimum = attr(local_name!("optimum"))
+            .unwrap_or((min + max) / 2.0)
+            .clamp(min, max);
+
+        let region = if optimum < low {
+            if value <= low {
+                MeterRegion::Optimum
+            } else if value <= high {
+                MeterRegion::SubOptimum
+            } else {
+                MeterRegion::EvenLessGood
+            }
+        } else if optimum > high {
+            if value >= high {
+                MeterRegion::Optimum
+            } else if value >= low {
+                MeterRegion::SubOptimum
+            } else {
+                MeterRegion::EvenLessGood
+            }
+        } else if value >= low && value <= high {
+            MeterRegion::Optimum
+        } else {
+            MeterRegion::SubOptimum
+        };
+
+        let fraction = if max > min {
+            (value - min) / (max - min)
+        } else {
+            0.0
+        };
+
+        (fraction, region)
+    }
+
+    /// The resolved value of an `<input type=range>`
+    pub fn range_value(&self, node_id: usize) -> RangeValue {
+        let node = &self.nodes[node_id];
+        let attr = |name| parse_f64(node.attr(name));
+
+        let min = attr(local_name!("min")).unwrap_or(0.0);
+        let max = attr(local_name!("max")).unwrap_or(100.0);
+        let step = match node.attr(local_name!("step")) {
+            Some(step) if step.trim().eq_ignore_ascii_case("any") => None,
+            step => Some(parse_f64(step).filter(|step| *step > 0.0).unwrap_or(1.0)),
+        };
+
+        let mut range = RangeValue {
+            min,
+            max,
+            step,
+            value: min,
+        };
+        let default_value = if max < min {
+            min
+        } else {
+            min + (max - min) / 2.0
+        };
+        range.value = range.sanitize(attr(local_name!("value")).unwrap_or(default_value));
+        range
+    }
+
+    /// Set the value of an `<input type=range>` from a position along its track (from 0.0 to 1.0).
+    ///
+    /// Returns true if the value changed.
+    pub fn set_range_fraction(&mut self, node_id: usize, fraction: f64) -> bool {
+        let range = self.range_value(node_id);
+        let value = range.sanitize(range.min + fraction.clamp(0.0, 1.0) * (range.max - range.min));
+        if value == range.value {
+            return false;
+        }
+
+        self.set_attribute(
+            node_id,
+            QualName::new(None, ns!(), local_name!("value")),
+            &value.to_string(),
+        );
+        true
+    }
+
+    /// Start dragging the thumb of an `<input type=range>`. Takes the x position of the pointer in document
+    /// coordinates.
+    ///
+    /// Returns false if the node is not an enabled range input. Otherwise the value is updated to match the
+    /// pointer position and the node is tracked until [`Document::end_range_drag`] is called.
+    pub fn begin_range_drag(&mut self, node_id: usize, x: f32) -> bool {
+        if self.control_kind(node_id) != Some(ControlKind::Range)
+            || self.nodes[node_id].is_disabled()
+        {
+            return false;
+        }
+        self.active_range_drag = Some(node_id);
+        self.update_range_drag(x);
+        true
+    }
+
+    /// Move the thumb of the range input being dragged (if any) to the pointer's x position.
+    ///
+    /// Returns the range input's node id if its value changed.
+    pub fn update_range_drag(&mut self, x: f32) -> Option<usize> {
+        let node_id = self.active_range_drag?;
+        if !self.nodes.contains(node_id) {
+            self.active_range_drag = None;
+            return None;
+        }
+
+        // The track spans the padding box, inset on each side by the radius of the thumb
+        let layout = self.nodes[node_id].final_layout;
+        let height = layout.size.height - layout.border.top - layout.border.bottom;
+        let thumb_radius = (height / 2.0).min(RANGE_THUMB_RADIUS);
+        let left = self.absolute_position(node_id).x + layout.border.left + thumb_radius;
+        let width =
+            layout.size.width - layout.border.left - layout.border.right - 2.0 * thumb_radius;
+        let fraction = if width > 0.0 { (x - left) / width } else { 0.0 };
+
+        self.set_range_fraction(node_id, fraction as f64)
+            .then_some(node_id)
+    }
+
+    /// Stop dragging. Returns the range input that was being dragged (if any).
+    pub fn end_range_drag(&mut self) -> Option<usize> {
+        self.active_range_drag.take()
+    }
+
+    pub fn active_range_drag(&self) -> Option<usize> {
+        self.active_range_drag
+    }
+}
@@ -0,0 +1,439 @@
+//! Storing the cookies set by servers and attaching them to later requests
+//!
+//! Each [`NetworkProvider`](crate::network::NetworkProvider) has a [`CookieJar`], which stores the cookies of every
+//! `Set-Cookie` header it receives and sends the matching ones with each request. Cookies that expire are removed, and
+//! `Secure` cookies are only sent over HTTPS. `SameSite=Strict` cookies are only sent with requests made by documents
+//! of the same site, and `SameSite=Lax` cookies also with navigations to the site from other sites.
+//!
+//! Session cookies only last as long as the jar, but embedders can [save](CookieJar::save) the persistent ones and
+//! [load](CookieJar::load) them again the next time they start.
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+/// Whether a cookie is sent with requests made by documents of other sites
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only sent with requests made by documents of the same site
+    Strict,
+    /// Also sent when navigating to the site from another site
+    Lax,
+    /// Sent with all requests
+    None,
+}
+
+/// A cookie set by a server
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The domain the cookie is sent to
+    pub domain: String,
+    /// Whether the cookie is only sent to `domain` itself, not its subdomains (because the server didn't set a
+    /// `Domain` attribute)
+    pub host_only: bool,
+    /// The path the cookie is sent to (including its descendants)
+    pub path: String,
+    /// When the cookie expires. Session cookies, which don't expire, are discarded with the jar.
+    pub expires: Option<SystemTime>,
+    /// Whether the cookie is only sent over HTTPS
+    pub secure: bool,
+    /// Whether the cookie is hidden from scripts
+    pub http_only: bool,
+    pub same_site: SameSite,
+}
+
+impl Cookie {
+    /// Parse the value of a `Set-Cookie` header received in response to a request for `url`. Returns `None` if the
+    /// header is invalid, or sets a cookie that `url` isn't allowed to set.
+    pub fn parse(set_cookie: &str, url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut parts = set_cookie.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: SameSite::Lax,
+        };
+        let mut max_age = None;
+        for attr in parts {
+            let (attr_name, attr_value) = match attr.split_once('=') {
+                Some((attr_name, attr_value)) => (attr_name.trim(), attr_value.trim()),
+                None => (attr.trim(), ""),
+            };
+            match attr_name.to_ascii_lowercase().as_str() {
+                "domain" => {
+                    let domain = attr_value.trim_start_matches('.').to_ascii_lowercase();
+                    // Without a public suffix list, top level domains are the only suffixes that can be rejected
+                    if !domain_match(&host, &domain) || !domain.contains('.') && domain != host {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if attr_value.starts_with('/') => cookie.path = attr_value.to_string(),
+                "expires" => {
+                    if let Some(expires) = parse_http_date(attr_value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = attr_value.parse::<i64>() {
+                        max_age = Some(seconds);
+                    }
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match attr_value.to_ascii_lowercase().as_str() {
+                        "strict" => SameSite::Strict,
+                        "none" => SameSite::None,
+                        _ => SameSite::Lax,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // `Max-Age` takes precedence over `Expires`
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(match u64::try_from(seconds) {
+                Ok(seconds) if seconds > 0 => SystemTime::now() + Duration::from_secs(seconds),
+                _ => UNIX_EPOCH,
+            });
+        }
+
+        // Only HTTPS responses can set secure cookies, and `SameSite=None` cookies must be secure
+        let is_https = url.scheme() == "https";
+        if (cookie.secure || cookie.same_site == SameSite::None) && !(cookie.secure && is_https) {
+            return None;
+        }
+
+        Some(cookie)
+    }
+
+    /// Whether the cookie has expired at `now`
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether the cookie is sent with a request for `url`, made by a document at `document_url` (or by the embedder
+    /// if it's `None`). `navigation` is whether the request loads a document that `document_url` navigates to, rather
+    /// than one of its subresources.
+    pub fn matches(&self, url: &Url, document_url: Option<&Url>, navigation: bool) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        let domain_matches = match self.host_only {
+            true => host == self.domain,
+            false => domain_match(&host, &self.domain),
+        };
+        let same_site = document_url.map_or(true, |document_url| is_same_site(url, document_url));
+        domain_matches
+            && path_match(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && match self.same_site {
+                SameSite::Strict => same_site,
+                SameSite::Lax => same_site || navigation,
+                SameSite::None => true,
+            }
+    }
+}
+
+/// The cookies a network provider has received
+#[derive(Clone, Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cookies in the jar, including expired cookies that haven't been removed yet
+    pub fn cookies(&self) -> &[Cookie] {
+        &self.cookies
+    }
+
+    /// Add a cookie to the jar, replacing the cookie with the same name, domain and path. An expired cookie removes
+    /// the cookie it replaces.
+    pub fn insert(&mut self, cookie: Cookie) {
+        self.cookies.retain(|existing| {
+            existing.name != cookie.name
+                || existing.domain != cookie.domain
+                || existing.path != cookie.path
+        });
+        if !cookie.is_expired(SystemTime::now()) {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// Store the cookie set by a `Set-Cookie` header received in response to a request for `url`
+    pub fn set_cookie(&mut self, set_cookie: &str, url: &Url) {
+        if let Some(cookie) = Cookie::parse(set_cookie, url) {
+            self.insert(cookie);
+        }
+    }
+
+    /// The value of the `Cookie` header for a request for `url`, made by a document at `document_url` (or by the
+    /// embedder if it's `None`). `navigation` is whether the request is a navigation (see [`Cookie::matches`]).
+    /// Returns `None` if no cookies match.
+    pub fn cookie_header(
+        &mut self,
+        url: &Url,
+        document_url: Option<&Url>,
+        navigation: bool,
+    ) -> Option<String> {
+        self.remove_expired();
+        let mut cookies: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url, document_url, navigation))
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        // Cookies with more specific paths are listed first
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Remove the cookies that have expired
+    pub fn remove_expired(&mut self) {
+        let now = SystemTime::now();
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    /// Remove all the cookies
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// Write the persistent (not session) cookies to `writer`, one per line, to be read back with [`CookieJar::load`]
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        let now = SystemTime::now();
+        for cookie in &self.cookies {
+            let Some(expires) = cookie.expires.filter(|_| !cookie.is_expired(now)) else {
+                continue;
+            };
+            let expires = expires.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let same_site = match cookie.same_site {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            };
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                cookie.domain,
+                cookie.host_only,
+                cookie.path,
+                cookie.secure,
+                cookie.http_only,
+                same_site,
+                expires.as_secs(),
+                cookie.name,
+                cookie.value
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read cookies written by [`CookieJar::save`]. Invalid lines and expired cookies are skipped.
+    pub fn load(reader: impl BufRead) -> io::Result<Self> {
+        let mut jar = Self::new();
+        for line in reader.lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.splitn(9, '\t').collect();
+            let [domain, host_only, path, secure, http_only, same_site, expires, name, value] =
+                fields[..]
+            else {
+                continue;
+            };
+            let (Ok(host_only), Ok(secure), Ok(http_only), Ok(expires)) = (
+                host_only.parse(),
+                secure.parse(),
+                http_only.parse(),
+                expires.parse(),
+            ) else {
+                continue;
+            };
+            jar.insert(Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.to_string(),
+                host_only,
+                path: path.to_string(),
+                expires: Some(UNIX_EPOCH + Duration::from_secs(expires)),
+                secure,
+                http_only,
+                same_site: match same_site {
+                    "Strict" => SameSite::Strict,
+                    "None" => SameSite::None,
+                    _ => SameSite::Lax,
+                },
+            });
+        }
+        Ok(jar)
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+/// Whether `path` is `cookie_path` or one of its descendants
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || path
+            .strip_prefix(cookie_path)
+            .is_some_and(|rest| cookie_path.ends_with('/') || rest.starts_with('/'))
+}
+
+/// The path of a cookie that doesn't set one: the directory of the URL's path
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(end) => url.path()[..end].to_string(),
+    }
+}
+
+/// Whether two URLs belong to the same site. Without a public suffix list, sites are approximated by the last two
+/// labels of their hosts.
+fn is_same_site(a: &Url, b: &Url) -> bool {
+    fn site(url: &Url) -> Option<String> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        if url
+            .host()
+            .is_some_and(|host| !matches!(host, url::Host::Domain(_)))
+        {
+            return Some(host);
+        }
+        let labels: Vec<&str> = host.rsplitn(3, '.').collect();
+        Some(match labels.as_slice() {
+            [tld, domain, ..] => format!("{}.{}", domain, tld),
+            _ => host,
+        })
+    }
+    site(a).is_some() && site(a) == site(b)
+}
+
+/// Parse a date in the formats used by the `Expires` attribute, e.g. `Wed, 21 Oct 2015 07:28:00 GMT` or
+/// `Wednesday, 21-Oct-15 07:28:00 GMT`
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    // Skip the day of the week, leaving the day, month, year, hours, minutes and seconds
+    let mut tokens = date
+        .split([' ', ',', '-', ':'])
+        .filter(|token| !token.is_empty())
+        .skip(1);
+    let day: u64 = tokens.next()?.parse().ok()?;
+    let month = tokens.next()?.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|name| month.starts_with(name))? as u64 + 1;
+    let year: u64 = match tokens.next()?.parse().ok()? {
+        year @ 0..=69 => year + 2000,
+        year @ 70..=99 => year + 1900,
+        year => year,
+    };
+    let hours: u64 = tokens.next()?.parse().ok()?;
+    let minutes: u64 = tokens.next()?.parse().ok()?;
+    let seconds: u64 = tokens.next()?.parse().ok()?;
+    if year < 1970 || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    // Days since the epoch of the civil date (see http://howardhinnant.github.io/date_algorithms.html)
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hours * 3600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+#[test]
+fn stores_and_sends_cookies() {
+    let url = Url::parse("https://www.example.com/account/login").unwrap();
+    let mut jar = CookieJar::new();
+    jar.set_cookie("session=abc; Secure; HttpOnly", &url);
+    jar.set_cookie(
+        "theme=dark; Domain=example.com; Path=/; Expires=Wed, 21 Oct 2099 07:28:00 GMT",
+        &url,
+    );
+    // Cookies can't be set for other domains
+    jar.set_cookie("tracker=1; Domain=other.com", &url);
+
+    let page = Url::parse("https://www.example.com/account/settings").unwrap();
+    assert_eq!(
+        jar.cookie_header(&page, None, false).as_deref(),
+        Some("session=abc; theme=dark")
+    );
+    let subdomain = Url::parse("http://static.example.com/style.css").unwrap();
+    assert_eq!(
+        jar.cookie_header(&subdomain, None, false).as_deref(),
+        Some("theme=dark")
+    );
+
+    // Only persistent cookies are saved
+    let mut saved = Vec::new();
+    jar.save(&mut saved).unwrap();
+    let loaded = CookieJar::load(saved.as_slice()).unwrap();
+    assert_eq!(loaded.cookies().len(), 1);
+    assert_eq!(loaded.cookies()[0], jar.cookies()[1]);
+
+    // An expired cookie removes the cookie it replaces
+    jar.set_cookie("theme=; Domain=example.com; Path=/; Max-Age=0", &url);
+    assert_eq!(jar.cookie_header(&subdomain, None, false), None);
+
+    // Cookies are sent to other sites by their `SameSite` attribute (`Lax` by default), and navigations also send
+    // `Lax` cookies
+    jar.set_cookie("strict=1; SameSite=Strict", &url);
+    jar.set_cookie("lax=1; SameSite=Lax", &url);
+    jar.set_cookie("none=1; SameSite=None; Secure", &url);
+    let other_site = Url::parse("https://other.com/").unwrap();
+    assert_eq!(
+        jar.cookie_header(&page, Some(&other_site), false)
+            .as_deref(),
+        Some("none=1")
+    );
+    assert_eq!(
+        jar.cookie_header(&page, Some(&other_site), true).as_deref(),
+        Some("session=abc; lax=1; none=1")
+    );
+    assert_eq!(
+        jar.cookie_header(&page, Some(&url), false).as_deref(),
+        Some("session=abc; strict=1; lax=1; none=1")
+    );
+}
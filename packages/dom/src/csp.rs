@@ -0,0 +1,423 @@
+//! Content Security Policy enforcement for subresources
+//!
+//! Policies come from the `Content-Security-Policy` header of the document's response (passed in by the embedder with
+//! [`Document::add_content_security_policy`]) and from `<meta http-equiv=Content-Security-Policy>` elements. The
+//! `img-src` and `style-src` directives (falling back to `default-src`) restrict where images and stylesheets are
+//! loaded from, and whether inline `<style>` elements are applied. A resource is only loaded if every policy allows
+//! it. (Blitz doesn't load web fonts, so `font-src` has nothing to restrict.)
+//!
+//! Blocked resources are passed to the embedder's violation handler (set with
+//! [`Document::set_csp_violation_handler`]), or logged as warnings with `tracing` if there isn't one.
+use std::fmt;
+
+use url::Url;
+
+use crate::Document;
+
+/// The directives that restrict subresources
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CspDirective {
+    /// `img-src`: images, including the posters of `<video>` elements
+    ImgSrc,
+    /// `style-src`: linked stylesheets and `<style>` elements
+    StyleSrc,
+}
+
+impl CspDirective {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ImgSrc => "img-src",
+            Self::StyleSrc => "style-src",
+        }
+    }
+}
+
+/// A source expression in a directive's source list
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    /// `*`
+    Any,
+    /// `'self'`
+    SelfOrigin,
+    /// `'unsafe-inline'`
+    UnsafeInline,
+    /// `'nonce-...'`
+    Nonce(String),
+    /// `https:`, `data:`, etc.
+    Scheme(String),
+    /// `https://*.example.com:443/path`
+    Host {
+        scheme: Option<String>,
+        host: String,
+        port: Option<String>,
+        path: Option<String>,
+    },
+}
+
+impl Source {
+    fn parse(expr: &str) -> Option<Self> {
+        let lower = expr.to_ascii_lowercase();
+        match lower.as_str() {
+            "*" => return Some(Self::Any),
+            "'self'" => return Some(Self::SelfOrigin),
+            "'unsafe-inline'" => return Some(Self::UnsafeInline),
+            _ => {}
+        }
+        if let Some(nonce) = expr
+            .strip_prefix("'nonce-")
+            .and_then(|nonce| nonce.strip_suffix('\''))
+        {
+            return Some(Self::Nonce(nonce.to_string()));
+        }
+        if expr.starts_with('\'') {
+            // Other keywords (e.g. `'none'`, `'unsafe-eval'`) don't allow any subresources
+            return None;
+        }
+        if let Some(scheme) = lower.strip_suffix(':') {
+            return Some(Self::Scheme(scheme.to_string()));
+        }
+
+        let (scheme, rest) = match lower.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme.to_string()), rest),
+            None => (None, lower.as_str()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(start) => (&rest[..start], Some(rest[start..].to_string())),
+            None => (rest, None),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.to_string())),
+            None => (authority, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self::Host {
+            scheme,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// Whether the source allows loading `url` in a document at `document_url`
+    fn matches(&self, url: &Url, document_url: Option<&Url>) -> bool {
+        match self {
+            Self::Any => !matches!(url.scheme(), "data" | "blob" | "filesystem"),
+            Self::SelfOrigin => document_url.is_some_and(|document_url| {
+                let upgraded = document_url.scheme() == "http" && url.scheme() == "https";
+                (url.scheme() == document_url.scheme() || upgraded)
+                    && url.host_str() == document_url.host_str()
+                    && (url.port_or_known_default() == document_url.port_or_known_default()
+                        || upgraded)
+            }),
+            Self::UnsafeInline | Self::Nonce(_) => false,
+            Self::Scheme(scheme) => scheme_matches(scheme, url.scheme()),
+            Self::Host {
+                scheme,
+                host,
+                port,
+                path,
+            } => {
+                // Sources without a scheme use the scheme of the document (or `http`, if it has no URL)
+                let scheme = scheme
+                    .as_deref()
+                    .or(document_url.map(Url::scheme))
+                    .unwrap_or("http");
+                let Some(url_host) = url.host_str() else {
+                    return false;
+                };
+                let host_matches = match host.strip_prefix("*.") {
+                    Some(domain) => url_host
+                        .strip_suffix(domain)
+                        .is_some_and(|subdomain| subdomain.ends_with('.')),
+                    None => url_host == host,
+                };
+                let port_matches = match port.as_deref() {
+                    Some("*") => true,
+                    Some(port) => {
+                        url.port_or_known_default()
+                            .map(|p| p.to_string())
+                            .as_deref()
+                            == Some(port)
+                    }
+                    None => url.port().is_none(),
+                };
+                let path_matches = match path {
+                    Some(path) if path.ends_with('/') => url.path().starts_with(path.as_str()),
+                    Some(path) => url.path() == path,
+                    None => true,
+                };
+                scheme_matches(scheme, url.scheme()) && host_matches && port_matches && path_matches
+            }
+        }
+    }
+}
+
+/// Whether a URL's scheme matches the scheme of a source. Sources for `http` also allow `https`.
+fn scheme_matches(source_scheme: &str, url_scheme: &str) -> bool {
+    url_scheme == source_scheme || source_scheme == "http" && url_scheme == "https"
+}
+
+/// A parsed Content Security Policy
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentSecurityPolicy {
+    source: String,
+    directives: Vec<(String, Vec<Source>)>,
+}
+
+impl ContentSecurityPolicy {
+    /// Parse a policy, e.g. `default-src 'self'; img-src *`. Unknown directives are ignored, and only the first of
+    /// repeated directives is used.
+    pub fn parse(policy: &str) -> Self {
+        let mut directives: Vec<(String, Vec<Source>)> = Vec::new();
+        for directive in policy.split(';') {
+            let mut tokens = directive.split_ascii_whitespace();
+            let Some(name) = tokens.next() else {
+                continue;
+            };
+            let name = name.to_ascii_lowercase();
+            if directives.iter().any(|(existing, _)| *existing == name) {
+                continue;
+            }
+            directives.push((name, tokens.filter_map(Source::parse).collect()));
+        }
+        Self {
+            source: policy.trim().to_string(),
+            directives,
+        }
+    }
+
+    /// The policy as it was written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The source list that applies to `directive`, if the policy restricts it
+    fn sources(&self, directive: CspDirective) -> Option<&[Source]> {
+        [directive.name(), "default-src"].iter().find_map(|name| {
+            self.directives
+                .iter()
+                .find(|(directive_name, _)| directive_name == name)
+                .map(|(_, sources)| sources.as_slice())
+        })
+    }
+
+    /// Whether the policy allows loading the resource at `url` in a document at `document_url`
+    pub fn allows_url(
+        &self,
+        directive: CspDirective,
+        url: &Url,
+        document_url: Option<&Url>,
+    ) -> bool {
+        self.sources(directive).map_or(true, |sources| {
+            sources
+                .iter()
+                .any(|source| source.matches(url, document_url))
+        })
+    }
+
+    /// Whether the policy allows an inline resource (e.g. a `<style>` element) with the given `nonce` attribute
+    pub fn allows_inline(&self, directive: CspDirective, nonce: Option<&str>) -> bool {
+        self.sources(directive).map_or(true, |sources| {
+            // Nonces disable `'unsafe-inline'`
+            let has_nonce = sources
+                .iter()
+                .any(|source| matches!(source, Source::Nonce(_)));
+            sources.iter().any(|source| match source {
+                Source::UnsafeInline => !has_nonce,
+                Source::Nonce(expected) => nonce == Some(expected.as_str()),
+                _ => false,
+            })
+        })
+    }
+}
+
+/// A resource that was blocked by a Content Security Policy
+#[derive(Clone, Debug)]
+pub struct CspViolation {
+    /// The element that the resource was loaded for
+    pub node_id: usize,
+    pub directive: CspDirective,
+    /// The URL of the resource, or `None` for inline resources
+    pub blocked_url: Option<String>,
+    /// The policy that blocked the resource
+    pub policy: String,
+}
+
+impl fmt::Display for CspViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resource = self.blocked_url.as_deref().unwrap_or("inline resource");
+        write!(
+            f,
+            "Refused to load {} because it violates the {} directive of the Content Security Policy \"{}\"",
+            resource,
+            self.directive.name(),
+            self.policy
+        )
+    }
+}
+
+/// A handler for resources blocked by a Content Security Policy, set with [`Document::set_csp_violation_handler`]
+pub type CspViolationHandler = Box<dyn FnMut(&CspViolation) + Send>;
+
+#[derive(Default)]
+pub(crate) struct ContentSecurity {
+    policies: Vec<ContentSecurityPolicy>,
+    violation_handler: Option<CspViolationHandler>,
+}
+
+impl Document {
+    /// Enforce a Content Security Policy, e.g. from the `Content-Security-Policy` header of the document's response.
+    /// Add it before parsing the document for it to apply to all of the document's resources.
+    pub fn add_content_security_policy(&mut self, policy: &str) {
+        self.content_security
+            .policies
+            .push(ContentSecurityPolicy::parse(policy));
+    }
+
+    /// The Content Security Policies that the document enforces
+    pub fn content_security_policies(&self) -> &[ContentSecurityPolicy] {
+        &self.content_security.policies
+    }
+
+    /// Set the handler that is called with each resource blocked by a Content Security Policy
+    pub fn set_csp_violation_handler(
+        &mut self,
+        handler: impl FnMut(&CspViolation) + Send + 'static,
+    ) {
+        self.content_security.violation_handler = Some(Box::new(handler));
+    }
+
+    /// Whether the document's policies allow loading the resource at `url` for `node_id`. Violations are reported.
+    pub(crate) fn check_csp_url(
+        &mut self,
+        node_id: usize,
+        directive: CspDirective,
+        url: &Url,
+    ) -> bool {
+        let document_url = self.base_url.clone();
+        let blocking = self
+            .content_security
+            .policies
+            .iter()
+            .find(|policy| !policy.allows_url(directive, url, document_url.as_ref()))
+            .map(|policy| policy.as_str().to_string());
+        self.report_csp_violation(node_id, directive, Some(url.to_string()), blocking)
+    }
+
+    /// Whether the document's policies allow the inline resource of `node_id` (with the given `nonce` attribute).
+    /// Violations are reported.
+    pub(crate) fn check_csp_inline(
+        &mut self,
+        node_id: usize,
+        directive: CspDirective,
+        nonce: Option<&str>,
+    ) -> bool {
+        let blocking = self
+            .content_security
+            .policies
+            .iter()
+            .find(|policy| !policy.allows_inline(directive, nonce))
+            .map(|policy| policy.as_str().to_string());
+        self.report_csp_violation(node_id, directive, None, blocking)
+    }
+
+    /// Report the violation of the `blocking` policy (if there is one), returning whether the resource is allowed
+    fn report_csp_violation(
+        &mut self,
+        node_id: usize,
+        directive: CspDirective,
+        blocked_url: Option<String>,
+        blocking: Option<String>,
+    ) -> bool {
+        let Some(policy) = blocking else {
+            return true;
+        };
+        let violation = CspViolation {
+            node_id,
+            directive,
+            blocked_url,
+            policy,
+        };
+        match self.content_security.violation_handler.as_mut() {
+            Some(handler) => handler(&violation),
+            None => tracing::warn!("{}", violation),
+        }
+        false
+    }
+}
+
+#[test]
+fn enforces_subresource_directives() {
+    let document_url = Url::parse("https://example.com/page").unwrap();
+    let document_url = Some(&document_url);
+    let url = |url: &str| Url::parse(url).unwrap();
+
+    let policy = ContentSecurityPolicy::parse(
+        "default-src 'self'; img-src https://*.cdn.com data:; style-src 'self' 'nonce-abc'",
+    );
+    assert!(policy.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://img.cdn.com/a.png"),
+        document_url
+    ));
+    assert!(policy.allows_url(CspDirective::ImgSrc, &url("data:image/png,"), document_url));
+    assert!(!policy.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://example.com/a.png"),
+        document_url
+    ));
+    // Stylesheets fall back to `default-src`
+    assert!(policy.allows_url(
+        CspDirective::StyleSrc,
+        &url("https://example.com/style.css"),
+        document_url
+    ));
+    assert!(!policy.allows_url(
+        CspDirective::StyleSrc,
+        &url("https://styles.example.net/style.css"),
+        document_url
+    ));
+
+    assert!(policy.allows_inline(CspDirective::StyleSrc, Some("abc")));
+    assert!(!policy.allows_inline(CspDirective::StyleSrc, None));
+
+    let none = ContentSecurityPolicy::parse("img-src 'none'");
+    assert!(!none.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://example.com/a.png"),
+        document_url
+    ));
+    assert!(none.allows_inline(CspDirective::StyleSrc, None));
+
+    // Hosts without a scheme use the document's scheme
+    let host = ContentSecurityPolicy::parse("img-src cdn.com");
+    assert!(host.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://cdn.com/a.png"),
+        document_url
+    ));
+    assert!(!host.allows_url(
+        CspDirective::ImgSrc,
+        &url("http://cdn.com/a.png"),
+        document_url
+    ));
+    let file_url = Url::parse("file:///index.html").unwrap();
+    assert!(host.allows_url(
+        CspDirective::ImgSrc,
+        &url("file://cdn.com/a.png"),
+        Some(&file_url)
+    ));
+    assert!(!host.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://cdn.com/a.png"),
+        Some(&file_url)
+    ));
+    let http_url = Url::parse("http://example.com/").unwrap();
+    assert!(host.allows_url(
+        CspDirective::ImgSrc,
+        &url("https://cdn.com/a.png"),
+        Some(&http_url)
+    ));
+}
@@ -0,0 +1,117 @@
+//! Editing the rules of the document's stylesheets (the equivalent of `CSSStyleSheet.insertRule` and friends)
+//!
+//! Stylesheets are edited by serializing their rules, making the change and parsing the result into a new stylesheet
+//! that takes the old one's place in the stylist. Sheets keep the key they were added with, so
+//! [`Document::remove_stylehsheet`] still removes them after they've been edited.
+use style::shared_lock::ToCssWithGuard;
+use style::stylesheets::{DocumentStyleSheet, Origin};
+
+use crate::Document;
+
+/// Why a rule couldn't be inserted or deleted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// The index is past the end of the stylesheet's rules
+    IndexOutOfBounds,
+    /// The text isn't a single valid rule
+    Syntax,
+}
+
+/// One of the document's stylesheets, returned by [`Document::style_sheet`]
+pub struct CssStyleSheet<'doc> {
+    doc: &'doc mut Document,
+    sheet: DocumentStyleSheet,
+}
+
+impl Document {
+    /// The number of author stylesheets applied to the document
+    pub fn style_sheet_count(&self) -> usize {
+        self.stylist.sheet_count(Origin::Author)
+    }
+
+    /// One of the author stylesheets applied to the document, in the order they were added
+    pub fn style_sheet(&mut self, index: usize) -> Option<CssStyleSheet<'_>> {
+        let sheet = self.stylist.sheet_at(Origin::Author, index)?.clone();
+        Some(CssStyleSheet { doc: self, sheet })
+    }
+}
+
+impl CssStyleSheet<'_> {
+    /// The serialized rules of the stylesheet
+    pub fn css_rules(&self) -> Vec<String> {
+        serialize_rules(self.doc, &self.sheet)
+    }
+
+    /// Insert a rule before the rule at `index` (or at the end, if `index` is the number of rules). Returns the
+    /// index of the new rule.
+    pub fn insert_rule(&mut self, rule: &str, index: usize) -> Result<usize, RuleError> {
+        let mut rules = self.css_rules();
+        if index > rules.len() {
+            return Err(RuleError::IndexOutOfBounds);
+        }
+
+        // Parse the rule on its own first, so invalid rules leave the stylesheet unchanged
+        let origin = self.sheet.0.contents.origin;
+        let parsed = serialize_rules(self.doc, &self.doc.parse_stylesheet(rule, origin));
+        let [parsed_rule] = <[String; 1]>::try_from(parsed).map_err(|_| RuleError::Syntax)?;
+
+        rules.insert(index, parsed_rule);
+        self.replace(&rules.join("\n"));
+        Ok(index)
+    }
+
+    /// Delete the rule at `index`
+    pub fn delete_rule(&mut self, index: usize) -> Result<(), RuleError> {
+        let mut rules = self.css_rules();
+        if index >= rules.len() {
+            return Err(RuleError::IndexOutOfBounds);
+        }
+        rules.remove(index);
+        self.replace(&rules.join("\n"));
+        Ok(())
+    }
+
+    /// Replace all of the stylesheet's rules by parsing new source text
+    pub fn replace(&mut self, css: &str) {
+        self.sheet = self.doc.replace_stylesheet(self.sheet.clone(), css);
+    }
+}
+
+impl Document {
+    /// Parse new source text for a stylesheet and put it in the old sheet's place. Returns the new sheet.
+    pub(crate) fn replace_stylesheet(
+        &mut self,
+        old_sheet: DocumentStyleSheet,
+        css: &str,
+    ) -> DocumentStyleSheet {
+        let sheet = self.parse_stylesheet(css, old_sheet.0.contents.origin);
+        {
+            let guard = self.guard.read();
+            self.stylist
+                .insert_stylesheet_before(sheet.clone(), old_sheet.clone(), &guard);
+            self.stylist.remove_stylesheet(old_sheet.clone(), &guard);
+        }
+        for existing in self.stylesheets.values_mut() {
+            if *existing == old_sheet {
+                *existing = sheet.clone();
+            }
+        }
+        self.stylist
+            .force_stylesheet_origins_dirty(sheet.0.contents.origin.into());
+        self.invalidate();
+        sheet
+    }
+}
+
+fn serialize_rules(doc: &Document, sheet: &DocumentStyleSheet) -> Vec<String> {
+    let guard = doc.guard.read();
+    sheet
+        .0
+        .contents
+        .rules
+        .read_with(&guard)
+        .0
+        .iter()
+        .map(|rule| rule.to_css_string(&guard).to_string())
+        .collect()
+}
@@ -0,0 +1,299 @@
+//! Custom elements defined by the embedder (the equivalent of `customElements.define`)
+//!
+//! Embedders register a tag name with Rust callbacks for the custom element lifecycle. Elements with that name
+//! (whether parsed, created by the embedder or cloned) are upgraded, which makes them match `:defined` and runs their
+//! `created` callback. Like the DOM's custom element reactions, callbacks don't run in the middle of the change that
+//! triggered them: they're queued, and run at the start of the next [`Document::resolve`].
+//!
+//! Custom elements can also expose their internal state to stylesheets with custom states, which are matched by
+//! `:state()` selectors.
+use std::collections::HashMap;
+
+use html5ever::LocalName;
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style::Atom;
+use style_traits::dom::ElementState;
+
+use crate::Document;
+
+/// Called with the id of a custom element
+pub type CustomElementCallback = Box<dyn FnMut(&mut Document, usize) + Send>;
+
+/// Called with the id of a custom element, the name of the attribute that changed, and its old and new values
+pub type AttributeChangedCallback =
+    Box<dyn FnMut(&mut Document, usize, &LocalName, Option<&str>, Option<&str>) + Send>;
+
+/// The lifecycle callbacks of a custom element
+#[derive(Default)]
+pub struct CustomElementDefinition {
+    /// Called when an element is upgraded (when it's created, or when its name is defined if it already exists)
+    pub created: Option<CustomElementCallback>,
+    /// Called when an element is inserted into the document
+    pub connected: Option<CustomElementCallback>,
+    /// Called when one of the observed attributes of an element changes
+    pub attribute_changed: Option<AttributeChangedCallback>,
+    /// The attributes whose changes call `attribute_changed`
+    pub observed_attributes: Vec<LocalName>,
+}
+
+enum CustomElementReaction {
+    Upgrade(usize),
+    Connected(usize),
+    AttributeChanged {
+        node_id: usize,
+        name: LocalName,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    },
+}
+
+/// The custom elements defined for a document, and the reactions waiting to run
+#[derive(Default)]
+pub struct CustomElementRegistry {
+    definitions: HashMap<LocalName, CustomElementDefinition>,
+    reactions: Vec<CustomElementReaction>,
+}
+
+impl CustomElementRegistry {
+    pub fn is_defined(&self, name: &LocalName) -> bool {
+        self.definitions.contains_key(name)
+    }
+}
+
+/// Whether a tag name is a valid custom element name: it starts with a lowercase ASCII letter, contains a dash, has
+/// no uppercase ASCII letters and isn't one of the reserved names
+/// (<https://html.spec.whatwg.org/multipage/custom-elements.html#valid-custom-element-name>)
+pub fn is_valid_custom_element_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.contains('-')
+        && !name.contains(|c: char| c.is_ascii_uppercase())
+        && !matches!(
+            name,
+            "annotation-xml"
+                | "color-profile"
+                | "font-face"
+                | "font-face-src"
+                | "font-face-uri"
+                | "font-face-format"
+                | "font-face-name"
+                | "missing-glyph"
+        )
+}
+
+impl Document {
+    /// The custom elements defined for the document
+    pub fn custom_elements(&self) -> &CustomElementRegistry {
+        &self.custom_elements
+    }
+
+    /// Define a custom element, upgrading the elements that already have its name. Returns `false` (and does nothing)
+    /// if the name isn't a valid custom element name or is already defined.
+    pub fn define_custom_element(
+        &mut self,
+        name: &str,
+        definition: CustomElementDefinition,
+    ) -> bool {
+        let name = LocalName::from(name);
+        if !is_valid_custom_element_name(&name) || self.custom_elements.is_defined(&name) {
+            return false;
+        }
+        self.custom_elements
+            .definitions
+            .insert(name.clone(), definition);
+
+        // Upgrade existing elements in tree order
+        let mut stack = vec![0];
+        while let Some(node_id) = stack.pop() {
+            let node = &self.nodes[node_id];
+            if node
+                .element_data()
+                .is_some_and(|element_data| element_data.name.local == name)
+            {
+                self.custom_elements
+                    .reactions
+                    .push(CustomElementReaction::Upgrade(node_id));
+            }
+            stack.extend(node.children.iter().rev().copied());
+        }
+        self.invalidate();
+        true
+    }
+
+    /// Queue the upgrade of a newly created element, if its name has been defined
+    pub(crate) fn queue_custom_element_upgrade(&mut self, node_id: usize) {
+        let Some(element_data) = self.nodes[node_id].element_data() else {
+            return;
+        };
+        if self.custom_elements.is_defined(&element_data.name.local) {
+            self.custom_elements
+                .reactions
+                .push(CustomElementReaction::Upgrade(node_id));
+        }
+    }
+
+    /// Queue the `connected` callbacks of the upgraded custom elements in a subtree that was inserted into the
+    /// document
+    pub(crate) fn queue_custom_element_connected(&mut self, node_id: usize) {
+        if self.custom_elements.definitions.is_empty() || !self.is_connected(node_id) {
+            return;
+        }
+        let mut stack = vec![node_id];
+        while let Some(node_id) = stack.pop() {
+            let node = &self.nodes[node_id];
+            if self.is_upgraded_custom_element(node_id) {
+                self.custom_elements
+                    .reactions
+                    .push(CustomElementReaction::Connected(node_id));
+            }
+            stack.extend(node.children.iter().rev().copied());
+        }
+    }
+
+    /// Queue the `attribute_changed` callback of an upgraded custom element, if it observes the attribute
+    pub(crate) fn queue_custom_element_attribute_changed(
+        &mut self,
+        node_id: usize,
+        name: &LocalName,
+        old_value: Option<String>,
+    ) {
+        if !self.is_upgraded_custom_element(node_id) {
+            return;
+        }
+        let element_data = self.nodes[node_id].element_data().unwrap();
+        let observed = self
+            .custom_elements
+            .definitions
+            .get(&element_data.name.local)
+            .is_some_and(|definition| definition.observed_attributes.contains(name));
+        if !observed {
+            return;
+        }
+        let new_value = element_data.attr(name.clone()).map(String::from);
+        self.custom_elements
+            .reactions
+            .push(CustomElementReaction::AttributeChanged {
+                node_id,
+                name: name.clone(),
+                old_value,
+                new_value,
+            });
+    }
+
+    /// Run the custom element callbacks queued since they last ran, including those queued by the callbacks
+    /// themselves
+    pub(crate) fn process_custom_element_reactions(&mut self) {
+        while !self.custom_elements.reactions.is_empty() {
+            let reactions = std::mem::take(&mut self.custom_elements.reactions);
+            for reaction in reactions {
+                self.run_custom_element_reaction(reaction);
+            }
+        }
+    }
+
+    fn run_custom_element_reaction(&mut self, reaction: CustomElementReaction) {
+        let node_id = match &reaction {
+            CustomElementReaction::Upgrade(node_id)
+            | CustomElementReaction::Connected(node_id)
+            | CustomElementReaction::AttributeChanged { node_id, .. } => *node_id,
+        };
+
+        // The element may have been removed since the reaction was queued
+        let Some(name) = self
+            .nodes
+            .get(node_id)
+            .and_then(|node| node.element_data())
+            .map(|element_data| element_data.name.local.clone())
+        else {
+            return;
+        };
+
+        // Take the definition out of the registry while its callbacks run, so they can use the document
+        let Some(mut definition) = self.custom_elements.definitions.remove(&name) else {
+            return;
+        };
+        match reaction {
+            CustomElementReaction::Upgrade(node_id) => {
+                if !self.is_upgraded_custom_element(node_id) {
+                    self.snapshot_node(node_id);
+                    self.nodes[node_id]
+                        .element_state
+                        .insert(ElementState::DEFINED);
+                    if let Some(created) = definition.created.as_mut() {
+                        created(self, node_id);
+                    }
+                    if self.is_connected(node_id) {
+                        if let Some(connected) = definition.connected.as_mut() {
+                            connected(self, node_id);
+                        }
+                    }
+                }
+            }
+            CustomElementReaction::Connected(node_id) => {
+                if let Some(connected) = definition.connected.as_mut() {
+                    connected(self, node_id);
+                }
+            }
+            CustomElementReaction::AttributeChanged {
+                node_id,
+                name,
+                old_value,
+                new_value,
+            } => {
+                if let Some(attribute_changed) = definition.attribute_changed.as_mut() {
+                    attribute_changed(
+                        self,
+                        node_id,
+                        &name,
+                        old_value.as_deref(),
+                        new_value.as_deref(),
+                    );
+                }
+            }
+        }
+        self.custom_elements.definitions.insert(name, definition);
+    }
+
+    /// Whether an element has a custom state (matched by `:state(name)`)
+    pub fn has_custom_state(&self, node_id: usize, state: &str) -> bool {
+        self.nodes[node_id]
+            .custom_states
+            .contains(&Atom::from(state))
+    }
+
+    /// Add or remove a custom state of an element, restyling the element and its descendants if it changed
+    pub fn set_custom_state(&mut self, node_id: usize, state: &str, present: bool) {
+        let state = Atom::from(state);
+        let node = &mut self.nodes[node_id];
+        if node.custom_states.contains(&state) == present {
+            return;
+        }
+        match present {
+            true => node.custom_states.push(state),
+            false => node.custom_states.retain(|other| *other != state),
+        }
+
+        if let Some(data) = node.stylo_element_data.borrow_mut().as_mut() {
+            data.hint.insert(RestyleHint::restyle_subtree());
+        }
+        self.invalidate();
+    }
+
+    fn is_upgraded_custom_element(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        node.element_data()
+            .is_some_and(|element_data| is_valid_custom_element_name(&element_data.name.local))
+            && node.element_state.contains(ElementState::DEFINED)
+    }
+
+    /// Whether a node is in the document (rather than in a detached subtree)
+    fn is_connected(&self, node_id: usize) -> bool {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            if id == 0 {
+                return true;
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        false
+    }
+}
@@ -0,0 +1,410 @@
+//! Damage tracking
+//!
+//! To avoid repainting the whole viewport every frame, the document records what each box looked like when it was
+//! last painted: the rectangle it covered and a hash of everything that affects how it is drawn (its computed style,
+//! attributes, image and text). Computed styles and images are identified by generation numbers that are bumped
+//! whenever they're replaced (see [`next_paint_generation`]). [`Document::collect_damage`] compares the current state
+//! of the document against these records and returns the regions of the viewport that have changed since the last
+//! call.
+//!
+//! Boxes are visited in the same order that the renderer paints them: layout children are walked from the root
+//! element, and the top layer is walked separately relative to the viewport. Anonymous blocks are recreated whenever
+//! layout children are rebuilt, so they are keyed by their container and position rather than by node id.
+//!
+//! Records of scrolling content are kept in document coordinates, and those of fixed position elements and the top
+//! layer in viewport coordinates, so that scrolling alone doesn't change them. This lets renderers tell a scroll
+//! (which moves everything, but can be composited from cached layers) apart from changes to the content.
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::node::NodeData;
+use crate::Document;
+
+static NEXT_PAINT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// A number that identifies a node's computed style or image in paint signatures. Generations are never reused (unlike
+/// the addresses of the styles and images, which can be once they're dropped).
+pub(crate) fn next_paint_generation() -> u64 {
+    NEXT_PAINT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A rectangle of the viewport, in CSS pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DamageRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DamageRect {
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+
+    /// The smallest rectangle that contains both rectangles
+    pub fn union(&self, other: &DamageRect) -> DamageRect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        DamageRect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+
+    pub fn intersects(&self, other: &DamageRect) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    fn translate(&self, dy: f32) -> DamageRect {
+        DamageRect {
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
+    fn inflate(&self, amount: f32) -> DamageRect {
+        DamageRect {
+            x: self.x - amount,
+            y: self.y - amount,
+            width: self.width + 2.0 * amount,
+            height: self.height + 2.0 * amount,
+        }
+    }
+}
+
+/// The regions of the viewport that need to be repainted
+#[derive(Clone, Debug, Default)]
+pub struct Damage {
+    /// The whole viewport needs to be repainted (e.g. because it was resized)
+    pub full: bool,
+    /// The viewport was scrolled, which moves all of the scrolling content. Boxes that have only moved with the
+    /// scroll position are not included in `rects`.
+    pub scrolled: bool,
+    /// Damaged regions (not meaningful if `full` or `scrolled` is set)
+    pub rects: Vec<DamageRect>,
+    /// The boxes (by node id) whose appearance or position changed, which renderers can use to tell which of their
+    /// layers are affected
+    pub nodes: Vec<usize>,
+    /// Whether damage was added that isn't attributed to a box in `nodes`: boxes that are no longer painted, or
+    /// regions marked damaged by [`Document::mark_damaged`]
+    pub unattributed: bool,
+}
+
+impl Damage {
+    pub fn is_empty(&self) -> bool {
+        !self.full && !self.scrolled && self.rects.is_empty()
+    }
+
+    /// Whether anything other than the scroll position has changed
+    pub fn content_changed(&self) -> bool {
+        self.full || !self.rects.is_empty()
+    }
+
+    /// The smallest rectangle containing all of the damaged regions
+    pub fn bounds(&self) -> Option<DamageRect> {
+        self.rects
+            .iter()
+            .copied()
+            .reduce(|bounds, rect| bounds.union(&rect))
+    }
+
+    pub fn add(&mut self, rect: DamageRect) {
+        if !rect.is_empty() {
+            self.rects.push(rect);
+        }
+    }
+}
+
+/// How paint records are keyed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum PaintKey {
+    Node(usize),
+    /// The nth anonymous block among a container's layout children
+    Anonymous(usize, usize),
+}
+
+/// What a box looked like when it was last painted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PaintRecord {
+    /// In viewport coordinates for fixed boxes, document coordinates otherwise
+    rect: DamageRect,
+    fixed: bool,
+    signature: u64,
+}
+
+impl PaintRecord {
+    fn viewport_rect(&self, scroll: f64) -> DamageRect {
+        if self.fixed {
+            self.rect
+        } else {
+            self.rect.translate(-scroll as f32)
+        }
+    }
+}
+
+impl Document {
+    /// Mark a region of the viewport (in CSS pixels) as needing to be repainted
+    pub fn mark_damaged(&mut self, rect: DamageRect) {
+        self.pending_damage.add(rect);
+        self.pending_damage.unattributed = true;
+    }
+
+    /// Mark the whole viewport as needing to be repainted
+    pub fn mark_fully_damaged(&mut self) {
+        self.pending_damage.full = true;
+    }
+
+    /// The regions of the viewport that have changed since the last call. Call this after [`Document::resolve`] and
+    /// before painting.
+    pub fn collect_damage(&mut self) -> Damage {
+        let mut damage = std::mem::take(&mut self.pending_damage);
+
+        // Scrolling moves everything
+        if self.painted_scroll != Some(self.viewport_scroll) {
+            damage.scrolled = true;
+            self.painted_scroll = Some(self.viewport_scroll);
+        }
+
+        let mut records = HashMap::with_capacity(self.paint_records.len());
+        if let Some(root) = self.try_root_element() {
+            let root_id = root.id;
+            self.collect_box_damage(
+                PaintKey::Node(root_id),
+                root_id,
+                (0.0, 0.0),
+                false,
+                &mut records,
+                &mut damage,
+            );
+        }
+        for (node_id, _) in self.top_layer.clone() {
+            self.collect_box_damage(
+                PaintKey::Node(node_id),
+                node_id,
+                (0.0, 0.0),
+                true,
+                &mut records,
+                &mut damage,
+            );
+        }
+
+        // Boxes that are no longer painted leave damage where they used to be
+        for (_, record) in self.paint_records.drain() {
+            damage.add(record.viewport_rect(self.viewport_scroll));
+            damage.unattributed = true;
+        }
+        self.paint_records = records;
+
+        damage
+    }
+
+    fn collect_box_damage(
+        &mut self,
+        key: PaintKey,
+        node_id: usize,
+        origin: (f32, f32),
+        fixed: bool,
+        records: &mut HashMap<PaintKey, PaintRecord>,
+        damage: &mut Damage,
+    ) {
+        let node = &self.nodes[node_id];
+        let fixed = fixed || node.is_fixed_position();
+        let layout = node.final_layout;
+        let position = (origin.0 + layout.location.x, origin.1 + layout.location.y);
+
+        // Outlines and rounding can paint slightly outside of the border box
+        let outline = node
+            .primary_styles()
+            .map(|style| {
+                let outline = style.get_outline();
+                outline.outline_width.to_f32_px() + outline.outline_offset.px().max(0.0)
+            })
+            .unwrap_or(0.0);
+        let record = PaintRecord {
+            rect: DamageRect {
+                x: position.0,
+                y: position.1,
+                width: layout.size.width,
+                height: layout.size.height,
+            }
+            .inflate(outline + 1.0),
+            fixed,
+            signature: self.paint_signature(node_id),
+        };
+
+        // Nested documents track their own damage, which damages the whole iframe
+        let sub_document_damaged = self
+            .sub_documents
+            .get_mut(&node_id)
+            .is_some_and(|sub_document| !sub_document.collect_damage().is_empty());
+
+        let scroll = self.viewport_scroll;
+        match self.paint_records.remove(&key) {
+            Some(old) if old == record && !sub_document_damaged => {}
+            Some(old) => {
+                damage.add(old.viewport_rect(scroll));
+                damage.add(record.viewport_rect(scroll));
+                damage.nodes.push(node_id);
+            }
+            None => {
+                damage.add(record.viewport_rect(scroll));
+                damage.nodes.push(node_id);
+            }
+        }
+        records.insert(key, record);
+
+        // Visit children in paint order. Inline boxes are among the layout children of inline roots, and are positioned
+        // relative to the root's content box.
+        let node = &self.nodes[node_id];
+        let children_origin = if node.is_inline_root {
+            (
+                position.0 + layout.border.left + layout.padding.left,
+                position.1 + layout.border.top + layout.padding.top,
+            )
+        } else {
+            position
+        };
+        let children = node.layout_children.borrow().clone();
+        let mut anonymous_idx = 0;
+        for child_id in children.into_iter().flatten() {
+            let child = &self.nodes[child_id];
+            if child.in_top_layer {
+                continue;
+            }
+            let child_key = if matches!(child.raw_dom_data, NodeData::AnonymousBlock(_)) {
+                anonymous_idx += 1;
+                PaintKey::Anonymous(node_id, anonymous_idx - 1)
+            } else {
+                PaintKey::Node(child_id)
+            };
+            self.collect_box_damage(child_key, child_id, children_origin, fixed, records, damage);
+        }
+    }
+
+    /// A hash of everything that affects how a box is painted (other than its position and size). Renderers can use
+    /// this to tell whether the display items they encoded for a box can be reused.
+    pub fn paint_signature(&self, node_id: usize) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let node = &self.nodes[node_id];
+
+        // Anonymous blocks are given fresh (but equal) styles whenever they are recreated, so they are identified by
+        // their content alone
+        if !matches!(node.raw_dom_data, NodeData::AnonymousBlock(_)) {
+            self.hash_element_appearance(node_id, &mut hasher);
+        }
+        node.hidden.hash(&mut hasher);
+        layout_bits(&node.final_layout).hash(&mut hasher);
+
+        // The text of an inline root is drawn with the styles of its inline descendants
+        if node.is_inline_root {
+            if let Some(text_layout) = node
+                .element_data()
+                .and_then(|element_data| element_data.inline_layout.as_ref())
+            {
+                text_layout.text.hash(&mut hasher);
+            }
+            for child_id in node.children.iter().copied() {
+                self.hash_inline_content(child_id, &mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    fn hash_inline_content(&self, node_id: usize, hasher: &mut impl Hasher) {
+        let node = &self.nodes[node_id];
+        match &node.raw_dom_data {
+            NodeData::Text(text) => text.content.hash(hasher),
+            NodeData::Element(_) => {
+                self.hash_element_appearance(node_id, hasher);
+                for child_id in node.children.iter().copied() {
+                    self.hash_inline_content(child_id, hasher);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn hash_element_appearance(&self, node_id: usize, hasher: &mut impl Hasher) {
+        let node = &self.nodes[node_id];
+        node.primary_styles()
+            .map(|_| node.style_generation.load(Ordering::Relaxed))
+            .hash(hasher);
+
+        if let Some(element_data) = node.element_data() {
+            for attr in element_data.attrs() {
+                let value: &str = &attr.value;
+                attr.name.local.hash(hasher);
+                value.hash(hasher);
+            }
+            element_data
+                .image
+                .as_ref()
+                .map(|_| element_data.image_generation)
+                .hash(hasher);
+        }
+    }
+}
+
+/// The parts of a layout that affect painting, in a hashable form
+fn layout_bits(layout: &taffy::Layout) -> [u32; 8] {
+    [
+        layout.border.left.to_bits(),
+        layout.border.right.to_bits(),
+        layout.border.top.to_bits(),
+        layout.border.bottom.to_bits(),
+        layout.padding.left.to_bits(),
+        layout.padding.right.to_bits(),
+        layout.padding.top.to_bits(),
+        layout.padding.bottom.to_bits(),
+    ]
+}
+
+#[test]
+fn damages_restyled_boxes() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div id=a style='width: 100px; height: 50px'></div><div id=b style='height: 10px'></div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+    let a = doc.get_element_by_id("a").unwrap();
+    doc.collect_damage();
+
+    // Resolving again without changes keeps the same styles
+    doc.resolve();
+    assert!(doc.collect_damage().is_empty());
+
+    // A changed style damages its box, even if the new style is allocated where an old one was
+    doc.inline_style(a).set_property("background-color", "red");
+    doc.resolve();
+    let damage = doc.collect_damage();
+    assert!(damage.nodes.contains(&a));
+    assert!(damage
+        .rects
+        .iter()
+        .any(|rect| rect.width >= 100.0 && rect.height >= 50.0));
+
+    // As does each new image, even one of the same size
+    for _ in 0..2 {
+        let image = std::sync::Arc::new(image::DynamicImage::new_rgba8(100, 50));
+        doc.nodes[a].element_data_mut().unwrap().set_image(image);
+        assert!(doc.collect_damage().nodes.contains(&a));
+    }
+}
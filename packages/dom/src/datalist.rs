@@ -0,0 +1,224 @@
+//! Suggestions for inputs with a `<datalist>`
+//!
+//! <https://html.spec.whatwg.org/multipage/form-elements.html#the-datalist-element>
+//!
+//! A text-like `<input>` whose `list` attribute names a `<datalist>` suggests the values of the datalist's options.
+//! Activating the input (or pressing the down arrow while it has focus) opens a dropdown of the suggestions that
+//! contain its current value, ignoring case. The dropdown is an [`OpenPicker`] of kind [`PickerKind::Suggestions`], so
+//! it's drawn and clicked like the other pickers. While it's open, the up and down arrows highlight a suggestion and
+//! enter chooses it, which stores it in the input's `value` attribute. The suggestions are filtered again whenever the
+//! input's value changes, such as when the user types in it.
+use html5ever::local_name;
+
+use crate::pickers::{OpenPicker, PickerAction, PickerCell, PickerKind, PICKER_PADDING};
+use crate::Document;
+
+/// The most suggestions that are shown at once
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// The height of a suggestion in the dropdown
+pub const SUGGESTION_HEIGHT: f32 = 24.0;
+
+/// The space before the text of a suggestion
+pub const SUGGESTION_PADDING: f32 = 8.0;
+
+/// The `type`s of input that a datalist can suggest values for
+const SUGGESTING_TYPES: [&str; 6] = ["text", "search", "url", "tel", "email", "number"];
+
+impl Document {
+    /// The `<datalist>` that suggests values for an input (if it has one)
+    pub fn input_datalist(&self, node_id: usize) -> Option<usize> {
+        let element_data = self.nodes[node_id].element_data()?;
+        if element_data.name.local != local_name!("input")
+            || element_data.attr(local_name!("readonly")).is_some()
+        {
+            return None;
+        }
+        let input_type = element_data.attr(local_name!("type")).unwrap_or("text");
+        if !SUGGESTING_TYPES
+            .iter()
+            .any(|suggesting| input_type.eq_ignore_ascii_case(suggesting))
+        {
+            return None;
+        }
+
+        let list_id = self.get_element_by_id(element_data.attr(local_name!("list"))?)?;
+        self.nodes[list_id]
+            .is_element_with_tag_name(&local_name!("datalist"))
+            .then_some(list_id)
+    }
+
+    /// The values suggested for an input: the values of its datalist's enabled options that contain the input's
+    /// current value, in document order and without duplicates
+    pub fn suggestions(&self, node_id: usize) -> Vec<String> {
+        let Some(list_id) = self.input_datalist(node_id) else {
+            return Vec::new();
+        };
+        let typed = self.nodes[node_id]
+            .attr(local_name!("value"))
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        let mut suggestions: Vec<String> = Vec::new();
+        for option_id in self.datalist_options(list_id) {
+            let option = &self.nodes[option_id];
+            if option.is_disabled() {
+                continue;
+            }
+            let value = match option.attr(local_name!("value")) {
+                Some(value) => value.to_string(),
+                None => option.text_content().trim().to_string(),
+            };
+            if !value.is_empty()
+                && value.to_lowercase().contains(&typed)
+                && !suggestions.contains(&value)
+            {
+                suggestions.push(value);
+            }
+        }
+        suggestions
+    }
+
+    /// Whether the open picker is a dropdown of suggestions
+    pub fn suggestions_open(&self) -> bool {
+        self.open_picker
+            .as_ref()
+            .is_some_and(|picker| picker.kind == PickerKind::Suggestions)
+    }
+
+    /// Highlight the next (or previous) suggestion of the open dropdown, wrapping around at the ends. Returns true if
+    /// the dropdown is open.
+    pub fn move_suggestion_highlight(&mut self, forward: bool) -> bool {
+        if !self.suggestions_open() {
+            return false;
+        }
+        let picker = self.open_picker.as_mut().unwrap();
+        let count = picker.suggestions.len();
+        picker.highlighted = Some(match (picker.highlighted, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+        });
+        for (index, cell) in picker.cells.iter_mut().enumerate() {
+            cell.selected = picker.highlighted == Some(index);
+        }
+        true
+    }
+
+    /// Choose the highlighted suggestion of the open dropdown, closing it. Returns the input if its value changed.
+    pub fn choose_highlighted_suggestion(&mut self) -> Option<usize> {
+        if !self.suggestions_open() {
+            return None;
+        }
+        let picker = self.open_picker.take().unwrap();
+        let value = picker.suggestions.get(picker.highlighted?)?.clone();
+        if self.nodes[picker.node_id].attr(local_name!("value")) == Some(value.as_str()) {
+            return None;
+        }
+        self.set_input_value(picker.node_id, value);
+        Some(picker.node_id)
+    }
+
+    /// Filter the suggestions of the open dropdown again after its input's value has changed, closing it if none
+    /// are left
+    pub(crate) fn refilter_suggestions(&mut self, node_id: usize) {
+        if self.suggestions_open() && self.open_picker.as_ref().unwrap().node_id == node_id {
+            self.open_picker.as_mut().unwrap().highlighted = None;
+            self.layout_picker();
+        }
+    }
+
+    /// Lay out a dropdown of suggestions, one per row, at least as wide as the input
+    pub(crate) fn layout_suggestions(
+        &mut self,
+        picker: &OpenPicker,
+        cells: &mut Vec<PickerCell>,
+    ) -> taffy::Size<f32> {
+        let labels: Vec<_> = picker
+            .suggestions
+            .iter()
+            .map(|suggestion| self.build_picker_label(picker.node_id, suggestion))
+            .collect();
+        let label_width = labels
+            .iter()
+            .map(|label| label.layout.width() / self.scale)
+            .fold(0.0, f32::max);
+        let width = (self.nodes[picker.node_id].final_layout.size.width - 2.0 * PICKER_PADDING)
+            .max(label_width + 2.0 * SUGGESTION_PADDING);
+
+        let rows = labels.len();
+        for (index, label) in labels.into_iter().enumerate() {
+            cells.push(PickerCell {
+                location: taffy::Point {
+                    x: PICKER_PADDING,
+                    y: PICKER_PADDING + index as f32 * SUGGESTION_HEIGHT,
+                },
+                size: taffy::Size {
+                    width,
+                    height: SUGGESTION_HEIGHT,
+                },
+                action: PickerAction::SelectSuggestion(index),
+                selected: picker.highlighted == Some(index),
+                label: Some(label),
+                color: None,
+            });
+        }
+
+        taffy::Size {
+            width: width + 2.0 * PICKER_PADDING,
+            height: rows as f32 * SUGGESTION_HEIGHT + 2.0 * PICKER_PADDING,
+        }
+    }
+
+    /// The `<option>` elements within a datalist, in document order
+    fn datalist_options(&self, list_id: usize) -> Vec<usize> {
+        fn collect(doc: &Document, node_id: usize, options: &mut Vec<usize>) {
+            for &child_id in &doc.nodes[node_id].children {
+                if doc.nodes[child_id].is_element_with_tag_name(&local_name!("option")) {
+                    options.push(child_id);
+                } else {
+                    collect(doc, child_id, options);
+                }
+            }
+        }
+        let mut options = Vec::new();
+        collect(self, list_id, &mut options);
+        options
+    }
+}
+
+#[test]
+fn suggests_datalist_values() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input id=browser list=browsers><input id=checkbox type=checkbox list=browsers>\
+        <datalist id=browsers><option value=Firefox><option>Chrome</option>\
+        <option value=Safari disabled><option value=Edge></datalist></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let input = doc.get_element_by_id("browser").unwrap();
+    let checkbox = doc.get_element_by_id("checkbox").unwrap();
+    assert_eq!(doc.suggestions(input), ["Firefox", "Chrome", "Edge"]);
+    assert!(doc.suggestions(checkbox).is_empty());
+
+    // Opening the dropdown and typing filters the suggestions
+    doc.activate_picker(input);
+    assert!(doc.suggestions_open());
+    assert_eq!(doc.open_picker().unwrap().cells.len(), 3);
+    let value = QualName::new(None, ns!(), local_name!("value"));
+    doc.set_attribute(input, value, "R");
+    assert_eq!(doc.open_picker().unwrap().cells.len(), 2);
+
+    // The arrow keys wrap around, and choosing a suggestion stores it as the value
+    assert!(doc.move_suggestion_highlight(false));
+    assert!(doc.move_suggestion_highlight(true));
+    assert!(doc.move_suggestion_highlight(true));
+    assert_eq!(doc.choose_highlighted_suggestion(), Some(input));
+    assert_eq!(doc.nodes[input].attr(local_name!("value")), Some("Chrome"));
+    assert!(!doc.suggestions_open());
+}
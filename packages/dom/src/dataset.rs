@@ -0,0 +1,125 @@
+//! Access to `data-*` attributes (the equivalent of `HTMLElement.dataset`)
+//!
+//! Names are converted between the camelCase names used by the dataset (`fooBar`) and the dashed names of the
+//! attributes (`data-foo-bar`). Changes are written through [`Document::set_attribute`] so that they invalidate
+//! styles that select on the attributes.
+use html5ever::{namespace_url, ns, LocalName, QualName};
+
+use crate::node::ElementNodeData;
+use crate::Document;
+
+impl ElementNodeData {
+    /// The element's `data-*` attributes, as (camelCase name, value) pairs
+    pub fn dataset(&self) -> impl Iterator<Item = (String, &str)> {
+        self.attrs.iter().filter_map(|attr| {
+            let name = dataset_name(&attr.name.local)?;
+            Some((name, attr.value.as_str()))
+        })
+    }
+
+    /// The value of a `data-*` attribute, by its camelCase name
+    pub fn data(&self, name: &str) -> Option<&str> {
+        let attr_name = data_attribute_name(name)?;
+        self.attr(attr_name)
+    }
+}
+
+/// The `data-*` attributes of an element, returned by [`Document::dataset`]
+pub struct Dataset<'doc> {
+    doc: &'doc mut Document,
+    node_id: usize,
+}
+
+impl Document {
+    /// The `data-*` attributes of an element, for reading and writing them by their camelCase names
+    pub fn dataset(&mut self, node_id: usize) -> Dataset<'_> {
+        Dataset { doc: self, node_id }
+    }
+}
+
+impl Dataset<'_> {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.doc.nodes[self.node_id].element_data()?.data(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Every `data-*` attribute, as (camelCase name, value) pairs
+    pub fn to_vec(&self) -> Vec<(String, String)> {
+        self.doc.nodes[self.node_id]
+            .element_data()
+            .map(|element_data| {
+                element_data
+                    .dataset()
+                    .map(|(name, value)| (name, value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Set a `data-*` attribute. Returns `false` (and does nothing) if the name can't be converted to an attribute
+    /// name, because it contains a dash followed by a lowercase letter.
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        let Some(attr_name) = data_attribute_name(name) else {
+            return false;
+        };
+        self.doc
+            .set_attribute(self.node_id, QualName::new(None, ns!(), attr_name), value);
+        true
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        if let Some(attr_name) = data_attribute_name(name) {
+            self.doc.remove_attribute(self.node_id, &attr_name);
+        }
+    }
+}
+
+/// The camelCase dataset name of a `data-*` attribute (`data-foo-bar` is `fooBar`)
+fn dataset_name(attr_name: &str) -> Option<String> {
+    let name = attr_name.strip_prefix("data-")?;
+    let mut dataset_name = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '-' && next.is_ascii_lowercase() => {
+                dataset_name.push(next.to_ascii_uppercase());
+                chars.next();
+            }
+            _ => dataset_name.push(c),
+        }
+    }
+    Some(dataset_name)
+}
+
+/// The `data-*` attribute of a camelCase dataset name (`fooBar` is `data-foo-bar`)
+fn data_attribute_name(name: &str) -> Option<LocalName> {
+    let mut chars = name.chars().peekable();
+    let mut attr_name = String::from("data-");
+    while let Some(c) = chars.next() {
+        if c == '-' && chars.peek().is_some_and(|next| next.is_ascii_lowercase()) {
+            return None;
+        }
+        if c.is_ascii_uppercase() {
+            attr_name.push('-');
+            attr_name.push(c.to_ascii_lowercase());
+        } else {
+            attr_name.push(c);
+        }
+    }
+    Some(LocalName::from(attr_name))
+}
+
+#[test]
+fn dataset_names_round_trip() {
+    assert_eq!(dataset_name("data-foo-bar").as_deref(), Some("fooBar"));
+    assert_eq!(dataset_name("data-foo--bar").as_deref(), Some("foo-Bar"));
+    assert_eq!(dataset_name("class"), None);
+    assert_eq!(
+        data_attribute_name("fooBar").as_deref(),
+        Some("data-foo-bar")
+    );
+    assert_eq!(data_attribute_name("foo-bar"), None);
+}
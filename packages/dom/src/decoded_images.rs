@@ -0,0 +1,286 @@
+//! Decoding images at the size they're displayed at
+//!
+//! An `<img>` keeps the encoded image it fetched (its [`ImageSource`]), and only reads the image's natural size up
+//! front, which is all that layout needs. After layout, the image is decoded and downscaled to the size it's displayed
+//! at in device pixels (its content box times the document's scale, which includes the device pixel ratio and zoom),
+//! so an 8000px photo shown in a 300px box only keeps 300px worth of pixels. It's decoded again at a higher resolution
+//! when it's displayed larger (e.g. when zooming in), and at a lower one when it's displayed at less than half the
+//! size it was decoded at.
+//!
+//! Decoded images are kept under a memory limit ([`Document::set_image_memory_limit`]). Images are given memory in
+//! order of their distance from the visual viewport, so once the limit is reached, images far outside the viewport
+//! are decoded at a lower resolution, or not at all until they're scrolled closer.
+//!
+//! SVG images are rasterized at exactly the size they're displayed at, even beyond their natural size, on a worker
+//! thread (see [`crate::svg_images`]).
+//!
+//! Note: the `image` crate can't decode at a reduced size, so each image is briefly decoded at its natural size before
+//! it's downscaled. The frames of videos are kept at their natural size.
+use std::sync::Arc;
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::image_formats::{decode_image, image_dimensions};
+use crate::resource_errors::ResourceError;
+use crate::util::ImageFetchErr;
+use crate::Document;
+
+/// The memory that a document's decoded images can use by default, in bytes
+pub const DEFAULT_IMAGE_MEMORY_LIMIT: usize = 512 * 1024 * 1024;
+
+/// The bytes of a fetched image, which are decoded when the image is displayed
+#[derive(Clone, Debug)]
+pub struct ImageSource {
+    pub url: String,
+    pub bytes: Arc<Vec<u8>>,
+    /// The natural width of the image, in pixels
+    pub width: u32,
+    /// The natural height of the image, in pixels
+    pub height: u32,
+    /// The parsed image, if it's an SVG image (see [`crate::svg_images`])
+    pub svg: Option<Arc<usvg::Tree>>,
+}
+
+impl ImageSource {
+    /// Read the format and natural size of an encoded image, without decoding it
+    pub(crate) fn new(url: String, bytes: Vec<u8>) -> Result<Self, ImageFetchErr> {
+        if is_svg(&bytes) {
+            let tree = usvg::Tree::from_data(&bytes, &usvg::Options::default())?;
+            let size = tree.size().to_int_size();
+            return Ok(Self {
+                url,
+                bytes: Arc::new(bytes),
+                width: size.width(),
+                height: size.height(),
+                svg: Some(Arc::new(tree)),
+            });
+        }
+
+        let (width, height) = image_dimensions(&bytes)?;
+        Ok(Self {
+            url,
+            bytes: Arc::new(bytes),
+            width,
+            height,
+            svg: None,
+        })
+    }
+
+    /// Decode the image, downscaled to the given size if it's smaller than the natural size
+    fn decode(&self, width: u32, height: u32) -> Result<DynamicImage, ImageFetchErr> {
+        let image = decode_image(&self.bytes, Some((width, height)))?;
+        if width >= image.width() && height >= image.height() {
+            return Ok(image);
+        }
+        Ok(image.resize_exact(width, height, FilterType::Triangle))
+    }
+}
+
+/// Whether an image is an SVG image, which starts with an XML declaration or an `<svg>` element
+fn is_svg(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    bytes.starts_with(b"<svg") || bytes.starts_with(b"<?xml")
+}
+
+/// The memory used by a decoded image of the given size, in bytes
+fn image_bytes(width: u32, height: u32) -> usize {
+    width as usize * height as usize * 4
+}
+
+impl Document {
+    /// How much memory (in bytes) the document's decoded images can use
+    pub fn image_memory_limit(&self) -> usize {
+        self.image_memory_limit
+    }
+
+    /// Set how much memory (in bytes) the document's decoded images can use. Images are decoded again to fit.
+    pub fn set_image_memory_limit(&mut self, bytes: usize) {
+        self.image_memory_limit = bytes;
+        self.invalidate();
+    }
+
+    /// The memory used by the document's decoded images (other than videos), in bytes
+    pub fn decoded_image_bytes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter_map(|(_, node)| node.element_data())
+            .filter(|element_data| element_data.image_source.is_some())
+            .filter_map(|element_data| element_data.image.as_ref())
+            .map(|image| image_bytes(image.width(), image.height()))
+            .sum()
+    }
+
+    /// Show an encoded image in an element. It's decoded once the element has been laid out.
+    pub(crate) fn set_image_source(&mut self, node_id: usize, source: ImageSource) {
+        let Some(element_data) = self.nodes[node_id].element_data_mut() else {
+            return;
+        };
+        element_data.image_source = Some(source);
+        element_data.image = None;
+        element_data.broken_image = false;
+        element_data.alt_text_layout = None;
+        self.invalidate();
+    }
+
+    /// Decode (or drop) each image at the size it's displayed at, within the memory limit
+    pub(crate) fn fit_decoded_images(&mut self) {
+        let visible = self.visual_viewport();
+        let mut images: Vec<(f32, usize, (u32, u32))> = Vec::new();
+        for (node_id, node) in self.nodes.iter() {
+            let Some(source) = node
+                .element_data()
+                .and_then(|data| data.image_source.as_ref())
+            else {
+                continue;
+            };
+            let Some(rect) = self.node_document_rect(node_id) else {
+                continue;
+            };
+
+            // The size of the content box in device pixels, which an image is never decoded beyond
+            let layout = node.final_layout;
+            let content_width = layout.size.width
+                - layout.padding.left
+                - layout.padding.right
+                - layout.border.left
+                - layout.border.right;
+            let content_height = layout.size.height
+                - layout.padding.top
+                - layout.padding.bottom
+                - layout.border.top
+                - layout.border.bottom;
+            let mut size = (
+                (content_width * self.scale).ceil().max(1.0) as u32,
+                (content_height * self.scale).ceil().max(1.0) as u32,
+            );
+            // SVG images are rasterized at any size, but raster images are never scaled up
+            if source.svg.is_none() {
+                size = (size.0.min(source.width), size.1.min(source.height));
+            }
+
+            let distance = (visible.y - rect.bottom())
+                .max(rect.y - visible.bottom())
+                .max(0.0);
+            images.push((distance, node_id, size));
+        }
+        images.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut available = self.image_memory_limit;
+        for (_, node_id, (width, height)) in images {
+            // Images beyond the limit are shrunk to the memory that's left, or not decoded at all
+            let (width, height) = match image_bytes(width, height) {
+                bytes if bytes <= available => (width, height),
+                bytes => {
+                    let shrink = (available as f64 / bytes as f64).sqrt();
+                    (
+                        (width as f64 * shrink) as u32,
+                        (height as f64 * shrink) as u32,
+                    )
+                }
+            };
+
+            let element_data = self.nodes[node_id].element_data_mut().unwrap();
+            let decoded = element_data
+                .image
+                .as_ref()
+                .map(|image| (image.width(), image.height()));
+            if width == 0 || height == 0 {
+                element_data.image = None;
+                continue;
+            }
+
+            // The decoded image is kept while it's at least as large as needed, and not more than twice as large.
+            // SVG images are kept while they're exactly the size needed, so that they stay crisp.
+            let source = element_data.image_source.clone().unwrap();
+            let max_size = match source.svg {
+                Some(_) => (width, height),
+                None => (width.saturating_mul(2), height.saturating_mul(2)),
+            };
+            let keep = decoded.filter(|(decoded_width, decoded_height)| {
+                (width..=max_size.0).contains(decoded_width)
+                    && (height..=max_size.1).contains(decoded_height)
+                    && image_bytes(*decoded_width, *decoded_height) <= available
+            });
+            if let Some((decoded_width, decoded_height)) = keep {
+                available -= image_bytes(decoded_width, decoded_height);
+                continue;
+            }
+
+            // SVG images are rasterized on a worker thread, and keep showing their previous rasterization meanwhile
+            if let Some(tree) = &source.svg {
+                available -= image_bytes(width, height).min(available);
+                self.svg_rasterizer.request(node_id, tree, width, height);
+                continue;
+            }
+
+            match source.decode(width, height) {
+                Ok(image) => {
+                    available -= image_bytes(image.width(), image.height()).min(available);
+                    element_data.set_image(Arc::new(image));
+                }
+                Err(error) => {
+                    element_data.image = None;
+                    element_data.image_source = None;
+                    element_data.broken_image = true;
+                    // The image was laid out at its natural size, but is now laid out with its alt text
+                    self.invalidate();
+                    self.report_resource_error(ResourceError::Image {
+                        node_id,
+                        url: source.url,
+                        error,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn decodes_images_at_their_displayed_size() {
+    use crate::test_util::parse_test_document;
+    use std::io::Cursor;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <img id=photo style='width: 30px; display: block'>\
+        <div style='height: 1000px'></div><img id=far style='width: 40px; display: block'>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+
+    // A 200x100 PNG
+    let mut png = Vec::new();
+    DynamicImage::new_rgba8(200, 100)
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let photo = doc.get_element_by_id("photo").unwrap();
+    let far = doc.get_element_by_id("far").unwrap();
+    for node_id in [photo, far] {
+        let source = ImageSource::new("photo.png".into(), png.clone()).unwrap();
+        doc.set_image_source(node_id, source);
+    }
+    doc.resolve();
+
+    // Layout uses the natural aspect ratio, and the pixels are decoded at the displayed size
+    let decoded = |doc: &Document, node_id: usize| {
+        let image = doc.nodes[node_id].element_data().unwrap().image.clone();
+        image.map(|image| (image.width(), image.height()))
+    };
+    assert_eq!(doc.nodes[photo].final_layout.size.height, 15.0);
+    assert_eq!(decoded(&doc, photo), Some((30, 15)));
+    assert_eq!(decoded(&doc, far), Some((40, 20)));
+
+    // Zooming in decodes at a higher resolution
+    doc.set_scale(2.0);
+    doc.resolve();
+    assert_eq!(decoded(&doc, photo), Some((60, 30)));
+
+    // Images far from the viewport go without once the limit is reached
+    doc.set_image_memory_limit(60 * 30 * 4);
+    doc.resolve();
+    assert_eq!(decoded(&doc, photo), Some((60, 30)));
+    assert_eq!(decoded(&doc, far), None);
+    assert_eq!(doc.decoded_image_bytes(), 60 * 30 * 4);
+}
@@ -1,14 +1,55 @@
+use crate::adopted_stylesheets::{next_lock_id, ConstructedStyleSheet};
+use crate::color_scheme::ColorSchemeState;
+use crate::context_menu::{Clipboard, ContextMenuProvider, DefaultContextMenu, OpenContextMenu};
+use crate::csp::ContentSecurity;
+use crate::custom_elements::CustomElementRegistry;
+use crate::damage::{Damage, PaintKey, PaintRecord};
+use crate::decoded_images::DEFAULT_IMAGE_MEMORY_LIMIT;
+use crate::editing::Caret;
 use crate::events::RendererEvent;
+use crate::find::FindState;
+use crate::font_faces::FontFaces;
+use crate::hot_reload::LocalResource;
+use crate::intersection_observer::IntersectionObservers;
+use crate::layout::text_cache::ShapingCache;
+use crate::layout::LayoutStats;
+use crate::metadata::{DocumentMetadata, MetadataHandler};
+use crate::mutation_observer::MutationObservers;
+use crate::navigation::Navigation;
+use crate::network::NetworkProvider;
+use crate::node::Attribute;
 use crate::node::TextBrush;
+use crate::pickers::{FileDialog, OpenPicker};
+use crate::profiling::{FramePhase, FrameProfiler};
+use crate::resize_observer::ResizeObservers;
+use crate::resource_errors::ResourceErrorHandler;
+use crate::scheduler::FrameScheduler;
+use crate::scrolling::ScrollAnimation;
+use crate::svg_images::SvgRasterizer;
+use crate::tab_size::TabSize;
+use crate::timers::Timers;
+use crate::top_layer::TopLayerKind;
+use crate::undo::EditHistory;
+use crate::video::VideoSource;
+use crate::visual_viewport::ViewportInsets;
 use crate::{Node, NodeData, TextNodeData};
+use html5ever::{local_name, LocalName, QualName};
 // use quadtree_rs::Quadtree;
-use selectors::{matching::QuirksMode, Element};
+use selectors::{
+    matching::{ElementSelectorFlags, QuirksMode},
+    Element,
+};
 use slab::Slab;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use style::attr::{AttrIdentifier, AttrValue};
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::selector_parser::ServoElementSnapshot;
 use style::servo::media_queries::FontMetricsProvider;
 use style::servo_arc::Arc as ServoArc;
+use style::values::GenericAtomIdent;
+use style::Atom;
 use style::{
     dom::{TDocument, TNode},
     media_queries::{Device, MediaList},
@@ -62,6 +103,9 @@ pub struct Document {
 
     pub(crate) guard: SharedRwLock,
 
+    /// Identifies the lock, which is shared by documents created with [`Document::new_sharing_lock`]
+    pub(crate) lock_id: u64,
+
     /// The styling engine of firefox
     pub(crate) stylist: Stylist,
 
@@ -81,21 +125,179 @@ pub struct Document {
 
     pub(crate) stylesheets: HashMap<String, DocumentStyleSheet>,
 
+    /// The user agent stylesheets, in order (see [`crate::ua_stylesheets`])
+    pub(crate) ua_stylesheets: Vec<DocumentStyleSheet>,
+
+    /// The user stylesheets, keyed by their source text (see [`crate::ua_stylesheets`])
+    pub(crate) user_stylesheets: HashMap<String, DocumentStyleSheet>,
+
+    /// Constructed stylesheets adopted by the document, and the sheets applied to the stylist for them (see
+    /// [`crate::adopted_stylesheets`])
+    pub(crate) adopted_stylesheets: Vec<(ConstructedStyleSheet, DocumentStyleSheet)>,
+
     /// A Parley font context
     pub(crate) font_ctx: parley::FontContext,
     /// A Parley layout context
     pub(crate) layout_ctx: parley::LayoutContext<TextBrush>,
+    /// The `@font-face` rules of the document's stylesheets (see [`crate::font_faces`])
+    pub(crate) font_faces: FontFaces,
+    /// The distance between tab stops (see [`crate::tab_size`])
+    pub(crate) tab_size: TabSize,
+    /// The time spent in each phase of recent frames (see [`crate::profiling`])
+    pub(crate) profiler: FrameProfiler,
+    /// How much memory decoded images can use (see [`crate::decoded_images`])
+    pub(crate) image_memory_limit: usize,
+    /// Rasterizes SVG images on a worker thread (see [`crate::svg_images`])
+    pub(crate) svg_rasterizer: SvgRasterizer,
 
     pub(crate) hover_node_id: Option<usize>,
+
+    /// The element that has keyboard focus (see [`crate::focus`])
+    pub(crate) focus_node_id: Option<usize>,
+
+    /// Frame providers attached to `<video>` elements, keyed by node id
+    pub(crate) video_sources: HashMap<usize, VideoSource>,
+
+    /// Nested documents of `<iframe>` elements, keyed by node id
+    pub(crate) sub_documents: HashMap<usize, Box<Document>>,
+
+    /// The popovers that are currently showing, in the order they were shown
+    pub(crate) open_popovers: Vec<usize>,
+    /// The `popovertarget` button that showed each open popover (see [`crate::anchor`])
+    pub(crate) popover_invokers: HashMap<usize, usize>,
+
+    /// The elements in the top layer, from bottom-most to top-most
+    pub(crate) top_layer: Vec<(usize, TopLayerKind)>,
+
+    /// How far the viewport has been scrolled down the document
+    pub(crate) viewport_scroll: f64,
+    /// How far the edges of the viewport are covered by the soft keyboard or system bars
+    pub(crate) viewport_insets: ViewportInsets,
+
+    /// The `<input type=range>` whose thumb is being dragged
+    pub(crate) active_range_drag: Option<usize>,
+
+    /// The date or color picker popup that is showing
+    pub(crate) open_picker: Option<OpenPicker>,
+
+    /// The embedder-provided file dialog used by `<input type=file>`
+    pub(crate) file_dialog: Option<Box<dyn FileDialog>>,
+    /// The file input whose file dialog is showing
+    pub(crate) file_dialog_input: Option<usize>,
+
+    /// The context menu that is showing (see [`crate::context_menu`])
+    pub(crate) context_menu: Option<OpenContextMenu>,
+    /// Decides what context menus contain
+    pub(crate) context_menu_provider: Option<Box<dyn ContextMenuProvider>>,
+    /// The embedder-provided clipboard that text is copied to
+    pub(crate) clipboard: Option<Box<dyn Clipboard>>,
+    /// The element chosen to be inspected from a context menu, until the embedder takes it
+    pub(crate) inspect_request: Option<usize>,
+
+    /// The embedder-provided navigator, and any navigation scheduled by `<meta http-equiv=refresh>` (see
+    /// [`crate::navigation`])
+    pub(crate) navigation: Navigation,
+
+    /// Delayed and repeating callbacks (see [`crate::timers`])
+    pub(crate) timers: Timers,
+
+    /// The embedder's handler for resources that couldn't be loaded (see [`crate::resource_errors`])
+    pub(crate) resource_error_handler: Option<ResourceErrorHandler>,
+
+    /// The embedder's handler for changes to the title, icon and theme color (see [`crate::metadata`])
+    pub(crate) metadata_handler: Option<MetadataHandler>,
+    /// The metadata that was last given to the metadata handler
+    pub(crate) metadata: Option<DocumentMetadata>,
+
+    /// The stylesheets and images that were loaded from files (see [`crate::hot_reload`])
+    pub(crate) local_resources: Vec<LocalResource>,
+
+    /// The provider that stylesheets and images are fetched with (see [`crate::network`])
+    pub(crate) network: Arc<NetworkProvider>,
+
+    /// The Content Security Policies that restrict the document's subresources (see [`crate::csp`])
+    pub(crate) content_security: ContentSecurity,
+
+    /// The files chosen for each `<input type=file>`, keyed by node id
+    pub(crate) selected_files: HashMap<usize, Vec<PathBuf>>,
+
+    /// Form controls that the user has interacted with (for `:user-invalid`)
+    pub(crate) user_interacted: HashSet<usize>,
+
+    /// Password inputs whose value is displayed rather than masked
+    pub(crate) revealed_passwords: HashSet<usize>,
+
+    /// The selections of text fields that have been edited or selected, as the (anchor, focus) character offsets
+    /// within their value
+    pub(crate) input_selections: HashMap<usize, (usize, usize)>,
+
+    /// The `value` attributes of inputs from before the user changed them, which resetting their form restores
+    pub(crate) default_values: HashMap<usize, Option<String>>,
+
+    /// The element being pressed, which (with its ancestors) matches `:active`
+    pub(crate) active_element: Option<usize>,
+
+    /// The caret within a `contenteditable` element
+    pub(crate) caret: Option<Caret>,
+
+    /// The other end of the selection from the caret (if the selection is not collapsed)
+    pub(crate) selection_anchor: Option<Caret>,
+
+    /// The edits that can be undone and redone
+    pub(crate) edit_history: EditHistory,
+
+    /// The results of the last find-in-page search
+    pub(crate) find_state: Option<FindState>,
+
+    /// What each box looked like when it was last painted (see [`Document::collect_damage`])
+    pub(crate) paint_records: HashMap<PaintKey, PaintRecord>,
+
+    /// The viewport scroll when the document was last painted
+    pub(crate) painted_scroll: Option<f64>,
+
+    /// Damage that was marked explicitly since the last call to [`Document::collect_damage`]
+    pub(crate) pending_damage: Damage,
+    /// Pending animation frame callbacks and the frame clock
+    pub(crate) frame_scheduler: FrameScheduler,
+    /// The smooth scroll of the viewport in progress (see [`crate::scrolling`])
+    pub(crate) scroll_animation: Option<ScrollAnimation>,
+
+    /// The nodes that need to be laid out again in the current layout pass (see [`crate::layout::incremental`])
+    pub(crate) layout_dirty: HashSet<usize>,
+
+    /// How much of the document was laid out again by the last layout pass
+    pub(crate) layout_stats: LayoutStats,
+
+    /// Shaped text of inline layouts (see [`crate::layout::text_cache`])
+    pub(crate) shaping_cache: ShapingCache,
+
+    /// Nodes whose visibility is being observed (see [`crate::intersection_observer`])
+    pub(crate) intersection_observers: IntersectionObservers,
+
+    /// Nodes whose size is being observed (see [`crate::resize_observer`])
+    pub(crate) resize_observers: ResizeObservers,
+
+    /// Subscribers to changes to the tree, and the changes since the last update (see
+    /// [`crate::mutation_observer`])
+    pub(crate) mutation_observers: MutationObservers,
+
+    /// Custom element definitions and the lifecycle callbacks waiting to run (see [`crate::custom_elements`])
+    pub(crate) custom_elements: CustomElementRegistry,
+
+    /// The preferred and used color schemes (see [`crate::color_scheme`])
+    pub(crate) color_scheme: ColorSchemeState,
 }
 
 impl Document {
     pub fn new(device: Device) -> Self {
+        Self::new_with_lock(device, SharedRwLock::new(), next_lock_id())
+    }
+
+    pub(crate) fn new_with_lock(device: Device, guard: SharedRwLock, lock_id: u64) -> Self {
         let quirks = QuirksMode::NoQuirks;
         let stylist = Stylist::new(device, quirks);
         let snapshots = SnapshotMap::new();
         let nodes = Box::new(Slab::new());
-        let guard = SharedRwLock::new();
         let nodes_to_id = HashMap::new();
 
         // Make sure we turn on servo features
@@ -103,9 +305,12 @@ impl Document {
         style_config::set_bool("layout.grid.enabled", true);
         style_config::set_bool("layout.legacy_layout", true);
         style_config::set_bool("layout.columns.enabled", true);
+        // Logical properties are mapped to physical ones using the writing mode
+        style_config::set_bool("layout.writing-mode.enabled", true);
 
         let mut doc = Self {
             guard,
+            lock_id,
             nodes,
             stylist,
             snapshots,
@@ -114,10 +319,65 @@ impl Document {
             base_url: None,
             // quadtree: Quadtree::new(20),
             stylesheets: HashMap::new(),
+            ua_stylesheets: Vec::new(),
+            user_stylesheets: HashMap::new(),
+            adopted_stylesheets: Vec::new(),
             font_ctx: parley::FontContext::default(),
             layout_ctx: parley::LayoutContext::new(),
+            font_faces: FontFaces::default(),
+            tab_size: TabSize::default(),
+            profiler: FrameProfiler::default(),
+            image_memory_limit: DEFAULT_IMAGE_MEMORY_LIMIT,
+            svg_rasterizer: SvgRasterizer::default(),
 
             hover_node_id: None,
+            focus_node_id: None,
+            video_sources: HashMap::new(),
+            sub_documents: HashMap::new(),
+            open_popovers: Vec::new(),
+            popover_invokers: HashMap::new(),
+            top_layer: Vec::new(),
+            viewport_scroll: 0.0,
+            viewport_insets: ViewportInsets::default(),
+            active_range_drag: None,
+            open_picker: None,
+            file_dialog: None,
+            file_dialog_input: None,
+            context_menu: None,
+            context_menu_provider: Some(Box::new(DefaultContextMenu)),
+            clipboard: None,
+            inspect_request: None,
+            navigation: Navigation::default(),
+            timers: Timers::default(),
+            resource_error_handler: None,
+            metadata_handler: None,
+            metadata: None,
+            local_resources: Vec::new(),
+            network: Arc::new(NetworkProvider::default()),
+            content_security: ContentSecurity::default(),
+            selected_files: HashMap::new(),
+            user_interacted: HashSet::new(),
+            revealed_passwords: HashSet::new(),
+            input_selections: HashMap::new(),
+            default_values: HashMap::new(),
+            active_element: None,
+            caret: None,
+            selection_anchor: None,
+            edit_history: EditHistory::default(),
+            find_state: None,
+            paint_records: HashMap::new(),
+            painted_scroll: None,
+            pending_damage: Damage::default(),
+            frame_scheduler: FrameScheduler::default(),
+            scroll_animation: None,
+            layout_dirty: HashSet::new(),
+            layout_stats: LayoutStats::default(),
+            shaping_cache: ShapingCache::default(),
+            intersection_observers: IntersectionObservers::default(),
+            resize_observers: ResizeObservers::default(),
+            mutation_observers: MutationObservers::default(),
+            custom_elements: CustomElementRegistry::default(),
+            color_scheme: ColorSchemeState::default(),
         };
 
         // Initialise document with root Document node
@@ -131,6 +391,10 @@ impl Document {
         self.base_url = Url::parse(url).ok();
     }
 
+    pub fn base_url(&self) -> Option<&Url> {
+        self.base_url.as_ref()
+    }
+
     pub fn guard(&self) -> &SharedRwLock {
         &self.guard
     }
@@ -147,19 +411,67 @@ impl Document {
         self.nodes.get_mut(node_id)
     }
 
+    /// Find the element with the given id attribute
+    pub fn get_element_by_id(&self, id: &str) -> Option<usize> {
+        if let Some(&node_id) = self.nodes_to_id.get(id) {
+            if self
+                .nodes
+                .get(node_id)
+                .and_then(|node| node.attr(local_name!("id")))
+                == Some(id)
+            {
+                return Some(node_id);
+            }
+        }
+
+        // Fall back to a full scan as the id map isn't kept up to date by all mutations
+        self.nodes
+            .iter()
+            .find(|(node_id, node)| {
+                node.is_element()
+                    && node.attr(local_name!("id")) == Some(id)
+                    && !self.is_template_contents(*node_id)
+            })
+            .map(|(node_id, _)| node_id)
+    }
+
+    /// Find the elements with the given tag name, in tree order
+    pub fn get_elements_by_tag_name(&self, name: LocalName) -> Vec<usize> {
+        fn collect(doc: &Document, node_id: usize, name: &LocalName, found: &mut Vec<usize>) {
+            let node = &doc.nodes[node_id];
+            if node.raw_dom_data.is_element_with_tag_name(name) {
+                found.push(node_id);
+            }
+            for child_id in node.children.iter().copied() {
+                collect(doc, child_id, name, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(self, 0, &name, &mut found);
+        found
+    }
+
     pub fn root_node(&self) -> &Node {
         &self.nodes[0]
     }
 
     pub fn root_element(&self) -> &Node {
+        self.try_root_element().unwrap()
+    }
+
+    /// The root element, or `None` if the document is empty
+    pub fn try_root_element(&self) -> Option<&Node> {
         TDocument::as_node(&self.root_node())
-            .first_element_child()
-            .unwrap()
+            .first_element_child()?
             .as_element()
-            .unwrap()
     }
 
     pub fn set_scale(&mut self, scale: f32) {
+        if scale != self.scale {
+            // Text is shaped at the new scale
+            self.invalidate_layout();
+        }
         self.scale = scale;
     }
 
@@ -170,6 +482,7 @@ impl Document {
         let guard = self.guard.clone();
 
         entry.insert(Node::new(slab_ptr, id, guard, node_data));
+        self.queue_custom_element_upgrade(id);
 
         // self.quadtree.insert(
         //     AreaBuilder::default()
@@ -190,23 +503,61 @@ impl Document {
     }
 
     pub fn deep_clone_node(&mut self, node_id: usize) -> usize {
+        self.clone_node(node_id, true)
+    }
+
+    /// Create a detached copy of a node (the equivalent of `Node.cloneNode`)
+    ///
+    /// Elements are copied with their attributes and inline styles, but not their event listeners or layout. If
+    /// `deep` is set, the node's descendants (and the contents of `<template>` elements) are copied too.
+    pub fn clone_node(&mut self, node_id: usize, deep: bool) -> usize {
         // Load existing node
         let node = &self.nodes[node_id];
-        let data = node.raw_dom_data.clone();
+        let mut data = node.raw_dom_data.clone();
         let children = node.children.clone();
 
+        // Give the copy its own inline style and leave derived state to be recomputed
+        let mut template_contents = None;
+        if let NodeData::Element(ref mut elem) | NodeData::AnonymousBlock(ref mut elem) = data {
+            elem.flush_style_attribute(&self.guard);
+            elem.inline_layout = None;
+            template_contents = elem.template_contents.take();
+        }
+
         // Create new node
         let new_node_id = self.create_node(data);
+        let new_node = &self.nodes[new_node_id];
+        if new_node.is_element() {
+            *new_node.stylo_element_data.borrow_mut() = Some(Default::default());
+        }
+        if let Some(contents_id) = template_contents {
+            let contents_clone_id = if deep {
+                self.clone_node(contents_id, true)
+            } else {
+                self.create_node(NodeData::DocumentFragment)
+            };
+            self.nodes[new_node_id]
+                .element_data_mut()
+                .unwrap()
+                .template_contents = Some(contents_clone_id);
+        }
+
+        if !deep {
+            return new_node_id;
+        }
 
         // Recursively clone children
         let new_children: Vec<usize> = children
             .into_iter()
-            .map(|child_id| self.deep_clone_node(child_id))
+            .map(|child_id| self.clone_node(child_id, true))
             .collect();
-        for &child_id in &new_children {
-            self.nodes[child_id].parent = Some(new_node_id);
+        for (child_idx, &child_id) in new_children.iter().enumerate() {
+            let child = &mut self.nodes[child_id];
+            child.parent = Some(new_node_id);
+            child.child_idx = child_idx;
         }
         self.nodes[new_node_id].children = new_children;
+        self.flush_sibling_elements(new_node_id);
 
         new_node_id
     }
@@ -238,6 +589,7 @@ impl Document {
         }
 
         self.nodes[parent_id].children = children;
+        self.child_list_changed(parent_id, inserted_node_ids, &[]);
     }
 
     pub fn append(&mut self, node_id: usize, appended_node_ids: &[usize]) {
@@ -261,12 +613,31 @@ impl Document {
         }
 
         self.nodes[parent_id].children = children;
+        self.child_list_changed(parent_id, appended_node_ids, &[]);
+    }
+
+    /// Append nodes to the end of the children of `parent_id`.
+    ///
+    /// (Note that [`Document::append`] instead appends nodes to the children of the *parent* of its node.)
+    pub fn append_children(&mut self, parent_id: usize, child_ids: &[usize]) {
+        let mut child_idx = self.nodes[parent_id].children.len();
+        for &child_id in child_ids {
+            let child = &mut self.nodes[child_id];
+            child.parent = Some(parent_id);
+            child.child_idx = child_idx;
+            child_idx += 1;
+        }
+        self.nodes[parent_id].children.extend_from_slice(child_ids);
+        self.child_list_changed(parent_id, child_ids, &[]);
     }
 
     pub fn remove_node(&mut self, node_id: usize) -> Option<Node> {
         fn remove_node_ignoring_parent(doc: &mut Document, node_id: usize) -> Option<Node> {
             let node = doc.nodes.try_remove(node_id);
             if let Some(node) = &node {
+                // Observers mustn't carry on observing whichever node reuses the id
+                doc.unobserve_removed_intersections(node_id);
+                doc.unobserve_removed_resizes(node_id);
                 for &child in &node.children {
                     remove_node_ignoring_parent(doc, child);
                 }
@@ -297,19 +668,89 @@ impl Document {
             }
 
             self.nodes[parent_id].children = children;
+            self.child_list_changed(parent_id, &[], &[node_id]);
         }
 
         node
     }
 
-    pub fn resolve_url(&self, raw: &str) -> url::Url {
+    /// Update the caches and styles that depend on a node's children after its child list changes, and record the
+    /// change for mutation observers
+    pub(crate) fn child_list_changed(
+        &mut self,
+        parent_id: usize,
+        added: &[usize],
+        removed: &[usize],
+    ) {
+        self.flush_sibling_elements(parent_id);
+        self.restyle_for_child_list_change(parent_id);
+        for node_id in added.iter().copied() {
+            self.queue_custom_element_connected(node_id);
+        }
+        self.record_child_list_change(parent_id, added, removed);
+    }
+
+    /// Cache the previous and next sibling elements of a node's children
+    pub fn flush_sibling_elements(&mut self, parent_id: usize) {
+        let children = std::mem::take(&mut self.nodes[parent_id].children);
+        let mut prev_element = None;
+        for child_id in children.iter().copied() {
+            self.nodes[child_id].prev_sibling_element = prev_element;
+            if self.nodes[child_id].is_element() {
+                prev_element = Some(child_id);
+            }
+        }
+        let mut next_element = None;
+        for child_id in children.iter().rev().copied() {
+            self.nodes[child_id].next_sibling_element = next_element;
+            if self.nodes[child_id].is_element() {
+                next_element = Some(child_id);
+            }
+        }
+        self.nodes[parent_id].children = children;
+    }
+
+    /// Restyle the children whose structural selectors (`:nth-child`, `:first-child`, `:empty`, etc) may match
+    /// differently now that the node's child list has changed. Selector matching flags the nodes whose children are
+    /// matched by such selectors.
+    fn restyle_for_child_list_change(&mut self, parent_id: usize) {
+        let flags = self.nodes[parent_id].element_selector_flags();
+        let mut restyle = |node: &Node| {
+            if let Some(data) = node.stylo_element_data.borrow_mut().as_mut() {
+                data.hint.insert(RestyleHint::restyle_subtree());
+            }
+        };
+
+        if flags.contains(ElementSelectorFlags::HAS_EMPTY_SELECTOR) {
+            restyle(&self.nodes[parent_id]);
+        }
+
+        let children = &self.nodes[parent_id].children;
+        let mut elements = children
+            .iter()
+            .map(|id| &self.nodes[*id])
+            .filter(|node| node.is_element());
+        if flags.intersects(
+            ElementSelectorFlags::HAS_SLOW_SELECTOR
+                | ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS,
+        ) {
+            elements.for_each(restyle);
+        } else if flags.contains(ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR) {
+            let first = elements.next();
+            let last = elements.last();
+            first.into_iter().chain(last).for_each(restyle);
+        }
+    }
+
+    pub fn resolve_url(&self, raw: &str) -> Result<url::Url, url::ParseError> {
         match &self.base_url {
-            Some(base_url) => base_url.join(raw).unwrap(),
-            None => url::Url::parse(raw).unwrap(),
+            Some(base_url) => base_url.join(raw),
+            None => url::Url::parse(raw),
         }
     }
 
     pub fn flush_child_indexes(&mut self, target_id: usize, child_idx: usize, _level: usize) {
+        self.flush_sibling_elements(target_id);
         let node = &mut self.nodes[target_id];
         node.child_idx = child_idx;
 
@@ -337,6 +778,17 @@ impl Document {
     }
 
     pub fn add_stylesheet(&mut self, css: &str) {
+        let sheet = self.parse_stylesheet(css, Origin::Author);
+
+        self.stylesheets.insert(css.to_string(), sheet.clone());
+
+        self.stylist.append_stylesheet(sheet, &self.guard.read());
+
+        self.stylist
+            .force_stylesheet_origins_dirty(Origin::Author.into());
+    }
+
+    pub(crate) fn parse_stylesheet(&self, css: &str, origin: Origin) -> DocumentStyleSheet {
         let data = Stylesheet::from_str(
             css,
             UrlExtraData::from(
@@ -344,7 +796,7 @@ impl Document {
                     .parse::<Url>()
                     .unwrap(),
             ),
-            Origin::UserAgent,
+            origin,
             ServoArc::new(self.guard.wrap(MediaList::empty())),
             self.guard.clone(),
             None,
@@ -353,14 +805,7 @@ impl Document {
             AllowImportRules::Yes,
         );
 
-        let sheet = DocumentStyleSheet(ServoArc::new(data));
-
-        self.stylesheets.insert(css.to_string(), sheet.clone());
-
-        self.stylist.append_stylesheet(sheet, &self.guard.read());
-
-        self.stylist
-            .force_stylesheet_origins_dirty(Origin::Author.into());
+        DocumentStyleSheet(ServoArc::new(data))
     }
 
     pub fn snapshot_node(&mut self, node_id: usize) {
@@ -370,10 +815,9 @@ impl Document {
         node.snapshot_handled
             .store(false, std::sync::atomic::Ordering::SeqCst);
 
-        // TODO: handle invalidations other than hover
-        if let Some(_existing_snapshot) = self.snapshots.get_mut(&opaque_node_id) {
-            // Do nothing
-            // TODO: update snapshot
+        // Snapshots hold the state from before the first change since the last restyle
+        if let Some(existing_snapshot) = self.snapshots.get_mut(&opaque_node_id) {
+            existing_snapshot.state.get_or_insert(node.element_state);
         } else {
             self.snapshots.insert(
                 opaque_node_id,
@@ -389,6 +833,133 @@ impl Document {
         }
     }
 
+    /// Set the value of an attribute, adding the attribute if it doesn't exist. The change is recorded in a snapshot
+    /// so that only the elements whose selectors it affects are restyled.
+    pub fn set_attribute(&mut self, node_id: usize, name: QualName, value: &str) {
+        let Some(element_data) = self.nodes[node_id].element_data() else {
+            return;
+        };
+        let old_value = element_data.attr(name.local.clone());
+        if old_value == Some(value) {
+            return;
+        }
+        let old_value = old_value.map(String::from);
+
+        self.snapshot_attribute_change(node_id, &name.local);
+        self.record_attribute_change(node_id, &name.local);
+
+        let element_data = self.nodes[node_id].element_data_mut().unwrap();
+        match element_data
+            .attrs
+            .iter_mut()
+            .find(|attr| attr.name.local == name.local)
+        {
+            Some(attr) => attr.value = value.to_string(),
+            None => element_data.attrs.push(Attribute {
+                name: name.clone(),
+                value: value.to_string(),
+            }),
+        }
+        self.flush_attribute(node_id, &name.local);
+        self.queue_custom_element_attribute_changed(node_id, &name.local, old_value);
+    }
+
+    /// Remove an attribute (if the element has it), recording the change like [`Document::set_attribute`]
+    pub fn remove_attribute(&mut self, node_id: usize, name: &LocalName) {
+        let Some(element_data) = self.nodes[node_id].element_data() else {
+            return;
+        };
+        let Some(old_value) = element_data.attr(name.clone()).map(String::from) else {
+            return;
+        };
+
+        self.snapshot_attribute_change(node_id, name);
+        self.record_attribute_change(node_id, name);
+
+        let element_data = self.nodes[node_id].element_data_mut().unwrap();
+        element_data.attrs.retain(|attr| attr.name.local != *name);
+        self.flush_attribute(node_id, name);
+        self.queue_custom_element_attribute_changed(node_id, name, Some(old_value));
+    }
+
+    /// Record an element's attributes from before the first attribute change since the last restyle, and which
+    /// attributes have changed since, so that stylo can work out which selectors the changes affect
+    pub(crate) fn snapshot_attribute_change(&mut self, node_id: usize, name: &LocalName) {
+        self.snapshot_node(node_id);
+
+        let node = &self.nodes[node_id];
+        let Some(element_data) = node.element_data() else {
+            return;
+        };
+        let snapshot = self.snapshots.get_mut(&TNode::opaque(&node)).unwrap();
+        if snapshot.attrs.is_none() {
+            let attrs = element_data
+                .attrs
+                .iter()
+                .map(|attr| {
+                    let value = match attr.name.local {
+                        local_name!("id") => AttrValue::from_atomic(attr.value.clone()),
+                        local_name!("class") => {
+                            AttrValue::from_serialized_tokenlist(attr.value.clone())
+                        }
+                        _ => AttrValue::String(attr.value.clone()),
+                    };
+                    let identifier = AttrIdentifier {
+                        local_name: GenericAtomIdent(attr.name.local.clone()),
+                        name: GenericAtomIdent(attr.name.local.clone()),
+                        namespace: GenericAtomIdent(attr.name.ns.clone()),
+                        prefix: attr.name.prefix.clone().map(GenericAtomIdent),
+                    };
+                    (identifier, value)
+                })
+                .collect();
+            snapshot.attrs = Some(attrs);
+        }
+
+        match *name {
+            local_name!("id") => snapshot.id_changed = true,
+            local_name!("class") => snapshot.class_changed = true,
+            _ => snapshot.other_attributes_changed = true,
+        }
+        let name = GenericAtomIdent(name.clone());
+        if !snapshot.changed_attrs.contains(&name) {
+            snapshot.changed_attrs.push(name);
+        }
+    }
+
+    /// Update the state derived from an attribute after it changes
+    fn flush_attribute(&mut self, node_id: usize, name: &LocalName) {
+        let guard = self.guard.clone();
+        let node = &mut self.nodes[node_id];
+        let element_data = node.element_data_mut().unwrap();
+        match *name {
+            local_name!("id") => {
+                element_data.id = element_data.attr(local_name!("id")).map(Atom::from);
+            }
+            local_name!("style") => {
+                element_data.flush_style_attribute(&guard);
+                if let Some(data) = node.stylo_element_data.borrow_mut().as_mut() {
+                    data.hint.insert(RestyleHint::RESTYLE_STYLE_ATTRIBUTE);
+                }
+            }
+            // Disabling a fieldset disables the controls within it, which changes whether they match `:disabled`
+            local_name!("disabled") => {
+                if let Some(data) = node.stylo_element_data.borrow_mut().as_mut() {
+                    data.hint.insert(RestyleHint::restyle_subtree());
+                }
+            }
+            // Inputs that display their value display the new one, and an open dropdown of suggestions is filtered by it.
+            // The selection of a text field moves to the end of the new value.
+            local_name!("value") => {
+                self.input_selections.remove(&node_id);
+                self.update_picker_input_text(node_id);
+                self.refilter_suggestions(node_id);
+            }
+            _ => {}
+        }
+        self.invalidate();
+    }
+
     /// Restyle the tree and then relayout it
     pub fn resolve(&mut self) {
         if TDocument::as_node(&&self.nodes[0])
@@ -399,20 +970,52 @@ impl Document {
             return;
         }
 
-        // we need to resolve stylist first since it will need to drive our layout bits
-        self.resolve_stylist();
+        self.time_phase(FramePhase::Style, |doc| {
+            // Run the custom element callbacks queued by the changes made since the last update, then report the changes
+            doc.process_custom_element_reactions();
+            doc.deliver_mutation_records();
+
+            // Closed popovers are hidden
+            doc.flush_popover_visibility();
 
-        // Fix up tree for layout (insert anonymous blocks as necessary, etc)
-        self.resolve_layout_children();
+            // Switch between the light and dark user agent styles
+            doc.flush_color_scheme();
 
-        // Merge stylo into taffy
-        self.flush_styles_to_layout(vec![self.root_element().id]);
+            // we need to resolve stylist first since it will need to drive our layout bits
+            doc.resolve_stylist();
+            doc.flush_font_faces();
+        });
 
-        // Next we resolve layout with the data resolved by stlist
-        self.resolve_layout();
+        self.time_phase(FramePhase::Layout, |doc| {
+            // Find the nodes whose layout inputs have changed since they were last laid out
+            doc.collect_layout_dirty();
+
+            // Fix up tree for layout (insert anonymous blocks as necessary, etc)
+            doc.resolve_layout_children();
+            doc.shaping_cache.sweep();
+
+            // Merge stylo into taffy
+            doc.flush_styles_to_layout(vec![doc.root_element().id]);
+
+            // Next we resolve layout with the data resolved by stlist
+            doc.resolve_layout();
+
+            // Nested documents are sized by the layout of their iframes
+            doc.resolve_sub_documents();
+
+            // Observe sizes and visibility now that nodes are in their final positions
+            doc.update_resize_observations();
+            doc.update_intersection_observations();
+
+            // Decode images at the size they're displayed at
+            doc.fit_decoded_images();
+        });
+
+        // Tell the embedder if the title, icon or theme color have changed
+        self.flush_metadata();
     }
 
-    // Takes (x, y) co-ordinates (relative to the )
+    // Takes (x, y) co-ordinates (relative to the document)
     pub fn hit(&self, x: f32, y: f32) -> Option<usize> {
         if TDocument::as_node(&&self.nodes[0])
             .first_element_child()
@@ -422,12 +1025,28 @@ impl Document {
             return None;
         }
 
+        // The top layer is positioned relative to the viewport and is above everything else
+        if let Some(hit) = self.hit_top_layer(x, y - self.viewport_scroll as f32) {
+            return hit;
+        }
+
         self.root_element().hit(x, y)
     }
 
     pub fn set_hover_to(&mut self, x: f32, y: f32) -> bool {
         let hover_node_id = self.hit(x, y);
 
+        // Route the hover into nested documents. Those that the point isn't within have their hover cleared.
+        let sub_document_hit = self.hit_sub_document(x, y);
+        let mut sub_document_changed = false;
+        for (node_id, sub_document) in self.sub_documents.iter_mut() {
+            let (x, y) = match sub_document_hit {
+                Some((hit_id, x, y)) if hit_id == *node_id => (x, y),
+                _ => (-1.0, -1.0),
+            };
+            sub_document_changed |= sub_document.set_hover_to(x, y);
+        }
+
         if hover_node_id != self.hover_node_id {
             let mut maybe_id = self.hover_node_id;
             while let Some(id) = maybe_id {
@@ -461,10 +1080,55 @@ impl Document {
 
             true
         } else {
-            false
+            sub_document_changed
         }
     }
 
+    /// How far the viewport has been scrolled down the document, in CSS pixels
+    pub fn viewport_scroll(&self) -> f64 {
+        self.viewport_scroll
+    }
+
+    pub fn set_viewport_scroll(&mut self, scroll: f64) {
+        self.viewport_scroll = scroll;
+    }
+
+    /// Scroll the viewport the minimum distance needed to make a vertical span of the document (in CSS pixels)
+    /// visible within the visual viewport. Spans taller than the visual viewport are aligned with its top.
+    pub fn scroll_into_view(&mut self, top: f64, bottom: f64) {
+        let inset_top = self.viewport_insets.top as f64;
+        let visible_height = self.visual_viewport().height as f64;
+
+        let scroll = if top < self.viewport_scroll + inset_top || bottom - top > visible_height {
+            top - inset_top
+        } else if bottom > self.viewport_scroll + inset_top + visible_height {
+            bottom - inset_top - visible_height
+        } else {
+            return;
+        };
+        self.viewport_scroll = scroll.min(self.max_viewport_scroll()).max(0.0);
+    }
+
+    /// The position of a node's border box relative to the document. Elements in the top layer (and their
+    /// descendants) are positioned relative to the viewport, so the viewport scroll is added for them.
+    pub fn absolute_position(&self, node_id: usize) -> taffy::Point<f32> {
+        let mut node = &self.nodes[node_id];
+        let mut x = node.final_layout.location.x;
+        let mut y = node.final_layout.location.y;
+        while let Some(parent_id) = node.parent {
+            if node.in_top_layer {
+                return taffy::Point {
+                    x,
+                    y: y + self.viewport_scroll as f32,
+                };
+            }
+            node = &self.nodes[parent_id];
+            x += node.final_layout.location.x;
+            y += node.final_layout.location.y;
+        }
+        taffy::Point { x, y }
+    }
+
     pub fn get_hover_node_id(&self) -> Option<usize> {
         self.hover_node_id
     }
@@ -478,17 +1142,43 @@ impl Document {
         };
         let origins = self.stylist.set_device(device, &guards);
         self.stylist.force_stylesheet_origins_dirty(origins);
+
+        // The viewport may have been resized
+        self.pending_damage.full = true;
     }
     pub fn stylist_device(&mut self) -> &Device {
         self.stylist.device()
     }
 
+    /// A new device with the same viewport as the one the document is styled for, to style another document with
+    /// (e.g. one that replaces this document after a navigation)
+    pub fn clone_stylist_device(&self) -> Device {
+        let device = self.stylist.device();
+        let viewport_size = device.au_viewport_size();
+        Device::new(
+            device.media_type(),
+            device.quirks_mode(),
+            euclid::Size2D::new(
+                viewport_size.width.to_f32_px(),
+                viewport_size.height.to_f32_px(),
+            ),
+            device.device_pixel_ratio(),
+            Box::new(DummyFontMetricsProvider),
+        )
+    }
+
     /// Ensure that the layout_children field is populated for all nodes
     pub fn resolve_layout_children(&mut self) {
         let root_node_id = self.root_node().id;
         resolve_layout_children_recursive(self, root_node_id);
 
         pub fn resolve_layout_children_recursive(doc: &mut Document, node_id: usize) {
+            // The layout children of nodes that haven't changed (which only contain nodes that haven't changed) can
+            // be reused
+            if !doc.is_layout_dirty(node_id) {
+                return;
+            }
+
             doc.ensure_layout_children(node_id);
 
             let children = std::mem::take(&mut doc.nodes[node_id].children);
@@ -518,9 +1208,16 @@ impl Document {
 
         // println!("\n\nRESOLVE LAYOUT\n===========\n");
 
+        self.take_top_layer_out_of_flow();
+
         taffy::compute_root_layout(self, root_node_id, available_space);
         taffy::round_layout(self, root_node_id);
 
+        self.resolve_top_layer_layout(taffy::Size {
+            width: size.width.to_f32_px(),
+            height: size.height.to_f32_px(),
+        });
+
         // println!("\n\n");
         // taffy::print_tree(self, root_node_id)
     }
@@ -0,0 +1,1323 @@
+//! Editing of `contenteditable` elements
+//!
+//! <https://html.spec.whatwg.org/multipage/interaction.html#contenteditable>
+//! <https://w3c.github.io/editing/docs/execCommand/>
+//!
+//! The caret (and the other end of the selection, its anchor) is a byte offset within a text node inside an
+//! editing host. Caret placement uses the inline layout of the text's inline formatting context: the text of an
+//! inline layout is the concatenation of the text nodes (and `<br>` elements) within it, so an offset into the
+//! layout can be mapped back to a text node and vice versa. The UA stylesheet makes editing hosts `pre-wrap` so
+//! that whitespace is never collapsed and this mapping is exact.
+//!
+//! Edits are applied directly to the DOM. Blocks are only split and merged at the level of the editing host's
+//! children, which matches what browsers produce for flat content such as `<div contenteditable>abc<div>def</div></div>`.
+use html5ever::{local_name, namespace_url, ns, QualName};
+use parley::layout::Cursor;
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style::values::specified::box_::{DisplayInside, DisplayOutside};
+
+use crate::events::InputType;
+use crate::node::{ElementNodeData, NodeData};
+use crate::Document;
+
+/// A position within the text of a `contenteditable` element
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Caret {
+    /// The text node that the caret is in
+    pub node_id: usize,
+    /// The byte offset of the caret within the text node's content
+    pub offset: usize,
+}
+
+/// An editing command, as accepted by [`Document::exec_command`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditCommand {
+    /// Toggle bold formatting (`<b>`) of the selection
+    Bold,
+    /// Toggle italic formatting (`<i>`) of the selection
+    Italic,
+    /// Replace the selection with text
+    InsertText(String),
+    /// Delete the selection, or the character before the caret
+    Delete,
+    /// Delete the selection, or the character after the caret
+    ForwardDelete,
+    /// Split the block containing the caret (what pressing Enter does)
+    InsertParagraph,
+}
+
+impl EditCommand {
+    /// The `inputType` of the input events that the command dispatches
+    pub fn input_type(&self) -> InputType {
+        match self {
+            EditCommand::Bold => InputType::FormatBold,
+            EditCommand::Italic => InputType::FormatItalic,
+            EditCommand::InsertText(_) => InputType::InsertText,
+            EditCommand::Delete => InputType::DeleteContentBackward,
+            EditCommand::ForwardDelete => InputType::DeleteContentForward,
+            EditCommand::InsertParagraph => InputType::InsertParagraph,
+        }
+    }
+
+    /// The text that the command inserts (the `data` of its input events), if any
+    pub fn data(&self) -> Option<&str> {
+        match self {
+            EditCommand::InsertText(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of the text of an inline layout
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Segment {
+    pub(crate) node_id: usize,
+    /// Byte offset of the start of the segment within the layout's text
+    pub(crate) start: usize,
+    pub(crate) len: usize,
+    /// `<br>` elements contribute a newline that the caret cannot be placed within
+    pub(crate) is_text: bool,
+}
+
+fn contenteditable_state(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "true" | "plaintext-only" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn prev_char_boundary(text: &str, offset: usize) -> usize {
+    text[..offset]
+        .char_indices()
+        .next_back()
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+fn next_char_boundary(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .chars()
+        .next()
+        .map(|c| offset + c.len_utf8())
+        .unwrap_or(offset)
+}
+
+impl Document {
+    /// Whether a node is an element with a `contenteditable` attribute that makes it editable
+    pub fn is_editing_host(&self, node_id: usize) -> bool {
+        self.nodes[node_id]
+            .attr(local_name!("contenteditable"))
+            .and_then(contenteditable_state)
+            .unwrap_or(false)
+    }
+
+    /// The editing host that a node is within (if any). A node is in the editing host of its nearest ancestor with a
+    /// valid `contenteditable` attribute, unless that attribute is `false`.
+    pub fn editing_host(&self, node_id: usize) -> Option<usize> {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            match node
+                .attr(local_name!("contenteditable"))
+                .and_then(contenteditable_state)
+            {
+                Some(true) => return Some(id),
+                Some(false) => return None,
+                None => maybe_id = node.parent,
+            }
+        }
+        None
+    }
+
+    /// The caret (if any editing host has one)
+    pub fn caret(&self) -> Option<Caret> {
+        self.caret
+    }
+
+    /// The selection as (start, end) in tree order. Returns `None` if there is no caret, and equal positions if the
+    /// selection is collapsed.
+    pub fn selection(&self) -> Option<(Caret, Caret)> {
+        let caret = self.caret?;
+        let anchor = self.selection_anchor.unwrap_or(caret);
+        Some(self.order_positions(anchor, caret))
+    }
+
+    /// Place the caret at a position within a text node. Returns false if the node is not editable text.
+    ///
+    /// If `extend` is true the selection is extended to the new position, otherwise it is collapsed.
+    pub fn set_caret(&mut self, caret: Caret, extend: bool) -> bool {
+        let Some(node) = self.nodes.get(caret.node_id) else {
+            return false;
+        };
+        let Some(text) = node.text_data() else {
+            return false;
+        };
+        if self.editing_host(caret.node_id).is_none()
+            || !text.content.is_char_boundary(caret.offset)
+        {
+            return false;
+        }
+
+        let anchor = match (extend, self.caret) {
+            (true, Some(current)) => Some(self.selection_anchor.unwrap_or(current)),
+            _ => None,
+        };
+        self.selection_anchor = anchor.filter(|anchor| *anchor != caret);
+        self.caret = Some(caret);
+        true
+    }
+
+    /// Remove the caret and selection
+    pub fn clear_caret(&mut self) {
+        self.caret = None;
+        self.selection_anchor = None;
+    }
+
+    /// Place the caret at the text position closest to a point (in document coordinates).
+    ///
+    /// Returns false (and removes the caret) if the point is not within an editing host.
+    pub fn place_caret(&mut self, x: f32, y: f32, extend: bool) -> bool {
+        let Some(caret) = self
+            .hit(x, y)
+            .and_then(|hit_id| self.caret_from_point(hit_id, x, y))
+        else {
+            self.clear_caret();
+            return false;
+        };
+        self.set_caret(caret, extend)
+    }
+
+    fn caret_from_point(&mut self, hit_id: usize, x: f32, y: f32) -> Option<Caret> {
+        let host = self.editing_host(hit_id)?;
+
+        // Find the inline formatting context that was hit
+        let mut maybe_id = Some(hit_id);
+        let mut inline_context = None;
+        while let Some(id) = maybe_id {
+            if self.nodes[id].is_inline_root {
+                inline_context =
+                    Some((id, self.content_box_origin(id, self.absolute_position(id))));
+                break;
+            }
+            // Inline content alongside blocks is laid out in anonymous blocks
+            let position = self.absolute_position(id);
+            inline_context = self.anonymous_inline_roots(id).find_map(|anon_id| {
+                let layout = self.nodes[anon_id].final_layout;
+                let top = position.y + layout.location.y;
+                (y >= top && y <= top + layout.size.height).then(|| {
+                    let origin = taffy::Point {
+                        x: position.x + layout.location.x,
+                        y: top,
+                    };
+                    (anon_id, self.content_box_origin(anon_id, origin))
+                })
+            });
+            if inline_context.is_some() || id == host {
+                break;
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+
+        let Some((root_id, origin)) = inline_context else {
+            // The host has no text (yet), so give it an empty text node to hold the caret
+            let last_text = self.editable_text_nodes(host).last().copied();
+            let node_id = match last_text {
+                Some(node_id) => node_id,
+                None => {
+                    let node_id = self.create_text_node("");
+                    self.append_children(host, &[node_id]);
+                    self.invalidate_edited_node(host);
+                    node_id
+                }
+            };
+            let offset = self.nodes[node_id].text_data().unwrap().content.len();
+            return Some(Caret { node_id, offset });
+        };
+
+        let local_x = (x - origin.x) * self.scale;
+        let local_y = (y - origin.y) * self.scale;
+
+        let text_layout = self.nodes[root_id].element_data()?.inline_layout.as_ref()?;
+        let cursor = Cursor::from_point(&text_layout.layout, local_x, local_y);
+        let text_offset = if local_x > cursor.offset + cursor.advance / 2.0 {
+            cursor.text_end
+        } else {
+            cursor.text_start
+        };
+
+        self.caret_from_layout_offset(root_id, text_offset)
+    }
+
+    /// The rectangle of the caret as (position, height) in document coordinates. The renderer draws the caret as a
+    /// vertical line of this height. Without a caret in a `contenteditable` element, this is the caret of the focused
+    /// text field (if any).
+    pub fn caret_rect(&self) -> Option<(taffy::Point<f32>, f32)> {
+        let Some(caret) = self.caret else {
+            return self
+                .focused_element()
+                .and_then(|node_id| self.text_field_caret_rect(node_id));
+        };
+        let node = self.nodes.get(caret.node_id)?;
+
+        if let Some(rects) = self.text_range_rects(caret.node_id, caret.offset..caret.offset) {
+            if let Some(rect) = rects.first() {
+                return Some((rect.location, rect.size.height));
+            }
+        }
+
+        // Empty text isn't laid out, so put the caret at the start of its parent's content box
+        Some(self.empty_caret_rect(node.parent?))
+    }
+
+    /// The caret of an element without text: at the start of its content box, as tall as a line of its font
+    pub(crate) fn empty_caret_rect(&self, node_id: usize) -> (taffy::Point<f32>, f32) {
+        let height = self.nodes[node_id]
+            .primary_styles()
+            .map(|style| style.get_font().font_size.computed_size().px() * 1.2)
+            .unwrap_or(16.0);
+        (
+            self.content_box_origin(node_id, self.absolute_position(node_id)),
+            height,
+        )
+    }
+
+    /// The rectangles (in document coordinates) covered by a byte range of a text node, one per line. A collapsed
+    /// range gives a zero-width rectangle at its position.
+    ///
+    /// Returns `None` if the text node isn't laid out.
+    pub fn text_range_rects(
+        &self,
+        node_id: usize,
+        range: std::ops::Range<usize>,
+    ) -> Option<Vec<taffy::Layout>> {
+        let (root_id, origin) = self.inline_context(node_id)?;
+        let segment = self
+            .inline_segments(root_id)
+            .into_iter()
+            .find(|segment| segment.node_id == node_id)?;
+        let layout = &self.nodes[root_id]
+            .element_data()?
+            .inline_layout
+            .as_ref()?
+            .layout;
+
+        let start = Cursor::from_position(layout, segment.start + range.start, true);
+        let end = Cursor::from_position(layout, segment.start + range.end, true);
+        let start_line = start.path.line_index;
+        let end_line = end.path.line_index;
+
+        let mut rects = Vec::new();
+        for line_index in start_line..=end_line {
+            let metrics = *layout.get(line_index)?.metrics();
+            let left = if line_index == start_line {
+                start.offset
+            } else {
+                0.0
+            };
+            let right = if line_index == end_line {
+                end.offset
+            } else {
+                metrics.advance
+            };
+
+            let mut rect = taffy::Layout::new();
+            rect.location = taffy::Point {
+                x: origin.x + left / self.scale,
+                y: origin.y + (metrics.baseline - metrics.ascent) / self.scale,
+            };
+            rect.size = taffy::Size {
+                width: (right - left).max(0.0) / self.scale,
+                height: (metrics.ascent + metrics.descent) / self.scale,
+            };
+            rects.push(rect);
+        }
+        Some(rects)
+    }
+
+    /// Move the caret one character to the left or right (within the editing host). If `extend` is true the selection
+    /// is extended, otherwise a non-collapsed selection is collapsed to its start or end.
+    pub fn move_caret(&mut self, forward: bool, extend: bool) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        let caret = self.caret.unwrap();
+
+        if !extend && start != end {
+            let target = if forward { end } else { start };
+            return self.set_caret(target, false);
+        }
+
+        let content = &self.nodes[caret.node_id].text_data().unwrap().content;
+        let target = if forward && caret.offset < content.len() {
+            Some(Caret {
+                node_id: caret.node_id,
+                offset: next_char_boundary(content, caret.offset),
+            })
+        } else if !forward && caret.offset > 0 {
+            Some(Caret {
+                node_id: caret.node_id,
+                offset: prev_char_boundary(content, caret.offset),
+            })
+        } else {
+            self.adjacent_text_node(caret.node_id, forward)
+                .map(|node_id| Caret {
+                    node_id,
+                    offset: if forward {
+                        0
+                    } else {
+                        self.nodes[node_id].text_data().unwrap().content.len()
+                    },
+                })
+        };
+
+        match target {
+            Some(target) => self.set_caret(target, extend),
+            None => false,
+        }
+    }
+
+    /// The text of the selection (empty if there is no selection or it is collapsed)
+    pub fn selected_text(&self) -> String {
+        let Some((start, end)) = self.selection() else {
+            return String::new();
+        };
+        let Some(host) = self.editing_host(start.node_id) else {
+            return String::new();
+        };
+
+        let mut text = String::new();
+        let mut selecting = false;
+        for node_id in self.editable_text_nodes(host) {
+            let content = &self.nodes[node_id].text_data().unwrap().content;
+            let from = if node_id == start.node_id {
+                selecting = true;
+                start.offset
+            } else {
+                0
+            };
+            if !selecting {
+                continue;
+            }
+            if node_id == end.node_id {
+                text.push_str(&content[from..end.offset]);
+                break;
+            }
+            text.push_str(&content[from..]);
+        }
+        text
+    }
+
+    /// Select all of the text of an editing host. Returns false if it has no text.
+    pub fn select_all(&mut self, host: usize) -> bool {
+        let text_nodes = self.editable_text_nodes(host);
+        let (Some(&first), Some(&last)) = (text_nodes.first(), text_nodes.last()) else {
+            return false;
+        };
+        let end = self.nodes[last].text_data().unwrap().content.len();
+        self.set_caret(
+            Caret {
+                node_id: first,
+                offset: 0,
+            },
+            false,
+        ) && self.set_caret(
+            Caret {
+                node_id: last,
+                offset: end,
+            },
+            true,
+        )
+    }
+
+    /// Execute an editing command at the caret. Returns true if the document was changed. Changes can be undone with
+    /// [`Document::undo`].
+    ///
+    /// Bold and italic only apply to a non-collapsed selection: the selected text is wrapped in `<b>` (or `<i>`), or
+    /// unwrapped if all of it is already bold (or italic).
+    pub fn exec_command(&mut self, command: EditCommand) -> bool {
+        // The caret's node may have been removed by a mutation since it was placed
+        let Some(host) = self
+            .caret
+            .filter(|caret| {
+                self.nodes
+                    .get(caret.node_id)
+                    .is_some_and(|node| node.is_text_node())
+            })
+            .and_then(|caret| self.editing_host(caret.node_id))
+        else {
+            self.clear_caret();
+            return false;
+        };
+
+        let typed = match &command {
+            EditCommand::InsertText(text) => Some(text.clone()),
+            _ => None,
+        };
+        let changed = self.transact_edit(host, typed.as_deref(), |doc| match command {
+            EditCommand::Bold => doc.toggle_formatting(local_name!("b"), local_name!("strong")),
+            EditCommand::Italic => doc.toggle_formatting(local_name!("i"), local_name!("em")),
+            EditCommand::InsertText(text) => doc.insert_text(&text),
+            EditCommand::Delete => doc.delete_selection() || doc.delete_backward(),
+            EditCommand::ForwardDelete => doc.delete_selection() || doc.delete_forward(),
+            EditCommand::InsertParagraph => {
+                doc.delete_selection();
+                doc.insert_paragraph()
+            }
+        });
+
+        if changed {
+            self.invalidate_edited_node(host);
+        }
+        changed
+    }
+
+    fn insert_text(&mut self, text: &str) -> bool {
+        self.delete_selection();
+        let caret = self.caret.unwrap();
+        self.record_text_change(caret.node_id);
+        let content = &mut self.nodes[caret.node_id].text_data_mut().unwrap().content;
+        content.insert_str(caret.offset, text);
+        self.caret = Some(Caret {
+            node_id: caret.node_id,
+            offset: caret.offset + text.len(),
+        });
+        true
+    }
+
+    /// Delete the selected text (if the selection is not collapsed)
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        if start == end {
+            return false;
+        }
+
+        if start.node_id == end.node_id {
+            self.record_text_change(start.node_id);
+            let content = &mut self.nodes[start.node_id].text_data_mut().unwrap().content;
+            content.replace_range(start.offset..end.offset, "");
+        } else {
+            let host = self.editing_host(start.node_id).unwrap();
+            let text_nodes = self.editable_text_nodes(host);
+            let start_idx = text_nodes
+                .iter()
+                .position(|id| *id == start.node_id)
+                .unwrap();
+            let end_idx = text_nodes.iter().position(|id| *id == end.node_id).unwrap();
+
+            self.record_text_change(start.node_id);
+            self.record_text_change(end.node_id);
+            self.nodes[start.node_id]
+                .text_data_mut()
+                .unwrap()
+                .content
+                .truncate(start.offset);
+            self.nodes[end.node_id]
+                .text_data_mut()
+                .unwrap()
+                .content
+                .replace_range(..end.offset, "");
+            for node_id in text_nodes[start_idx + 1..end_idx].iter().copied() {
+                self.remove_node(node_id);
+            }
+
+            // Blocks between the ends of the selection are removed along with their text
+            let first_block = self.host_child(host, start.node_id);
+            let last_block = self.host_child(host, end.node_id);
+            if first_block != last_block {
+                let between: Vec<usize> = self.nodes[host]
+                    .children
+                    .iter()
+                    .copied()
+                    .skip_while(|id| *id != first_block)
+                    .skip(1)
+                    .take_while(|id| *id != last_block)
+                    .collect();
+                for node_id in between {
+                    self.remove_node(node_id);
+                }
+            }
+
+            self.merge_blocks(start.node_id, end.node_id);
+        }
+
+        self.caret = Some(start);
+        self.selection_anchor = None;
+        true
+    }
+
+    fn delete_backward(&mut self) -> bool {
+        let caret = self.caret.unwrap();
+        if caret.offset > 0 {
+            self.record_text_change(caret.node_id);
+            let content = &mut self.nodes[caret.node_id].text_data_mut().unwrap().content;
+            let offset = prev_char_boundary(content, caret.offset);
+            content.replace_range(offset..caret.offset, "");
+            self.caret = Some(Caret {
+                node_id: caret.node_id,
+                offset,
+            });
+            return true;
+        }
+
+        let Some(prev_id) = self.adjacent_text_node(caret.node_id, false) else {
+            return false;
+        };
+
+        // At the start of a block, backspace merges the block into the previous one
+        if self.merge_blocks(prev_id, caret.node_id) {
+            let offset = self.nodes[prev_id].text_data().unwrap().content.len();
+            self.caret = Some(Caret {
+                node_id: prev_id,
+                offset,
+            });
+            return true;
+        }
+
+        // Otherwise it deletes the last character of the previous text node
+        let content = &self.nodes[prev_id].text_data().unwrap().content;
+        self.caret = Some(Caret {
+            node_id: prev_id,
+            offset: content.len(),
+        });
+        self.delete_backward()
+    }
+
+    fn delete_forward(&mut self) -> bool {
+        let caret = self.caret.unwrap();
+        let content = &self.nodes[caret.node_id].text_data().unwrap().content;
+        if caret.offset < content.len() {
+            self.caret = Some(Caret {
+                node_id: caret.node_id,
+                offset: next_char_boundary(content, caret.offset),
+            });
+            return self.delete_backward();
+        }
+
+        let Some(next_id) = self.adjacent_text_node(caret.node_id, true) else {
+            return false;
+        };
+        if self.merge_blocks(caret.node_id, next_id) {
+            return true;
+        }
+        self.caret = Some(Caret {
+            node_id: next_id,
+            offset: 0,
+        });
+        self.delete_forward()
+    }
+
+    /// Split the caret's block in two, moving the caret to the start of the new block
+    fn insert_paragraph(&mut self) -> bool {
+        let caret = self.caret.unwrap();
+        let host = self.editing_host(caret.node_id).unwrap();
+
+        // Split the text node
+        let tail = self.nodes[caret.node_id]
+            .text_data_mut()
+            .unwrap()
+            .content
+            .split_off(caret.offset);
+        let head_id = caret.node_id;
+        let tail_id = self.create_text_node(&tail);
+        self.insert_after(head_id, &[tail_id]);
+
+        // Split each of its ancestors up to the host's child, so that the tail ends up in a sibling of that child
+        let mut split_id = tail_id;
+        while self.nodes[split_id].parent != Some(host) {
+            let parent_id = self.nodes[split_id].parent.unwrap();
+            let clone_id = self.clone_node(parent_id, false);
+            let parent = &self.nodes[parent_id];
+            let moved = parent.children[parent
+                .children
+                .iter()
+                .position(|id| *id == split_id)
+                .unwrap()..]
+                .to_vec();
+            for node_id in moved.iter().copied() {
+                self.detach_node(node_id);
+            }
+            self.append_children(clone_id, &moved);
+            self.insert_after(parent_id, &[clone_id]);
+            split_id = clone_id;
+        }
+
+        // Inline content that was directly in the host is given a block of its own
+        if !self.is_block(split_id) {
+            let div_id = self.create_element(local_name!("div"));
+            self.insert_after(split_id, &[div_id]);
+            let mut moved = vec![split_id];
+            let mut maybe_next = self.next_sibling(split_id);
+            while let Some(next_id) = maybe_next.filter(|id| !self.is_block(*id)) {
+                moved.push(next_id);
+                maybe_next = self.next_sibling(next_id);
+            }
+            for node_id in moved.iter().copied() {
+                self.detach_node(node_id);
+            }
+            self.append_children(div_id, &moved);
+        }
+
+        // Empty blocks would have no height, so they are given a line break
+        for node_id in [head_id, tail_id] {
+            if self.nodes[node_id].text_data().unwrap().content.is_empty()
+                && self.next_sibling(node_id).is_none()
+            {
+                let br_id = self.create_element(local_name!("br"));
+                self.insert_after(node_id, &[br_id]);
+            }
+        }
+
+        self.caret = Some(Caret {
+            node_id: tail_id,
+            offset: 0,
+        });
+        true
+    }
+
+    /// If the two text nodes are in different children of their editing host, and either child is a block, move the
+    /// content of the second block to the end of the first and remove the second. Returns false if there was no block
+    /// boundary between the nodes.
+    fn merge_blocks(&mut self, first_id: usize, second_id: usize) -> bool {
+        let host = self.editing_host(first_id).unwrap();
+        let first_block = self.host_child(host, first_id);
+        let second_block = self.host_child(host, second_id);
+        if first_block == second_block
+            || !(self.is_block(first_block) || self.is_block(second_block))
+        {
+            return false;
+        }
+
+        // A placeholder line break at the end of the first block is no longer needed
+        if let Some(&last_id) = self.nodes[first_block].children.last() {
+            if self.nodes[last_id]
+                .element_data()
+                .is_some_and(|element| element.name.local == local_name!("br"))
+            {
+                self.remove_node(last_id);
+            }
+        }
+
+        let moved = if self.is_block(second_block) {
+            std::mem::take(&mut self.nodes[second_block].children)
+        } else {
+            self.detach_node(second_block);
+            vec![second_block]
+        };
+
+        if self.nodes[first_block].element_data().is_some() && self.is_block(first_block) {
+            self.append_children(first_block, &moved);
+        } else {
+            for node_id in moved.iter().copied() {
+                self.nodes[node_id].parent = None;
+            }
+            self.insert_after(first_block, &moved);
+        }
+
+        if self.nodes.contains(second_block) && self.nodes[second_block].parent == Some(host) {
+            self.remove_node(second_block);
+        }
+        true
+    }
+
+    /// Wrap the selected text in a formatting element, or unwrap it if it is all already formatted
+    fn toggle_formatting(
+        &mut self,
+        tag: html5ever::LocalName,
+        alt_tag: html5ever::LocalName,
+    ) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        if start == end {
+            return false;
+        }
+        let host = self.editing_host(start.node_id).unwrap();
+
+        // Split the text nodes at the ends of the selection so that it covers whole text nodes
+        let same_node = start.node_id == end.node_id;
+        self.split_text(end.node_id, end.offset);
+        let start_node = self.split_text(start.node_id, start.offset);
+        let end_node = if same_node { start_node } else { end.node_id };
+
+        let text_nodes = self.editable_text_nodes(host);
+        let mut start_idx = text_nodes.iter().position(|id| *id == start_node).unwrap();
+        let mut end_idx = text_nodes.iter().position(|id| *id == end_node).unwrap();
+        // A selection that ends at the very start (or starts at the very end) of a text node doesn't include it
+        if !same_node && end.offset == 0 {
+            end_idx = end_idx.saturating_sub(1);
+        }
+        if !same_node && start_node == start.node_id && start.offset > 0 {
+            start_idx += 1;
+        }
+        if start_idx > end_idx {
+            return false;
+        }
+        let selected = text_nodes[start_idx..=end_idx].to_vec();
+        let (start_node, end_node) = (selected[0], selected[selected.len() - 1]);
+
+        let formatting_ancestor = |doc: &Document, node_id: usize| {
+            let mut maybe_id = doc.nodes[node_id].parent;
+            while let Some(id) = maybe_id.filter(|id| *id != host) {
+                let name = &doc.nodes[id].element_data().unwrap().name.local;
+                if *name == tag || *name == alt_tag {
+                    return Some(id);
+                }
+                maybe_id = doc.nodes[id].parent;
+            }
+            None
+        };
+
+        let all_formatted = selected
+            .iter()
+            .all(|node_id| formatting_ancestor(self, *node_id).is_some());
+        if all_formatted {
+            for node_id in selected.iter().copied() {
+                if let Some(element_id) = formatting_ancestor(self, node_id) {
+                    let children = std::mem::take(&mut self.nodes[element_id].children);
+                    for child_id in children.iter().copied() {
+                        self.nodes[child_id].parent = None;
+                    }
+                    self.insert_after(element_id, &children);
+                    self.remove_node(element_id);
+                }
+            }
+        } else {
+            for node_id in selected.iter().copied() {
+                if formatting_ancestor(self, node_id).is_none() {
+                    let element_id = self.create_element(tag.clone());
+                    self.insert_after(node_id, &[element_id]);
+                    self.detach_node(node_id);
+                    self.append_children(element_id, &[node_id]);
+                }
+            }
+        }
+
+        // Keep the formatted text selected
+        let end_len = self.nodes[end_node].text_data().unwrap().content.len();
+        self.selection_anchor = Some(Caret {
+            node_id: start_node,
+            offset: 0,
+        });
+        self.caret = Some(Caret {
+            node_id: end_node,
+            offset: end_len,
+        });
+        true
+    }
+
+    /// Split a text node at an offset, returning the id of the text node that starts at the offset. The original
+    /// node is left unchanged (and returned) if the offset is at its start or end.
+    fn split_text(&mut self, node_id: usize, offset: usize) -> usize {
+        let len = self.nodes[node_id].text_data().unwrap().content.len();
+        if offset == 0 || offset == len {
+            return node_id;
+        }
+        self.record_text_change(node_id);
+        let content = &mut self.nodes[node_id].text_data_mut().unwrap().content;
+        let tail = content.split_off(offset);
+        let tail_id = self.create_text_node(&tail);
+        self.insert_after(node_id, &[tail_id]);
+        tail_id
+    }
+
+    /// Map an offset into the text of an inline layout to a caret position
+    fn caret_from_layout_offset(&self, root_id: usize, text_offset: usize) -> Option<Caret> {
+        let segments = self.inline_segments(root_id);
+        let segment = segments
+            .iter()
+            .filter(|segment| segment.is_text)
+            .find(|segment| text_offset <= segment.start + segment.len)
+            .or_else(|| segments.iter().filter(|segment| segment.is_text).last())?;
+        let content = &self.nodes[segment.node_id].text_data()?.content;
+        let mut offset = text_offset.saturating_sub(segment.start).min(content.len());
+        while !content.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        Some(Caret {
+            node_id: segment.node_id,
+            offset,
+        })
+    }
+
+    /// The inline formatting context that a node's text is laid out in, and the position of that context's content box
+    /// in document coordinates.
+    ///
+    /// Inline content that shares a container with blocks is laid out in an anonymous block, which is not one of its
+    /// ancestors in the tree, so the layout children of each ancestor are searched too.
+    pub(crate) fn inline_context(&self, node_id: usize) -> Option<(usize, taffy::Point<f32>)> {
+        let mut child_id = node_id;
+        let mut maybe_id = self.nodes[node_id].parent;
+        while let Some(id) = maybe_id {
+            if self.nodes[id].is_inline_root {
+                return Some((id, self.content_box_origin(id, self.absolute_position(id))));
+            }
+            if let Some(anon_id) = self
+                .anonymous_inline_roots(id)
+                .find(|anon_id| self.nodes[*anon_id].children.contains(&child_id))
+            {
+                let position = self.absolute_position(id);
+                let location = self.nodes[anon_id].final_layout.location;
+                let origin = taffy::Point {
+                    x: position.x + location.x,
+                    y: position.y + location.y,
+                };
+                return Some((anon_id, self.content_box_origin(anon_id, origin)));
+            }
+            child_id = id;
+            maybe_id = self.nodes[id].parent;
+        }
+        None
+    }
+
+    /// The anonymous blocks among a node's layout children that contain inline layouts
+    fn anonymous_inline_roots(&self, node_id: usize) -> impl Iterator<Item = usize> + '_ {
+        let anon_ids: Vec<usize> = self.nodes[node_id]
+            .layout_children
+            .borrow()
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|id| {
+                let node = &self.nodes[*id];
+                node.is_inline_root && matches!(node.raw_dom_data, NodeData::AnonymousBlock(_))
+            })
+            .collect();
+        anon_ids.into_iter()
+    }
+
+    /// The position of a node's content box, given the position of its border box
+    pub(crate) fn content_box_origin(
+        &self,
+        node_id: usize,
+        position: taffy::Point<f32>,
+    ) -> taffy::Point<f32> {
+        let layout = self.nodes[node_id].final_layout;
+        taffy::Point {
+            x: position.x + layout.border.left + layout.padding.left,
+            y: position.y + layout.border.top + layout.padding.top,
+        }
+    }
+
+    /// The text segments of an inline layout, walked in the same order that the layout is built in
+    pub(crate) fn inline_segments(&self, root_id: usize) -> Vec<Segment> {
+        fn collect(
+            doc: &Document,
+            node_id: usize,
+            segments: &mut Vec<Segment>,
+            offset: &mut usize,
+        ) {
+            let node = &doc.nodes[node_id];
+            match &node.raw_dom_data {
+                NodeData::Text(text) => {
+                    segments.push(Segment {
+                        node_id,
+                        start: *offset,
+                        len: text.content.len(),
+                        is_text: true,
+                    });
+                    *offset += text.content.len();
+                }
+                NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
+                    if node.hidden || element_data.attr(local_name!("hidden")).is_some() {
+                        return;
+                    }
+                    let Some(display) = node.display_style() else {
+                        return;
+                    };
+                    if display.inside() == DisplayInside::None {
+                        return;
+                    }
+                    if element_data.name.local == local_name!("br") {
+                        segments.push(Segment {
+                            node_id,
+                            start: *offset,
+                            len: 1,
+                            is_text: false,
+                        });
+                        *offset += 1;
+                        return;
+                    }
+                    // Inline boxes (and replaced elements) don't contribute text
+                    if display.inside() != DisplayInside::Contents
+                        && (display.outside() != DisplayOutside::Inline
+                            || matches!(
+                                element_data.name.local.as_ref(),
+                                "img" | "video" | "iframe" | "progress" | "meter" | "input"
+                            ))
+                    {
+                        return;
+                    }
+                    for child_id in node.children.iter().copied() {
+                        collect(doc, child_id, segments, offset);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        for child_id in self.nodes[root_id].children.iter().copied() {
+            collect(self, child_id, &mut segments, &mut offset);
+        }
+        segments
+    }
+
+    /// The text nodes within an editing host, in tree order
+    pub(crate) fn editable_text_nodes(&self, host: usize) -> Vec<usize> {
+        fn collect(doc: &Document, node_id: usize, text_nodes: &mut Vec<usize>) {
+            let node = &doc.nodes[node_id];
+            if node.is_text_node() {
+                text_nodes.push(node_id);
+            } else if node
+                .attr(local_name!("contenteditable"))
+                .and_then(contenteditable_state)
+                != Some(false)
+            {
+                for child_id in node.children.iter().copied() {
+                    collect(doc, child_id, text_nodes);
+                }
+            }
+        }
+
+        let mut text_nodes = Vec::new();
+        for child_id in self.nodes[host].children.iter().copied() {
+            collect(self, child_id, &mut text_nodes);
+        }
+        text_nodes
+    }
+
+    /// The previous or next text node within the same editing host
+    fn adjacent_text_node(&self, node_id: usize, forward: bool) -> Option<usize> {
+        let text_nodes = self.editable_text_nodes(self.editing_host(node_id)?);
+        let idx = text_nodes.iter().position(|id| *id == node_id)?;
+        if forward {
+            text_nodes.get(idx + 1).copied()
+        } else {
+            idx.checked_sub(1).map(|idx| text_nodes[idx])
+        }
+    }
+
+    fn order_positions(&self, a: Caret, b: Caret) -> (Caret, Caret) {
+        if a.node_id == b.node_id {
+            return if a.offset <= b.offset { (a, b) } else { (b, a) };
+        }
+        let Some(host) = self.editing_host(a.node_id) else {
+            return (a, b);
+        };
+        let text_nodes = self.editable_text_nodes(host);
+        let a_idx = text_nodes.iter().position(|id| *id == a.node_id);
+        let b_idx = text_nodes.iter().position(|id| *id == b.node_id);
+        if a_idx <= b_idx {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+
+    /// The child of the editing host that contains a node
+    fn host_child(&self, host: usize, node_id: usize) -> usize {
+        let mut id = node_id;
+        while let Some(parent_id) = self.nodes[id].parent.filter(|parent_id| *parent_id != host) {
+            id = parent_id;
+        }
+        id
+    }
+
+    fn is_block(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        node.element_data().is_some()
+            && node
+                .display_style()
+                .map(|display| display.outside() == DisplayOutside::Block)
+                .unwrap_or_else(|| {
+                    // Newly created elements haven't been styled yet
+                    node.element_data()
+                        .is_some_and(|element| element.name.local == local_name!("div"))
+                })
+    }
+
+    fn next_sibling(&self, node_id: usize) -> Option<usize> {
+        let node = &self.nodes[node_id];
+        let parent = &self.nodes[node.parent?];
+        parent.children.get(node.child_idx + 1).copied()
+    }
+
+    /// Insert nodes after a node (as its siblings)
+    fn insert_after(&mut self, node_id: usize, inserted_node_ids: &[usize]) {
+        match self.next_sibling(node_id) {
+            Some(next_id) => self.insert_before(next_id, inserted_node_ids),
+            None => self.append(node_id, inserted_node_ids),
+        }
+    }
+
+    /// Remove a node from its parent without freeing it
+    fn detach_node(&mut self, node_id: usize) {
+        let Some(parent_id) = self.nodes[node_id].parent.take() else {
+            return;
+        };
+        let parent = &mut self.nodes[parent_id];
+        parent.children.retain(|id| *id != node_id);
+        for (child_idx, child_id) in parent.children.clone().into_iter().enumerate() {
+            self.nodes[child_id].child_idx = child_idx;
+        }
+        self.child_list_changed(parent_id, &[], &[node_id]);
+    }
+
+    fn create_element(&mut self, name: html5ever::LocalName) -> usize {
+        let data = ElementNodeData::new(QualName::new(None, ns!(html), name), Vec::new());
+        let node_id = self.create_node(NodeData::Element(data));
+        *self.nodes[node_id].stylo_element_data.borrow_mut() = Some(Default::default());
+        node_id
+    }
+
+    /// Restyle and relayout an editing host after its content has changed
+    pub(crate) fn invalidate_edited_node(&mut self, host: usize) {
+        self.snapshot_node(host);
+        if let Some(element_data) = self.nodes[host].stylo_element_data.borrow_mut().as_mut() {
+            element_data.hint.insert(RestyleHint::restyle_subtree());
+        }
+
+        let mut maybe_id = Some(host);
+        while let Some(id) = maybe_id {
+            let node = &mut self.nodes[id];
+            node.cache.clear();
+            maybe_id = node.parent;
+        }
+    }
+}
+
+/// The contents of a node as markup, to compare the results of edits against
+#[cfg(test)]
+fn markup(doc: &Document, node_id: usize) -> String {
+    doc.nodes[node_id]
+        .children
+        .iter()
+        .map(|child_id| {
+            let child = &doc.nodes[*child_id];
+            match (child.text_data(), child.element_data()) {
+                (Some(text), _) => text.content.clone(),
+                (_, Some(element)) if element.name.local == local_name!("br") => "<br>".to_string(),
+                (_, Some(element)) => {
+                    format!("<{0}>{1}</{0}>", element.name.local, markup(doc, *child_id))
+                }
+                _ => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn types_and_deletes_characters() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div contenteditable id=host>aéb</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let host = doc.get_element_by_id("host").unwrap();
+    let text = doc.nodes[host].children[0];
+
+    doc.set_caret(
+        Caret {
+            node_id: text,
+            offset: 1,
+        },
+        false,
+    );
+    assert!(doc.exec_command(EditCommand::InsertText("xy".into())));
+    assert_eq!(markup(&doc, host), "axyéb");
+    assert_eq!(
+        doc.caret(),
+        Some(Caret {
+            node_id: text,
+            offset: 3
+        })
+    );
+
+    // Backspace and delete remove whole characters
+    assert!(doc.exec_command(EditCommand::ForwardDelete));
+    assert_eq!(markup(&doc, host), "axyb");
+    assert!(doc.exec_command(EditCommand::Delete));
+    assert_eq!(markup(&doc, host), "axb");
+    assert_eq!(
+        doc.caret(),
+        Some(Caret {
+            node_id: text,
+            offset: 2
+        })
+    );
+
+    // Typing replaces the selection
+    doc.set_caret(
+        Caret {
+            node_id: text,
+            offset: 0,
+        },
+        false,
+    );
+    doc.set_caret(
+        Caret {
+            node_id: text,
+            offset: 2,
+        },
+        true,
+    );
+    assert!(doc.exec_command(EditCommand::InsertText("z".into())));
+    assert_eq!(markup(&doc, host), "zb");
+
+    // There's nothing before the start of the host to delete
+    doc.set_caret(
+        Caret {
+            node_id: text,
+            offset: 0,
+        },
+        false,
+    );
+    assert!(!doc.exec_command(EditCommand::Delete));
+    assert_eq!(markup(&doc, host), "zb");
+}
+
+#[test]
+fn deletes_across_block_boundaries() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <div contenteditable id=host><div>ab</div><div>cd<br></div><div>ef</div></div></body></html>";
+    let mut doc = parse_test_document(html, &["div { display: block }"]);
+    doc.resolve();
+    let host = doc.get_element_by_id("host").unwrap();
+    let text_nodes = doc.editable_text_nodes(host);
+    let (ab, cd, ef) = (text_nodes[0], text_nodes[1], text_nodes[2]);
+
+    // Backspace at the start of a block merges it into the previous block
+    doc.set_caret(
+        Caret {
+            node_id: cd,
+            offset: 0,
+        },
+        false,
+    );
+    assert!(doc.exec_command(EditCommand::Delete));
+    assert_eq!(markup(&doc, host), "<div>abcd<br></div><div>ef</div>");
+    assert_eq!(
+        doc.caret(),
+        Some(Caret {
+            node_id: ab,
+            offset: 2
+        })
+    );
+
+    // Delete at the end of a block merges the next block into it, without its placeholder line break
+    doc.set_caret(
+        Caret {
+            node_id: cd,
+            offset: 2,
+        },
+        false,
+    );
+    assert!(doc.exec_command(EditCommand::ForwardDelete));
+    assert_eq!(markup(&doc, host), "<div>abcdef</div>");
+    assert_eq!(
+        doc.caret(),
+        Some(Caret {
+            node_id: cd,
+            offset: 2
+        })
+    );
+    assert!(doc.nodes[ef].parent.is_some());
+}
+
+#[test]
+fn deletes_selections_spanning_nodes() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div contenteditable id=host>\
+        <div>abc</div><div>d<b>e</b>f</div><div>ghi</div></div></body></html>";
+    let mut doc = parse_test_document(html, &["div { display: block }"]);
+    doc.resolve();
+    let host = doc.get_element_by_id("host").unwrap();
+    let text_nodes = doc.editable_text_nodes(host);
+    let (abc, ghi) = (text_nodes[0], text_nodes[4]);
+
+    doc.set_caret(
+        Caret {
+            node_id: ghi,
+            offset: 2,
+        },
+        false,
+    );
+    doc.set_caret(
+        Caret {
+            node_id: abc,
+            offset: 1,
+        },
+        true,
+    );
+    assert_eq!(doc.selected_text(), "bcdefgh");
+    assert!(doc.exec_command(EditCommand::Delete));
+
+    // The blocks in between are removed, and the ends of the selection are merged into one block
+    assert_eq!(markup(&doc, host), "<div>ai</div>");
+    assert_eq!(
+        doc.caret(),
+        Some(Caret {
+            node_id: abc,
+            offset: 1
+        })
+    );
+    assert_eq!(
+        doc.selection(),
+        Some((doc.caret().unwrap(), doc.caret().unwrap()))
+    );
+}
+
+#[test]
+fn toggles_formatting_of_partly_formatted_selections() {
+    use crate::test_util::parse_test_document;
+
+    let html =
+        "<!DOCTYPE html><html><body><div contenteditable id=host>ab<b>cd</b>ef</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let host = doc.get_element_by_id("host").unwrap();
+    let text_nodes = doc.editable_text_nodes(host);
+
+    doc.set_caret(
+        Caret {
+            node_id: text_nodes[0],
+            offset: 1,
+        },
+        false,
+    );
+    doc.set_caret(
+        Caret {
+            node_id: text_nodes[2],
+            offset: 1,
+        },
+        true,
+    );
+    assert_eq!(doc.selected_text(), "bcde");
+
+    // Only some of the selection is bold, so all of it is made bold
+    assert!(doc.exec_command(EditCommand::Bold));
+    assert_eq!(markup(&doc, host), "a<b>b</b><b>cd</b><b>e</b>f");
+    assert_eq!(doc.selected_text(), "bcde");
+
+    // Now all of it is bold, so it's unwrapped
+    assert!(doc.exec_command(EditCommand::Bold));
+    assert_eq!(markup(&doc, host), "abcdef");
+    assert_eq!(doc.selected_text(), "bcde");
+
+    // Collapsed selections aren't formatted
+    doc.set_caret(
+        Caret {
+            node_id: text_nodes[0],
+            offset: 1,
+        },
+        false,
+    );
+    assert!(!doc.exec_command(EditCommand::Italic));
+}
@@ -1,3 +1,5 @@
+use crate::Document;
+
 pub struct EventListener {
     pub name: String,
 }
@@ -9,6 +11,150 @@ pub struct RendererEvent {
 }
 
 pub enum EventData {
-    Click { x: f64, y: f64 },
+    Click(PointerEventData),
+    /// The value of a form control or the text of an editing host is about to change or has changed (used by
+    /// "beforeinput", "input" and "change" events). `value` is the value when the event is dispatched. "submit" and
+    /// "reset" events on forms have an empty value.
+    Input {
+        value: String,
+        /// The kind of edit, for "beforeinput" and "input" events that come from editing text
+        input_type: Option<InputType>,
+        /// The text being inserted, if any
+        data: Option<String>,
+    },
     Hover,
+    Wheel(WheelEventData),
+}
+
+/// The kind of edit that an "input" event comes from, as in the `inputType` of DOM input events
+///
+/// <https://w3c.github.io/input-events/#interface-InputEvent-Attributes>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputType {
+    InsertText,
+    InsertParagraph,
+    DeleteContentBackward,
+    DeleteContentForward,
+    FormatBold,
+    FormatItalic,
+    HistoryUndo,
+    HistoryRedo,
+}
+
+impl InputType {
+    /// The name of the input type, as in `InputEvent.inputType`
+    pub fn name(self) -> &'static str {
+        match self {
+            InputType::InsertText => "insertText",
+            InputType::InsertParagraph => "insertParagraph",
+            InputType::DeleteContentBackward => "deleteContentBackward",
+            InputType::DeleteContentForward => "deleteContentForward",
+            InputType::FormatBold => "formatBold",
+            InputType::FormatItalic => "formatItalic",
+            InputType::HistoryUndo => "historyUndo",
+            InputType::HistoryRedo => "historyRedo",
+        }
+    }
+}
+
+/// Where a pointer event happened. Coordinates are in CSS pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PointerEventData {
+    /// The node under the pointer: the event's target, or one of its descendants
+    pub hit: usize,
+    /// Relative to the viewport
+    pub client_x: f64,
+    pub client_y: f64,
+    /// Relative to the document
+    pub page_x: f64,
+    pub page_y: f64,
+    /// Relative to the padding edge of the event's target
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+/// How far a wheel (or touchpad) asked to scroll, as reported by the platform. Positive values scroll towards the
+/// content that is above and to the left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WheelDelta {
+    /// Lines (or rows) of text, as reported for mouse wheels that turn in steps
+    Lines { x: f64, y: f64 },
+    /// Physical pixels, as reported for touchpads and other devices that scroll smoothly
+    Pixels { x: f64, y: f64 },
+}
+
+impl WheelDelta {
+    /// The delta in CSS pixels, following the DOM's convention that positive values scroll down and to the right.
+    /// Each line is `line_height` CSS pixels, and `scale` is the number of physical pixels per CSS pixel.
+    pub fn to_css_pixels(self, line_height: f64, scale: f64) -> (f64, f64) {
+        match self {
+            WheelDelta::Lines { x, y } => (-x * line_height, -y * line_height),
+            WheelDelta::Pixels { x, y } => (-x / scale, -y / scale),
+        }
+    }
+}
+
+/// A wheel event, with both the delta reported by the platform and the delta normalized to CSS pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WheelEventData {
+    pub pointer: PointerEventData,
+    pub raw: WheelDelta,
+    /// CSS pixels, positive to scroll right
+    pub delta_x: f64,
+    /// CSS pixels, positive to scroll down
+    pub delta_y: f64,
+}
+
+impl Document {
+    /// The coordinates of a pointer event at a point in the document that is dispatched to `target`
+    pub fn pointer_event_data(
+        &self,
+        target: usize,
+        hit: usize,
+        x: f32,
+        y: f32,
+    ) -> PointerEventData {
+        let layout = self.nodes[target].final_layout;
+        let position = self.absolute_position(target);
+        let padding_x = position.x + layout.border.left;
+        let padding_y = position.y + layout.border.top;
+        PointerEventData {
+            hit,
+            client_x: f64::from(x),
+            client_y: f64::from(y) - self.viewport_scroll,
+            page_x: f64::from(x),
+            page_y: f64::from(y),
+            offset_x: f64::from(x - padding_x),
+            offset_y: f64::from(y - padding_y),
+        }
+    }
+}
+
+#[test]
+fn computes_pointer_event_coordinates() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div id=target style='margin: 30px 0 0 20px; border: 5px solid; width: 100px; height: 100px'></div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    doc.set_viewport_scroll(10.0);
+
+    let target_id = doc.get_element_by_id("target").unwrap();
+    let data = doc.pointer_event_data(target_id, target_id, 40.0, 60.0);
+    assert_eq!((data.page_x, data.page_y), (40.0, 60.0));
+    assert_eq!((data.client_x, data.client_y), (40.0, 50.0));
+    // The target's padding edge is inside its 5px border, at (25, 35)
+    assert_eq!((data.offset_x, data.offset_y), (15.0, 25.0));
+}
+
+#[test]
+fn normalizes_wheel_deltas() {
+    let lines = WheelDelta::Lines { x: 0.0, y: 3.0 };
+    assert_eq!(lines.to_css_pixels(20.0, 2.0), (0.0, -60.0));
+
+    // Pixel deltas are physical pixels, so they don't depend on the line height
+    let pixels = WheelDelta::Pixels { x: -10.0, y: 30.0 };
+    assert_eq!(pixels.to_css_pixels(20.0, 2.0), (5.0, -15.0));
 }
@@ -0,0 +1,153 @@
+//! Fieldsets, their legends, and disabled form controls
+//!
+//! <https://html.spec.whatwg.org/multipage/form-elements.html#the-fieldset-element>
+//! <https://html.spec.whatwg.org/multipage/rendering.html#the-fieldset-and-legend-elements>
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-disabled>
+//!
+//! The first `<legend>` child of a fieldset is its rendered legend. The legend is laid out at the top of the fieldset
+//! like any other block, and the renderer draws the fieldset's top border through the middle of it, leaving a gap (the
+//! notch) where the legend is. [`Document::legend_notch`] gives where that is.
+//!
+//! A form control is disabled if it has a `disabled` attribute, or if it's within a `<fieldset disabled>` (unless it's
+//! within that fieldset's rendered legend). Disabled controls match `:disabled` rather than `:enabled`, can't be
+//! focused, and ignore clicks and typing.
+use html5ever::local_name;
+
+use crate::{Document, Node};
+
+/// Where a fieldset's top border is drawn and the gap that its legend leaves in it, in CSS pixels relative to the
+/// fieldset's border box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LegendNotch {
+    /// The distance from the top of the border box to the top of the border
+    pub border_top: f32,
+    /// The left edge of the gap
+    pub left: f32,
+    /// The right edge of the gap
+    pub right: f32,
+}
+
+impl Node {
+    /// Whether the node is a form control (or `<fieldset>`) that can be disabled
+    pub fn is_disableable(&self) -> bool {
+        self.element_data().is_some_and(|element_data| {
+            matches!(
+                element_data.name.local,
+                local_name!("button")
+                    | local_name!("input")
+                    | local_name!("select")
+                    | local_name!("textarea")
+                    | local_name!("fieldset")
+                    | local_name!("optgroup")
+                    | local_name!("option")
+            )
+        })
+    }
+
+    /// Whether the node is a disabled form control: it has a `disabled` attribute, is an option in a disabled
+    /// `<optgroup>`, or is within a disabled `<fieldset>` other than in its rendered legend
+    pub fn is_disabled(&self) -> bool {
+        if !self.is_disableable() {
+            return false;
+        }
+        if self.attr(local_name!("disabled")).is_some() {
+            return true;
+        }
+        if self.is_element_with_tag_name(&local_name!("optgroup")) {
+            return false;
+        }
+        if self.is_element_with_tag_name(&local_name!("option")) {
+            return self.parent.is_some_and(|parent_id| {
+                let parent = self.with(parent_id);
+                parent.is_element_with_tag_name(&local_name!("optgroup"))
+                    && parent.attr(local_name!("disabled")).is_some()
+            });
+        }
+
+        let mut child = self;
+        while let Some(parent_id) = child.parent {
+            let parent = self.with(parent_id);
+            if parent.is_element_with_tag_name(&local_name!("fieldset"))
+                && parent.attr(local_name!("disabled")).is_some()
+                && parent.rendered_legend() != Some(child.id)
+            {
+                return true;
+            }
+            child = parent;
+        }
+        false
+    }
+
+    /// The first `<legend>` child of a `<fieldset>`
+    fn rendered_legend(&self) -> Option<usize> {
+        self.children.iter().copied().find(|&child_id| {
+            self.with(child_id)
+                .is_element_with_tag_name(&local_name!("legend"))
+        })
+    }
+}
+
+impl Document {
+    /// Whether an element is a disabled form control (see [`Node::is_disabled`])
+    pub fn is_disabled(&self, node_id: usize) -> bool {
+        self.nodes[node_id].is_disabled()
+    }
+
+    /// The rendered legend of a `<fieldset>` (if it has one)
+    pub fn fieldset_legend(&self, node_id: usize) -> Option<usize> {
+        let node = &self.nodes[node_id];
+        if !node.is_element_with_tag_name(&local_name!("fieldset")) {
+            return None;
+        }
+        node.rendered_legend()
+    }
+
+    /// Where a fieldset's top border is drawn through its legend, or `None` if it has no legend that's laid out
+    pub fn legend_notch(&self, node_id: usize) -> Option<LegendNotch> {
+        let legend_id = self.fieldset_legend(node_id)?;
+        let legend = &self.nodes[legend_id];
+        if legend.style.display == taffy::Display::None {
+            return None;
+        }
+        let layout = legend.final_layout;
+        let border_width = self.nodes[node_id].final_layout.border.top;
+        Some(LegendNotch {
+            border_top: (layout.location.y + layout.size.height / 2.0 - border_width / 2.0)
+                .max(0.0),
+            left: layout.location.x,
+            right: layout.location.x + layout.size.width,
+        })
+    }
+}
+
+#[test]
+fn propagates_disabled_fieldsets() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <fieldset disabled id=fieldset><legend id=legend>Shipping <input id=in-legend></legend>\
+        <div><input id=nested><button id=button>Go</button></div></fieldset>\
+        <input id=enabled><select><optgroup disabled><option id=option>A</option></optgroup></select>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div, fieldset, legend { display: block } fieldset { border: 2px solid } legend { height: 20px }"]);
+    doc.resolve();
+
+    let disabled = |id: &str| doc.is_disabled(doc.get_element_by_id(id).unwrap());
+    assert!(disabled("fieldset"));
+    assert!(disabled("nested"));
+    assert!(disabled("button"));
+    assert!(disabled("option"));
+    assert!(!disabled("in-legend"));
+    assert!(!disabled("enabled"));
+
+    // Disabled controls can't be focused
+    assert!(!doc.is_focusable(doc.get_element_by_id("nested").unwrap()));
+
+    // The top border goes through the middle of the legend
+    let fieldset = doc.get_element_by_id("fieldset").unwrap();
+    let legend = doc.get_element_by_id("legend").unwrap();
+    let notch = doc.legend_notch(fieldset).unwrap();
+    let legend_layout = doc.nodes[legend].final_layout;
+    assert_eq!(notch.border_top, legend_layout.location.y + 9.0);
+    assert_eq!(notch.left, legend_layout.location.x);
+}
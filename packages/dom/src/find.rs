@@ -0,0 +1,220 @@
+//! Find-in-page
+//!
+//! Searches the visible text of the document for a query. Matches are found within individual text nodes, so a
+//! query will not match text that is split across elements (e.g. `foo<b>bar</b>`). The matches are kept in the
+//! document so that the renderer can highlight them, and one of them is "active": it is highlighted in a different
+//! color and scrolled into view when navigating with [`Document::find_next`] and [`Document::find_previous`].
+use std::ops::Range;
+
+use html5ever::local_name;
+use style::properties::generated::longhands::visibility::computed_value::T as StyloVisibility;
+use style::values::specified::box_::DisplayInside;
+
+use crate::node::NodeData;
+use crate::Document;
+
+/// Options for [`Document::find`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Only match text with the same case as the query
+    pub case_sensitive: bool,
+    /// Only match text that isn't directly preceded or followed by a letter or digit
+    pub whole_word: bool,
+}
+
+/// A match of a find-in-page query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FindMatch {
+    /// The text node that the match is in
+    pub node_id: usize,
+    /// The byte range of the match within the text node's content
+    pub range: Range<usize>,
+}
+
+/// The results of the last call to [`Document::find`]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FindState {
+    pub(crate) matches: Vec<FindMatch>,
+    pub(crate) active: usize,
+}
+
+/// Find the byte ranges of the (non-overlapping) occurrences of a query in some text
+fn find_in_text(text: &str, query: &str, options: FindOptions) -> Vec<Range<usize>> {
+    let chars_match = |a: char, b: char| {
+        a == b || (!options.case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+    };
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    let mut ranges = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+
+        let mut text_chars = text[start..].char_indices();
+        let mut end = start;
+        let matched = query.chars().all(|query_char| match text_chars.next() {
+            Some((idx, text_char)) if chars_match(text_char, query_char) => {
+                end = start + idx + text_char.len_utf8();
+                true
+            }
+            _ => false,
+        });
+        if !matched {
+            continue;
+        }
+
+        if options.whole_word
+            && (is_word_char(text[..start].chars().next_back())
+                || is_word_char(text[end..].chars().next()))
+        {
+            continue;
+        }
+
+        ranges.push(start..end);
+        search_from = end;
+    }
+    ranges
+}
+
+impl Document {
+    /// Search the visible text of the document for a query, replacing the results of any previous search. The first
+    /// match becomes the active match and is scrolled into view.
+    ///
+    /// Returns the matches in tree order.
+    pub fn find(&mut self, query: &str, options: FindOptions) -> &[FindMatch] {
+        let mut matches = Vec::new();
+        if !query.is_empty() {
+            self.collect_find_matches(self.root_element().id, query, options, &mut matches);
+        }
+
+        self.find_state = Some(FindState { matches, active: 0 });
+        self.scroll_to_active_find_match();
+        self.find_matches()
+    }
+
+    /// The matches of the last search (if any)
+    pub fn find_matches(&self) -> &[FindMatch] {
+        self.find_state
+            .as_ref()
+            .map(|state| state.matches.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The index of the active match within [`Document::find_matches`]
+    pub fn active_find_match(&self) -> Option<usize> {
+        self.find_state
+            .as_ref()
+            .filter(|state| !state.matches.is_empty())
+            .map(|state| state.active)
+    }
+
+    /// Make the next match active (wrapping around to the first) and scroll it into view
+    pub fn find_next(&mut self) -> Option<&FindMatch> {
+        self.step_find_match(true)
+    }
+
+    /// Make the previous match active (wrapping around to the last) and scroll it into view
+    pub fn find_previous(&mut self) -> Option<&FindMatch> {
+        self.step_find_match(false)
+    }
+
+    /// Remove the matches of the last search
+    pub fn clear_find(&mut self) {
+        self.find_state = None;
+    }
+
+    /// The rectangles (in document coordinates) that a match covers, one per line
+    pub fn find_match_rects(&self, find_match: &FindMatch) -> Vec<taffy::Layout> {
+        let text_len = self
+            .nodes
+            .get(find_match.node_id)
+            .and_then(|node| node.text_data())
+            .map(|text| text.content.len());
+
+        // The text may have changed since it was searched
+        if text_len.map_or(true, |len| find_match.range.end > len) {
+            return Vec::new();
+        }
+        self.text_range_rects(find_match.node_id, find_match.range.clone())
+            .unwrap_or_default()
+    }
+
+    fn step_find_match(&mut self, forward: bool) -> Option<&FindMatch> {
+        let state = self.find_state.as_mut()?;
+        let len = state.matches.len();
+        if len == 0 {
+            return None;
+        }
+        state.active = if forward {
+            (state.active + 1) % len
+        } else {
+            (state.active + len - 1) % len
+        };
+
+        self.scroll_to_active_find_match();
+        let state = self.find_state.as_ref()?;
+        state.matches.get(state.active)
+    }
+
+    fn scroll_to_active_find_match(&mut self) {
+        let Some(find_match) = self
+            .find_state
+            .as_ref()
+            .and_then(|state| state.matches.get(state.active))
+        else {
+            return;
+        };
+
+        let rects = self.find_match_rects(find_match);
+        let (Some(first), Some(last)) = (rects.first(), rects.last()) else {
+            return;
+        };
+        let top = first.location.y as f64;
+        let bottom = (last.location.y + last.size.height) as f64;
+        self.scroll_into_view(top, bottom);
+    }
+
+    fn collect_find_matches(
+        &self,
+        node_id: usize,
+        query: &str,
+        options: FindOptions,
+        matches: &mut Vec<FindMatch>,
+    ) {
+        let node = &self.nodes[node_id];
+        match &node.raw_dom_data {
+            NodeData::Text(text) => {
+                let visible = node
+                    .parent
+                    .and_then(|parent_id| self.nodes[parent_id].primary_styles())
+                    .map_or(true, |style| {
+                        style.get_inherited_box().visibility == StyloVisibility::Visible
+                    });
+                if visible {
+                    matches.extend(
+                        find_in_text(&text.content, query, options)
+                            .into_iter()
+                            .map(|range| FindMatch { node_id, range }),
+                    );
+                }
+            }
+            NodeData::Element(element_data) => {
+                // Text that isn't rendered can't be found
+                if node.hidden
+                    || element_data.attr(local_name!("hidden")).is_some()
+                    || node
+                        .display_style()
+                        .is_some_and(|display| display.inside() == DisplayInside::None)
+                {
+                    return;
+                }
+                for child_id in node.children.iter().copied() {
+                    self.collect_find_matches(child_id, query, options, matches);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Keyboard focus
+//!
+//! At most one element of a document has focus, which matches the `:focus` pseudo-class. Links, form controls,
+//! editing hosts and elements with a `tabindex` attribute can be focused, unless they're disabled or aren't rendered.
+use html5ever::local_name;
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style_traits::dom::ElementState;
+
+use crate::Document;
+
+impl Document {
+    /// The element that has focus (if any)
+    pub fn focused_element(&self) -> Option<usize> {
+        // A node that has been removed (and whose id has been reused) doesn't have the focus state
+        self.focus_node_id.filter(|&node_id| {
+            self.nodes
+                .get(node_id)
+                .is_some_and(|node| node.element_state.contains(ElementState::FOCUS))
+        })
+    }
+
+    /// Whether an element can be focused
+    pub fn is_focusable(&self, node_id: usize) -> bool {
+        let Some(node) = self.nodes.get(node_id) else {
+            return false;
+        };
+        let Some(element_data) = node.element_data() else {
+            return false;
+        };
+        if node.is_disabled() || !self.is_rendered(node_id) {
+            return false;
+        }
+        if let Some(tabindex) = node.attr(local_name!("tabindex")) {
+            return tabindex.trim().parse::<i32>().is_ok();
+        }
+        match element_data.name.local {
+            local_name!("a") | local_name!("area") => node.attr(local_name!("href")).is_some(),
+            local_name!("input") => !node
+                .attr(local_name!("type"))
+                .is_some_and(|ty| ty.eq_ignore_ascii_case("hidden")),
+            local_name!("button")
+            | local_name!("select")
+            | local_name!("textarea")
+            | local_name!("iframe")
+            | local_name!("summary") => true,
+            _ => self.is_editing_host(node_id),
+        }
+    }
+
+    /// The focusable elements of the document, in tree order
+    pub fn focusable_elements(&self) -> Vec<usize> {
+        let mut elements = Vec::new();
+        if let Some(root) = self.try_root_element() {
+            self.collect_focusable_elements(root.id, &mut elements);
+        }
+        elements
+    }
+
+    /// The element itself if it's focusable, otherwise its nearest focusable ancestor
+    pub fn focusable_ancestor(&self, node_id: usize) -> Option<usize> {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            if self.is_focusable(id) {
+                return Some(id);
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        None
+    }
+
+    /// Give an element focus, taking it from the element that had it. Returns false (and leaves focus where it is) if
+    /// the element can't be focused.
+    pub fn focus(&mut self, node_id: usize) -> bool {
+        if !self.is_focusable(node_id) {
+            return false;
+        }
+        if self.focused_element() == Some(node_id) {
+            return true;
+        }
+        self.blur();
+        self.set_focus_state(node_id, true);
+        self.focus_node_id = Some(node_id);
+        true
+    }
+
+    /// Remove focus from the focused element (if any)
+    pub fn blur(&mut self) {
+        if let Some(node_id) = self.focused_element() {
+            self.set_focus_state(node_id, false);
+        }
+        self.focus_node_id = None;
+    }
+
+    fn set_focus_state(&mut self, node_id: usize, focused: bool) {
+        self.snapshot_node(node_id);
+        let node = &mut self.nodes[node_id];
+        if focused {
+            node.element_state.insert(ElementState::FOCUS);
+        } else {
+            node.element_state.remove(ElementState::FOCUS);
+        }
+        if let Some(element_data) = node.stylo_element_data.borrow_mut().as_mut() {
+            element_data.hint.insert(RestyleHint::RESTYLE_SELF);
+        }
+    }
+
+    /// Whether an element and its ancestors are rendered (i.e. none of them are `display: none` or hidden)
+    fn is_rendered(&self, node_id: usize) -> bool {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            if node.hidden || node.style.display == taffy::Display::None {
+                return false;
+            }
+            maybe_id = node.parent;
+        }
+        true
+    }
+
+    fn collect_focusable_elements(&self, node_id: usize, elements: &mut Vec<usize>) {
+        let node = &self.nodes[node_id];
+        if node.hidden || node.style.display == taffy::Display::None {
+            return;
+        }
+        if self.is_focusable(node_id) {
+            elements.push(node_id);
+        }
+        for &child_id in &node.children {
+            self.collect_focusable_elements(child_id, elements);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Metric overrides for `@font-face` rules that alias local fonts
+//!
+//! <https://drafts.csswg.org/css-fonts-5/#font-metrics-override-desc>
+//!
+//! Pages reduce the layout shift caused by web fonts replacing their fallbacks by declaring a fallback face that
+//! adjusts a local font to match the web font's metrics:
+//!
+//! ```css
+//! @font-face {
+//!     font-family: "Inter Fallback";
+//!     src: local("Arial");
+//!     size-adjust: 107%;
+//!     ascent-override: 90%;
+//! }
+//! ```
+//!
+//! Text whose `font-family` resolves to such a face is shaped with the local font, scaled by `size-adjust`, and its
+//! `normal` line height is taken from the ascent, descent and line gap overrides. Faces are matched by walking the
+//! family list: faces without an available local source (such as web fonts, which Blitz doesn't load) are skipped,
+//! and the walk stops at the first family that isn't declared by an `@font-face` rule.
+//!
+//! The rules are read from the stylist, so they include those of adopted stylesheets and of stylesheets edited
+//! through the CSSOM, and `@media` and `@supports` conditions are honored. Parley's text styles borrow their family
+//! stacks for `'static`, so local family names and the stacks that faces are substituted into are leaked. They are
+//! interned, so the leak is bounded: each distinct name and stack is leaked once per document, however many times
+//! text is laid out with it.
+//!
+//! `font-size-adjust` isn't supported, as the servo configuration of Stylo doesn't parse it.
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use parley::style::{FontFamily, FontStack, TextStyle};
+use style::font_face::Source;
+use style::stylesheets::{CssRule, Origin, StylesheetInDocument};
+use style::values::specified::font::MetricsOverride;
+
+use crate::node::TextBrush;
+use crate::Document;
+
+/// An `@font-face` rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontFace {
+    /// The family name that the rule declares
+    pub family: String,
+    /// The first of the rule's `local()` sources that is installed (if any)
+    pub local_family: Option<&'static str>,
+    pub metrics: FontMetricOverrides,
+}
+
+/// The metric override descriptors of an `@font-face` rule. Overrides are fractions of the font size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetricOverrides {
+    /// `size-adjust`, which scales the glyphs of the face
+    pub size_adjust: f32,
+    pub ascent: Option<f32>,
+    pub descent: Option<f32>,
+    pub line_gap: Option<f32>,
+}
+
+impl Default for FontMetricOverrides {
+    fn default() -> Self {
+        Self {
+            size_adjust: 1.0,
+            ascent: None,
+            descent: None,
+            line_gap: None,
+        }
+    }
+}
+
+/// The `@font-face` rules of a document
+#[derive(Default)]
+pub(crate) struct FontFaces {
+    faces: Vec<FontFace>,
+    /// Whether the document's stylesheets have changed since the rules were read
+    stale: bool,
+    /// Local family names, leaked and interned
+    names: HashSet<&'static str>,
+    /// Family stacks with a face's local family substituted, leaked and interned by the stack they were
+    /// substituted into
+    stacks: RefCell<HashMap<Vec<FontFamily<'static>>, &'static [FontFamily<'static>]>>,
+}
+
+impl FontFaces {
+    /// Apply the metric overrides of the face that a text style's font family resolves to (if any).
+    /// `normal_line_height` is whether the style's `line-height` is `normal`.
+    pub(crate) fn adjust_text_style(
+        &self,
+        style: &mut TextStyle<'static, TextBrush>,
+        normal_line_height: bool,
+    ) {
+        if self.faces.is_empty() {
+            return;
+        }
+        let families: &[FontFamily<'static>] = match &style.font_stack {
+            FontStack::List(families) => families,
+            FontStack::Single(family) => std::slice::from_ref(family),
+            FontStack::Source(_) => return,
+        };
+
+        let mut matched = None;
+        for (index, family) in families.iter().enumerate() {
+            // Families that no rule declares are installed fonts (or generic families), which are always used
+            let FontFamily::Named(name) = family else {
+                break;
+            };
+            // Later rules take precedence
+            let Some(face) = self
+                .faces
+                .iter()
+                .rev()
+                .find(|face| face.family.eq_ignore_ascii_case(name))
+            else {
+                break;
+            };
+            if let Some(local_family) = face.local_family {
+                matched = Some((index, local_family, face.metrics));
+                break;
+            }
+        }
+        let Some((index, local_family, metrics)) = matched else {
+            return;
+        };
+
+        // The local font takes the place of the face's family name, which the font collection doesn't know
+        let stack = *self
+            .stacks
+            .borrow_mut()
+            .entry(families.to_vec())
+            .or_insert_with(|| {
+                let mut stack = families.to_vec();
+                stack[index] = FontFamily::Named(local_family);
+                // Leaked once per distinct stack (see the module docs)
+                Box::leak(stack.into_boxed_slice())
+            });
+        style.font_stack = FontStack::List(stack);
+
+        // Line heights are multiples of the font size, so they're rescaled to stay the same height. The metric
+        // overrides aren't affected by `size-adjust`.
+        let font_size = style.font_size;
+        let adjusted_size = font_size * metrics.size_adjust;
+        let line_height = match (normal_line_height, metrics.ascent, metrics.descent) {
+            (true, Some(ascent), Some(descent)) => {
+                (ascent + descent + metrics.line_gap.unwrap_or(0.0)) * font_size
+            }
+            (true, _, _) => style.line_height * adjusted_size,
+            (false, _, _) => style.line_height * font_size,
+        };
+        style.font_size = adjusted_size;
+        style.line_height = line_height / adjusted_size;
+    }
+}
+
+impl Document {
+    /// The `@font-face` rules of the document's stylesheets
+    pub fn font_faces(&self) -> &[FontFace] {
+        &self.font_faces.faces
+    }
+
+    /// Read the `@font-face` rules again if the document's stylesheets have changed since they were last read
+    pub(crate) fn flush_font_faces(&mut self) {
+        if !std::mem::take(&mut self.font_faces.stale) {
+            return;
+        }
+
+        let guard = self.guard.read();
+        let device = self.stylist.device();
+        let mut faces = Vec::new();
+        for origin in [Origin::UserAgent, Origin::User, Origin::Author] {
+            for index in 0..self.stylist.sheet_count(origin) {
+                let Some(sheet) = self.stylist.sheet_at(origin, index) else {
+                    continue;
+                };
+                for rule in sheet.effective_rules(device, &guard) {
+                    let CssRule::FontFace(rule) = rule else {
+                        continue;
+                    };
+                    let rule = rule.read_with(&guard);
+                    let Some(family) = &rule.family else {
+                        continue;
+                    };
+                    let local_family = rule
+                        .sources
+                        .iter()
+                        .flat_map(|sources| sources.0.iter())
+                        .find_map(|source| match source {
+                            Source::Local(name) => Some(&*name.name).filter(|name| {
+                                self.font_ctx.collection.family_by_name(name).is_some()
+                            }),
+                            Source::Url(_) => None,
+                        });
+                    faces.push(FontFace {
+                        family: family.name.to_string(),
+                        local_family: local_family.map(|name| self.font_faces.intern_name(name)),
+                        metrics: FontMetricOverrides {
+                            size_adjust: rule
+                                .size_adjust
+                                .as_ref()
+                                .map_or(1.0, |size_adjust| size_adjust.0.get()),
+                            ascent: metric_override(&rule.ascent_override),
+                            descent: metric_override(&rule.descent_override),
+                            line_gap: metric_override(&rule.line_gap_override),
+                        },
+                    });
+                }
+            }
+        }
+        self.font_faces.faces = faces;
+    }
+}
+
+impl FontFaces {
+    /// Note that the document's stylesheets have changed, so their rules need to be read again
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Leak a local family name, once per distinct name (see the module docs)
+    fn intern_name(&mut self, name: &str) -> &'static str {
+        if let Some(interned) = self.names.get(name) {
+            return interned;
+        }
+        let interned: &'static str = String::from(name).leak();
+        self.names.insert(interned);
+        interned
+    }
+}
+
+/// A metric override descriptor as a fraction of the font size. `normal` (or no override) is `None`.
+fn metric_override(value: &Option<MetricsOverride>) -> Option<f32> {
+    match value {
+        Some(MetricsOverride::Override(percentage)) => Some(percentage.0.get()),
+        _ => None,
+    }
+}
+
+#[test]
+fn reads_metric_override_descriptors() {
+    use crate::adopted_stylesheets::ConstructedStyleSheet;
+    use crate::test_util::parse_test_document;
+
+    let html = r#"<!DOCTYPE html><html><head><style>
+        @font-face {
+            font-family: "Inter Fallback";
+            src: url(inter.woff2), local("Arial"), local(Helvetica);
+            size-adjust: 107%;
+            ascent-override: 90%;
+            descent-override: normal;
+            line-gap-override: 0%;
+        }
+        @media (max-width: 10px) {
+            @font-face { font-family: Narrow; src: local(Arial) }
+        }
+        body { font-family: "Inter", "Inter Fallback" }
+    </style></head><body>Text</body></html>"#;
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let faces = doc.font_faces();
+    assert_eq!(faces.len(), 1);
+    let FontFace {
+        family, metrics, ..
+    } = faces[0].clone();
+    assert_eq!(family, "Inter Fallback");
+    assert_eq!(
+        metrics,
+        FontMetricOverrides {
+            size_adjust: 1.07,
+            ascent: Some(0.9),
+            descent: None,
+            line_gap: Some(0.0),
+        }
+    );
+
+    // Rules of adopted stylesheets are read too
+    let sheet = ConstructedStyleSheet::new(
+        &doc,
+        "@font-face { font-family: Adopted; src: local(Arial) }",
+    );
+    doc.set_adopted_stylesheets(vec![sheet]);
+    doc.resolve();
+    let families: Vec<_> = doc
+        .font_faces()
+        .iter()
+        .map(|face| face.family.as_str())
+        .collect();
+    assert_eq!(families, ["Inter Fallback", "Adopted"]);
+
+    // Text whose family resolves to the face uses the local font, scaled, with a line height from the overrides
+    let faces = FontFaces {
+        faces: vec![
+            // A web font, which isn't loaded
+            FontFace {
+                family: String::from("Inter"),
+                local_family: None,
+                metrics: FontMetricOverrides::default(),
+            },
+            FontFace {
+                family: family.clone(),
+                local_family: Some("Arial"),
+                metrics: FontMetricOverrides {
+                    descent: Some(0.3),
+                    ..metrics
+                },
+            },
+        ],
+        ..Default::default()
+    };
+    let families: &'static [FontFamily<'static>] = &[
+        FontFamily::Named("Inter"),
+        FontFamily::Named("inter fallback"),
+    ];
+    let mut style = TextStyle {
+        font_stack: FontStack::List(families),
+        font_size: 10.0,
+        line_height: 1.2,
+        ..Default::default()
+    };
+    faces.adjust_text_style(&mut style, true);
+    let FontStack::List(families) = style.font_stack else {
+        panic!("expected a list of families");
+    };
+    assert_eq!(
+        families,
+        [FontFamily::Named("Inter"), FontFamily::Named("Arial")]
+    );
+    assert!((style.font_size - 10.7).abs() < 1e-4);
+    assert!((style.line_height * style.font_size - 12.0).abs() < 1e-4);
+
+    // Laying the same text out again reuses the substituted stack
+    let mut restyled = TextStyle {
+        font_stack: FontStack::List(&[
+            FontFamily::Named("Inter"),
+            FontFamily::Named("inter fallback"),
+        ]),
+        ..Default::default()
+    };
+    faces.adjust_text_style(&mut restyled, true);
+    let FontStack::List(restyled_families) = restyled.font_stack else {
+        panic!("expected a list of families");
+    };
+    assert!(std::ptr::eq(families, restyled_families));
+
+    // Families declared by no rule are used as they are
+    let families: &'static [FontFamily<'static>] = &[FontFamily::Named("Georgia")];
+    let mut style = TextStyle {
+        font_stack: FontStack::List(families),
+        font_size: 10.0,
+        ..Default::default()
+    };
+    faces.adjust_text_style(&mut style, true);
+    assert_eq!(style.font_size, 10.0);
+}
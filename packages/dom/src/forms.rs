@@ -0,0 +1,355 @@
+//! Buttons, and submitting and resetting forms
+//!
+//! <https://html.spec.whatwg.org/multipage/form-elements.html#the-button-element>
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm>
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-2>
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#resetting-a-form>
+//!
+//! A form control belongs to the form named by its `form` attribute, or else to the form it's within (its form
+//! owner). Activating a submit button submits its form owner: unless the form has `novalidate` (or the button
+//! `formnovalidate`), the form's controls are validated first, and an invalid form isn't submitted. The submission is
+//! the name and value of each of the form's enabled controls (and of the button), sent to the form's `action` with its
+//! `method`, which the button's `formaction` and `formmethod` override. GET submissions navigate to the action with the
+//! entries as its query, and POST submissions are handed to the [`Navigator`](crate::navigation::Navigator). A form with the `dialog` method closes
+//! the dialog that it's in instead.
+//!
+//! Activating a reset button resets its form owner: the value of each control that the user changed (and the files
+//! chosen for file inputs) is restored to what it was before.
+//!
+//! Note: the files of file inputs are submitted by name, without their contents.
+use html5ever::{local_name, namespace_url, ns, QualName};
+use url::Url;
+
+use crate::Document;
+
+/// What activating a button does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonType {
+    /// Submit the button's form
+    Submit,
+    /// Reset the button's form
+    Reset,
+    /// Nothing (the page handles it)
+    Button,
+}
+
+/// How a form is submitted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormMethod {
+    Get,
+    Post,
+    /// Close the `<dialog>` that the form is in
+    Dialog,
+}
+
+/// A form that is ready to be submitted
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormSubmission {
+    /// The `<form>` element
+    pub form: usize,
+    /// The button that submitted the form, if any
+    pub submitter: Option<usize>,
+    pub method: FormMethod,
+    /// Where the form is submitted to, or `None` if it can't be resolved
+    pub action: Option<Url>,
+    /// The names and values of the form's controls, in tree order
+    pub entries: Vec<(String, String)>,
+}
+
+impl Document {
+    /// What activating an element does, or `None` if it isn't a button
+    pub fn button_type(&self, node_id: usize) -> Option<ButtonType> {
+        let element_data = self.nodes[node_id].element_data()?;
+        let ty = element_data
+            .attr(local_name!("type"))
+            .map(|ty| ty.trim().to_ascii_lowercase());
+        match element_data.name.local {
+            local_name!("button") => Some(match ty.as_deref() {
+                Some("reset") => ButtonType::Reset,
+                Some("button") => ButtonType::Button,
+                _ => ButtonType::Submit,
+            }),
+            local_name!("input") => match ty.as_deref() {
+                Some("submit" | "image") => Some(ButtonType::Submit),
+                Some("reset") => Some(ButtonType::Reset),
+                Some("button") => Some(ButtonType::Button),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The button that a node is (or is within), if any
+    pub fn button_ancestor(&self, node_id: usize) -> Option<usize> {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            if self.button_type(id).is_some() {
+                return Some(id);
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        None
+    }
+
+    /// The form that a form control belongs to
+    pub fn form_owner(&self, node_id: usize) -> Option<usize> {
+        let node = &self.nodes[node_id];
+        if let Some(id) = node.attr(local_name!("form")) {
+            return self.get_element_by_id(id).filter(|&form_id| {
+                self.nodes[form_id].is_element_with_tag_name(&local_name!("form"))
+            });
+        }
+        let mut maybe_id = node.parent;
+        while let Some(id) = maybe_id {
+            if self.nodes[id].is_element_with_tag_name(&local_name!("form")) {
+                return Some(id);
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        None
+    }
+
+    /// The controls that belong to a form, in tree order
+    pub fn form_controls(&self, form_id: usize) -> Vec<usize> {
+        fn collect(doc: &Document, node_id: usize, form_id: usize, controls: &mut Vec<usize>) {
+            let node = &doc.nodes[node_id];
+            let is_control = node.element_data().is_some_and(|element_data| {
+                matches!(
+                    element_data.name.local,
+                    local_name!("button")
+                        | local_name!("input")
+                        | local_name!("select")
+                        | local_name!("textarea")
+                )
+            });
+            if is_control && doc.form_owner(node_id) == Some(form_id) {
+                controls.push(node_id);
+            }
+            for &child_id in &node.children {
+                collect(doc, child_id, form_id, controls);
+            }
+        }
+        let mut controls = Vec::new();
+        if let Some(root) = self.try_root_element() {
+            collect(self, root.id, form_id, &mut controls);
+        }
+        controls
+    }
+
+    /// The first submit button of a form, which pressing enter in one of its text fields activates
+    pub fn default_button(&self, form_id: usize) -> Option<usize> {
+        self.form_controls(form_id)
+            .into_iter()
+            .find(|&node_id| self.button_type(node_id) == Some(ButtonType::Submit))
+    }
+
+    /// The names and values that a form submits, with the button that submitted it (if any)
+    pub fn form_entries(&self, form_id: usize, submitter: Option<usize>) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        for node_id in self.form_controls(form_id) {
+            let node = &self.nodes[node_id];
+            let name = node.attr(local_name!("name")).unwrap_or_default();
+            if name.is_empty() || node.is_disabled() {
+                continue;
+            }
+            let name = name.to_string();
+            let value = || {
+                node.attr(local_name!("value"))
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            if self.button_type(node_id).is_some() {
+                if submitter == Some(node_id) {
+                    entries.push((name, value()));
+                }
+                continue;
+            }
+            if !node.is_element_with_tag_name(&local_name!("input")) {
+                entries.push((name, node.control_value()));
+                continue;
+            }
+            match node.input_type().as_str() {
+                "checkbox" | "radio" => {
+                    if node.attr(local_name!("checked")).is_some() {
+                        let value = node.attr(local_name!("value")).unwrap_or("on");
+                        entries.push((name, value.to_string()));
+                    }
+                }
+                "file" => {
+                    let files = self.selected_files(node_id);
+                    if files.is_empty() {
+                        entries.push((name, String::new()));
+                    }
+                    for file in files {
+                        let file_name = file
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        entries.push((name.clone(), file_name));
+                    }
+                }
+                _ => entries.push((name, value())),
+            }
+        }
+        entries
+    }
+
+    /// Validate a form and collect what it submits. Returns `None` (and focuses the first invalid control) if the
+    /// form is invalid.
+    pub fn prepare_form_submission(
+        &mut self,
+        form_id: usize,
+        submitter: Option<usize>,
+    ) -> Option<FormSubmission> {
+        let submitter_attr = |doc: &Document, name| {
+            submitter
+                .and_then(|submitter_id| doc.nodes[submitter_id].attr(name).map(str::to_string))
+        };
+        let form_attr = |doc: &Document, name| doc.nodes[form_id].attr(name).map(str::to_string);
+
+        let validate = form_attr(self, local_name!("novalidate")).is_none()
+            && submitter_attr(self, local_name!("formnovalidate")).is_none();
+        if validate {
+            if let Some(&(invalid_id, _)) = self.report_validity(form_id).first() {
+                self.focus(invalid_id);
+                return None;
+            }
+        }
+
+        let method = submitter_attr(self, local_name!("formmethod"))
+            .or_else(|| form_attr(self, local_name!("method")))
+            .unwrap_or_default();
+        let method = match method.trim().to_ascii_lowercase().as_str() {
+            "post" => FormMethod::Post,
+            "dialog" => FormMethod::Dialog,
+            _ => FormMethod::Get,
+        };
+        let action = submitter_attr(self, local_name!("formaction"))
+            .or_else(|| form_attr(self, local_name!("action")))
+            .unwrap_or_default();
+
+        Some(FormSubmission {
+            form: form_id,
+            submitter,
+            method,
+            action: self.resolve_url(action.trim()).ok(),
+            entries: self.form_entries(form_id, submitter),
+        })
+    }
+
+    /// Submit a form: navigate for a GET submission, hand a POST submission to the navigator, or close the form's
+    /// dialog
+    pub fn submit_form(&mut self, submission: FormSubmission) {
+        match submission.method {
+            FormMethod::Dialog => {
+                let mut maybe_id = Some(submission.form);
+                while let Some(id) = maybe_id {
+                    if self.nodes[id].is_element_with_tag_name(&local_name!("dialog")) {
+                        self.close_dialog(id);
+                        break;
+                    }
+                    maybe_id = self.nodes[id].parent;
+                }
+            }
+            FormMethod::Get => {
+                let Some(mut url) = submission.action else {
+                    return;
+                };
+                url.set_query(None);
+                if !submission.entries.is_empty() {
+                    url.query_pairs_mut().extend_pairs(&submission.entries);
+                }
+                self.navigate(url);
+            }
+            FormMethod::Post => {
+                if let Some(url) = submission.action {
+                    self.navigate_with_post(url, submission.entries);
+                }
+            }
+        }
+    }
+
+    /// Reset a form, restoring the values that the user changed. Returns the controls whose value changed.
+    pub fn reset_form(&mut self, form_id: usize) -> Vec<usize> {
+        let mut changed = Vec::new();
+        for node_id in self.form_controls(form_id) {
+            self.user_interacted.remove(&node_id);
+            let mut reset = false;
+            if let Some(default) = self.default_values.remove(&node_id) {
+                match default {
+                    Some(value) => {
+                        let name = QualName::new(None, ns!(), local_name!("value"));
+                        self.set_attribute(node_id, name, &value);
+                    }
+                    None => self.remove_attribute(node_id, &local_name!("value")),
+                }
+                reset = true;
+            }
+            if self.selected_files.remove(&node_id).is_some() {
+                self.update_picker_input_text(node_id);
+                reset = true;
+            }
+            if reset {
+                changed.push(node_id);
+            }
+        }
+        changed
+    }
+}
+
+#[test]
+fn submits_and_resets_forms() {
+    use crate::test_util::{parse_html, test_document};
+
+    let html = "<!DOCTYPE html><html><body>\
+        <form id=form action=/search><input name=q id=q value=blitz>\
+        <input type=checkbox name=safe checked><input name=off disabled value=x>\
+        <button name=go value=1 id=go>Go</button><button type=reset id=reset>Reset</button></form>\
+        <input name=outside form=form value=y><button type=button id=plain>Plain</button>\
+        <form id=required><input name=r required><button id=submit-required>Go</button></form>\
+        </body></html>";
+    let mut doc = test_document(&[]);
+    doc.set_base_url("https://example.com/page");
+    parse_html(&mut doc, html);
+    doc.resolve();
+
+    let id = |doc: &Document, id: &str| doc.get_element_by_id(id).unwrap();
+    let form = id(&doc, "form");
+    let go = id(&doc, "go");
+    assert_eq!(doc.button_type(go), Some(ButtonType::Submit));
+    assert_eq!(doc.button_type(id(&doc, "reset")), Some(ButtonType::Reset));
+    assert_eq!(doc.button_type(id(&doc, "plain")), Some(ButtonType::Button));
+    assert_eq!(doc.default_button(form), Some(go));
+
+    // The submission has the enabled controls (including those associated with the `form` attribute) and the button
+    let submission = doc.prepare_form_submission(form, Some(go)).unwrap();
+    assert_eq!(submission.method, FormMethod::Get);
+    assert_eq!(
+        submission.action.as_ref().map(Url::as_str),
+        Some("https://example.com/search")
+    );
+    let entries: Vec<_> = submission
+        .entries
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    assert_eq!(entries, ["q=blitz", "safe=on", "go=1", "outside=y"]);
+
+    // Invalid forms aren't submitted, and their first invalid control gets focus
+    let required = id(&doc, "required");
+    let submit_required = id(&doc, "submit-required");
+    assert!(doc
+        .prepare_form_submission(required, Some(submit_required))
+        .is_none());
+    assert_eq!(
+        doc.focused_element(),
+        doc.form_controls(required).first().copied()
+    );
+
+    // Resetting restores the values that the user changed
+    let q = id(&doc, "q");
+    doc.set_input_value(q, "changed".to_string());
+    assert_eq!(doc.reset_form(form), [q]);
+    assert_eq!(doc.nodes[q].attr(local_name!("value")), Some("blitz"));
+}
@@ -0,0 +1,465 @@
+//! Geometry queries
+//!
+//! The equivalents of `getBoundingClientRect`, `clientWidth`, `scrollWidth`, `offsetParent` and friends, for embedders
+//! that need to position things (popups, tooltips, anchored overlays) relative to laid out nodes. They reflect the
+//! last layout, so should be called after [`Document::resolve`].
+//!
+//! Boxes are positioned by walking up their layout parents, which (unlike their parents in the tree) include
+//! anonymous blocks and the inline roots that inline boxes are laid out in. Text nodes and inline elements that don't
+//! generate boxes of their own are measured from the text they lay out.
+use html5ever::local_name;
+use style::properties::longhands::position::computed_value::T as StyloPosition;
+use style::values::specified::box_::DisplayInside;
+
+use crate::node::NodeData;
+use crate::Document;
+
+/// A rectangle in CSS pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DomRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DomRect {
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// The smallest rectangle that contains both rectangles
+    pub fn union(&self, other: &DomRect) -> DomRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        DomRect {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+
+    /// The area in which the rectangles overlap (if any)
+    pub fn intersection(&self, other: &DomRect) -> Option<DomRect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (right >= x && bottom >= y).then_some(DomRect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+
+    fn translate(&self, dx: f32, dy: f32) -> DomRect {
+        DomRect {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+}
+
+impl Document {
+    /// The border box of a node relative to the document. Text nodes, and inline elements that are laid out as part
+    /// of their inline formatting context, give the smallest rectangle containing their text.
+    ///
+    /// Returns `None` if the node isn't rendered.
+    pub fn node_document_rect(&self, node_id: usize) -> Option<DomRect> {
+        let (rect, fixed) = self.node_layout_rect(node_id)?;
+
+        // Fixed position elements and the top layer are positioned relative to the viewport
+        Some(match fixed {
+            true => rect.translate(0.0, self.viewport_scroll as f32),
+            false => rect,
+        })
+    }
+
+    /// The border box of a node relative to the viewport (like `getBoundingClientRect`)
+    ///
+    /// Returns `None` if the node isn't rendered.
+    pub fn node_bounding_rect(&self, node_id: usize) -> Option<DomRect> {
+        let (rect, fixed) = self.node_layout_rect(node_id)?;
+        Some(match fixed {
+            true => rect,
+            false => rect.translate(0.0, -self.viewport_scroll as f32),
+        })
+    }
+
+    /// The padding box of a node relative to its border box (like `clientLeft`, `clientTop`, `clientWidth` and
+    /// `clientHeight`). Nodes that don't generate boxes have an empty client rect.
+    pub fn client_rect(&self, node_id: usize) -> Option<DomRect> {
+        self.nodes.get(node_id)?;
+        if self.box_position(node_id).is_none() {
+            return Some(DomRect::default());
+        }
+
+        let layout = self.nodes[node_id].final_layout;
+        Some(DomRect {
+            x: layout.border.left,
+            y: layout.border.top,
+            width: layout.size.width - layout.border.left - layout.border.right,
+            height: layout.size.height - layout.border.top - layout.border.bottom,
+        })
+    }
+
+    /// The size of a node's content including the parts that overflow it (like `scrollWidth` and `scrollHeight`). For
+    /// the root element this is the size of the scrollable area of the viewport.
+    pub fn scroll_size(&self, node_id: usize) -> Option<taffy::Size<f32>> {
+        let client = self.client_rect(node_id)?;
+        let node = &self.nodes[node_id];
+        let layout = node.final_layout;
+        let mut size = taffy::Size {
+            width: client.width,
+            height: client.height,
+        };
+
+        // Content is measured from the padding box
+        let mut include = |right: f32, bottom: f32| {
+            size.width = size
+                .width
+                .max(right + layout.padding.right - layout.border.left);
+            size.height = size
+                .height
+                .max(bottom + layout.padding.bottom - layout.border.top);
+        };
+        for child_id in node.layout_children.borrow().iter().flatten().copied() {
+            let child = &self.nodes[child_id].final_layout;
+            include(
+                child.location.x + child.size.width,
+                child.location.y + child.size.height,
+            );
+        }
+        if let Some(text_layout) = node
+            .element_data()
+            .and_then(|element_data| element_data.inline_layout.as_ref())
+        {
+            include(
+                layout.border.left + layout.padding.left + text_layout.layout.width() / self.scale,
+                layout.border.top + layout.padding.top + text_layout.layout.height() / self.scale,
+            );
+        }
+
+        // The root element scrolls the viewport
+        if self
+            .try_root_element()
+            .is_some_and(|root| root.id == node_id)
+        {
+            let viewport = self.stylist.device().au_viewport_size();
+            size.width = size.width.max(viewport.width.to_f32_px());
+            size.height = size.height.max(viewport.height.to_f32_px());
+        }
+
+        Some(size)
+    }
+
+    /// The nearest positioned ancestor of a node, which its offset is measured from (like `offsetParent`)
+    ///
+    /// Returns `None` for the root and `<body>` elements, fixed position elements and nodes that aren't rendered.
+    pub fn offset_parent(&self, node_id: usize) -> Option<usize> {
+        let node = self.nodes.get(node_id)?;
+        if node.is_fixed_position()
+            || self.node_layout_rect(node_id).is_none()
+            || matches!(
+                node.element_data()
+                    .map(|element_data| &element_data.name.local),
+                Some(&local_name!("html") | &local_name!("body"))
+            )
+        {
+            return None;
+        }
+
+        let is_static = |node_id: usize| {
+            self.nodes[node_id].primary_styles().map_or(true, |style| {
+                style.get_box().position == StyloPosition::Static
+            })
+        };
+        let node_is_static = is_static(node_id);
+
+        let mut maybe_id = node.parent;
+        while let Some(id) = maybe_id {
+            let ancestor = &self.nodes[id];
+            let Some(element_data) = ancestor.element_data() else {
+                break;
+            };
+            let name = &element_data.name.local;
+            if !is_static(id)
+                || *name == local_name!("body")
+                || (node_is_static
+                    && matches!(
+                        *name,
+                        local_name!("td") | local_name!("th") | local_name!("table")
+                    ))
+            {
+                return Some(id);
+            }
+            maybe_id = ancestor.parent;
+        }
+        None
+    }
+
+    /// A node's border box relative to the padding box of its offset parent (like `offsetLeft`, `offsetTop`,
+    /// `offsetWidth` and `offsetHeight`), or to the document if it doesn't have one
+    pub fn offset_rect(&self, node_id: usize) -> Option<DomRect> {
+        let rect = self.node_document_rect(node_id)?;
+        let Some(parent_id) = self.offset_parent(node_id) else {
+            return Some(rect);
+        };
+        let parent_rect = self.node_document_rect(parent_id)?;
+        let border = self.nodes[parent_id].final_layout.border;
+        Some(rect.translate(
+            -(parent_rect.x + border.left),
+            -(parent_rect.y + border.top),
+        ))
+    }
+
+    /// A node's rect in the coordinates it was laid out in, and whether those are relative to the viewport rather than
+    /// the document
+    fn node_layout_rect(&self, node_id: usize) -> Option<(DomRect, bool)> {
+        let node = self.nodes.get(node_id)?;
+        if let Some((position, fixed)) = self.box_position(node_id) {
+            let size = node.final_layout.size;
+            let rect = DomRect {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            };
+            return Some((rect, fixed));
+        }
+
+        // Measure text and inline elements from the text they lay out
+        let mut rects = Vec::new();
+        self.collect_inline_rects(node_id, &mut rects);
+        let rect = rects.into_iter().reduce(|a, b| a.union(&b))?;
+        let fixed = self.is_in_fixed_layer(node_id);
+        Some((
+            if fixed {
+                rect.translate(0.0, -self.viewport_scroll as f32)
+            } else {
+                rect
+            },
+            fixed,
+        ))
+    }
+
+    fn collect_inline_rects(&self, node_id: usize, rects: &mut Vec<DomRect>) {
+        let node = &self.nodes[node_id];
+        match &node.raw_dom_data {
+            NodeData::Text(text) => {
+                let text_rects = self
+                    .text_range_rects(node_id, 0..text.content.len())
+                    .unwrap_or_default();
+                rects.extend(text_rects.into_iter().map(|layout| DomRect {
+                    x: layout.location.x,
+                    y: layout.location.y,
+                    width: layout.size.width,
+                    height: layout.size.height,
+                }));
+            }
+            NodeData::Element(_) => {
+                if node.hidden || node.style.display == taffy::Display::None {
+                    return;
+                }
+                for child_id in node.children.iter().copied() {
+                    match self.box_position(child_id) {
+                        Some(_) => {
+                            if let Some(rect) = self.node_document_rect(child_id) {
+                                rects.push(rect);
+                            }
+                        }
+                        None => self.collect_inline_rects(child_id, rects),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a node is laid out relative to the viewport (it or one of its ancestors is fixed or in the top layer)
+    fn is_in_fixed_layer(&self, node_id: usize) -> bool {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            if node.in_top_layer || node.is_fixed_position() {
+                return true;
+            }
+            maybe_id = node.parent;
+        }
+        false
+    }
+
+    /// The position of a node's border box in the coordinates it was laid out in (see [`Document::node_layout_rect`]).
+    ///
+    /// Returns `None` if the node doesn't generate a box, or isn't rendered.
+    fn box_position(&self, node_id: usize) -> Option<(taffy::Point<f32>, bool)> {
+        let node = &self.nodes[node_id];
+        if node.hidden || node.style.display == taffy::Display::None {
+            return None;
+        }
+        let location = node.final_layout.location;
+
+        // The top layer is laid out relative to the viewport
+        if node.in_top_layer {
+            return Some((location, true));
+        }
+        if node.parent.is_none() {
+            return Some((location, false));
+        }
+
+        let (container_id, anonymous_id) = self.layout_parent(node_id)?;
+        let (mut origin, fixed) = self.box_position(container_id)?;
+        let mut inline_root = container_id;
+        if let Some(anonymous_id) = anonymous_id {
+            let anonymous_location = self.nodes[anonymous_id].final_layout.location;
+            origin.x += anonymous_location.x;
+            origin.y += anonymous_location.y;
+            inline_root = anonymous_id;
+        }
+
+        // Inline boxes are positioned relative to the content box of their inline root
+        if self.nodes[inline_root].is_inline_root {
+            origin = self.content_box_origin(inline_root, origin);
+        }
+
+        Some((
+            taffy::Point {
+                x: origin.x + location.x,
+                y: origin.y + location.y,
+            },
+            fixed || node.is_fixed_position(),
+        ))
+    }
+
+    /// The node whose layout children contain a node (either directly, or within one of its anonymous blocks)
+    fn layout_parent(&self, node_id: usize) -> Option<(usize, Option<usize>)> {
+        let contains = |container_id: usize| {
+            self.nodes[container_id]
+                .layout_children
+                .borrow()
+                .as_ref()
+                .is_some_and(|children| children.contains(&node_id))
+        };
+
+        let mut maybe_id = self.nodes[node_id].parent;
+        while let Some(id) = maybe_id {
+            if contains(id) {
+                return Some((id, None));
+            }
+            let anonymous_id = self.nodes[id]
+                .layout_children
+                .borrow()
+                .iter()
+                .flatten()
+                .copied()
+                .find(|child_id| {
+                    matches!(
+                        self.nodes[*child_id].raw_dom_data,
+                        NodeData::AnonymousBlock(_)
+                    ) && contains(*child_id)
+                });
+            if anonymous_id.is_some() {
+                return Some((id, anonymous_id));
+            }
+
+            // The children of display: contents elements are laid out by the element's own layout parent
+            let is_contents = self.nodes[id]
+                .display_style()
+                .is_some_and(|display| display.inside() == DisplayInside::Contents);
+            if !is_contents {
+                return None;
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        None
+    }
+}
+
+#[test]
+fn measures_box_model_rects() {
+    use crate::test_util::parse_test_document;
+
+    let css = "html, body, div { display: block } body { margin: 0 }\
+        #box { margin: 10px; border: 5px solid; padding: 20px; width: 100px; height: 50px }\
+        #wide { width: 300px; height: 10px } #tall { height: 2000px }";
+    let html = "<!DOCTYPE html><html><body><div id=box><div id=wide></div></div><div id=tall></div></body></html>";
+    let mut doc = parse_test_document(html, &[css]);
+    doc.resolve();
+    let node_id = doc.get_element_by_id("box").unwrap();
+
+    let border_box = DomRect {
+        x: 10.0,
+        y: 10.0,
+        width: 150.0,
+        height: 100.0,
+    };
+    assert_eq!(doc.node_bounding_rect(node_id), Some(border_box));
+    assert_eq!(
+        doc.client_rect(node_id),
+        Some(DomRect {
+            x: 5.0,
+            y: 5.0,
+            width: 140.0,
+            height: 90.0,
+        })
+    );
+    // The overflowing child is measured from the padding box, and padded on the far side
+    assert_eq!(
+        doc.scroll_size(node_id),
+        Some(taffy::Size {
+            width: 340.0,
+            height: 90.0,
+        })
+    );
+
+    // Scrolling the viewport moves bounding rects, but not the position in the document
+    doc.set_viewport_scroll(30.0);
+    assert_eq!(
+        doc.node_bounding_rect(node_id),
+        Some(DomRect {
+            y: -20.0,
+            ..border_box
+        })
+    );
+    assert_eq!(doc.node_document_rect(node_id), Some(border_box));
+}
+
+#[test]
+fn finds_offset_parents() {
+    use crate::test_util::parse_test_document;
+
+    let css = "html, body, div { display: block } body { margin: 0 }\
+        #positioned { position: relative; margin-left: 30px; border: 2px solid; padding: 8px }\
+        #target { height: 20px } #fixed { position: fixed }";
+    let html = "<!DOCTYPE html><html><body><div id=positioned><div id=middle><div id=target></div></div></div>\
+        <div id=fixed></div></body></html>";
+    let mut doc = parse_test_document(html, &[css]);
+    doc.resolve();
+    let id = |id| doc.get_element_by_id(id).unwrap();
+    let body = doc.nodes[id("positioned")].parent.unwrap();
+
+    // Static ancestors are skipped over
+    assert_eq!(doc.offset_parent(id("target")), Some(id("positioned")));
+    assert_eq!(doc.offset_parent(id("middle")), Some(id("positioned")));
+    assert_eq!(doc.offset_parent(id("positioned")), Some(body));
+    assert_eq!(doc.offset_parent(body), None);
+    assert_eq!(doc.offset_parent(id("fixed")), None);
+
+    // Offsets are measured from the padding box of the offset parent
+    assert_eq!(
+        doc.offset_rect(id("target")),
+        Some(DomRect {
+            x: 8.0,
+            y: 8.0,
+            width: 750.0,
+            height: 20.0,
+        })
+    );
+}
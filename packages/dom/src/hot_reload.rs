@@ -0,0 +1,165 @@
+//! Reloading the stylesheets and images of documents loaded from disk
+//!
+//! Linked stylesheets and images that are loaded from `file://` URLs are recorded as the document is parsed. Embedders
+//! watch the files of [`Document::local_resources`] and call [`Document::reload_local_resource`] when one changes: the
+//! resource is fetched again and takes the place of the old one, and the document is restyled and repainted.
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+use crate::resource_errors::ResourceError;
+use crate::Document;
+
+/// What a local resource was loaded for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum LocalResourceKind {
+    /// A `<link rel=stylesheet>`, and the source text of the stylesheet it added (which the sheet is keyed by)
+    Stylesheet { key: String },
+    /// The image of an `<img>` element (or the poster of a `<video>`)
+    Image,
+}
+
+/// A stylesheet or image loaded from a file
+#[derive(Clone, Debug)]
+pub(crate) struct LocalResource {
+    pub(crate) path: PathBuf,
+    pub(crate) url: Url,
+    /// The element that loaded the resource
+    pub(crate) node_id: usize,
+    pub(crate) kind: LocalResourceKind,
+}
+
+impl Document {
+    /// The files that the document's linked stylesheets and images were loaded from
+    pub fn local_resources(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .local_resources
+            .iter()
+            .map(|resource| resource.path.clone())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Fetch the resources loaded from a file again, after it has changed. Returns false if the document has no
+    /// resources loaded from the file.
+    pub fn reload_local_resource(&mut self, path: &Path) -> bool {
+        let resources: Vec<LocalResource> = self
+            .local_resources
+            .iter()
+            .filter(|resource| resource.path == path && self.nodes.contains(resource.node_id))
+            .cloned()
+            .collect();
+        if resources.is_empty() {
+            return false;
+        }
+
+        for resource in resources {
+            match &resource.kind {
+                LocalResourceKind::Stylesheet { key } => {
+                    let result = crate::util::fetch_string(
+                        &self.network,
+                        resource.url.as_str(),
+                        self.base_url.as_ref(),
+                    );
+                    match result {
+                        Ok(css) => {
+                            let css = html_escape::decode_html_entities(&css);
+                            if let Some(sheet) = self.stylesheets.get(key).cloned() {
+                                self.replace_stylesheet(sheet, &css);
+                            }
+                        }
+                        Err(error) => self.report_resource_error(ResourceError::Stylesheet {
+                            node_id: resource.node_id,
+                            url: resource.url.to_string(),
+                            error,
+                        }),
+                    }
+                }
+                LocalResourceKind::Image => {
+                    let result = crate::util::fetch_image_source(
+                        &self.network,
+                        resource.url.as_str(),
+                        self.base_url.as_ref(),
+                    );
+                    let Some(element_data) = self.nodes[resource.node_id].element_data_mut() else {
+                        continue;
+                    };
+                    element_data.broken_image = result.is_err();
+                    match result {
+                        // The new image is decoded after the next layout, which changes the element's paint signature
+                        Ok(source) => self.set_image_source(resource.node_id, source),
+                        Err(error) => self.report_resource_error(ResourceError::Image {
+                            node_id: resource.node_id,
+                            url: resource.url.to_string(),
+                            error,
+                        }),
+                    }
+                }
+            }
+        }
+        self.invalidate();
+        true
+    }
+
+    /// Record a resource that the document loaded, if it was loaded from a file
+    pub(crate) fn track_local_resource(
+        &mut self,
+        url: &Url,
+        node_id: usize,
+        kind: LocalResourceKind,
+    ) {
+        if url.scheme() != "file" {
+            return;
+        }
+        if let Ok(path) = url.to_file_path() {
+            self.local_resources.push(LocalResource {
+                path,
+                url: url.clone(),
+                node_id,
+                kind,
+            });
+        }
+    }
+}
+
+#[test]
+fn reloads_changed_stylesheets() {
+    use crate::test_util::{parse_html, test_document};
+
+    let dir = std::env::temp_dir().join(format!("blitz-hot-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let css_path = dir.join("style.css");
+    std::fs::write(&css_path, "#a { display: flex }").unwrap();
+
+    let html = "<!DOCTYPE html><html><head><link rel=stylesheet href=style.css></head>\
+        <body><div id=a></div></body></html>";
+    let mut doc = test_document(&[]);
+    doc.set_base_url(
+        Url::from_file_path(dir.join("index.html"))
+            .unwrap()
+            .as_str(),
+    );
+    parse_html(&mut doc, html);
+    doc.resolve();
+    let a_id = doc.get_element_by_id("a").unwrap();
+    assert_eq!(doc.local_resources(), [css_path.clone()]);
+    assert_eq!(doc.nodes[a_id].style.display, taffy::Display::Flex);
+
+    // Reloading the stylesheet replaces its rules, and it can be reloaded again
+    for display in ["grid", "flex"] {
+        std::fs::write(&css_path, format!("#a {{ display: {} }}", display)).unwrap();
+        assert!(doc.reload_local_resource(&css_path));
+        doc.resolve();
+        let expected = match display {
+            "grid" => taffy::Display::Grid,
+            _ => taffy::Display::Flex,
+        };
+        assert_eq!(doc.nodes[a_id].style.display, expected);
+    }
+    assert_eq!(doc.style_sheet_count(), 1);
+    assert!(!doc.reload_local_resource(&dir.join("other.css")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
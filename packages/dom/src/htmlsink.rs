@@ -1,12 +1,17 @@
 use std::borrow::Cow;
 use std::collections::HashSet;
-use std::sync::Arc;
 
+use crate::csp::CspDirective;
+use crate::hot_reload::LocalResourceKind;
 use crate::node::{Attribute, ElementNodeData, Node, NodeData};
+use crate::profiling::FramePhase;
+use crate::resource_errors::ResourceError;
+use crate::sanitize::{is_unsafe_attribute, is_unsafe_element, sanitize_attributes};
+use crate::util::{FetchErr, ImageFetchErr};
 use crate::Document;
-use html5ever::local_name;
+use html5ever::{local_name, LocalName};
 use html5ever::{
-    tendril::{StrTendril, TendrilSink},
+    tendril::{stream::Utf8LossyDecoder, ByteTendril, StrTendril, TendrilSink},
     tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink},
     ExpandedName, QualName,
 };
@@ -25,6 +30,11 @@ pub struct DocumentHtmlParser<'a> {
 
     style_nodes: Vec<usize>,
 
+    /// Whether unsafe elements and attributes are removed (see [`crate::sanitize`])
+    sanitize: bool,
+    /// The unsafe elements that have been removed from the tree (and are dropped when parsing finishes)
+    sanitized_nodes: Vec<usize>,
+
     /// Errors that occurred during parsing.
     pub errors: Vec<Cow<'static, str>>,
 
@@ -37,17 +47,53 @@ impl<'a> DocumentHtmlParser<'a> {
         DocumentHtmlParser {
             doc,
             style_nodes: Vec::new(),
+            sanitize: false,
+            sanitized_nodes: Vec::new(),
             errors: Vec::new(),
             quirks_mode: QuirksMode::NoQuirks,
         }
     }
 
+    /// Remove scripts, embedded documents, event handlers and `javascript:` URLs from the parsed HTML, for parsing
+    /// untrusted content (see [`crate::sanitize`])
+    pub fn sanitized(mut self) -> Self {
+        self.sanitize = true;
+        self
+    }
+
+    /// Create a parser that is fed the document's source in chunks as it arrives (e.g. from the network)
+    pub fn streaming(doc: &'a mut Document) -> StreamingHtmlParser<'a> {
+        Self::new(doc).into_streaming()
+    }
+
+    /// Feed the parser the document's source in chunks as it arrives
+    pub fn into_streaming(self) -> StreamingHtmlParser<'a> {
+        StreamingHtmlParser {
+            parser: html5ever::parse_document(self, Default::default()).from_utf8(),
+        }
+    }
+
     pub fn parse_into_doc<'d>(doc: &'d mut Document, html: &str) -> &'d mut Document {
-        let sink = Self::new(doc);
-        html5ever::parse_document(sink, Default::default())
-            .from_utf8()
-            .read_from(&mut html.as_bytes())
-            .unwrap()
+        doc.time_phase(FramePhase::Parse, |doc| {
+            let sink = DocumentHtmlParser::new(doc);
+            html5ever::parse_document(sink, Default::default())
+                .from_utf8()
+                .read_from(&mut html.as_bytes())
+                .unwrap();
+        });
+        doc
+    }
+
+    /// Parse untrusted HTML into the document, removing anything that could run code (see [`crate::sanitize`])
+    pub fn parse_sanitized_into_doc<'d>(doc: &'d mut Document, html: &str) -> &'d mut Document {
+        doc.time_phase(FramePhase::Parse, |doc| {
+            let sink = DocumentHtmlParser::new(doc).sanitized();
+            html5ever::parse_document(sink, Default::default())
+                .from_utf8()
+                .read_from(&mut html.as_bytes())
+                .unwrap();
+        });
+        doc
     }
 
     fn create_node(&mut self, node_data: NodeData) -> usize {
@@ -92,38 +138,162 @@ impl<'a> DocumentHtmlParser<'a> {
         let href_attr = node.attr(local_name!("href"));
 
         if let (Some("stylesheet"), Some(href)) = (rel_attr, href_attr) {
-            let url = self.doc.resolve_url(href);
-            match crate::util::fetch_string(url.as_str()) {
-                Ok(css) => {
+            let href = href.to_string();
+            let url = self.doc.resolve_url(&href).map_err(FetchErr::InvalidUrl);
+            if let Ok(url) = &url {
+                if !self
+                    .doc
+                    .check_csp_url(target_id, CspDirective::StyleSrc, url)
+                {
+                    return;
+                }
+            }
+            let result = url.and_then(|url| {
+                crate::util::fetch_string(
+                    &self.doc.network,
+                    url.as_str(),
+                    self.doc.base_url.as_ref(),
+                )
+                .map(|css| (url, css))
+            });
+            match result {
+                Ok((url, css)) => {
                     let css = html_escape::decode_html_entities(&css);
                     self.doc.add_stylesheet(&css);
+                    let kind = LocalResourceKind::Stylesheet {
+                        key: css.into_owned(),
+                    };
+                    self.doc.track_local_resource(&url, target_id, kind);
+                }
+                Err(error) => {
+                    self.doc.report_resource_error(ResourceError::Stylesheet {
+                        node_id: target_id,
+                        url: href,
+                        error,
+                    });
                 }
-                Err(_) => eprintln!("Error fetching stylesheet {}", url),
             }
         }
     }
 
     fn load_image(&mut self, target_id: usize) {
+        self.load_image_from_attr(target_id, local_name!("src"))
+    }
+
+    fn load_video_poster(&mut self, target_id: usize) {
+        self.load_image_from_attr(target_id, local_name!("poster"))
+    }
+
+    fn load_image_from_attr(&mut self, target_id: usize, attr_name: LocalName) {
         let node = self.node(target_id);
-        if let Some(raw_src) = node.attr(local_name!("src")) {
+        if let Some(raw_src) = node.attr(attr_name) {
             if !raw_src.is_empty() {
-                let src = self.doc.resolve_url(raw_src);
-
+                let raw_src = raw_src.to_string();
+                let src = self
+                    .doc
+                    .resolve_url(&raw_src)
+                    .map_err(|err| ImageFetchErr::FetchErr(FetchErr::InvalidUrl(err)));
+                if let Ok(src) = &src {
+                    if !self.doc.check_csp_url(target_id, CspDirective::ImgSrc, src) {
+                        self.node_mut(target_id)
+                            .element_data_mut()
+                            .unwrap()
+                            .broken_image = true;
+                        return;
+                    }
+                }
                 // FIXME: Image fetching should not be a synchronous network request during parsing
-                let image_result = crate::util::fetch_image(src.as_str());
+                let image_result = src.and_then(|src| {
+                    crate::util::fetch_image_source(
+                        &self.doc.network,
+                        src.as_str(),
+                        self.doc.base_url.as_ref(),
+                    )
+                    .map(|source| (src, source))
+                });
                 match image_result {
-                    Ok(image) => {
-                        self.node_mut(target_id).element_data_mut().unwrap().image =
-                            Some(Arc::new(image));
+                    Ok((src, source)) => {
+                        self.doc.set_image_source(target_id, source);
+                        self.doc
+                            .track_local_resource(&src, target_id, LocalResourceKind::Image);
                     }
-                    Err(_) => {
-                        eprintln!("Error fetching image {}", src);
+                    Err(error) => {
+                        self.node_mut(target_id)
+                            .element_data_mut()
+                            .unwrap()
+                            .broken_image = true;
+                        self.doc.report_resource_error(ResourceError::Image {
+                            node_id: target_id,
+                            url: raw_src,
+                            error,
+                        });
                     }
                 }
             }
         }
     }
 
+    /// Enforce the Content Security Policy of a `<meta http-equiv=Content-Security-Policy>` element
+    fn process_meta_element(&mut self, target_id: usize) {
+        let node = self.node(target_id);
+        let is_csp = node
+            .attr(local_name!("http-equiv"))
+            .is_some_and(|http_equiv| {
+                http_equiv
+                    .trim()
+                    .eq_ignore_ascii_case("content-security-policy")
+            });
+        if let (true, Some(content)) = (is_csp, node.attr(local_name!("content"))) {
+            let content = content.to_string();
+            self.doc.add_content_security_policy(&content);
+        }
+    }
+
+    /// Drop an element removed by the sanitizer, and its descendants
+    fn drop_sanitized_node(&mut self, node_id: usize) {
+        // Unsafe elements within unsafe elements have already been dropped with their ancestor
+        if !self.doc.nodes.contains(node_id) {
+            return;
+        }
+        if let Some(parent_id) = self.node_mut(node_id).parent.take() {
+            self.node_mut(parent_id)
+                .children
+                .retain(|child_id| *child_id != node_id);
+        }
+
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.doc.nodes.try_remove(id) else {
+                continue;
+            };
+            stack.extend(&node.children);
+            if let Some(contents_id) = node.element_data().and_then(|data| data.template_contents) {
+                stack.push(contents_id);
+            }
+        }
+        let nodes = &self.doc.nodes;
+        self.doc.nodes_to_id.retain(|_, id| nodes.contains(*id));
+        self.style_nodes.retain(|id| nodes.contains(*id));
+    }
+
+    /// Add the stylesheet of a `<style>` element once all of its text has been parsed
+    fn process_style_element(&mut self, target_id: usize) {
+        self.style_nodes.retain(|id| *id != target_id);
+        if self.doc.is_template_contents(target_id) {
+            return;
+        }
+        let nonce = self
+            .node(target_id)
+            .attr(local_name!("nonce"))
+            .map(str::to_string);
+        if self
+            .doc
+            .check_csp_inline(target_id, CspDirective::StyleSrc, nonce.as_deref())
+        {
+            self.doc.process_style_element(target_id);
+        }
+    }
+
     fn process_button_input(&mut self, target_id: usize) {
         let node = self.node(target_id);
         let Some(data) = node.element_data() else {
@@ -152,14 +322,19 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
     // we use the ID of the nodes in the tree as the handle
     type Handle = usize;
 
-    fn finish(self) -> Self::Output {
-        // Add inline stylesheets (<style> elements)
-        for id in &self.style_nodes {
-            self.doc.process_style_element(*id);
+    fn finish(mut self) -> Self::Output {
+        for id in std::mem::take(&mut self.sanitized_nodes) {
+            self.drop_sanitized_node(id);
+        }
+
+        // Add the stylesheets of any <style> elements that were never closed
+        for id in std::mem::take(&mut self.style_nodes) {
+            self.process_style_element(id);
         }
 
         // Compute child_idx fields.
         self.doc.flush_child_indexes(0, 0, 0);
+        self.doc.flush_template_contents();
 
         for error in self.errors {
             println!("ERROR: {}", error);
@@ -190,7 +365,10 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         attrs: Vec<html5ever::Attribute>,
         _flags: ElementFlags,
     ) -> Self::Handle {
-        let attrs = attrs.into_iter().map(html5ever_to_blitz_attr).collect();
+        let mut attrs: Vec<Attribute> = attrs.into_iter().map(html5ever_to_blitz_attr).collect();
+        if self.sanitize {
+            sanitize_attributes(&mut attrs);
+        }
         let mut data = ElementNodeData::new(name.clone(), attrs);
         data.flush_style_attribute(&self.doc.guard);
 
@@ -200,6 +378,12 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         // Initialise style data
         *node.stylo_element_data.borrow_mut() = Some(Default::default());
 
+        // Unsafe elements are never attached to the tree, so none of their resources are loaded
+        if self.sanitize && is_unsafe_element(&name.local) {
+            self.sanitized_nodes.push(id);
+            return id;
+        }
+
         // If the node has an "id" attribute, store it in the ID map.
         if let Some(id_attr) = node.attr(local_name!("id")) {
             self.doc.nodes_to_id.insert(id_attr.to_string(), id);
@@ -208,9 +392,21 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         // Custom post-processing by element tag name
         match name.local.as_ref() {
             "link" => self.load_linked_stylesheet(id),
+            "meta" => self.process_meta_element(id),
             "img" => self.load_image(id),
-            "input" => self.process_button_input(id),
+            "video" => self.load_video_poster(id),
+            "input" => {
+                self.process_button_input(id);
+                self.doc.update_picker_input_text(id);
+            }
             "style" => self.style_nodes.push(id),
+            "template" => {
+                let contents_id = self.create_node(NodeData::DocumentFragment);
+                self.node_mut(id)
+                    .element_data_mut()
+                    .unwrap()
+                    .template_contents = Some(contents_id);
+            }
             _ => {}
         }
 
@@ -228,6 +424,7 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
 
     fn append(&mut self, parent_id: &Self::Handle, child: NodeOrText<Self::Handle>) {
         match child {
+            NodeOrText::AppendNode(child_id) if self.sanitized_nodes.contains(&child_id) => {}
             NodeOrText::AppendNode(child_id) => {
                 self.node_mut(*parent_id).children.push(child_id);
                 self.node_mut(child_id).parent = Some(*parent_id);
@@ -250,6 +447,12 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         sibling_id: &Self::Handle,
         new_node: NodeOrText<Self::Handle>,
     ) {
+        if let NodeOrText::AppendNode(id) = &new_node {
+            if self.sanitized_nodes.contains(id) {
+                return;
+            }
+        }
+
         let sibling = self.node(*sibling_id);
         let parent_id = sibling.parent.expect("Sibling has not parent");
         let parent = self.node(parent_id);
@@ -308,8 +511,17 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
         // Ignore. We don't care about the DOCTYPE for now.
     }
 
-    fn get_template_contents(&mut self, _target: &Self::Handle) -> Self::Handle {
-        unimplemented!()
+    fn get_template_contents(&mut self, target: &Self::Handle) -> Self::Handle {
+        self.node(*target)
+            .element_data()
+            .and_then(|data| data.template_contents)
+            .expect("TreeSink::get_template_contents called on a node which is not a template!")
+    }
+
+    fn pop(&mut self, node: &Self::Handle) {
+        if self.style_nodes.contains(node) {
+            self.process_style_element(*node);
+        }
     }
 
     fn same_node(&self, x: &Self::Handle, y: &Self::Handle) -> bool {
@@ -321,6 +533,7 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
     }
 
     fn add_attrs_if_missing(&mut self, target: &Self::Handle, attrs: Vec<html5ever::Attribute>) {
+        let sanitize = self.sanitize;
         let element_data = self
             .node_mut(*target)
             .element_data_mut()
@@ -336,13 +549,17 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
             attrs
                 .into_iter()
                 .map(html5ever_to_blitz_attr)
-                .filter(|attr| !existing_names.contains(&attr.name)),
+                .filter(|attr| !existing_names.contains(&attr.name))
+                .filter(|attr| !sanitize || !is_unsafe_attribute(attr)),
         );
     }
 
     fn remove_from_parent(&mut self, target: &Self::Handle) {
+        // Elements removed by the sanitizer have no parent
         let node = self.node_mut(*target);
-        let parent_id = node.parent.take().expect("Node has no parent");
+        let Some(parent_id) = node.parent.take() else {
+            return;
+        };
         self.node_mut(parent_id)
             .children
             .retain(|child_id| child_id != target);
@@ -363,6 +580,38 @@ impl<'b> TreeSink for DocumentHtmlParser<'b> {
     }
 }
 
+/// An HTML parser that is fed the document's source in chunks, created by [`DocumentHtmlParser::streaming`]
+///
+/// The document can be resolved and painted between chunks, to show it progressively as it loads.
+pub struct StreamingHtmlParser<'a> {
+    parser: Utf8LossyDecoder<html5ever::Parser<DocumentHtmlParser<'a>>>,
+}
+
+impl<'a> StreamingHtmlParser<'a> {
+    /// Parse the next chunk of the document's source. Chunks may split UTF-8 sequences.
+    pub fn write(&mut self, chunk: &[u8]) {
+        self.parser.process(ByteTendril::from_slice(chunk));
+
+        // Bring the parts of the tree that aren't maintained while parsing up to date so that it can be resolved
+        let doc = self.document();
+        doc.flush_child_indexes(0, 0, 0);
+        doc.flush_template_contents();
+        doc.invalidate();
+    }
+
+    /// The document parsed so far
+    ///
+    /// Note that the document can't be resolved until the parser has created its root element.
+    pub fn document(&mut self) -> &mut Document {
+        self.parser.inner_sink.tokenizer.sink.sink.doc
+    }
+
+    /// Parse the rest of the document, once its source has been written in full
+    pub fn finish(self) -> &'a mut Document {
+        self.parser.finish()
+    }
+}
+
 #[test]
 fn parses_some_html() {
     use crate::document::DummyFontMetricsProvider;
@@ -392,3 +641,57 @@ fn parses_some_html() {
 
     // Now our tree should have some nodes in it
 }
+
+#[test]
+fn parses_html_in_chunks() {
+    use crate::test_util::test_document;
+
+    let html = "<!DOCTYPE html><html><head><style>#a { display: flex }</style></head>\
+        <body><div id=a>caf\u{e9}</div><div id=b>second</div></body></html>";
+    let mut doc = test_document(&[]);
+    let mut parser = DocumentHtmlParser::streaming(&mut doc);
+
+    // Chunks split tags and the multi-byte "é"
+    let (head, tail) = html
+        .as_bytes()
+        .split_at(html.find("<div id=b>").unwrap() - 3);
+    for chunk in head.chunks(5) {
+        parser.write(chunk);
+    }
+
+    // The first div can be styled and laid out before the rest of the document has arrived
+    let partial = parser.document();
+    partial.resolve();
+    let a_id = partial.get_element_by_id("a").unwrap();
+    assert_eq!(partial.nodes[a_id].style.display, taffy::Display::Flex);
+    assert!(partial.get_element_by_id("b").is_none());
+
+    parser.write(tail);
+    let doc = parser.finish();
+    doc.resolve();
+    assert_eq!(doc.nodes[a_id].text_content(), "caf\u{e9}");
+    assert!(doc.get_element_by_id("b").is_some());
+}
+
+#[test]
+fn sanitizes_untrusted_html() {
+    use crate::test_util::test_document;
+
+    let html = "<p id=a onclick=\"steal()\">hello<script>steal()</script>\
+        <a id=b href=\" javascript:steal()\" title=link>link</a>\
+        <iframe id=c src=\"https://example.com\"></iframe><object><span id=d></span></object></p>";
+    let mut doc = test_document(&[]);
+    DocumentHtmlParser::parse_sanitized_into_doc(&mut doc, html);
+
+    let a_id = doc.get_element_by_id("a").unwrap();
+    assert_eq!(doc.nodes[a_id].attr(local_name!("onclick")), None);
+    assert_eq!(doc.nodes[a_id].text_content(), "hellolink");
+
+    let b_id = doc.get_element_by_id("b").unwrap();
+    assert_eq!(doc.nodes[b_id].attr(local_name!("href")), None);
+    assert_eq!(doc.nodes[b_id].attr(local_name!("title")), Some("link"));
+
+    assert!(doc.get_element_by_id("c").is_none());
+    assert!(doc.get_element_by_id("d").is_none());
+    assert!(doc.iframe_sources().is_empty());
+}
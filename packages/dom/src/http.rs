@@ -0,0 +1,224 @@
+//! Making HTTP requests for a [`NetworkProvider`](crate::network::NetworkProvider)
+//!
+//! This is the only part of blitz-dom that talks to the network (with ureq and rustls), and it's only compiled with
+//! the `network` feature. Without it, the rest of the DOM, style and layout core has no native dependencies, so it
+//! can be built for `wasm32` targets or embedded in servers that lay out documents without fetching anything.
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Condvar, Mutex};
+
+use url::Url;
+
+use crate::cookies::CookieJar;
+use crate::network::{ConnectionConfig, ConnectionConfigErr, FetchPolicy};
+use crate::util::FetchErr;
+
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:60.0) Gecko/20100101 Firefox/81.0";
+const FILE_SIZE_LIMIT: u64 = 1_000_000_000; // 1GB
+
+/// Makes requests according to a [`FetchPolicy`] and [`ConnectionConfig`]
+pub(crate) struct HttpClient {
+    policy: FetchPolicy,
+    agent: ureq::Agent,
+    /// The number of requests being made to each host
+    connections: Mutex<HashMap<String, usize>>,
+    connection_closed: Condvar,
+}
+
+impl HttpClient {
+    pub(crate) fn new(
+        policy: &FetchPolicy,
+        connection_config: &ConnectionConfig,
+    ) -> Result<Self, ConnectionConfigErr> {
+        let mut builder = ureq::AgentBuilder::new()
+            .user_agent(USER_AGENT)
+            .redirects(0)
+            .timeout_connect(policy.connect_timeout)
+            .timeout_read(policy.read_timeout);
+        builder = match &connection_config.proxy {
+            Some(proxy) => builder.proxy(
+                ureq::Proxy::new(proxy)
+                    .map_err(|err| ConnectionConfigErr::InvalidProxy(Box::new(err)))?,
+            ),
+            None => builder.try_proxy_from_env(true),
+        };
+        if let Some(tls_config) = tls_config(connection_config)? {
+            builder = builder.tls_config(Arc::new(tls_config));
+        }
+
+        Ok(Self {
+            policy: policy.clone(),
+            agent: builder.build(),
+            connections: Mutex::new(HashMap::new()),
+            connection_closed: Condvar::new(),
+        })
+    }
+
+    /// Fetch a resource over HTTP, following redirects and storing the cookies that are set along the way.
+    /// `navigation` is whether the resource is a document navigated to (see [`CookieJar::cookie_header`]).
+    pub(crate) fn fetch(
+        &self,
+        mut url: Url,
+        document_url: Option<&Url>,
+        navigation: bool,
+        cookies: &Mutex<CookieJar>,
+    ) -> Result<(Vec<u8>, Option<String>), FetchErr> {
+        // Redirects are followed here rather than by the agent, so that cookies are stored and sent along the way
+        let mut redirects = 0;
+        let (resp, _connection) = loop {
+            let (resp, connection) = self.request(&url, document_url, navigation, cookies)?;
+            let location = match resp.status() {
+                301 | 302 | 303 | 307 | 308 => resp.header("Location"),
+                _ => None,
+            };
+            let Some(location) = location else {
+                break (resp, connection);
+            };
+            if redirects >= self.policy.max_redirects {
+                return Err(FetchErr::TooManyRedirects);
+            }
+            redirects += 1;
+            url = url.join(location).map_err(FetchErr::InvalidUrl)?;
+        };
+        let content_type = resp.header("Content-Type").map(str::to_string);
+
+        let len: usize = resp
+            .header("Content-Length")
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0);
+        let mut bytes: Vec<u8> = Vec::with_capacity(len);
+
+        resp.into_reader()
+            .take(FILE_SIZE_LIMIT)
+            .read_to_end(&mut bytes)
+            .map_err(FetchErr::Io)?;
+
+        Ok((bytes, content_type))
+    }
+
+    /// Request `url` (retrying if it fails), sending and storing cookies. The request counts towards the host's
+    /// connections until the returned guard is dropped.
+    fn request(
+        &self,
+        url: &Url,
+        document_url: Option<&Url>,
+        navigation: bool,
+        cookies: &Mutex<CookieJar>,
+    ) -> Result<(ureq::Response, ConnectionGuard<'_>), FetchErr> {
+        let connection = self.open_connection(url.host_str().unwrap_or_default().to_string());
+
+        let mut retry = 0;
+        loop {
+            let mut request = self.agent.request_url("GET", url);
+            let cookie_header =
+                cookies
+                    .lock()
+                    .unwrap()
+                    .cookie_header(url, document_url, navigation);
+            if let Some(cookies) = cookie_header {
+                request = request.set("Cookie", &cookies);
+            }
+            let result = request.call();
+
+            let resp = match &result {
+                Ok(resp) | Err(ureq::Error::Status(_, resp)) => Some(resp),
+                Err(ureq::Error::Transport(_)) => None,
+            };
+            if let Some(resp) = resp {
+                let mut jar = cookies.lock().unwrap();
+                for set_cookie in resp.all("Set-Cookie") {
+                    jar.set_cookie(set_cookie, url);
+                }
+            }
+
+            match result {
+                Ok(resp) => return Ok((resp, connection)),
+                Err(err) if retry < self.policy.max_retries && is_retryable(&err) => {
+                    std::thread::sleep(self.policy.backoff(retry));
+                    retry += 1;
+                }
+                Err(err) => return Err(FetchErr::Request(Box::new(err))),
+            }
+        }
+    }
+
+    /// Wait until a request can be made to `host`. The request is counted until the returned guard is dropped.
+    fn open_connection(&self, host: String) -> ConnectionGuard<'_> {
+        let mut connections = self.connections.lock().unwrap();
+        while connections.get(&host).copied().unwrap_or(0) >= self.policy.max_connections_per_host {
+            connections = self.connection_closed.wait(connections).unwrap();
+        }
+        *connections.entry(host.clone()).or_insert(0) += 1;
+        ConnectionGuard { client: self, host }
+    }
+}
+
+/// A request being made to a host, which counts towards [`FetchPolicy::max_connections_per_host`]
+struct ConnectionGuard<'a> {
+    client: &'a HttpClient,
+    host: String,
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        let mut connections = self.client.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.host) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.host);
+            }
+        }
+        self.client.connection_closed.notify_all();
+    }
+}
+
+/// The TLS configuration of the agent, if it differs from the default
+fn tls_config(
+    connection_config: &ConnectionConfig,
+) -> Result<Option<rustls::ClientConfig>, ConnectionConfigErr> {
+    if connection_config.root_certificates.is_empty()
+        && connection_config.builtin_root_certificates
+        && connection_config.client_certificate.is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut roots = rustls::RootCertStore::empty();
+    if connection_config.builtin_root_certificates {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+    for pem in &connection_config.root_certificates {
+        for certificate in rustls_pemfile::certs(&mut pem.as_slice()) {
+            let certificate = certificate.map_err(ConnectionConfigErr::InvalidPem)?;
+            roots.add(certificate).map_err(ConnectionConfigErr::Tls)?;
+        }
+    }
+
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    let config = match &connection_config.client_certificate {
+        Some(client_certificate) => {
+            let chain = rustls_pemfile::certs(&mut client_certificate.certificate_chain.as_slice())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ConnectionConfigErr::InvalidPem)?;
+            let key = rustls_pemfile::private_key(&mut client_certificate.private_key.as_slice())
+                .map_err(ConnectionConfigErr::InvalidPem)?
+                .ok_or(ConnectionConfigErr::MissingPrivateKey)?;
+            builder
+                .with_client_auth_cert(chain, key)
+                .map_err(ConnectionConfigErr::Tls)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Some(config))
+}
+
+/// Whether a request that failed might succeed if it's made again
+fn is_retryable(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::Status(status, _) => *status == 429 || *status >= 500,
+        ureq::Error::Transport(transport) => matches!(
+            transport.kind(),
+            ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io | ureq::ErrorKind::Dns
+        ),
+    }
+}
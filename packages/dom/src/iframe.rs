@@ -0,0 +1,130 @@
+//! Support for `<iframe>` elements
+//!
+//! Each iframe owns a nested [`Document`] with its own url, stylist and layout. The nested document's viewport
+//! is sized to the iframe's content box during [`Document::resolve`], it is painted clipped into that box by the
+//! renderer, and hover/click events that land inside the iframe are routed into it via hit testing.
+use euclid::{Scale, Size2D};
+use html5ever::local_name;
+use selectors::matching::QuirksMode;
+use style::media_queries::{Device, MediaType};
+use url::Url;
+
+use crate::document::DummyFontMetricsProvider;
+use crate::Document;
+
+/// The intrinsic size of an `<iframe>` element
+///
+/// <https://html.spec.whatwg.org/multipage/rendering.html#attributes-for-embedded-content-and-images>
+pub const DEFAULT_IFRAME_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 300.0,
+    height: 150.0,
+};
+
+/// Where the content of an `<iframe>` should be loaded from
+#[derive(Debug, Clone)]
+pub enum IframeSource {
+    /// The `srcdoc` attribute: inline HTML
+    SrcDoc(String),
+    /// The `src` attribute, resolved against the document's base url
+    Url(Url),
+}
+
+impl Document {
+    /// Attach a nested document to an `<iframe>` element. Any previously attached document is dropped.
+    pub fn set_sub_document(&mut self, node_id: usize, mut sub_document: Document) {
+        sub_document.set_scale(self.scale);
+        self.sub_documents.insert(node_id, Box::new(sub_document));
+    }
+
+    pub fn sub_document(&self, node_id: usize) -> Option<&Document> {
+        self.sub_documents.get(&node_id).map(|doc| &**doc)
+    }
+
+    pub fn sub_document_mut(&mut self, node_id: usize) -> Option<&mut Document> {
+        self.sub_documents.get_mut(&node_id).map(|doc| &mut **doc)
+    }
+
+    /// List the `<iframe>` elements in the document along with the source their content should be loaded from
+    pub fn iframe_sources(&self) -> Vec<(usize, IframeSource)> {
+        self.nodes
+            .iter()
+            .filter_map(|(node_id, node)| {
+                let element_data = node.element_data()?;
+                if element_data.name.local != local_name!("iframe") {
+                    return None;
+                }
+
+                // srcdoc takes priority over src
+                if let Some(srcdoc) = element_data.attr(local_name!("srcdoc")) {
+                    return Some((node_id, IframeSource::SrcDoc(srcdoc.to_string())));
+                }
+
+                let src = element_data.attr(local_name!("src"))?;
+                if src.is_empty() {
+                    return None;
+                }
+                let url = match &self.base_url {
+                    Some(base_url) => base_url.join(src).ok()?,
+                    None => Url::parse(src).ok()?,
+                };
+                Some((node_id, IframeSource::Url(url)))
+            })
+            .collect()
+    }
+
+    /// Size the viewport of each nested document to its iframe's content box, then restyle and relayout it.
+    pub(crate) fn resolve_sub_documents(&mut self) {
+        // Drop nested documents whose iframes have been removed from the tree
+        let nodes = &self.nodes;
+        self.sub_documents
+            .retain(|node_id, _| nodes.contains(*node_id));
+
+        for (node_id, sub_document) in self.sub_documents.iter_mut() {
+            let layout = self.nodes[*node_id].final_layout;
+            let width = layout.size.width
+                - layout.border.left
+                - layout.border.right
+                - layout.padding.left
+                - layout.padding.right;
+            let height = layout.size.height
+                - layout.border.top
+                - layout.border.bottom
+                - layout.padding.top
+                - layout.padding.bottom;
+
+            let viewport_size = sub_document.stylist_device().au_viewport_size();
+            if viewport_size.width.to_f32_px() != width
+                || viewport_size.height.to_f32_px() != height
+                || sub_document.scale != self.scale
+            {
+                let device = Device::new(
+                    MediaType::screen(),
+                    QuirksMode::NoQuirks,
+                    Size2D::new(width.max(0.0), height.max(0.0)),
+                    Scale::new(self.scale),
+                    Box::new(DummyFontMetricsProvider),
+                );
+                sub_document.set_stylist_device(device);
+                sub_document.set_scale(self.scale);
+            }
+
+            sub_document.resolve();
+        }
+    }
+
+    /// If (x, y) lies within an iframe that has a nested document, returns the iframe's node id along with the
+    /// position translated into the nested document's coordinate space.
+    pub fn hit_sub_document(&self, x: f32, y: f32) -> Option<(usize, f32, f32)> {
+        let node_id = self.hit(x, y)?;
+        if !self.sub_documents.contains_key(&node_id) {
+            return None;
+        }
+
+        let layout = self.nodes[node_id].final_layout;
+        let position = self.absolute_position(node_id);
+        let abs_x = position.x + layout.border.left + layout.padding.left;
+        let abs_y = position.y + layout.border.top + layout.padding.top;
+
+        Some((node_id, x - abs_x, y - abs_y))
+    }
+}
@@ -0,0 +1,275 @@
+//! Decoding the image formats that the `image` crate doesn't handle on its own
+//!
+//! Most formats are sniffed and decoded by the `image` crate. On top of that:
+//!
+//! - ICO files (favicons) usually hold the same icon at several sizes. The smallest one that's at least as large as
+//!   the size the image is wanted at is decoded, rather than always the largest.
+//! - AVIF images are decoded with dav1d, behind the `avif` feature (which needs dav1d to be installed).
+//! - JPEG XL images are decoded with jxl-oxide, behind the `jxl` feature.
+//!
+//! Images in a format whose feature is disabled fail with [`ImageFetchErr::UnsupportedFormat`].
+//!
+//! Note: the colors of JPEG XL images are taken as sRGB, whatever color space they were encoded in.
+use std::cmp::Reverse;
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+
+use crate::util::{FetchErr, ImageFetchErr};
+
+/// A format that's sniffed here rather than by the `image` crate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Ico,
+    Avif,
+    JpegXl,
+}
+
+impl Format {
+    fn sniff(bytes: &[u8]) -> Option<Format> {
+        if bytes.starts_with(&[0, 0, 1, 0]) {
+            Some(Format::Ico)
+        } else if bytes.get(4..8) == Some(b"ftyp")
+            && matches!(bytes.get(8..12), Some(b"avif" | b"avis"))
+        {
+            Some(Format::Avif)
+        } else if bytes.starts_with(&[0xFF, 0x0A]) || bytes.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n")
+        {
+            Some(Format::JpegXl)
+        } else {
+            None
+        }
+    }
+}
+
+/// Read the natural size of an encoded image, without decoding it. For an ICO file, this is the size of its largest
+/// icon.
+pub(crate) fn image_dimensions(bytes: &[u8]) -> Result<(u32, u32), ImageFetchErr> {
+    match Format::sniff(bytes) {
+        Some(Format::Ico) => {
+            let icon = largest_icon(&ico_entries(bytes)?);
+            Ok((icon.width, icon.height))
+        }
+        Some(Format::Avif) => avif_reader(bytes)?.into_dimensions().map_err(Into::into),
+        Some(Format::JpegXl) => jxl_dimensions(bytes),
+        None => image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(FetchErr::Io)?
+            .into_dimensions()
+            .map_err(Into::into),
+    }
+}
+
+/// Decode an image. `size` is the size the image is wanted at (in pixels), which picks the icon of an ICO file. The
+/// image isn't resized to it.
+pub(crate) fn decode_image(
+    bytes: &[u8],
+    size: Option<(u32, u32)>,
+) -> Result<DynamicImage, ImageFetchErr> {
+    match Format::sniff(bytes) {
+        Some(Format::Ico) => decode_ico(bytes, size),
+        Some(Format::Avif) => avif_reader(bytes)?.decode().map_err(Into::into),
+        Some(Format::JpegXl) => decode_jxl(bytes),
+        None => image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(FetchErr::Io)?
+            .decode()
+            .map_err(Into::into),
+    }
+}
+
+/// An icon in an ICO file's directory
+#[derive(Clone, Copy, Debug)]
+struct IcoEntry {
+    width: u32,
+    height: u32,
+    bits_per_pixel: u16,
+    /// The entry's 16 bytes in the directory
+    entry: [u8; 16],
+    /// Where the icon's image is in the file
+    data: (usize, usize),
+}
+
+/// The icons listed in an ICO file's directory
+fn ico_entries(bytes: &[u8]) -> Result<Vec<IcoEntry>, ImageFetchErr> {
+    let malformed = || {
+        ImageFetchErr::ImageError(image::ImageError::Decoding(
+            image::error::DecodingError::new(ImageFormat::Ico.into(), "malformed icon directory"),
+        ))
+    };
+    let u16_at = |offset: usize| {
+        bytes
+            .get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    let count = u16_at(4).ok_or_else(malformed)? as usize;
+    let mut entries = Vec::with_capacity(count);
+    for index in 0..count {
+        let offset = 6 + index * 16;
+        let entry: [u8; 16] = bytes
+            .get(offset..offset + 16)
+            .and_then(|entry| entry.try_into().ok())
+            .ok_or_else(malformed)?;
+        let start = u32_at(offset + 12).ok_or_else(malformed)? as usize;
+        let len = u32_at(offset + 8).ok_or_else(malformed)? as usize;
+        if start.checked_add(len).map_or(true, |end| end > bytes.len()) {
+            return Err(malformed());
+        }
+        // A size of 0 means 256
+        entries.push(IcoEntry {
+            width: if entry[0] == 0 { 256 } else { entry[0] as u32 },
+            height: if entry[1] == 0 { 256 } else { entry[1] as u32 },
+            bits_per_pixel: u16_at(offset + 6).ok_or_else(malformed)?,
+            entry,
+            data: (start, len),
+        });
+    }
+    if entries.is_empty() {
+        return Err(malformed());
+    }
+    Ok(entries)
+}
+
+/// The largest icon, preferring more colors between icons of the same size
+fn largest_icon(entries: &[IcoEntry]) -> IcoEntry {
+    *entries
+        .iter()
+        .max_by_key(|icon| (icon.width * icon.height, icon.bits_per_pixel))
+        .unwrap()
+}
+
+/// Decode the icon of an ICO file that best fits the given size: the smallest one that's at least that large, or
+/// the largest one if none are. Without a size, the largest one.
+fn decode_ico(bytes: &[u8], size: Option<(u32, u32)>) -> Result<DynamicImage, ImageFetchErr> {
+    let entries = ico_entries(bytes)?;
+    let icon = size
+        .and_then(|(width, height)| {
+            entries
+                .iter()
+                .filter(|icon| icon.width >= width && icon.height >= height)
+                .min_by_key(|icon| (icon.width * icon.height, Reverse(icon.bits_per_pixel)))
+                .copied()
+        })
+        .unwrap_or_else(|| largest_icon(&entries));
+
+    // The `image` crate always decodes the largest icon, so it's given an ICO file with just this one
+    let (start, len) = icon.data;
+    let mut single = Vec::with_capacity(22 + len);
+    single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    single.extend_from_slice(&icon.entry[..12]);
+    single.extend_from_slice(&22u32.to_le_bytes());
+    single.extend_from_slice(&bytes[start..start + len]);
+    Ok(image::load_from_memory_with_format(
+        &single,
+        ImageFormat::Ico,
+    )?)
+}
+
+#[cfg(feature = "avif")]
+fn avif_reader(bytes: &[u8]) -> Result<image::io::Reader<Cursor<&[u8]>>, ImageFetchErr> {
+    Ok(image::io::Reader::with_format(
+        Cursor::new(bytes),
+        ImageFormat::Avif,
+    ))
+}
+
+#[cfg(not(feature = "avif"))]
+fn avif_reader(_bytes: &[u8]) -> Result<image::io::Reader<Cursor<&[u8]>>, ImageFetchErr> {
+    Err(ImageFetchErr::UnsupportedFormat("avif"))
+}
+
+#[cfg(feature = "jxl")]
+fn jxl_image(bytes: &[u8]) -> Result<jxl_oxide::JxlImage, ImageFetchErr> {
+    jxl_oxide::JxlImage::builder()
+        .read(Cursor::new(bytes))
+        .map_err(jxl_error)
+}
+
+#[cfg(feature = "jxl")]
+fn jxl_error(error: jxl_oxide::Error) -> ImageFetchErr {
+    ImageFetchErr::ImageError(image::ImageError::Decoding(
+        image::error::DecodingError::new(
+            image::error::ImageFormatHint::Name("JPEG XL".into()),
+            error,
+        ),
+    ))
+}
+
+#[cfg(feature = "jxl")]
+fn jxl_dimensions(bytes: &[u8]) -> Result<(u32, u32), ImageFetchErr> {
+    let image = jxl_image(bytes)?;
+    Ok((image.width(), image.height()))
+}
+
+#[cfg(feature = "jxl")]
+fn decode_jxl(bytes: &[u8]) -> Result<DynamicImage, ImageFetchErr> {
+    let image = jxl_image(bytes)?;
+    let render = image.render_frame(0).map_err(jxl_error)?;
+    let frame = render.image_all_channels();
+    let (width, height) = (frame.width() as u32, frame.height() as u32);
+
+    // Samples are interleaved floats from 0 to 1, with 1 to 4 channels (gray, gray and alpha, RGB, or RGBA)
+    let channels = frame.channels();
+    let to_u8 = |sample: f32| (sample.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let rgba = frame
+        .buf()
+        .chunks_exact(channels)
+        .flat_map(|pixel| match *pixel {
+            [gray] => [to_u8(gray), to_u8(gray), to_u8(gray), 255],
+            [gray, alpha] => [to_u8(gray), to_u8(gray), to_u8(gray), to_u8(alpha)],
+            [red, green, blue] => [to_u8(red), to_u8(green), to_u8(blue), 255],
+            [red, green, blue, alpha, ..] => [to_u8(red), to_u8(green), to_u8(blue), to_u8(alpha)],
+            [] => [0; 4],
+        })
+        .collect();
+    Ok(DynamicImage::ImageRgba8(
+        image::RgbaImage::from_raw(width, height, rgba).unwrap(),
+    ))
+}
+
+#[cfg(not(feature = "jxl"))]
+fn jxl_dimensions(_bytes: &[u8]) -> Result<(u32, u32), ImageFetchErr> {
+    Err(ImageFetchErr::UnsupportedFormat("jxl"))
+}
+
+#[cfg(not(feature = "jxl"))]
+fn decode_jxl(_bytes: &[u8]) -> Result<DynamicImage, ImageFetchErr> {
+    Err(ImageFetchErr::UnsupportedFormat("jxl"))
+}
+
+#[test]
+fn picks_the_icon_that_fits() {
+    // An ICO file with 16x16 and 64x64 PNG icons
+    let png = |size: u32| {
+        let mut png = Vec::new();
+        DynamicImage::new_rgba8(size, size)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    };
+    let icons = [png(16), png(64)];
+    let mut ico = vec![0, 0, 1, 0, 2, 0];
+    let mut offset = 6 + 16 * icons.len() as u32;
+    for (size, icon) in [16u8, 64].into_iter().zip(&icons) {
+        ico.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0]);
+        ico.extend_from_slice(&(icon.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&offset.to_le_bytes());
+        offset += icon.len() as u32;
+    }
+    for icon in &icons {
+        ico.extend_from_slice(icon);
+    }
+
+    let decoded_width = |size| decode_image(&ico, size).unwrap().width();
+    assert_eq!(image_dimensions(&ico).unwrap(), (64, 64));
+    assert_eq!(decoded_width(None), 64);
+    assert_eq!(decoded_width(Some((16, 16))), 16);
+    assert_eq!(decoded_width(Some((20, 20))), 64);
+    assert_eq!(decoded_width(Some((128, 128))), 64);
+}
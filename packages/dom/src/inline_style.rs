@@ -0,0 +1,116 @@
+//! Setting individual properties of an element's inline style (the equivalent of `HTMLElement.style`)
+//!
+//! Values are parsed by stylo, so invalid values are rejected rather than written to the `style` attribute. Changes
+//! are serialized back to the attribute through [`Document::set_attribute`], which reparses it and schedules a
+//! restyle of the element.
+use html5ever::{local_name, namespace_url, ns, QualName};
+use selectors::matching::QuirksMode;
+use style::properties::{
+    parse_style_attribute, PropertyDeclarationBlock, PropertyDeclarationId, PropertyId,
+};
+use style::stylesheets::{CssRuleType, UrlExtraData};
+use url::Url;
+
+use crate::Document;
+
+/// The inline style of an element, returned by [`Document::inline_style`]
+pub struct InlineStyle<'doc> {
+    doc: &'doc mut Document,
+    node_id: usize,
+}
+
+impl Document {
+    /// The inline style of an element, for reading and writing individual properties
+    pub fn inline_style(&mut self, node_id: usize) -> InlineStyle<'_> {
+        InlineStyle { doc: self, node_id }
+    }
+}
+
+impl InlineStyle<'_> {
+    /// The serialized declarations of the `style` attribute
+    pub fn css_text(&self) -> String {
+        let mut css = String::new();
+        let _ = self.declarations().to_css(&mut css);
+        css
+    }
+
+    /// The serialized value of a property (longhand or shorthand), if it is set
+    pub fn get_property(&self, name: &str) -> Option<String> {
+        let property_id = PropertyId::parse_enabled_for_all_content(name).ok()?;
+        let mut value = String::new();
+        self.declarations()
+            .property_value_to_css(&property_id, &mut value)
+            .ok()?;
+        (!value.is_empty()).then_some(value)
+    }
+
+    /// Set a property (longhand or shorthand) to a value, which may end with `!important`. Returns `false` (and
+    /// leaves the style unchanged) if the property is unknown or the value is invalid.
+    pub fn set_property(&mut self, name: &str, value: &str) -> bool {
+        let set = parse(&format!("{name}: {value}"));
+        if set.declarations().is_empty() {
+            return false;
+        }
+        let ids: Vec<PropertyDeclarationId> =
+            set.declarations().iter().map(|decl| decl.id()).collect();
+
+        let mut block = self.declarations_without(&ids);
+        for (declaration, importance) in set.declaration_importance_iter() {
+            block.push(declaration.clone(), importance);
+        }
+        self.write(block);
+        true
+    }
+
+    /// Remove a property (longhand or shorthand)
+    pub fn remove_property(&mut self, name: &str) {
+        // Parsing the property with any value gives the longhands it sets
+        let removed = parse(&format!("{name}: initial"));
+        let ids: Vec<PropertyDeclarationId> = removed
+            .declarations()
+            .iter()
+            .map(|decl| decl.id())
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        let block = self.declarations_without(&ids);
+        self.write(block);
+    }
+
+    fn declarations(&self) -> PropertyDeclarationBlock {
+        let style = self.doc.nodes[self.node_id].attr(local_name!("style"));
+        parse(style.unwrap_or_default())
+    }
+
+    fn declarations_without(&self, ids: &[PropertyDeclarationId]) -> PropertyDeclarationBlock {
+        let mut block = PropertyDeclarationBlock::new();
+        for (declaration, importance) in self.declarations().declaration_importance_iter() {
+            if !ids.contains(&declaration.id()) {
+                block.push(declaration.clone(), importance);
+            }
+        }
+        block
+    }
+
+    fn write(&mut self, block: PropertyDeclarationBlock) {
+        let mut css = String::new();
+        let _ = block.to_css(&mut css);
+        if css.is_empty() {
+            self.doc
+                .remove_attribute(self.node_id, &local_name!("style"));
+        } else {
+            let name = QualName::new(None, ns!(), local_name!("style"));
+            self.doc.set_attribute(self.node_id, name, &css);
+        }
+    }
+}
+
+fn parse(css: &str) -> PropertyDeclarationBlock {
+    let url = UrlExtraData::from(
+        "data:text/css;charset=utf-8;base64,"
+            .parse::<Url>()
+            .unwrap(),
+    );
+    parse_style_attribute(css, &url, None, QuirksMode::NoQuirks, CssRuleType::Style)
+}
@@ -0,0 +1,108 @@
+//! Which virtual keyboard a text field wants
+//!
+//! <https://html.spec.whatwg.org/multipage/interaction.html#input-modalities:-the-inputmode-attribute>
+//!
+//! The `inputmode` attribute hints at the kind of data that's entered into a text field, so that shells with a
+//! virtual keyboard can show a matching one (e.g. a number pad). Without the attribute, the hint follows from the
+//! type of input: number inputs want decimal digits, telephone inputs a phone pad, and so on. The shell reads the
+//! hint of the focused field with [`Document::focused_input_mode`], and hides its keyboard when nothing that takes
+//! text has focus (or the hint is `none`).
+use html5ever::local_name;
+
+use crate::Document;
+
+/// The kind of virtual keyboard that a text field wants
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputMode {
+    /// No virtual keyboard: the page provides its own input
+    None,
+    Text,
+    Decimal,
+    Numeric,
+    Tel,
+    Search,
+    Email,
+    Url,
+}
+
+impl InputMode {
+    /// Parse the value of an `inputmode` attribute
+    pub fn parse(value: &str) -> Option<InputMode> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => InputMode::None,
+            "text" => InputMode::Text,
+            "decimal" => InputMode::Decimal,
+            "numeric" => InputMode::Numeric,
+            "tel" => InputMode::Tel,
+            "search" => InputMode::Search,
+            "email" => InputMode::Email,
+            "url" => InputMode::Url,
+            _ => return None,
+        })
+    }
+}
+
+impl Document {
+    /// The input mode of an element that takes text (a text-like `<input>`, a `<textarea>` or an editing host), or
+    /// `None` if it doesn't take text
+    pub fn input_mode(&self, node_id: usize) -> Option<InputMode> {
+        let element_data = self.nodes[node_id].element_data()?;
+        let implicit = match element_data.name.local {
+            local_name!("input") => {
+                let input_type = element_data
+                    .attr(local_name!("type"))
+                    .unwrap_or("text")
+                    .trim()
+                    .to_ascii_lowercase();
+                match input_type.as_str() {
+                    "number" => InputMode::Decimal,
+                    "tel" => InputMode::Tel,
+                    "search" => InputMode::Search,
+                    "email" => InputMode::Email,
+                    "url" => InputMode::Url,
+                    "text" | "password" => InputMode::Text,
+                    _ => return None,
+                }
+            }
+            local_name!("textarea") => InputMode::Text,
+            _ if self.is_editing_host(node_id) => InputMode::Text,
+            _ => return None,
+        };
+        Some(
+            element_data
+                .attr(local_name!("inputmode"))
+                .and_then(InputMode::parse)
+                .unwrap_or(implicit),
+        )
+    }
+
+    /// Whether an element is an `<input type=password>`, whose text shouldn't be remembered or suggested by IME
+    pub fn is_password_input(&self, node_id: usize) -> bool {
+        self.nodes[node_id].is_password_input()
+    }
+
+    /// The input mode of the focused element, or `None` if nothing that takes text has focus
+    pub fn focused_input_mode(&self) -> Option<InputMode> {
+        self.input_mode(self.focused_element()?)
+    }
+}
+
+#[test]
+fn input_modes_follow_the_input_type() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input id=text><input type=number id=number><input type=number inputmode=numeric id=numeric>\
+        <input type=tel inputmode=bogus id=tel><input type=checkbox id=checkbox>\
+        <div contenteditable inputmode=none id=editable></div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let mode = |id: &str| doc.input_mode(doc.get_element_by_id(id).unwrap());
+    assert_eq!(mode("text"), Some(InputMode::Text));
+    assert_eq!(mode("number"), Some(InputMode::Decimal));
+    assert_eq!(mode("numeric"), Some(InputMode::Numeric));
+    assert_eq!(mode("tel"), Some(InputMode::Tel));
+    assert_eq!(mode("checkbox"), None);
+    assert_eq!(mode("editable"), Some(InputMode::None));
+}
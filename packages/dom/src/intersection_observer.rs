@@ -0,0 +1,280 @@
+//! Visibility callbacks (the equivalent of `IntersectionObserver`)
+//!
+//! Embedders register a node along with the visibility ratios they care about, and are called back when the fraction
+//! of the node that's visible in the viewport crosses one of them. Observations are taken at the end of
+//! [`Document::resolve`], so they reflect both layout changes and scrolling. As with `IntersectionObserver`, the first
+//! observation of a node is always delivered. Removing a node from the document stops its observation.
+//!
+//! Only the viewport clips nodes: overflow clipping by scroll containers isn't taken into account.
+use crate::geometry::DomRect;
+use crate::Document;
+
+/// Called with the observation of a node whose visibility crossed a threshold
+pub type IntersectionCallback = Box<dyn FnMut(&mut Document, &IntersectionEntry) + Send>;
+
+/// Identifies an observation registered with [`Document::observe_intersection`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IntersectionObserverHandle(u64);
+
+/// What to observe the visibility of a node with
+#[derive(Clone, Debug)]
+pub struct IntersectionObserverOptions {
+    /// The visible ratios (from `0.0` to `1.0`) that trigger a callback when crossed. A threshold of `0.0` is crossed
+    /// whenever the node starts or stops intersecting the viewport.
+    pub thresholds: Vec<f32>,
+    /// How far (in CSS pixels) to grow the viewport on each side before intersecting it with nodes. Negative margins
+    /// shrink the viewport.
+    pub root_margin: taffy::Rect<f32>,
+}
+
+impl Default for IntersectionObserverOptions {
+    fn default() -> Self {
+        Self {
+            thresholds: vec![0.0],
+            root_margin: taffy::Rect {
+                left: 0.0,
+                right: 0.0,
+                top: 0.0,
+                bottom: 0.0,
+            },
+        }
+    }
+}
+
+/// An observation of a node's visibility
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntersectionEntry {
+    pub node_id: usize,
+    /// The timestamp of the frame it was observed in
+    pub time: f64,
+    /// The node's border box, relative to the viewport (if it's rendered)
+    pub bounding_rect: Option<DomRect>,
+    /// The viewport grown by the root margin
+    pub root_rect: DomRect,
+    /// The visible part of the node, relative to the viewport
+    pub intersection_rect: Option<DomRect>,
+    /// The fraction of the node's area that's visible
+    pub ratio: f32,
+    pub is_intersecting: bool,
+}
+
+struct IntersectionObservation {
+    handle: IntersectionObserverHandle,
+    node_id: usize,
+    options: IntersectionObserverOptions,
+    callback: IntersectionCallback,
+    /// The threshold index and whether the node was intersecting when it was last observed
+    previous: Option<(usize, bool)>,
+}
+
+#[derive(Default)]
+pub(crate) struct IntersectionObservers {
+    observations: Vec<IntersectionObservation>,
+    next_handle: u64,
+}
+
+impl Document {
+    /// Call back when the visibility of a node crosses one of the given thresholds
+    pub fn observe_intersection(
+        &mut self,
+        node_id: usize,
+        mut options: IntersectionObserverOptions,
+        callback: impl FnMut(&mut Document, &IntersectionEntry) + Send + 'static,
+    ) -> IntersectionObserverHandle {
+        options
+            .thresholds
+            .retain(|threshold| (0.0..=1.0).contains(threshold));
+        options.thresholds.sort_by(f32::total_cmp);
+        if options.thresholds.is_empty() {
+            options.thresholds.push(0.0);
+        }
+
+        let observers = &mut self.intersection_observers;
+        let handle = IntersectionObserverHandle(observers.next_handle);
+        observers.next_handle += 1;
+        observers.observations.push(IntersectionObservation {
+            handle,
+            node_id,
+            options,
+            callback: Box::new(callback),
+            previous: None,
+        });
+
+        // Take the first observation in the next frame
+        self.invalidate();
+        handle
+    }
+
+    /// Stop observing the node registered with [`Document::observe_intersection`]
+    pub fn unobserve_intersection(&mut self, handle: IntersectionObserverHandle) {
+        self.intersection_observers
+            .observations
+            .retain(|observation| observation.handle != handle);
+    }
+
+    /// Stop observing a node that has been removed from the document, so that a node which reuses its id isn't
+    /// observed in its place
+    pub(crate) fn unobserve_removed_intersections(&mut self, node_id: usize) {
+        self.intersection_observers
+            .observations
+            .retain(|observation| observation.node_id != node_id);
+    }
+
+    fn intersection_observation(
+        &self,
+        handle: IntersectionObserverHandle,
+    ) -> Option<&IntersectionObservation> {
+        self.intersection_observers
+            .observations
+            .iter()
+            .find(|observation| observation.handle == handle)
+    }
+
+    fn intersection_observation_mut(
+        &mut self,
+        handle: IntersectionObserverHandle,
+    ) -> Option<&mut IntersectionObservation> {
+        self.intersection_observers
+            .observations
+            .iter_mut()
+            .find(|observation| observation.handle == handle)
+    }
+
+    /// Observe the visibility of every observed node, and call back those that crossed a threshold
+    pub(crate) fn update_intersection_observations(&mut self) {
+        if self.intersection_observers.observations.is_empty() {
+            return;
+        }
+
+        let viewport = self.stylist.device().au_viewport_size();
+        let time = self.frame_time();
+
+        // Callbacks may register (or unregister) observations while they run, so each observation is looked up again
+        // before it's taken. Observations registered by the callbacks are first taken in the next frame.
+        let handles: Vec<IntersectionObserverHandle> = self
+            .intersection_observers
+            .observations
+            .iter()
+            .map(|observation| observation.handle)
+            .collect();
+
+        let mut delivered = false;
+        for handle in handles {
+            let Some(observation) = self.intersection_observation(handle) else {
+                continue;
+            };
+            let node_id = observation.node_id;
+            let margin = observation.options.root_margin;
+            let root_rect = DomRect {
+                x: -margin.left,
+                y: -margin.top,
+                width: viewport.width.to_f32_px() + margin.left + margin.right,
+                height: viewport.height.to_f32_px() + margin.top + margin.bottom,
+            };
+
+            let bounding_rect = self.node_bounding_rect(node_id);
+            let intersection_rect = bounding_rect.and_then(|rect| rect.intersection(&root_rect));
+            let is_intersecting = intersection_rect.is_some();
+            let ratio = match (bounding_rect, intersection_rect) {
+                (Some(rect), Some(intersection)) => {
+                    let area = rect.width * rect.height;
+                    if area > 0.0 {
+                        intersection.width * intersection.height / area
+                    } else {
+                        1.0
+                    }
+                }
+                _ => 0.0,
+            };
+
+            // The number of thresholds that have been reached
+            let threshold_index = match is_intersecting {
+                true => observation
+                    .options
+                    .thresholds
+                    .iter()
+                    .filter(|threshold| ratio >= **threshold)
+                    .count(),
+                false => 0,
+            };
+            if observation.previous == Some((threshold_index, is_intersecting)) {
+                continue;
+            }
+            let observation = self.intersection_observation_mut(handle).unwrap();
+            observation.previous = Some((threshold_index, is_intersecting));
+
+            let entry = IntersectionEntry {
+                node_id,
+                time,
+                bounding_rect,
+                root_rect,
+                intersection_rect,
+                ratio,
+                is_intersecting,
+            };
+            // The callback is put back unless it unregistered its own observation
+            let mut callback = std::mem::replace(&mut observation.callback, Box::new(|_, _| {}));
+            callback(self, &entry);
+            if let Some(observation) = self.intersection_observation_mut(handle) {
+                observation.callback = callback;
+            }
+            delivered = true;
+        }
+
+        // Changes made by the callbacks are rendered in the next frame
+        if delivered {
+            self.invalidate();
+        }
+    }
+}
+
+#[test]
+fn delivers_threshold_crossings() {
+    use crate::test_util::parse_test_document;
+    use std::sync::{Arc, Mutex};
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div style='height: 1000px'></div><div id=target style='height: 100px'></div></body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+    let target = doc.get_element_by_id("target").unwrap();
+
+    // The callback unregisters its own observation once `stop` is set
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let own_handle = Arc::new(Mutex::new(None));
+    let stop = Arc::new(Mutex::new(false));
+    let (log, handle_cell, stop_flag) = (entries.clone(), own_handle.clone(), stop.clone());
+    let handle = doc.observe_intersection(target, Default::default(), move |doc, entry| {
+        log.lock().unwrap().push(entry.is_intersecting);
+        if *stop_flag.lock().unwrap() {
+            doc.unobserve_intersection(handle_cell.lock().unwrap().unwrap());
+        }
+    });
+    *own_handle.lock().unwrap() = Some(handle);
+    let delivered = || std::mem::take(&mut *entries.lock().unwrap());
+
+    // The first observation is delivered, then only crossings are
+    doc.resolve();
+    assert_eq!(delivered(), [false]);
+    doc.set_viewport_scroll(500.0);
+    doc.resolve();
+    assert_eq!(delivered(), [true]);
+    doc.resolve();
+    assert!(delivered().is_empty());
+
+    // Unregistering from within the callback stops delivery
+    *stop.lock().unwrap() = true;
+    doc.set_viewport_scroll(0.0);
+    doc.resolve();
+    assert_eq!(delivered(), [false]);
+    doc.set_viewport_scroll(500.0);
+    doc.resolve();
+    assert!(delivered().is_empty());
+
+    // Removing an observed node stops its observation
+    doc.observe_intersection(target, Default::default(), |_, _| {
+        panic!("removed nodes aren't observed")
+    });
+    doc.remove_node(target);
+    doc.resolve();
+}
@@ -0,0 +1,272 @@
+//! Reconciling keyed lists of children
+//!
+//! Frameworks that don't diff their own lists describe the children a parent should have, each with a key that
+//! identifies it across updates. [`Document::reconcile_keyed_children`] compares them with the parent's current
+//! children and produces the [`Mutation`]s that turn one into the other: children whose keys are gone are removed,
+//! new keys are created, and the children that are kept are moved as little as possible. Kept children that form the
+//! longest increasing subsequence of their old positions stay where they are, and only the rest are moved.
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::mutation::{Mutation, NodeRef};
+use crate::Document;
+
+/// A child that a keyed list should contain
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyedChild<K> {
+    pub key: K,
+    /// The mutations that create the child, which are only used if the list doesn't already have a child with its
+    /// key. The first node they create is the child, and `NodeRef::Created` indices count from the start of these
+    /// mutations.
+    pub create: Vec<Mutation>,
+}
+
+impl Document {
+    /// The mutations that turn the children of `parent_id` into `new_children`. The parent's current children have
+    /// the keys `old_keys`, in order.
+    pub fn reconcile_keyed_children<K: Eq + Hash>(
+        &self,
+        parent_id: usize,
+        old_keys: &[K],
+        new_children: Vec<KeyedChild<K>>,
+    ) -> Vec<Mutation> {
+        let old_ids = &self.nodes[parent_id].children;
+        debug_assert_eq!(old_ids.len(), old_keys.len());
+        let old_positions: HashMap<&K, usize> = old_keys
+            .iter()
+            .enumerate()
+            .map(|(position, key)| (key, position))
+            .collect();
+
+        // Where each new child was in the old list (if it was)
+        let sources: Vec<Option<usize>> = new_children
+            .iter()
+            .map(|child| old_positions.get(&child.key).copied())
+            .collect();
+
+        // Children whose keys aren't in the new list are removed first, so that appending puts nodes at the end
+        let mut kept = vec![false; old_keys.len()];
+        for &position in sources.iter().flatten() {
+            kept[position] = true;
+        }
+        let mut mutations: Vec<Mutation> = old_ids
+            .iter()
+            .zip(&kept)
+            .filter(|(_, &kept)| !kept)
+            .map(|(&node_id, _)| Mutation::Remove {
+                node: NodeRef::Node(node_id),
+            })
+            .collect();
+
+        // The children in the longest increasing run of old positions stay where they are
+        let kept_sources: Vec<(usize, usize)> = sources
+            .iter()
+            .enumerate()
+            .filter_map(|(index, source)| Some((index, (*source)?)))
+            .collect();
+        let mut stays = vec![false; new_children.len()];
+        let positions: Vec<usize> = kept_sources.iter().map(|(_, source)| *source).collect();
+        for run_index in longest_increasing_subsequence(&positions) {
+            stays[kept_sources[run_index].0] = true;
+        }
+
+        // Place the rest from the end of the list, before the child that follows them
+        let mut created = 0;
+        let mut next: Option<NodeRef> = None;
+        let mut placements = Vec::new();
+        for (index, child) in new_children.into_iter().enumerate().rev() {
+            let node = match sources[index] {
+                Some(position) => NodeRef::Node(old_ids[position]),
+                None => {
+                    // The child's own indices count from where its mutations start
+                    let base = created;
+                    for mut mutation in child.create {
+                        mutation.offset_created(base);
+                        created += mutation.creates_node() as usize;
+                        mutations.push(mutation);
+                    }
+                    NodeRef::Created(base)
+                }
+            };
+            if !stays[index] {
+                placements.push(match next {
+                    Some(anchor) => Mutation::InsertBefore {
+                        anchor,
+                        nodes: vec![node],
+                    },
+                    None => Mutation::AppendChildren {
+                        parent: NodeRef::Node(parent_id),
+                        children: vec![node],
+                    },
+                });
+            }
+            next = Some(node);
+        }
+
+        // The new children are all created before any are placed
+        mutations.extend(placements);
+        mutations
+    }
+}
+
+impl Mutation {
+    /// Whether the mutation creates a node (and so can be referred to by `NodeRef::Created`)
+    fn creates_node(&self) -> bool {
+        matches!(
+            self,
+            Mutation::CreateElement { .. } | Mutation::CreateText { .. }
+        )
+    }
+
+    /// Shift the `NodeRef::Created` indices of the mutation, so that it can follow other creating mutations
+    fn offset_created(&mut self, offset: usize) {
+        let offset_ref = |node: &mut NodeRef| {
+            if let NodeRef::Created(index) = node {
+                *index += offset;
+            }
+        };
+        match self {
+            Mutation::CreateElement { .. } | Mutation::CreateText { .. } => {}
+            Mutation::SetAttribute { node, .. }
+            | Mutation::RemoveAttribute { node, .. }
+            | Mutation::SetText { node, .. }
+            | Mutation::Remove { node } => offset_ref(node),
+            Mutation::AppendChildren { parent, children } => {
+                offset_ref(parent);
+                children.iter_mut().for_each(offset_ref);
+            }
+            Mutation::InsertBefore { anchor, nodes } => {
+                offset_ref(anchor);
+                nodes.iter_mut().for_each(offset_ref);
+            }
+        }
+    }
+}
+
+/// The indices of a longest strictly increasing subsequence of `values`
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // The index of the smallest value that ends an increasing run of each length, and the index before each value
+    // in the run it ends
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (index, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        previous[index] = length.checked_sub(1).map(|length| tails[length]);
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut run = Vec::with_capacity(tails.len());
+    let mut index = tails.last().copied();
+    while let Some(i) = index {
+        run.push(i);
+        index = previous[i];
+    }
+    run.reverse();
+    run
+}
+
+#[test]
+fn finds_longest_increasing_subsequence() {
+    assert_eq!(longest_increasing_subsequence(&[3, 0, 2, 1, 4]), [1, 3, 4]);
+    assert_eq!(longest_increasing_subsequence(&[]), Vec::<usize>::new());
+}
+
+#[test]
+fn reconciles_keyed_children() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{local_name, namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body><ul id=list><li id=a></li><li id=b></li><li id=c></li><li id=d></li></ul>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+
+    let list_id = doc.get_element_by_id("list").unwrap();
+    let ids = ["a", "b", "c", "d"].map(|id| doc.get_element_by_id(id).unwrap());
+    let child = |key: &'static str| KeyedChild {
+        key,
+        create: vec![
+            Mutation::CreateElement {
+                name: QualName::new(None, ns!(html), local_name!("li")),
+                attrs: vec![(QualName::new(None, ns!(), local_name!("id")), key.into())],
+            },
+            Mutation::CreateText { text: key.into() },
+            Mutation::AppendChildren {
+                parent: NodeRef::Created(0),
+                children: vec![NodeRef::Created(1)],
+            },
+        ],
+    };
+
+    let mutations = doc.reconcile_keyed_children(
+        list_id,
+        &["a", "b", "c", "d"],
+        vec![child("d"), child("a"), child("e"), child("c"), child("f")],
+    );
+
+    // b is removed, and of the kept children only d (which is out of order) moves
+    assert_eq!(
+        mutations
+            .iter()
+            .filter(|mutation| matches!(mutation, Mutation::Remove { .. }))
+            .count(),
+        1
+    );
+    assert!(mutations.contains(&Mutation::InsertBefore {
+        anchor: NodeRef::Node(ids[0]),
+        nodes: vec![NodeRef::Node(ids[3])],
+    }));
+    assert!(!mutations.iter().any(|mutation| matches!(
+        mutation,
+        Mutation::InsertBefore { nodes, .. } if nodes.contains(&NodeRef::Node(ids[0])) || nodes.contains(&NodeRef::Node(ids[2]))
+    )));
+
+    doc.apply_mutations(mutations);
+    let order: Vec<String> = doc.nodes[list_id]
+        .children
+        .iter()
+        .map(|&id| doc.nodes[id].attr(local_name!("id")).unwrap().to_string())
+        .collect();
+    assert_eq!(order, ["d", "a", "e", "c", "f"]);
+    let e_id = doc.get_element_by_id("e").unwrap();
+    assert_eq!(doc.nodes[e_id].text_content(), "e");
+}
+
+#[test]
+fn creates_children_with_their_own_descendants() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{local_name, namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body><ul id=list></ul></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    let list_id = doc.get_element_by_id("list").unwrap();
+
+    // Each child is an element with a text child, which its mutations refer to from index 0
+    let child = |key: &'static str| KeyedChild {
+        key,
+        create: vec![
+            Mutation::CreateElement {
+                name: QualName::new(None, ns!(html), local_name!("li")),
+                attrs: Vec::new(),
+            },
+            Mutation::CreateText { text: key.into() },
+            Mutation::AppendChildren {
+                parent: NodeRef::Created(0),
+                children: vec![NodeRef::Created(1)],
+            },
+        ],
+    };
+    let mutations =
+        doc.reconcile_keyed_children(list_id, &[], vec![child("x"), child("y"), child("z")]);
+    doc.apply_mutations(mutations);
+
+    let items: Vec<(usize, String)> = doc.nodes[list_id]
+        .children
+        .iter()
+        .map(|&id| (doc.nodes[id].children.len(), doc.nodes[id].text_content()))
+        .collect();
+    assert_eq!(items, [(1, "x".into()), (1, "y".into()), (1, "z".into())]);
+}
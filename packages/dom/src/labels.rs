@@ -0,0 +1,213 @@
+//! Labels of form controls and accessible names
+//!
+//! <https://html.spec.whatwg.org/multipage/forms.html#the-label-element>
+//! <https://www.w3.org/TR/accname-1.2/>
+//!
+//! A `<label>` labels the control named by its `for` attribute, or else the first labelable element within it.
+//! Clicking a label (other than on an interactive element within it) focuses its control and activates it as if it had
+//! been clicked, which the renderer does for both pointer clicks and access keys.
+//!
+//! The accessible name of an element is what assistive technologies announce for it: the text of the elements named
+//! by its `aria-labelledby`, its `aria-label`, the text of its labels, its `alt` text, its own text (for buttons,
+//! links and the like) or its `title`, whichever comes first. Labels that contain their control leave the control out
+//! of the name.
+//!
+//! Note: Blitz doesn't build an accessibility tree yet, so the names are only available through
+//! [`Document::accessible_name`].
+use html5ever::local_name;
+
+use crate::Document;
+
+impl Document {
+    /// Whether an element can be labelled by a `<label>`
+    pub fn is_labelable(&self, node_id: usize) -> bool {
+        let Some(element_data) = self.nodes[node_id].element_data() else {
+            return false;
+        };
+        match element_data.name.local {
+            local_name!("input") => !element_data
+                .attr(local_name!("type"))
+                .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("hidden")),
+            local_name!("button")
+            | local_name!("meter")
+            | local_name!("output")
+            | local_name!("progress")
+            | local_name!("select")
+            | local_name!("textarea") => true,
+            _ => false,
+        }
+    }
+
+    /// The control labelled by a `<label>` (if any)
+    pub fn labeled_control(&self, label_id: usize) -> Option<usize> {
+        let label = &self.nodes[label_id];
+        if !label.is_element_with_tag_name(&local_name!("label")) {
+            return None;
+        }
+        match label.attr(local_name!("for")) {
+            Some(id) => self
+                .get_element_by_id(id)
+                .filter(|&control_id| self.is_labelable(control_id)),
+            None => self.first_labelable_descendant(label_id),
+        }
+    }
+
+    /// The `<label>`s of a control, in tree order
+    pub fn labels(&self, control_id: usize) -> Vec<usize> {
+        fn collect(doc: &Document, node_id: usize, control_id: usize, labels: &mut Vec<usize>) {
+            if doc.labeled_control(node_id) == Some(control_id) {
+                labels.push(node_id);
+            }
+            for &child_id in &doc.nodes[node_id].children {
+                collect(doc, child_id, control_id, labels);
+            }
+        }
+        let mut labels = Vec::new();
+        if self.is_labelable(control_id) {
+            if let Some(root) = self.try_root_element() {
+                collect(self, root.id, control_id, &mut labels);
+            }
+        }
+        labels
+    }
+
+    /// The control that clicking a node activates through a label: the control of the nearest `<label>` the node is
+    /// within, unless the node is within an interactive element inside the label or the control is disabled
+    pub fn label_target(&self, node_id: usize) -> Option<usize> {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            if node.is_element_with_tag_name(&local_name!("label")) {
+                return self
+                    .labeled_control(id)
+                    .filter(|&control_id| !self.is_disabled(control_id));
+            }
+            if self.is_labelable(id) || self.is_focusable(id) {
+                return None;
+            }
+            maybe_id = node.parent;
+        }
+        None
+    }
+
+    /// The accessible name of an element, with white space collapsed
+    pub fn accessible_name(&self, node_id: usize) -> String {
+        let node = &self.nodes[node_id];
+        let ids = node
+            .attr(local_name!("aria-labelledby"))
+            .unwrap_or_default();
+        let labelled_by: Vec<String> = ids
+            .split_ascii_whitespace()
+            .filter_map(|id| self.get_element_by_id(id))
+            .map(|label_id| self.name_text(label_id, None))
+            .collect();
+        let labels: Vec<String> = self
+            .labels(node_id)
+            .into_iter()
+            .map(|label_id| self.name_text(label_id, Some(node_id)))
+            .collect();
+        let tag_name = node
+            .element_data()
+            .map(|element_data| element_data.name.local.clone());
+        let own_text = match tag_name {
+            Some(
+                local_name!("button")
+                | local_name!("a")
+                | local_name!("summary")
+                | local_name!("legend")
+                | local_name!("caption")
+                | local_name!("label"),
+            ) => self.name_text(node_id, None),
+            Some(local_name!("input")) => match node.attr(local_name!("type")) {
+                Some(ty)
+                    if ["button", "submit", "reset"]
+                        .iter()
+                        .any(|t| ty.trim().eq_ignore_ascii_case(t)) =>
+                {
+                    node.attr(local_name!("value"))
+                        .unwrap_or_default()
+                        .to_string()
+                }
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        [
+            labelled_by.join(" "),
+            node.attr(local_name!("aria-label"))
+                .unwrap_or_default()
+                .to_string(),
+            labels.join(" "),
+            node.attr(local_name!("alt"))
+                .unwrap_or_default()
+                .to_string(),
+            own_text,
+            node.attr(local_name!("title"))
+                .unwrap_or_default()
+                .to_string(),
+        ]
+        .into_iter()
+        .map(|name| name.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|name| !name.is_empty())
+        .unwrap_or_default()
+    }
+
+    /// The text of an element for use in an accessible name, leaving out an element within it (and the text of
+    /// hidden elements)
+    fn name_text(&self, node_id: usize, skip: Option<usize>) -> String {
+        fn collect(doc: &Document, node_id: usize, skip: Option<usize>, out: &mut String) {
+            let node = &doc.nodes[node_id];
+            if Some(node_id) == skip || node.hidden || node.is_password_input() {
+                return;
+            }
+            if let Some(text) = node.text_data() {
+                out.push_str(&text.content);
+            }
+            for &child_id in &node.children {
+                collect(doc, child_id, skip, out);
+            }
+        }
+        let mut out = String::new();
+        collect(self, node_id, skip, &mut out);
+        out
+    }
+
+    fn first_labelable_descendant(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id].children.iter().find_map(|&child_id| {
+            if self.is_labelable(child_id) {
+                Some(child_id)
+            } else {
+                self.first_labelable_descendant(child_id)
+            }
+        })
+    }
+}
+
+#[test]
+fn associates_labels_with_controls() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <label id=for-label for=email>Email <b id=bold>address</b></label><input id=email>\
+        <label id=wrapping>Remember me <input type=checkbox id=remember> <a id=link href=/terms>terms</a></label>\
+        <label id=disabled-label>Off <input id=disabled disabled></label>\
+        <button id=button aria-label=Close>x</button></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let id = |id: &str| doc.get_element_by_id(id).unwrap();
+    assert_eq!(doc.labeled_control(id("for-label")), Some(id("email")));
+    assert_eq!(doc.labeled_control(id("wrapping")), Some(id("remember")));
+    assert_eq!(doc.labels(id("email")), [id("for-label")]);
+
+    // Clicks within a label go to its control, except clicks on interactive elements and disabled controls
+    assert_eq!(doc.label_target(id("bold")), Some(id("email")));
+    assert_eq!(doc.label_target(id("link")), None);
+    assert_eq!(doc.label_target(id("disabled-label")), None);
+
+    // Labels name their controls, leaving out the control itself
+    assert_eq!(doc.accessible_name(id("email")), "Email address");
+    assert_eq!(doc.accessible_name(id("remember")), "Remember me terms");
+    assert_eq!(doc.accessible_name(id("button")), "Close");
+}
@@ -0,0 +1,164 @@
+//! The alt text of broken images
+//!
+//! <https://html.spec.whatwg.org/multipage/rendering.html#images-3>
+//!
+//! An `<img>` whose image couldn't be loaded or decoded shows the broken image icon, followed by its `alt` text laid
+//! out as inline content in the element's font. Without a `width`, `height` or CSS size, the element is sized to fit
+//! the icon and the text (wrapping the text to the available width). With one, it keeps that size and the text wraps
+//! within it, clipped to the content box when painted.
+//!
+//! Note: the element is still an atomic inline box rather than an inline formatting context, so its text doesn't
+//! flow on from the surrounding line.
+use html5ever::local_name;
+use parley::style::WhiteSpaceCollapse;
+use taffy::{
+    compute_leaf_layout, AvailableSpace, Dimension, LayoutInput, LayoutOutput, ResolveOrZero, Size,
+};
+
+use crate::image::{image_measure_function, ImageContext};
+use crate::node::TextLayout;
+use crate::resource_errors::BROKEN_IMAGE_SIZE;
+use crate::{stylo_to_parley, Document};
+
+/// The space between the broken image icon and the alt text, in CSS pixels
+pub const ALT_TEXT_GAP: f32 = 4.0;
+
+impl Document {
+    /// Lay out a broken image that has alt text, sizing it to fit the text unless it has a size of its own
+    pub(crate) fn compute_alt_text_layout(
+        &mut self,
+        node_id: usize,
+        inputs: LayoutInput,
+        image_context: ImageContext,
+    ) -> LayoutOutput {
+        let scale = self.scale;
+        let mut text_layout = self.build_alt_text_layout(node_id);
+        let style = self.nodes[node_id].style.clone();
+        let inset = BROKEN_IMAGE_SIZE.width + ALT_TEXT_GAP;
+
+        // Sizes from CSS or the `width` and `height` attributes are kept, as they are for images that load
+        let has_size = style.size.width != Dimension::Auto
+            || style.size.height != Dimension::Auto
+            || image_context.attr_size.width.is_some()
+            || image_context.attr_size.height.is_some();
+
+        let output = compute_leaf_layout(inputs, &style, |known_dimensions, available_space| {
+            if has_size {
+                return image_measure_function(
+                    known_dimensions,
+                    inputs.parent_size,
+                    &image_context,
+                    &style,
+                    false,
+                );
+            }
+
+            let text_width = match (known_dimensions.width, available_space.width) {
+                (Some(width), _) | (None, AvailableSpace::Definite(width)) => {
+                    Some((width - inset).max(0.0))
+                }
+                (None, AvailableSpace::MinContent) => Some(0.0),
+                (None, AvailableSpace::MaxContent) => None,
+            };
+            text_layout
+                .layout
+                .break_all_lines(text_width.map(|width| width * scale));
+            Size {
+                width: known_dimensions
+                    .width
+                    .unwrap_or(inset + text_layout.layout.width() / scale),
+                height: known_dimensions.height.unwrap_or(
+                    BROKEN_IMAGE_SIZE
+                        .height
+                        .max(text_layout.layout.height() / scale),
+                ),
+            }
+        });
+
+        // Break the text for the width the element ended up with
+        let padding_border = (style.padding.resolve_or_zero(inputs.parent_size)
+            + style.border.resolve_or_zero(inputs.parent_size))
+        .horizontal_components()
+        .sum();
+        let text_width = (output.size.width - padding_border - inset).max(0.0);
+        text_layout.layout.break_all_lines(Some(text_width * scale));
+        text_layout
+            .layout
+            .align(None, parley::layout::Alignment::Start);
+
+        if let Some(element_data) = self.nodes[node_id].element_data_mut() {
+            element_data.alt_text_layout = Some(Box::new(text_layout));
+        }
+        output
+    }
+
+    /// Shape an image's alt text in the image's font
+    fn build_alt_text_layout(&mut self, node_id: usize) -> TextLayout {
+        let node = &self.nodes[node_id];
+        let alt = node
+            .attr(local_name!("alt"))
+            .unwrap_or_default()
+            .to_string();
+        let styles = node.primary_styles();
+        let mut parley_style = styles
+            .as_ref()
+            .map(|s| stylo_to_parley::style(s))
+            .unwrap_or_default();
+        self.font_faces.adjust_text_style(
+            &mut parley_style,
+            styles
+                .as_ref()
+                .map_or(true, |s| stylo_to_parley::has_normal_line_height(s)),
+        );
+        drop(styles);
+
+        let mut builder =
+            self.layout_ctx
+                .tree_builder(&mut self.font_ctx, self.scale, &parley_style);
+        builder.set_white_space_mode(WhiteSpaceCollapse::Collapse);
+        builder.push_text(&alt);
+        let (layout, text) = builder.build();
+        TextLayout {
+            text,
+            layout,
+            line_break_bucket: None,
+        }
+    }
+}
+
+#[test]
+fn broken_images_show_their_alt_text() {
+    use crate::test_util::{parse_html, test_document};
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <img id=described src='data:image/png,broken' alt='A photo of a cat'>\
+        <img id=sized src='data:image/png,broken' alt='A photo of a cat' width=20 height=30>\
+        <img id=bare src='data:image/png,broken'></body></html>";
+    let mut doc = test_document(&[]);
+    doc.set_resource_error_handler(|_| {});
+    parse_html(&mut doc, html);
+    doc.resolve();
+
+    let size = |id: &str| {
+        let node = &doc.nodes[doc.get_element_by_id(id).unwrap()];
+        (node.final_layout.size, node.element_data().unwrap())
+    };
+
+    // The text is laid out after the icon
+    let (described, element_data) = size("described");
+    assert!(element_data.broken_image);
+    let text_layout = element_data.alt_text_layout.as_ref().unwrap();
+    assert_eq!(text_layout.text, "A photo of a cat");
+    let expected_width = BROKEN_IMAGE_SIZE.width + ALT_TEXT_GAP + text_layout.layout.width();
+    assert!((described.width - expected_width).abs() <= 1.0);
+    assert!(described.height >= BROKEN_IMAGE_SIZE.height);
+
+    // Sized images keep their size
+    let (sized, _) = size("sized");
+    assert_eq!((sized.width, sized.height), (20.0, 30.0));
+
+    // Images without alt text only show the icon
+    let (bare, element_data) = size("bare");
+    assert_eq!(bare, BROKEN_IMAGE_SIZE);
+    assert!(element_data.alt_text_layout.is_none());
+}
@@ -10,9 +10,16 @@ use style::{
     },
 };
 
+use std::hash::Hash;
+
+use super::text_cache::{self, ShapingKey};
+use super::text_indent::{push_text_indent, TextIndent, TEXT_INDENT_BOX_ID};
 use crate::{
+    font_faces::FontFaces,
     node::{NodeKind, TextBrush, TextLayout},
-    stylo_to_parley, Document, ElementNodeData, Node, NodeData,
+    stylo_to_parley,
+    tab_size::TabStops,
+    Document, ElementNodeData, Node, NodeData,
 };
 
 pub(crate) fn collect_layout_children(
@@ -25,6 +32,15 @@ pub(crate) fn collect_layout_children(
         return;
     }
 
+    // The children of iframes, progress bars and meters are fallback content which is never rendered
+    let container_data = &doc.nodes[container_node_id].raw_dom_data;
+    if container_data.is_element_with_tag_name(&local_name!("iframe"))
+        || container_data.is_element_with_tag_name(&local_name!("progress"))
+        || container_data.is_element_with_tag_name(&local_name!("meter"))
+    {
+        return;
+    }
+
     let container_display = doc.nodes[container_node_id].display_style().unwrap_or(
         match doc.nodes[container_node_id].raw_dom_data.kind() {
             NodeKind::AnonymousBlock => Display::Block,
@@ -245,41 +261,91 @@ pub(crate) fn build_inline_layout(
             .and_then(|parent_id| doc.nodes[parent_id].primary_styles())
     });
 
-    let parley_style = root_node_style
+    let mut parley_style = root_node_style
         .as_ref()
         .map(|s| stylo_to_parley::style(s))
         .unwrap_or_default();
+    doc.font_faces.adjust_text_style(
+        &mut parley_style,
+        root_node_style
+            .as_ref()
+            .map_or(true, |s| stylo_to_parley::has_normal_line_height(s)),
+    );
 
     let root_line_height = parley_style.line_height;
 
+    // Everything pushed to the builder is also added to the key of the shaped text cache
+    let mut key = ShapingKey::default();
+    text_cache::hash_text_style(&parley_style, &mut key);
+    doc.scale.to_bits().hash(&mut key);
+
     // Create a parley tree builder
     let mut builder = doc
         .layout_ctx
         .tree_builder(&mut doc.font_ctx, doc.scale, &parley_style);
 
+    // The first line of an element is indented by its `text-indent`, but text in an anonymous block isn't the first
+    // line of its parent (see `text_indent`)
+    let text_indent = match root_node.raw_dom_data {
+        NodeData::Element(_) => root_node_style
+            .as_ref()
+            .and_then(|s| TextIndent::from_style(s)),
+        _ => None,
+    };
+    if text_indent.is_some() {
+        push_text_indent(&mut builder, &mut key);
+    }
+    let indent_each_line = text_indent.is_some_and(|text_indent| text_indent.each_line);
+
     // Set whitespace collapsing mode
     let collapse_mode = root_node_style
         .map(|s| s.get_inherited_text().white_space_collapse)
         .map(stylo_to_parley::white_space_collapse)
         .unwrap_or(WhiteSpaceCollapse::Collapse);
     builder.set_white_space_mode(collapse_mode);
+    (collapse_mode as u8).hash(&mut key);
 
+    let mut tab_stops = TabStops::new(doc.tab_size);
     for child_id in root_node.children.iter().copied() {
         build_inline_layout_recursive(
             &mut builder,
+            &mut key,
             &doc.nodes,
             child_id,
             collapse_mode,
             root_line_height,
+            &doc.font_faces,
+            &mut tab_stops,
+            indent_each_line,
         );
     }
 
-    let (layout, text) = builder.build();
+    // Only shape the text if it isn't cached
+    let text_layout = match doc.shaping_cache.get(&key) {
+        Some(text_layout) => {
+            drop(builder);
+            doc.layout_stats.reused_shaped_text += 1;
+            text_layout
+        }
+        None => {
+            let (layout, text) = builder.build();
+            let text_layout = TextLayout {
+                text,
+                layout,
+                line_break_bucket: None,
+            };
+            doc.shaping_cache.insert(key, &text_layout);
+            doc.layout_stats.shaped_text += 1;
+            text_layout
+        }
+    };
+    let layout = &text_layout.layout;
 
     // Obtain layout children for the inline layout
     let layout_children: Vec<usize> = layout
         .inline_boxes()
         .iter()
+        .filter(|ibox| ibox.id != TEXT_INDENT_BOX_ID)
         .map(|ibox| ibox.id as usize)
         .collect();
 
@@ -288,14 +354,18 @@ pub(crate) fn build_inline_layout(
         doc.ensure_layout_children(child_id);
     }
 
-    return (TextLayout { text, layout }, layout_children);
+    return (text_layout, layout_children);
 
     fn build_inline_layout_recursive(
         builder: &mut TreeBuilder<TextBrush>,
+        key: &mut ShapingKey,
         nodes: &Slab<Node>,
         node_id: usize,
         collapse_mode: WhiteSpaceCollapse,
         root_line_height: f32,
+        font_faces: &FontFaces,
+        tab_stops: &mut TabStops,
+        indent_each_line: bool,
     ) {
         let node = &nodes[node_id];
 
@@ -306,10 +376,14 @@ pub(crate) fn build_inline_layout(
             .map(stylo_to_parley::white_space_collapse)
             .unwrap_or(collapse_mode);
         builder.set_white_space_mode(collapse_mode);
+        (collapse_mode as u8).hash(key);
 
         match &node.raw_dom_data {
             NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
                 // Hide hidden nodes
+                if node.hidden {
+                    return;
+                }
                 if let Some("hidden" | "") = element_data.attr(local_name!("hidden")) {
                     return;
                 }
@@ -329,17 +403,28 @@ pub(crate) fn build_inline_layout(
                         for child_id in node.children.iter().copied() {
                             build_inline_layout_recursive(
                                 builder,
+                                key,
                                 nodes,
                                 child_id,
                                 collapse_mode,
                                 root_line_height,
+                                font_faces,
+                                tab_stops,
+                                indent_each_line,
                             );
                         }
                     }
                     (DisplayOutside::Inline, DisplayInside::Flow) => {
                         let tag_name = &element_data.name.local;
 
-                        if *tag_name == local_name!("img") || *tag_name == local_name!("input") {
+                        if *tag_name == local_name!("img")
+                            || *tag_name == local_name!("video")
+                            || *tag_name == local_name!("iframe")
+                            || *tag_name == local_name!("progress")
+                            || *tag_name == local_name!("meter")
+                            || *tag_name == local_name!("input")
+                        {
+                            ("inline-box", node_id).hash(key);
                             builder.push_inline_box(InlineBox {
                                 id: node_id as u64,
                                 // Overridden by push_inline_box method
@@ -349,38 +434,57 @@ pub(crate) fn build_inline_layout(
                                 height: 0.0,
                             });
                         } else if *tag_name == local_name!("br") {
+                            "br".hash(key);
                             builder.push_style_modification_span(&[]);
                             builder.set_white_space_mode(WhiteSpaceCollapse::Preserve);
                             builder.push_text("\n");
+                            tab_stops.advance("\n");
                             builder.pop_style_span();
                             builder.set_white_space_mode(collapse_mode);
+                            if indent_each_line {
+                                push_text_indent(builder, key);
+                            }
                         } else {
-                            let mut style = node
-                                .primary_styles()
-                                .map(|s| stylo_to_parley::style(&s))
+                            let styles = node.primary_styles();
+                            let mut style = styles
+                                .as_ref()
+                                .map(|s| stylo_to_parley::style(s))
                                 .unwrap_or_default();
+                            font_faces.adjust_text_style(
+                                &mut style,
+                                styles
+                                    .as_ref()
+                                    .map_or(true, |s| stylo_to_parley::has_normal_line_height(s)),
+                            );
 
                             // Floor the line-height of the span by the line-height of the inline context
                             // See https://www.w3.org/TR/CSS21/visudet.html#line-height
                             style.line_height = style.line_height.max(root_line_height);
 
+                            text_cache::hash_text_style(&style, key);
                             builder.push_style_span(style);
 
                             for child_id in node.children.iter().copied() {
                                 build_inline_layout_recursive(
                                     builder,
+                                    key,
                                     nodes,
                                     child_id,
                                     collapse_mode,
                                     root_line_height,
+                                    font_faces,
+                                    tab_stops,
+                                    indent_each_line,
                                 );
                             }
 
                             builder.pop_style_span();
+                            "pop".hash(key);
                         }
                     }
                     // Inline box
                     (_, _) => {
+                        ("inline-box", node_id).hash(key);
                         builder.push_inline_box(InlineBox {
                             id: node_id as u64,
                             // Overridden by push_inline_box method
@@ -393,10 +497,36 @@ pub(crate) fn build_inline_layout(
                 };
             }
             NodeData::Text(data) => {
-                builder.push_text(&data.content);
+                // Tab stops are measured in the font size of the text's parent
+                let font_size = node
+                    .parent
+                    .and_then(|parent_id| nodes[parent_id].primary_styles())
+                    .map_or(16.0, |s| s.get_font().font_size.used_size.0.px());
+                let text = tab_stops.expand(
+                    &data.content,
+                    matches!(collapse_mode, WhiteSpaceCollapse::Preserve),
+                    font_size,
+                );
+                text.hash(key);
+
+                // With `text-indent: each-line`, preserved newlines are followed by an indent
+                let preserve_newlines = !matches!(collapse_mode, WhiteSpaceCollapse::Collapse);
+                if indent_each_line && preserve_newlines && text.contains('\n') {
+                    for (index, line) in text.split('\n').enumerate() {
+                        if index > 0 {
+                            builder.push_text("\n");
+                            push_text_indent(builder, key);
+                        }
+                        if !line.is_empty() {
+                            builder.push_text(line);
+                        }
+                    }
+                } else {
+                    builder.push_text(&text);
+                }
             }
             NodeData::Comment => {}
-            NodeData::Document => unreachable!(),
+            NodeData::Document | NodeData::DocumentFragment => unreachable!(),
         }
     }
 }
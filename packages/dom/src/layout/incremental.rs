@@ -0,0 +1,187 @@
+//! Incremental layout
+//!
+//! Rebuilding layout children (and with them, inline layouts and anonymous blocks) and clearing taffy's caches for the
+//! whole tree makes every change as expensive as the first layout. Instead, each node records a signature of its
+//! layout inputs (its computed style, children, attributes, text and image) when it is laid out. Before layout the
+//! signatures are compared against the current tree: nodes that have changed, and their ancestors, are dirty. Only
+//! dirty nodes have their layout children rebuilt and their caches cleared, so taffy can reuse the cached layouts of
+//! every subtree that hasn't changed.
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+
+use crate::node::NodeData;
+use crate::Document;
+
+/// How much of the document was laid out again by the last call to [`Document::resolve`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayoutStats {
+    /// Nodes whose layout inputs changed (or that contain such nodes), and so were laid out again
+    pub dirty_nodes: usize,
+    /// All of the nodes in the document
+    pub total_nodes: usize,
+    /// Inline layouts whose text was shaped
+    pub shaped_text: usize,
+    /// Inline layouts whose shaped text was reused from the cache
+    pub reused_shaped_text: usize,
+}
+
+impl Document {
+    /// Lay a node (and its ancestors) out again on the next call to [`Document::resolve`], for changes that the
+    /// document can't detect itself
+    pub fn mark_layout_dirty(&mut self, node_id: usize) {
+        if let Some(node) = self.nodes.get_mut(node_id) {
+            node.layout_signature = None;
+        }
+    }
+
+    /// Lay the whole document out again on the next call to [`Document::resolve`]
+    pub fn invalidate_layout(&mut self) {
+        for (_, node) in self.nodes.iter_mut() {
+            node.layout_signature = None;
+        }
+    }
+
+    /// How much of the document was laid out again by the last call to [`Document::resolve`]
+    pub fn layout_stats(&self) -> LayoutStats {
+        self.layout_stats
+    }
+
+    /// Whether a node was dirty in the current layout pass
+    pub(crate) fn is_layout_dirty(&self, node_id: usize) -> bool {
+        self.layout_dirty.contains(&node_id)
+    }
+
+    /// Compare each node's layout inputs against those it was last laid out with, to find the nodes that need to be
+    /// laid out again
+    pub(crate) fn collect_layout_dirty(&mut self) {
+        let mut dirty = HashSet::new();
+        let root_id = self.root_node().id;
+        self.collect_layout_dirty_recursive(root_id, &mut dirty);
+
+        self.layout_stats = LayoutStats {
+            dirty_nodes: dirty.len(),
+            total_nodes: self.nodes.len(),
+            ..Default::default()
+        };
+        self.layout_dirty = dirty;
+    }
+
+    /// Returns whether the node or any of its descendants have changed
+    fn collect_layout_dirty_recursive(
+        &mut self,
+        node_id: usize,
+        dirty: &mut HashSet<usize>,
+    ) -> bool {
+        let signature = self.layout_signature(node_id);
+        let node = &mut self.nodes[node_id];
+        let mut changed = node.layout_signature != Some(signature);
+        node.layout_signature = Some(signature);
+
+        for child_id in node.children.clone() {
+            changed |= self.collect_layout_dirty_recursive(child_id, dirty);
+        }
+
+        if changed {
+            dirty.insert(node_id);
+        }
+        changed
+    }
+
+    /// A hash of a node's own layout inputs
+    fn layout_signature(&self, node_id: usize) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let node = &self.nodes[node_id];
+
+        node.children.hash(&mut hasher);
+        node.hidden.hash(&mut hasher);
+        node.in_top_layer.hash(&mut hasher);
+
+        // Restyled nodes are given a new style generation
+        node.primary_styles()
+            .map(|_| node.style_generation.load(Ordering::Relaxed))
+            .hash(&mut hasher);
+
+        match &node.raw_dom_data {
+            NodeData::Text(text) => text.content.hash(&mut hasher),
+            NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
+                for attr in element_data.attrs() {
+                    let value: &str = &attr.value;
+                    attr.name.local.hash(&mut hasher);
+                    value.hash(&mut hasher);
+                }
+                // Broken images are laid out with their alt text
+                element_data.broken_image.hash(&mut hasher);
+                // Images are laid out at their natural size, which doesn't change when they're decoded again
+                match &element_data.image_source {
+                    Some(source) => Some((source.width, source.height)).hash(&mut hasher),
+                    None => element_data
+                        .image
+                        .as_ref()
+                        .map(|image| (image.width(), image.height()))
+                        .hash(&mut hasher),
+                }
+            }
+            _ => {}
+        }
+
+        hasher.finish()
+    }
+}
+
+#[test]
+fn stable_layouts_are_skipped() {
+    use crate::test_util::parse_test_document;
+
+    let html =
+        "<!DOCTYPE html><html><body><div id=a>first</div><div id=b>second</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+
+    // Everything is laid out the first time
+    doc.resolve();
+    let stats = doc.layout_stats();
+    assert_eq!(stats.dirty_nodes, stats.total_nodes);
+
+    // Nothing has changed
+    doc.resolve();
+    assert_eq!(doc.layout_stats().dirty_nodes, 0);
+
+    // Changing the text of the first div only dirties it and its ancestors
+    let find_div = |doc: &Document, id: &str| {
+        doc.nodes
+            .iter()
+            .find(|(_, node)| node.attr(html5ever::local_name!("id")) == Some(id))
+            .map(|(node_id, _)| node_id)
+            .unwrap()
+    };
+    let a = find_div(&doc, "a");
+    let b = find_div(&doc, "b");
+    let text_id = doc.nodes[a].children[0];
+    if let NodeData::Text(text) = &mut doc.nodes[text_id].raw_dom_data {
+        text.content = String::from("changed");
+    }
+    let b_layout = doc.nodes[b].final_layout;
+    doc.resolve();
+
+    assert!(doc.is_layout_dirty(a));
+    assert!(!doc.is_layout_dirty(b));
+    assert!(doc.layout_stats().dirty_nodes < doc.layout_stats().total_nodes);
+    assert_eq!(doc.nodes[b].final_layout.size, b_layout.size);
+}
+
+#[test]
+fn restyled_nodes_are_dirty() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div id=b>second</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    // A new stylesheet restyles the div without touching its attributes or text
+    doc.add_stylesheet("#b { padding: 10px }");
+    doc.resolve();
+
+    let b = doc.get_element_by_id("b").unwrap();
+    assert!(doc.is_layout_dirty(b));
+    assert_eq!(doc.nodes[b].final_layout.padding.top, 10.0);
+}
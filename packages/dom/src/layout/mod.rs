@@ -9,18 +9,35 @@ use crate::{
     document::Document,
     image::{image_measure_function, ImageContext},
     node::Node,
+    stylo_to_parley,
 };
 use html5ever::local_name;
 use std::cell::Ref;
+use std::collections::HashMap;
 use taffy::{
     compute_block_layout, compute_cached_layout, compute_flexbox_layout, compute_grid_layout,
     compute_leaf_layout, prelude::*, Cache, FlexDirection, LayoutPartialTree, MaybeMath as _,
     MaybeResolve, NodeId, ResolveOrZero, RoundTree, Size, Style, TraversePartialTree, TraverseTree,
 };
 
+pub(crate) mod alt_text;
+pub use alt_text::ALT_TEXT_GAP;
+
 pub(crate) mod construct;
 pub(crate) use construct::collect_layout_children;
 
+pub(crate) mod incremental;
+pub use incremental::LayoutStats;
+
+pub(crate) mod text_cache;
+
+pub(crate) mod text_indent;
+use text_indent::TextIndent;
+pub use text_indent::TEXT_INDENT_BOX_ID;
+
+pub(crate) mod vertical_align;
+use vertical_align::{inline_box_top, LineBox, VerticalAlign};
+
 impl Document {
     fn node_from_id(&self, node_id: taffy::prelude::NodeId) -> &Node {
         &self.nodes[node_id.into()]
@@ -97,6 +114,7 @@ impl LayoutPartialTree for Document {
         inputs: taffy::tree::LayoutInput,
     ) -> taffy::tree::LayoutOutput {
         compute_cached_layout(self, node_id, inputs, |tree, node_id, inputs| {
+            let control_kind = tree.control_kind(usize::from(node_id));
             let node = tree.node_from_id_mut(node_id);
 
             match &mut node.raw_dom_data {
@@ -130,6 +148,9 @@ impl LayoutPartialTree for Document {
                 }
                 NodeData::Element(element_data) | NodeData::AnonymousBlock(element_data) => {
                     // Hide hidden nodes
+                    if node.hidden {
+                        return taffy::LayoutOutput::HIDDEN;
+                    }
                     if let Some("hidden" | "") = element_data.attr(local_name!("hidden")) {
                         node.style.display = Display::None;
                         return taffy::LayoutOutput::HIDDEN;
@@ -144,7 +165,8 @@ impl LayoutPartialTree for Document {
                         }
                     }
 
-                    if *element_data.name.local == *"img" {
+                    let is_video = *element_data.name.local == *"video";
+                    if *element_data.name.local == *"img" || is_video {
                         // Get width and height attributes on image element
                         //
                         // TODO: smarter sizing using these (depending on object-fit, they shouldn't
@@ -158,13 +180,23 @@ impl LayoutPartialTree for Document {
                                 .and_then(|val| val.parse::<f32>().ok()),
                         };
 
-                        // Get image's native size
-                        let inherent_size = match &element_data.image {
-                            Some(image) => taffy::Size {
+                        // Get image's native size (for videos this is the poster or the current frame)
+                        let inherent_size = match (&element_data.image_source, &element_data.image)
+                        {
+                            // Images are decoded after layout, so their natural size is read from the source
+                            (Some(source), _) => taffy::Size {
+                                width: source.width as f32,
+                                height: source.height as f32,
+                            },
+                            (None, Some(image)) => taffy::Size {
                                 width: image.width() as f32,
                                 height: image.height() as f32,
                             },
-                            None => taffy::Size {
+                            (None, None) if is_video => crate::video::DEFAULT_VIDEO_SIZE,
+                            (None, None) if element_data.broken_image => {
+                                crate::resource_errors::BROKEN_IMAGE_SIZE
+                            }
+                            (None, None) => taffy::Size {
                                 width: 0.0,
                                 height: 0.0,
                             },
@@ -175,6 +207,21 @@ impl LayoutPartialTree for Document {
                             attr_size,
                         };
 
+                        // Broken images show their alt text after the broken image icon
+                        let is_broken = element_data.broken_image
+                            && element_data.image_source.is_none()
+                            && element_data.image.is_none();
+                        let has_alt_text = element_data
+                            .attr(local_name!("alt"))
+                            .is_some_and(|alt| !alt.trim().is_empty());
+                        if is_broken && !is_video && has_alt_text {
+                            return tree.compute_alt_text_layout(
+                                usize::from(node_id),
+                                inputs,
+                                image_context,
+                            );
+                        }
+
                         let computed = compute_leaf_layout(
                             inputs,
                             &node.style,
@@ -192,6 +239,35 @@ impl LayoutPartialTree for Document {
                         return computed;
                     }
 
+                    if *element_data.name.local == *"iframe" {
+                        // The content of an iframe is a nested document which is laid out separately
+                        return compute_leaf_layout(
+                            inputs,
+                            &node.style,
+                            |known_dimensions, _available_space| taffy::Size {
+                                width: known_dimensions
+                                    .width
+                                    .unwrap_or(crate::iframe::DEFAULT_IFRAME_SIZE.width),
+                                height: known_dimensions
+                                    .height
+                                    .unwrap_or(crate::iframe::DEFAULT_IFRAME_SIZE.height),
+                            },
+                        );
+                    }
+
+                    if let Some(control_kind) = control_kind {
+                        // Controls are drawn by the renderer rather than by laying out their children
+                        let default_size = control_kind.default_size();
+                        return compute_leaf_layout(
+                            inputs,
+                            &node.style,
+                            |known_dimensions, _available_space| taffy::Size {
+                                width: known_dimensions.width.unwrap_or(default_size.width),
+                                height: known_dimensions.height.unwrap_or(default_size.height),
+                            },
+                        );
+                    }
+
                     if node.is_inline_root {
                         return tree.compute_inline_layout(node_id, inputs);
                     }
@@ -231,10 +307,22 @@ impl Document {
 
         // TODO: eliminate clone
         let style = self.nodes[usize::from(node_id)].style.clone();
+        let font_size = self.nodes[usize::from(node_id)]
+            .primary_styles()
+            .map_or(16.0, |s| s.get_font().font_size.used_size.0.px())
+            * scale;
+
+        // The baseline of each inline box, from the top of its margin box
+        let mut inline_box_baselines = HashMap::new();
 
         let output = compute_leaf_layout(inputs, &style, |_known_dimensions, available_space| {
             // Short circuit if inline context contains no text or inline boxes
-            if inline_layout.text.is_empty() && inline_layout.layout.inline_boxes().is_empty() {
+            let has_inline_boxes = inline_layout
+                .layout
+                .inline_boxes()
+                .iter()
+                .any(|ibox| ibox.id != TEXT_INDENT_BOX_ID);
+            if inline_layout.text.is_empty() && !has_inline_boxes {
                 return Size::ZERO;
             }
 
@@ -245,7 +333,19 @@ impl Document {
                 parent_size: available_space.into_options(),
                 ..inputs
             };
+            let text_indent = self.nodes[usize::from(node_id)]
+                .primary_styles()
+                .and_then(|s| TextIndent::from_style(&s))
+                .map_or(0.0, |text_indent| {
+                    text_indent.resolve(available_space.width.into_option().unwrap_or(0.0))
+                });
             for ibox in inline_layout.layout.inline_boxes_mut() {
+                if ibox.id == TEXT_INDENT_BOX_ID {
+                    ibox.width = text_indent * scale;
+                    ibox.height = 0.0;
+                    continue;
+                }
+
                 let output = self.compute_child_layout(NodeId::from(ibox.id), child_inputs);
 
                 let style = &self.nodes[ibox.id as usize].style;
@@ -253,6 +353,9 @@ impl Document {
 
                 ibox.width = (margin.left + margin.right + output.size.width) * scale;
                 ibox.height = (margin.top + margin.bottom + output.size.height) * scale;
+                if let Some(baseline) = output.first_baselines.y {
+                    inline_box_baselines.insert(ibox.id, (margin.top + baseline) * scale);
+                }
             }
 
             // Perform inline layout
@@ -262,29 +365,20 @@ impl Document {
                 AvailableSpace::MaxContent => None,
             };
 
+            // Lines only need to be broken again if the available width has changed
+            let bucket = text_cache::width_bucket(max_advance);
+            if inline_layout.line_break_bucket != Some(bucket) {
+                inline_layout.layout.break_all_lines(max_advance);
+                inline_layout.line_break_bucket = Some(bucket);
+            }
+
+            // A single line is also the last line, which `text-align-last` applies to
+            let single_line = inline_layout.layout.lines().count() == 1;
             let alignment = self.nodes[usize::from(node_id)]
                 .primary_styles()
-                .map(|s| {
-                    use parley::layout::Alignment;
-                    use style::values::specified::TextAlignKeyword;
-
-                    let itext_styles = (*s).get_inherited_text();
-                    match itext_styles.text_align {
-                        TextAlignKeyword::Start => Alignment::Start,
-                        TextAlignKeyword::Left => Alignment::Start,
-                        TextAlignKeyword::Right => Alignment::End,
-                        TextAlignKeyword::Center => Alignment::Middle,
-                        TextAlignKeyword::Justify => Alignment::Justified,
-                        TextAlignKeyword::End => Alignment::End,
-                        TextAlignKeyword::ServoCenter => Alignment::Middle,
-                        TextAlignKeyword::ServoLeft => Alignment::Start,
-                        TextAlignKeyword::ServoRight => Alignment::End,
-                    }
-                })
+                .map(|s| stylo_to_parley::text_alignment(&s, single_line))
                 .unwrap_or(parley::layout::Alignment::Start);
 
-            inline_layout.layout.break_all_lines(max_advance);
-
             let padding = style
                 .padding
                 .resolve_or_zero(inputs.parent_size)
@@ -330,8 +424,46 @@ impl Document {
 
             // Store sizes and positions of inline boxes
             for line in inline_layout.layout.lines() {
+                let metrics = line.metrics();
+                let (strut_ascent, strut_descent) = LineBox::strut_metrics(font_size);
+                let (text_ascent, text_descent) = line
+                    .items()
+                    .filter_map(|item| match item {
+                        parley::layout::LayoutItem2::GlyphRun(glyph_run) => {
+                            let run_metrics = glyph_run.run().metrics();
+                            Some((run_metrics.ascent, run_metrics.descent))
+                        }
+                        parley::layout::LayoutItem2::InlineBox(_) => None,
+                    })
+                    .reduce(|(a1, d1), (a2, d2)| (a1.max(a2), d1.max(d2)))
+                    .unwrap_or((strut_ascent, strut_descent));
+                let line_box = LineBox {
+                    top: metrics.baseline - metrics.ascent - metrics.leading / 2.0,
+                    bottom: metrics.baseline + metrics.descent + metrics.leading / 2.0,
+                    baseline: metrics.baseline,
+                    text_ascent,
+                    text_descent,
+                    font_size,
+                };
+
                 for item in line.items() {
                     if let parley::layout::LayoutItem2::InlineBox(ibox) = item {
+                        if ibox.id == TEXT_INDENT_BOX_ID {
+                            continue;
+                        }
+
+                        let align = self.nodes[ibox.id as usize]
+                            .primary_styles()
+                            .map_or(VerticalAlign::Baseline, |s| {
+                                VerticalAlign::from_style(&s, scale)
+                            });
+                        let y = inline_box_top(
+                            align,
+                            &line_box,
+                            ibox.height,
+                            inline_box_baselines.get(&ibox.id).copied(),
+                        );
+
                         let node = &mut self.nodes[ibox.id as usize];
                         let padding = node.style.padding.resolve_or_zero(child_inputs.parent_size);
                         let border = node.style.border.resolve_or_zero(child_inputs.parent_size);
@@ -341,7 +473,7 @@ impl Document {
                         layout.size.width = (ibox.width / scale) - margin.left - margin.right;
                         layout.size.height = (ibox.height / scale) - margin.top - margin.bottom;
                         layout.location.x = (ibox.x / scale) + margin.left;
-                        layout.location.y = (ibox.y / scale) + margin.top;
+                        layout.location.y = (y / scale) + margin.top;
                         layout.padding = padding; //.map(|p| p / scale);
                         layout.border = border; //.map(|p| p / scale);
                     }
@@ -360,6 +492,14 @@ impl Document {
             })
         });
 
+        // The first baseline is used to align the inline context with its siblings (e.g. as an inline block, or a
+        // flex item aligned by its baseline)
+        let first_baseline = inline_layout.layout.lines().next().map(|line| {
+            let padding = style.padding.resolve_or_zero(inputs.parent_size);
+            let border = style.border.resolve_or_zero(inputs.parent_size);
+            padding.top + border.top + line.metrics().baseline / scale
+        });
+
         // Put layout back
         self.nodes[usize::from(node_id)]
             .raw_dom_data
@@ -367,7 +507,13 @@ impl Document {
             .unwrap()
             .inline_layout = Some(inline_layout);
 
-        output
+        taffy::LayoutOutput {
+            first_baselines: taffy::Point {
+                x: None,
+                y: first_baseline,
+            },
+            ..output
+        }
     }
 }
 
@@ -388,6 +534,7 @@ impl PrintTree for Document {
 
         match node.raw_dom_data {
             NodeData::Document => "DOCUMENT",
+            NodeData::DocumentFragment => "DOCUMENT FRAGMENT",
             // NodeData::Doctype { .. } => return "DOCTYPE",
             NodeData::Text { .. } => node.node_debug_str().leak(),
             NodeData::Comment { .. } => "COMMENT",
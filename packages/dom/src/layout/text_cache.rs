@@ -0,0 +1,173 @@
+//! Caching of shaped text
+//!
+//! Shaping text into glyphs is the most expensive part of building an inline layout. Shaped layouts are cached, keyed
+//! on everything that affects shaping: the text, the resolved text style of each span (font family, size, weight,
+//! style, letter-spacing, line-height and color), whitespace collapsing, the inline boxes and the scale. Restyling
+//! or resizing the document only reshapes the inline contexts whose text or fonts have actually changed.
+//!
+//! A [`ShapingKey`] records these inputs in full rather than a hash of them, so two inline contexts only share a
+//! shaped layout if their inputs are equal.
+//!
+//! Breaking shaped text into lines depends on the available width, so it isn't cached here. Instead each layout
+//! remembers the width bucket its lines were last broken for, and skips breaking them again when it's measured at a
+//! width in the same bucket.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use parley::style::{FontSettings, FontStack, FontStyle};
+
+use crate::node::{TextBrush, TextLayout};
+
+/// How many shaped layouts are kept
+const CAPACITY: usize = 4096;
+
+/// Widths within a quarter of a pixel of each other break lines in the same places. `None` is an unbounded width.
+pub(crate) type WidthBucket = Option<i32>;
+
+pub(crate) fn width_bucket(max_advance: Option<f32>) -> WidthBucket {
+    max_advance.map(|width| (width * 4.0).round() as i32)
+}
+
+/// The inputs to shaping an inline layout, as the bytes that they are hashed to. Everything pushed to the layout
+/// builder is written to the key with [`Hash`].
+#[derive(Default, PartialEq, Eq, Hash)]
+pub(crate) struct ShapingKey(Vec<u8>);
+
+impl Hasher for ShapingKey {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&self.0);
+        hasher.finish()
+    }
+}
+
+/// Add a text style to a shaping key. Floats are added by their bits.
+pub(crate) fn hash_text_style(
+    style: &parley::style::TextStyle<'_, TextBrush>,
+    key: &mut ShapingKey,
+) {
+    match &style.font_stack {
+        FontStack::Source(source) => (0u8, source).hash(key),
+        FontStack::Single(family) => (1u8, family).hash(key),
+        FontStack::List(families) => (2u8, families).hash(key),
+    }
+    match style.font_style {
+        FontStyle::Normal => 0u8.hash(key),
+        FontStyle::Italic => 1u8.hash(key),
+        FontStyle::Oblique(angle) => (2u8, angle.map(f32::to_bits)).hash(key),
+    }
+    // Blitz doesn't support `font-variation-settings` or `font-feature-settings`, so these are always empty
+    debug_assert!(matches!(style.font_variations, FontSettings::List(&[])));
+    debug_assert!(matches!(style.font_features, FontSettings::List(&[])));
+
+    [
+        style.font_size,
+        style.font_stretch.ratio(),
+        style.font_weight.value(),
+        style.line_height,
+        style.word_spacing,
+        style.letter_spacing,
+    ]
+    .map(f32::to_bits)
+    .hash(key);
+    [
+        style.underline_offset,
+        style.underline_size,
+        style.strikethrough_offset,
+        style.strikethrough_size,
+    ]
+    .map(|value| value.map(f32::to_bits))
+    .hash(key);
+    style.locale.hash(key);
+
+    let color = |brush: &TextBrush| [brush.color.r, brush.color.g, brush.color.b, brush.color.a];
+    color(&style.brush).hash(key);
+    (
+        style.has_underline,
+        style.underline_brush.as_ref().map(color),
+    )
+        .hash(key);
+    (
+        style.has_strikethrough,
+        style.strikethrough_brush.as_ref().map(color),
+    )
+        .hash(key);
+}
+
+#[derive(Default)]
+pub(crate) struct ShapingCache {
+    entries: HashMap<ShapingKey, CachedText>,
+    /// Incremented on each layout pass, to evict the entries that haven't been used for longest
+    generation: u64,
+}
+
+struct CachedText {
+    layout: TextLayout,
+    last_used: u64,
+}
+
+impl ShapingCache {
+    pub(crate) fn get(&mut self, key: &ShapingKey) -> Option<TextLayout> {
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.generation;
+        Some(entry.layout.clone())
+    }
+
+    pub(crate) fn insert(&mut self, key: ShapingKey, layout: &TextLayout) {
+        self.entries.insert(
+            key,
+            CachedText {
+                layout: layout.clone(),
+                last_used: self.generation,
+            },
+        );
+    }
+
+    /// Start a new generation, evicting the least recently used half of the entries if the cache is full
+    pub(crate) fn sweep(&mut self) {
+        self.generation += 1;
+        if self.entries.len() <= CAPACITY {
+            return;
+        }
+
+        let mut last_used: Vec<u64> = self.entries.values().map(|entry| entry.last_used).collect();
+        last_used.sort_unstable();
+        let cutoff = last_used[last_used.len() / 2];
+        self.entries.retain(|_, entry| entry.last_used > cutoff);
+    }
+}
+
+#[test]
+fn keys_compare_style_fields() {
+    use parley::style::{FontFamily, TextStyle};
+
+    let key = |style: &TextStyle<'_, TextBrush>, text: &str| {
+        let mut key = ShapingKey::default();
+        hash_text_style(style, &mut key);
+        text.hash(&mut key);
+        key
+    };
+    let style = TextStyle {
+        font_stack: FontStack::List(&[FontFamily::Named("Inter")]),
+        font_size: 16.0,
+        ..Default::default()
+    };
+    let spaced = TextStyle {
+        letter_spacing: 0.5,
+        ..style.clone()
+    };
+    let renamed = TextStyle {
+        font_stack: FontStack::List(&[FontFamily::Named("Arial")]),
+        ..style.clone()
+    };
+
+    assert!(key(&style, "text") == key(&style.clone(), "text"));
+    assert!(key(&style, "text") != key(&style, "texts"));
+    assert!(key(&style, "text") != key(&spaced, "text"));
+    assert!(key(&style, "text") != key(&renamed, "text"));
+}
@@ -0,0 +1,65 @@
+//! Indenting the first line of block containers
+//!
+//! <https://drafts.csswg.org/css-text-3/#text-indent-property>
+//!
+//! Parley has no notion of indentation, so the first line of an inline formatting context is indented by an empty
+//! inline box at the start of its layout, which is as wide as the indent. With the `each-line` keyword, the lines
+//! after forced line breaks (`<br>` and preserved newlines) are indented by a box after each break too. Percentages
+//! are of the width of the block container.
+//!
+//! Note: Parley can't indent lines that start after a soft wrap, and inline boxes can't be narrower than zero, so
+//! hanging indents (the `hanging` keyword, which indents every line but the first, and negative indents, which pull
+//! the first line out into the margin) only leave the first line unindented. Text that shares its parent with block
+//! children is wrapped in an anonymous block, and isn't indented.
+use parley::{builder::TreeBuilder, InlineBox};
+use std::hash::Hash;
+use style::properties::ComputedValues;
+use style::values::computed::{Length, LengthPercentage};
+
+use super::text_cache::ShapingKey;
+use crate::node::TextBrush;
+
+/// The id of the inline boxes that indent lines, which (unlike other inline boxes) don't belong to a node
+pub const TEXT_INDENT_BOX_ID: u64 = u64::MAX;
+
+/// The `text-indent` of an inline formatting context
+pub(crate) struct TextIndent {
+    length: LengthPercentage,
+    /// Whether lines after forced line breaks are indented too
+    pub(crate) each_line: bool,
+}
+
+impl TextIndent {
+    /// The indent of the root of an inline formatting context, or `None` if its first line isn't indented
+    pub(crate) fn from_style(style: &ComputedValues) -> Option<Self> {
+        let text_indent = &style.get_inherited_text().text_indent;
+        if text_indent.hanging || text_indent.length.is_definitely_zero() {
+            return None;
+        }
+        Some(TextIndent {
+            length: text_indent.length.clone(),
+            each_line: text_indent.each_line,
+        })
+    }
+
+    /// The width of the indent, in a block container of the given width
+    pub(crate) fn resolve(&self, container_width: f32) -> f32 {
+        self.length
+            .resolve(Length::new(container_width))
+            .px()
+            .max(0.0)
+    }
+}
+
+/// Push an indent to an inline layout, whose width is set when the layout is measured
+pub(crate) fn push_text_indent(builder: &mut TreeBuilder<TextBrush>, key: &mut ShapingKey) {
+    "text-indent".hash(key);
+    builder.push_inline_box(InlineBox {
+        id: TEXT_INDENT_BOX_ID,
+        // Overridden by push_inline_box method
+        index: 0,
+        // Width is set during layout
+        width: 0.0,
+        height: 0.0,
+    });
+}
@@ -0,0 +1,142 @@
+//! Vertical alignment of inline boxes
+//!
+//! <https://drafts.csswg.org/css2/#propdef-vertical-align>
+//!
+//! Parley places each inline box (an image, form control or inline block) with the bottom of its margin box on the
+//! baseline, and sizes the line box to fit it there. Once lines have been broken and aligned, each box is moved to the
+//! position its `vertical-align` asks for, relative to the line's baseline, its text and its line box:
+//!
+//! - `baseline` aligns the box's own baseline (the first baseline of its content) with the line's, falling back to
+//!   the bottom of its margin box for boxes without one (such as images)
+//! - `sub`, `super` and lengths shift the box's baseline down or up, and percentages are of the box's `line-height`
+//! - `middle` centers the box on the baseline raised by half the x-height of the text around it
+//! - `text-top` and `text-bottom` align it with the top or bottom of the text around it
+//! - `top` and `bottom` align it with the top or bottom of the line box
+//!
+//! Note: Parley can't shift the baseline of text, so `vertical-align` only applies to inline boxes and not to inline
+//! elements such as `<sub>` and `<sup>`. Line boxes are also sized before boxes are moved, so a box that's aligned
+//! below the baseline can overlap the next line.
+use style::properties::ComputedValues;
+use style::values::computed::Length;
+use style::values::generics::box_::VerticalAlign as StyloVerticalAlign;
+use style::values::generics::box_::VerticalAlignKeyword;
+
+/// How far `vertical-align: sub` lowers a box, as a fraction of the font size of its line
+const SUBSCRIPT_OFFSET: f32 = 0.2;
+/// How far `vertical-align: super` raises a box, as a fraction of the font size of its line
+const SUPERSCRIPT_OFFSET: f32 = 0.34;
+/// The x-height of a typical font as a fraction of its size, as Parley doesn't expose fonts' x-heights
+const X_HEIGHT: f32 = 0.5;
+/// The ascent and descent of text in a typical font as a fraction of its size, for lines without text
+const ASCENT: f32 = 0.8;
+const DESCENT: f32 = 0.2;
+
+/// The `vertical-align` of an inline box, with lengths and percentages resolved to pixels (at the layout's scale)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum VerticalAlign {
+    Baseline,
+    Sub,
+    Super,
+    TextTop,
+    TextBottom,
+    Middle,
+    Top,
+    Bottom,
+    /// Raise the box's baseline by a distance (lowering it if negative)
+    Raise(f32),
+}
+
+impl VerticalAlign {
+    pub(crate) fn from_style(style: &ComputedValues, scale: f32) -> Self {
+        match &style.get_box().vertical_align {
+            StyloVerticalAlign::Keyword(keyword) => match keyword {
+                VerticalAlignKeyword::Baseline => VerticalAlign::Baseline,
+                VerticalAlignKeyword::Sub => VerticalAlign::Sub,
+                VerticalAlignKeyword::Super => VerticalAlign::Super,
+                VerticalAlignKeyword::Top => VerticalAlign::Top,
+                VerticalAlignKeyword::TextTop => VerticalAlign::TextTop,
+                VerticalAlignKeyword::Middle => VerticalAlign::Middle,
+                VerticalAlignKeyword::Bottom => VerticalAlign::Bottom,
+                VerticalAlignKeyword::TextBottom => VerticalAlign::TextBottom,
+            },
+            StyloVerticalAlign::Length(length) => {
+                // Percentages are of the box's own line height
+                let text_style = crate::stylo_to_parley::style(style);
+                let line_height = text_style.line_height * text_style.font_size;
+                VerticalAlign::Raise(length.resolve(Length::new(line_height)).px() * scale)
+            }
+        }
+    }
+}
+
+/// The metrics of a line that inline boxes are aligned against, in the coordinates of its layout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LineBox {
+    pub top: f32,
+    pub bottom: f32,
+    pub baseline: f32,
+    /// The ascent and descent of the line's text
+    pub text_ascent: f32,
+    pub text_descent: f32,
+    /// The font size of the line's inline formatting context
+    pub font_size: f32,
+}
+
+impl LineBox {
+    /// The text metrics of a line without text, which come from the font size of its inline formatting context (the
+    /// line's "strut")
+    pub(crate) fn strut_metrics(font_size: f32) -> (f32, f32) {
+        (font_size * ASCENT, font_size * DESCENT)
+    }
+}
+
+/// The top of an inline box's margin box. `height` is the height of its margin box, and `baseline` the distance
+/// from the top of its margin box to its baseline (if it has one).
+pub(crate) fn inline_box_top(
+    align: VerticalAlign,
+    line: &LineBox,
+    height: f32,
+    baseline: Option<f32>,
+) -> f32 {
+    let baseline = baseline.unwrap_or(height);
+    match align {
+        VerticalAlign::Baseline => line.baseline - baseline,
+        VerticalAlign::Sub => line.baseline + line.font_size * SUBSCRIPT_OFFSET - baseline,
+        VerticalAlign::Super => line.baseline - line.font_size * SUPERSCRIPT_OFFSET - baseline,
+        VerticalAlign::Raise(distance) => line.baseline - distance - baseline,
+        VerticalAlign::TextTop => line.baseline - line.text_ascent,
+        VerticalAlign::TextBottom => line.baseline + line.text_descent - height,
+        VerticalAlign::Middle => line.baseline - line.font_size * X_HEIGHT / 2.0 - height / 2.0,
+        VerticalAlign::Top => line.top,
+        VerticalAlign::Bottom => line.bottom - height,
+    }
+}
+
+#[test]
+fn aligns_inline_boxes_within_lines() {
+    let line = LineBox {
+        top: 0.0,
+        bottom: 40.0,
+        baseline: 30.0,
+        text_ascent: 13.0,
+        text_descent: 3.0,
+        font_size: 16.0,
+    };
+    let top = |align| inline_box_top(align, &line, 20.0, Some(15.0));
+
+    // Baselines line up, and boxes without a baseline sit on the line's
+    assert_eq!(top(VerticalAlign::Baseline), 15.0);
+    assert_eq!(
+        inline_box_top(VerticalAlign::Baseline, &line, 20.0, None),
+        10.0
+    );
+    assert_eq!(top(VerticalAlign::Raise(5.0)), 10.0);
+    assert!(top(VerticalAlign::Sub) > top(VerticalAlign::Baseline));
+    assert!(top(VerticalAlign::Super) < top(VerticalAlign::Baseline));
+
+    assert_eq!(top(VerticalAlign::TextTop), 17.0);
+    assert_eq!(top(VerticalAlign::TextBottom), 13.0);
+    assert_eq!(top(VerticalAlign::Middle), 30.0 - 4.0 - 10.0);
+    assert_eq!(top(VerticalAlign::Top), 0.0);
+    assert_eq!(top(VerticalAlign::Bottom), 20.0);
+}
@@ -20,6 +20,9 @@ pub mod layout;
 /// A collection of methods for manipulating the DOM.
 pub mod mutation;
 
+/// Reconciling keyed lists of children with as few moves as possible.
+pub mod keyed;
+
 /// The nodes themsleves, and their data.
 ///
 /// todo: we want this to use ECS, but we're not done with the design yet.
@@ -34,10 +37,216 @@ pub mod stylo_to_taffy;
 
 pub mod image;
 
+/// Support for `<iframe>` elements, each of which owns a nested document.
+pub mod iframe;
+
+/// Rendering state and interaction for `<progress>`, `<meter>` and `<input type=range>`.
+pub mod controls;
+
+/// Light and dark color schemes, and the palette form controls are drawn with.
+pub mod color_scheme;
+
+/// Date and color picker popups, and embedder-provided file dialogs, for `<input>` elements.
+pub mod pickers;
+
+/// Constraint validation of form controls.
+pub mod validation;
+
+/// Caret placement and rich text editing of `contenteditable` elements.
+pub mod editing;
+
+/// Searching the visible text of a document (find-in-page).
+pub mod find;
+
+/// Activating elements with the keys given by their `accesskey` attribute.
+pub mod access_keys;
+
+/// Keyboard focus, and the elements that can be focused.
+pub mod focus;
+
+/// Moving focus to the nearest focusable element in a direction, for arrow keys and d-pads.
+pub mod spatial_navigation;
+
+/// The `contextmenu` event's default action: a menu of actions on the right clicked element.
+pub mod context_menu;
+
+/// Approximate rendering of MathML rows, fractions, scripts and radicals.
+pub mod mathml;
+
+/// Support for `<video>` elements backed by embedder-provided decoders.
+pub mod video;
+
 pub mod util;
 
 pub mod events;
 
+/// The HTML popover API.
+pub mod popover;
+
+/// Positioning popovers and dialogs relative to their anchor elements.
+pub mod anchor;
+
+/// Adding and removing the classes of elements.
+pub mod class_list;
+
+/// Reading and writing the `data-*` attributes of elements.
+pub mod dataset;
+
+/// Stylesheets constructed once and adopted by several documents.
+pub mod adopted_stylesheets;
+
+/// Inspecting the order of cascade layers.
+pub mod cascade_layers;
+
+/// The user agent and user stylesheets, which apply at their own cascade origins.
+pub mod ua_stylesheets;
+
+/// Editing the rules of the document's stylesheets.
+pub mod cssom;
+
+/// Expanding tab characters to the next tab stop.
+pub mod tab_size;
+
+/// Metric overrides of `@font-face` rules that alias local fonts, to reduce layout shift when web fonts load.
+pub mod font_faces;
+
+/// Setting individual properties of the inline styles of elements.
+pub mod inline_style;
+
+/// Custom elements defined by the embedder, and their lifecycle callbacks.
+pub mod custom_elements;
+
+/// The inert contents of `<template>` elements.
+pub mod templates;
+
+/// The viewport configuration requested by `<meta name=viewport>` elements.
+pub mod meta_viewport;
+
+/// The title, icon and theme color of a document, for embedders to show in their window's chrome.
+pub mod metadata;
+
+/// Timing the phases of each frame, and tracing them with `tracing` spans.
+pub mod profiling;
+
+/// JSON snapshots of the tree, layout and computed styles of a document, and golden file tests built on them.
+pub mod tree_dump;
+
+/// Navigating to other documents, including `<meta http-equiv=refresh>`.
+pub mod navigation;
+
+/// Capturing and restoring scroll positions, form values and the caret across reloads.
+pub mod session;
+
+/// Detecting the character encoding of fetched documents.
+pub mod charset;
+
+/// Reporting the resources that a document couldn't load.
+pub mod resource_errors;
+
+/// Fetching resources with a configurable policy for redirects, timeouts, retries and per-host limits.
+pub mod network;
+
+/// Making HTTP requests with ureq, when built with the `network` feature.
+#[cfg(feature = "network")]
+mod http;
+
+/// Reloading stylesheets and images loaded from files when they change.
+pub mod hot_reload;
+
+/// Storing cookies and attaching them to requests.
+pub mod cookies;
+
+/// Content Security Policy enforcement for images, stylesheets and fonts.
+pub mod csp;
+
+/// Removing scripts and other unsafe content from untrusted HTML while it's parsed.
+pub mod sanitize;
+
+/// The `clip-path` basic shapes that nodes are clipped to.
+pub mod clip_path;
+
+/// Querying the geometry of laid out nodes.
+pub mod geometry;
+
+/// The margin, border, padding and content boxes of laid out nodes, for devtools overlays.
+pub mod box_model;
+
+/// Visibility callbacks for nodes scrolling into and out of the viewport.
+pub mod intersection_observer;
+
+/// Callbacks for nodes changing size between layouts.
+pub mod resize_observer;
+
+/// Batched notifications of changes to the document tree.
+pub mod mutation_observer;
+
+/// Decoding images at the size they're displayed at, under a memory limit.
+pub mod decoded_images;
+
+/// Rasterizing SVG images on a worker thread.
+pub mod svg_images;
+
+/// Decoding ICO, AVIF and JPEG XL images.
+pub mod image_formats;
+
+/// The headings and landmarks of a document.
+pub mod outline;
+
+/// The colors of the selection and the caret.
+pub mod selection_style;
+
+/// Undoing and redoing edits.
+pub mod undo;
+
+/// Suggestions for inputs with a `<datalist>`.
+pub mod datalist;
+
+/// The spin buttons of number inputs.
+pub mod number_input;
+
+/// Which virtual keyboard a text field wants.
+pub mod input_mode;
+
+/// Masking the value of password inputs.
+pub mod password;
+
+/// Editing the value of text fields, with their selection and maximum length.
+pub mod text_input;
+
+/// Labels of form controls and accessible names.
+pub mod labels;
+
+/// Fieldsets, their legends, and disabled form controls.
+pub mod fieldset;
+
+/// Buttons, and submitting and resetting forms.
+pub mod forms;
+
+/// The `:active` state of elements being pressed.
+pub mod active;
+
+/// Tracking which regions of the viewport need to be repainted.
+pub mod damage;
+
+/// Scheduling frames and running animation frame callbacks.
+pub mod scheduler;
+
+/// Smooth scrolling of the viewport.
+pub mod scrolling;
+
+/// The part of the viewport that isn't covered by the soft keyboard or system bars.
+pub mod visual_viewport;
+
+/// Delayed and repeating callbacks run on the frame loop.
+pub mod timers;
+
+/// The top layer, in which popovers, modal dialogs and fullscreen elements are rendered above everything else.
+pub mod top_layer;
+
+/// Setting up documents for tests.
+#[cfg(test)]
+mod test_util;
+
 pub use document::{Document, DocumentLike};
 pub use html5ever::{
     local_name, namespace_prefix, namespace_url, ns, Namespace, NamespaceStaticSet, Prefix,
@@ -0,0 +1,57 @@
+//! MathML
+//!
+//! <https://w3c.github.io/mathml-core/>
+//!
+//! Blitz has no dedicated math layout. Instead the default user agent stylesheet approximates MathML Core for the
+//! common elements with CSS: rows (`mrow`) are inline flex boxes aligned on their baselines, fractions (`mfrac`)
+//! stack their numerator over a rule and the denominator, scripts (`msup`, `msub` and `msubsup`) are shrunk and
+//! shifted off the baseline, and square roots (`msqrt` and `mroot`) have an overbar drawn by their top border. The
+//! stylesheet reserves left padding for the radical sign, which the renderer draws (see
+//! [`Document::is_radical`]), as the servo configuration of stylo has no generated content to draw it with.
+use html5ever::{namespace_url, ns};
+
+use crate::{Document, Node};
+
+impl Node {
+    /// Whether the node is an element in the MathML namespace
+    pub fn is_mathml_element(&self) -> bool {
+        self.element_data()
+            .is_some_and(|element_data| element_data.name.ns == ns!(mathml))
+    }
+}
+
+impl Document {
+    /// Whether a node is a MathML radical (`msqrt` or `mroot`), whose radical sign is drawn in its left padding
+    pub fn is_radical(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        node.is_mathml_element()
+            && node
+                .element_data()
+                .is_some_and(|element_data| matches!(&*element_data.name.local, "msqrt" | "mroot"))
+    }
+}
+
+#[test]
+fn matches_mathml_elements() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><head><style>\
+        @namespace math url(http://www.w3.org/1998/Math/MathML); \
+        math|mfrac { display: flex } \
+        </style></head><body><mfrac id=html-frac></mfrac>\
+        <math><mfrac id=frac><mi>x</mi><mn>2</mn></mfrac><msqrt id=sqrt><mi>y</mi></msqrt></math>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let id = |id: &str| doc.get_element_by_id(id).unwrap();
+    assert!(doc.nodes[id("frac")].is_mathml_element());
+    assert!(!doc.nodes[id("html-frac")].is_mathml_element());
+    assert_eq!(doc.nodes[id("frac")].style.display, taffy::Display::Flex);
+    assert_ne!(
+        doc.nodes[id("html-frac")].style.display,
+        taffy::Display::Flex
+    );
+    assert!(doc.is_radical(id("sqrt")));
+    assert!(!doc.is_radical(id("frac")));
+}
@@ -0,0 +1,158 @@
+//! The viewport configuration requested by `<meta name=viewport>` elements
+//!
+//! Pages written for mobile devices declare the width they should be laid out at and how far they should be scaled,
+//! e.g. `<meta name="viewport" content="width=device-width, initial-scale=1">`. The document only parses the
+//! configuration: it's up to the renderer to apply it to the size and scale of the viewport that it lays the
+//! document out with.
+use html5ever::local_name;
+
+use crate::Document;
+
+/// The smallest scale a page can request
+pub const MIN_VIEWPORT_SCALE: f32 = 0.1;
+/// The largest scale a page can request
+pub const MAX_VIEWPORT_SCALE: f32 = 10.0;
+
+/// The width of the layout viewport requested by a page
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewportWidth {
+    /// `width=device-width`: the width of the window
+    DeviceWidth,
+    /// A fixed width in CSS pixels
+    Px(f32),
+}
+
+/// The configuration of a `<meta name=viewport>` element
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewportMeta {
+    /// The width of the layout viewport (`width`)
+    pub width: Option<ViewportWidth>,
+    /// The scale the page is first shown at (`initial-scale`)
+    pub initial_scale: Option<f32>,
+    /// The smallest scale the page can be zoomed to (`minimum-scale`)
+    pub minimum_scale: f32,
+    /// The largest scale the page can be zoomed to (`maximum-scale`)
+    pub maximum_scale: f32,
+    /// Whether the user can zoom the page (`user-scalable`)
+    pub user_scalable: bool,
+}
+
+impl Default for ViewportMeta {
+    fn default() -> Self {
+        Self {
+            width: None,
+            initial_scale: None,
+            minimum_scale: MIN_VIEWPORT_SCALE,
+            maximum_scale: MAX_VIEWPORT_SCALE,
+            user_scalable: true,
+        }
+    }
+}
+
+impl ViewportMeta {
+    /// Parse the `content` attribute of a `<meta name=viewport>` element
+    ///
+    /// Properties are separated by commas (or semicolons), and unknown properties and invalid values are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut meta = Self::default();
+        for property in content.split([',', ';']) {
+            let Some((name, value)) = property.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "width" => {
+                    meta.width = if value.eq_ignore_ascii_case("device-width") {
+                        Some(ViewportWidth::DeviceWidth)
+                    } else {
+                        parse_number(value)
+                            .map(|width| ViewportWidth::Px(width.clamp(1.0, 10000.0)))
+                    }
+                }
+                "initial-scale" => meta.initial_scale = parse_scale(value),
+                "minimum-scale" => {
+                    meta.minimum_scale = parse_scale(value).unwrap_or(MIN_VIEWPORT_SCALE)
+                }
+                "maximum-scale" => {
+                    meta.maximum_scale = parse_scale(value).unwrap_or(MAX_VIEWPORT_SCALE)
+                }
+                "user-scalable" => {
+                    meta.user_scalable = match value.to_ascii_lowercase().as_str() {
+                        "yes" => true,
+                        "no" => false,
+                        other => parse_number(other).map_or(false, |n| n.abs() >= 1.0),
+                    }
+                }
+                _ => {}
+            }
+        }
+        meta.maximum_scale = meta.maximum_scale.max(meta.minimum_scale);
+        meta
+    }
+
+    /// The scale the page is shown at in a window `window_width` CSS pixels wide, after the user has zoomed it by
+    /// `zoom`
+    ///
+    /// Pages with a fixed width and no initial scale are scaled to fit the window.
+    pub fn scale(&self, window_width: f32, zoom: f32) -> f32 {
+        let initial_scale = match (self.initial_scale, self.width) {
+            (Some(scale), _) => scale,
+            (None, Some(ViewportWidth::Px(width))) => window_width / width,
+            (None, _) => 1.0,
+        };
+        let zoom = if self.user_scalable { zoom } else { 1.0 };
+        (initial_scale * zoom).clamp(self.minimum_scale, self.maximum_scale)
+    }
+
+    /// The width of the layout viewport in CSS pixels, for a window `window_width` CSS pixels wide shown at `scale`
+    pub fn layout_width(&self, window_width: f32, scale: f32) -> f32 {
+        let visible_width = window_width / scale;
+        match self.width {
+            Some(ViewportWidth::Px(width)) => width.max(visible_width),
+            _ => visible_width,
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+fn parse_scale(value: &str) -> Option<f32> {
+    parse_number(value).map(|scale| scale.clamp(MIN_VIEWPORT_SCALE, MAX_VIEWPORT_SCALE))
+}
+
+impl Document {
+    /// The viewport configuration of the document's last `<meta name=viewport>` element (if it has one)
+    pub fn viewport_meta(&self) -> Option<ViewportMeta> {
+        self.get_elements_by_tag_name(local_name!("meta"))
+            .into_iter()
+            .filter_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                let name = element.attr(local_name!("name"))?;
+                if !name.trim().eq_ignore_ascii_case("viewport") {
+                    return None;
+                }
+                Some(ViewportMeta::parse(element.attr(local_name!("content"))?))
+            })
+            .last()
+    }
+}
+
+#[test]
+fn parses_viewport_meta_content() {
+    let meta = ViewportMeta::parse(
+        "width=device-width, initial-scale=2; maximum-scale=3, user-scalable=no",
+    );
+    assert_eq!(meta.width, Some(ViewportWidth::DeviceWidth));
+    assert_eq!(meta.initial_scale, Some(2.0));
+    assert_eq!(meta.maximum_scale, 3.0);
+    assert!(!meta.user_scalable);
+    // Zooming is disabled, so the page is always shown at its initial scale
+    assert_eq!(meta.scale(400.0, 1.5), 2.0);
+
+    // A fixed width page is scaled to fit the window
+    let meta = ViewportMeta::parse("width=980");
+    assert_eq!(meta.scale(490.0, 1.0), 0.5);
+    assert_eq!(meta.layout_width(490.0, 0.5), 980.0);
+}
@@ -0,0 +1,172 @@
+//! The title, icon and theme color of a document
+//!
+//! These come from the document's `<title>`, `<link rel=icon>` and `<meta name=theme-color>` elements. They aren't
+//! shown by the document itself: embedders show them in their window's chrome. The metadata is collected each time
+//! the document is resolved, and the embedder's [`MetadataHandler`] is called whenever it has changed.
+use html5ever::local_name;
+use image::imageops::FilterType;
+use peniko::Color;
+use url::Url;
+
+use crate::util::ImageFetchErr;
+use crate::Document;
+
+/// Icons are scaled down to fit within this size, which is larger than any window icon
+const MAX_ICON_SIZE: u32 = 256;
+
+/// The metadata of a document
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentMetadata {
+    /// The text of the first `<title>` element, with its whitespace collapsed
+    pub title: Option<String>,
+    /// The resolved URL of the last `<link rel=icon>` element
+    pub icon: Option<Url>,
+    /// The color of the first `<meta name=theme-color>` element with a valid color
+    pub theme_color: Option<Color>,
+}
+
+/// An icon decoded to 8-bit RGBA pixels, ready to be given to a windowing library
+#[derive(Clone, Debug)]
+pub struct Favicon {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A handler for changes to a document's metadata, set with [`Document::set_metadata_handler`]
+pub type MetadataHandler = Box<dyn FnMut(&Document, &DocumentMetadata) + Send>;
+
+impl Document {
+    /// The title, icon and theme color of the document
+    pub fn metadata(&self) -> DocumentMetadata {
+        DocumentMetadata {
+            title: self.title(),
+            icon: self.icon_url(),
+            theme_color: self.theme_color(),
+        }
+    }
+
+    /// The text of the document's first `<title>` element (if it has one)
+    pub fn title(&self) -> Option<String> {
+        let title_id = *self
+            .get_elements_by_tag_name(local_name!("title"))
+            .first()?;
+        let text = self.nodes[title_id].text_content();
+        Some(text.split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// The URL of the icon given by the document's last `<link rel=icon>` element (if it has one)
+    pub fn icon_url(&self) -> Option<Url> {
+        self.get_elements_by_tag_name(local_name!("link"))
+            .into_iter()
+            .filter_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                let is_icon = element
+                    .attr(local_name!("rel"))?
+                    .split_ascii_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("icon"));
+                if !is_icon {
+                    return None;
+                }
+                self.resolve_url(element.attr(local_name!("href"))?).ok()
+            })
+            .last()
+    }
+
+    /// The color of the document's first `<meta name=theme-color>` element (if it has one)
+    pub fn theme_color(&self) -> Option<Color> {
+        self.get_elements_by_tag_name(local_name!("meta"))
+            .into_iter()
+            .find_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                let name = element.attr(local_name!("name"))?;
+                if !name.trim().eq_ignore_ascii_case("theme-color") {
+                    return None;
+                }
+                Color::parse(element.attr(local_name!("content"))?.trim())
+            })
+    }
+
+    /// Fetch and decode an icon, scaling it down if it's very large
+    pub fn load_favicon(&self, url: &Url) -> Result<Favicon, ImageFetchErr> {
+        let mut image = crate::util::fetch_image(
+            &self.network,
+            url.as_str(),
+            self.base_url.as_ref(),
+            Some((MAX_ICON_SIZE, MAX_ICON_SIZE)),
+        )?;
+        if image.width() > MAX_ICON_SIZE || image.height() > MAX_ICON_SIZE {
+            image = image.resize(MAX_ICON_SIZE, MAX_ICON_SIZE, FilterType::Triangle);
+        }
+        let image = image.into_rgba8();
+        Ok(Favicon {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        })
+    }
+
+    /// Set the handler that is called with the document's metadata whenever it changes. It is also called the next
+    /// time the document is resolved, so that the embedder is told about the current metadata.
+    pub fn set_metadata_handler(
+        &mut self,
+        handler: impl FnMut(&Document, &DocumentMetadata) + Send + 'static,
+    ) {
+        self.metadata_handler = Some(Box::new(handler));
+        self.metadata = None;
+    }
+
+    /// Call the metadata handler if the metadata has changed since it was last called
+    pub(crate) fn flush_metadata(&mut self) {
+        if self.metadata_handler.is_none() {
+            return;
+        }
+        let metadata = self.metadata();
+        if self.metadata.as_ref() == Some(&metadata) {
+            return;
+        }
+
+        // The handler is taken out of the document so that it can be given the document
+        let mut handler = self.metadata_handler.take().unwrap();
+        handler(self, &metadata);
+        self.metadata_handler.get_or_insert(handler);
+        self.metadata = Some(metadata);
+    }
+}
+
+#[test]
+fn reports_title_icon_and_theme_color() {
+    use crate::test_util::{parse_html, test_document};
+    use std::sync::{Arc, Mutex};
+
+    let html = "<!DOCTYPE html><html><head>\
+        <title>  Hello\n  world </title>\
+        <link rel=icon href=first.png><link rel='shortcut ICON' href=/favicon.ico>\
+        <meta name=theme-color content=nonsense><meta name=theme-color content='#ff0000'>\
+        </head><body></body></html>";
+    let mut doc = test_document(&[]);
+    doc.set_base_url("https://example.com/docs/page.html");
+    parse_html(&mut doc, html);
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let log = reported.clone();
+    doc.set_metadata_handler(move |_, metadata| log.lock().unwrap().push(metadata.clone()));
+    doc.resolve();
+    doc.resolve();
+
+    // The handler is only called again once the metadata changes
+    let title_id = doc.get_elements_by_tag_name(local_name!("title"))[0];
+    let text_id = doc.nodes[title_id].children[0];
+    doc.nodes[text_id].text_data_mut().unwrap().content = "Renamed".to_string();
+    doc.resolve();
+
+    let reported = reported.lock().unwrap();
+    assert_eq!(reported.len(), 2);
+    assert_eq!(reported[0].title.as_deref(), Some("Hello world"));
+    assert_eq!(
+        reported[0].icon.as_ref().map(Url::as_str),
+        Some("https://example.com/favicon.ico")
+    );
+    assert_eq!(reported[0].theme_color, Some(Color::rgb8(0xff, 0, 0)));
+    assert_eq!(reported[1].title.as_deref(), Some("Renamed"));
+}
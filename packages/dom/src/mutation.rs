@@ -2,3 +2,302 @@
 //!
 //! We need to track changes the dom while they happen
 //! They will mark nodes as damaged but not affect rendering until the next frame happens
+//!
+//! UI frameworks that diff a virtual DOM produce their changes as a list of [`Mutation`]s, which
+//! [`Document::apply_mutations`] applies as a batch: each parent whose children change is restyled (and reported to
+//! mutation observers) once, however many of its children were added, moved or removed, and the document is then
+//! restyled and laid out in a single pass.
+use std::collections::HashMap;
+
+use html5ever::{local_name, LocalName, QualName};
+
+use crate::node::{Attribute, ElementNodeData, NodeData};
+use crate::Document;
+
+/// A node that a mutation applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeRef {
+    /// A node that is already in the document
+    Node(usize),
+    /// The node created by the `n`th creating mutation (counting from zero) of the batch
+    Created(usize),
+}
+
+/// A change to the document, applied with [`Document::apply_mutations`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mutation {
+    /// Create a detached element
+    CreateElement {
+        name: QualName,
+        attrs: Vec<(QualName, String)>,
+    },
+    /// Create a detached text node
+    CreateText {
+        text: String,
+    },
+    /// Set the value of an attribute, adding it if it doesn't exist
+    SetAttribute {
+        node: NodeRef,
+        name: QualName,
+        value: String,
+    },
+    RemoveAttribute {
+        node: NodeRef,
+        name: LocalName,
+    },
+    /// Replace the content of a text node
+    SetText {
+        node: NodeRef,
+        text: String,
+    },
+    /// Append nodes to the children of `parent`, moving them if they're already in the tree
+    AppendChildren {
+        parent: NodeRef,
+        children: Vec<NodeRef>,
+    },
+    /// Insert nodes before `anchor`, moving them if they're already in the tree
+    InsertBefore {
+        anchor: NodeRef,
+        nodes: Vec<NodeRef>,
+    },
+    /// Remove a node and its descendants from the document
+    Remove {
+        node: NodeRef,
+    },
+}
+
+/// The children added to and removed from a parent during a batch of mutations
+#[derive(Default)]
+struct ChildListChange {
+    added: Vec<usize>,
+    removed: Vec<usize>,
+}
+
+/// The parents whose children change during a batch of mutations, in the order they were first changed
+#[derive(Default)]
+struct ChildListChanges {
+    parents: Vec<usize>,
+    changes: HashMap<usize, ChildListChange>,
+}
+
+impl ChildListChanges {
+    fn parent(&mut self, parent_id: usize) -> &mut ChildListChange {
+        self.changes.entry(parent_id).or_insert_with(|| {
+            self.parents.push(parent_id);
+            ChildListChange::default()
+        })
+    }
+}
+
+impl Document {
+    /// Apply a batch of mutations, then restyle and relayout the document. Returns the ids of the nodes created by
+    /// the batch, in the order they were created.
+    ///
+    /// Mutations that refer to nodes that don't exist (or to nodes of the wrong kind) are ignored.
+    pub fn apply_mutations(&mut self, mutations: Vec<Mutation>) -> Vec<usize> {
+        let mut created = Vec::new();
+        let mut child_lists = ChildListChanges::default();
+
+        for mutation in mutations {
+            let resolve = |doc: &Document, node: NodeRef| {
+                let node_id = match node {
+                    NodeRef::Node(node_id) => node_id,
+                    NodeRef::Created(index) => *created.get(index)?,
+                };
+                doc.nodes.contains(node_id).then_some(node_id)
+            };
+
+            match mutation {
+                Mutation::CreateElement { name, attrs } => {
+                    let attrs = attrs
+                        .into_iter()
+                        .map(|(name, value)| Attribute { name, value })
+                        .collect();
+                    let mut data = ElementNodeData::new(name, attrs);
+                    data.flush_style_attribute(self.guard());
+                    let node_id = self.create_node(NodeData::Element(data));
+                    *self.nodes[node_id].stylo_element_data.borrow_mut() = Some(Default::default());
+                    created.push(node_id);
+                }
+                Mutation::CreateText { text } => created.push(self.create_text_node(&text)),
+                Mutation::SetAttribute { node, name, value } => {
+                    if let Some(node_id) = resolve(self, node) {
+                        self.set_attribute(node_id, name, &value);
+                    }
+                }
+                Mutation::RemoveAttribute { node, name } => {
+                    if let Some(node_id) = resolve(self, node) {
+                        self.remove_attribute(node_id, &name);
+                    }
+                }
+                Mutation::SetText { node, text } => {
+                    if let Some(node_id) = resolve(self, node) {
+                        self.set_text(node_id, text);
+                    }
+                }
+                Mutation::AppendChildren { parent, children } => {
+                    let Some(parent_id) = resolve(self, parent) else {
+                        continue;
+                    };
+                    for child in children {
+                        let Some(child_id) = resolve(self, child) else {
+                            continue;
+                        };
+                        if let Some(old_parent_id) = self.detach(child_id) {
+                            child_lists.parent(old_parent_id).removed.push(child_id);
+                        }
+                        let parent = &mut self.nodes[parent_id];
+                        let child_idx = parent.children.len();
+                        parent.children.push(child_id);
+                        let child = &mut self.nodes[child_id];
+                        child.parent = Some(parent_id);
+                        child.child_idx = child_idx;
+                        child_lists.parent(parent_id).added.push(child_id);
+                    }
+                }
+                Mutation::InsertBefore { anchor, nodes } => {
+                    let Some(anchor_id) = resolve(self, anchor) else {
+                        continue;
+                    };
+                    for node in nodes {
+                        let Some(node_id) = resolve(self, node) else {
+                            continue;
+                        };
+                        if node_id == anchor_id {
+                            continue;
+                        }
+                        let Some(parent_id) = self.nodes[anchor_id].parent else {
+                            break;
+                        };
+                        if let Some(old_parent_id) = self.detach(node_id) {
+                            child_lists.parent(old_parent_id).removed.push(node_id);
+                        }
+                        let anchor_idx = self.nodes[anchor_id].child_idx;
+                        self.nodes[parent_id].children.insert(anchor_idx, node_id);
+                        self.nodes[node_id].parent = Some(parent_id);
+                        self.flush_child_idx(parent_id, anchor_idx);
+                        child_lists.parent(parent_id).added.push(node_id);
+                    }
+                }
+                Mutation::Remove { node } => {
+                    let Some(node_id) = resolve(self, node) else {
+                        continue;
+                    };
+                    if let Some(parent_id) = self.detach(node_id) {
+                        child_lists.parent(parent_id).removed.push(node_id);
+                    }
+                    self.remove_node(node_id);
+                }
+            }
+        }
+
+        // Each parent is restyled and reported to mutation observers once. Nodes that were added and then removed
+        // within the batch no longer exist.
+        for parent_id in child_lists.parents {
+            if !self.nodes.contains(parent_id) {
+                continue;
+            }
+            let mut change = child_lists.changes.remove(&parent_id).unwrap();
+            change.added.retain(|&node_id| {
+                self.nodes.contains(node_id) && self.nodes[node_id].parent == Some(parent_id)
+            });
+            self.child_list_changed(parent_id, &change.added, &change.removed);
+        }
+
+        self.resolve();
+        created
+    }
+
+    /// Replace the content of a text node. If it's the content of a `<style>` element, the element's stylesheet is
+    /// replaced too.
+    pub fn set_text(&mut self, node_id: usize, text: String) {
+        match self.nodes[node_id].text_data() {
+            Some(data) if data.content != text => {}
+            _ => return,
+        }
+        let style_id = self.nodes[node_id].parent.filter(|&parent_id| {
+            self.nodes[parent_id].is_element_with_tag_name(&local_name!("style"))
+        });
+        let old_css = style_id.map(|style_id| self.nodes[style_id].text_content());
+
+        self.record_text_change(node_id);
+        self.nodes[node_id].text_data_mut().unwrap().content = text;
+
+        if let (Some(style_id), Some(old_css)) = (style_id, old_css) {
+            self.remove_stylehsheet(&html_escape::decode_html_entities(&old_css));
+            self.process_style_element(style_id);
+        }
+    }
+
+    /// Remove a node from its parent's children without removing it from the document. Returns the parent (if it had
+    /// one).
+    fn detach(&mut self, node_id: usize) -> Option<usize> {
+        let parent_id = self.nodes[node_id].parent.take()?;
+        let child_idx = self.nodes[node_id].child_idx;
+        self.nodes[parent_id].children.remove(child_idx);
+        self.flush_child_idx(parent_id, child_idx);
+        Some(parent_id)
+    }
+
+    /// Update the `child_idx` of a node's children from `start` onwards
+    fn flush_child_idx(&mut self, parent_id: usize, start: usize) {
+        for child_idx in start..self.nodes[parent_id].children.len() {
+            let child_id = self.nodes[parent_id].children[child_idx];
+            self.nodes[child_id].child_idx = child_idx;
+        }
+    }
+}
+
+#[test]
+fn applies_a_batch_of_mutations() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{namespace_url, ns};
+
+    let html = "<!DOCTYPE html><html><head><style>#list { display: block }</style></head>\
+        <body><div id=list><p id=a>a</p><p id=b>b</p></div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let list_id = doc.get_element_by_id("list").unwrap();
+    let a_id = doc.get_element_by_id("a").unwrap();
+    let b_id = doc.get_element_by_id("b").unwrap();
+    let style_id = doc.get_elements_by_tag_name(local_name!("style"))[0];
+    let style_text_id = doc.nodes[style_id].children[0];
+    let name = |local: &str| QualName::new(None, ns!(html), LocalName::from(local));
+
+    let created = doc.apply_mutations(vec![
+        Mutation::CreateElement {
+            name: name("span"),
+            attrs: vec![(QualName::new(None, ns!(), local_name!("id")), "c".into())],
+        },
+        Mutation::CreateText { text: "c".into() },
+        Mutation::AppendChildren {
+            parent: NodeRef::Created(0),
+            children: vec![NodeRef::Created(1)],
+        },
+        // Move b before a, then append the new element
+        Mutation::InsertBefore {
+            anchor: NodeRef::Node(a_id),
+            nodes: vec![NodeRef::Node(b_id)],
+        },
+        Mutation::AppendChildren {
+            parent: NodeRef::Node(list_id),
+            children: vec![NodeRef::Created(0)],
+        },
+        Mutation::Remove {
+            node: NodeRef::Node(a_id),
+        },
+        Mutation::SetText {
+            node: NodeRef::Node(style_text_id),
+            text: "#list { display: flex }".into(),
+        },
+    ]);
+
+    let c_id = created[0];
+    assert_eq!(doc.nodes[list_id].children, [b_id, c_id]);
+    assert_eq!(doc.nodes[c_id].child_idx, 1);
+    assert_eq!(doc.get_element_by_id("c"), Some(c_id));
+    assert!(doc.get_node(a_id).is_none());
+    assert_eq!(doc.nodes[c_id].text_content(), "c");
+    assert_eq!(doc.nodes[list_id].style.display, taffy::Display::Flex);
+}
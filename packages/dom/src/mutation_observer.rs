@@ -0,0 +1,248 @@
+//! Change notifications (the equivalent of `MutationObserver`)
+//!
+//! While anything is subscribed, the document records changes to its tree: nodes being added and removed, attribute
+//! changes and text changes. The records are delivered to every subscriber in a single batch at the start of the next
+//! [`Document::resolve`], so that devtools, accessibility trees and embedders can sync with the document once per
+//! update rather than once per change.
+//!
+//! Changes made through [`Document`]'s tree methods are recorded automatically. Embedders that change attributes or
+//! text by mutating nodes directly should call [`Document::record_attribute_change`] or
+//! [`Document::record_text_change`] first.
+use html5ever::{namespace_url, ns, LocalName, QualName};
+
+use crate::Document;
+
+/// Called with the changes made to the document since the last update
+pub type MutationCallback = Box<dyn FnMut(&mut Document, &[MutationRecord]) + Send>;
+
+/// Identifies a subscription made with [`Document::subscribe_mutations`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MutationSubscription(u64);
+
+/// A change to the document. Nodes are identified by their ids when the change was made: a node that's been removed
+/// since may have had its id reused by a node created after it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MutationRecord {
+    /// Children were added to or removed from a node
+    ChildList {
+        target: usize,
+        added: Vec<usize>,
+        removed: Vec<usize>,
+    },
+    /// An attribute of an element was set or removed
+    Attribute {
+        target: usize,
+        name: QualName,
+        old_value: Option<String>,
+    },
+    /// The text of a text node was changed
+    Text { target: usize, old_value: String },
+}
+
+impl MutationRecord {
+    /// The node that was changed
+    pub fn target(&self) -> usize {
+        match self {
+            MutationRecord::ChildList { target, .. }
+            | MutationRecord::Attribute { target, .. }
+            | MutationRecord::Text { target, .. } => *target,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct MutationObservers {
+    subscribers: Vec<(MutationSubscription, MutationCallback)>,
+    records: Vec<MutationRecord>,
+    next_subscription: u64,
+}
+
+impl Document {
+    /// Be called back with the changes made to the document after each update
+    pub fn subscribe_mutations(
+        &mut self,
+        callback: impl FnMut(&mut Document, &[MutationRecord]) + Send + 'static,
+    ) -> MutationSubscription {
+        let observers = &mut self.mutation_observers;
+        let subscription = MutationSubscription(observers.next_subscription);
+        observers.next_subscription += 1;
+        observers
+            .subscribers
+            .push((subscription, Box::new(callback)));
+        subscription
+    }
+
+    /// Cancel a subscription made with [`Document::subscribe_mutations`]. Changes stop being recorded once there are
+    /// no subscribers.
+    pub fn unsubscribe_mutations(&mut self, subscription: MutationSubscription) {
+        let observers = &mut self.mutation_observers;
+        observers
+            .subscribers
+            .retain(|(other, _)| *other != subscription);
+        if observers.subscribers.is_empty() {
+            observers.records.clear();
+        }
+    }
+
+    /// Record that an attribute of an element is about to change. Call this before changing the attribute, so that
+    /// its old value can be recorded.
+    pub fn record_attribute_change(&mut self, node_id: usize, name: &LocalName) {
+        if self.mutation_observers.subscribers.is_empty() {
+            return;
+        }
+        let Some(element_data) = self.nodes[node_id].element_data() else {
+            return;
+        };
+        let attr = element_data
+            .attrs
+            .iter()
+            .find(|attr| attr.name.local == *name);
+        let record = MutationRecord::Attribute {
+            target: node_id,
+            name: attr.map_or_else(
+                || QualName::new(None, ns!(), name.clone()),
+                |attr| attr.name.clone(),
+            ),
+            old_value: attr.map(|attr| attr.value.clone()),
+        };
+        self.mutation_observers.records.push(record);
+    }
+
+    /// Record that the text of a text node is about to change. Call this before changing the text, so that its old
+    /// value can be recorded.
+    pub fn record_text_change(&mut self, node_id: usize) {
+        if self.mutation_observers.subscribers.is_empty() {
+            return;
+        }
+        let Some(text) = self.nodes[node_id].text_data() else {
+            return;
+        };
+        let record = MutationRecord::Text {
+            target: node_id,
+            old_value: text.content.clone(),
+        };
+        self.mutation_observers.records.push(record);
+    }
+
+    /// Record children being added to or removed from a node
+    pub(crate) fn record_child_list_change(
+        &mut self,
+        node_id: usize,
+        added: &[usize],
+        removed: &[usize],
+    ) {
+        if self.mutation_observers.subscribers.is_empty() {
+            return;
+        }
+        self.mutation_observers
+            .records
+            .push(MutationRecord::ChildList {
+                target: node_id,
+                added: added.to_vec(),
+                removed: removed.to_vec(),
+            });
+    }
+
+    /// Deliver the changes recorded since the last update to every subscriber
+    pub(crate) fn deliver_mutation_records(&mut self) {
+        let records = std::mem::take(&mut self.mutation_observers.records);
+        if records.is_empty() {
+            return;
+        }
+
+        // Callbacks may subscribe (or unsubscribe) while they run, so each subscriber is looked up again before it's
+        // called. Subscribers added by the callbacks, and changes the callbacks make, are delivered after the next
+        // update.
+        let subscriptions: Vec<MutationSubscription> = self
+            .mutation_observers
+            .subscribers
+            .iter()
+            .map(|(subscription, _)| *subscription)
+            .collect();
+        for subscription in subscriptions {
+            let Some(callback) = self.mutation_callback_mut(subscription) else {
+                continue;
+            };
+            // The callback is put back unless it cancelled its own subscription
+            let mut callback = std::mem::replace(callback, Box::new(|_, _| {}));
+            callback(self, &records);
+            if let Some(slot) = self.mutation_callback_mut(subscription) {
+                *slot = callback;
+            }
+        }
+    }
+
+    fn mutation_callback_mut(
+        &mut self,
+        subscription: MutationSubscription,
+    ) -> Option<&mut MutationCallback> {
+        self.mutation_observers
+            .subscribers
+            .iter_mut()
+            .find(|(other, _)| *other == subscription)
+            .map(|(_, callback)| callback)
+    }
+}
+
+#[test]
+fn delivers_batched_records() {
+    use crate::test_util::parse_test_document;
+    use html5ever::local_name;
+    use std::sync::{Arc, Mutex};
+
+    let html = "<!DOCTYPE html><html><body><div id=a title=old></div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let a = doc.get_element_by_id("a").unwrap();
+    let title = QualName::new(None, ns!(), local_name!("title"));
+
+    // The callback cancels its own subscription once `stop` is set
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let own_subscription = Arc::new(Mutex::new(None));
+    let stop = Arc::new(Mutex::new(false));
+    let (log, subscription_cell, stop_flag) =
+        (batches.clone(), own_subscription.clone(), stop.clone());
+    let subscription = doc.subscribe_mutations(move |doc, records| {
+        log.lock().unwrap().push(records.to_vec());
+        if *stop_flag.lock().unwrap() {
+            doc.unsubscribe_mutations(subscription_cell.lock().unwrap().unwrap());
+        }
+    });
+    *own_subscription.lock().unwrap() = Some(subscription);
+    let delivered = || std::mem::take(&mut *batches.lock().unwrap());
+
+    // The changes made between updates are delivered in one batch, in order
+    doc.set_attribute(a, title.clone(), "new");
+    let text = doc.create_text_node("hello");
+    doc.append_children(a, &[text]);
+    doc.resolve();
+    let batch = delivered();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(
+        batch[0],
+        [
+            MutationRecord::Attribute {
+                target: a,
+                name: title.clone(),
+                old_value: Some("old".into()),
+            },
+            MutationRecord::ChildList {
+                target: a,
+                added: vec![text],
+                removed: Vec::new(),
+            },
+        ]
+    );
+    doc.resolve();
+    assert!(delivered().is_empty());
+
+    // Unsubscribing from within the callback stops delivery, and changes stop being recorded
+    *stop.lock().unwrap() = true;
+    doc.set_attribute(a, title.clone(), "newer");
+    doc.resolve();
+    assert_eq!(delivered().len(), 1);
+    doc.set_attribute(a, title, "newest");
+    assert!(doc.mutation_observers.records.is_empty());
+    doc.resolve();
+    assert!(delivered().is_empty());
+}
@@ -0,0 +1,173 @@
+//! Navigating to other documents, and `<meta http-equiv=refresh>`
+//!
+//! The document can't replace itself: navigations are handed to a [`Navigator`] provided by the embedder, which loads
+//! the new document. Without a navigator, navigations are ignored.
+//!
+//! A `<meta http-equiv=refresh>` element schedules a navigation (or a reload, if it has no URL) after a delay, with a
+//! [timer](crate::timers).
+use std::time::Duration;
+
+use html5ever::local_name;
+use url::Url;
+
+use crate::timers::TimerHandle;
+use crate::Document;
+
+/// Loads the documents that a document navigates to, provided by the embedder
+pub trait Navigator: Send {
+    /// Replace the document with the one at `url`
+    fn navigate(&mut self, url: Url);
+
+    /// Replace the document with the response to a form posted to `url` (with the form's names and values). Navigators
+    /// that can't post forms ignore them.
+    fn post(&mut self, url: Url, entries: Vec<(String, String)>) {
+        let _ = entries;
+        eprintln!("Can't post a form to {}", url);
+    }
+}
+
+/// The contents of a `<meta http-equiv=refresh>` element, e.g. `5; url=/next`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetaRefresh {
+    /// How long to wait before navigating
+    pub delay: Duration,
+    /// Where to navigate to (unresolved). The document is reloaded if this is `None`.
+    pub url: Option<String>,
+}
+
+impl MetaRefresh {
+    /// Parse the `content` attribute of a `<meta http-equiv=refresh>` element
+    pub fn parse(content: &str) -> Option<Self> {
+        let content = content.trim_start();
+        let digits = content
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(content.len());
+        if digits == 0 {
+            return None;
+        }
+        // Fractional parts of the delay are ignored
+        let seconds: u64 = content[..digits]
+            .split('.')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap_or(0);
+        let delay = Duration::from_secs(seconds);
+
+        // The delay may be followed by a URL, optionally introduced by `url=` and quoted
+        let rest = content[digits..].trim_start();
+        let rest = match rest.strip_prefix([';', ',']) {
+            Some(rest) => rest.trim_start(),
+            None if rest.is_empty() => rest,
+            None => return None,
+        };
+        let rest = match rest.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+                match rest[3..].trim_start().strip_prefix('=') {
+                    Some(rest) => rest.trim_start(),
+                    None => rest,
+                }
+            }
+            _ => rest,
+        };
+        let url = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next().unwrap(),
+            _ => rest,
+        }
+        .trim();
+
+        Some(MetaRefresh {
+            delay,
+            url: (!url.is_empty()).then(|| url.to_string()),
+        })
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Navigation {
+    navigator: Option<Box<dyn Navigator>>,
+    /// The timer of a navigation scheduled by a `<meta http-equiv=refresh>` element
+    pending: Option<TimerHandle>,
+}
+
+impl Document {
+    /// Set the navigator that loads the documents this document navigates to
+    pub fn set_navigator(&mut self, navigator: Box<dyn Navigator>) {
+        self.navigation.navigator = Some(navigator);
+    }
+
+    /// Navigate to another document, cancelling any scheduled navigation
+    pub fn navigate(&mut self, url: Url) {
+        self.cancel_scheduled_navigation();
+        match self.navigation.navigator.as_mut() {
+            Some(navigator) => navigator.navigate(url),
+            None => eprintln!("No navigator to navigate to {}", url),
+        }
+    }
+
+    /// Navigate to the response to a form posted to `url`, cancelling any scheduled navigation
+    pub fn navigate_with_post(&mut self, url: Url, entries: Vec<(String, String)>) {
+        self.cancel_scheduled_navigation();
+        match self.navigation.navigator.as_mut() {
+            Some(navigator) => navigator.post(url, entries),
+            None => eprintln!("No navigator to post to {}", url),
+        }
+    }
+
+    /// The refresh requested by the document's first `<meta http-equiv=refresh>` element (if it has one)
+    pub fn meta_refresh(&self) -> Option<MetaRefresh> {
+        self.get_elements_by_tag_name(local_name!("meta"))
+            .into_iter()
+            .find_map(|node_id| {
+                let element = self.nodes[node_id].element_data()?;
+                let http_equiv = element.attr(local_name!("http-equiv"))?;
+                if !http_equiv.trim().eq_ignore_ascii_case("refresh") {
+                    return None;
+                }
+                MetaRefresh::parse(element.attr(local_name!("content"))?)
+            })
+    }
+
+    /// Schedule the navigation requested by the document's `<meta http-equiv=refresh>` element, if it has one. Call
+    /// this once the document has been loaded.
+    pub fn schedule_meta_refresh(&mut self) {
+        let Some(refresh) = self.meta_refresh() else {
+            return;
+        };
+        let url = match &refresh.url {
+            Some(url) => match &self.base_url {
+                Some(base_url) => base_url.join(url).ok(),
+                None => Url::parse(url).ok(),
+            },
+            None => self.base_url.clone(),
+        };
+        if let Some(url) = url {
+            self.cancel_scheduled_navigation();
+            let timer = self.set_timeout(refresh.delay, move |doc| {
+                doc.navigation.pending = None;
+                doc.navigate(url);
+            });
+            self.navigation.pending = Some(timer);
+        }
+    }
+
+    /// Cancel a navigation scheduled with [`Document::schedule_meta_refresh`]
+    pub fn cancel_scheduled_navigation(&mut self) {
+        if let Some(timer) = self.navigation.pending.take() {
+            self.clear_timer(timer);
+        }
+    }
+}
+
+#[test]
+fn parses_meta_refresh_content() {
+    let refresh = MetaRefresh::parse("5; URL='/next page'").unwrap();
+    assert_eq!(refresh.delay, Duration::from_secs(5));
+    assert_eq!(refresh.url.as_deref(), Some("/next page"));
+
+    let refresh = MetaRefresh::parse("0.5").unwrap();
+    assert_eq!(refresh.delay, Duration::ZERO);
+    assert_eq!(refresh.url, None);
+
+    assert_eq!(MetaRefresh::parse("soon"), None);
+}
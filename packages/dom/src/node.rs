@@ -1,17 +1,20 @@
 use atomic_refcell::{AtomicRef, AtomicRefCell};
-use html5ever::{local_name, LocalName, QualName};
+use html5ever::{local_name, namespace_url, ns, LocalName, QualName};
 use image::DynamicImage;
-use selectors::matching::QuirksMode;
+use selectors::matching::{ElementSelectorFlags, QuirksMode};
 use slab::Slab;
 use std::cell::RefCell;
 use std::fmt::Write;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use style::values::computed::Display;
 use style_traits::dom::ElementState;
 // use string_cache::Atom;
+use style::properties::generated::longhands::pointer_events::computed_value::T as PointerEvents;
+use style::properties::generated::longhands::visibility::computed_value::T as Visibility;
 use style::properties::ComputedValues;
 use style::stylesheets::UrlExtraData;
+use style::values::specified::box_::WillChangeBits;
 use style::Atom;
 use style::{
     data::ElementData,
@@ -26,7 +29,10 @@ use taffy::{
 };
 use url::Url;
 
+use crate::decoded_images::ImageSource;
 use crate::events::EventListener;
+use crate::layout::text_cache::WidthBucket;
+use crate::validation::PatternCache;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DisplayOuter {
@@ -48,6 +54,10 @@ pub struct Node {
     pub child_idx: usize,
     // What are our children?
     pub children: Vec<usize>,
+    /// The closest element siblings, cached for structural pseudo-class matching (see
+    /// [`crate::Document::flush_sibling_elements`])
+    pub(crate) prev_sibling_element: Option<usize>,
+    pub(crate) next_sibling_element: Option<usize>,
     /// A separate child list that includes anonymous collections of inline elements
     pub layout_children: RefCell<Option<Vec<usize>>>,
 
@@ -57,15 +67,24 @@ pub struct Node {
     // This little bundle of joy is our style data from stylo and a lock guard that allows access to it
     // TODO: See if guard can be hoisted to a higher level
     pub stylo_element_data: AtomicRefCell<Option<ElementData>>,
+    /// Bumped whenever the style traversal replaces the node's computed style (see [`crate::damage`])
+    pub(crate) style_generation: AtomicU64,
     pub guard: SharedRwLock,
     pub element_state: ElementState,
 
     // Taffy layout data:
     pub style: Style,
+    /// Whether the node has been hidden by blitz independently of its styles (e.g. a closed popover)
     pub hidden: bool,
+    /// Whether the node is in the document's top layer. Such nodes are laid out, painted and hit tested separately.
+    pub in_top_layer: bool,
     pub is_hovered: bool,
+    /// The states matched by `:state()` selectors (see [`crate::Document::set_custom_state`])
+    pub(crate) custom_states: Vec<Atom>,
     pub has_snapshot: bool,
     pub snapshot_handled: AtomicBool,
+    /// Which structural selectors matching has found to depend on the node (stylo's `ElementSelectorFlags`)
+    pub(crate) selector_flags: AtomicUsize,
     pub display_outer: DisplayOuter,
     pub cache: Cache,
     pub unrounded_layout: Layout,
@@ -74,6 +93,9 @@ pub struct Node {
 
     // Inline layout data
     pub is_inline_root: bool,
+
+    /// A hash of the layout inputs the node was last laid out with (see [`crate::layout::incremental`])
+    pub(crate) layout_signature: Option<u64>,
 }
 
 impl Node {
@@ -84,25 +106,32 @@ impl Node {
             id,
             parent: None,
             children: vec![],
+            prev_sibling_element: None,
+            next_sibling_element: None,
             layout_children: RefCell::new(None),
             child_idx: 0,
 
             raw_dom_data: data,
             stylo_element_data: Default::default(),
+            style_generation: AtomicU64::new(0),
             guard,
             element_state: ElementState::empty(),
 
             style: Default::default(),
             hidden: false,
+            in_top_layer: false,
             is_hovered: false,
+            custom_states: Vec::new(),
             has_snapshot: false,
             snapshot_handled: AtomicBool::new(false),
+            selector_flags: AtomicUsize::new(0),
             display_outer: DisplayOuter::Block,
             cache: Cache::new(),
             unrounded_layout: Layout::new(),
             final_layout: Layout::new(),
             listeners: Default::default(),
             is_inline_root: false,
+            layout_signature: None,
         }
     }
 
@@ -127,6 +156,7 @@ impl Node {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NodeKind {
     Document,
+    DocumentFragment,
     Element,
     AnonymousBlock,
     Text,
@@ -139,6 +169,9 @@ pub enum NodeData {
     /// The `Document` itself - the root node of a HTML document.
     Document,
 
+    /// A detached fragment holding the inert contents of a `<template>` element.
+    DocumentFragment,
+
     /// An element with attributes.
     Element(ElementNodeData),
 
@@ -184,6 +217,14 @@ impl NodeData {
         *name == elem.name.local
     }
 
+    /// Whether the node is an `<input type=password>`, whose value is kept out of its text content
+    pub fn is_password_input(&self) -> bool {
+        self.is_element_with_tag_name(&local_name!("input"))
+            && self
+                .attr(local_name!("type"))
+                .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("password"))
+    }
+
     pub fn attrs(&self) -> Option<&[Attribute]> {
         Some(&self.downcast_element()?.attrs)
     }
@@ -195,6 +236,7 @@ impl NodeData {
     pub fn kind(&self) -> NodeKind {
         match self {
             NodeData::Document => NodeKind::Document,
+            NodeData::DocumentFragment => NodeKind::DocumentFragment,
             NodeData::Element(_) => NodeKind::Element,
             NodeData::AnonymousBlock(_) => NodeKind::AnonymousBlock,
             NodeData::Text(_) => NodeKind::Text,
@@ -234,12 +276,24 @@ pub struct ElementNodeData {
     /// Parley text layout (elements with inline inner display mode only)
     pub inline_layout: Option<Box<TextLayout>>,
 
-    /// The element's image content (\<img\> element's only)
+    /// The element's image content (\<img\> element's only), decoded at the size it's displayed at (see
+    /// [`crate::decoded_images`]). Images are shown with [`ElementNodeData::set_image`].
     pub image: Option<Arc<DynamicImage>>,
-    pub resized_image: RefCell<Option<Arc<peniko::Image>>>,
+    /// Bumped whenever an image is shown (see [`crate::damage`])
+    pub(crate) image_generation: u64,
+    /// The encoded image that `image` is decoded from
+    pub image_source: Option<ImageSource>,
+    /// Whether the element's image couldn't be loaded, so a broken image placeholder is shown instead
+    pub broken_image: bool,
+    /// The element's alt text, laid out after the broken image icon (broken \<img\> elements only, see
+    /// [`crate::layout::alt_text`])
+    pub alt_text_layout: Option<Box<TextLayout>>,
 
     /// The element's template contents (\<template\> elements only)
     pub template_contents: Option<usize>,
+
+    /// The element's compiled `pattern` attribute (\<input\> elements only, see [`crate::validation`])
+    pub(crate) pattern_cache: PatternCache,
     // /// Whether the node is a [HTML integration point] (https://html.spec.whatwg.org/multipage/#html-integration-point)
     // pub mathml_annotation_xml_integration_point: bool,
 }
@@ -258,12 +312,22 @@ impl ElementNodeData {
             style_attribute: Default::default(),
             inline_layout: None,
             image: None,
-            resized_image: RefCell::new(None),
+            image_generation: 0,
+            image_source: None,
+            broken_image: false,
+            alt_text_layout: None,
             template_contents: None,
+            pattern_cache: PatternCache::default(),
             // listeners: FxHashSet::default(),
         }
     }
 
+    /// Show a decoded image in the element
+    pub fn set_image(&mut self, image: Arc<DynamicImage>) {
+        self.image = Some(image);
+        self.image_generation = crate::damage::next_paint_generation();
+    }
+
     pub fn attrs(&self) -> &[Attribute] {
         &self.attrs
     }
@@ -273,6 +337,22 @@ impl ElementNodeData {
         Some(&attr.value)
     }
 
+    /// Set the value of an (un-namespaced) attribute, adding the attribute if it doesn't exist
+    pub fn set_attr(&mut self, name: LocalName, value: impl Into<String>) {
+        let value = value.into();
+        match self.attrs.iter_mut().find(|attr| attr.name.local == name) {
+            Some(attr) => attr.value = value,
+            None => self.attrs.push(Attribute {
+                name: QualName::new(None, ns!(), name),
+                value,
+            }),
+        }
+    }
+
+    pub fn remove_attr(&mut self, name: LocalName) {
+        self.attrs.retain(|attr| attr.name.local != name);
+    }
+
     pub fn flush_style_attribute(&mut self, guard: &SharedRwLock) {
         self.style_attribute = self.attr(local_name!("style")).map(|style_str| {
             let url = UrlExtraData::from(
@@ -301,6 +381,8 @@ pub struct TextBrush {
 pub struct TextLayout {
     pub text: String,
     pub layout: parley::layout::Layout<TextBrush>,
+    /// The width bucket that the layout's lines were last broken for
+    pub(crate) line_break_bucket: Option<WidthBucket>,
 }
 
 impl std::fmt::Debug for TextLayout {
@@ -390,6 +472,21 @@ impl Node {
             .map(|id| self.with(*id))
     }
 
+    /// Look up a sibling element cached by [`crate::Document::flush_sibling_elements`]. Returns `None` if the cached
+    /// sibling has since been removed or moved to another parent.
+    pub(crate) fn cached_sibling_element(&self, cached: Option<usize>) -> Option<Option<&Node>> {
+        let Some(sibling_id) = cached else {
+            return Some(None);
+        };
+        let sibling = self.tree().get(sibling_id)?;
+        (sibling.parent == self.parent && sibling.is_element()).then_some(Some(sibling))
+    }
+
+    /// The flags set on the node by selector matching (see [`Node::selector_flags`])
+    pub(crate) fn element_selector_flags(&self) -> ElementSelectorFlags {
+        ElementSelectorFlags::from_bits_truncate(self.selector_flags.load(Ordering::Relaxed) as _)
+    }
+
     pub fn is_element(&self) -> bool {
         matches!(self.raw_dom_data, NodeData::Element { .. })
     }
@@ -433,6 +530,7 @@ impl Node {
 
         match &self.raw_dom_data {
             NodeData::Document => write!(s, "DOCUMENT"),
+            NodeData::DocumentFragment => write!(s, "DOCUMENT FRAGMENT"),
             // NodeData::Doctype { name, .. } => write!(s, "DOCTYPE {name}"),
             NodeData::Text(data) => {
                 let bytes = data.content.as_bytes();
@@ -494,6 +592,23 @@ impl Node {
         }
     }
 
+    /// Whether the node is `position: fixed`, and so stays in place when the viewport is scrolled
+    pub fn is_fixed_position(&self) -> bool {
+        self.primary_styles().is_some_and(|style| {
+            style.get_box().position
+                == style::properties::longhands::position::computed_value::T::Fixed
+        })
+    }
+
+    /// Whether the element's `will-change` hints that its transform, opacity or scroll position will be animated
+    pub fn will_change_compositing(&self) -> bool {
+        self.primary_styles().is_some_and(|style| {
+            style.get_box().will_change.bits.intersects(
+                WillChangeBits::TRANSFORM | WillChangeBits::OPACITY | WillChangeBits::SCROLL,
+            )
+        })
+    }
+
     pub fn text_content(&self) -> String {
         let mut out = String::new();
         self.write_text_content(&mut out);
@@ -505,6 +620,8 @@ impl Node {
             NodeData::Text(data) => {
                 out.push_str(&data.content);
             }
+            // The text of a password input is its (masked or revealed) value
+            NodeData::Element(..) if self.is_password_input() => {}
             NodeData::Element(..) | NodeData::AnonymousBlock(..) => {
                 for child_id in self.children.iter() {
                     self.with(*child_id).write_text_content(out);
@@ -529,9 +646,25 @@ impl Node {
             .unwrap_or(0)
     }
 
+    /// Whether the node can be the target of pointer events. Nodes that are invisible (`visibility: hidden` or
+    /// `collapse`) or have `pointer-events: none` are skipped by hit testing, though their descendants may not be.
+    /// Text takes after its parent.
+    pub fn is_hit_target(&self) -> bool {
+        match self.primary_styles() {
+            Some(style) => {
+                style.get_inherited_box().visibility == Visibility::Visible
+                    && style.get_inherited_ui().pointer_events != PointerEvents::None
+            }
+            None => self
+                .parent
+                .map_or(true, |parent_id| self.with(parent_id).is_hit_target()),
+        }
+    }
+
     /// Takes an (x, y) position (relative to the *parent's* top-left corner) and returns:
     ///    - None if the position is outside of this node's bounds
-    ///    - Some(self.id) is the position is within the node but doesn't match any children
+    ///    - Some(self.id) is the position is within the node but doesn't match any children (and the node is a
+    ///      hit target, see [`Node::is_hit_target`])
     ///    - The result of recursively calling child.hit() on the the child element that is
     ///      positioned at that position if there is one.
     ///
@@ -546,11 +679,18 @@ impl Node {
             return None;
         }
 
+        // Clip paths clip the node's descendants as well as the node itself
+        if self.clip_shape().is_some_and(|shape| !shape.contains(x, y)) {
+            return None;
+        }
+
         // Call `.hit()` on each child in turn. If any return `Some` then return that value. Else return `Some(self.id).
+        // Children in the top layer are hit tested by the document before the rest of the tree.
         self.children
             .iter()
+            .filter(|&&i| !self.with(i).in_top_layer)
             .find_map(|&i| self.with(i).hit(x, y))
-            .or(Some(self.id))
+            .or_else(|| self.is_hit_target().then_some(self.id))
     }
 }
 
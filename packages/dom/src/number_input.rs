@@ -0,0 +1,179 @@
+//! The spin buttons of `<input type=number>`
+//!
+//! <https://html.spec.whatwg.org/multipage/input.html#number-state-(type=number)>
+//! <https://html.spec.whatwg.org/multipage/input.html#dom-input-stepup>
+//!
+//! Number inputs display their value as text, with a pair of spin buttons drawn by the renderer in the right of their
+//! padding box. Pressing a button (or the up and down arrows while the input has focus) steps the value up or down by
+//! the input's `step`, from its `min` (or zero). A value that isn't a whole number of steps from there is first
+//! rounded to one that is, in the direction of the step, and the result is kept between `min` and `max`. The new
+//! value is stored in the input's `value` attribute, with as many decimal places as the value, `min` or `step` have.
+use html5ever::local_name;
+
+use crate::Document;
+
+/// The width of the spin buttons of a number input
+pub const SPIN_BUTTON_WIDTH: f32 = 16.0;
+
+/// Which way a spin button steps a number input's value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpinDirection {
+    Up,
+    Down,
+}
+
+fn parse_number(value: Option<&str>) -> Option<f64> {
+    value
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+}
+
+/// The number of digits after the decimal point of a number as written
+fn decimal_places(value: Option<&str>) -> usize {
+    value
+        .and_then(|value| value.trim().split_once('.'))
+        .map(|(_, fraction)| fraction.chars().take_while(char::is_ascii_digit).count())
+        .unwrap_or(0)
+}
+
+impl Document {
+    /// Whether an element is an `<input type=number>`
+    pub fn is_number_input(&self, node_id: usize) -> bool {
+        self.nodes[node_id]
+            .element_data()
+            .is_some_and(|element_data| {
+                element_data.name.local == local_name!("input")
+                    && element_data
+                        .attr(local_name!("type"))
+                        .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("number"))
+            })
+    }
+
+    /// Step the value of a number input up or down by its step. Returns true if the value changed.
+    pub fn step_number_input(&mut self, node_id: usize, direction: SpinDirection) -> bool {
+        let node = &self.nodes[node_id];
+        if !self.is_number_input(node_id)
+            || node.is_disabled()
+            || node.attr(local_name!("readonly")).is_some()
+        {
+            return false;
+        }
+        let attr = |name| node.attr(name);
+        let min = parse_number(attr(local_name!("min")));
+        let max = parse_number(attr(local_name!("max")));
+        if min.zip(max).is_some_and(|(min, max)| min > max) {
+            return false;
+        }
+        // The buttons step by 1 when any value is allowed
+        let step = parse_number(attr(local_name!("step")))
+            .filter(|step| *step > 0.0)
+            .unwrap_or(1.0);
+        let base = min.unwrap_or(0.0);
+        let value = parse_number(attr(local_name!("value"))).unwrap_or(0.0);
+
+        // Values between steps are rounded to a step in the direction of the step
+        let steps = (value - base) / step;
+        let steps = if (steps - steps.round()).abs() <= 1e-9 {
+            match direction {
+                SpinDirection::Up => steps.round() + 1.0,
+                SpinDirection::Down => steps.round() - 1.0,
+            }
+        } else {
+            match direction {
+                SpinDirection::Up => steps.ceil(),
+                SpinDirection::Down => steps.floor(),
+            }
+        };
+        let mut stepped = base + steps * step;
+        if let Some(max) = max {
+            if stepped > max {
+                stepped = base + ((max - base) / step + 1e-9).floor() * step;
+            }
+        }
+        if let Some(min) = min {
+            stepped = stepped.max(min);
+        }
+
+        // Stepping never moves the value the wrong way (e.g. stepping up from above the maximum)
+        let moved = match direction {
+            SpinDirection::Up => stepped > value,
+            SpinDirection::Down => stepped < value,
+        };
+        if !moved && attr(local_name!("value")).is_some() {
+            return false;
+        }
+
+        let decimals = [
+            local_name!("value"),
+            local_name!("min"),
+            local_name!("step"),
+        ]
+        .into_iter()
+        .map(|name| decimal_places(attr(name)))
+        .max()
+        .unwrap_or(0);
+        // Avoid writing `-0`
+        let stepped = if stepped == 0.0 { 0.0 } else { stepped };
+        let value = format!("{stepped:.decimals$}");
+        if node.attr(local_name!("value")) == Some(value.as_str()) {
+            return false;
+        }
+        self.set_input_value(node_id, value);
+        true
+    }
+
+    /// The spin button of a number input at a point in document coordinates (if any)
+    pub fn spin_button_at(&self, node_id: usize, x: f32, y: f32) -> Option<SpinDirection> {
+        if !self.is_number_input(node_id) {
+            return None;
+        }
+        let layout = self.nodes[node_id].final_layout;
+        let position = self.absolute_position(node_id);
+        let right = position.x + layout.size.width - layout.border.right;
+        let left = (right - SPIN_BUTTON_WIDTH).max(position.x + layout.border.left);
+        let top = position.y + layout.border.top;
+        let bottom = position.y + layout.size.height - layout.border.bottom;
+        if x < left || x > right || y < top || y > bottom {
+            return None;
+        }
+        Some(if y < (top + bottom) / 2.0 {
+            SpinDirection::Up
+        } else {
+            SpinDirection::Down
+        })
+    }
+}
+
+#[test]
+fn steps_number_inputs() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input type=number id=plain>\
+        <input type=number id=bounded min=1 max=10 step=3 value=5>\
+        <input type=number id=decimal step=0.1 value=0.2>\
+        <input type=number id=disabled value=1 disabled></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let mut step = |id: &str, direction| {
+        let node_id = doc.get_element_by_id(id).unwrap();
+        doc.step_number_input(node_id, direction);
+        doc.nodes[node_id]
+            .attr(local_name!("value"))
+            .map(str::to_string)
+    };
+
+    // An empty input steps from zero
+    assert_eq!(step("plain", SpinDirection::Down).as_deref(), Some("-1"));
+
+    // Off-step values are rounded to a step first, and the value stays within the bounds
+    assert_eq!(step("bounded", SpinDirection::Up).as_deref(), Some("7"));
+    assert_eq!(step("bounded", SpinDirection::Up).as_deref(), Some("10"));
+    assert_eq!(step("bounded", SpinDirection::Up).as_deref(), Some("10"));
+    assert_eq!(step("bounded", SpinDirection::Down).as_deref(), Some("7"));
+
+    // Decimal steps don't accumulate rounding errors
+    assert_eq!(step("decimal", SpinDirection::Up).as_deref(), Some("0.3"));
+    assert_eq!(step("disabled", SpinDirection::Up).as_deref(), Some("1"));
+}
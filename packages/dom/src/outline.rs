@@ -0,0 +1,322 @@
+//! The document outline: its headings and landmarks
+//!
+//! <https://html.spec.whatwg.org/multipage/sections.html#headings-and-outlines>
+//! <https://www.w3.org/TR/wai-aria-1.2/#landmark_roles>
+//!
+//! Headings (`<h1>` to `<h6>`, or elements with `role=heading` and an `aria-level`) are nested by their level: each
+//! heading contains the headings after it with a higher level, up to the next heading with the same or a lower level.
+//! Landmarks are the elements that mark out the regions of a page, either with their `role` or implicitly by their
+//! tag (e.g. `<nav>` is a navigation landmark). Embedders can use the outline for tables of contents, reader modes,
+//! or to let users jump between the regions of a page.
+//!
+//! Elements that aren't rendered (`display: none`, `hidden`) or are hidden from assistive technologies
+//! (`aria-hidden=true`) are left out, along with their descendants.
+//!
+//! Note: there is no accessibility tree yet for the outline to be exposed through.
+use html5ever::local_name;
+
+use crate::node::Node;
+use crate::Document;
+
+/// The headings and landmarks of a document
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentOutline {
+    /// The top level headings, with the headings nested under them
+    pub headings: Vec<OutlineHeading>,
+    /// The landmarks, in document order
+    pub landmarks: Vec<Landmark>,
+}
+
+/// A heading, and the headings nested under it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlineHeading {
+    pub node_id: usize,
+    /// From 1 (`<h1>`) to 6 (`<h6>`)
+    pub level: u8,
+    /// The text of the heading, with its whitespace collapsed
+    pub text: String,
+    pub children: Vec<OutlineHeading>,
+}
+
+/// The role of a landmark
+///
+/// <https://www.w3.org/TR/wai-aria-1.2/#landmark_roles>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LandmarkRole {
+    /// The page's header (a `<header>` that isn't within a sectioning element or `<main>`)
+    Banner,
+    /// Content that complements the main content (`<aside>`)
+    Complementary,
+    /// The page's footer (a `<footer>` that isn't within a sectioning element or `<main>`)
+    ContentInfo,
+    /// A `<form>` with an accessible name
+    Form,
+    /// The main content (`<main>`)
+    Main,
+    /// A group of links for navigating the site or page (`<nav>`)
+    Navigation,
+    /// A `<section>` with an accessible name
+    Region,
+    /// A search facility (`<search>`)
+    Search,
+}
+
+impl LandmarkRole {
+    /// The role's ARIA name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Banner => "banner",
+            Self::Complementary => "complementary",
+            Self::ContentInfo => "contentinfo",
+            Self::Form => "form",
+            Self::Main => "main",
+            Self::Navigation => "navigation",
+            Self::Region => "region",
+            Self::Search => "search",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Banner,
+            Self::Complementary,
+            Self::ContentInfo,
+            Self::Form,
+            Self::Main,
+            Self::Navigation,
+            Self::Region,
+            Self::Search,
+        ]
+        .into_iter()
+        .find(|role| role.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A landmark region of the document
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Landmark {
+    pub node_id: usize,
+    pub role: LandmarkRole,
+    /// The landmark's accessible name, from its `aria-label` or `aria-labelledby`
+    pub label: Option<String>,
+}
+
+/// What an element contributes to the outline
+enum OutlineRole {
+    Heading(u8),
+    Landmark(LandmarkRole),
+}
+
+impl Document {
+    /// The document's headings and landmarks
+    pub fn outline(&self) -> DocumentOutline {
+        let mut headings = Vec::new();
+        let mut landmarks = Vec::new();
+        self.collect_outline(self.root_node().id, false, &mut headings, &mut landmarks);
+        DocumentOutline {
+            headings: nest_headings(headings),
+            landmarks,
+        }
+    }
+
+    /// Collect the headings and landmarks of a subtree in document order. `sectioned` is whether the subtree is
+    /// within a sectioning element or `<main>`, where headers and footers aren't landmarks.
+    fn collect_outline(
+        &self,
+        node_id: usize,
+        sectioned: bool,
+        headings: &mut Vec<OutlineHeading>,
+        landmarks: &mut Vec<Landmark>,
+    ) {
+        let node = &self.nodes[node_id];
+        if node.hidden || node.style.display == taffy::Display::None {
+            return;
+        }
+        let mut sectioned = sectioned;
+        if let Some(element_data) = node.element_data() {
+            if element_data.attr(local_name!("hidden")).is_some()
+                || element_data
+                    .attr(local_name!("aria-hidden"))
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+            {
+                return;
+            }
+
+            match self.outline_role(node, sectioned) {
+                Some(OutlineRole::Heading(level)) => headings.push(OutlineHeading {
+                    node_id,
+                    level,
+                    text: collapse_whitespace(&node.text_content()),
+                    children: Vec::new(),
+                }),
+                Some(OutlineRole::Landmark(role)) => landmarks.push(Landmark {
+                    node_id,
+                    role,
+                    label: self.accessible_label(node),
+                }),
+                None => {}
+            }
+            sectioned |= matches!(
+                element_data.name.local.as_ref(),
+                "article" | "aside" | "main" | "nav" | "section"
+            );
+        }
+
+        for &child_id in &node.children {
+            self.collect_outline(child_id, sectioned, headings, landmarks);
+        }
+    }
+
+    /// The explicit role of an element (from its `role` attribute), or its implicit role
+    fn outline_role(&self, node: &Node, sectioned: bool) -> Option<OutlineRole> {
+        let element_data = node.element_data()?;
+
+        // The first role that's recognized is used
+        if let Some(roles) = element_data.attr(local_name!("role")) {
+            for role in roles.split_ascii_whitespace() {
+                if role.eq_ignore_ascii_case("heading") {
+                    let level = element_data
+                        .attr(local_name!("aria-level"))
+                        .and_then(|level| level.trim().parse::<u8>().ok())
+                        .filter(|level| *level >= 1)
+                        .unwrap_or(2);
+                    return Some(OutlineRole::Heading(level.min(6)));
+                }
+                if let Some(role) = LandmarkRole::from_name(role) {
+                    return Some(OutlineRole::Landmark(role));
+                }
+                if role.eq_ignore_ascii_case("none") || role.eq_ignore_ascii_case("presentation") {
+                    return None;
+                }
+            }
+        }
+
+        let role = match element_data.name.local.as_ref() {
+            "h1" => return Some(OutlineRole::Heading(1)),
+            "h2" => return Some(OutlineRole::Heading(2)),
+            "h3" => return Some(OutlineRole::Heading(3)),
+            "h4" => return Some(OutlineRole::Heading(4)),
+            "h5" => return Some(OutlineRole::Heading(5)),
+            "h6" => return Some(OutlineRole::Heading(6)),
+            "header" if !sectioned => LandmarkRole::Banner,
+            "footer" if !sectioned => LandmarkRole::ContentInfo,
+            "aside" => LandmarkRole::Complementary,
+            "main" => LandmarkRole::Main,
+            "nav" => LandmarkRole::Navigation,
+            "search" => LandmarkRole::Search,
+            // Forms and sections are only landmarks if they're named
+            "form" | "section" if self.accessible_label(node).is_some() => {
+                match element_data.name.local.as_ref() {
+                    "form" => LandmarkRole::Form,
+                    _ => LandmarkRole::Region,
+                }
+            }
+            _ => return None,
+        };
+        Some(OutlineRole::Landmark(role))
+    }
+
+    /// An element's accessible name from its `aria-labelledby` (the text of the elements it refers to) or its
+    /// `aria-label`
+    fn accessible_label(&self, node: &Node) -> Option<String> {
+        let element_data = node.element_data()?;
+        let labelled_by = element_data
+            .attr(local_name!("aria-labelledby"))
+            .map(|ids| {
+                ids.split_ascii_whitespace()
+                    .filter_map(|id| self.get_element_by_id(id))
+                    .map(|node_id| self.nodes[node_id].text_content())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            });
+        [
+            labelled_by.as_deref(),
+            element_data.attr(local_name!("aria-label")),
+        ]
+        .into_iter()
+        .flatten()
+        .map(collapse_whitespace)
+        .find(|label| !label.is_empty())
+    }
+}
+
+/// Nest each heading under the closest heading before it with a lower level
+fn nest_headings(headings: Vec<OutlineHeading>) -> Vec<OutlineHeading> {
+    let mut roots: Vec<OutlineHeading> = Vec::new();
+    // The chain of open headings, from a root heading to the last heading
+    let mut open: Vec<OutlineHeading> = Vec::new();
+    let close = |heading: OutlineHeading,
+                 open: &mut Vec<OutlineHeading>,
+                 roots: &mut Vec<OutlineHeading>| match open.last_mut() {
+        Some(parent) => parent.children.push(heading),
+        None => roots.push(heading),
+    };
+
+    for heading in headings {
+        while open.last().is_some_and(|last| last.level >= heading.level) {
+            let closed = open.pop().unwrap();
+            close(closed, &mut open, &mut roots);
+        }
+        open.push(heading);
+    }
+    while let Some(closed) = open.pop() {
+        close(closed, &mut open, &mut roots);
+    }
+    roots
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn outlines_headings_and_landmarks() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <header><h1>Blog</h1><nav aria-label='Site'></nav></header>\
+        <main><article><header>Not a banner</header><h2>First  post</h2><h3>Details</h3></article>\
+        <h2>Second post</h2><div role=heading aria-level=4>Aside</div>\
+        <section>Unnamed</section><section aria-labelledby=comments><h2 id=comments>Comments</h2></section>\
+        <h2 hidden>Hidden</h2></main><footer>Contact</footer></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let outline = doc.outline();
+
+    // Headings are nested by level
+    fn texts(headings: &[OutlineHeading]) -> Vec<(u8, &str, usize)> {
+        headings
+            .iter()
+            .map(|heading| (heading.level, heading.text.as_str(), heading.children.len()))
+            .collect()
+    }
+    assert_eq!(texts(&outline.headings), [(1, "Blog", 3)]);
+    let posts = &outline.headings[0].children;
+    assert_eq!(
+        texts(posts),
+        [
+            (2, "First post", 1),
+            (2, "Second post", 1),
+            (2, "Comments", 0)
+        ]
+    );
+    assert_eq!(texts(&posts[0].children), [(3, "Details", 0)]);
+    assert_eq!(texts(&posts[1].children), [(4, "Aside", 0)]);
+
+    // Headers and footers within articles aren't landmarks, and sections are only landmarks when they're named
+    let landmarks: Vec<(LandmarkRole, Option<&str>)> = outline
+        .landmarks
+        .iter()
+        .map(|landmark| (landmark.role, landmark.label.as_deref()))
+        .collect();
+    assert_eq!(
+        landmarks,
+        [
+            (LandmarkRole::Banner, None),
+            (LandmarkRole::Navigation, Some("Site")),
+            (LandmarkRole::Main, None),
+            (LandmarkRole::Region, Some("Comments")),
+            (LandmarkRole::ContentInfo, None),
+        ]
+    );
+}
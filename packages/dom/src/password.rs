@@ -0,0 +1,83 @@
+//! Masking the value of `<input type=password>`
+//!
+//! <https://html.spec.whatwg.org/multipage/input.html#password-state-(type=password)>
+//!
+//! Password inputs display their value as a text child, like date and number inputs, but with each character replaced
+//! by a bullet. The value itself stays in the `value` attribute. Embedders can reveal the value (e.g. for a "show
+//! password" toggle) with [`Document::set_password_revealed`], which displays it as it is until it's masked again.
+//!
+//! The displayed text is left out of the input's text content, and the value and text are left out of tree dumps and
+//! saved session state, so that neither a reveal nor a debugging tool exposes the password.
+use html5ever::local_name;
+
+use crate::Document;
+
+/// The character that each character of a masked password is displayed as
+pub const PASSWORD_MASK: char = '•';
+
+impl Document {
+    /// Whether the value of a password input is displayed rather than masked
+    pub fn is_password_revealed(&self, node_id: usize) -> bool {
+        self.revealed_passwords.contains(&node_id)
+    }
+
+    /// Display the value of a password input as it is, or mask it again. Returns true if the input is a password
+    /// input.
+    pub fn set_password_revealed(&mut self, node_id: usize, revealed: bool) -> bool {
+        if !self.is_password_input(node_id) {
+            return false;
+        }
+        let changed = if revealed {
+            self.revealed_passwords.insert(node_id)
+        } else {
+            self.revealed_passwords.remove(&node_id)
+        };
+        if changed {
+            self.update_picker_input_text(node_id);
+        }
+        true
+    }
+
+    /// The text that a password input displays: its value, masked unless it has been revealed
+    pub(crate) fn password_display_text(&self, node_id: usize) -> String {
+        let value = self.nodes[node_id]
+            .attr(local_name!("value"))
+            .unwrap_or_default();
+        if self.is_password_revealed(node_id) {
+            value.to_string()
+        } else {
+            value.chars().map(|_| PASSWORD_MASK).collect()
+        }
+    }
+}
+
+#[test]
+fn masks_passwords() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body><form id=form>\
+        <input type=password id=password value=hunter2></form></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let password = doc.get_element_by_id("password").unwrap();
+    let displayed = |doc: &Document| {
+        let text_id = doc.nodes[password].children[0];
+        doc.nodes[text_id].text_data().unwrap().content.clone()
+    };
+    assert_eq!(displayed(&doc), "•••••••");
+
+    // The mask follows the value, and revealing it displays the value
+    let value = QualName::new(None, ns!(), local_name!("value"));
+    doc.set_attribute(password, value, "swordfish");
+    assert_eq!(displayed(&doc), "•••••••••");
+    assert!(doc.set_password_revealed(password, true));
+    assert_eq!(displayed(&doc), "swordfish");
+
+    // Neither the text content nor the saved state has the password
+    let form = doc.get_element_by_id("form").unwrap();
+    assert_eq!(doc.nodes[form].text_content(), "");
+    assert!(doc.session_state().fields.is_empty());
+    assert!(!doc.dump_json().contains("swordfish"));
+}
@@ -0,0 +1,833 @@
+//! Pickers for `<input type=date>`, `<input type=color>` and `<input type=file>`
+//!
+//! Activating a date or color input opens a popup (a calendar or a palette of color swatches) anchored to the
+//! input. Inputs with a `<datalist>` open a dropdown of suggestions in the same way (see [`crate::datalist`]). The popup is laid out here in viewport coordinates and drawn by the renderer above the top layer.
+//! Choosing a date or color stores it in the input's `value` attribute.
+//!
+//! Blitz has no file dialog of its own: embedders provide one by implementing [`FileDialog`]. Without a file
+//! dialog, activating a file input does nothing. File dialogs are shown without blocking the event loop, and the
+//! chosen files are applied by [`Document::poll_file_dialog`].
+use std::path::PathBuf;
+use std::task::{Context, Poll};
+
+use html5ever::{local_name, namespace_url, ns, QualName};
+use parley::layout::Alignment;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::datalist::MAX_SUGGESTIONS;
+use crate::node::{NodeData, TextLayout};
+use crate::{stylo_to_parley, Document};
+
+/// The kind of picker an `<input>` element opens when activated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickerKind {
+    Date,
+    Color,
+    File,
+    /// The suggestions of an input's `<datalist>`
+    Suggestions,
+}
+
+/// Options for showing a file dialog, derived from the attributes of the `<input type=file>`
+#[derive(Clone, Debug, Default)]
+pub struct FileDialogOptions {
+    /// The `multiple` attribute: whether more than one file may be chosen
+    pub multiple: bool,
+    /// The `accept` attribute, split on commas: file extensions (`.png`) and/or MIME types (`image/*`)
+    pub accept: Vec<String>,
+}
+
+/// A platform file dialog, provided by the embedder
+pub trait FileDialog: Send {
+    /// Start showing a file dialog. This must not block until the user has chosen files: the files are collected
+    /// with [`FileDialog::poll_files`].
+    fn show(&mut self, options: &FileDialogOptions);
+
+    /// Poll for the files chosen in the dialog that was last shown.
+    ///
+    /// - `Poll::Ready(Some(files))` once the user has chosen files
+    /// - `Poll::Ready(None)` if the dialog was cancelled
+    /// - `Poll::Pending` while the dialog is still showing. Implementors must arrange for the waker in `cx` to be
+    ///   woken once the dialog is closed.
+    fn poll_files(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<PathBuf>>>;
+}
+
+/// A calendar date, as stored in the value of an `<input type=date>` (`yyyy-mm-dd`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Parse a valid date string
+    ///
+    /// <https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#valid-date-string>
+    pub fn parse(value: &str) -> Option<Date> {
+        let mut parts = value.trim().splitn(3, '-');
+        let year = parts.next()?;
+        let month = parts.next()?;
+        let day = parts.next()?;
+        if year.len() < 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+
+        let year: i32 = year.parse().ok().filter(|year| *year > 0)?;
+        let month: u32 = month
+            .parse()
+            .ok()
+            .filter(|month| (1..=12).contains(month))?;
+        let day: u32 = day
+            .parse()
+            .ok()
+            .filter(|day| (1..=days_in_month(year, month)).contains(day))?;
+
+        Some(Date { year, month, day })
+    }
+
+    /// The day of the week, where 0 is Sunday
+    pub fn weekday(&self) -> u32 {
+        // Sakamoto's method
+        const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = if self.month < 3 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let weekday = year + year / 4 - year / 100
+            + year / 400
+            + OFFSETS[self.month as usize - 1]
+            + self.day as i32;
+        weekday.rem_euclid(7) as u32
+    }
+
+    /// Today's date (in UTC)
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or(0);
+        Date::from_unix_days((seconds / 86_400) as i64)
+    }
+
+    /// The date a number of days after 1970-01-01
+    fn from_unix_days(days: i64) -> Date {
+        // Howard Hinnant's civil_from_days, which counts from 0000-03-01 so that leap days end each year
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Parse a valid simple color (`#rrggbb`), as stored in the value of an `<input type=color>`
+///
+/// <https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#valid-simple-colour>
+pub fn parse_simple_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+
+pub(crate) const PICKER_PADDING: f32 = 8.0;
+const CALENDAR_CELL_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 30.0,
+    height: 24.0,
+};
+const SWATCH_SIZE: f32 = 20.0;
+const SWATCH_GAP: f32 = 4.0;
+const SWATCH_COLUMNS: usize = 8;
+
+/// What clicking on a cell of an open picker does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PickerAction {
+    PreviousMonth,
+    NextMonth,
+    SelectDate(Date),
+    SelectColor([u8; 3]),
+    /// Choose the suggestion with the given index
+    SelectSuggestion(usize),
+    /// The cell is a label and does nothing
+    None,
+}
+
+/// A cell of an open picker (a day of a calendar, a color swatch, a label, etc)
+pub struct PickerCell {
+    /// Position relative to the picker
+    pub location: taffy::Point<f32>,
+    pub size: taffy::Size<f32>,
+    pub action: PickerAction,
+    /// Whether the cell represents the input's current value (or is the highlighted suggestion)
+    pub selected: bool,
+    /// Text to draw centered within the cell
+    pub label: Option<TextLayout>,
+    /// A color to fill the cell with (color swatches)
+    pub color: Option<[u8; 3]>,
+}
+
+/// A date or color picker popup that is currently showing
+pub struct OpenPicker {
+    /// The `<input>` element that the picker belongs to
+    pub node_id: usize,
+    pub kind: PickerKind,
+    /// Position relative to the viewport
+    pub location: taffy::Point<f32>,
+    pub size: taffy::Size<f32>,
+    pub cells: Vec<PickerCell>,
+    /// The month displayed by a calendar
+    month: (i32, u32),
+    /// The values suggested by a datalist
+    pub(crate) suggestions: Vec<String>,
+    /// The suggestion highlighted with the arrow keys
+    pub(crate) highlighted: Option<usize>,
+}
+
+/// The result of a click while a picker is open
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickerClick {
+    /// The click was outside the picker, which has been closed
+    Outside,
+    /// The click was inside the picker but did not change the input's value
+    Handled,
+    /// The click changed the value of the given input, and the picker has been closed
+    ValueChanged(usize),
+}
+
+impl Document {
+    /// The kind of picker an element opens when activated (if any)
+    pub fn picker_kind(&self, node_id: usize) -> Option<PickerKind> {
+        let element_data = self.nodes[node_id].element_data()?;
+        if element_data.name.local != local_name!("input") {
+            return None;
+        }
+        let input_type = element_data.attr(local_name!("type")).unwrap_or("text");
+        if input_type.eq_ignore_ascii_case("date") {
+            Some(PickerKind::Date)
+        } else if input_type.eq_ignore_ascii_case("color") {
+            Some(PickerKind::Color)
+        } else if input_type.eq_ignore_ascii_case("file") {
+            Some(PickerKind::File)
+        } else {
+            self.input_datalist(node_id)
+                .map(|_| PickerKind::Suggestions)
+        }
+    }
+
+    /// Set the file dialog used by `<input type=file>` elements
+    pub fn set_file_dialog(&mut self, file_dialog: Box<dyn FileDialog>) {
+        self.file_dialog = Some(file_dialog);
+    }
+
+    /// The files chosen for an `<input type=file>`
+    pub fn selected_files(&self, node_id: usize) -> &[PathBuf] {
+        self.selected_files
+            .get(&node_id)
+            .map(|files| files.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The picker popup that is currently showing (if any)
+    pub fn open_picker(&self) -> Option<&OpenPicker> {
+        self.open_picker.as_ref()
+    }
+
+    pub fn close_picker(&mut self) {
+        self.open_picker = None;
+    }
+
+    /// Activate the picker of an input: opens the popup of a date or color input, or shows the file dialog
+    /// for a file input.
+    ///
+    /// The input's value doesn't change straight away: popups are interacted with via
+    /// [`Document::handle_picker_click`], and the files chosen in a file dialog are applied by
+    /// [`Document::poll_file_dialog`].
+    pub fn activate_picker(&mut self, node_id: usize) {
+        // Disabled inputs can't be activated
+        if self.nodes[node_id].is_disabled() {
+            return;
+        }
+
+        match self.picker_kind(node_id) {
+            Some(PickerKind::File) => self.show_file_dialog(node_id),
+            Some(kind) => {
+                // Calendars open at the month of the input's date, or of its minimum date, or otherwise at the
+                // current month
+                let min = self.nodes[node_id]
+                    .attr(local_name!("min"))
+                    .and_then(Date::parse);
+                let date = self.date_value(node_id).or(min).unwrap_or_else(Date::today);
+                let month = (date.year, date.month);
+                self.open_picker = Some(OpenPicker {
+                    node_id,
+                    kind,
+                    location: taffy::Point::ZERO,
+                    size: taffy::Size::ZERO,
+                    cells: Vec::new(),
+                    month,
+                    suggestions: Vec::new(),
+                    highlighted: None,
+                });
+                self.layout_picker();
+            }
+            None => {}
+        }
+    }
+
+    /// Handle a click while a picker is open. Takes a position relative to the viewport.
+    pub fn handle_picker_click(&mut self, x: f32, y: f32) -> PickerClick {
+        let Some(picker) = &self.open_picker else {
+            return PickerClick::Outside;
+        };
+
+        let x = x - picker.location.x;
+        let y = y - picker.location.y;
+        if x < 0.0 || y < 0.0 || x > picker.size.width || y > picker.size.height {
+            self.close_picker();
+            return PickerClick::Outside;
+        }
+
+        let node_id = picker.node_id;
+        let action = picker
+            .cells
+            .iter()
+            .find(|cell| {
+                x >= cell.location.x
+                    && y >= cell.location.y
+                    && x <= cell.location.x + cell.size.width
+                    && y <= cell.location.y + cell.size.height
+            })
+            .map(|cell| cell.action)
+            .unwrap_or(PickerAction::None);
+
+        let value = match action {
+            PickerAction::PreviousMonth | PickerAction::NextMonth => {
+                let picker = self.open_picker.as_mut().unwrap();
+                let (year, month) = picker.month;
+                picker.month = match (action, month) {
+                    (PickerAction::PreviousMonth, 1) => (year - 1, 12),
+                    (PickerAction::PreviousMonth, _) => (year, month - 1),
+                    (_, 12) => (year + 1, 1),
+                    (_, _) => (year, month + 1),
+                };
+                self.layout_picker();
+                return PickerClick::Handled;
+            }
+            PickerAction::SelectDate(date) => date.to_string(),
+            PickerAction::SelectColor([r, g, b]) => format!("#{r:02x}{g:02x}{b:02x}"),
+            PickerAction::SelectSuggestion(index) => picker.suggestions[index].clone(),
+            PickerAction::None => return PickerClick::Handled,
+        };
+
+        self.close_picker();
+        if self.nodes[node_id].attr(local_name!("value")) == Some(value.as_str()) {
+            return PickerClick::Handled;
+        }
+        self.set_input_value(node_id, value);
+        PickerClick::ValueChanged(node_id)
+    }
+
+    /// Show the file dialog for a file input, unless one is already showing
+    fn show_file_dialog(&mut self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let options = FileDialogOptions {
+            multiple: node.attr(local_name!("multiple")).is_some(),
+            accept: node
+                .attr(local_name!("accept"))
+                .map(|accept| {
+                    accept
+                        .split(',')
+                        .map(|token| token.trim().to_ascii_lowercase())
+                        .filter(|token| !token.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        let Some(file_dialog) = self.file_dialog.as_mut() else {
+            return;
+        };
+        if self.file_dialog_input.is_some() {
+            return;
+        }
+        file_dialog.show(&options);
+        self.file_dialog_input = Some(node_id);
+    }
+
+    /// Apply the files chosen in the file dialog once it has closed.
+    ///
+    /// Returns the file input if its chosen files changed, in which case `input` and `change` events should be
+    /// dispatched to it.
+    pub fn poll_file_dialog(&mut self, cx: &mut Context<'_>) -> Option<usize> {
+        let node_id = self.file_dialog_input?;
+        let Poll::Ready(files) = self.file_dialog.as_mut()?.poll_files(cx) else {
+            return None;
+        };
+        self.file_dialog_input = None;
+        let mut files = files?;
+
+        // The input may have been removed (or changed type) while the dialog was showing
+        if !self.nodes.contains(node_id) || self.picker_kind(node_id) != Some(PickerKind::File) {
+            return None;
+        }
+        if self.nodes[node_id].attr(local_name!("multiple")).is_none() {
+            files.truncate(1);
+        }
+        if self.selected_files(node_id) == files.as_slice() {
+            return None;
+        }
+
+        // Like browsers, only expose the file name (not the full path) through the value
+        let value = files
+            .first()
+            .and_then(|path| path.file_name())
+            .map(|name| format!("C:\\fakepath\\{}", name.to_string_lossy()))
+            .unwrap_or_default();
+        self.selected_files.insert(node_id, files);
+        self.set_input_value(node_id, value);
+        Some(node_id)
+    }
+
+    /// The value of a date input, if it is a valid date
+    fn date_value(&self, node_id: usize) -> Option<Date> {
+        Date::parse(self.nodes[node_id].attr(local_name!("value"))?)
+    }
+
+    /// Store a new value on a date, color, file or text input and update the text it displays (which also depends
+    /// on the chosen files of a file input)
+    pub(crate) fn set_input_value(&mut self, node_id: usize, value: String) {
+        // Resetting the input's form restores the value from before the user changed it
+        let default = self.nodes[node_id]
+            .attr(local_name!("value"))
+            .map(str::to_string);
+        self.default_values.entry(node_id).or_insert(default);
+        self.set_attribute(
+            node_id,
+            QualName::new(None, ns!(), local_name!("value")),
+            &value,
+        );
+        self.update_picker_input_text(node_id);
+    }
+
+    /// Date, file, number, password and other text inputs display their value as a text child. Create or update that child to reflect the
+    /// input's current value. Called when inputs are created and whenever their value changes.
+    pub(crate) fn update_picker_input_text(&mut self, node_id: usize) {
+        let text = match self.picker_kind(node_id) {
+            Some(PickerKind::Date) => match self.date_value(node_id) {
+                Some(date) => date.to_string(),
+                None => "yyyy-mm-dd".to_string(),
+            },
+            Some(PickerKind::File) => {
+                let files = self.selected_files(node_id);
+                match files {
+                    [] => "No file chosen".to_string(),
+                    [file] => file
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    files => format!("{} files", files.len()),
+                }
+            }
+            _ if self.is_password_input(node_id) => self.password_display_text(node_id),
+            _ if self.is_number_input(node_id) || self.is_text_field(node_id) => self.nodes
+                [node_id]
+                .attr(local_name!("value"))
+                .unwrap_or_default()
+                .to_string(),
+            Some(PickerKind::Color | PickerKind::Suggestions) | None => return,
+        };
+
+        let text_child = self.nodes[node_id]
+            .children
+            .iter()
+            .copied()
+            .find(|&child_id| self.nodes[child_id].is_text_node());
+        match text_child {
+            Some(child_id) if self.nodes[child_id].text_data().unwrap().content == text => {}
+            Some(child_id) => {
+                self.record_text_change(child_id);
+                if let NodeData::Text(data) = &mut self.nodes[child_id].raw_dom_data {
+                    data.content = text;
+                }
+            }
+            None => {
+                let child_id = self.create_text_node(&text);
+                self.append_children(node_id, &[child_id]);
+            }
+        }
+
+        // Clear the layout cache of the input and its ancestors
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            self.nodes[id].cache.clear();
+            maybe_id = self.nodes[id].parent;
+        }
+    }
+
+    /// Compute the cells, size and position of the open picker
+    pub(crate) fn layout_picker(&mut self) {
+        let Some(mut picker) = self.open_picker.take() else {
+            return;
+        };
+        let node_id = picker.node_id;
+        if !self.nodes.contains(node_id) {
+            return;
+        }
+
+        let mut cells = Vec::new();
+        let size = match picker.kind {
+            PickerKind::Date => self.layout_calendar(node_id, picker.month, &mut cells),
+            PickerKind::Color => self.layout_swatches(node_id, &mut cells),
+            PickerKind::Suggestions => {
+                // The dropdown closes when nothing matches
+                picker.suggestions = self.suggestions(node_id);
+                picker.suggestions.truncate(MAX_SUGGESTIONS);
+                if picker.suggestions.is_empty() {
+                    return;
+                }
+                picker.highlighted = picker
+                    .highlighted
+                    .filter(|index| *index < picker.suggestions.len());
+                self.layout_suggestions(&picker, &mut cells)
+            }
+            PickerKind::File => return,
+        };
+
+        // Anchor the picker below the input, or above it if there isn't room below
+        let layout = self.nodes[node_id].final_layout;
+        let position = self.absolute_position(node_id);
+        let viewport = self.stylist.device().au_viewport_size();
+        let viewport_width = viewport.width.to_f32_px();
+        let viewport_height = viewport.height.to_f32_px();
+        let top = position.y - self.viewport_scroll as f32;
+        let bottom = top + layout.size.height;
+        let y = if bottom + size.height > viewport_height && top - size.height >= 0.0 {
+            top - size.height
+        } else {
+            bottom
+        };
+        let x = position.x.min(viewport_width - size.width).max(0.0);
+
+        self.open_picker = Some(OpenPicker {
+            location: taffy::Point { x, y },
+            size,
+            cells,
+            ..picker
+        });
+    }
+
+    fn layout_calendar(
+        &mut self,
+        node_id: usize,
+        (year, month): (i32, u32),
+        cells: &mut Vec<PickerCell>,
+    ) -> taffy::Size<f32> {
+        let selected = self.date_value(node_id);
+        let cell_size = CALENDAR_CELL_SIZE;
+        let width = cell_size.width * 7.0;
+
+        // Header: previous month, month and year, next month
+        let mut cell = |location: taffy::Point<f32>, size, action, text: &str| PickerCell {
+            location,
+            size,
+            action,
+            selected: false,
+            label: Some(self.build_picker_label(node_id, text)),
+            color: None,
+        };
+        let origin = taffy::Point {
+            x: PICKER_PADDING,
+            y: PICKER_PADDING,
+        };
+        cells.push(cell(origin, cell_size, PickerAction::PreviousMonth, "‹"));
+        cells.push(cell(
+            taffy::Point {
+                x: origin.x + cell_size.width,
+                y: origin.y,
+            },
+            taffy::Size {
+                width: width - 2.0 * cell_size.width,
+                height: cell_size.height,
+            },
+            PickerAction::None,
+            &format!("{} {}", MONTH_NAMES[month as usize - 1], year),
+        ));
+        cells.push(cell(
+            taffy::Point {
+                x: origin.x + width - cell_size.width,
+                y: origin.y,
+            },
+            cell_size,
+            PickerAction::NextMonth,
+            "›",
+        ));
+
+        // Weekday names
+        for (column, name) in WEEKDAY_NAMES.iter().enumerate() {
+            cells.push(cell(
+                taffy::Point {
+                    x: origin.x + column as f32 * cell_size.width,
+                    y: origin.y + cell_size.height,
+                },
+                cell_size,
+                PickerAction::None,
+                name,
+            ));
+        }
+
+        // Days
+        let first_weekday = Date {
+            year,
+            month,
+            day: 1,
+        }
+        .weekday();
+        let mut rows = 0;
+        for day in 1..=days_in_month(year, month) {
+            let index = first_weekday + day - 1;
+            let (row, column) = (index / 7, index % 7);
+            rows = row + 1;
+            let date = Date { year, month, day };
+            cells.push(PickerCell {
+                selected: selected == Some(date),
+                ..cell(
+                    taffy::Point {
+                        x: origin.x + column as f32 * cell_size.width,
+                        y: origin.y + (row + 2) as f32 * cell_size.height,
+                    },
+                    cell_size,
+                    PickerAction::SelectDate(date),
+                    &day.to_string(),
+                )
+            });
+        }
+
+        taffy::Size {
+            width: width + 2.0 * PICKER_PADDING,
+            height: (rows + 2) as f32 * cell_size.height + 2.0 * PICKER_PADDING,
+        }
+    }
+
+    fn layout_swatches(&mut self, node_id: usize, cells: &mut Vec<PickerCell>) -> taffy::Size<f32> {
+        let selected = self.nodes[node_id]
+            .attr(local_name!("value"))
+            .and_then(parse_simple_color);
+
+        // A row of greys followed by rows of saturated hues at decreasing lightness
+        let mut palette = Vec::new();
+        for i in 0..SWATCH_COLUMNS {
+            let level = (255.0 * i as f32 / (SWATCH_COLUMNS - 1) as f32).round() as u8;
+            palette.push([level, level, level]);
+        }
+        for lightness in [0.8, 0.6, 0.5, 0.35] {
+            for i in 0..SWATCH_COLUMNS {
+                let hue = 360.0 * i as f32 / SWATCH_COLUMNS as f32;
+                palette.push(hsl_to_rgb(hue, 0.8, lightness));
+            }
+        }
+
+        let step = SWATCH_SIZE + SWATCH_GAP;
+        for (i, color) in palette.iter().copied().enumerate() {
+            let (row, column) = (i / SWATCH_COLUMNS, i % SWATCH_COLUMNS);
+            cells.push(PickerCell {
+                location: taffy::Point {
+                    x: PICKER_PADDING + column as f32 * step,
+                    y: PICKER_PADDING + row as f32 * step,
+                },
+                size: taffy::Size {
+                    width: SWATCH_SIZE,
+                    height: SWATCH_SIZE,
+                },
+                action: PickerAction::SelectColor(color),
+                selected: selected == Some(color),
+                label: None,
+                color: Some(color),
+            });
+        }
+
+        let rows = palette.len().div_ceil(SWATCH_COLUMNS);
+        taffy::Size {
+            width: SWATCH_COLUMNS as f32 * step - SWATCH_GAP + 2.0 * PICKER_PADDING,
+            height: rows as f32 * step - SWATCH_GAP + 2.0 * PICKER_PADDING,
+        }
+    }
+
+    /// Shape a label for a picker cell using the text styles of the input
+    pub(crate) fn build_picker_label(&mut self, node_id: usize, text: &str) -> TextLayout {
+        let style = self.nodes[node_id]
+            .primary_styles()
+            .map(|style| stylo_to_parley::style(&style))
+            .unwrap_or_default();
+
+        let mut builder = self
+            .layout_ctx
+            .tree_builder(&mut self.font_ctx, self.scale, &style);
+        builder.push_text(text);
+        let (mut layout, text) = builder.build();
+        layout.break_all_lines(None);
+        layout.align(None, Alignment::Start);
+
+        TextLayout {
+            text,
+            layout,
+            line_break_bucket: Some(None),
+        }
+    }
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    [channel(r), channel(g), channel(b)]
+}
+
+#[test]
+fn opens_calendars_at_the_input_month() {
+    use crate::test_util::parse_test_document;
+
+    assert_eq!(Date::from_unix_days(0), Date::parse("1970-01-01").unwrap());
+    assert_eq!(
+        Date::from_unix_days(19_782),
+        Date::parse("2024-02-29").unwrap()
+    );
+    assert_eq!(Date::from_unix_days(-1), Date::parse("1969-12-31").unwrap());
+
+    let html =
+        "<!DOCTYPE html><html><body><input type=date id=value value=2021-06-15 min=2020-01-01>\
+        <input type=date id=min min=2030-03-01><input type=date id=empty></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let mut month = |id| {
+        let node_id = doc.get_element_by_id(id).unwrap();
+        doc.activate_picker(node_id);
+        let month = doc.open_picker().unwrap().month;
+        doc.close_picker();
+        month
+    };
+
+    assert_eq!(month("value"), (2021, 6));
+    assert_eq!(month("min"), (2030, 3));
+    let today = Date::today();
+    assert_eq!(month("empty"), (today.year, today.month));
+}
+
+#[test]
+fn applies_files_once_the_dialog_closes() {
+    use crate::test_util::parse_test_document;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Wake, Waker};
+
+    struct NoopWaker;
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Records the options it was shown with, and returns whatever files the test chooses
+    #[derive(Clone, Default)]
+    struct TestDialog {
+        shown: Arc<Mutex<Vec<bool>>>,
+        chosen: Arc<Mutex<Option<Option<Vec<PathBuf>>>>>,
+    }
+    impl FileDialog for TestDialog {
+        fn show(&mut self, options: &FileDialogOptions) {
+            self.shown.lock().unwrap().push(options.multiple);
+        }
+        fn poll_files(&mut self, _cx: &mut Context<'_>) -> Poll<Option<Vec<PathBuf>>> {
+            match self.chosen.lock().unwrap().take() {
+                Some(files) => Poll::Ready(files),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    let html = "<!DOCTYPE html><html><body><input type=file id=file></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let dialog = TestDialog::default();
+    doc.set_file_dialog(Box::new(dialog.clone()));
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let file = doc.get_element_by_id("file").unwrap();
+
+    // Nothing changes while the dialog is showing, and activating the input again doesn't show another
+    doc.activate_picker(file);
+    doc.activate_picker(file);
+    assert_eq!(*dialog.shown.lock().unwrap(), [false]);
+    assert_eq!(doc.poll_file_dialog(&mut cx), None);
+
+    // Only the first file is kept, as the input doesn't accept multiple files
+    let files = vec![PathBuf::from("/tmp/a.txt"), PathBuf::from("/tmp/b.txt")];
+    *dialog.chosen.lock().unwrap() = Some(Some(files));
+    assert_eq!(doc.poll_file_dialog(&mut cx), Some(file));
+    assert_eq!(doc.selected_files(file), [PathBuf::from("/tmp/a.txt")]);
+    assert_eq!(
+        doc.nodes[file].attr(local_name!("value")),
+        Some("C:\\fakepath\\a.txt")
+    );
+
+    // Cancelling the dialog keeps the chosen files
+    doc.activate_picker(file);
+    *dialog.chosen.lock().unwrap() = Some(None);
+    assert_eq!(doc.poll_file_dialog(&mut cx), None);
+    assert_eq!(doc.selected_files(file).len(), 1);
+}
@@ -0,0 +1,222 @@
+//! The HTML popover API
+//!
+//! <https://html.spec.whatwg.org/multipage/popover.html>
+//!
+//! Elements with a `popover` attribute are hidden until they are shown, either programmatically or by clicking a
+//! button whose `popovertarget` attribute references the popover's id. Open "auto" popovers are light dismissed
+//! by clicking outside of them or by pressing Escape.
+//!
+//! Note: the servo configuration of stylo does not have a `:popover-open` pseudo-class, so closed popovers are
+//! hidden by blitz directly (via [`Node::hidden`](crate::Node::hidden)) rather than by the UA stylesheet.
+use html5ever::LocalName;
+
+use crate::top_layer::TopLayerKind;
+use crate::Document;
+
+/// The state of an element's `popover` attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopoverState {
+    /// `popover`, `popover=""` or `popover="auto"`: can be light dismissed, and closes other auto popovers
+    Auto,
+    /// `popover="manual"`: must be closed explicitly
+    Manual,
+}
+
+/// The action performed by a `popovertarget` button (its `popovertargetaction` attribute)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopoverTargetAction {
+    Toggle,
+    Show,
+    Hide,
+}
+
+impl Document {
+    /// The popover state of an element, or `None` if it isn't a popover
+    pub fn popover_state(&self, node_id: usize) -> Option<PopoverState> {
+        let value = self.nodes[node_id].attr(LocalName::from("popover"))?;
+        match value.to_ascii_lowercase().as_str() {
+            "manual" => Some(PopoverState::Manual),
+            _ => Some(PopoverState::Auto),
+        }
+    }
+
+    /// Whether the popover is showing (ie. whether it matches `:popover-open`)
+    pub fn is_popover_open(&self, node_id: usize) -> bool {
+        self.open_popovers.contains(&node_id)
+    }
+
+    /// The open popovers, in the order in which they were shown
+    pub fn open_popovers(&self) -> &[usize] {
+        &self.open_popovers
+    }
+
+    /// Show a popover. Returns false if the node is not a popover or is already showing.
+    pub fn show_popover(&mut self, node_id: usize) -> bool {
+        let Some(state) = self.popover_state(node_id) else {
+            return false;
+        };
+        if self.is_popover_open(node_id) {
+            return false;
+        }
+
+        // Showing an auto popover closes all other auto popovers that aren't its ancestors
+        if state == PopoverState::Auto {
+            let to_close: Vec<usize> = self
+                .open_popovers
+                .iter()
+                .copied()
+                .filter(|&id| self.popover_state(id) == Some(PopoverState::Auto))
+                .filter(|&id| !self.is_inclusive_ancestor(id, node_id))
+                .collect();
+            for id in to_close {
+                self.hide_popover(id);
+            }
+        }
+
+        self.open_popovers.push(node_id);
+        self.set_node_hidden(node_id, false);
+        self.add_to_top_layer(node_id, TopLayerKind::Popover);
+        true
+    }
+
+    /// Hide a popover. Returns false if the node is not a showing popover.
+    pub fn hide_popover(&mut self, node_id: usize) -> bool {
+        let Some(idx) = self.open_popovers.iter().position(|&id| id == node_id) else {
+            return false;
+        };
+        self.open_popovers.remove(idx);
+        self.popover_invokers.remove(&node_id);
+        self.set_node_hidden(node_id, true);
+        self.remove_from_top_layer(node_id);
+        true
+    }
+
+    /// Toggle a popover. Returns false if the node is not a popover.
+    pub fn toggle_popover(&mut self, node_id: usize) -> bool {
+        if self.is_popover_open(node_id) {
+            self.hide_popover(node_id)
+        } else {
+            self.show_popover(node_id)
+        }
+    }
+
+    /// Close the most recently shown auto popover (in response to the Escape key).
+    ///
+    /// Returns true if a popover was closed.
+    pub fn hide_topmost_popover(&mut self) -> bool {
+        let topmost = self
+            .open_popovers
+            .iter()
+            .rev()
+            .copied()
+            .find(|&id| self.popover_state(id) == Some(PopoverState::Auto));
+        match topmost {
+            Some(id) => self.hide_popover(id),
+            None => false,
+        }
+    }
+
+    /// Perform the popover default actions for a click on `target`:
+    ///   - Invoke the popover targeted by a `popovertarget` button that is (or contains) the target
+    ///   - Light dismiss any open auto popovers that do not contain the target
+    ///
+    /// Returns true if any popover was shown or hidden.
+    pub fn handle_popover_click(&mut self, target: usize) -> bool {
+        let mut changed = false;
+
+        // Light dismiss
+        let to_close: Vec<usize> = self
+            .open_popovers
+            .iter()
+            .copied()
+            .filter(|&id| self.popover_state(id) == Some(PopoverState::Auto))
+            .filter(|&id| !self.is_inclusive_ancestor(id, target))
+            .collect();
+
+        // Find the popovertarget button (if any)
+        let mut invoker = None;
+        let mut maybe_id = Some(target);
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            if let Some(target_id) = node.attr(LocalName::from("popovertarget")) {
+                let action = match node.attr(LocalName::from("popovertargetaction")) {
+                    Some(action) if action.eq_ignore_ascii_case("show") => {
+                        PopoverTargetAction::Show
+                    }
+                    Some(action) if action.eq_ignore_ascii_case("hide") => {
+                        PopoverTargetAction::Hide
+                    }
+                    _ => PopoverTargetAction::Toggle,
+                };
+                invoker = self
+                    .get_element_by_id(target_id)
+                    .map(|popover_id| (popover_id, id, action));
+                break;
+            }
+            maybe_id = node.parent;
+        }
+
+        for id in to_close {
+            // Clicking the invoker of an open popover should toggle it closed, not close-then-reopen it
+            if invoker.map(|(popover_id, _, _)| popover_id) == Some(id) {
+                continue;
+            }
+            changed |= self.hide_popover(id);
+        }
+
+        if let Some((popover_id, invoker_id, action)) = invoker {
+            changed |= match action {
+                PopoverTargetAction::Toggle => self.toggle_popover(popover_id),
+                PopoverTargetAction::Show => self.show_popover(popover_id),
+                PopoverTargetAction::Hide => self.hide_popover(popover_id),
+            };
+            // The invoker is the implicit anchor of the popover it shows
+            if self.is_popover_open(popover_id) {
+                self.popover_invokers.insert(popover_id, invoker_id);
+            }
+        }
+
+        changed
+    }
+
+    /// Hide all popovers that are not showing. Called before each restyle so that popovers inserted since the
+    /// last frame start hidden.
+    pub(crate) fn flush_popover_visibility(&mut self) {
+        let popover_attr = LocalName::from("popover");
+        self.open_popovers.retain(|&id| {
+            self.nodes.contains(id) && self.nodes[id].attr(popover_attr.clone()).is_some()
+        });
+        let open_popovers = &self.open_popovers;
+        self.top_layer
+            .retain(|(id, kind)| *kind != TopLayerKind::Popover || open_popovers.contains(id));
+
+        for (id, node) in self.nodes.iter_mut() {
+            if node.attr(popover_attr.clone()).is_some() {
+                node.hidden = !self.open_popovers.contains(&id);
+            }
+        }
+    }
+
+    /// Whether `ancestor_id` is `node_id` or one of its ancestors
+    fn is_inclusive_ancestor(&self, ancestor_id: usize, node_id: usize) -> bool {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            if id == ancestor_id {
+                return true;
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        false
+    }
+
+    fn set_node_hidden(&mut self, node_id: usize, hidden: bool) {
+        self.nodes[node_id].hidden = hidden;
+
+        // Clear the layout cache of the node and its ancestors
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            self.nodes[id].cache.clear();
+            maybe_id = self.nodes[id].parent;
+        }
+    }
+}
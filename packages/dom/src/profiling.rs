@@ -0,0 +1,173 @@
+//! Frame timing instrumentation
+//!
+//! Each phase of producing a frame is timed and wrapped in a [`tracing`] span (named after the phase), so slow frames
+//! can be diagnosed either from the recorded timings or with any `tracing` subscriber (e.g. a flamegraph or Chrome
+//! trace layer). The document keeps the timings of its recent frames: [`Document::frame_timings`] is the last
+//! complete frame, and [`Document::frame_timing_history`] the ones before it.
+//!
+//! Building the DOM and resolving styles and layout are timed by the document itself. Renderers time painting and
+//! GPU submission, add them with [`Document::record_timing`], and complete the frame with [`Document::end_frame`]
+//! once it has been presented. Work done between frames (such as applying the mutations of a framework when the
+//! document is polled) counts towards the next frame.
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+use crate::Document;
+
+/// How many frames of timings are kept
+pub const FRAME_TIMING_HISTORY: usize = 120;
+
+/// A phase of producing a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FramePhase {
+    /// Building the DOM: parsing HTML and applying the mutations of frameworks
+    Parse,
+    /// Resolving styles
+    Style,
+    /// Laying out the document
+    Layout,
+    /// Building the display list (the scene) that the document is drawn with
+    Paint,
+    /// Rendering the display list on the GPU and presenting it
+    GpuSubmit,
+}
+
+impl FramePhase {
+    pub const ALL: [FramePhase; 5] = [
+        FramePhase::Parse,
+        FramePhase::Style,
+        FramePhase::Layout,
+        FramePhase::Paint,
+        FramePhase::GpuSubmit,
+    ];
+
+    /// The phase's name, which is also the name of its `tracing` span
+    pub fn name(self) -> &'static str {
+        match self {
+            FramePhase::Parse => "parse",
+            FramePhase::Style => "style",
+            FramePhase::Layout => "layout",
+            FramePhase::Paint => "paint",
+            FramePhase::GpuSubmit => "gpu_submit",
+        }
+    }
+}
+
+/// The time spent in each phase of a frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameTimings {
+    pub parse: Duration,
+    pub style: Duration,
+    pub layout: Duration,
+    pub paint: Duration,
+    pub gpu_submit: Duration,
+}
+
+impl FrameTimings {
+    pub fn get(&self, phase: FramePhase) -> Duration {
+        match phase {
+            FramePhase::Parse => self.parse,
+            FramePhase::Style => self.style,
+            FramePhase::Layout => self.layout,
+            FramePhase::Paint => self.paint,
+            FramePhase::GpuSubmit => self.gpu_submit,
+        }
+    }
+
+    fn get_mut(&mut self, phase: FramePhase) -> &mut Duration {
+        match phase {
+            FramePhase::Parse => &mut self.parse,
+            FramePhase::Style => &mut self.style,
+            FramePhase::Layout => &mut self.layout,
+            FramePhase::Paint => &mut self.paint,
+            FramePhase::GpuSubmit => &mut self.gpu_submit,
+        }
+    }
+
+    /// The time spent in all phases
+    pub fn total(&self) -> Duration {
+        FramePhase::ALL.iter().map(|phase| self.get(*phase)).sum()
+    }
+}
+
+/// The timings of the frame in progress and of recent frames
+#[derive(Default)]
+pub(crate) struct FrameProfiler {
+    current: FrameTimings,
+    history: VecDeque<FrameTimings>,
+}
+
+impl Document {
+    /// Add time spent in a phase to the frame in progress
+    pub fn record_timing(&mut self, phase: FramePhase, duration: Duration) {
+        *self.profiler.current.get_mut(phase) += duration;
+    }
+
+    /// Run `f` as part of a phase of the frame in progress, timing it and tracing it with a span
+    pub fn time_phase<R>(&mut self, phase: FramePhase, f: impl FnOnce(&mut Document) -> R) -> R {
+        let span = tracing::info_span!("frame_phase", phase = phase.name());
+        let _entered = span.enter();
+        let start = Instant::now();
+        let result = f(self);
+        self.record_timing(phase, start.elapsed());
+        result
+    }
+
+    /// Complete the frame in progress, adding its timings to the history
+    pub fn end_frame(&mut self) {
+        let profiler = &mut self.profiler;
+        if profiler.history.len() == FRAME_TIMING_HISTORY {
+            profiler.history.pop_front();
+        }
+        profiler
+            .history
+            .push_back(std::mem::take(&mut profiler.current));
+    }
+
+    /// The timings of the last complete frame
+    pub fn frame_timings(&self) -> FrameTimings {
+        self.profiler.history.back().copied().unwrap_or_default()
+    }
+
+    /// The timings of recent frames, oldest first
+    pub fn frame_timing_history(&self) -> impl ExactSizeIterator<Item = &FrameTimings> {
+        self.profiler.history.iter()
+    }
+
+    /// The mean timings of recent frames
+    pub fn average_frame_timings(&self) -> FrameTimings {
+        let history = &self.profiler.history;
+        let mut average = FrameTimings::default();
+        if history.is_empty() {
+            return average;
+        }
+        for phase in FramePhase::ALL {
+            let total: Duration = history.iter().map(|timings| timings.get(phase)).sum();
+            *average.get_mut(phase) = total / history.len() as u32;
+        }
+        average
+    }
+}
+
+#[test]
+fn records_frame_timings() {
+    use crate::test_util::test_document;
+
+    let mut doc = test_document(&[]);
+    let ms = Duration::from_millis;
+
+    doc.record_timing(FramePhase::Paint, ms(2));
+    doc.record_timing(FramePhase::Paint, ms(1));
+    assert_eq!(doc.time_phase(FramePhase::Style, |_| 7), 7);
+    // Timings only become visible when the frame is complete
+    assert_eq!(doc.frame_timings(), FrameTimings::default());
+
+    doc.end_frame();
+    assert_eq!(doc.frame_timings().paint, ms(3));
+    doc.record_timing(FramePhase::Paint, ms(5));
+    doc.end_frame();
+    assert_eq!(doc.frame_timings().paint, ms(5));
+    assert_eq!(doc.frame_timing_history().len(), 2);
+    assert_eq!(doc.average_frame_timings().paint, ms(4));
+}
@@ -0,0 +1,264 @@
+//! Size-change callbacks (the equivalent of `ResizeObserver`)
+//!
+//! Embedders create an observer with a callback, then add the nodes it should observe. At the end of each
+//! [`Document::resolve`], every observer whose nodes changed size is called back once with the new sizes of all of
+//! them. As with `ResizeObserver`, the first observation of a node is always delivered. Removing a node from the
+//! document stops it being observed.
+use crate::Document;
+
+/// Called with the new sizes of the nodes an observer observes that changed size in a frame
+pub type ResizeCallback = Box<dyn FnMut(&mut Document, &[ResizeEntry]) + Send>;
+
+/// Identifies an observer created with [`Document::create_resize_observer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResizeObserverHandle(u64);
+
+/// Which box of a node to watch for size changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeObserverBox {
+    #[default]
+    ContentBox,
+    BorderBox,
+}
+
+/// The size of a node that changed size
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResizeEntry {
+    pub node_id: usize,
+    pub border_box_size: taffy::Size<f32>,
+    pub content_box_size: taffy::Size<f32>,
+}
+
+struct ResizeTarget {
+    node_id: usize,
+    observed_box: ResizeObserverBox,
+    /// The size of the observed box when it was last delivered
+    last_size: Option<taffy::Size<f32>>,
+}
+
+struct ResizeObserver {
+    handle: ResizeObserverHandle,
+    callback: ResizeCallback,
+    targets: Vec<ResizeTarget>,
+}
+
+#[derive(Default)]
+pub(crate) struct ResizeObservers {
+    observers: Vec<ResizeObserver>,
+    next_handle: u64,
+}
+
+impl Document {
+    /// Create an observer that calls back when any of the nodes it observes change size
+    pub fn create_resize_observer(
+        &mut self,
+        callback: impl FnMut(&mut Document, &[ResizeEntry]) + Send + 'static,
+    ) -> ResizeObserverHandle {
+        let observers = &mut self.resize_observers;
+        let handle = ResizeObserverHandle(observers.next_handle);
+        observers.next_handle += 1;
+        observers.observers.push(ResizeObserver {
+            handle,
+            callback: Box::new(callback),
+            targets: Vec::new(),
+        });
+        handle
+    }
+
+    /// Start watching a node for changes to the size of one of its boxes. Observing a node again changes which box
+    /// is watched.
+    pub fn observe_resize(
+        &mut self,
+        handle: ResizeObserverHandle,
+        node_id: usize,
+        observed_box: ResizeObserverBox,
+    ) {
+        let Some(observer) = self.resize_observer_mut(handle) else {
+            return;
+        };
+        observer.targets.retain(|target| target.node_id != node_id);
+        observer.targets.push(ResizeTarget {
+            node_id,
+            observed_box,
+            last_size: None,
+        });
+
+        // Take the first observation in the next frame
+        self.invalidate();
+    }
+
+    /// Stop watching a node for size changes
+    pub fn unobserve_resize(&mut self, handle: ResizeObserverHandle, node_id: usize) {
+        if let Some(observer) = self.resize_observer_mut(handle) {
+            observer.targets.retain(|target| target.node_id != node_id);
+        }
+    }
+
+    /// Stop watching every node and drop the observer's callback
+    pub fn disconnect_resize_observer(&mut self, handle: ResizeObserverHandle) {
+        self.resize_observers
+            .observers
+            .retain(|observer| observer.handle != handle);
+    }
+
+    /// Stop observing a node that has been removed from the document, so that a node which reuses its id isn't
+    /// observed in its place
+    pub(crate) fn unobserve_removed_resizes(&mut self, node_id: usize) {
+        for observer in self.resize_observers.observers.iter_mut() {
+            observer.targets.retain(|target| target.node_id != node_id);
+        }
+    }
+
+    fn resize_observer_mut(&mut self, handle: ResizeObserverHandle) -> Option<&mut ResizeObserver> {
+        self.resize_observers
+            .observers
+            .iter_mut()
+            .find(|observer| observer.handle == handle)
+    }
+
+    /// Measure every observed node, and call back each observer whose nodes changed size
+    pub(crate) fn update_resize_observations(&mut self) {
+        if self.resize_observers.observers.is_empty() {
+            return;
+        }
+
+        // Callbacks may create (or disconnect) observers while they run, so each observer is looked up again before
+        // its nodes are measured. Observers created by the callbacks are first called back in the next frame.
+        let handles: Vec<ResizeObserverHandle> = self
+            .resize_observers
+            .observers
+            .iter()
+            .map(|observer| observer.handle)
+            .collect();
+
+        let mut delivered = false;
+        for handle in handles {
+            let Some(observer) = self
+                .resize_observers
+                .observers
+                .iter()
+                .find(|observer| observer.handle == handle)
+            else {
+                continue;
+            };
+
+            // The index of each target that changed size, with its new size
+            let mut changed = Vec::new();
+            let mut entries = Vec::new();
+            for (index, target) in observer.targets.iter().enumerate() {
+                let (border_box_size, content_box_size) =
+                    self.resize_observer_sizes(target.node_id);
+                let size = match target.observed_box {
+                    ResizeObserverBox::ContentBox => content_box_size,
+                    ResizeObserverBox::BorderBox => border_box_size,
+                };
+                if target.last_size == Some(size) {
+                    continue;
+                }
+                changed.push((index, size));
+                entries.push(ResizeEntry {
+                    node_id: target.node_id,
+                    border_box_size,
+                    content_box_size,
+                });
+            }
+            if entries.is_empty() {
+                continue;
+            }
+
+            let observer = self.resize_observer_mut(handle).unwrap();
+            for (index, size) in changed {
+                observer.targets[index].last_size = Some(size);
+            }
+            // The callback is put back unless it disconnected its own observer
+            let mut callback = std::mem::replace(&mut observer.callback, Box::new(|_, _| {}));
+            callback(self, &entries);
+            if let Some(observer) = self.resize_observer_mut(handle) {
+                observer.callback = callback;
+            }
+            delivered = true;
+        }
+
+        // Changes made by the callbacks are laid out in the next frame
+        if delivered {
+            self.invalidate();
+        }
+    }
+
+    /// The border box and content box sizes of a node. Nodes that don't generate boxes have empty boxes.
+    fn resize_observer_sizes(&self, node_id: usize) -> (taffy::Size<f32>, taffy::Size<f32>) {
+        let zero = taffy::Size {
+            width: 0.0,
+            height: 0.0,
+        };
+        let Some(client_rect) = self.client_rect(node_id) else {
+            return (zero, zero);
+        };
+        if client_rect == Default::default() {
+            return (zero, zero);
+        }
+
+        let layout = self.nodes[node_id].final_layout;
+        let content_box_size = taffy::Size {
+            width: (client_rect.width - layout.padding.left - layout.padding.right).max(0.0),
+            height: (client_rect.height - layout.padding.top - layout.padding.bottom).max(0.0),
+        };
+        (layout.size, content_box_size)
+    }
+}
+
+#[test]
+fn delivers_size_changes() {
+    use crate::test_util::parse_test_document;
+    use std::sync::{Arc, Mutex};
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div id=a style='width: 100px; height: 50px; padding: 5px'></div><div id=b style='height: 10px'></div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+    let (a, b) = (
+        doc.get_element_by_id("a").unwrap(),
+        doc.get_element_by_id("b").unwrap(),
+    );
+
+    // The callback disconnects its own observer once `stop` is set
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let own_handle = Arc::new(Mutex::new(None));
+    let stop = Arc::new(Mutex::new(false));
+    let (log, handle_cell, stop_flag) = (batches.clone(), own_handle.clone(), stop.clone());
+    let handle = doc.create_resize_observer(move |doc, entries| {
+        log.lock().unwrap().push(entries.to_vec());
+        if *stop_flag.lock().unwrap() {
+            doc.disconnect_resize_observer(handle_cell.lock().unwrap().unwrap());
+        }
+    });
+    *own_handle.lock().unwrap() = Some(handle);
+    doc.observe_resize(handle, a, ResizeObserverBox::ContentBox);
+    doc.observe_resize(handle, b, ResizeObserverBox::BorderBox);
+    let delivered = || std::mem::take(&mut *batches.lock().unwrap());
+
+    // The first observations of both nodes are delivered together, then only changes are
+    doc.resolve();
+    let first = delivered();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].len(), 2);
+    assert_eq!(
+        first[0][0].content_box_size,
+        taffy::Size {
+            width: 100.0,
+            height: 50.0
+        }
+    );
+    doc.resolve();
+    assert!(delivered().is_empty());
+
+    // Disconnecting from within the callback stops delivery
+    *stop.lock().unwrap() = true;
+    doc.inline_style(a).set_property("height", "60px");
+    doc.resolve();
+    assert_eq!(delivered().len(), 1);
+    doc.inline_style(a).set_property("height", "70px");
+    doc.resolve();
+    assert!(delivered().is_empty());
+}
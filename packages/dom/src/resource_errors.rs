@@ -0,0 +1,82 @@
+//! Reporting the resources (stylesheets and images) that a document couldn't load
+//!
+//! Failing to load a resource never stops the document from loading: a missing stylesheet is skipped, and a missing
+//! image is rendered as a broken image placeholder. The errors are passed to the embedder's handler (set with
+//! [`Document::set_resource_error_handler`]), or printed if there isn't one.
+use std::fmt;
+
+use crate::util::{FetchErr, ImageFetchErr};
+use crate::Document;
+
+/// The size of the broken image placeholder of an `<img>` without a `width` and `height`
+pub const BROKEN_IMAGE_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 16.0,
+    height: 16.0,
+};
+
+/// A resource that couldn't be loaded
+#[derive(Debug)]
+pub enum ResourceError {
+    /// The stylesheet of a `<link rel=stylesheet>` element
+    Stylesheet {
+        node_id: usize,
+        url: String,
+        error: FetchErr,
+    },
+    /// The image of an `<img>` element, or the poster of a `<video>` element
+    Image {
+        node_id: usize,
+        url: String,
+        error: ImageFetchErr,
+    },
+}
+
+impl ResourceError {
+    /// The element that the resource was loaded for
+    pub fn node_id(&self) -> usize {
+        match self {
+            Self::Stylesheet { node_id, .. } | Self::Image { node_id, .. } => *node_id,
+        }
+    }
+
+    /// The URL of the resource
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Stylesheet { url, .. } | Self::Image { url, .. } => url,
+        }
+    }
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stylesheet { url, error, .. } => {
+                write!(f, "Error fetching stylesheet {}: {}", url, error)
+            }
+            Self::Image { url, error, .. } => write!(f, "Error fetching image {}: {}", url, error),
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+/// A handler for resources that couldn't be loaded, set with [`Document::set_resource_error_handler`]
+pub type ResourceErrorHandler = Box<dyn FnMut(&ResourceError) + Send>;
+
+impl Document {
+    /// Set the handler that is called with each resource that couldn't be loaded. Set this before parsing the
+    /// document to be told about all of its resources.
+    pub fn set_resource_error_handler(
+        &mut self,
+        handler: impl FnMut(&ResourceError) + Send + 'static,
+    ) {
+        self.resource_error_handler = Some(Box::new(handler));
+    }
+
+    pub(crate) fn report_resource_error(&mut self, error: ResourceError) {
+        match self.resource_error_handler.as_mut() {
+            Some(handler) => handler(&error),
+            None => eprintln!("{}", error),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Sanitizing untrusted HTML while it's parsed
+//!
+//! Apps that show user-generated HTML (e.g. chat clients and feed readers) can parse it with
+//! [`DocumentHtmlParser::sanitized`](crate::DocumentHtmlParser::sanitized), which keeps the content but removes
+//! everything that could run code, embed other documents or take over the page:
+//!
+//! - `<script>`, `<iframe>`, `<frame>`, `<frameset>`, `<object>`, `<embed>`, `<applet>` and `<base>` elements are
+//!   removed, along with their contents
+//! - event handler attributes (`onclick`, `onload`, etc.) are removed
+//! - attributes whose value is a `javascript:` (or `vbscript:`) URL are removed
+//! - `http-equiv` attributes are removed, so that `<meta>` elements can't refresh or redirect the page
+use html5ever::{local_name, LocalName};
+
+use crate::node::Attribute;
+
+/// Whether an element with this name is removed from sanitized documents
+pub fn is_unsafe_element(name: &LocalName) -> bool {
+    matches!(
+        *name,
+        local_name!("script")
+            | local_name!("iframe")
+            | local_name!("frame")
+            | local_name!("frameset")
+            | local_name!("object")
+            | local_name!("embed")
+            | local_name!("applet")
+            | local_name!("base")
+    )
+}
+
+/// Whether an attribute is removed from the elements of sanitized documents
+pub fn is_unsafe_attribute(attr: &Attribute) -> bool {
+    attr.name.local.starts_with("on")
+        || attr.name.local == local_name!("http-equiv")
+        || is_script_url(&attr.value)
+}
+
+/// Whether a URL runs a script when it's followed, e.g. `javascript:alert(1)`. Browsers ignore whitespace and
+/// control characters around and within the scheme, so these are ignored here too.
+pub fn is_script_url(url: &str) -> bool {
+    let scheme: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .take_while(|c| *c != ':')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let has_scheme = url.contains(':');
+    has_scheme && (scheme == "javascript" || scheme == "vbscript")
+}
+
+/// Remove the unsafe attributes of an element
+pub(crate) fn sanitize_attributes(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|attr| !is_unsafe_attribute(attr));
+}
+
+#[test]
+fn detects_script_urls() {
+    assert!(is_script_url("javascript:alert(1)"));
+    assert!(is_script_url(" JaVa\tScRiPt:alert(1)"));
+    assert!(!is_script_url("https://example.com/javascript:"));
+    assert!(!is_script_url("javascript"));
+}
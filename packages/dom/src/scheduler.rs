@@ -0,0 +1,136 @@
+//! Frame scheduling
+//!
+//! Rather than redrawing whenever something might have changed, embedders ask the document when its next frame is due
+//! ([`Document::next_frame_deadline`]) and start frames with [`Document::begin_frame`] before resolving and painting
+//! the document. Invalidations made between frames (with [`Document::invalidate`]) are coalesced into a single frame,
+//! and frames are paced to the frame interval so that animation frame callbacks which keep requesting frames don't
+//! spin the event loop.
+//!
+//! Frame timestamps come from a monotonic clock and are measured in milliseconds since the document was created. They
+//! are passed to animation frame callbacks and used as the current time of style animations.
+use std::time::Duration;
+use web_time::Instant;
+
+use crate::Document;
+
+/// The default interval between frames (60fps)
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+
+/// A callback to run at the start of the next frame, with the frame's timestamp
+pub type AnimationFrameCallback = Box<dyn FnOnce(&mut Document, f64) + Send>;
+
+/// Identifies a callback registered with [`Document::request_animation_frame`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AnimationFrameHandle(u64);
+
+pub(crate) struct FrameScheduler {
+    time_origin: Instant,
+    frame_interval: Duration,
+    /// When the last frame began
+    last_frame: Option<Instant>,
+    /// The timestamp of the last frame
+    frame_time: f64,
+    callbacks: Vec<(AnimationFrameHandle, AnimationFrameCallback)>,
+    next_handle: u64,
+    invalidated: bool,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self {
+            time_origin: Instant::now(),
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            last_frame: None,
+            frame_time: 0.0,
+            callbacks: Vec::new(),
+            next_handle: 0,
+            // The first frame is always needed
+            invalidated: true,
+        }
+    }
+}
+
+impl Document {
+    /// Run a callback at the start of the next frame. The callback is passed the frame's timestamp, and may request
+    /// another frame to keep animating.
+    pub fn request_animation_frame(
+        &mut self,
+        callback: impl FnOnce(&mut Document, f64) + Send + 'static,
+    ) -> AnimationFrameHandle {
+        let scheduler = &mut self.frame_scheduler;
+        let handle = AnimationFrameHandle(scheduler.next_handle);
+        scheduler.next_handle += 1;
+        scheduler.callbacks.push((handle, Box::new(callback)));
+        handle
+    }
+
+    /// Cancel a callback registered with [`Document::request_animation_frame`] that hasn't run yet
+    pub fn cancel_animation_frame(&mut self, handle: AnimationFrameHandle) {
+        self.frame_scheduler
+            .callbacks
+            .retain(|(other, _)| *other != handle);
+    }
+
+    /// Request a frame because something that affects rendering has changed
+    pub fn invalidate(&mut self) {
+        self.frame_scheduler.invalidated = true;
+    }
+
+    /// Whether a frame is needed, because the document was invalidated or there are animation frame callbacks waiting
+    /// to run
+    pub fn needs_frame(&self) -> bool {
+        self.frame_scheduler.invalidated || !self.frame_scheduler.callbacks.is_empty()
+    }
+
+    /// When the next frame should begin (if one is needed). This is never sooner than a frame interval after the
+    /// last frame began, unless a [timer](crate::timers) is due sooner.
+    pub fn next_frame_deadline(&self) -> Option<Instant> {
+        let scheduler = &self.frame_scheduler;
+        let frame_deadline = self.needs_frame().then(|| match scheduler.last_frame {
+            Some(last_frame) => last_frame + scheduler.frame_interval,
+            None => scheduler.time_origin,
+        });
+        match (frame_deadline, self.next_timer_deadline()) {
+            (Some(frame), Some(timer)) => Some(frame.min(timer)),
+            (frame, timer) => frame.or(timer),
+        }
+    }
+
+    /// Set the interval between frames, usually to match the refresh rate of the display
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.frame_scheduler.frame_interval = interval;
+    }
+
+    /// The timestamp of the current frame, in milliseconds since the document was created
+    pub fn frame_time(&self) -> f64 {
+        self.frame_scheduler.frame_time
+    }
+
+    /// Start a new frame: advance the frame clock, run the animation frame callbacks that were requested before it
+    /// began and the timers that are due, and advance smooth scrolling. Call this before resolving and painting the
+    /// document. Returns the frame's timestamp.
+    pub fn begin_frame(&mut self, now: Instant) -> f64 {
+        let scheduler = &mut self.frame_scheduler;
+
+        // The clock is monotonic even if the embedder's timestamps aren't
+        let frame_time = now
+            .saturating_duration_since(scheduler.time_origin)
+            .as_secs_f64()
+            * 1000.0;
+        scheduler.frame_time = scheduler.frame_time.max(frame_time);
+        scheduler.last_frame = Some(now);
+        scheduler.invalidated = false;
+
+        // Callbacks requested by these callbacks run in the next frame
+        let frame_time = scheduler.frame_time;
+        let callbacks = std::mem::take(&mut scheduler.callbacks);
+        for (_, callback) in callbacks {
+            callback(self, frame_time);
+        }
+
+        self.run_due_timers(now);
+        self.advance_scroll_animation(frame_time);
+
+        frame_time
+    }
+}
@@ -0,0 +1,131 @@
+//! Smooth scrolling of the viewport
+//!
+//! <https://drafts.csswg.org/cssom-view/#smooth-scrolling>
+//!
+//! Smooth scrolls animate the viewport towards their target over a short duration, advancing with the frame clock
+//! (see [`crate::scheduler`]). Scrolling again before an animation has finished retargets it, so that scrolls which
+//! follow each other quickly (such as the clicks of a mouse wheel) add up. Instant scrolls cancel any animation.
+//!
+//! The behavior of a scroll is chosen by whoever starts it. The renderer smooths the steps of mouse wheels, which is
+//! the user agent's choice: `scroll-behavior` only governs scrolls started by the page.
+//!
+//! Note: the `scroll-behavior` property and scroll snapping (`scroll-snap-type`, `scroll-snap-align`, etc) aren't
+//! supported, because the servo configuration of stylo doesn't have their properties. Scroll positions aren't
+//! snapped.
+use crate::Document;
+
+/// How long a smooth scroll takes, in milliseconds
+const SMOOTH_SCROLL_DURATION: f64 = 200.0;
+
+/// Whether a scroll jumps straight to its target or animates towards it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollBehavior {
+    #[default]
+    Instant,
+    Smooth,
+}
+
+/// A smooth scroll of the viewport in progress
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ScrollAnimation {
+    from: f64,
+    to: f64,
+    /// The frame time the animation started at, or `None` until its first frame
+    start_time: Option<f64>,
+}
+
+impl Document {
+    /// The furthest the viewport can be scrolled down the document. The end of the document can be scrolled into
+    /// the visual viewport (see [`crate::visual_viewport`]), out from under the soft keyboard.
+    pub fn max_viewport_scroll(&self) -> f64 {
+        let viewport_height = self.stylist.device().au_viewport_size().height.to_f64_px();
+        let insets = self.viewport_insets;
+        let content_height = self
+            .try_root_element()
+            .map_or(0.0, |root| root.final_layout.size.height as f64);
+        (content_height - viewport_height + insets.top as f64 + insets.bottom as f64).max(0.0)
+    }
+
+    /// Scroll the viewport to a position (clamped to the scrollable range of the document)
+    pub fn scroll_viewport_to(&mut self, scroll: f64, behavior: ScrollBehavior) {
+        let scroll = scroll.min(self.max_viewport_scroll()).max(0.0);
+        match behavior {
+            ScrollBehavior::Instant => {
+                self.scroll_animation = None;
+                self.viewport_scroll = scroll;
+            }
+            ScrollBehavior::Smooth => {
+                self.scroll_animation = Some(ScrollAnimation {
+                    from: self.viewport_scroll,
+                    to: scroll,
+                    start_time: None,
+                });
+            }
+        }
+        self.invalidate();
+    }
+
+    /// Where the viewport is scrolling to: the target of the smooth scroll in progress, or otherwise the current
+    /// scroll position. Relative scrolls should be measured from here.
+    pub fn viewport_scroll_target(&self) -> f64 {
+        self.scroll_animation
+            .map_or(self.viewport_scroll, |animation| animation.to)
+    }
+
+    /// Whether a smooth scroll is in progress
+    pub fn is_smooth_scrolling(&self) -> bool {
+        self.scroll_animation.is_some()
+    }
+
+    /// Advance the smooth scroll in progress (if any) to the given frame time
+    pub(crate) fn advance_scroll_animation(&mut self, frame_time: f64) {
+        let Some(animation) = self.scroll_animation.as_mut() else {
+            return;
+        };
+        let start_time = *animation.start_time.get_or_insert(frame_time);
+        let progress = ((frame_time - start_time) / SMOOTH_SCROLL_DURATION).clamp(0.0, 1.0);
+
+        // Ease out, so the scroll responds immediately and settles gently
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        self.viewport_scroll = animation.from + (animation.to - animation.from) * eased;
+
+        if progress >= 1.0 {
+            self.scroll_animation = None;
+        } else {
+            self.invalidate();
+        }
+    }
+}
+
+#[test]
+fn interpolates_smooth_scrolls() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'><div style='height: 2000px'></div></body></html>";
+    let mut doc = parse_test_document(html, &["html, body, div { display: block }"]);
+    doc.resolve();
+
+    // The scroll starts from where the viewport is on its first frame, easing out towards its target
+    doc.scroll_viewport_to(300.0, ScrollBehavior::Smooth);
+    assert!(doc.is_smooth_scrolling());
+    assert_eq!(doc.viewport_scroll_target(), 300.0);
+    doc.advance_scroll_animation(1000.0);
+    assert_eq!(doc.viewport_scroll(), 0.0);
+    doc.advance_scroll_animation(1100.0);
+    assert_eq!(doc.viewport_scroll(), 262.5);
+
+    // Scrolling again retargets the animation from the current position
+    let target = doc.viewport_scroll_target() + 100.0;
+    doc.scroll_viewport_to(target, ScrollBehavior::Smooth);
+    doc.advance_scroll_animation(1150.0);
+    assert_eq!(doc.viewport_scroll(), 262.5);
+    doc.advance_scroll_animation(1350.0);
+    assert_eq!(doc.viewport_scroll(), 400.0);
+    assert!(!doc.is_smooth_scrolling());
+
+    // Instant scrolls cancel the animation, and scrolls are clamped to the document
+    doc.scroll_viewport_to(100.0, ScrollBehavior::Smooth);
+    doc.scroll_viewport_to(5000.0, ScrollBehavior::Instant);
+    assert!(!doc.is_smooth_scrolling());
+    assert_eq!(doc.viewport_scroll(), 1400.0);
+}
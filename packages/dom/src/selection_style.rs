@@ -0,0 +1,164 @@
+//! The colors of the selection and the caret
+//!
+//! <https://drafts.csswg.org/css-pseudo-4/#highlight-pseudos>
+//! <https://drafts.csswg.org/css-ui/#caret-color>
+//!
+//! Selected text is highlighted with the `background-color` of its `::selection` pseudo-element, and drawn again in
+//! its `color`. Elements without `::selection` rules use the `::selection` of their closest ancestor that has them
+//! (as highlight pseudo-elements inherit), or the palette's translucent selection color over the text as it is.
+//!
+//! Note: the servo configuration of stylo doesn't parse the `caret-color` property, so the caret is always drawn with
+//! `caret-color: auto`: in the text color of the element that it's in.
+use peniko::Color;
+use style::properties::ComputedValues;
+use style::selector_parser::PseudoElement;
+use style::servo_arc::Arc;
+
+use crate::util::ToPenikoColor;
+use crate::Document;
+
+/// The part of the selection within one text node, and how to highlight it
+#[derive(Clone, Debug)]
+pub struct SelectionHighlight {
+    /// The selected text node
+    pub node_id: usize,
+    /// The rectangles covered by the selected text (in document coordinates), one per line
+    pub rects: Vec<taffy::Layout>,
+    /// The color to fill the rectangles with
+    pub background: Color,
+    /// The color to draw the selected text in, if a `::selection` rule sets one. Otherwise, the background is
+    /// translucent and drawn over the text.
+    pub color: Option<Color>,
+    /// The inline formatting context that the text is laid out in, and the origin of its content box in document
+    /// coordinates, for drawing the text again
+    pub inline_root: (usize, taffy::Point<f32>),
+}
+
+impl Document {
+    /// How to highlight each selected text node. Empty if there is no selection or it is collapsed.
+    pub fn selection_highlights(&self) -> Vec<SelectionHighlight> {
+        let Some((start, end)) = self.selection() else {
+            return Vec::new();
+        };
+        if start == end {
+            return Vec::new();
+        }
+        let Some(host) = self.editing_host(start.node_id) else {
+            return Vec::new();
+        };
+
+        let mut highlights = Vec::new();
+        let mut selecting = false;
+        for node_id in self.editable_text_nodes(host) {
+            let content = &self.nodes[node_id].text_data().unwrap().content;
+            if node_id == start.node_id {
+                selecting = true;
+            }
+            if !selecting {
+                continue;
+            }
+            let from = if node_id == start.node_id {
+                start.offset
+            } else {
+                0
+            };
+            let to = if node_id == end.node_id {
+                end.offset
+            } else {
+                content.len()
+            };
+
+            if from < to {
+                let rects = self.text_range_rects(node_id, from..to);
+                let inline_root = self.inline_context(node_id);
+                if let (Some(rects), Some(inline_root)) = (rects, inline_root) {
+                    let (background, color) = self.selection_colors(node_id);
+                    highlights.push(SelectionHighlight {
+                        node_id,
+                        rects,
+                        background,
+                        color,
+                        inline_root,
+                    });
+                }
+            }
+            if node_id == end.node_id {
+                break;
+            }
+        }
+        highlights
+    }
+
+    /// The color of the caret: the text color of the element that it's in
+    pub fn caret_color(&self) -> Option<Color> {
+        let caret = self.caret()?;
+        let parent_id = self.nodes.get(caret.node_id)?.parent?;
+        let style = self.nodes[parent_id].primary_styles()?;
+        Some(style.get_inherited_text().color.as_peniko())
+    }
+
+    /// The background and text colors of selected text
+    fn selection_colors(&self, node_id: usize) -> (Color, Option<Color>) {
+        let Some(style) = self.selection_style(node_id) else {
+            let [r, g, b, a] = self.control_palette().selection;
+            return (Color::rgba8(r, g, b, a), None);
+        };
+        let color = style.get_inherited_text().color;
+        let background = style
+            .get_background()
+            .background_color
+            .resolve_to_absolute(&color);
+        (background.as_peniko(), Some(color.as_peniko()))
+    }
+
+    /// The `::selection` style of the closest element with one, from a node's parent up
+    fn selection_style(&self, node_id: usize) -> Option<Arc<ComputedValues>> {
+        let mut maybe_id = self.nodes[node_id].parent;
+        while let Some(id) = maybe_id {
+            let node = &self.nodes[id];
+            let style = node
+                .stylo_element_data
+                .borrow()
+                .as_ref()
+                .and_then(|data| data.styles.pseudos.get(&PseudoElement::Selection).cloned());
+            if style.is_some() {
+                return style;
+            }
+            maybe_id = node.parent;
+        }
+        None
+    }
+}
+
+#[test]
+fn selection_uses_selection_styles() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><head><style>\
+        #styled::selection { background-color: rgb(255, 0, 0); color: rgb(255, 255, 255) }\
+        </style></head><body>\
+        <div contenteditable id=styled>hello <b>world</b></div>\
+        <div contenteditable id=plain style='color: rgb(0, 0, 255)'>plain</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    // Both text nodes of the styled host use its `::selection`, including the one within `<b>`
+    let styled = doc.get_element_by_id("styled").unwrap();
+    assert!(doc.select_all(styled));
+    let highlights = doc.selection_highlights();
+    assert_eq!(highlights.len(), 2);
+    for highlight in &highlights {
+        assert_eq!(highlight.background, Color::rgb8(255, 0, 0));
+        assert_eq!(highlight.color, Some(Color::rgb8(255, 255, 255)));
+    }
+
+    // Other text is highlighted with the palette's color, and the caret is drawn in the text color
+    let plain = doc.get_element_by_id("plain").unwrap();
+    assert!(doc.select_all(plain));
+    let highlights = doc.selection_highlights();
+    assert_eq!(highlights.len(), 1);
+    let [r, g, b, a] = doc.control_palette().selection;
+    assert_eq!(highlights[0].background, Color::rgba8(r, g, b, a));
+    assert_eq!(highlights[0].color, None);
+    assert_eq!(doc.caret_color(), Some(Color::rgb8(0, 0, 255)));
+}
@@ -0,0 +1,323 @@
+//! Saving and restoring the state of a document that isn't in its markup
+//!
+//! A [`SessionState`] records how far the viewport has been scrolled, the values of form controls and where the
+//! caret is. Embedders capture it before replacing a document (e.g. when reloading it, or hot-reloading an app) and
+//! restore it into the new document, so that the user doesn't lose their place or what they've typed.
+//!
+//! Node ids aren't stable across reloads, so elements are identified by their `id` attribute or, failing that, by
+//! their position in the tree. Only the viewport is recorded: elements don't scroll in Blitz. The values of password
+//! inputs aren't recorded, so that they aren't written out with the rest of the state.
+use html5ever::{local_name, namespace_url, ns, QualName};
+use serde::{Deserialize, Serialize};
+
+use crate::editing::Caret;
+use crate::Document;
+
+/// Identifies an element in a document that may have been reloaded
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ElementKey {
+    /// The element's `id` attribute
+    Id(String),
+    /// The index of the element among its parent's element children, for each of its ancestors from the root
+    Path(Vec<usize>),
+}
+
+/// The state of a form control
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldState {
+    /// The value of a text-like `<input>` or a `<textarea>`
+    Value(String),
+    /// Whether a checkbox or radio button is checked
+    Checked(bool),
+    /// The indices of the selected options of a `<select>`
+    Selected(Vec<usize>),
+}
+
+/// The position of the caret, as the index of a text node within its editing host and a byte offset into it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaretState {
+    pub host: ElementKey,
+    pub text_node: usize,
+    pub offset: usize,
+}
+
+/// The state of a document that embedders can capture and restore across reloads
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// How far the viewport was scrolled down the document
+    pub viewport_scroll: f64,
+    /// The state of each form control
+    pub fields: Vec<(ElementKey, FieldState)>,
+    /// The caret, within the focused editing host
+    pub caret: Option<CaretState>,
+}
+
+impl Document {
+    /// Capture the scroll position, form control values and caret of the document
+    pub fn session_state(&self) -> SessionState {
+        let mut fields = Vec::new();
+        for tag in [
+            local_name!("input"),
+            local_name!("textarea"),
+            local_name!("select"),
+        ] {
+            for node_id in self.get_elements_by_tag_name(tag) {
+                if let Some(field) = self.field_state(node_id) {
+                    fields.push((self.element_key(node_id), field));
+                }
+            }
+        }
+
+        let caret = self.caret.and_then(|caret| {
+            let host = self.editing_host(caret.node_id)?;
+            let text_node = self
+                .editable_text_nodes(host)
+                .iter()
+                .position(|&node_id| node_id == caret.node_id)?;
+            Some(CaretState {
+                host: self.element_key(host),
+                text_node,
+                offset: caret.offset,
+            })
+        });
+
+        SessionState {
+            viewport_scroll: self.viewport_scroll,
+            fields,
+            caret,
+        }
+    }
+
+    /// Restore state captured with [`Document::session_state`]. Elements that can no longer be found, or that are no
+    /// longer the same kind of form control, are skipped.
+    pub fn restore_session_state(&mut self, state: &SessionState) {
+        self.set_viewport_scroll(state.viewport_scroll);
+
+        for (key, field) in &state.fields {
+            if let Some(node_id) = self.find_element(key) {
+                self.restore_field_state(node_id, field);
+            }
+        }
+
+        self.clear_caret();
+        if let Some(caret) = &state.caret {
+            let text_node = self
+                .find_element(&caret.host)
+                .filter(|&host| self.editing_host(host) == Some(host))
+                .and_then(|host| self.editable_text_nodes(host).get(caret.text_node).copied());
+            if let Some(node_id) = text_node {
+                let len = self.nodes[node_id].text_data().unwrap().content.len();
+                let offset = caret.offset.min(len);
+                self.set_caret(Caret { node_id, offset }, false);
+            }
+        }
+    }
+
+    /// A key that identifies the element across reloads
+    pub fn element_key(&self, node_id: usize) -> ElementKey {
+        if let Some(id) = self.nodes[node_id].attr(local_name!("id")) {
+            return ElementKey::Id(id.to_string());
+        }
+
+        let mut path = Vec::new();
+        let mut node = &self.nodes[node_id];
+        while let Some(parent_id) = node.parent {
+            let parent = &self.nodes[parent_id];
+            let index = parent.children[..node.child_idx]
+                .iter()
+                .filter(|&&sibling_id| self.nodes[sibling_id].is_element())
+                .count();
+            path.push(index);
+            node = parent;
+        }
+        path.reverse();
+        ElementKey::Path(path)
+    }
+
+    /// The element identified by a key (if there still is one)
+    pub fn find_element(&self, key: &ElementKey) -> Option<usize> {
+        match key {
+            ElementKey::Id(id) => self.get_element_by_id(id),
+            ElementKey::Path(path) => path.iter().try_fold(0, |node_id, &index| {
+                self.nodes[node_id]
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|&child_id| self.nodes[child_id].is_element())
+                    .nth(index)
+            }),
+        }
+    }
+
+    fn field_state(&self, node_id: usize) -> Option<FieldState> {
+        let node = &self.nodes[node_id];
+        let element_data = node.element_data()?;
+        match element_data.name.local.as_ref() {
+            "textarea" => Some(FieldState::Value(node.text_content())),
+            "select" => Some(FieldState::Selected(
+                self.select_options(node_id)
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &option_id)| {
+                        self.nodes[option_id]
+                            .attr(local_name!("selected"))
+                            .is_some()
+                    })
+                    .map(|(index, _)| index)
+                    .collect(),
+            )),
+            _ => {
+                let ty = element_data.attr(local_name!("type")).unwrap_or("text");
+                if ty.eq_ignore_ascii_case("checkbox") || ty.eq_ignore_ascii_case("radio") {
+                    Some(FieldState::Checked(
+                        element_data.attr(local_name!("checked")).is_some(),
+                    ))
+                } else if [
+                    "button", "submit", "reset", "image", "file", "hidden", "password",
+                ]
+                .iter()
+                .any(|skipped| ty.eq_ignore_ascii_case(skipped))
+                {
+                    None
+                } else {
+                    Some(FieldState::Value(
+                        element_data
+                            .attr(local_name!("value"))
+                            .unwrap_or_default()
+                            .to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn restore_field_state(&mut self, node_id: usize, field: &FieldState) {
+        if self.field_state(node_id).as_ref() == Some(field) {
+            return;
+        }
+        let is_textarea = self.nodes[node_id].is_element_with_tag_name(&local_name!("textarea"));
+        let is_select = self.nodes[node_id].is_element_with_tag_name(&local_name!("select"));
+        match field {
+            FieldState::Value(value) if is_textarea => {
+                for child_id in self.nodes[node_id].children.clone() {
+                    self.remove_node(child_id);
+                }
+                let text_id = self.create_text_node(value);
+                self.append_children(node_id, &[text_id]);
+            }
+            FieldState::Value(value) if !is_select => {
+                self.set_attribute(
+                    node_id,
+                    QualName::new(None, ns!(), local_name!("value")),
+                    value,
+                );
+            }
+            FieldState::Checked(true) if !is_textarea && !is_select => {
+                self.set_attribute(
+                    node_id,
+                    QualName::new(None, ns!(), local_name!("checked")),
+                    "",
+                );
+            }
+            FieldState::Checked(false) if !is_textarea && !is_select => {
+                self.remove_attribute(node_id, &local_name!("checked"));
+            }
+            FieldState::Selected(indices) if is_select => {
+                for (index, option_id) in self.select_options(node_id).into_iter().enumerate() {
+                    if indices.contains(&index) {
+                        self.set_attribute(
+                            option_id,
+                            QualName::new(None, ns!(), local_name!("selected")),
+                            "",
+                        );
+                    } else {
+                        self.remove_attribute(option_id, &local_name!("selected"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The `<option>` elements of a `<select>`, including those within `<optgroup>`s
+    fn select_options(&self, node_id: usize) -> Vec<usize> {
+        let mut options = Vec::new();
+        for child_id in self.nodes[node_id].children.iter().copied() {
+            let child = &self.nodes[child_id];
+            if child.is_element_with_tag_name(&local_name!("option")) {
+                options.push(child_id);
+            } else if child.is_element_with_tag_name(&local_name!("optgroup")) {
+                options.extend(
+                    child.children.iter().copied().filter(|&id| {
+                        self.nodes[id].is_element_with_tag_name(&local_name!("option"))
+                    }),
+                );
+            }
+        }
+        options
+    }
+}
+
+#[test]
+fn restores_form_state_into_a_reloaded_document() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input id=name value=initial><form><input type=checkbox><textarea>notes</textarea></form>\
+        <select id=size><option>S<optgroup><option selected>M<option>L</optgroup></select>\
+        <div id=editor contenteditable>hello</div>\
+        </body></html>";
+    let load = || parse_test_document(html, &[]);
+
+    let mut doc = load();
+    let name_id = doc.get_element_by_id("name").unwrap();
+    let checkbox_id = doc.get_elements_by_tag_name(local_name!("input"))[1];
+    let textarea_id = doc.get_elements_by_tag_name(local_name!("textarea"))[0];
+    let select_id = doc.get_element_by_id("size").unwrap();
+    let options = doc.select_options(select_id);
+    doc.set_attribute(
+        name_id,
+        QualName::new(None, ns!(), local_name!("value")),
+        "typed",
+    );
+    doc.set_attribute(
+        checkbox_id,
+        QualName::new(None, ns!(), local_name!("checked")),
+        "",
+    );
+    let text_id = doc.nodes[textarea_id].children[0];
+    doc.nodes[text_id].text_data_mut().unwrap().content = "edited notes".to_string();
+    doc.remove_attribute(options[1], &local_name!("selected"));
+    doc.set_attribute(
+        options[2],
+        QualName::new(None, ns!(), local_name!("selected")),
+        "",
+    );
+    let editor_id = doc.get_element_by_id("editor").unwrap();
+    let editor_text_id = doc.nodes[editor_id].children[0];
+    doc.caret = Some(Caret {
+        node_id: editor_text_id,
+        offset: 3,
+    });
+    doc.set_viewport_scroll(120.0);
+    let state = doc.session_state();
+
+    // The checkbox has no id, so it is found by its position
+    assert!(state.fields.contains(&(
+        ElementKey::Path(vec![0, 1, 1, 0]),
+        FieldState::Checked(true)
+    )));
+
+    let mut reloaded = load();
+    reloaded.restore_session_state(&state);
+    assert_eq!(reloaded.session_state(), state);
+    assert_eq!(reloaded.viewport_scroll(), 120.0);
+    let editor_id = reloaded.get_element_by_id("editor").unwrap();
+    assert_eq!(
+        reloaded.caret(),
+        Some(Caret {
+            node_id: reloaded.nodes[editor_id].children[0],
+            offset: 3,
+        })
+    );
+}
@@ -0,0 +1,142 @@
+//! Spatial navigation
+//!
+//! Apps without a pointer (on TVs, kiosks and game consoles) move focus with arrow keys or a d-pad instead of the
+//! tab key. [`Document::navigate_spatially`] moves focus from the focused element to the nearest focusable element in
+//! a direction, using the elements' laid out boxes. Candidates must be entirely beyond the focused element's edge in
+//! that direction. Of those, the closest along the direction of travel wins, with boxes that are out of line with the
+//! focused element counting as further away.
+use crate::Document;
+
+/// How much further away a candidate counts as being for each pixel it is out of line with the focused element
+const MISALIGNMENT_WEIGHT: f32 = 2.0;
+
+/// A direction to move focus in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NavigationDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// An element's border box in document coordinates
+#[derive(Clone, Copy, Debug)]
+struct FocusRect {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl FocusRect {
+    /// How far along the direction of travel the rect is from `from`, or `None` if it isn't entirely beyond `from`'s
+    /// edge in that direction
+    fn distance_from(&self, from: &FocusRect, direction: NavigationDirection) -> Option<f32> {
+        let distance = match direction {
+            NavigationDirection::Up => from.top - self.bottom,
+            NavigationDirection::Down => self.top - from.bottom,
+            NavigationDirection::Left => from.left - self.right,
+            NavigationDirection::Right => self.left - from.right,
+        };
+        // Adjacent boxes are often a fraction of a pixel apart (or overlap by one)
+        (distance > -1.0).then_some(distance.max(0.0))
+    }
+
+    /// How far the rect is from overlapping `from` on the axis across the direction of travel
+    fn misalignment(&self, from: &FocusRect, direction: NavigationDirection) -> f32 {
+        let (start, end, from_start, from_end) = match direction {
+            NavigationDirection::Up | NavigationDirection::Down => {
+                (self.left, self.right, from.left, from.right)
+            }
+            NavigationDirection::Left | NavigationDirection::Right => {
+                (self.top, self.bottom, from.top, from.bottom)
+            }
+        };
+        (from_start - end).max(start - from_end).max(0.0)
+    }
+}
+
+impl Document {
+    /// Move focus to the nearest focusable element in a direction, scrolling it into view. If nothing has focus, the
+    /// first focusable element is focused. Returns the newly focused element, or `None` if there's nothing to move to.
+    pub fn navigate_spatially(&mut self, direction: NavigationDirection) -> Option<usize> {
+        let target = match self.focused_element() {
+            Some(node_id) => self.spatial_navigation_target(node_id, direction)?,
+            None => *self.focusable_elements().first()?,
+        };
+
+        self.focus(target);
+        let rect = self.focus_rect(target);
+        self.scroll_into_view(rect.top as f64, rect.bottom as f64);
+        Some(target)
+    }
+
+    /// The focusable element that spatial navigation from `node_id` in a direction would move to (if any)
+    pub fn spatial_navigation_target(
+        &self,
+        node_id: usize,
+        direction: NavigationDirection,
+    ) -> Option<usize> {
+        let from = self.focus_rect(node_id);
+        self.focusable_elements()
+            .into_iter()
+            .filter(|&candidate_id| candidate_id != node_id)
+            .filter_map(|candidate_id| {
+                let rect = self.focus_rect(candidate_id);
+                let distance = rect.distance_from(&from, direction)?;
+                let score = distance + MISALIGNMENT_WEIGHT * rect.misalignment(&from, direction);
+                Some((candidate_id, score))
+            })
+            // Ties go to the first candidate in tree order
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(candidate_id, _)| candidate_id)
+    }
+
+    fn focus_rect(&self, node_id: usize) -> FocusRect {
+        let position = self.absolute_position(node_id);
+        let size = self.nodes[node_id].final_layout.size;
+        FocusRect {
+            left: position.x,
+            top: position.y,
+            right: position.x + size.width,
+            bottom: position.y + size.height,
+        }
+    }
+}
+
+#[test]
+fn moves_focus_to_the_nearest_element_in_a_direction() {
+    use crate::test_util::parse_test_document;
+
+    // A 2x2 grid of buttons, and a wide button below it that is closer to the right column
+    let html = "<!DOCTYPE html><html><head><style>\
+        body { margin: 0 } \
+        button { position: absolute; width: 100px; height: 50px; border: 0; padding: 0 } \
+        </style></head><body>\
+        <button id=a style='left: 0; top: 0'></button><button id=b style='left: 200px; top: 0'></button>\
+        <button id=c style='left: 0; top: 100px'></button><button id=d style='left: 200px; top: 100px'></button>\
+        <button id=e style='left: 150px; top: 200px'></button><button id=f disabled style='left: 0; top: 300px'>\
+        </button></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    let id = |id: &str| doc.get_element_by_id(id).unwrap();
+    let (a, b, c, d, e) = (id("a"), id("b"), id("c"), id("d"), id("e"));
+
+    // With nothing focused, the first focusable element is focused
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Down), Some(a));
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Right), Some(b));
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Down), Some(d));
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Left), Some(c));
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Down), Some(e));
+    assert_eq!(doc.focused_element(), Some(e));
+
+    // The disabled button can't be focused, so there's nowhere further down to go
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Down), None);
+    assert_eq!(doc.navigate_spatially(NavigationDirection::Up), Some(d));
+    assert!(doc.nodes[d]
+        .element_state
+        .contains(style_traits::dom::ElementState::FOCUS));
+    assert!(!doc.nodes[e]
+        .element_state
+        .contains(style_traits::dom::ElementState::FOCUS));
+}
@@ -3,17 +3,20 @@
 
 use std::sync::atomic::Ordering;
 
+use crate::custom_elements::is_valid_custom_element_name;
 use crate::node::Node;
 
 use crate::node::NodeData;
 use atomic_refcell::{AtomicRef, AtomicRefMut};
 use html5ever::{local_name, LocalName, Namespace};
 use selectors::{
+    attr::NamespaceConstraint,
     matching::{ElementSelectorFlags, MatchingContext, VisitedHandlingMode},
     sink::Push,
     Element, OpaqueElement,
 };
 // use slab::Slab;
+use style::properties::generated::longhands::visibility::computed_value::T as Visibility;
 use style::values::specified::box_::DisplayOutside;
 use style::CaseSensitivityExt;
 use style::{
@@ -47,6 +50,26 @@ impl crate::document::Document {
     pub fn flush_styles_to_layout(&mut self, children: Vec<usize>) {
         // make a floating element
         for child in children.iter() {
+            // Nodes that haven't changed keep their styles and cached layouts. Anonymous blocks are recreated whenever
+            // their container changes, so any that are visited are new.
+            if !self.is_layout_dirty(*child)
+                && !matches!(self.nodes[*child].raw_dom_data, NodeData::AnonymousBlock(_))
+            {
+                continue;
+            }
+
+            let parent_display = self.nodes[*child]
+                .parent
+                .map(|parent_id| self.nodes[parent_id].style.display);
+            // Grid items are placed against their container's named lines (and subgrids take its tracks)
+            let parent_grid = self.nodes[*child]
+                .parent
+                .filter(|_| parent_display == Some(taffy::Display::Grid))
+                .and_then(|parent_id| {
+                    let parent = &self.nodes[parent_id];
+                    let style = parent.primary_styles()?;
+                    Some(stylo_to_taffy::GridNames::new(&style, &parent.style))
+                });
             let (display, mut children) = {
                 let node = self.nodes.get_mut(*child).unwrap();
                 let stylo_element_data = node.stylo_element_data.borrow();
@@ -134,7 +157,24 @@ impl crate::document::Document {
                     ..
                 }: &BoxStyle = style.get_box();
 
-                let display = stylo_to_taffy::display(*stylo_display);
+                // Collapsed flex items and table rows and columns are removed from layout entirely
+                let collapsed = style.get_inherited_box().visibility == Visibility::Collapse
+                    && (parent_display == Some(taffy::Display::Flex)
+                        || stylo_display.outside() == DisplayOutside::InternalTable);
+                let display = match collapsed {
+                    true => taffy::Display::None,
+                    false => stylo_to_taffy::display(*stylo_display),
+                };
+                let grid_row = stylo_to_taffy::grid_placement(
+                    grid_row_start,
+                    grid_row_end,
+                    parent_grid.as_ref().map(|grid| &grid.rows),
+                );
+                let grid_column = stylo_to_taffy::grid_placement(
+                    grid_column_start,
+                    grid_column_end,
+                    parent_grid.as_ref().map(|grid| &grid.columns),
+                );
                 node.style = Style {
                     display,
                     position: stylo_to_taffy::position(*position),
@@ -194,20 +234,20 @@ impl crate::document::Document {
 
                     // CSS Grid properties
                     grid_auto_flow: stylo_to_taffy::grid_auto_flow(*grid_auto_flow),
-                    grid_template_rows: stylo_to_taffy::grid_template_tracks(grid_template_rows),
+                    grid_template_rows: stylo_to_taffy::grid_template_tracks(
+                        grid_template_rows,
+                        grid_row,
+                        parent_grid.as_ref().map(|grid| &grid.rows),
+                    ),
                     grid_template_columns: stylo_to_taffy::grid_template_tracks(
                         grid_template_columns,
+                        grid_column,
+                        parent_grid.as_ref().map(|grid| &grid.columns),
                     ),
                     grid_auto_rows: stylo_to_taffy::grid_auto_tracks(grid_auto_rows),
                     grid_auto_columns: stylo_to_taffy::grid_auto_tracks(grid_auto_columns),
-                    grid_row: taffy::Line {
-                        start: stylo_to_taffy::grid_line(grid_row_start),
-                        end: stylo_to_taffy::grid_line(grid_row_end),
-                    },
-                    grid_column: taffy::Line {
-                        start: stylo_to_taffy::grid_line(grid_column_start),
-                        end: stylo_to_taffy::grid_line(grid_column_end),
-                    },
+                    grid_row,
+                    grid_column,
                 };
 
                 node.display_outer = match stylo_display.outside() {
@@ -218,8 +258,7 @@ impl crate::document::Document {
                     DisplayOutside::InternalTable => crate::node::DisplayOuter::Block,
                 };
 
-                // Clear Taffy cache
-                // TODO: smarter cache invalidation
+                // Clear Taffy cache (the node or one of its descendants has changed)
                 node.cache.clear();
 
                 // would like to change this not require a clone, but requires some refactoring
@@ -266,8 +305,12 @@ impl crate::document::Document {
             .as_element()
             .unwrap();
 
-        self.stylist
+        let stylesheets_changed = self
+            .stylist
             .flush(&guards, Some(root), Some(&self.snapshots));
+        if stylesheets_changed {
+            self.font_faces.invalidate();
+        }
 
         // Build the style context used by the style traversal
         let context = SharedStyleContext {
@@ -277,7 +320,7 @@ impl crate::document::Document {
             guards,
             visited_styles_enabled: false,
             animations: DocumentAnimationSet::default().clone(),
-            current_time_for_animations: 0.0,
+            current_time_for_animations: self.frame_time() / 1000.0,
             snapshot_map: &self.snapshots,
             registered_speculative_painters: &RegisteredPaintersImpl,
         };
@@ -293,6 +336,12 @@ impl crate::document::Document {
             style::driver::traverse_dom(&traverser, token, None);
         }
 
+        // Snapshots only describe the changes since the last restyle
+        for (_, node) in self.nodes.iter_mut() {
+            node.has_snapshot = false;
+        }
+        self.snapshots.clear();
+
         style::thread_state::exit(ThreadState::LAYOUT);
     }
 }
@@ -446,9 +495,13 @@ impl<'a> selectors::Element for BlitzNode<'a> {
         false
     }
 
-    // These methods are implemented naively since we only threaded real nodes and not fake nodes
-    // we should try and use `find` instead of this foward/backward stuff since its ugly and slow
+    // Sibling elements are cached when the child list changes, so structural pseudo-classes (:nth-child and
+    // friends) don't scan the siblings for every match. Stale entries fall back to scanning.
     fn prev_sibling_element(&self) -> Option<Self> {
+        if let Some(sibling) = self.cached_sibling_element(self.prev_sibling_element) {
+            return sibling;
+        }
+
         let mut n = 1;
         while let Some(node) = self.backward(n) {
             if node.is_element() {
@@ -461,6 +514,10 @@ impl<'a> selectors::Element for BlitzNode<'a> {
     }
 
     fn next_sibling_element(&self) -> Option<Self> {
+        if let Some(sibling) = self.cached_sibling_element(self.next_sibling_element) {
+            return sibling;
+        }
+
         let mut n = 1;
         while let Some(node) = self.forward(n) {
             if node.is_element() {
@@ -497,22 +554,27 @@ impl<'a> selectors::Element for BlitzNode<'a> {
 
     fn attr_matches(
         &self,
-        _ns: &selectors::attr::NamespaceConstraint<
+        ns: &selectors::attr::NamespaceConstraint<
             &<Self::Impl as selectors::SelectorImpl>::NamespaceUrl,
         >,
         local_name: &<Self::Impl as selectors::SelectorImpl>::LocalName,
-        _operation: &selectors::attr::AttrSelectorOperation<
+        operation: &selectors::attr::AttrSelectorOperation<
             &<Self::Impl as selectors::SelectorImpl>::AttrValue,
         >,
     ) -> bool {
-        // println!("attr matches  {}", self.id);
-        let mut has_attr = false;
-        self.each_attr_name(|f| {
-            if f.as_ref() == local_name.as_ref() {
-                has_attr = true;
-            }
-        });
-        has_attr
+        // The operation compares values with the selector's case sensitivity, which selectors has already resolved
+        // for attributes (like `type`) that HTML defines to be case-insensitive
+        let Some(attrs) = self.raw_dom_data.attrs() else {
+            return false;
+        };
+        attrs
+            .iter()
+            .filter(|attr| attr.name.local == local_name.0)
+            .filter(|attr| match ns {
+                NamespaceConstraint::Any => true,
+                NamespaceConstraint::Specific(ns) => attr.name.ns == ns.0,
+            })
+            .any(|attr| operation.eval_str(&attr.value))
     }
 
     fn match_non_ts_pseudo_class(
@@ -521,20 +583,26 @@ impl<'a> selectors::Element for BlitzNode<'a> {
         _context: &mut MatchingContext<Self::Impl>,
     ) -> bool {
         match *psuedo_class {
-            NonTSPseudoClass::Active => false,
+            NonTSPseudoClass::Active => self.element_state.contains(ElementState::ACTIVE),
             NonTSPseudoClass::AnyLink => false,
             NonTSPseudoClass::Checked => false,
-            NonTSPseudoClass::Valid => false,
-            NonTSPseudoClass::Invalid => false,
-            NonTSPseudoClass::Defined => false,
-            NonTSPseudoClass::Disabled => false,
-            NonTSPseudoClass::Enabled => false,
-            NonTSPseudoClass::Focus => false,
-            NonTSPseudoClass::Fullscreen => false,
+            NonTSPseudoClass::Valid => self.is_valid(),
+            NonTSPseudoClass::Invalid => self.is_invalid(),
+            NonTSPseudoClass::Defined => {
+                // Only custom elements can be undefined
+                !self
+                    .element_data()
+                    .is_some_and(|data| is_valid_custom_element_name(&data.name.local))
+                    || self.element_state.contains(ElementState::DEFINED)
+            }
+            NonTSPseudoClass::Disabled => self.is_disabled(),
+            NonTSPseudoClass::Enabled => self.is_disableable() && !self.is_disabled(),
+            NonTSPseudoClass::Focus => self.element_state.contains(ElementState::FOCUS),
+            NonTSPseudoClass::Fullscreen => self.element_state.contains(ElementState::FULLSCREEN),
             NonTSPseudoClass::Hover => self.is_hovered,
             NonTSPseudoClass::Indeterminate => false,
             NonTSPseudoClass::Lang(_) => false,
-            NonTSPseudoClass::CustomState(_) => false,
+            NonTSPseudoClass::CustomState(ref state) => self.has_custom_state(&state.0),
             NonTSPseudoClass::Link => false,
             NonTSPseudoClass::PlaceholderShown => false,
             NonTSPseudoClass::ReadWrite => false,
@@ -553,8 +621,21 @@ impl<'a> selectors::Element for BlitzNode<'a> {
         false
     }
 
-    fn apply_selector_flags(&self, _flags: ElementSelectorFlags) {
-        // unimplemented!()
+    fn apply_selector_flags(&self, flags: ElementSelectorFlags) {
+        let self_flags = flags.for_self();
+        if !self_flags.is_empty() {
+            self.selector_flags
+                .fetch_or(self_flags.bits() as usize, Ordering::Relaxed);
+        }
+
+        let parent_flags = flags.for_parent();
+        if !parent_flags.is_empty() {
+            if let Some(parent) = self.parent_node() {
+                parent
+                    .selector_flags
+                    .fetch_or(parent_flags.bits() as usize, Ordering::Relaxed);
+            }
+        }
     }
 
     fn is_link(&self) -> bool {
@@ -617,11 +698,8 @@ impl<'a> selectors::Element for BlitzNode<'a> {
             .is_none()
     }
 
-    fn has_custom_state(
-        &self,
-        _name: &<Self::Impl as selectors::SelectorImpl>::Identifier,
-    ) -> bool {
-        false
+    fn has_custom_state(&self, name: &<Self::Impl as selectors::SelectorImpl>::Identifier) -> bool {
+        self.custom_states.contains(&name.0)
     }
 
     fn add_element_unique_hashes(&self, _filter: &mut selectors::bloom::BloomFilter) -> bool {
@@ -650,9 +728,8 @@ impl<'a> TElement for BlitzNode<'a> {
         self.is_element()
     }
 
-    // not implemented.....
     fn is_mathml_element(&self) -> bool {
-        false
+        Node::is_mathml_element(self)
     }
 
     // need to check the namespace
@@ -877,15 +954,17 @@ impl<'a> TElement for BlitzNode<'a> {
         Default::default()
     }
 
-    fn each_custom_state<F>(&self, _callback: F)
+    fn each_custom_state<F>(&self, mut callback: F)
     where
         F: FnMut(&AtomIdent),
     {
-        todo!()
+        for state in self.custom_states.iter() {
+            callback(AtomIdent::cast(state));
+        }
     }
 
-    fn has_selector_flags(&self, _flags: ElementSelectorFlags) -> bool {
-        todo!()
+    fn has_selector_flags(&self, flags: ElementSelectorFlags) -> bool {
+        self.element_selector_flags().contains(flags)
     }
 
     fn relative_selector_search_direction(&self) -> ElementSelectorFlags {
@@ -962,16 +1041,12 @@ impl<'a> RecalcStyle<'a> {
 }
 
 #[allow(unsafe_code)]
-impl<'a, 'dom, E> DomTraversal<E> for RecalcStyle<'a>
-where
-    E: TElement,
-    E::ConcreteNode: 'dom,
-{
-    fn process_preorder<F: FnMut(E::ConcreteNode)>(
+impl<'a, 'dom> DomTraversal<BlitzNode<'dom>> for RecalcStyle<'a> {
+    fn process_preorder<F: FnMut(BlitzNode<'dom>)>(
         &self,
         traversal_data: &PerLevelTraversalData,
-        context: &mut StyleContext<E>,
-        node: E::ConcreteNode,
+        context: &mut StyleContext<BlitzNode<'dom>>,
+        node: BlitzNode<'dom>,
         note_child: F,
     ) {
         // Don't process textnodees in this traversal
@@ -982,7 +1057,17 @@ where
         let el = node.as_element().unwrap();
         // let mut data = el.mutate_data().unwrap();
         let mut data = unsafe { el.ensure_data() };
+        // The previous style is kept alive until it's been compared, so the new one can't be allocated at its address
+        let previous_style = data.styles.primary.clone();
         recalc_style_at(self, traversal_data, context, el, &mut data, note_child);
+        let restyled = match (&previous_style, &data.styles.primary) {
+            (Some(previous), Some(current)) => !Arc::ptr_eq(previous, current),
+            (previous, current) => previous.is_some() != current.is_some(),
+        };
+        if restyled {
+            el.style_generation
+                .store(crate::damage::next_paint_generation(), Ordering::Relaxed);
+        }
 
         // Gets set later on
         unsafe { el.unset_dirty_descendants() }
@@ -993,7 +1078,11 @@ where
         false
     }
 
-    fn process_postorder(&self, _style_context: &mut StyleContext<E>, _node: E::ConcreteNode) {
+    fn process_postorder(
+        &self,
+        _style_context: &mut StyleContext<BlitzNode<'dom>>,
+        _node: BlitzNode<'dom>,
+    ) {
         panic!("this should never be called")
     }
 
@@ -1030,3 +1119,50 @@ fn parse_inline() {
 
     // let val = CSSInlineStyleDeclaration();
 }
+
+#[test]
+fn attribute_selectors_respect_case_sensitivity() {
+    use crate::test_util::parse_test_document;
+
+    let html = r#"<!DOCTYPE html><html><body>
+        <input id=input type=text>
+        <div id=exact title=hello></div>
+        <div id=insensitive title=hello></div>
+    </body></html>"#;
+    let mut doc = parse_test_document(html, &[]);
+    doc.add_stylesheet(
+        r#"
+        input[type=TEXT i] { display: flex }
+        #exact[title=HELLO] { display: flex }
+        #insensitive[title=HELLO i] { display: flex }
+        "#,
+    );
+    doc.resolve();
+
+    let display = |id: &str| doc.nodes[doc.get_element_by_id(id).unwrap()].style.display;
+    assert_eq!(display("input"), taffy::Display::Flex);
+    assert_ne!(display("exact"), taffy::Display::Flex);
+    assert_eq!(display("insensitive"), taffy::Display::Flex);
+}
+
+#[test]
+fn attribute_selectors_respect_namespaces() {
+    use crate::test_util::parse_test_document;
+
+    let html = r##"<!DOCTYPE html><html><body><svg>
+        <a id=namespaced xlink:href="#target"></a>
+        <a id=plain href="#target"></a>
+    </svg></body></html>"##;
+    let mut doc = parse_test_document(html, &[]);
+    doc.add_stylesheet(
+        r#"
+        @namespace xlink url(http://www.w3.org/1999/xlink);
+        [xlink|href] { display: flex }
+        "#,
+    );
+    doc.resolve();
+
+    let display = |id: &str| doc.nodes[doc.get_element_by_id(id).unwrap()].style.display;
+    assert_eq!(display("namespaced"), taffy::Display::Flex);
+    assert_ne!(display("plain"), taffy::Display::Flex);
+}
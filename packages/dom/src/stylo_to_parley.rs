@@ -10,6 +10,9 @@ pub(crate) mod stylo {
     pub(crate) use style::values::computed::font::GenericFontFamily;
     pub(crate) use style::values::computed::font::LineHeight;
     pub(crate) use style::values::computed::font::SingleFontFamily;
+    pub(crate) use style::values::computed::text::TextAlignLast;
+    pub(crate) use style::values::computed::text::TextJustify;
+    pub(crate) use style::values::specified::TextAlignKeyword;
 }
 
 pub(crate) mod parley {
@@ -27,6 +30,61 @@ pub(crate) fn white_space_collapse(input: stylo::WhiteSpaceCollapse) -> parley::
     }
 }
 
+/// The alignment of the lines of an inline formatting context. `last_line` is whether the layout has a single line,
+/// which is also its last line and so aligned by `text-align-last`.
+///
+/// Parley aligns all of a layout's lines the same way, except that it never justifies the last line. So
+/// `text-align-last` only applies to layouts with a single line, and a justified last line is only possible when it's
+/// the only line. Parley also only justifies between words, so `text-justify: inter-character` is treated like
+/// `inter-word`, while `text-justify: none` turns justification off.
+pub(crate) fn text_alignment(
+    style: &stylo::ComputedValues,
+    last_line: bool,
+) -> ::parley::layout::Alignment {
+    use ::parley::layout::Alignment;
+    use stylo::{TextAlignKeyword, TextAlignLast, TextJustify};
+
+    let itext_styles = style.get_inherited_text();
+    let mut text_align = itext_styles.text_align;
+    if last_line {
+        text_align = match itext_styles.text_align_last {
+            TextAlignLast::Auto if text_align == TextAlignKeyword::Justify => {
+                TextAlignKeyword::Start
+            }
+            TextAlignLast::Auto => text_align,
+            TextAlignLast::Start => TextAlignKeyword::Start,
+            TextAlignLast::End => TextAlignKeyword::End,
+            TextAlignLast::Left => TextAlignKeyword::Left,
+            TextAlignLast::Right => TextAlignKeyword::Right,
+            TextAlignLast::Center => TextAlignKeyword::Center,
+            TextAlignLast::Justify => TextAlignKeyword::Justify,
+        };
+    }
+
+    // Parley's alignments are physical: `Start` is the left and `End` the right
+    let (start, end) = match style.writing_mode.is_bidi_ltr() {
+        true => (Alignment::Start, Alignment::End),
+        false => (Alignment::End, Alignment::Start),
+    };
+    match text_align {
+        TextAlignKeyword::Start => start,
+        TextAlignKeyword::Left => Alignment::Start,
+        TextAlignKeyword::Right => Alignment::End,
+        TextAlignKeyword::Center => Alignment::Middle,
+        TextAlignKeyword::Justify if itext_styles.text_justify == TextJustify::None => start,
+        TextAlignKeyword::Justify => Alignment::Justified,
+        TextAlignKeyword::End => end,
+        TextAlignKeyword::ServoCenter => Alignment::Middle,
+        TextAlignKeyword::ServoLeft => Alignment::Start,
+        TextAlignKeyword::ServoRight => Alignment::End,
+    }
+}
+
+/// Whether a style's `line-height` is `normal`
+pub(crate) fn has_normal_line_height(style: &stylo::ComputedValues) -> bool {
+    matches!(style.get_font().line_height, stylo::LineHeight::Normal)
+}
+
 pub(crate) fn style(style: &stylo::ComputedValues) -> parley::TextStyle<'static, TextBrush> {
     let font_styles = style.get_font();
     // let text_styles = style.get_text();
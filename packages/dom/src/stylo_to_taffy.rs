@@ -1,5 +1,8 @@
 //! Conversion functions from Stylo types to Taffy types
 
+use style::properties::ComputedValues;
+use style::Atom;
+
 // Module of type aliases so we can refer to stylo types with nicer names
 mod stylo {
     pub(crate) use style::computed_values::flex_direction::T as FlexDirection;
@@ -29,7 +32,10 @@ mod stylo {
     pub(crate) use style::values::specified::box_::DisplayInside;
     pub(crate) use style::values::specified::box_::DisplayOutside;
     pub(crate) use style::values::specified::box_::Overflow;
+    pub(crate) use style::values::specified::position::GridTemplateAreas;
     pub(crate) use style::values::specified::GenericGridTemplateComponent;
+    pub(crate) use style::values::CustomIdent;
+    pub(crate) use style::OwnedSlice;
     pub(crate) type LengthPercentageAuto = GenericLengthPercentageOrAuto<LengthPercentage>;
     pub(crate) type Size = GenericSize<NonNegative<LengthPercentage>>;
     pub(crate) type MaxSize = GenericMaxSize<NonNegative<LengthPercentage>>;
@@ -226,6 +232,8 @@ pub(crate) fn grid_line(input: &stylo::GridLine) -> taffy::GridPlacement {
 
 pub(crate) fn grid_template_tracks(
     input: &stylo::GridTemplateComponent,
+    placement: taffy::Line<taffy::GridPlacement>,
+    parent: Option<&GridAxis>,
 ) -> Vec<taffy::TrackSizingFunction> {
     match input {
         stylo::GenericGridTemplateComponent::None => Vec::new(),
@@ -243,12 +251,233 @@ pub(crate) fn grid_template_tracks(
             })
             .collect(),
 
-        // TODO: Implement subgrid and masonry
-        stylo::GenericGridTemplateComponent::Subgrid(_) => Vec::new(),
+        // TODO: Implement masonry
+        stylo::GenericGridTemplateComponent::Subgrid(_) => parent
+            .and_then(|parent| parent.subgrid_tracks(placement))
+            .unwrap_or_default(),
         stylo::GenericGridTemplateComponent::Masonry => Vec::new(),
     }
 }
 
+/// The named lines and explicit tracks of one axis of a grid container, which its items are placed against
+///
+/// Lines named inside `repeat(auto-fill, ...)` and `repeat(auto-fit, ...)`, and the lines after them, are numbered as
+/// if the repetition occurred once, as the number of repetitions isn't known until layout.
+#[derive(Debug, Default)]
+pub(crate) struct GridAxis {
+    /// The names of each explicit grid line (the first line is line 1)
+    lines: Vec<Vec<Atom>>,
+    /// The explicit tracks, if they can be known without laying out the grid (used by subgrids)
+    tracks: Option<Vec<taffy::NonRepeatedTrackSizingFunction>>,
+}
+
+impl GridAxis {
+    /// Resolve an axis from the container's computed template and areas, and its already converted taffy tracks
+    pub(crate) fn new(
+        template: &stylo::GridTemplateComponent,
+        areas: &stylo::GridTemplateAreas,
+        axis: taffy::AbsoluteAxis,
+        tracks: &[taffy::TrackSizingFunction],
+    ) -> Self {
+        let mut lines = vec![Vec::new()];
+        if let stylo::GenericGridTemplateComponent::TrackList(list) = template {
+            let mut name_line =
+                |lines: &mut Vec<Vec<Atom>>,
+                 names: Option<&stylo::OwnedSlice<stylo::CustomIdent>>| {
+                    let line = lines.last_mut().unwrap();
+                    line.extend(names.into_iter().flatten().map(|name| name.0.clone()));
+                };
+            for (index, value) in list.values.iter().enumerate() {
+                name_line(&mut lines, list.line_names.get(index));
+                match value {
+                    stylo::TrackListValue::TrackSize(_) => lines.push(Vec::new()),
+                    stylo::TrackListValue::TrackRepeat(repeat) => {
+                        let count = match repeat.count {
+                            stylo::RepeatCount::Number(count) => count.max(1) as usize,
+                            stylo::RepeatCount::AutoFill | stylo::RepeatCount::AutoFit => 1,
+                        };
+                        for _ in 0..count {
+                            for index in 0..repeat.track_sizes.len() {
+                                name_line(&mut lines, repeat.line_names.get(index));
+                                lines.push(Vec::new());
+                            }
+                            name_line(&mut lines, repeat.line_names.get(repeat.track_sizes.len()));
+                        }
+                    }
+                }
+            }
+            name_line(&mut lines, list.line_names.get(list.values.len()));
+        }
+
+        // Each named area implicitly names the lines at its edges `<name>-start` and `<name>-end`
+        if let stylo::GridTemplateAreas::Areas(areas) = areas {
+            for area in areas.0.areas.iter() {
+                let range = match axis {
+                    taffy::AbsoluteAxis::Horizontal => &area.columns,
+                    taffy::AbsoluteAxis::Vertical => &area.rows,
+                };
+                for (line, suffix) in [(range.start, "start"), (range.end, "end")] {
+                    let line = line as usize;
+                    if lines.len() < line {
+                        lines.resize(line, Vec::new());
+                    }
+                    lines[line - 1].push(Atom::from(format!("{}-{suffix}", &*area.name)));
+                }
+            }
+        }
+
+        Self {
+            lines,
+            tracks: expand_tracks(tracks),
+        }
+    }
+
+    /// The number of the `nth` line named `name`, counting from the end if `nth` is negative
+    fn named_line(&self, name: &Atom, nth: i32) -> Option<i16> {
+        let mut matching = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, names)| names.contains(name))
+            .map(|(index, _)| index as i16 + 1);
+        match nth {
+            0 | 1 => matching.next(),
+            nth if nth > 0 => matching.nth(nth as usize - 1),
+            nth => matching.rev().nth((-nth) as usize - 1),
+        }
+    }
+
+    /// The parent's tracks spanned by a subgrid, if its placement is definite
+    fn subgrid_tracks(
+        &self,
+        placement: taffy::Line<taffy::GridPlacement>,
+    ) -> Option<Vec<taffy::TrackSizingFunction>> {
+        let tracks = self.tracks.as_ref()?;
+        let line_count = tracks.len() as i16 + 1;
+        let line = |line: taffy::GridLine| match line.as_i16() {
+            line if line < 0 => line_count + 1 + line,
+            line => line,
+        };
+        let (start, end) = match (placement.start, placement.end) {
+            (taffy::GridPlacement::Line(start), taffy::GridPlacement::Line(end)) => {
+                let (start, end) = (line(start), line(end));
+                (start.min(end), start.max(end))
+            }
+            (taffy::GridPlacement::Line(start), taffy::GridPlacement::Span(span)) => {
+                (line(start), line(start) + span as i16)
+            }
+            (taffy::GridPlacement::Line(start), taffy::GridPlacement::Auto) => {
+                (line(start), line(start) + 1)
+            }
+            (taffy::GridPlacement::Span(span), taffy::GridPlacement::Line(end)) => {
+                (line(end) - span as i16, line(end))
+            }
+            // Auto-placed subgrids can't be resolved before layout
+            _ => return None,
+        };
+        let start = (start.max(1) - 1) as usize;
+        let end = (end.max(1) - 1) as usize;
+        Some(
+            tracks
+                .get(start..end.min(tracks.len()))?
+                .iter()
+                .copied()
+                .map(taffy::TrackSizingFunction::Single)
+                .collect(),
+        )
+    }
+}
+
+/// The named lines and explicit tracks of a grid container
+#[derive(Debug, Default)]
+pub(crate) struct GridNames {
+    pub(crate) rows: GridAxis,
+    pub(crate) columns: GridAxis,
+}
+
+impl GridNames {
+    pub(crate) fn new(style: &ComputedValues, taffy_style: &taffy::Style) -> Self {
+        let position = style.get_position();
+        Self {
+            rows: GridAxis::new(
+                &position.grid_template_rows,
+                &position.grid_template_areas,
+                taffy::AbsoluteAxis::Vertical,
+                &taffy_style.grid_template_rows,
+            ),
+            columns: GridAxis::new(
+                &position.grid_template_columns,
+                &position.grid_template_areas,
+                taffy::AbsoluteAxis::Horizontal,
+                &taffy_style.grid_template_columns,
+            ),
+        }
+    }
+}
+
+/// Expand `repeat()`s with a fixed number of repetitions, or `None` if there is an `auto-fill` or `auto-fit` repeat
+fn expand_tracks(
+    tracks: &[taffy::TrackSizingFunction],
+) -> Option<Vec<taffy::NonRepeatedTrackSizingFunction>> {
+    let mut expanded = Vec::new();
+    for track in tracks {
+        match track {
+            taffy::TrackSizingFunction::Single(track) => expanded.push(*track),
+            taffy::TrackSizingFunction::Repeat(
+                taffy::GridTrackRepetition::Count(count),
+                tracks,
+            ) => {
+                for _ in 0..*count {
+                    expanded.extend(tracks.iter().copied());
+                }
+            }
+            taffy::TrackSizingFunction::Repeat(_, _) => return None,
+        }
+    }
+    Some(expanded)
+}
+
+/// Convert the start and end lines of an item's placement on one axis, resolving named lines against its grid
+/// container's (if it's in one)
+pub(crate) fn grid_placement(
+    start: &stylo::GridLine,
+    end: &stylo::GridLine,
+    names: Option<&GridAxis>,
+) -> taffy::Line<taffy::GridPlacement> {
+    taffy::Line {
+        start: named_grid_line(start, "start", names),
+        end: named_grid_line(end, "end", names),
+    }
+}
+
+fn named_grid_line(
+    input: &stylo::GridLine,
+    edge: &str,
+    names: Option<&GridAxis>,
+) -> taffy::GridPlacement {
+    let name = &input.ident.0;
+    if name.is_empty() {
+        return grid_line(input);
+    }
+    // TODO: Support spans to named lines in Taffy (they are treated as spanning one track)
+    if input.is_span {
+        return taffy::style_helpers::span(1);
+    }
+
+    // A bare area name refers to the area's edge, or failing that to a line of the same name
+    let line = names.and_then(|names| match input.line_num {
+        0 => names
+            .named_line(&Atom::from(format!("{}-{edge}", &**name)), 0)
+            .or_else(|| names.named_line(name, 0)),
+        nth => names.named_line(name, nth),
+    });
+    match line {
+        Some(line) => taffy::style_helpers::line(line),
+        // TODO: Lines that don't exist should be resolved against the implicit grid
+        None => taffy::GridPlacement::Auto,
+    }
+}
+
 pub(crate) fn grid_auto_tracks(
     input: &stylo::ImplicitGridTracks,
 ) -> Vec<taffy::NonRepeatedTrackSizingFunction> {
@@ -353,3 +582,92 @@ pub(crate) fn item_alignment(input: stylo::AlignFlags) -> Option<taffy::AlignIte
         _ => None,
     }
 }
+
+#[test]
+fn grid_items_are_placed_by_name() {
+    use crate::test_util::parse_test_document;
+
+    let html = r#"<!DOCTYPE html><html><body>
+        <div style="display: grid; grid-template-columns: [left] 100px [middle] 50px [right] repeat(2, [pair] 10px);
+                    grid-template-rows: 20px 30px; grid-template-areas: 'a b' 'c d'">
+            <div id=area style="grid-area: d"></div>
+            <div id=line style="grid-column: middle / right; grid-row: 1"></div>
+            <div id=nth style="grid-column: pair 2; grid-row: 1"></div>
+            <div id=last style="grid-column: pair -1; grid-row: 2"></div>
+        </div>
+    </body></html>"#;
+    let mut doc = parse_test_document(html, &["div, body, html { display: block }"]);
+    doc.resolve();
+
+    let location = |id: &str| {
+        let location = doc.nodes[doc.get_element_by_id(id).unwrap()]
+            .final_layout
+            .location;
+        (location.x, location.y)
+    };
+    assert_eq!(location("area"), (100.0, 20.0));
+    assert_eq!(location("line"), (100.0, 0.0));
+    assert_eq!(location("nth"), (160.0, 0.0));
+    assert_eq!(location("last"), (160.0, 20.0));
+}
+
+#[test]
+fn logical_properties_map_to_physical_sides() {
+    use crate::test_util::parse_test_document;
+    use style::values::computed::Length;
+    use taffy::{LengthPercentage, LengthPercentageAuto};
+
+    let logical = "position: relative; margin-inline-start: 1px; margin-block-start: 2px; \
+        padding-inline-end: 3px; inset-block-end: 4px; border-start-end-radius: 5px";
+    let html = format!(
+        r#"<!DOCTYPE html><html><body>
+        <div id=ltr style="{logical}"></div>
+        <div id=rtl style="direction: rtl; {logical}"></div>
+        <div id=vertical-rl style="writing-mode: vertical-rl; {logical}"></div>
+        <div id=vertical-lr-rtl style="writing-mode: vertical-lr; direction: rtl; {logical}"></div>
+    </body></html>"#
+    );
+    let mut doc = parse_test_document(html, &["div, body, html { display: block }"]);
+    doc.resolve();
+
+    let node = |id: &str| &doc.nodes[doc.get_element_by_id(id).unwrap()];
+    let px = LengthPercentageAuto::Length;
+    // The physical corners of a border radius, clockwise from the top left
+    let radii = |id: &str| {
+        let styles = node(id).primary_styles().unwrap();
+        let border = styles.get_border();
+        [
+            &border.border_top_left_radius,
+            &border.border_top_right_radius,
+            &border.border_bottom_right_radius,
+            &border.border_bottom_left_radius,
+        ]
+        .map(|radius| radius.0.width.0.resolve(Length::new(0.0)).px())
+    };
+
+    let style = &node("ltr").style;
+    assert_eq!((style.margin.left, style.margin.top), (px(1.0), px(2.0)));
+    assert_eq!(style.padding.right, LengthPercentage::Length(3.0));
+    assert_eq!(style.inset.bottom, px(4.0));
+    assert_eq!(radii("ltr"), [0.0, 5.0, 0.0, 0.0]);
+
+    // Inline start and end swap in right-to-left text
+    let style = &node("rtl").style;
+    assert_eq!((style.margin.right, style.margin.top), (px(1.0), px(2.0)));
+    assert_eq!(style.padding.left, LengthPercentage::Length(3.0));
+    assert_eq!(style.inset.bottom, px(4.0));
+    assert_eq!(radii("rtl"), [5.0, 0.0, 0.0, 0.0]);
+
+    // In vertical writing modes, the inline axis is vertical and blocks are stacked horizontally
+    let style = &node("vertical-rl").style;
+    assert_eq!((style.margin.top, style.margin.right), (px(1.0), px(2.0)));
+    assert_eq!(style.padding.bottom, LengthPercentage::Length(3.0));
+    assert_eq!(style.inset.left, px(4.0));
+    assert_eq!(radii("vertical-rl"), [0.0, 0.0, 5.0, 0.0]);
+
+    let style = &node("vertical-lr-rtl").style;
+    assert_eq!((style.margin.bottom, style.margin.left), (px(1.0), px(2.0)));
+    assert_eq!(style.padding.top, LengthPercentage::Length(3.0));
+    assert_eq!(style.inset.right, px(4.0));
+    assert_eq!(radii("vertical-lr-rtl"), [5.0, 0.0, 0.0, 0.0]);
+}
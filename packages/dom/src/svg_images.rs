@@ -0,0 +1,252 @@
+//! SVG images
+//!
+//! SVG images are parsed with usvg when they're fetched, and rasterized with resvg at the size they're displayed at in
+//! device pixels (see [`crate::decoded_images`]), so they stay crisp at any zoom. They're rasterized again whenever
+//! that size changes, on a worker thread: the element keeps showing its previous rasterization (or nothing, at first)
+//! until the new one is ready, which is picked up when the document is polled ([`Document::poll_rasterized_images`]).
+//! Where threads aren't available (such as on `wasm32-unknown-unknown`), images are rasterized as soon as they're
+//! requested instead, and picked up in the same way.
+//!
+//! Note: the usvg tree is parsed without any fonts, so text in SVG images isn't rendered.
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Waker};
+
+use image::{DynamicImage, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+
+use crate::Document;
+
+/// An SVG image to rasterize for an element
+struct RasterJob {
+    node_id: usize,
+    tree: Arc<usvg::Tree>,
+    width: u32,
+    height: u32,
+}
+
+/// An SVG image rasterized for an element
+struct RasterResult {
+    node_id: usize,
+    tree: Arc<usvg::Tree>,
+    image: DynamicImage,
+}
+
+/// Rasterizes SVG images on a worker thread, which is started when the first image is rasterized
+#[derive(Default)]
+pub(crate) struct SvgRasterizer {
+    jobs: Option<Sender<RasterJob>>,
+    results: Option<Receiver<RasterResult>>,
+    /// Set if the worker thread couldn't be started, in which case images are rasterized when they're requested
+    synchronous: bool,
+    /// Images rasterized without the worker thread, which haven't been shown yet
+    ready: Vec<RasterResult>,
+    /// Woken when a rasterization is ready
+    waker: Arc<Mutex<Option<Waker>>>,
+    /// The size that each element's image is being rasterized at
+    pending: HashMap<usize, (u32, u32)>,
+}
+
+impl SvgRasterizer {
+    /// Rasterize an element's SVG image at the given size, unless it's already being rasterized at that size
+    pub(crate) fn request(
+        &mut self,
+        node_id: usize,
+        tree: &Arc<usvg::Tree>,
+        width: u32,
+        height: u32,
+    ) {
+        if self.pending.get(&node_id) == Some(&(width, height)) {
+            return;
+        }
+        self.pending.insert(node_id, (width, height));
+
+        if self.jobs.is_none() && !self.synchronous {
+            match spawn_worker(self.waker.clone()) {
+                Some((jobs, results)) => {
+                    self.jobs = Some(jobs);
+                    self.results = Some(results);
+                }
+                None => self.synchronous = true,
+            }
+        }
+        match &self.jobs {
+            Some(jobs) => {
+                let _ = jobs.send(RasterJob {
+                    node_id,
+                    tree: tree.clone(),
+                    width,
+                    height,
+                });
+            }
+            None => self.ready.push(RasterResult {
+                node_id,
+                tree: tree.clone(),
+                image: rasterize(tree, width, height),
+            }),
+        }
+    }
+
+    /// The rasterizations that are ready to be shown
+    fn take_ready(&mut self) -> Vec<RasterResult> {
+        let mut results = std::mem::take(&mut self.ready);
+        if let Some(receiver) = &self.results {
+            results.extend(receiver.try_iter());
+        }
+        results
+    }
+}
+
+/// Start the worker thread, which rasterizes the jobs sent to it until the rasterizer is dropped. Returns `None` if
+/// the thread can't be started.
+fn spawn_worker(
+    waker: Arc<Mutex<Option<Waker>>>,
+) -> Option<(Sender<RasterJob>, Receiver<RasterResult>)> {
+    let (job_sender, jobs) = mpsc::channel::<RasterJob>();
+    let (result_sender, results) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("blitz-svg-rasterizer".into())
+        .spawn(move || {
+            for job in jobs {
+                let image = rasterize(&job.tree, job.width, job.height);
+                let result = RasterResult {
+                    node_id: job.node_id,
+                    tree: job.tree,
+                    image,
+                };
+                if result_sender.send(result).is_err() {
+                    return;
+                }
+                let waker = waker.lock().unwrap_or_else(PoisonError::into_inner).take();
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+        })
+        .ok()?;
+    Some((job_sender, results))
+}
+
+/// Render an SVG image into an image of the given size
+pub(crate) fn rasterize(tree: &usvg::Tree, width: u32, height: u32) -> DynamicImage {
+    let Some(mut pixmap) = Pixmap::new(width, height) else {
+        return DynamicImage::new_rgba8(width, height);
+    };
+    let size = tree.size();
+    let transform =
+        Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia's pixels have premultiplied alpha
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, data).unwrap())
+}
+
+impl Document {
+    /// Show the SVG images that have been rasterized since the document was last polled. Returns true if any were
+    /// (and thus a redraw is required). The waker in `cx` is woken when the next one is ready.
+    pub fn poll_rasterized_images(&mut self, cx: &mut Context<'_>) -> bool {
+        *self
+            .svg_rasterizer
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(cx.waker().clone());
+        let results = self.svg_rasterizer.take_ready();
+        let changed = results.into_iter().fold(false, |changed, result| {
+            self.show_rasterized_image(result) | changed
+        });
+        if changed {
+            self.invalidate();
+        }
+        changed
+    }
+
+    /// Block until every SVG image being rasterized is ready, and show them. For rendering without an event loop.
+    pub fn wait_for_rasterized_images(&mut self) {
+        for result in self.svg_rasterizer.take_ready() {
+            self.show_rasterized_image(result);
+        }
+        while !self.svg_rasterizer.pending.is_empty() {
+            let Some(result) = self
+                .svg_rasterizer
+                .results
+                .as_ref()
+                .and_then(|results| results.recv().ok())
+            else {
+                self.svg_rasterizer.pending.clear();
+                return;
+            };
+            self.show_rasterized_image(result);
+        }
+    }
+
+    fn show_rasterized_image(&mut self, result: RasterResult) -> bool {
+        let size = (result.image.width(), result.image.height());
+        if self.svg_rasterizer.pending.get(&result.node_id) == Some(&size) {
+            self.svg_rasterizer.pending.remove(&result.node_id);
+        }
+
+        // Rasterizations of an image that has since been replaced are dropped
+        let Some(element_data) = self
+            .nodes
+            .get_mut(result.node_id)
+            .and_then(|node| node.element_data_mut())
+        else {
+            return false;
+        };
+        let current = element_data
+            .image_source
+            .as_ref()
+            .and_then(|source| source.svg.as_ref());
+        if !current.is_some_and(|tree| Arc::ptr_eq(tree, &result.tree)) {
+            return false;
+        }
+        element_data.set_image(Arc::new(result.image));
+        true
+    }
+}
+
+#[test]
+fn rasterizes_svgs_at_their_displayed_size() {
+    use crate::decoded_images::ImageSource;
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <img id=icon style='width: 40px; display: block'></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+        <rect width="20" height="10" fill="red"/></svg>"#;
+    let icon = doc.get_element_by_id("icon").unwrap();
+    let source = ImageSource::new("icon.svg".into(), svg.as_bytes().to_vec()).unwrap();
+    assert_eq!((source.width, source.height), (20, 10));
+    doc.set_image_source(icon, source);
+    doc.resolve();
+    doc.wait_for_rasterized_images();
+
+    // SVGs are rasterized beyond their natural size, to stay crisp
+    let image = |doc: &Document| {
+        doc.nodes[icon]
+            .element_data()
+            .unwrap()
+            .image
+            .clone()
+            .unwrap()
+    };
+    assert_eq!((image(&doc).width(), image(&doc).height()), (40, 20));
+    assert_eq!(image(&doc).to_rgba8().get_pixel(20, 10).0, [255, 0, 0, 255]);
+
+    // And again when zooming
+    doc.set_scale(2.0);
+    doc.resolve();
+    doc.wait_for_rasterized_images();
+    assert_eq!((image(&doc).width(), image(&doc).height()), (80, 40));
+}
@@ -0,0 +1,141 @@
+//! Tab stops
+//!
+//! <https://drafts.csswg.org/css-text-3/#tab-size-property>
+//!
+//! Tab characters in text whose white space is preserved (e.g. `<pre>`) advance to the next tab stop. Parley has no
+//! notion of tab stops, so tabs are expanded to spaces while inline layouts are built, counting columns from the last
+//! line break. A tab size that is a length is converted to a number of columns with the advance of a space in a
+//! typical monospace font, since tabs are almost always found in monospace text.
+//!
+//! Note: the servo configuration of stylo doesn't parse the `tab-size` property, so the tab size is set for the whole
+//! document with [`Document::set_tab_size`] rather than per element.
+use std::borrow::Cow;
+
+use crate::Document;
+
+/// The advance of a space in a typical monospace font, as a fraction of the font size
+const MONOSPACE_ADVANCE: f32 = 0.6;
+
+/// The distance between tab stops
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabSize {
+    /// A number of spaces (which is rounded to a whole number of columns)
+    Spaces(f32),
+    /// A length in CSS pixels
+    Length(f32),
+}
+
+impl Default for TabSize {
+    fn default() -> Self {
+        TabSize::Spaces(8.0)
+    }
+}
+
+impl TabSize {
+    /// The number of columns between tab stops, for text of the given font size
+    fn columns(self, font_size: f32) -> usize {
+        let columns = match self {
+            TabSize::Spaces(spaces) => spaces,
+            TabSize::Length(length) => length / (font_size * MONOSPACE_ADVANCE),
+        };
+        columns.max(0.0).round() as usize
+    }
+}
+
+/// Expands the tabs of the text pushed to an inline layout, tracking the column the text has reached
+pub(crate) struct TabStops {
+    tab_size: TabSize,
+    column: usize,
+}
+
+impl TabStops {
+    pub(crate) fn new(tab_size: TabSize) -> Self {
+        Self {
+            tab_size,
+            column: 0,
+        }
+    }
+
+    /// The text with each of its tabs replaced by the spaces that reach the next tab stop. Text whose white space
+    /// collapses keeps its tabs (which collapse with the rest of its white space) but still moves the column.
+    pub(crate) fn expand<'a>(
+        &mut self,
+        text: &'a str,
+        preserve_white_space: bool,
+        font_size: f32,
+    ) -> Cow<'a, str> {
+        if !preserve_white_space || !text.contains('\t') {
+            self.advance(text);
+            return Cow::Borrowed(text);
+        }
+
+        let columns = self.tab_size.columns(font_size);
+        let mut expanded = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '\t' if columns == 0 => {}
+                '\t' => {
+                    let spaces = columns - self.column % columns;
+                    expanded.extend(std::iter::repeat(' ').take(spaces));
+                    self.column += spaces;
+                }
+                '\n' => {
+                    expanded.push(c);
+                    self.column = 0;
+                }
+                _ => {
+                    expanded.push(c);
+                    self.column += 1;
+                }
+            }
+        }
+        Cow::Owned(expanded)
+    }
+
+    /// Move the column past text that has been pushed to the layout
+    pub(crate) fn advance(&mut self, text: &str) {
+        match text.rfind('\n') {
+            Some(index) => self.column = text[index + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+    }
+}
+
+impl Document {
+    /// The distance between the tab stops of the document's text
+    pub fn tab_size(&self) -> TabSize {
+        self.tab_size
+    }
+
+    /// Set the distance between the tab stops of the document's text
+    pub fn set_tab_size(&mut self, tab_size: TabSize) {
+        if tab_size != self.tab_size {
+            self.tab_size = tab_size;
+            self.invalidate_layout();
+            self.invalidate();
+        }
+    }
+}
+
+#[test]
+fn expands_tabs_to_the_next_tab_stop() {
+    let mut tab_stops = TabStops::new(TabSize::Spaces(4.0));
+    assert_eq!(tab_stops.expand("a\tbcdef\tg", true, 16.0), "a   bcdef   g");
+
+    // Columns carry over between text nodes, and restart after line breaks
+    let mut tab_stops = TabStops::new(TabSize::Spaces(4.0));
+    tab_stops.advance("ab");
+    assert_eq!(tab_stops.expand("\tc\n\td", true, 16.0), "  c\n    d");
+    tab_stops.advance("\n");
+    assert_eq!(tab_stops.expand("x\t", true, 16.0), "x   ");
+
+    // Lengths are converted to columns of monospace text, and a tab size of zero hides tabs
+    let mut tab_stops = TabStops::new(TabSize::Length(24.0));
+    assert_eq!(tab_stops.expand("\t.", true, 10.0), "    .");
+    let mut tab_stops = TabStops::new(TabSize::Spaces(0.0));
+    assert_eq!(tab_stops.expand("a\tb", true, 16.0), "ab");
+
+    // Tabs in collapsible white space are left to collapse
+    let mut tab_stops = TabStops::new(TabSize::default());
+    assert_eq!(tab_stops.expand("a\tb", false, 16.0), "a\tb");
+}
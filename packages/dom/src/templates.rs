@@ -0,0 +1,91 @@
+//! The inert contents of `<template>` elements, and instantiating them into the live document
+//!
+//! When a `<template>` element is parsed, its children are placed in a detached [`NodeData::DocumentFragment`]
+//! rather than under the element itself. Nothing in the fragment is ever reached by style, layout or rendering,
+//! and `<style>` elements and ids inside it don't affect the document. [`Document::instantiate_template`] deep
+//! clones the fragment's children so that they can be inserted into the document.
+use crate::{Document, NodeData};
+
+impl Document {
+    /// The id of the fragment holding the contents of a `<template>` element
+    pub fn template_contents(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id].element_data()?.template_contents
+    }
+
+    /// Whether a node is inside the inert contents of a `<template>` element
+    pub fn is_template_contents(&self, node_id: usize) -> bool {
+        let mut node = &self.nodes[node_id];
+        while let Some(parent_id) = node.parent {
+            node = &self.nodes[parent_id];
+        }
+        matches!(node.raw_dom_data, NodeData::DocumentFragment)
+    }
+
+    /// Clone the contents of a `<template>` element into new, detached nodes
+    ///
+    /// Returns the ids of the clones of the fragment's top-level children, ready to be inserted into the document with
+    /// [`Document::append_children`] or [`Document::insert_before`]. Returns no nodes if `node_id` is not a
+    /// `<template>` element.
+    pub fn instantiate_template(&mut self, node_id: usize) -> Vec<usize> {
+        let Some(contents_id) = self.template_contents(node_id) else {
+            return Vec::new();
+        };
+
+        let children = self.nodes[contents_id].children.clone();
+        children
+            .into_iter()
+            .map(|child_id| self.clone_node(child_id, true))
+            .collect()
+    }
+
+    /// Finish the template contents created while parsing: compute their child indexes, and forget the ids of the
+    /// elements inside them
+    pub(crate) fn flush_template_contents(&mut self) {
+        let fragment_ids: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| matches!(node.raw_dom_data, NodeData::DocumentFragment))
+            .map(|(id, _)| id)
+            .collect();
+        for id in fragment_ids {
+            self.flush_child_indexes(id, 0, 0);
+        }
+
+        let inert_ids: Vec<String> = self
+            .nodes_to_id
+            .iter()
+            .filter(|(_, node_id)| self.is_template_contents(**node_id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in inert_ids {
+            self.nodes_to_id.remove(&id);
+        }
+    }
+}
+
+#[test]
+fn template_contents_are_inert_until_instantiated() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <template id=t><style>.item { display: flex }</style><div id=inner class=item>item</div></template>\
+        <div id=list class=item></div>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    // The template's stylesheet and ids don't apply to the document
+    let template_id = doc.get_element_by_id("t").unwrap();
+    let list_id = doc.get_element_by_id("list").unwrap();
+    assert!(doc.nodes[template_id].children.is_empty());
+    assert!(doc.get_element_by_id("inner").is_none());
+    assert_eq!(doc.nodes[list_id].style.display, taffy::Display::Block);
+
+    doc.add_stylesheet(".item { display: grid }");
+    let clone_ids = doc.instantiate_template(template_id);
+    doc.append_children(list_id, &clone_ids);
+    doc.resolve();
+
+    let item_id = *doc.nodes[list_id].children.last().unwrap();
+    assert_eq!(doc.nodes[item_id].style.display, taffy::Display::Grid);
+}
@@ -0,0 +1,37 @@
+//! Setting up documents for tests
+use euclid::{Scale, Size2D};
+use html5ever::tendril::TendrilSink;
+use selectors::matching::QuirksMode;
+use style::media_queries::{Device, MediaType};
+
+use crate::document::DummyFontMetricsProvider;
+use crate::{Document, DocumentHtmlParser};
+
+/// An empty document with an 800x600 viewport at a scale of 1, styled with the user agent stylesheets
+/// `ua_stylesheets`
+pub(crate) fn test_document(ua_stylesheets: &[&str]) -> Document {
+    let device = Device::new(
+        MediaType::screen(),
+        QuirksMode::NoQuirks,
+        Size2D::new(800.0, 600.0),
+        Scale::new(1.0),
+        Box::new(DummyFontMetricsProvider),
+    );
+    Document::new(device).with_ua_stylesheets(ua_stylesheets.iter().copied())
+}
+
+/// Parse `html` into a document
+pub(crate) fn parse_html(doc: &mut Document, html: &str) {
+    let sink = DocumentHtmlParser::new(doc);
+    html5ever::parse_document(sink, Default::default())
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .unwrap();
+}
+
+/// A [`test_document`] with `html` parsed into it. Its styles and layout aren't resolved yet.
+pub(crate) fn parse_test_document(html: &str, ua_stylesheets: &[&str]) -> Document {
+    let mut doc = test_document(ua_stylesheets);
+    parse_html(&mut doc, html);
+    doc
+}
@@ -0,0 +1,225 @@
+//! Editing the value of text fields
+//!
+//! <https://html.spec.whatwg.org/multipage/input.html#the-input-element>
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#textFieldSelection>
+//! <https://html.spec.whatwg.org/multipage/input.html#attr-input-maxlength>
+//!
+//! Text fields (`<input>`s of type text, search, url, tel, email and password) display their value as a text child,
+//! like number inputs, and keep it in their `value` attribute. [`Document::edit_text_field`] applies an
+//! [`EditCommand`] to the value at the field's selection, which is a range of characters of the value. Setting the
+//! value moves the selection to its end, and embedders can read and set it with [`Document::selection_range`] and
+//! [`Document::set_selection_range`] (like `selectionStart`, `selectionEnd` and `setSelectionRange`).
+//!
+//! A field with a `maxlength` attribute doesn't let the user insert more characters than it allows: inserted text is
+//! cut short to fit, and nothing is inserted once the field is full. As in browsers, values set by the page aren't
+//! cut short. Edits are recorded as transactions, so they can be undone like the edits of `contenteditable` elements.
+//!
+//! Note: the selection is counted in characters rather than UTF-16 code units, and only the caret (not a selected
+//! range) is drawn.
+use html5ever::local_name;
+
+use crate::editing::EditCommand;
+use crate::Document;
+
+/// The `type`s of input whose value is edited as text
+const TEXT_FIELD_TYPES: [&str; 6] = ["text", "search", "url", "tel", "email", "password"];
+
+/// The byte offset of a character offset within a string (its length if the offset is past the end)
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len())
+}
+
+impl Document {
+    /// Whether an element is an `<input>` whose value is edited as text
+    pub fn is_text_field(&self, node_id: usize) -> bool {
+        self.nodes[node_id]
+            .element_data()
+            .is_some_and(|element_data| {
+                let input_type = element_data.attr(local_name!("type")).unwrap_or("text");
+                element_data.name.local == local_name!("input")
+                    && TEXT_FIELD_TYPES
+                        .iter()
+                        .any(|text_type| input_type.trim().eq_ignore_ascii_case(text_type))
+            })
+    }
+
+    /// The most characters that the user can enter in a text field (its `maxlength`), if it's limited
+    pub fn max_length(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id]
+            .attr(local_name!("maxlength"))
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    /// The selected range of characters of a text field's value as (start, end), or `None` if the element isn't a
+    /// text field. A collapsed range is the position of the caret.
+    pub fn selection_range(&self, node_id: usize) -> Option<(usize, usize)> {
+        let (anchor, focus) = self.text_field_selection(node_id)?;
+        Some((anchor.min(focus), anchor.max(focus)))
+    }
+
+    /// Select a range of characters of a text field's value. Offsets past the end of the value are moved to its end,
+    /// and an end before the start collapses the range at the end. Returns true if the element is a text field.
+    pub fn set_selection_range(&mut self, node_id: usize, start: usize, end: usize) -> bool {
+        let Some(len) = self.text_field_len(node_id) else {
+            return false;
+        };
+        let end = end.min(len);
+        let start = start.min(end);
+        self.input_selections.insert(node_id, (start, end));
+        true
+    }
+
+    /// Move the caret of a text field one character to the left or right. If `extend` is true the selection is
+    /// extended, otherwise a selected range is collapsed to its start or end.
+    pub fn move_text_field_caret(&mut self, node_id: usize, forward: bool, extend: bool) -> bool {
+        let (Some(len), Some((anchor, focus))) = (
+            self.text_field_len(node_id),
+            self.text_field_selection(node_id),
+        ) else {
+            return false;
+        };
+        let focus = match (extend, anchor == focus, forward) {
+            (false, false, true) => anchor.max(focus),
+            (false, false, false) => anchor.min(focus),
+            (_, _, true) => (focus + 1).min(len),
+            (_, _, false) => focus.saturating_sub(1),
+        };
+        let anchor = if extend { anchor } else { focus };
+        self.input_selections.insert(node_id, (anchor, focus));
+        true
+    }
+
+    /// Apply an editing command to the value of a text field at its selection. Text fields hold a single line, so
+    /// line breaks are left out of inserted text, and paragraphs and formatting can't be inserted. Returns whether
+    /// the value changed.
+    pub fn edit_text_field(&mut self, node_id: usize, command: EditCommand) -> bool {
+        let node = &self.nodes[node_id];
+        if !self.is_text_field(node_id)
+            || node.is_disabled()
+            || node.attr(local_name!("readonly")).is_some()
+        {
+            return false;
+        }
+        let value = node.attr(local_name!("value")).unwrap_or_default();
+        let (start, end) = self.selection_range(node_id).unwrap();
+
+        let (start, end, inserted) = match command {
+            EditCommand::InsertText(text) => {
+                let text: String = text.chars().filter(|c| !matches!(c, '\n' | '\r')).collect();
+                // Only as much of the text as fits within the maximum length is inserted
+                let kept = value.chars().count() - (end - start);
+                let room = self
+                    .max_length(node_id)
+                    .map_or(usize::MAX, |max| max.saturating_sub(kept));
+                let text: String = text.chars().take(room).collect();
+                if text.is_empty() && start == end {
+                    return false;
+                }
+                (start, end, text)
+            }
+            EditCommand::Delete if start == end && start > 0 => (start - 1, end, String::new()),
+            EditCommand::ForwardDelete if start == end && end < value.chars().count() => {
+                (start, end + 1, String::new())
+            }
+            EditCommand::Delete | EditCommand::ForwardDelete if start != end => {
+                (start, end, String::new())
+            }
+            _ => return false,
+        };
+
+        let mut new_value = value.to_string();
+        new_value.replace_range(
+            byte_offset(value, start)..byte_offset(value, end),
+            &inserted,
+        );
+        self.transact(node_id, |doc| doc.set_input_value(node_id, new_value));
+        let caret = start + inserted.chars().count();
+        self.input_selections.insert(node_id, (caret, caret));
+        true
+    }
+
+    /// The position (in document coordinates) and height of the caret of a text field
+    pub(crate) fn text_field_caret_rect(&self, node_id: usize) -> Option<(taffy::Point<f32>, f32)> {
+        let (_, focus) = self.text_field_selection(node_id)?;
+        let text_id = self.nodes[node_id]
+            .children
+            .iter()
+            .copied()
+            .find(|&child_id| self.nodes[child_id].is_text_node())?;
+        let content = &self.nodes[text_id].text_data()?.content;
+        let offset = byte_offset(content, focus);
+        match self
+            .text_range_rects(text_id, offset..offset)
+            .and_then(|rects| rects.first().copied())
+        {
+            Some(rect) => Some((rect.location, rect.size.height)),
+            None => Some(self.empty_caret_rect(node_id)),
+        }
+    }
+
+    /// The number of characters in a text field's value, or `None` if the element isn't a text field
+    fn text_field_len(&self, node_id: usize) -> Option<usize> {
+        self.is_text_field(node_id).then(|| {
+            self.nodes[node_id]
+                .attr(local_name!("value"))
+                .unwrap_or_default()
+                .chars()
+                .count()
+        })
+    }
+
+    /// The anchor and focus of a text field's selection, clamped to its value
+    fn text_field_selection(&self, node_id: usize) -> Option<(usize, usize)> {
+        let len = self.text_field_len(node_id)?;
+        let (anchor, focus) = self
+            .input_selections
+            .get(&node_id)
+            .copied()
+            .unwrap_or((len, len));
+        Some((anchor.min(len), focus.min(len)))
+    }
+}
+
+#[test]
+fn edits_text_fields() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input id=name value=Ada maxlength=6><input type=checkbox id=checkbox></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let input = doc.get_element_by_id("name").unwrap();
+    let checkbox = doc.get_element_by_id("checkbox").unwrap();
+    let value = |doc: &Document| {
+        doc.nodes[input]
+            .attr(local_name!("value"))
+            .map(str::to_string)
+    };
+    assert_eq!(doc.selection_range(input), Some((3, 3)));
+    assert_eq!(doc.selection_range(checkbox), None);
+
+    // Typing replaces the selection, and stops at the maximum length
+    assert!(doc.set_selection_range(input, 0, 1));
+    assert!(doc.edit_text_field(input, EditCommand::InsertText("Gr".into())));
+    assert_eq!(value(&doc).as_deref(), Some("Grda"));
+    assert_eq!(doc.selection_range(input), Some((2, 2)));
+    assert!(doc.edit_text_field(input, EditCommand::InsertText("ace\n".into())));
+    assert_eq!(value(&doc).as_deref(), Some("Gracda"));
+    assert!(!doc.edit_text_field(input, EditCommand::InsertText("x".into())));
+
+    // Deleting removes the character before the caret, and can be undone
+    assert!(doc.edit_text_field(input, EditCommand::Delete));
+    assert_eq!(value(&doc).as_deref(), Some("Grada"));
+    doc.undo();
+    assert_eq!(value(&doc).as_deref(), Some("Gracda"));
+
+    // Setting the value moves the selection to its end
+    let value_attr = QualName::new(None, ns!(), local_name!("value"));
+    doc.set_attribute(input, value_attr, "Grace Hopper");
+    assert_eq!(doc.selection_range(input), Some((12, 12)));
+}
@@ -0,0 +1,155 @@
+//! Delayed and repeating callbacks
+//!
+//! Timers let embedders and the document's own features (e.g. `<meta http-equiv=refresh>`) run code after a delay
+//! without spawning threads. They run on the frame loop: [`Document::next_frame_deadline`] includes the deadline of
+//! the next timer so that the embedder wakes up in time, and [`Document::begin_frame`] runs the timers that are due.
+//! Timers that are due at the same time run in the order they were set.
+use std::collections::BTreeMap;
+use std::time::Duration;
+use web_time::Instant;
+
+use crate::Document;
+
+/// A callback run by a timer
+pub type TimerCallback = Box<dyn FnMut(&mut Document) + Send>;
+
+/// Identifies a timer set with [`Document::set_timeout`] or [`Document::set_interval`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerHandle(u64);
+
+struct Timer {
+    callback: TimerCallback,
+    /// How often a repeating timer runs
+    interval: Option<Duration>,
+}
+
+#[derive(Default)]
+pub(crate) struct Timers {
+    /// The timers that are waiting to run, ordered by when they're due
+    queue: BTreeMap<(Instant, TimerHandle), Timer>,
+    next_handle: u64,
+    /// The timer whose callback is running, and whether it has been cleared by its callback
+    running: Option<(TimerHandle, bool)>,
+}
+
+impl Document {
+    /// Run a callback once, after `delay`
+    pub fn set_timeout(
+        &mut self,
+        delay: Duration,
+        callback: impl FnOnce(&mut Document) + Send + 'static,
+    ) -> TimerHandle {
+        let mut callback = Some(callback);
+        let callback = move |doc: &mut Document| {
+            if let Some(callback) = callback.take() {
+                callback(doc)
+            }
+        };
+        self.add_timer(Instant::now() + delay, None, Box::new(callback))
+    }
+
+    /// Run a callback every `interval`, until the timer is cleared with [`Document::clear_timer`]
+    pub fn set_interval(
+        &mut self,
+        interval: Duration,
+        callback: impl FnMut(&mut Document) + Send + 'static,
+    ) -> TimerHandle {
+        self.add_timer(
+            Instant::now() + interval,
+            Some(interval),
+            Box::new(callback),
+        )
+    }
+
+    /// Stop a timer. Timers can clear themselves (or each other) from their callbacks.
+    pub fn clear_timer(&mut self, handle: TimerHandle) {
+        let timers = &mut self.timers;
+        match &mut timers.running {
+            Some((running, cleared)) if *running == handle => *cleared = true,
+            _ => timers.queue.retain(|(_, other), _| *other != handle),
+        }
+    }
+
+    fn add_timer(
+        &mut self,
+        deadline: Instant,
+        interval: Option<Duration>,
+        callback: TimerCallback,
+    ) -> TimerHandle {
+        let timers = &mut self.timers;
+        let handle = TimerHandle(timers.next_handle);
+        timers.next_handle += 1;
+        timers
+            .queue
+            .insert((deadline, handle), Timer { callback, interval });
+        handle
+    }
+
+    /// When the next timer is due (if there are any)
+    pub(crate) fn next_timer_deadline(&self) -> Option<Instant> {
+        self.timers
+            .queue
+            .first_key_value()
+            .map(|((deadline, _), _)| *deadline)
+    }
+
+    /// Run the timers that are due at `now`. Timers set by their callbacks run no sooner than the next frame.
+    pub(crate) fn run_due_timers(&mut self, now: Instant) {
+        let due: Vec<(Instant, TimerHandle)> = self
+            .timers
+            .queue
+            .range(..(now, TimerHandle(u64::MAX)))
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in due {
+            // Timers can be cleared by the callbacks of timers that ran before them
+            let Some(mut timer) = self.timers.queue.remove(&key) else {
+                continue;
+            };
+            let (deadline, handle) = key;
+            self.timers.running = Some((handle, false));
+            (timer.callback)(self);
+            let (_, cleared) = self.timers.running.take().unwrap();
+
+            if let (Some(interval), false) = (timer.interval, cleared) {
+                // Repeating timers that have fallen behind skip the runs they missed
+                let next_deadline = (deadline + interval).max(now);
+                self.timers.queue.insert((next_deadline, handle), timer);
+            }
+        }
+    }
+}
+
+#[test]
+fn runs_due_timers_in_order() {
+    use crate::test_util::test_document;
+    use std::sync::{Arc, Mutex};
+
+    let mut doc = test_document(&[]);
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let timeout_log = log.clone();
+    doc.set_timeout(Duration::from_millis(20), move |_| {
+        timeout_log.lock().unwrap().push("timeout")
+    });
+    let interval_log = log.clone();
+    let interval = doc.set_interval(Duration::from_millis(15), move |_| {
+        interval_log.lock().unwrap().push("interval")
+    });
+    let cleared = doc.set_timeout(Duration::from_millis(10), |_| {
+        panic!("cleared timers don't run")
+    });
+    doc.clear_timer(cleared);
+
+    let start = Instant::now();
+    doc.run_due_timers(start + Duration::from_millis(25));
+    assert_eq!(*log.lock().unwrap(), ["interval", "timeout"]);
+
+    // The interval repeats, and the timeout has gone
+    doc.run_due_timers(start + Duration::from_millis(60));
+    doc.clear_timer(interval);
+    doc.run_due_timers(start + Duration::from_millis(100));
+    assert_eq!(*log.lock().unwrap(), ["interval", "timeout", "interval"]);
+    assert_eq!(doc.next_timer_deadline(), None);
+}
@@ -0,0 +1,242 @@
+//! The top layer
+//!
+//! <https://fullscreen.spec.whatwg.org/#top-layer>
+//!
+//! Elements in the top layer (open popovers, modal dialogs and fullscreen elements) are rendered above all other
+//! content in the order in which they were added, regardless of their position in the tree or their z-index. They
+//! are laid out against the viewport rather than their parent, painted in a separate pass after the rest of the
+//! document, and take priority over the rest of the document when hit testing.
+use html5ever::{local_name, namespace_url, ns, QualName};
+use style::invalidation::element::restyle_hints::RestyleHint;
+use style_traits::dom::ElementState;
+use taffy::{AvailableSpace, LengthPercentageAuto, MaybeResolve, NodeId};
+
+use crate::Document;
+
+/// Why an element is in the top layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopLayerKind {
+    Popover,
+    /// A dialog opened with [`Document::show_modal_dialog`]. Content beneath it is inert.
+    ModalDialog,
+    Fullscreen,
+}
+
+impl Document {
+    /// The elements in the top layer, from bottom-most to top-most
+    pub fn top_layer(&self) -> &[(usize, TopLayerKind)] {
+        &self.top_layer
+    }
+
+    pub fn is_in_top_layer(&self, node_id: usize) -> bool {
+        self.top_layer.iter().any(|(id, _)| *id == node_id)
+    }
+
+    /// Add an element to the top of the top layer. If it is already in the top layer it is moved to the top.
+    pub fn add_to_top_layer(&mut self, node_id: usize, kind: TopLayerKind) {
+        self.top_layer.retain(|(id, _)| *id != node_id);
+        self.top_layer.push((node_id, kind));
+        self.nodes[node_id].in_top_layer = true;
+        self.invalidate_top_layer_element(node_id);
+    }
+
+    pub fn remove_from_top_layer(&mut self, node_id: usize) {
+        self.top_layer.retain(|(id, _)| *id != node_id);
+        if let Some(node) = self.nodes.get_mut(node_id) {
+            node.in_top_layer = false;
+        }
+        self.invalidate_top_layer_element(node_id);
+    }
+
+    /// The top-most modal dialog (if any). Content that isn't within it is inert.
+    pub fn active_modal_dialog(&self) -> Option<usize> {
+        self.top_layer
+            .iter()
+            .rev()
+            .find(|(_, kind)| *kind == TopLayerKind::ModalDialog)
+            .map(|(id, _)| *id)
+    }
+
+    /// Open a `<dialog>` element as a modal in the top layer
+    pub fn show_modal_dialog(&mut self, node_id: usize) {
+        let Some(element_data) = self.nodes[node_id].element_data_mut() else {
+            return;
+        };
+        if element_data.name.local != local_name!("dialog") {
+            return;
+        }
+        if element_data.attr(local_name!("open")).is_none() {
+            self.restyle_element(node_id);
+            self.set_attribute(node_id, QualName::new(None, ns!(), local_name!("open")), "");
+        }
+        self.add_to_top_layer(node_id, TopLayerKind::ModalDialog);
+    }
+
+    /// Close a `<dialog>` element, removing it from the top layer if it is modal
+    pub fn close_dialog(&mut self, node_id: usize) {
+        self.restyle_element(node_id);
+        self.remove_attribute(node_id, &local_name!("open"));
+        self.remove_from_top_layer(node_id);
+    }
+
+    /// Make an element fullscreen. Any existing fullscreen element is exited first.
+    pub fn request_fullscreen(&mut self, node_id: usize) {
+        self.exit_fullscreen();
+        self.restyle_element(node_id);
+        self.nodes[node_id]
+            .element_state
+            .insert(ElementState::FULLSCREEN);
+        self.add_to_top_layer(node_id, TopLayerKind::Fullscreen);
+    }
+
+    pub fn exit_fullscreen(&mut self) {
+        let Some(node_id) = self.fullscreen_element() else {
+            return;
+        };
+        self.restyle_element(node_id);
+        self.nodes[node_id]
+            .element_state
+            .remove(ElementState::FULLSCREEN);
+        self.remove_from_top_layer(node_id);
+    }
+
+    pub fn fullscreen_element(&self) -> Option<usize> {
+        self.top_layer
+            .iter()
+            .rev()
+            .find(|(_, kind)| *kind == TopLayerKind::Fullscreen)
+            .map(|(id, _)| *id)
+    }
+
+    /// Dismiss the top-most element of the top layer in response to the Escape key: exits fullscreen, closes
+    /// a modal dialog, or hides an auto popover.
+    ///
+    /// Returns true if anything was dismissed.
+    pub fn dismiss_top_layer(&mut self) -> bool {
+        match self.top_layer.last().copied() {
+            Some((_, TopLayerKind::Fullscreen)) => {
+                self.exit_fullscreen();
+                true
+            }
+            Some((node_id, TopLayerKind::ModalDialog)) => {
+                self.close_dialog(node_id);
+                true
+            }
+            Some((_, TopLayerKind::Popover)) => self.hide_topmost_popover(),
+            None => false,
+        }
+    }
+
+    /// Hit test the top layer. Takes a position relative to the viewport (top layer elements are not scrolled).
+    ///
+    /// Returns:
+    ///   - `Some(Some(id))` if the position hits a top layer element (or one of its descendants)
+    ///   - `Some(None)` if the position misses the top layer but the rest of the document is inert
+    ///   - `None` if the rest of the document should be hit tested as usual
+    pub(crate) fn hit_top_layer(&self, x: f32, y: f32) -> Option<Option<usize>> {
+        for (node_id, _) in self.top_layer.iter().rev() {
+            if let Some(hit_id) = self.nodes[*node_id].hit(x, y) {
+                return Some(Some(hit_id));
+            }
+        }
+
+        match self.active_modal_dialog() {
+            Some(_) => Some(None),
+            None => None,
+        }
+    }
+
+    /// Lay out each top layer element against the viewport. This runs after the main layout pass, during
+    /// which top layer elements are taken out of flow.
+    pub(crate) fn resolve_top_layer_layout(&mut self, viewport: taffy::Size<f32>) {
+        self.top_layer.retain(|(id, _)| self.nodes.contains(*id));
+
+        for (node_id, kind) in self.top_layer.clone() {
+            let node = &mut self.nodes[node_id];
+
+            // Fullscreen elements fill the viewport
+            if kind == TopLayerKind::Fullscreen {
+                node.style.size = taffy::Size {
+                    width: taffy::Dimension::Length(viewport.width),
+                    height: taffy::Dimension::Length(viewport.height),
+                };
+            }
+            node.cache.clear();
+
+            let available_space = taffy::Size {
+                width: AvailableSpace::Definite(viewport.width),
+                height: AvailableSpace::Definite(viewport.height),
+            };
+            taffy::compute_root_layout(self, NodeId::from(node_id), available_space);
+
+            // Position the element next to its anchor, or otherwise within the viewport. Elements with auto margins on both sides are centered
+            // (this is how the UA stylesheet positions dialogs and popovers), otherwise the insets are used.
+            let size = self.nodes[node_id].unrounded_layout.size;
+            if let Some(location) = self.anchored_position(node_id, size, viewport) {
+                self.nodes[node_id].unrounded_layout.location = location;
+                taffy::round_layout(self, NodeId::from(node_id));
+                continue;
+            }
+
+            let node = &mut self.nodes[node_id];
+            let style = &node.style;
+            let x = if style.margin.left == LengthPercentageAuto::Auto
+                && style.margin.right == LengthPercentageAuto::Auto
+            {
+                (viewport.width - size.width) / 2.0
+            } else {
+                style
+                    .inset
+                    .left
+                    .maybe_resolve(Some(viewport.width))
+                    .unwrap_or(0.0)
+            };
+            let y = if style.margin.top == LengthPercentageAuto::Auto
+                && style.margin.bottom == LengthPercentageAuto::Auto
+            {
+                (viewport.height - size.height) / 2.0
+            } else {
+                style
+                    .inset
+                    .top
+                    .maybe_resolve(Some(viewport.height))
+                    .unwrap_or(0.0)
+            };
+            node.unrounded_layout.location = taffy::Point {
+                x: x.max(0.0),
+                y: y.max(0.0),
+            };
+
+            taffy::round_layout(self, NodeId::from(node_id));
+        }
+    }
+
+    /// Take top layer elements out of flow before the main layout pass
+    pub(crate) fn take_top_layer_out_of_flow(&mut self) {
+        for (node_id, _) in self.top_layer.iter() {
+            if let Some(node) = self.nodes.get_mut(*node_id) {
+                node.style.position = taffy::Position::Absolute;
+            }
+        }
+    }
+
+    /// Snapshot an element and mark it (and its subtree) for restyling. Must be called *before* its state or
+    /// attributes are changed.
+    fn restyle_element(&mut self, node_id: usize) {
+        self.snapshot_node(node_id);
+        if let Some(element_data) = self.nodes[node_id].stylo_element_data.borrow_mut().as_mut() {
+            element_data.hint.insert(RestyleHint::restyle_subtree());
+        }
+    }
+
+    fn invalidate_top_layer_element(&mut self, node_id: usize) {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            let Some(node) = self.nodes.get_mut(id) else {
+                break;
+            };
+            node.cache.clear();
+            maybe_id = node.parent;
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Machine readable snapshots of a document
+//!
+//! [`Document::dump_json`] serializes the tree (like [`Document::print_tree`]) along with each element's layout and
+//! a selection of its computed styles, so that layouts can be diffed between builds. [`Document::assert_golden_dump`]
+//! compares a snapshot against a golden file checked into the repository, for layout regression tests in CI. Set the
+//! `BLITZ_UPDATE_GOLDEN` environment variable to write the golden files instead, after reviewing the changes.
+//!
+//! Snapshots are kept stable and compact: text nodes that are only white space are left out (as they are by
+//! [`Document::print_tree`]), layouts are rounded to hundredths of a pixel, and each box's location is relative to
+//! its parent box (as laid out by taffy) so that moving one box doesn't change the snapshot of every box after it.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use style::properties::ComputedValues;
+use style_traits::ToCss;
+
+use crate::node::NodeData;
+use crate::password::PASSWORD_MASK;
+use crate::{Document, Node};
+
+/// The environment variable that makes [`Document::assert_golden_dump`] write golden files rather than check them
+pub const UPDATE_GOLDEN_ENV: &str = "BLITZ_UPDATE_GOLDEN";
+
+/// A snapshot of a node and its descendants
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDump {
+    /// `#document`, `#fragment`, `#text`, `#comment` or `#anonymous` (for anonymous blocks), or the element's tag name
+    pub name: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attrs: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutDump>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub style: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NodeDump>,
+}
+
+/// The border box of a node, relative to its parent box
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayoutDump {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The difference between a snapshot and its golden file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenMismatch {
+    pub path: PathBuf,
+    /// The first line that differs (counting from one)
+    pub line: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |line: &Option<String>| line.clone().unwrap_or_else(|| "<end of file>".into());
+        write!(
+            f,
+            "snapshot differs from {} at line {}\n  expected: {}\n  actual:   {}\nset {}=1 to update it",
+            self.path.display(),
+            self.line,
+            line(&self.expected),
+            line(&self.actual),
+            UPDATE_GOLDEN_ENV,
+        )
+    }
+}
+
+impl std::error::Error for GoldenMismatch {}
+
+impl Document {
+    /// A snapshot of the document's tree, layout and computed styles
+    pub fn dump_tree(&self) -> NodeDump {
+        dump_node(self.root_node())
+    }
+
+    /// A snapshot of the document's tree, layout and computed styles, as pretty printed JSON
+    pub fn dump_json(&self) -> String {
+        serde_json::to_string_pretty(&self.dump_tree()).expect("snapshots are always serializable")
+    }
+
+    /// Compare a snapshot of the document with a golden file, or write the golden file if the `BLITZ_UPDATE_GOLDEN`
+    /// environment variable is set
+    pub fn check_golden_dump(&self, path: impl AsRef<Path>) -> Result<(), GoldenMismatch> {
+        let update = std::env::var_os(UPDATE_GOLDEN_ENV).is_some();
+        compare_golden(&self.dump_json(), path.as_ref(), update)
+    }
+
+    /// Like [`Document::check_golden_dump`], but panics with the first difference
+    #[track_caller]
+    pub fn assert_golden_dump(&self, path: impl AsRef<Path>) {
+        if let Err(mismatch) = self.check_golden_dump(path) {
+            panic!("{mismatch}");
+        }
+    }
+}
+
+fn dump_node(node: &Node) -> NodeDump {
+    let (name, attrs, text) = match &node.raw_dom_data {
+        NodeData::Document => ("#document".to_string(), BTreeMap::new(), None),
+        NodeData::DocumentFragment => ("#fragment".to_string(), BTreeMap::new(), None),
+        NodeData::Comment => ("#comment".to_string(), BTreeMap::new(), None),
+        NodeData::AnonymousBlock(_) => ("#anonymous".to_string(), BTreeMap::new(), None),
+        NodeData::Text(data) => (
+            "#text".to_string(),
+            BTreeMap::new(),
+            Some(data.content.clone()),
+        ),
+        NodeData::Element(data) => {
+            // The values of password inputs are masked
+            let attrs = data
+                .attrs
+                .iter()
+                .map(|attr| {
+                    let value = match attr.name.local.as_ref() {
+                        "value" if node.is_password_input() => {
+                            attr.value.chars().map(|_| PASSWORD_MASK).collect()
+                        }
+                        _ => attr.value.clone(),
+                    };
+                    (attr.name.local.to_string(), value)
+                })
+                .collect();
+            (data.name.local.to_string(), attrs, None)
+        }
+    };
+
+    let is_box = matches!(
+        node.raw_dom_data,
+        NodeData::Element(_) | NodeData::AnonymousBlock(_)
+    );
+    let layout = is_box.then(|| {
+        let layout = node.final_layout;
+        LayoutDump {
+            x: round(layout.location.x),
+            y: round(layout.location.y),
+            width: round(layout.size.width),
+            height: round(layout.size.height),
+        }
+    });
+    let style = match node.is_element() {
+        true => node
+            .primary_styles()
+            .map(|style| dump_style(&style))
+            .unwrap_or_default(),
+        false => BTreeMap::new(),
+    };
+
+    // As is the text that displays them, which has the value itself while it's revealed
+    let children = node
+        .children
+        .iter()
+        .filter(|_| !node.is_password_input())
+        .map(|child_id| node.with(*child_id))
+        .filter(|child| {
+            !child
+                .text_data()
+                .is_some_and(|data| data.content.chars().all(|c| c.is_ascii_whitespace()))
+        })
+        .map(dump_node)
+        .collect();
+
+    NodeDump {
+        name,
+        attrs,
+        text,
+        layout,
+        style,
+        children,
+    }
+}
+
+/// The computed values of the properties that most affect layout
+fn dump_style(style: &ComputedValues) -> BTreeMap<String, String> {
+    let position = style.get_position();
+    let margin = style.get_margin();
+    let padding = style.get_padding();
+    let border = style.get_border();
+    let font = style.get_font();
+    [
+        ("display", style.get_box().display.to_css_string()),
+        ("position", style.get_box().position.to_css_string()),
+        ("width", position.width.to_css_string()),
+        ("height", position.height.to_css_string()),
+        ("margin-top", margin.margin_top.to_css_string()),
+        ("margin-right", margin.margin_right.to_css_string()),
+        ("margin-bottom", margin.margin_bottom.to_css_string()),
+        ("margin-left", margin.margin_left.to_css_string()),
+        ("padding-top", padding.padding_top.to_css_string()),
+        ("padding-right", padding.padding_right.to_css_string()),
+        ("padding-bottom", padding.padding_bottom.to_css_string()),
+        ("padding-left", padding.padding_left.to_css_string()),
+        ("border-top-width", border.border_top_width.to_css_string()),
+        (
+            "border-right-width",
+            border.border_right_width.to_css_string(),
+        ),
+        (
+            "border-bottom-width",
+            border.border_bottom_width.to_css_string(),
+        ),
+        (
+            "border-left-width",
+            border.border_left_width.to_css_string(),
+        ),
+        ("font-size", font.font_size.to_css_string()),
+        ("line-height", font.line_height.to_css_string()),
+        ("color", style.get_inherited_text().color.to_css_string()),
+        (
+            "background-color",
+            style.get_background().background_color.to_css_string(),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+fn round(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+fn compare_golden(actual: &str, path: &Path, update: bool) -> Result<(), GoldenMismatch> {
+    if update {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("failed to create golden file directory");
+        }
+        std::fs::write(path, actual).expect("failed to write golden file");
+        return Ok(());
+    }
+
+    let expected = std::fs::read_to_string(path).unwrap_or_default();
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return Ok(()),
+            (expected, actual) if expected == actual => line += 1,
+            (expected, actual) => {
+                return Err(GoldenMismatch {
+                    path: path.to_path_buf(),
+                    line,
+                    expected: expected.map(str::to_string),
+                    actual: actual.map(str::to_string),
+                })
+            }
+        }
+    }
+}
+
+#[test]
+fn dumps_tree_layout_and_styles() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\n  \
+        <div id=box style='margin-left: 10px; width: 100px; height: 20.004px'></div>\n\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let json = doc.dump_json();
+    let dump: NodeDump = serde_json::from_str(&json).unwrap();
+    assert_eq!(dump, doc.dump_tree());
+
+    let html = dump
+        .children
+        .iter()
+        .find(|node| node.name == "html")
+        .unwrap();
+    let body = html
+        .children
+        .iter()
+        .find(|node| node.name == "body")
+        .unwrap();
+    // White space between elements is left out
+    assert_eq!(body.children.len(), 1);
+    let div = &body.children[0];
+    assert_eq!(div.attrs["id"], "box");
+    assert_eq!(
+        div.layout,
+        Some(LayoutDump {
+            x: 10.0,
+            y: 0.0,
+            width: 100.0,
+            height: 20.0,
+        })
+    );
+    assert_eq!(div.style["width"], "100px");
+    assert_eq!(div.style["display"], "block");
+
+    // Golden files match until the document changes
+    let path = std::env::temp_dir().join(format!("blitz-golden-{}.json", std::process::id()));
+    compare_golden(&json, &path, true).unwrap();
+    assert_eq!(compare_golden(&json, &path, false), Ok(()));
+    let changed = json.replace("\"width\": 100.0", "\"width\": 101.0");
+    let mismatch = compare_golden(&changed, &path, false).unwrap_err();
+    assert_eq!(
+        mismatch.expected.as_deref().map(str::trim),
+        Some("\"width\": 100.0,")
+    );
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,100 @@
+//! User agent and user stylesheets
+//!
+//! Stylesheets apply at one of three cascade origins. The user agent stylesheets provide the default styles of
+//! elements, user stylesheets customise them (e.g. to theme widgets), and the document's own (author) stylesheets take
+//! precedence over both. Because origins are ordered before specificity, embedders can restyle elements with user
+//! stylesheets without resorting to specificity hacks, and pages can still override those styles.
+//!
+//! The document starts with no user agent stylesheets: embedders supply the default stylesheet, and can replace or
+//! extend it.
+use style::stylesheets::Origin;
+
+use crate::Document;
+
+impl Document {
+    /// Replace the user agent stylesheets, applying `sheets` in order
+    pub fn with_ua_stylesheets<'a>(mut self, sheets: impl IntoIterator<Item = &'a str>) -> Self {
+        self.set_ua_stylesheets(sheets);
+        self
+    }
+
+    /// Replace the user agent stylesheets, applying `sheets` in order
+    pub fn set_ua_stylesheets<'a>(&mut self, sheets: impl IntoIterator<Item = &'a str>) {
+        for sheet in std::mem::take(&mut self.ua_stylesheets) {
+            self.stylist.remove_stylesheet(sheet, &self.guard.read());
+        }
+        for css in sheets {
+            self.add_ua_stylesheet(css);
+        }
+    }
+
+    /// Extend the user agent stylesheets with a stylesheet that applies after the existing ones
+    pub fn add_ua_stylesheet(&mut self, css: &str) {
+        let sheet = self.parse_stylesheet(css, Origin::UserAgent);
+
+        // The dark stylesheet (see `color_scheme`) always applies after the others
+        let guard = self.guard.read();
+        match self.color_scheme.dark_sheet.clone() {
+            Some(dark_sheet) => {
+                self.stylist
+                    .insert_stylesheet_before(sheet.clone(), dark_sheet, &guard)
+            }
+            None => self.stylist.append_stylesheet(sheet.clone(), &guard),
+        }
+        drop(guard);
+
+        self.ua_stylesheets.push(sheet);
+        self.stylist
+            .force_stylesheet_origins_dirty(Origin::UserAgent.into());
+        self.invalidate();
+    }
+
+    /// The number of user agent stylesheets applied to the document
+    pub fn ua_stylesheet_count(&self) -> usize {
+        self.ua_stylesheets.len()
+    }
+
+    /// Add a user stylesheet, which takes precedence over the user agent stylesheets but not the document's own
+    pub fn add_user_stylesheet(&mut self, css: &str) {
+        let sheet = self.parse_stylesheet(css, Origin::User);
+        self.stylist
+            .append_stylesheet(sheet.clone(), &self.guard.read());
+        if let Some(previous) = self.user_stylesheets.insert(css.to_string(), sheet) {
+            self.stylist.remove_stylesheet(previous, &self.guard.read());
+        }
+        self.flush_user_stylesheets();
+    }
+
+    /// Remove a user stylesheet added with the same source text
+    pub fn remove_user_stylesheet(&mut self, css: &str) {
+        if let Some(sheet) = self.user_stylesheets.remove(css) {
+            self.stylist.remove_stylesheet(sheet, &self.guard.read());
+            self.flush_user_stylesheets();
+        }
+    }
+
+    fn flush_user_stylesheets(&mut self) {
+        self.stylist
+            .force_stylesheet_origins_dirty(Origin::User.into());
+        self.invalidate();
+    }
+}
+
+#[test]
+fn stylesheets_apply_at_their_origins() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><div id=a></div><div id=b class=b></div></body></html>";
+    let mut doc = parse_test_document(html, &["div { display: block }"]);
+
+    // The user stylesheet beats the more specific user agent rule, and loses to the author rule
+    doc.add_ua_stylesheet("#a { display: grid }");
+    doc.add_user_stylesheet("div { display: flex }");
+    doc.add_stylesheet(".b { display: grid }");
+    doc.resolve();
+
+    let display = |id: &str| doc.nodes[doc.get_element_by_id(id).unwrap()].style.display;
+    assert_eq!(doc.ua_stylesheet_count(), 2);
+    assert_eq!(display("a"), taffy::Display::Flex);
+    assert_eq!(display("b"), taffy::Display::Grid);
+}
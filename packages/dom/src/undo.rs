@@ -0,0 +1,289 @@
+//! Undoing and redoing edits
+//!
+//! <https://w3c.github.io/editing/docs/execCommand/#the-undo-command>
+//!
+//! Each edit made with [`Document::exec_command`] (or [`Document::edit_text_field`]) is recorded as a transaction: a copy of the editing host's contents
+//! (and the selection within them) from before the edit. Undoing a transaction swaps the host's contents for the
+//! copy, keeping a copy of what was replaced so that the edit can be redone. Consecutive text insertions at the caret
+//! are coalesced into one transaction until a word is finished (or the caret is moved, or another edit is made), so
+//! undoing removes what was typed a word at a time rather than a character at a time. Making a new edit forgets the
+//! transactions that could have been redone.
+//!
+//! Embedders can record their own changes to the document as transactions with [`Document::transact`], to be undone
+//! and redone alongside the user's edits.
+//!
+//! The copies are detached nodes that are kept in the document until their transaction is dropped. At most
+//! [`MAX_UNDO_TRANSACTIONS`] transactions are kept, dropping the oldest.
+//!
+//! Note: the edits of text fields aren't coalesced, so they're undone a character at a time.
+use html5ever::QualName;
+
+use crate::editing::Caret;
+use crate::Document;
+
+/// The number of transactions that can be undone
+pub const MAX_UNDO_TRANSACTIONS: usize = 100;
+
+/// A position in the text of an element, as the index of a text node within it (in tree order) and a byte offset
+/// within that text node, which survives the element's contents being copied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TextPosition {
+    text_node: usize,
+    offset: usize,
+}
+
+/// The contents of an element at some point
+#[derive(Debug)]
+struct Transaction {
+    /// The element whose contents were changed
+    scope: usize,
+    /// A detached copy of the element (with its attributes and contents) from before or after the change
+    copy: usize,
+    /// The caret and selection anchor within the copy
+    selection: Option<(TextPosition, TextPosition)>,
+}
+
+/// The transactions that can be undone and redone
+#[derive(Debug, Default)]
+pub(crate) struct EditHistory {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    /// The editing host and caret position after the last text insertion, while typing is being coalesced
+    typing: Option<(usize, Caret)>,
+}
+
+impl Document {
+    /// Whether there is a transaction that can be undone
+    pub fn can_undo(&self) -> bool {
+        !self.edit_history.undo.is_empty()
+    }
+
+    /// Whether there is a transaction that can be redone
+    pub fn can_redo(&self) -> bool {
+        !self.edit_history.redo.is_empty()
+    }
+
+    /// Undo the last transaction. Returns the element whose contents were restored, if there was one to undo.
+    pub fn undo(&mut self) -> Option<usize> {
+        self.edit_history.typing = None;
+        let transaction = self.edit_history.undo.pop()?;
+        let scope = transaction.scope;
+        let current = self.capture_transaction(scope);
+        self.restore_transaction(transaction);
+        self.edit_history.redo.push(current);
+        Some(scope)
+    }
+
+    /// Redo the last transaction that was undone. Returns the element whose contents were restored, if there was one
+    /// to redo.
+    pub fn redo(&mut self) -> Option<usize> {
+        self.edit_history.typing = None;
+        let transaction = self.edit_history.redo.pop()?;
+        let scope = transaction.scope;
+        let current = self.capture_transaction(scope);
+        self.restore_transaction(transaction);
+        self.edit_history.undo.push(current);
+        Some(scope)
+    }
+
+    /// Forget all of the transactions
+    pub fn clear_edit_history(&mut self) {
+        let history = std::mem::take(&mut self.edit_history);
+        for transaction in history.undo.into_iter().chain(history.redo) {
+            self.remove_node(transaction.copy);
+        }
+    }
+
+    /// Make a change to the document that can be undone, restoring the attributes and contents of `scope` (an
+    /// element that contains everything that `change` changes) to what they were before it
+    pub fn transact(&mut self, scope: usize, change: impl FnOnce(&mut Document)) {
+        self.edit_history.typing = None;
+        let before = self.capture_transaction(scope);
+        change(self);
+        self.push_transaction(before);
+    }
+
+    /// Run an editing command in an editing host as a transaction, coalescing text insertions. Returns whether the
+    /// command changed the document.
+    pub(crate) fn transact_edit(
+        &mut self,
+        host: usize,
+        typed: Option<&str>,
+        edit: impl FnOnce(&mut Document) -> bool,
+    ) -> bool {
+        // Typing continues the current transaction if the caret is where the last insertion left it
+        let coalesced = typed.is_some()
+            && self.edit_history.typing.is_some()
+            && self.edit_history.typing == self.caret.map(|caret| (host, caret));
+        let before = (!coalesced).then(|| self.capture_transaction(host));
+
+        let changed = edit(self);
+        match before {
+            Some(before) if changed => self.push_transaction(before),
+            Some(before) => {
+                self.remove_node(before.copy);
+            }
+            None => {}
+        }
+
+        // A word ends the coalesced transaction when it's followed by white space
+        self.edit_history.typing = match (typed, self.caret) {
+            (Some(text), Some(caret)) if changed && !text.ends_with(char::is_whitespace) => {
+                Some((host, caret))
+            }
+            _ => None,
+        };
+        changed
+    }
+
+    fn push_transaction(&mut self, transaction: Transaction) {
+        for transaction in std::mem::take(&mut self.edit_history.redo) {
+            self.remove_node(transaction.copy);
+        }
+        self.edit_history.undo.push(transaction);
+        if self.edit_history.undo.len() > MAX_UNDO_TRANSACTIONS {
+            let oldest = self.edit_history.undo.remove(0);
+            self.remove_node(oldest.copy);
+        }
+    }
+
+    /// Copy the current attributes, contents and selection of an element
+    fn capture_transaction(&mut self, scope: usize) -> Transaction {
+        let text_nodes = self.editable_text_nodes(scope);
+        let position = |caret: Caret| {
+            text_nodes
+                .iter()
+                .position(|node_id| *node_id == caret.node_id)
+                .map(|text_node| TextPosition {
+                    text_node,
+                    offset: caret.offset,
+                })
+        };
+        let selection = self.caret.and_then(position).map(|caret| {
+            let anchor = self.selection_anchor.and_then(position).unwrap_or(caret);
+            (caret, anchor)
+        });
+        Transaction {
+            scope,
+            copy: self.clone_node(scope, true),
+            selection,
+        }
+    }
+
+    /// Replace the attributes and contents of a transaction's element with its copy (which is used up)
+    fn restore_transaction(&mut self, transaction: Transaction) {
+        let Transaction {
+            scope,
+            copy,
+            selection,
+        } = transaction;
+
+        // Attributes
+        let current_attrs = self.nodes[scope]
+            .element_data()
+            .map(|element_data| element_data.attrs().to_vec())
+            .unwrap_or_default();
+        let copied_attrs = self.nodes[copy]
+            .element_data()
+            .map(|element_data| element_data.attrs().to_vec())
+            .unwrap_or_default();
+        for attr in &current_attrs {
+            if !copied_attrs
+                .iter()
+                .any(|copied| copied.name.local == attr.name.local)
+            {
+                self.remove_attribute(scope, &attr.name.local);
+            }
+        }
+        for attr in copied_attrs {
+            let name = QualName::new(attr.name.prefix, attr.name.ns, attr.name.local);
+            self.set_attribute(scope, name, &attr.value);
+        }
+
+        // Contents
+        for child_id in self.nodes[scope].children.clone() {
+            self.remove_node(child_id);
+        }
+        let children = std::mem::take(&mut self.nodes[copy].children);
+        self.append_children(scope, &children);
+        self.remove_node(copy);
+        self.invalidate_edited_node(scope);
+
+        // Selection
+        let text_nodes = self.editable_text_nodes(scope);
+        let caret = |position: TextPosition| {
+            text_nodes.get(position.text_node).map(|node_id| Caret {
+                node_id: *node_id,
+                offset: position.offset,
+            })
+        };
+        match selection.and_then(|(caret_position, anchor_position)| {
+            Some((caret(caret_position)?, caret(anchor_position)?))
+        }) {
+            Some((caret, anchor)) => {
+                self.caret = Some(caret);
+                self.selection_anchor = Some(anchor).filter(|anchor| *anchor != caret);
+            }
+            None => {
+                if self
+                    .caret
+                    .is_some_and(|caret| !self.nodes.contains(caret.node_id))
+                {
+                    self.clear_caret();
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn undoes_and_redoes_edits() {
+    use crate::editing::EditCommand;
+    use crate::test_util::parse_test_document;
+    use html5ever::{local_name, ns};
+
+    let html = "<!DOCTYPE html><html><body><div contenteditable id=host>ab</div></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+
+    let host = doc.get_element_by_id("host").unwrap();
+    let text = doc.nodes[host].children[0];
+    doc.set_caret(
+        Caret {
+            node_id: text,
+            offset: 2,
+        },
+        false,
+    );
+    let content = |doc: &Document| doc.nodes[host].text_content();
+
+    // Typing is undone a word at a time
+    for typed in ["c", "d", " ", "e", "f"] {
+        doc.exec_command(EditCommand::InsertText(typed.into()));
+    }
+    assert_eq!(content(&doc), "abcd ef");
+    assert_eq!(doc.undo(), Some(host));
+    assert_eq!(content(&doc), "abcd ");
+    assert_eq!(doc.undo(), Some(host));
+    assert_eq!(content(&doc), "ab");
+    assert!(!doc.can_undo());
+
+    // The caret is restored with the contents, so typing carries on from there
+    assert_eq!(doc.redo(), Some(host));
+    assert_eq!(content(&doc), "abcd ");
+    assert_eq!(doc.caret().map(|caret| caret.offset), Some(5));
+    doc.exec_command(EditCommand::InsertText("g".into()));
+    assert_eq!(content(&doc), "abcd g");
+    assert!(!doc.can_redo());
+
+    // Embedders' changes are undone too
+    doc.transact(host, |doc| {
+        doc.set_attribute(host, QualName::new(None, ns!(), local_name!("title")), "x");
+        let text = doc.nodes[host].children[0];
+        doc.nodes[text].text_data_mut().unwrap().content = "replaced".into();
+    });
+    assert_eq!(content(&doc), "replaced");
+    doc.undo();
+    assert_eq!(content(&doc), "abcd g");
+    assert_eq!(doc.nodes[host].attr(local_name!("title")), None);
+}
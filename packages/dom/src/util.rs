@@ -1,39 +1,64 @@
-use std::io::{Cursor, Read};
+use std::fmt;
 
+use crate::decoded_images::ImageSource;
+use crate::network::NetworkProvider;
 use crate::node::{Node, NodeData};
 use image::DynamicImage;
+use url::Url;
+
+/// Why a resource couldn't be fetched
+#[derive(Debug)]
+pub enum FetchErr {
+    /// The resource's URL couldn't be parsed
+    InvalidUrl(url::ParseError),
+    /// A `data:` URL whose contents couldn't be decoded
+    InvalidDataUrl,
+    /// The server redirected more times than the [`FetchPolicy`](crate::network::FetchPolicy) allows
+    TooManyRedirects,
+    /// The request failed, or the server responded with an error status
+    #[cfg(feature = "network")]
+    Request(Box<ureq::Error>),
+    /// Blitz was built without the `network` feature, so the resource can't be requested
+    NetworkDisabled(Url),
+    /// Reading the response failed
+    Io(std::io::Error),
+}
 
-const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:60.0) Gecko/20100101 Firefox/81.0";
-const FILE_SIZE_LIMIT: u64 = 1_000_000_000; // 1GB
-
-pub(crate) fn fetch_blob(url: &str) -> Result<Vec<u8>, Box<ureq::Error>> {
-    if url.starts_with("data:") {
-        let data_url = data_url::DataUrl::process(url).unwrap();
-        let decoded = data_url.decode_to_vec().expect("Invalid data url");
-        return Ok(decoded.0);
+impl fmt::Display for FetchErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(err) => write!(f, "invalid url: {}", err),
+            Self::InvalidDataUrl => write!(f, "invalid data url"),
+            Self::TooManyRedirects => write!(f, "too many redirects"),
+            #[cfg(feature = "network")]
+            Self::Request(err) => write!(f, "request failed: {}", err),
+            Self::NetworkDisabled(url) => {
+                write!(f, "can't request {}: networking is disabled", url)
+            }
+            Self::Io(err) => write!(f, "error reading response: {}", err),
+        }
     }
+}
 
-    let resp = ureq::get(url)
-        .set("User-Agent", USER_AGENT)
-        .call()
-        .map_err(Box::new)?;
-
-    let len: usize = resp
-        .header("Content-Length")
-        .and_then(|c| c.parse().ok())
-        .unwrap_or(0);
-    let mut bytes: Vec<u8> = Vec::with_capacity(len);
-
-    resp.into_reader()
-        .take(FILE_SIZE_LIMIT)
-        .read_to_end(&mut bytes)
-        .unwrap();
+impl std::error::Error for FetchErr {}
 
-    Ok(bytes)
+pub(crate) fn fetch_blob(
+    network: &NetworkProvider,
+    url: &str,
+    document_url: Option<&Url>,
+) -> Result<Vec<u8>, FetchErr> {
+    network
+        .fetch_from(url, document_url)
+        .map(|(bytes, _)| bytes)
 }
 
-pub(crate) fn fetch_string(url: &str) -> Result<String, Box<ureq::Error>> {
-    fetch_blob(url).map(|vec| String::from_utf8(vec).expect("Invalid UTF8"))
+pub(crate) fn fetch_string(
+    network: &NetworkProvider,
+    url: &str,
+    document_url: Option<&Url>,
+) -> Result<String, FetchErr> {
+    let (bytes, content_type) = network.fetch_from(url, document_url)?;
+    Ok(crate::charset::decode_text(&bytes, content_type.as_deref()))
 }
 
 // pub(crate) fn fetch_buffered_stream(
@@ -43,13 +68,17 @@ pub(crate) fn fetch_string(url: &str) -> Result<String, Box<ureq::Error>> {
 //     Ok(BufReader::new(resp.into_reader().take(FILE_SIZE_LIMIT)))
 // }
 
-#[allow(unused)]
-pub(crate) enum ImageFetchErr {
-    FetchErr(Box<ureq::Error>),
+/// Why an image couldn't be loaded
+#[derive(Debug)]
+pub enum ImageFetchErr {
+    FetchErr(FetchErr),
     ImageError(image::error::ImageError),
+    SvgError(usvg::Error),
+    /// Blitz was built without the feature (named here) that decodes the image's format
+    UnsupportedFormat(&'static str),
 }
-impl From<Box<ureq::Error>> for ImageFetchErr {
-    fn from(value: Box<ureq::Error>) -> Self {
+impl From<FetchErr> for ImageFetchErr {
+    fn from(value: FetchErr) -> Self {
         Self::FetchErr(value)
     }
 }
@@ -59,13 +88,49 @@ impl From<image::error::ImageError> for ImageFetchErr {
     }
 }
 
-pub(crate) fn fetch_image(url: &str) -> Result<DynamicImage, ImageFetchErr> {
-    let blob = crate::util::fetch_blob(url)?;
-    let image = image::io::Reader::new(Cursor::new(blob))
-        .with_guessed_format()
-        .expect("IO errors impossible with Cursor")
-        .decode()?;
-    Ok(image)
+impl From<usvg::Error> for ImageFetchErr {
+    fn from(value: usvg::Error) -> Self {
+        Self::SvgError(value)
+    }
+}
+
+impl fmt::Display for ImageFetchErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FetchErr(err) => err.fmt(f),
+            Self::ImageError(err) => write!(f, "error decoding image: {}", err),
+            Self::SvgError(err) => write!(f, "error parsing SVG image: {}", err),
+            Self::UnsupportedFormat(feature) => write!(
+                f,
+                "can't decode image: blitz was built without the `{}` feature",
+                feature
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageFetchErr {}
+
+/// Fetch and decode an image. `size` is the size the image is wanted at, which picks the icon of an ICO file (see
+/// [`crate::image_formats`]).
+pub(crate) fn fetch_image(
+    network: &NetworkProvider,
+    url: &str,
+    document_url: Option<&Url>,
+    size: Option<(u32, u32)>,
+) -> Result<DynamicImage, ImageFetchErr> {
+    let blob = crate::util::fetch_blob(network, url, document_url)?;
+    crate::image_formats::decode_image(&blob, size)
+}
+
+/// Fetch an image without decoding it (see [`crate::decoded_images`])
+pub(crate) fn fetch_image_source(
+    network: &NetworkProvider,
+    url: &str,
+    document_url: Option<&Url>,
+) -> Result<ImageSource, ImageFetchErr> {
+    let blob = crate::util::fetch_blob(network, url, document_url)?;
+    ImageSource::new(url.to_string(), blob)
 }
 
 // Debug print an RcDom
@@ -80,6 +145,7 @@ pub fn walk_tree(indent: usize, node: &Node) {
     print!("{}", " ".repeat(indent));
     match &node.raw_dom_data {
         NodeData::Document => println!("#Document"),
+        NodeData::DocumentFragment => println!("#DocumentFragment"),
 
         NodeData::Text(data) => {
             if data.content.chars().all(|c| c.is_ascii_whitespace()) {
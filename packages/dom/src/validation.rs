@@ -0,0 +1,561 @@
+//! Constraint validation for form controls
+//!
+//! <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constraints>
+//!
+//! The validity of `<input>`, `<textarea>` and `<select>` elements is computed from their attributes
+//! (`required`, `pattern`, `min`, `max`, `step`, `minlength`, `maxlength`) and their type, and is used to match
+//! the `:valid` and `:invalid` pseudo-classes.
+//!
+//! Note: the servo configuration of stylo can't parse `:in-range`, `:out-of-range` or `:user-invalid`, so the
+//! states those pseudo-classes represent are only exposed through [`Document::is_in_range`] and
+//! [`Document::is_user_invalid`].
+use atomic_refcell::AtomicRefCell;
+use html5ever::local_name;
+use regex::Regex;
+
+use crate::pickers::Date;
+use crate::{Document, Node};
+
+/// A reason that a form control does not satisfy its constraints
+///
+/// <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#validitystate>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidityError {
+    ValueMissing,
+    TypeMismatch,
+    PatternMismatch,
+    TooLong,
+    TooShort,
+    RangeUnderflow,
+    RangeOverflow,
+    StepMismatch,
+}
+
+/// An input's compiled `pattern` attribute, kept so that matching `:valid` and `:invalid` doesn't compile the
+/// pattern on every restyle. It's compiled again when the attribute changes.
+#[derive(Default)]
+pub(crate) struct PatternCache(AtomicRefCell<Option<(String, Option<Regex>)>>);
+
+impl PatternCache {
+    /// The regex that `pattern` compiles to, or `None` if it isn't a valid pattern
+    fn regex(&self, pattern: &str) -> Option<Regex> {
+        // The pattern must match the entire value
+        let compile = || Regex::new(&format!("^(?:{pattern})$")).ok();
+        let Ok(mut cached) = self.0.try_borrow_mut() else {
+            return compile();
+        };
+        match &*cached {
+            Some((cached_pattern, regex)) if cached_pattern == pattern => regex.clone(),
+            _ => {
+                let regex = compile();
+                *cached = Some((pattern.to_string(), regex.clone()));
+                regex
+            }
+        }
+    }
+}
+
+impl Clone for PatternCache {
+    fn clone(&self) -> Self {
+        let cached = self.0.try_borrow().ok().and_then(|cached| cached.clone());
+        Self(AtomicRefCell::new(cached))
+    }
+}
+
+impl std::fmt::Debug for PatternCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PatternCache").finish_non_exhaustive()
+    }
+}
+
+/// The input types whose value is a number (or date) that `min`, `max` and `step` apply to
+fn is_ranged_type(input_type: &str) -> bool {
+    matches!(input_type, "number" | "range" | "date")
+}
+
+/// Parse the value of a ranged input as a number. Dates are converted to a number of days.
+fn parse_ranged_value(input_type: &str, value: &str) -> Option<f64> {
+    match input_type {
+        "date" => {
+            let date = Date::parse(value)?;
+            // Days since 0000-03-01, which is enough to compare and step dates
+            let (year, month) = if date.month < 3 {
+                (date.year - 1, date.month + 12)
+            } else {
+                (date.year, date.month)
+            };
+            let days = 365 * year as i64 + year as i64 / 4 - year as i64 / 100
+                + year as i64 / 400
+                + (153 * (month as i64 - 3) + 2) / 5
+                + date.day as i64;
+            Some(days as f64)
+        }
+        _ => value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite()),
+    }
+}
+
+fn is_valid_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.is_empty()
+        && !value.contains(char::is_whitespace)
+        && !domain.contains('@')
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+}
+
+impl Node {
+    /// The type of an `<input>` element, lowercased, defaulting to "text"
+    pub(crate) fn input_type(&self) -> String {
+        self.attr(local_name!("type"))
+            .map(|ty| ty.to_ascii_lowercase())
+            .unwrap_or_else(|| "text".to_string())
+    }
+
+    /// The current value of a form control
+    pub(crate) fn control_value(&self) -> String {
+        let Some(element_data) = self.element_data() else {
+            return String::new();
+        };
+        match element_data.name.local.as_ref() {
+            "textarea" => self.text_content(),
+            "select" => {
+                // The value of the first selected option, or the first option if none is selected
+                let mut options = Vec::new();
+                self.collect_options(&mut options);
+                let option = options
+                    .iter()
+                    .find(|option| option.attr(local_name!("selected")).is_some())
+                    .or(options.first());
+                option
+                    .map(|option| match option.attr(local_name!("value")) {
+                        Some(value) => value.to_string(),
+                        None => option.text_content().trim().to_string(),
+                    })
+                    .unwrap_or_default()
+            }
+            _ => element_data
+                .attr(local_name!("value"))
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    fn collect_options<'a>(&'a self, options: &mut Vec<&'a Node>) {
+        for child_id in self.children.iter() {
+            let child = self.with(*child_id);
+            if child.is_element_with_tag_name(&local_name!("option")) {
+                options.push(child);
+            } else if child.is_element_with_tag_name(&local_name!("optgroup")) {
+                child.collect_options(options);
+            }
+        }
+    }
+
+    /// Whether the element is a form control that is subject to constraint validation
+    ///
+    /// <https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#candidate-for-constraint-validation>
+    pub fn is_validation_candidate(&self) -> bool {
+        let Some(element_data) = self.element_data() else {
+            return false;
+        };
+        let is_candidate_type = match element_data.name.local.as_ref() {
+            "input" => !matches!(
+                self.input_type().as_str(),
+                "hidden" | "button" | "reset" | "image"
+            ),
+            "textarea" | "select" => true,
+            _ => false,
+        };
+
+        is_candidate_type && !self.is_disabled() && self.attr(local_name!("readonly")).is_none()
+    }
+
+    /// The constraints that the element's value does not satisfy. Empty if the element is valid (or is not a
+    /// candidate for constraint validation).
+    pub fn validity_errors(&self) -> Vec<ValidityError> {
+        let mut errors = Vec::new();
+        if !self.is_validation_candidate() {
+            return errors;
+        }
+
+        let value = self.control_value();
+        let is_input = self.is_element_with_tag_name(&local_name!("input"));
+        let input_type = if is_input {
+            self.input_type()
+        } else {
+            String::new()
+        };
+
+        if self.attr(local_name!("required")).is_some() {
+            let missing = match input_type.as_str() {
+                "checkbox" | "radio" => self.attr(local_name!("checked")).is_none(),
+                _ => value.is_empty(),
+            };
+            if missing {
+                errors.push(ValidityError::ValueMissing);
+            }
+        }
+
+        // The remaining constraints don't apply to empty values
+        if value.is_empty() {
+            return errors;
+        }
+
+        let multiple = self.attr(local_name!("multiple")).is_some();
+        let type_mismatch = match input_type.as_str() {
+            "email" if multiple => !value.split(',').all(|email| is_valid_email(email.trim())),
+            "email" => !is_valid_email(&value),
+            "url" => url::Url::parse(&value).is_err(),
+            "number" | "date" => parse_ranged_value(&input_type, &value).is_none(),
+            _ => false,
+        };
+        if type_mismatch {
+            errors.push(ValidityError::TypeMismatch);
+        }
+
+        let pattern = self
+            .element_data()
+            .filter(|_| is_input)
+            .zip(self.attr(local_name!("pattern")))
+            .and_then(|(element_data, pattern)| element_data.pattern_cache.regex(pattern));
+        if let Some(regex) = pattern {
+            let matches = if input_type == "email" && multiple {
+                value.split(',').all(|email| regex.is_match(email.trim()))
+            } else {
+                regex.is_match(&value)
+            };
+            if !matches {
+                errors.push(ValidityError::PatternMismatch);
+            }
+        }
+
+        let length = value.chars().count();
+        let length_attr = |name| {
+            self.attr(name)
+                .and_then(|value: &str| value.trim().parse::<usize>().ok())
+        };
+        if length_attr(local_name!("maxlength")).is_some_and(|max| length > max) {
+            errors.push(ValidityError::TooLong);
+        }
+        if length_attr(local_name!("minlength")).is_some_and(|min| length < min) {
+            errors.push(ValidityError::TooShort);
+        }
+
+        if is_ranged_type(&input_type) {
+            if let Some(number) = parse_ranged_value(&input_type, &value) {
+                let bound = |name| {
+                    self.attr(name)
+                        .and_then(|value| parse_ranged_value(&input_type, value))
+                };
+                let min = bound(local_name!("min"));
+                if min.is_some_and(|min| number < min) {
+                    errors.push(ValidityError::RangeUnderflow);
+                }
+                if bound(local_name!("max")).is_some_and(|max| number > max) {
+                    errors.push(ValidityError::RangeOverflow);
+                }
+
+                let step = match self.attr(local_name!("step")) {
+                    Some(step) if step.trim().eq_ignore_ascii_case("any") => None,
+                    step => Some(
+                        step.and_then(|step| step.trim().parse::<f64>().ok())
+                            .filter(|step| *step > 0.0 && step.is_finite())
+                            .unwrap_or(1.0),
+                    ),
+                };
+                if let Some(step) = step {
+                    let steps = (number - min.unwrap_or(0.0)) / step;
+                    if (steps - steps.round()).abs() > 1e-9 {
+                        errors.push(ValidityError::StepMismatch);
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Whether the element satisfies its constraints (matches `:valid`)
+    pub fn is_valid(&self) -> bool {
+        self.is_validation_candidate() && self.validity_errors().is_empty()
+    }
+
+    /// Whether the element does not satisfy its constraints (matches `:invalid`)
+    pub fn is_invalid(&self) -> bool {
+        self.is_validation_candidate() && !self.validity_errors().is_empty()
+    }
+}
+
+impl Document {
+    /// The constraints that an element's value does not satisfy
+    pub fn validity_errors(&self, node_id: usize) -> Vec<ValidityError> {
+        self.nodes[node_id].validity_errors()
+    }
+
+    /// Whether an element (or for a `<form>` or `<fieldset>`, every control within it) satisfies its constraints
+    pub fn check_validity(&self, node_id: usize) -> bool {
+        self.validation_candidates(node_id)
+            .iter()
+            .all(|id| self.nodes[*id].validity_errors().is_empty())
+    }
+
+    /// Check the validity of an element (or for a `<form>` or `<fieldset>`, every control within it), marking
+    /// the checked controls as interacted with (so that they match `:user-invalid` if invalid).
+    ///
+    /// Returns a localized validation message for each invalid control. The language of the messages is
+    /// determined by the `lang` attribute of the control or its nearest ancestor that has one.
+    pub fn report_validity(&mut self, node_id: usize) -> Vec<(usize, String)> {
+        let candidates = self.validation_candidates(node_id);
+        let mut messages = Vec::new();
+        for id in candidates {
+            self.user_interacted.insert(id);
+            if let Some(message) = self.validation_message(id) {
+                messages.push((id, message));
+            }
+        }
+        messages
+    }
+
+    /// A localized message describing the first constraint that an element does not satisfy
+    pub fn validation_message(&self, node_id: usize) -> Option<String> {
+        let node = &self.nodes[node_id];
+        let error = *node.validity_errors().first()?;
+        let lang = self.language_of(node_id);
+        let input_type = node.input_type();
+        let attr = |name| node.attr(name).unwrap_or_default().to_string();
+
+        Some(localized_message(
+            &lang,
+            error,
+            &input_type,
+            match error {
+                ValidityError::TooLong => attr(local_name!("maxlength")),
+                ValidityError::TooShort => attr(local_name!("minlength")),
+                ValidityError::RangeUnderflow => attr(local_name!("min")),
+                ValidityError::RangeOverflow => attr(local_name!("max")),
+                _ => String::new(),
+            },
+        ))
+    }
+
+    /// Whether a ranged input's value is within its `min` and `max` (`:in-range`). Returns `None` if the
+    /// element has no range limitations, in which case neither `:in-range` nor `:out-of-range` apply.
+    pub fn is_in_range(&self, node_id: usize) -> Option<bool> {
+        let node = &self.nodes[node_id];
+        if !node.is_element_with_tag_name(&local_name!("input"))
+            || !node.is_validation_candidate()
+            || !is_ranged_type(&node.input_type())
+        {
+            return None;
+        }
+        if node.attr(local_name!("min")).is_none() && node.attr(local_name!("max")).is_none() {
+            return None;
+        }
+
+        let errors = node.validity_errors();
+        Some(
+            !errors.contains(&ValidityError::RangeUnderflow)
+                && !errors.contains(&ValidityError::RangeOverflow),
+        )
+    }
+
+    /// Record that the user has interacted with a form control (changed its value or tried to submit it)
+    pub fn mark_user_interacted(&mut self, node_id: usize) {
+        self.user_interacted.insert(node_id);
+    }
+
+    /// Whether a form control is invalid and the user has interacted with it (`:user-invalid`)
+    pub fn is_user_invalid(&self, node_id: usize) -> bool {
+        self.user_interacted.contains(&node_id) && self.nodes[node_id].is_invalid()
+    }
+
+    /// The element itself if it is a validation candidate, or all candidates within it otherwise
+    fn validation_candidates(&self, node_id: usize) -> Vec<usize> {
+        let mut candidates = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id];
+            if node.is_validation_candidate() {
+                candidates.push(id);
+            }
+            stack.extend(node.children.iter().rev().copied());
+        }
+        candidates
+    }
+
+    /// The language of an element, from the `lang` attribute of it or its nearest ancestor
+    fn language_of(&self, node_id: usize) -> String {
+        let mut maybe_id = Some(node_id);
+        while let Some(id) = maybe_id {
+            if let Some(lang) = self.nodes[id].attr(local_name!("lang")) {
+                return lang.to_ascii_lowercase();
+            }
+            maybe_id = self.nodes[id].parent;
+        }
+        String::new()
+    }
+}
+
+/// The validation message for an error in the given language. Falls back to English.
+fn localized_message(lang: &str, error: ValidityError, input_type: &str, limit: String) -> String {
+    use ValidityError::*;
+
+    let primary_lang = lang.split('-').next().unwrap_or_default();
+    match (primary_lang, error) {
+        ("fr", ValueMissing) => "Veuillez renseigner ce champ.".to_string(),
+        ("fr", TypeMismatch) if input_type == "email" => {
+            "Veuillez saisir une adresse électronique valide.".to_string()
+        }
+        ("fr", TypeMismatch) if input_type == "url" => "Veuillez saisir une URL.".to_string(),
+        ("fr", TypeMismatch) => "Veuillez saisir une valeur valide.".to_string(),
+        ("fr", PatternMismatch) => "Veuillez respecter le format requis.".to_string(),
+        ("fr", TooLong) => format!("Veuillez utiliser au plus {limit} caractères."),
+        ("fr", TooShort) => format!("Veuillez utiliser au moins {limit} caractères."),
+        ("fr", RangeUnderflow) => format!("La valeur doit être supérieure ou égale à {limit}."),
+        ("fr", RangeOverflow) => format!("La valeur doit être inférieure ou égale à {limit}."),
+        ("fr", StepMismatch) => "Veuillez saisir une valeur valide.".to_string(),
+
+        ("de", ValueMissing) => "Bitte füllen Sie dieses Feld aus.".to_string(),
+        ("de", TypeMismatch) if input_type == "email" => {
+            "Bitte geben Sie eine gültige E-Mail-Adresse ein.".to_string()
+        }
+        ("de", TypeMismatch) if input_type == "url" => "Bitte geben Sie eine URL ein.".to_string(),
+        ("de", TypeMismatch) => "Bitte geben Sie einen gültigen Wert ein.".to_string(),
+        ("de", PatternMismatch) => "Bitte halten Sie sich an das vorgegebene Format.".to_string(),
+        ("de", TooLong) => format!("Bitte verwenden Sie höchstens {limit} Zeichen."),
+        ("de", TooShort) => format!("Bitte verwenden Sie mindestens {limit} Zeichen."),
+        ("de", RangeUnderflow) => format!("Der Wert muss größer oder gleich {limit} sein."),
+        ("de", RangeOverflow) => format!("Der Wert muss kleiner oder gleich {limit} sein."),
+        ("de", StepMismatch) => "Bitte geben Sie einen gültigen Wert ein.".to_string(),
+
+        ("es", ValueMissing) => "Completa este campo.".to_string(),
+        ("es", TypeMismatch) if input_type == "email" => {
+            "Introduce una dirección de correo electrónico válida.".to_string()
+        }
+        ("es", TypeMismatch) if input_type == "url" => "Introduce una URL.".to_string(),
+        ("es", TypeMismatch) => "Introduce un valor válido.".to_string(),
+        ("es", PatternMismatch) => "Utiliza el formato solicitado.".to_string(),
+        ("es", TooLong) => format!("Utiliza como máximo {limit} caracteres."),
+        ("es", TooShort) => format!("Utiliza al menos {limit} caracteres."),
+        ("es", RangeUnderflow) => format!("El valor debe ser superior o igual a {limit}."),
+        ("es", RangeOverflow) => format!("El valor debe ser inferior o igual a {limit}."),
+        ("es", StepMismatch) => "Introduce un valor válido.".to_string(),
+
+        (_, ValueMissing) => "Please fill out this field.".to_string(),
+        (_, TypeMismatch) if input_type == "email" => "Please enter an email address.".to_string(),
+        (_, TypeMismatch) if input_type == "url" => "Please enter a URL.".to_string(),
+        (_, TypeMismatch) => "Please enter a valid value.".to_string(),
+        (_, PatternMismatch) => "Please match the requested format.".to_string(),
+        (_, TooLong) => format!("Please use at most {limit} characters."),
+        (_, TooShort) => format!("Please use at least {limit} characters."),
+        (_, RangeUnderflow) => format!("Value must be greater than or equal to {limit}."),
+        (_, RangeOverflow) => format!("Value must be less than or equal to {limit}."),
+        (_, StepMismatch) => "Please enter a valid value.".to_string(),
+    }
+}
+
+#[test]
+fn validates_constraints() {
+    use crate::test_util::parse_test_document;
+    use ValidityError::*;
+
+    let html = "<!DOCTYPE html><html><body>\
+        <input id=required required><input id=filled required value=x>\
+        <input type=checkbox id=unchecked required><input type=checkbox id=checked required checked>\
+        <input id=pattern pattern=[a-z]+ value=abc1><input id=partial pattern=[a-z]+ value=abc>\
+        <input type=email id=email value=user@example><input type=email id=not-email value=user.example.com>\
+        <input type=email multiple id=emails value='a@example.com, b'>\
+        <input type=url id=url value=https://example.com><input type=url id=not-url value=example.com>\
+        <input type=number id=under min=5 value=3><input type=number id=over max=5 value=6>\
+        <input type=number id=step min=1 step=2 value=4><input type=number id=any step=any value=0.25>\
+        <input type=number id=not-number value=five>\
+        <input type=date id=early min=2024-01-10 value=2024-01-05>\
+        <input type=date id=late max=2024-01-10 value=2024-02-01>\
+        <input type=date id=weekly min=2024-01-01 step=7 value=2024-01-09>\
+        <input type=date id=week-later min=2024-01-01 step=7 value=2024-01-08>\
+        <input type=date id=not-date value=2024-13-01><input id=disabled required disabled>\
+        </body></html>";
+    let doc = parse_test_document(html, &[]);
+    let errors = |id| doc.validity_errors(doc.get_element_by_id(id).unwrap());
+
+    assert_eq!(errors("required"), [ValueMissing]);
+    assert!(errors("filled").is_empty());
+    assert_eq!(errors("unchecked"), [ValueMissing]);
+    assert!(errors("checked").is_empty());
+    assert_eq!(errors("pattern"), [PatternMismatch]);
+    assert!(errors("partial").is_empty());
+    assert!(errors("email").is_empty());
+    assert_eq!(errors("not-email"), [TypeMismatch]);
+    assert_eq!(errors("emails"), [TypeMismatch]);
+    assert!(errors("url").is_empty());
+    assert_eq!(errors("not-url"), [TypeMismatch]);
+    assert_eq!(errors("under"), [RangeUnderflow]);
+    assert_eq!(errors("over"), [RangeOverflow]);
+    assert_eq!(errors("step"), [StepMismatch]);
+    assert!(errors("any").is_empty());
+    assert_eq!(errors("not-number"), [TypeMismatch]);
+    assert_eq!(errors("early"), [RangeUnderflow]);
+    assert_eq!(errors("late"), [RangeOverflow]);
+    assert_eq!(errors("weekly"), [StepMismatch]);
+    assert!(errors("week-later").is_empty());
+    assert_eq!(errors("not-date"), [TypeMismatch]);
+    // Disabled controls are barred from constraint validation
+    assert!(errors("disabled").is_empty());
+    assert!(!doc.nodes[doc.get_element_by_id("disabled").unwrap()].is_invalid());
+    assert!(doc.nodes[doc.get_element_by_id("filled").unwrap()].is_valid());
+}
+
+#[test]
+fn recompiles_changed_patterns() {
+    use crate::test_util::parse_test_document;
+    use html5ever::{namespace_url, ns, QualName};
+
+    let html = "<!DOCTYPE html><html><body><input id=code pattern=[0-9]+ value=abc></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    let code = doc.get_element_by_id("code").unwrap();
+    assert_eq!(doc.validity_errors(code), [ValidityError::PatternMismatch]);
+
+    doc.set_attribute(
+        code,
+        QualName::new(None, ns!(), local_name!("pattern")),
+        "[a-z]+",
+    );
+    assert!(doc.validity_errors(code).is_empty());
+}
+
+#[test]
+fn reports_localized_messages() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body><form id=form>\
+        <input id=name required><input type=email id=email value=nobody>\
+        <fieldset lang=es><input id=code maxlength=2 value=abc><input type=number id=age min=18 value=3></fieldset>\
+        <input id=valid value=ok></form></body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    let form = doc.get_element_by_id("form").unwrap();
+    let id = |id| doc.get_element_by_id(id).unwrap();
+    let expected = vec![
+        (id("name"), "Please fill out this field.".to_string()),
+        (id("email"), "Please enter an email address.".to_string()),
+        (id("code"), "Utiliza como máximo 2 caracteres.".to_string()),
+        (
+            id("age"),
+            "El valor debe ser superior o igual a 18.".to_string(),
+        ),
+    ];
+
+    assert!(!doc.check_validity(form));
+    assert!(!doc.is_user_invalid(id("name")));
+    assert_eq!(doc.report_validity(form), expected);
+    // Reporting counts as interacting with the controls
+    assert!(doc.is_user_invalid(id("name")));
+    assert!(!doc.is_user_invalid(id("valid")));
+}
@@ -0,0 +1,115 @@
+//! Support for `<video>` as a replaced element
+//!
+//! Blitz doesn't decode video itself. Instead embedders implement [`VideoFrameProvider`] (typically
+//! on top of ffmpeg, gstreamer or a platform decoder) and attach it to a `<video>` element. Decoded frames
+//! are pulled from the provider whenever the document is polled and stored as the element's image, which
+//! means they are composited by the same code path as `<img>` elements.
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use image::DynamicImage;
+
+use crate::Document;
+
+/// The intrinsic size of a `<video>` element that has neither a poster nor a decoded frame.
+///
+/// <https://html.spec.whatwg.org/multipage/media.html#the-video-element>
+pub const DEFAULT_VIDEO_SIZE: taffy::Size<f32> = taffy::Size {
+    width: 300.0,
+    height: 150.0,
+};
+
+/// A source of decoded video frames for a `<video>` element.
+pub trait VideoFrameProvider: Send {
+    /// Poll for the next frame to display.
+    ///
+    /// - `Poll::Ready(Some(frame))` if a new frame is ready to be displayed
+    /// - `Poll::Ready(None)` if the stream has ended (the last frame remains displayed)
+    /// - `Poll::Pending` if no new frame is available yet. Implementors must arrange for the waker in `cx`
+    ///   to be woken once a frame becomes available.
+    fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Option<DynamicImage>>;
+}
+
+/// The state of a `<video>` element's frame source
+pub(crate) struct VideoSource {
+    provider: Box<dyn VideoFrameProvider>,
+    ended: bool,
+}
+
+impl Document {
+    /// Attach a frame provider to a `<video>` element. Any previously attached provider is dropped.
+    pub fn set_video_frame_provider(
+        &mut self,
+        node_id: usize,
+        provider: Box<dyn VideoFrameProvider>,
+    ) {
+        self.video_sources.insert(
+            node_id,
+            VideoSource {
+                provider,
+                ended: false,
+            },
+        );
+    }
+
+    /// Detach the frame provider from a `<video>` element. The last frame remains displayed.
+    pub fn remove_video_frame_provider(&mut self, node_id: usize) {
+        self.video_sources.remove(&node_id);
+    }
+
+    /// Pull new frames from all attached video providers into their elements.
+    ///
+    /// Returns true if any frame changed (and thus a redraw is required).
+    pub fn poll_video_frames(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut changed = false;
+
+        // Drop sources whose nodes have been removed from the tree
+        let nodes = &self.nodes;
+        self.video_sources
+            .retain(|node_id, _| nodes.contains(*node_id));
+
+        let mut resized_nodes = Vec::new();
+        for (&node_id, source) in self.video_sources.iter_mut() {
+            if source.ended {
+                continue;
+            }
+
+            let frame = match source.provider.poll_frame(cx) {
+                Poll::Ready(Some(frame)) => frame,
+                Poll::Ready(None) => {
+                    source.ended = true;
+                    continue;
+                }
+                Poll::Pending => continue,
+            };
+
+            let Some(element_data) = self.nodes[node_id].element_data_mut() else {
+                continue;
+            };
+
+            let old_size = element_data
+                .image
+                .as_ref()
+                .map(|image| (image.width(), image.height()));
+            if old_size != Some((frame.width(), frame.height())) {
+                resized_nodes.push(node_id);
+            }
+
+            element_data.set_image(Arc::new(frame));
+            // Frames replace the poster, and are shown at their natural size
+            element_data.image_source = None;
+            changed = true;
+        }
+
+        // A change of intrinsic size requires the video and its ancestors to be laid out again
+        for node_id in resized_nodes {
+            let mut maybe_id = Some(node_id);
+            while let Some(id) = maybe_id {
+                self.nodes[id].cache.clear();
+                maybe_id = self.nodes[id].parent;
+            }
+        }
+
+        changed
+    }
+}
@@ -0,0 +1,87 @@
+//! The visual viewport
+//!
+//! <https://drafts.csswg.org/cssom-view/#visual-viewport>
+//!
+//! On phones, parts of the window are covered by the soft keyboard and by system bars. The shell reports how far
+//! they cover each edge with [`Document::set_viewport_insets`], and the visual viewport is the part of the (layout)
+//! viewport that remains visible. Scrolling keeps content within it: the document can be scrolled far enough for its
+//! end to come out from under the keyboard, and the focused element is scrolled back into view when the keyboard
+//! opens.
+//!
+//! Note: pinch zooming isn't supported, so the visual viewport is never scaled or panned within the layout viewport.
+//! Layout isn't affected by the insets, so fixed position elements can still be covered by the keyboard.
+use crate::geometry::DomRect;
+use crate::Document;
+
+/// How far each edge of the viewport is covered by the soft keyboard or system bars, in CSS pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ViewportInsets {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Document {
+    /// How far each edge of the viewport is covered by the soft keyboard or system bars
+    pub fn viewport_insets(&self) -> ViewportInsets {
+        self.viewport_insets
+    }
+
+    /// Set how far each edge of the viewport is covered. If this shrinks the visual viewport, the focused element is
+    /// scrolled back into it.
+    pub fn set_viewport_insets(&mut self, insets: ViewportInsets) {
+        if insets == self.viewport_insets {
+            return;
+        }
+        self.viewport_insets = insets;
+
+        if let Some(rect) = self
+            .focused_element()
+            .and_then(|node_id| self.node_document_rect(node_id))
+        {
+            self.scroll_into_view(rect.y as f64, rect.bottom() as f64);
+        }
+        self.scroll_viewport_to(self.viewport_scroll, Default::default());
+        self.invalidate();
+    }
+
+    /// The part of the viewport that isn't covered, relative to the document
+    pub fn visual_viewport(&self) -> DomRect {
+        let size = self.stylist.device().au_viewport_size();
+        let insets = self.viewport_insets;
+        DomRect {
+            x: insets.left,
+            y: self.viewport_scroll as f32 + insets.top,
+            width: (size.width.to_f32_px() - insets.left - insets.right).max(0.0),
+            height: (size.height.to_f32_px() - insets.top - insets.bottom).max(0.0),
+        }
+    }
+}
+
+#[test]
+fn keeps_focused_elements_above_the_keyboard() {
+    use crate::test_util::parse_test_document;
+
+    let html = "<!DOCTYPE html><html><body style='margin: 0'>\
+        <div style='height: 500px'></div><input id=field style='height: 20px; border: 0; padding: 0; display: block'>\
+        </body></html>";
+    let mut doc = parse_test_document(html, &[]);
+    doc.resolve();
+    doc.focus(doc.get_element_by_id("field").unwrap());
+    assert_eq!(doc.max_viewport_scroll(), 0.0);
+
+    // A keyboard covering the bottom 300px hides the field, which ends at 520px
+    doc.set_viewport_insets(ViewportInsets {
+        bottom: 300.0,
+        ..Default::default()
+    });
+    assert_eq!(doc.max_viewport_scroll(), 220.0);
+    assert_eq!(doc.viewport_scroll(), 220.0);
+    let visible = doc.visual_viewport();
+    assert_eq!((visible.y, visible.height), (220.0, 300.0));
+
+    // Closing the keyboard scrolls back within the document
+    doc.set_viewport_insets(ViewportInsets::default());
+    assert_eq!(doc.viewport_scroll(), 0.0);
+}
@@ -0,0 +1,103 @@
+//! Layout conformance reftests
+//!
+//! Renders each test listed in `tests/wpt/MANIFEST` and its reference headlessly, and compares the screenshots. The
+//! pass rate is reported, and the suite fails if any test's result differs from the one expected by the manifest, so
+//! layout regressions (and newly passing tests) are caught.
+//!
+//! Rendering needs a GPU, so the suite is opt-in:
+//!
+//! ```sh
+//! cargo test --test layout_conformance -- --ignored --nocapture
+//! ```
+use std::path::Path;
+
+use blitz::RgbaImage;
+use dioxus_blitz::Config;
+
+const WIDTH: u32 = 400;
+const HEIGHT: u32 = 300;
+
+/// How far a channel may differ before a pixel counts as different, to allow for antialiasing
+const CHANNEL_TOLERANCE: u8 = 2;
+
+struct Reftest {
+    test: String,
+    reference: String,
+    expect_pass: bool,
+}
+
+fn read_manifest(dir: &Path) -> Vec<Reftest> {
+    let manifest = std::fs::read_to_string(dir.join("MANIFEST")).expect("missing MANIFEST");
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            let test = fields.next().unwrap().to_string();
+            let reference = fields.next().expect("test without a reference").to_string();
+            let expect_pass = fields.next() != Some("fail");
+            Reftest {
+                test,
+                reference,
+                expect_pass,
+            }
+        })
+        .collect()
+}
+
+fn render(dir: &Path, file: &str) -> RgbaImage {
+    let html = std::fs::read_to_string(dir.join(file)).unwrap();
+    dioxus_blitz::render_static_html_to_image(&html, Config::default(), WIDTH, HEIGHT)
+}
+
+/// The number of pixels that differ between two screenshots
+fn differing_pixels(a: &RgbaImage, b: &RgbaImage) -> usize {
+    a.pixels()
+        .zip(b.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count()
+}
+
+#[test]
+#[ignore = "renders on the GPU"]
+fn layout_conformance() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wpt");
+    let tests = read_manifest(&dir);
+
+    let mut passed = 0;
+    let mut unexpected = Vec::new();
+    for reftest in &tests {
+        let diff = differing_pixels(
+            &render(&dir, &reftest.test),
+            &render(&dir, &reftest.reference),
+        );
+        let pass = diff == 0;
+        passed += usize::from(pass);
+
+        let status = match (pass, reftest.expect_pass) {
+            (true, true) => "PASS",
+            (false, false) => "FAIL (expected)",
+            (true, false) => "PASS (unexpected)",
+            (false, true) => "FAIL (unexpected)",
+        };
+        println!("{status:<18} {} ({diff} pixels differ)", reftest.test);
+        if pass != reftest.expect_pass {
+            unexpected.push(reftest.test.as_str());
+        }
+    }
+
+    println!(
+        "\n{passed}/{} passed ({:.1}%)",
+        tests.len(),
+        100.0 * passed as f64 / tests.len().max(1) as f64
+    );
+    assert!(
+        unexpected.is_empty(),
+        "unexpected results (update tests/wpt/MANIFEST if they are expected): {unexpected:?}"
+    );
+}